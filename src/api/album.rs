@@ -0,0 +1,17 @@
+use crate::AudioController;
+use crate::helpers::albumdetails::{self, AlbumDetails};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use std::sync::Arc;
+
+/// Get everything known about an album in one document
+///
+/// Combines the track list with durations and favourite states, release information,
+/// artwork and the players that have the album in their library.
+#[get("/<artist>/<album>")]
+pub fn get_album(artist: &str, album: &str, controller: &State<Arc<AudioController>>) -> Result<Json<AlbumDetails>, Status> {
+    albumdetails::get_album_details(controller.inner(), artist, album)
+        .map(Json)
+        .ok_or(Status::NotFound)
+}
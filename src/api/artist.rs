@@ -0,0 +1,66 @@
+use crate::AudioController;
+use crate::helpers::artistconflicts::{self, ArtistConflict};
+use crate::helpers::artistdetails::{self, ArtistDetails};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Request body for choosing the MusicBrainz ID of an artist
+#[derive(Deserialize)]
+pub struct ResolveRequest {
+    pub mbid: String,
+}
+
+/// Simple status response
+#[derive(Serialize)]
+pub struct StatusResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+fn ok(msg: impl Into<String>) -> Json<StatusResponse> {
+    Json(StatusResponse { success: true, message: msg.into() })
+}
+
+fn err_response(status: Status, msg: impl Into<String>) -> Custom<Json<StatusResponse>> {
+    Custom(status, Json(StatusResponse { success: false, message: msg.into() }))
+}
+
+/// Get everything known about an artist in one document
+///
+/// Combines the albums of all libraries with cached metadata, similar artists, top tracks
+/// and favourites. Parts that are not cached yet are listed in `pending` and looked up in
+/// the background.
+#[get("/<name>")]
+pub fn get_artist(name: &str, controller: &State<Arc<AudioController>>) -> Json<ArtistDetails> {
+    Json(artistdetails::get_artist_details(controller.inner(), name))
+}
+
+/// GET /artist/conflicts — artists whose metadata providers disagree, least confident first
+#[get("/conflicts")]
+pub fn get_conflicts() -> Json<Vec<ArtistConflict>> {
+    Json(artistconflicts::list_conflicts())
+}
+
+/// POST /artist/<name>/resolve — choose the MusicBrainz ID of an ambiguous artist
+#[post("/<name>/resolve", data = "<req>")]
+pub fn post_resolve(name: &str, req: Json<ResolveRequest>) -> Result<Json<StatusResponse>, Custom<Json<StatusResponse>>> {
+    let mbid = req.into_inner().mbid;
+    match artistconflicts::resolve(name, &mbid) {
+        Ok(_) => Ok(ok(format!("'{}' resolved to {}, updating metadata", name, mbid.trim()))),
+        Err(e) => Err(err_response(Status::BadRequest, e)),
+    }
+}
+
+/// DELETE /artist/<name>/resolve — forget the chosen MusicBrainz ID
+#[delete("/<name>/resolve")]
+pub fn delete_resolve(name: &str) -> Result<Json<StatusResponse>, Custom<Json<StatusResponse>>> {
+    match artistconflicts::clear_resolution(name) {
+        Ok(true) => Ok(ok(format!("Resolution of '{}' removed, updating metadata", name))),
+        Ok(false) => Err(err_response(Status::NotFound, format!("'{}' has no resolution", name))),
+        Err(e) => Err(err_response(Status::InternalServerError, format!("Failed to remove resolution: {}", e))),
+    }
+}
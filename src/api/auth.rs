@@ -0,0 +1,197 @@
+use crate::constants::API_PREFIX;
+use crate::helpers::api_tokens::{self, ApiToken, ApiTokenError, Scope};
+use log::{debug, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, Data, Request};
+use serde::{Deserialize, Serialize};
+
+/// Query parameter with the token, for clients that can't send headers, e.g. WebSockets in browsers
+const TOKEN_QUERY_PARAMETER: &str = "access_token";
+
+/// Result of the authentication of a request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Authentication {
+    /// Authentication is disabled
+    Disabled,
+    /// The request came from this device
+    Local,
+    /// The request had a valid token
+    Token(Scope),
+    /// The request had no token or an invalid one
+    Anonymous,
+}
+
+impl Authentication {
+    /// Scope of the request, local requests and requests without authentication may do everything
+    pub fn scope(&self) -> Option<Scope> {
+        match self {
+            Authentication::Disabled | Authentication::Local => Some(Scope::Admin),
+            Authentication::Token(scope) => Some(*scope),
+            Authentication::Anonymous => None,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authentication {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(*request.local_cache(|| authenticate(request)))
+    }
+}
+
+fn authenticate(request: &Request<'_>) -> Authentication {
+    let config = api_tokens::config();
+    if !config.enable {
+        return Authentication::Disabled;
+    }
+    // The socket address, headers like X-Real-IP can be set by anyone
+    if config.allow_localhost && request.remote().is_some_and(|addr| addr.ip().is_loopback()) {
+        return Authentication::Local;
+    }
+    let header = request
+        .headers()
+        .get_one("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request
+        .query_value::<&str>(TOKEN_QUERY_PARAMETER)
+        .and_then(|value| value.ok());
+    match header.or(query).and_then(api_tokens::authorize) {
+        Some(scope) => Authentication::Token(scope),
+        None => Authentication::Anonymous,
+    }
+}
+
+/// Fairing that rejects API requests without a token of the required scope
+///
+/// Rocket can't answer a request from a fairing, so rejected requests are routed to
+/// `/api/auth/unauthorized` or `/api/auth/forbidden` instead of their handler.
+pub struct ApiAuth;
+
+#[rocket::async_trait]
+impl Fairing for ApiAuth {
+    fn info(&self) -> Info {
+        Info {
+            name: "API authentication",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        // Match on the path Rocket routes, not the raw one with percent escapes and empty segments
+        let path = api_tokens::normalize_path(request.uri().path().segments());
+        if path != API_PREFIX && !path.starts_with(&format!("{}/", API_PREFIX)) {
+            return;
+        }
+        if api_tokens::is_public(request.method().as_str(), &path) {
+            return;
+        }
+        let authentication = *request.local_cache(|| authenticate(request));
+        let required = api_tokens::required_scope(request.method().as_str(), &path, &api_tokens::config().admin_paths);
+        let target = match authentication.scope() {
+            Some(scope) if scope >= required => return,
+            Some(_) => "/api/auth/forbidden",
+            None => "/api/auth/unauthorized",
+        };
+        debug!("Rejecting {} {}, it needs the {} scope", request.method(), path, required);
+        request.set_method(Method::Get);
+        request.set_uri(Origin::parse(target).expect("valid URI"));
+    }
+}
+
+/// Request structure to create a token
+#[derive(Deserialize, Serialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scope: Scope,
+}
+
+/// Response structure for a created token
+#[derive(Serialize, Deserialize)]
+pub struct CreateTokenResponse {
+    #[serde(flatten)]
+    pub info: ApiToken,
+    /// The token to send with requests, it can't be read again later
+    pub token: String,
+}
+
+/// Response structure for the authentication status
+#[derive(Serialize, Deserialize)]
+pub struct AuthStatusResponse {
+    pub enable: bool,
+    pub allow_localhost: bool,
+    /// Scope of the request
+    pub scope: Option<Scope>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: String) -> ApiError {
+    Custom(status, Json(ErrorResponse { success: false, message }))
+}
+
+fn token_error(error: ApiTokenError) -> ApiError {
+    let status = match error {
+        ApiTokenError::EmptyName => Status::BadRequest,
+        ApiTokenError::NotFound(_) => Status::NotFound,
+        ApiTokenError::Storage(_) => Status::InternalServerError,
+    };
+    error_response(status, error.to_string())
+}
+
+/// Get whether authentication is enabled and the scope of the request
+#[get("/")]
+pub fn get_status(authentication: Authentication) -> Json<AuthStatusResponse> {
+    let config = api_tokens::config();
+    Json(AuthStatusResponse {
+        enable: config.enable,
+        allow_localhost: config.allow_localhost,
+        scope: authentication.scope(),
+    })
+}
+
+/// List all tokens without their secrets
+#[get("/tokens")]
+pub fn list_tokens() -> Json<Vec<ApiToken>> {
+    Json(api_tokens::list_tokens())
+}
+
+/// Create a token
+#[post("/tokens", data = "<request>")]
+pub fn create_token(request: Json<CreateTokenRequest>) -> Result<Json<CreateTokenResponse>, ApiError> {
+    info!("API request: create {} token '{}'", request.scope, request.name);
+    let (info, token) = api_tokens::create_token(&request.name, request.scope).map_err(token_error)?;
+    Ok(Json(CreateTokenResponse { info, token }))
+}
+
+/// Revoke a token
+#[delete("/tokens/<id>")]
+pub fn revoke_token(id: &str) -> Result<Json<ApiToken>, ApiError> {
+    info!("API request: revoke token {}", id);
+    api_tokens::revoke_token(id).map(Json).map_err(token_error)
+}
+
+/// Target of requests without a valid token
+#[get("/unauthorized")]
+pub fn unauthorized() -> ApiError {
+    error_response(Status::Unauthorized, "A valid API token is required".to_string())
+}
+
+/// Target of requests with a token that lacks the required scope
+#[get("/forbidden")]
+pub fn forbidden() -> ApiError {
+    error_response(Status::Forbidden, "The API token doesn't allow this request".to_string())
+}
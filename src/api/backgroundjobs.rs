@@ -1,8 +1,10 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
 use rocket::serde::json::Json;
-use rocket::get;
+use rocket::{get, post};
 use serde::{Deserialize, Serialize};
 use log::{debug, error};
-use crate::helpers::backgroundjobs::{get_all_jobs, BackgroundJob};
+use crate::helpers::backgroundjobs::{get_active_jobs, get_all_jobs, BackgroundJob, CancelError};
 
 /// Response structure for background jobs listing
 #[derive(Serialize, Deserialize)]
@@ -27,6 +29,8 @@ pub struct BackgroundJobInfo {
     pub completion_percentage: Option<f64>,
     pub finished: bool,
     pub finish_time: Option<u64>,
+    pub cancellable: bool,
+    pub cancelled: bool,
 }
 
 impl From<BackgroundJob> for BackgroundJobInfo {
@@ -54,6 +58,8 @@ impl From<BackgroundJob> for BackgroundJobInfo {
             completion_percentage,
             finished: job.finished,
             finish_time: job.finish_time,
+            cancellable: job.cancellable && !job.finished,
+            cancelled: job.cancelled,
         }
     }
 }
@@ -69,11 +75,17 @@ pub struct ErrorResponse {
 /// 
 /// This endpoint retrieves information about all background jobs currently
 /// running in the system, including their progress and timing information.
-#[get("/jobs")]
-pub fn get_background_jobs() -> Json<BackgroundJobsResponse> {
+/// With `active=true` finished jobs are left out.
+#[get("/jobs?<active>")]
+pub fn get_background_jobs(active: Option<bool>) -> Json<BackgroundJobsResponse> {
     debug!("API request: get background jobs");
 
-    match get_all_jobs() {
+    let jobs = if active.unwrap_or(false) {
+        Ok(get_active_jobs())
+    } else {
+        get_all_jobs()
+    };
+    match jobs {
         Ok(jobs) => {
             debug!("Successfully retrieved {} background jobs", jobs.len());
             
@@ -136,3 +148,30 @@ pub fn get_background_job(job_id: String) -> Json<BackgroundJobsResponse> {
         }
     }
 }
+
+/// Cancel a running background job
+///
+/// The job stops after its current step, it is listed as finished and cancelled then.
+#[post("/jobs/<job_id>/cancel")]
+pub fn cancel_background_job(job_id: String) -> Custom<Json<BackgroundJobsResponse>> {
+    debug!("API request: cancel background job with ID: {}", job_id);
+
+    match crate::helpers::backgroundjobs::cancel_job(&job_id) {
+        Ok(job) => Custom(Status::Ok, Json(BackgroundJobsResponse {
+            success: true,
+            jobs: Some(vec![BackgroundJobInfo::from(job)]),
+            message: None,
+        })),
+        Err(e) => {
+            let status = match e {
+                CancelError::NotFound(_) => Status::NotFound,
+                CancelError::NotCancellable(_) | CancelError::Finished(_) => Status::Conflict,
+            };
+            Custom(status, Json(BackgroundJobsResponse {
+                success: false,
+                jobs: None,
+                message: Some(e.to_string()),
+            }))
+        }
+    }
+}
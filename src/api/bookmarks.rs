@@ -0,0 +1,215 @@
+use crate::data::{PlaybackState, PlayerCommand, Song};
+use crate::helpers::bookmarks::{self, BookmarkError, TrackBookmarks, AUTO_BOOKMARK};
+use crate::players::PlayerController;
+use crate::AudioController;
+use log::{debug, info};
+use parking_lot::RwLock;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+type PlayerLock = Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>;
+
+/// Response structure for the bookmarks of all tracks
+#[derive(Serialize, Deserialize)]
+pub struct BookmarkListResponse {
+    pub success: bool,
+    pub tracks: Vec<TrackBookmarks>,
+}
+
+/// Response structure for the bookmarks of the current song of a player
+#[derive(Serialize, Deserialize)]
+pub struct PlayerBookmarksResponse {
+    pub success: bool,
+    pub player_name: String,
+    /// Current position in seconds
+    pub position: Option<f64>,
+    pub track: Option<TrackBookmarks>,
+}
+
+/// Request structure to add a bookmark
+#[derive(Deserialize, Serialize, Default)]
+pub struct AddBookmarkRequest {
+    /// Name of the bookmark, the position is used if not given, e.g. "1:02:03"
+    pub name: Option<String>,
+    /// Position in seconds, the current position if not given
+    pub position: Option<f64>,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+fn bookmark_error(error: BookmarkError) -> ApiError {
+    let status = match error {
+        BookmarkError::NoSong | BookmarkError::NotFound(_) => Status::NotFound,
+        BookmarkError::Invalid(_) => Status::BadRequest,
+        BookmarkError::Storage(_) => Status::InternalServerError,
+    };
+    error_response(status, error.to_string())
+}
+
+/// Find a player by name, "active" is the active player
+fn find_player(controller: &AudioController, player_name: &str) -> Result<PlayerLock, ApiError> {
+    let player = if player_name.eq_ignore_ascii_case("active") {
+        controller.get_active_controller()
+    } else {
+        controller.get_player_by_name(player_name)
+    };
+    player.ok_or_else(|| error_response(Status::NotFound, format!("Player '{}' not found", player_name)))
+}
+
+fn current_song(player: &PlayerLock) -> Result<Song, ApiError> {
+    player.read().get_song().ok_or_else(|| bookmark_error(BookmarkError::NoSong))
+}
+
+/// Format a position like "1:02:03" or "2:03"
+fn format_position(position: f64) -> String {
+    let seconds = position as u64;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// List the bookmarks of all tracks
+#[get("/list")]
+pub fn list_bookmarks() -> Json<BookmarkListResponse> {
+    debug!("API request: list bookmarks");
+    Json(BookmarkListResponse {
+        success: true,
+        tracks: bookmarks::list_bookmarks(),
+    })
+}
+
+/// Get the bookmarks of the current song of a player
+#[get("/<player_name>")]
+pub fn get_player_bookmarks(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<PlayerBookmarksResponse>, ApiError> {
+    let player = find_player(controller.inner(), player_name)?;
+    let (song, position) = {
+        let player = player.read();
+        (player.get_song(), player.get_position())
+    };
+    Ok(Json(PlayerBookmarksResponse {
+        success: true,
+        player_name: player_name.to_string(),
+        position,
+        track: song.as_ref().and_then(bookmarks::track_key).and_then(|key| bookmarks::get_bookmarks(&key)),
+    }))
+}
+
+/// Bookmark a position of the current song of a player
+#[post("/<player_name>", data = "<request>")]
+pub fn add_bookmark(
+    player_name: &str,
+    request: Option<Json<AddBookmarkRequest>>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<TrackBookmarks>, ApiError> {
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let player = find_player(controller.inner(), player_name)?;
+    let song = current_song(&player)?;
+    let position = match request.position {
+        Some(position) => position,
+        None => player
+            .read()
+            .get_position()
+            .ok_or_else(|| error_response(Status::ServiceUnavailable, "The player doesn't report its position"))?,
+    };
+    let name = request.name.unwrap_or_else(|| format_position(position));
+
+    info!("API request: bookmark '{}' at {:.0}s of {}", name, position, song);
+    bookmarks::set_bookmark(&song, &name, position).map(Json).map_err(bookmark_error)
+}
+
+/// Continue the current song of a player from a bookmark, the automatic bookmark if no name is given
+#[post("/<player_name>/resume?<name>")]
+pub fn resume_bookmark(
+    player_name: &str,
+    name: Option<&str>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let name = name.unwrap_or(AUTO_BOOKMARK);
+    let player = find_player(controller.inner(), player_name)?;
+    let song = current_song(&player)?;
+    let bookmark = bookmarks::track_key(&song)
+        .and_then(|key| bookmarks::get_bookmarks(&key))
+        .and_then(|track| track.get(name).cloned())
+        .ok_or_else(|| bookmark_error(BookmarkError::NotFound(name.to_string())))?;
+
+    info!("API request: resume {} from bookmark '{}' at {:.0}s", song, name, bookmark.position);
+    let player = player.read();
+    if player.get_playback_state() != PlaybackState::Playing && !player.send_command(PlayerCommand::Play) {
+        return Err(error_response(Status::InternalServerError, "Failed to start playback"));
+    }
+    if !player.send_command(PlayerCommand::Seek(bookmark.position)) {
+        return Err(error_response(Status::InternalServerError, "Failed to seek to the bookmark"));
+    }
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Resumed from {}", format_position(bookmark.position)),
+    }))
+}
+
+/// Remove a bookmark of the current song of a player
+#[delete("/<player_name>/<name>")]
+pub fn remove_player_bookmark(
+    player_name: &str,
+    name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let player = find_player(controller.inner(), player_name)?;
+    let song = current_song(&player)?;
+    let key = bookmarks::track_key(&song).ok_or_else(|| bookmark_error(BookmarkError::NoSong))?;
+    bookmarks::remove_bookmark(&key, name).map_err(bookmark_error)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Bookmark '{}' removed", name),
+    }))
+}
+
+/// Remove a bookmark of any track, given by the key returned by the list
+#[delete("/track?<key>&<name>")]
+pub fn remove_track_bookmark(key: &str, name: &str) -> Result<Json<MessageResponse>, ApiError> {
+    bookmarks::remove_bookmark(key, name).map_err(bookmark_error)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Bookmark '{}' removed", name),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_position() {
+        assert_eq!(format_position(123.4), "2:03");
+        assert_eq!(format_position(3723.0), "1:02:03");
+    }
+}
@@ -1,9 +1,15 @@
+use rocket::http::Status;
+use rocket::response::status::Custom;
 use rocket::serde::json::Json;
-use rocket::get;
+use rocket::{delete, get, post};
 use serde::{Deserialize, Serialize};
-use log::{debug, error};
-use crate::helpers::attributecache::{get_cache_stats, CacheStats};
+use log::{debug, error, info};
+use crate::helpers::attributecache::{self, get_cache_stats, CacheEntry, CacheStats, ServiceStats};
 use crate::helpers::imagecache;
+use crate::helpers::locale;
+
+/// Default number of entries returned by the entry listing
+const DEFAULT_ENTRY_LIMIT: usize = 100;
 
 /// Response structure for cache statistics
 #[derive(Serialize, Deserialize)]
@@ -19,7 +25,54 @@ pub struct CacheStatsResponse {
 pub struct ImageCacheStats {
     pub total_images: usize,
     pub total_size: u64,
+    /// Size on disk, identical images are only stored once
+    pub stored_size: u64,
     pub last_updated: u64,
+    /// Localized relative time of the last update, e.g. "3 minutes ago"
+    pub last_updated_display: String,
+}
+
+/// Response structure for the statistics of the services
+#[derive(Serialize, Deserialize)]
+pub struct ServiceStatsResponse {
+    pub success: bool,
+    pub services: Vec<ServiceStats>,
+}
+
+/// Response structure for a list of cache entries
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntriesResponse {
+    pub success: bool,
+    /// Number of matching entries, the list itself is limited
+    pub total: usize,
+    pub entries: Vec<CacheEntry>,
+}
+
+/// Response structure for a single cache entry
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntryResponse {
+    pub success: bool,
+    pub entry: CacheEntry,
+    pub value: serde_json::Value,
+}
+
+/// Response structure for deletions
+#[derive(Serialize, Deserialize)]
+pub struct CacheDeleteResponse {
+    pub success: bool,
+    pub deleted: usize,
+}
+
+/// Response structure for the removal of expired data
+#[derive(Serialize, Deserialize)]
+pub struct CacheCleanupResponse {
+    pub success: bool,
+    /// Expired entries removed from the attribute cache
+    pub expired_entries: usize,
+    /// Expired images removed from the image cache
+    pub expired_images: usize,
+    /// Stored image contents that no image used anymore
+    pub removed_contents: usize,
 }
 
 /// Response structure for error operations
@@ -29,6 +82,20 @@ pub struct ErrorResponse {
     pub message: String,
 }
 
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+fn cache_error(message: String) -> ApiError {
+    error!("Cache operation failed: {}", message);
+    error_response(Status::InternalServerError, message)
+}
+
 /// Get cache statistics
 /// 
 /// This endpoint retrieves current cache statistics including disk entries,
@@ -58,7 +125,9 @@ pub fn get_cache_statistics() -> Json<CacheStatsResponse> {
             Some(ImageCacheStats {
                 total_images: stats.total_images,
                 total_size: stats.total_size,
+                stored_size: stats.stored_size,
                 last_updated: stats.last_updated,
+                last_updated_display: locale::language().relative_timestamp(Some(stats.last_updated).filter(|t| *t > 0)),
             })
         }
         Err(e) => {
@@ -86,3 +155,111 @@ pub fn get_cache_statistics() -> Json<CacheStatsResponse> {
         message,
     })
 }
+
+/// Get the number and size of the attribute cache entries of each service
+#[get("/services")]
+pub fn get_service_statistics() -> Result<Json<ServiceStatsResponse>, ApiError> {
+    let services = attributecache::get_service_stats().map_err(cache_error)?;
+    Ok(Json(ServiceStatsResponse {
+        success: true,
+        services,
+    }))
+}
+
+/// List attribute cache entries, optionally only the ones starting with a prefix
+#[get("/entries?<prefix>&<limit>&<offset>")]
+pub fn list_cache_entries(
+    prefix: Option<&str>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Json<CacheEntriesResponse>, ApiError> {
+    let entries = attributecache::list_entries(prefix).map_err(cache_error)?;
+    let total = entries.len();
+    let entries = entries.into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(DEFAULT_ENTRY_LIMIT))
+        .collect();
+    Ok(Json(CacheEntriesResponse {
+        success: true,
+        total,
+        entries,
+    }))
+}
+
+/// Get a single attribute cache entry with its value
+#[get("/entry?<key>")]
+pub fn get_cache_entry(key: &str) -> Result<Json<CacheEntryResponse>, ApiError> {
+    let not_found = || error_response(Status::NotFound, format!("Cache entry '{}' not found", key));
+
+    let value = attributecache::get::<serde_json::Value>(key)
+        .map_err(cache_error)?
+        .ok_or_else(not_found)?;
+    let entry = attributecache::list_entries(Some(key))
+        .map_err(cache_error)?
+        .into_iter()
+        .find(|entry| entry.key == key)
+        .ok_or_else(not_found)?;
+
+    Ok(Json(CacheEntryResponse {
+        success: true,
+        entry,
+        value,
+    }))
+}
+
+/// Delete a single attribute cache entry
+#[delete("/entry?<key>")]
+pub fn delete_cache_entry(key: &str) -> Result<Json<CacheDeleteResponse>, ApiError> {
+    if !attributecache::remove(key).map_err(cache_error)? {
+        return Err(error_response(Status::NotFound, format!("Cache entry '{}' not found", key)));
+    }
+    info!("Deleted cache entry '{}'", key);
+    Ok(Json(CacheDeleteResponse {
+        success: true,
+        deleted: 1,
+    }))
+}
+
+/// Delete all attribute cache entries starting with a prefix
+#[delete("/entries?<prefix>")]
+pub fn delete_cache_entries(prefix: &str) -> Result<Json<CacheDeleteResponse>, ApiError> {
+    if prefix.is_empty() {
+        return Err(error_response(Status::BadRequest, "The prefix must not be empty"));
+    }
+    let deleted = attributecache::remove_by_prefix(prefix).map_err(cache_error)?;
+    info!("Deleted {} cache entries with prefix '{}'", deleted, prefix);
+    Ok(Json(CacheDeleteResponse {
+        success: true,
+        deleted,
+    }))
+}
+
+/// Delete all attribute cache entries of a service, e.g. `theaudiodb`
+#[delete("/services/<service>")]
+pub fn purge_service_cache(service: &str) -> Result<Json<CacheDeleteResponse>, ApiError> {
+    if service.is_empty() || service.contains("::") {
+        return Err(error_response(Status::BadRequest, format!("Invalid service name '{}'", service)));
+    }
+    let deleted = attributecache::remove_by_prefix(&format!("{}::", service)).map_err(cache_error)?;
+    info!("Purged {} cache entries of service '{}'", deleted, service);
+    Ok(Json(CacheDeleteResponse {
+        success: true,
+        deleted,
+    }))
+}
+
+/// Remove expired attribute cache entries and images, and image contents that are not used anymore
+#[post("/cleanup")]
+pub fn cleanup_caches() -> Result<Json<CacheCleanupResponse>, ApiError> {
+    let expired_entries = attributecache::remove_expired().map_err(cache_error)?;
+    let expired_images = imagecache::expire_images().map_err(cache_error)?;
+    let removed_contents = imagecache::collect_garbage().map_err(cache_error)?;
+    info!("Cache cleanup removed {} entries, {} images and {} image contents",
+          expired_entries, expired_images, removed_contents);
+    Ok(Json(CacheCleanupResponse {
+        success: true,
+        expired_entries,
+        expired_images,
+        removed_contents,
+    }))
+}
@@ -0,0 +1,105 @@
+use crate::helpers::cdsource::{self, CdDisc, CdError, RipReport};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use serde::{Deserialize, Serialize};
+use log::info;
+
+/// Response structure for the CD status
+#[derive(Serialize, Deserialize)]
+pub struct CdStatusResponse {
+    /// Whether the CD source is enabled
+    pub enabled: bool,
+    /// CD drive, e.g. /dev/sr0
+    pub drive: Option<String>,
+    /// The inserted disc
+    pub disc: Option<CdDisc>,
+    pub ripping: bool,
+    /// Report of the running or last rip
+    pub rip: Option<RipReport>,
+}
+
+/// Response structure for a started rip
+#[derive(Serialize, Deserialize)]
+pub struct RipResponse {
+    pub success: bool,
+    pub rip: RipReport,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(error: CdError) -> ApiError {
+    let status = match error {
+        CdError::NoDrive | CdError::NoDisc | CdError::InvalidTrack(_) => Status::NotFound,
+        CdError::AlreadyRunning => Status::Conflict,
+        CdError::NoPlayer(_) => Status::ServiceUnavailable,
+        CdError::Command(_) => Status::InternalServerError,
+    };
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: error.to_string(),
+    }))
+}
+
+/// Get the CD drive, the inserted disc and the rip status
+#[get("/status")]
+pub fn get_status() -> Json<CdStatusResponse> {
+    Json(CdStatusResponse {
+        enabled: cdsource::is_enabled(),
+        drive: cdsource::get_drive(),
+        disc: cdsource::get_disc(),
+        ripping: cdsource::is_ripping(),
+        rip: cdsource::get_rip_report(),
+    })
+}
+
+/// Play the inserted disc with MPD
+///
+/// # Parameters
+/// * `track` - Optional track number to start with
+#[post("/play?<track>")]
+pub fn play(track: Option<u32>) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: play CD from track {:?}", track);
+    cdsource::play(track).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: "CD playback started".to_string(),
+    }))
+}
+
+/// Rip the inserted disc to FLAC files in the background
+#[post("/rip")]
+pub fn rip() -> Result<Json<RipResponse>, ApiError> {
+    info!("API request: rip CD");
+    let rip = cdsource::start_rip().map_err(error_response)?;
+    Ok(Json(RipResponse {
+        success: true,
+        rip,
+    }))
+}
+
+/// Eject the disc
+#[post("/eject")]
+pub fn eject() -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: eject CD");
+    cdsource::eject().map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: "Disc ejected".to_string(),
+    }))
+}
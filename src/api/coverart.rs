@@ -26,6 +26,9 @@ pub struct CoverartMethodsResponse {
 #[derive(Deserialize)]
 pub struct UpdateImageRequest {
     url: String,
+    /// Cover art provider the URL was taken from, "custom" if not given
+    #[serde(default)]
+    provider: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -268,7 +271,8 @@ pub fn update_artist_image(artist_b64: String, request: Json<UpdateImageRequest>
                 let artist_store = crate::helpers::artist_store::get_artist_store();
                 let mut store_lock = artist_store.lock();
                 
-                match store_lock.download_and_store_user_image(&artist_name, &request.url, "custom") {
+                let provider = request.provider.as_deref().filter(|p| !p.is_empty()).unwrap_or("custom");
+                match store_lock.download_and_store_user_image(&artist_name, &request.url, "custom", provider) {
                     crate::helpers::artist_store::ArtistImageResult::Found { cache_path } => {
                         info!("Successfully downloaded and stored custom image in user directory for artist '{}': {}", artist_name, cache_path);
                     }
@@ -359,3 +363,232 @@ pub fn get_artist_image(artist_b64: String) -> Result<(rocket::http::ContentType
         }
     }
 }
+
+/// Full-resolution image served as a file download
+#[derive(rocket::Responder)]
+pub struct ArtworkDownload {
+    inner: (rocket::http::ContentType, Vec<u8>),
+    disposition: rocket::http::Header<'static>,
+}
+
+impl ArtworkDownload {
+    fn new(name: &str, data: Vec<u8>, mime_type: &str) -> Self {
+        let content_type = rocket::http::ContentType::parse_flexible(mime_type)
+            .unwrap_or(rocket::http::ContentType::Binary);
+        let filename = format!(
+            "{}.{}",
+            crate::helpers::sanitize::filename_from_string(name),
+            extension_for_mime_type(mime_type)
+        );
+        Self {
+            inner: (content_type, data),
+            disposition: rocket::http::Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        }
+    }
+}
+
+fn extension_for_mime_type(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "jpg",
+    }
+}
+
+fn mime_type_for_path(path: &str) -> &'static str {
+    if path.ends_with(".png") {
+        "image/png"
+    } else if path.ends_with(".gif") {
+        "image/gif"
+    } else if path.ends_with(".webp") {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// Response structure for the source of cached artwork
+#[derive(Serialize)]
+pub struct ArtworkSourceResponse {
+    /// Whether a cached image exists
+    found: bool,
+    /// Provider the image came from, if known
+    provider: Option<String>,
+    /// Original URL of the image, if it was downloaded
+    url: Option<String>,
+    mime_type: Option<String>,
+    size_bytes: Option<usize>,
+}
+
+impl ArtworkSourceResponse {
+    fn new(image: Option<(usize, String)>, source: Option<crate::helpers::coverart::ArtworkSource>) -> Self {
+        let found = image.is_some();
+        let (size_bytes, mime_type) = image.unzip();
+        let (provider, url) = match source {
+            Some(source) if found => (Some(source.provider), source.url),
+            _ => (None, None),
+        };
+        Self {
+            found,
+            provider,
+            url,
+            mime_type,
+            size_bytes,
+        }
+    }
+}
+
+fn decode_album_params(
+    title_b64: &str,
+    artist_b64: &str,
+) -> Result<(String, String), rocket::response::status::Custom<String>> {
+    match (decode_url_safe(title_b64), decode_url_safe(artist_b64)) {
+        (Some(title), Some(artist)) => Ok((title, artist)),
+        _ => {
+            warn!("Failed to decode album parameters: {} / {}", title_b64, artist_b64);
+            Err(rocket::response::status::Custom(
+                rocket::http::Status::BadRequest,
+                "Invalid album or artist name encoding".to_string(),
+            ))
+        }
+    }
+}
+
+fn decode_artist_param(artist_b64: &str) -> Result<String, rocket::response::status::Custom<String>> {
+    decode_url_safe(artist_b64).ok_or_else(|| {
+        warn!("Failed to decode artist parameter: {}", artist_b64);
+        rocket::response::status::Custom(
+            rocket::http::Status::BadRequest,
+            "Invalid artist name encoding".to_string(),
+        )
+    })
+}
+
+/// Download the full-resolution cached cover of an album
+///
+/// Only the cache is used, no providers are queried.
+///
+/// # Parameters
+/// * `title_b64` - Base64 encoded album title
+/// * `artist_b64` - Base64 encoded artist name
+/// * `year` - Optional release year
+#[get("/album/<title_b64>/<artist_b64>/download?<year>")]
+pub fn download_album_artwork(
+    title_b64: String,
+    artist_b64: String,
+    year: Option<i32>,
+) -> Result<ArtworkDownload, rocket::response::status::Custom<String>> {
+    let (title, artist) = decode_album_params(&title_b64, &artist_b64)?;
+
+    match crate::helpers::imagecache::get_album_cover(&artist, &title, year) {
+        Ok((data, mime_type)) => {
+            debug!("Serving album cover download for '{}' by '{}'", title, artist);
+            Ok(ArtworkDownload::new(&format!("{} - {}", artist, title), data, &mime_type))
+        }
+        Err(_) => Err(rocket::response::status::Custom(
+            rocket::http::Status::NotFound,
+            format!("No cached cover found for album '{}' by '{}'", title, artist),
+        )),
+    }
+}
+
+/// Download the full-resolution cached image of an artist
+///
+/// Only the cache is used, no download is triggered.
+///
+/// # Parameters
+/// * `artist_b64` - Base64 encoded artist name
+#[get("/artist/<artist_b64>/download")]
+pub fn download_artist_artwork(artist_b64: String) -> Result<ArtworkDownload, rocket::response::status::Custom<String>> {
+    let artist_name = decode_artist_param(&artist_b64)?;
+
+    let not_found = || rocket::response::status::Custom(
+        rocket::http::Status::NotFound,
+        format!("No cached image found for artist '{}'", artist_name),
+    );
+    let cache_path = crate::helpers::artist_store::get_artist_cached_image(&artist_name).ok_or_else(not_found)?;
+
+    match std::fs::read(&cache_path) {
+        Ok(data) => {
+            debug!("Serving artist image download for '{}' from {}", artist_name, cache_path);
+            Ok(ArtworkDownload::new(&artist_name, data, mime_type_for_path(&cache_path)))
+        }
+        Err(e) => {
+            warn!("Failed to read cached image for artist '{}' at '{}': {}", artist_name, cache_path, e);
+            Err(rocket::response::status::Custom(
+                rocket::http::Status::InternalServerError,
+                format!("Failed to read cached image: {}", e),
+            ))
+        }
+    }
+}
+
+/// Get the provider the cached cover of an album came from
+///
+/// # Parameters
+/// * `title_b64` - Base64 encoded album title
+/// * `artist_b64` - Base64 encoded artist name
+/// * `year` - Optional release year
+#[get("/album/<title_b64>/<artist_b64>/source?<year>")]
+pub fn get_album_artwork_source(
+    title_b64: String,
+    artist_b64: String,
+    year: Option<i32>,
+) -> Result<Json<ArtworkSourceResponse>, rocket::response::status::Custom<String>> {
+    let (title, artist) = decode_album_params(&title_b64, &artist_b64)?;
+
+    let image = crate::helpers::imagecache::get_album_cover(&artist, &title, year)
+        .ok()
+        .map(|(data, mime_type)| (data.len(), mime_type));
+    let cache_key = crate::helpers::local_coverart::album_cache_key(&artist, &title, year);
+    let source = crate::helpers::coverart::get_artwork_source("album", &cache_key);
+
+    Ok(Json(ArtworkSourceResponse::new(image, source)))
+}
+
+/// Get the provider the cached image of an artist came from
+///
+/// # Parameters
+/// * `artist_b64` - Base64 encoded artist name
+#[get("/artist/<artist_b64>/source")]
+pub fn get_artist_artwork_source(artist_b64: String) -> Result<Json<ArtworkSourceResponse>, rocket::response::status::Custom<String>> {
+    let artist_name = decode_artist_param(&artist_b64)?;
+
+    let image = crate::helpers::artist_store::get_artist_cached_image(&artist_name).and_then(|path| {
+        std::fs::metadata(&path)
+            .ok()
+            .map(|meta| (meta.len() as usize, mime_type_for_path(&path).to_string()))
+    });
+    let source = crate::helpers::coverart::get_artwork_source("artist", &artist_name);
+
+    Ok(Json(ArtworkSourceResponse::new(image, source)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artwork_source_response() {
+        let source = crate::helpers::coverart::ArtworkSource::new("fanarttv", Some("http://example.com/a.jpg"));
+        let response = ArtworkSourceResponse::new(Some((1234, "image/jpeg".to_string())), Some(source.clone()));
+        assert!(response.found);
+        assert_eq!(response.provider.as_deref(), Some("fanarttv"));
+        assert_eq!(response.size_bytes, Some(1234));
+
+        // A stale source is not reported if the image is gone
+        let response = ArtworkSourceResponse::new(None, Some(source));
+        assert!(!response.found);
+        assert!(response.provider.is_none());
+    }
+
+    #[test]
+    fn test_extension_for_mime_type() {
+        assert_eq!(extension_for_mime_type("image/png"), "png");
+        assert_eq!(extension_for_mime_type("image/jpeg"), "jpg");
+    }
+}
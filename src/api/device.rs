@@ -0,0 +1,50 @@
+use crate::helpers::device_identity::{self, DeviceIdentity};
+use crate::helpers::federation;
+use log::info;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, put};
+use serde::{Deserialize, Serialize};
+
+/// Request structure to set the friendly name
+#[derive(Deserialize, Serialize)]
+pub struct NameRequest {
+    /// New name, an empty name switches back to the configured name
+    pub name: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Get the ID and friendly name of this device
+#[get("/")]
+pub fn get_device() -> Json<DeviceIdentity> {
+    Json(device_identity::identity())
+}
+
+fn rename(request: NameRequest) -> Result<Json<DeviceIdentity>, Custom<Json<ErrorResponse>>> {
+    info!("API request: set device name to {:?}", request.name);
+    let identity = device_identity::set_name(&request.name).map_err(|message| {
+        Custom(Status::BadRequest, Json(ErrorResponse { success: false, message }))
+    })?;
+    // Peers learn the new name from the announcement
+    federation::announce();
+    Ok(Json(identity))
+}
+
+/// Set the friendly name
+#[put("/name", data = "<request>")]
+pub fn set_name(request: Json<NameRequest>) -> Result<Json<DeviceIdentity>, Custom<Json<ErrorResponse>>> {
+    rename(request.into_inner())
+}
+
+/// Set the friendly name, for clients that can't send PUT requests
+#[post("/name", data = "<request>")]
+pub fn set_name_post(request: Json<NameRequest>) -> Result<Json<DeviceIdentity>, Custom<Json<ErrorResponse>>> {
+    rename(request.into_inner())
+}
@@ -0,0 +1,17 @@
+use crate::helpers::network_diagnostics::{self, NetworkDiagnostics, PlayerStalls};
+use rocket::get;
+use rocket::serde::json::Json;
+
+/// Measure latency and packet loss to the gateway, LMS, Spotify and playing streams
+///
+/// Takes a few seconds, the endpoints are measured in parallel.
+#[get("/network?<attempts>")]
+pub fn get_network_diagnostics(attempts: Option<u32>) -> Json<NetworkDiagnostics> {
+    Json(network_diagnostics::run_diagnostics(attempts))
+}
+
+/// Get the recent playback stalls of all players without measuring the network
+#[get("/stalls")]
+pub fn get_stalls() -> Json<Vec<PlayerStalls>> {
+    Json(network_diagnostics::get_stalls())
+}
@@ -0,0 +1,140 @@
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use serde::Serialize;
+
+/// The `?dry_run=true` query parameter of endpoints that change or delete data
+///
+/// Endpoints take this as a request guard and pass the planning and the applying
+/// part of the operation to `run`, so all of them behave the same in dry-run mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DryRun(pub bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DryRun {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.query_value::<bool>("dry_run") {
+            None => Outcome::Success(DryRun(false)),
+            Some(Ok(dry_run)) => Outcome::Success(DryRun(dry_run)),
+            Some(Err(_)) => Outcome::Error((Status::BadRequest, "dry_run must be true or false".to_string())),
+        }
+    }
+}
+
+/// A single change an operation would make
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedChange {
+    /// What would be done, e.g. "delete_file" or "remove_from_queue"
+    pub action: String,
+    /// What it would be done to, e.g. a file path or a track URI
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl PlannedChange {
+    pub fn new(action: &str, target: impl Into<String>) -> Self {
+        Self {
+            action: action.to_string(),
+            target: target.into(),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Response of an operation called in dry-run mode
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunPreview {
+    pub dry_run: bool,
+    pub operation: String,
+    pub changes: Vec<PlannedChange>,
+}
+
+/// Either the preview of an operation or the response of the applied operation
+#[derive(Debug)]
+pub enum DryRunResponse<R> {
+    Preview(DryRunPreview),
+    Applied(R),
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for DryRunResponse<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
+        match self {
+            DryRunResponse::Preview(preview) => {
+                let mut response = Json(preview).respond_to(request)?;
+                response.set_header(Header::new("X-Dry-Run", "true"));
+                Ok(response)
+            }
+            DryRunResponse::Applied(inner) => inner.respond_to(request),
+        }
+    }
+}
+
+impl DryRun {
+    pub fn is_set(&self) -> bool {
+        self.0
+    }
+
+    /// Run an operation or, in dry-run mode, only report what it would change
+    ///
+    /// `plan` must not modify anything. It should fail in the same cases `apply`
+    /// would fail, so a dry run shows errors as well.
+    pub fn run<T, E>(
+        self,
+        operation: &str,
+        plan: impl FnOnce() -> Result<Vec<PlannedChange>, E>,
+        apply: impl FnOnce() -> Result<T, E>,
+    ) -> Result<DryRunResponse<T>, E> {
+        if self.is_set() {
+            let changes = plan()?;
+            log::debug!("Dry run of {}: {} change(s)", operation, changes.len());
+            Ok(DryRunResponse::Preview(DryRunPreview {
+                dry_run: true,
+                operation: operation.to_string(),
+                changes,
+            }))
+        } else {
+            apply().map(DryRunResponse::Applied)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dry_run_does_not_apply() {
+        let mut applied = false;
+        let result: Result<_, String> = DryRun(true).run(
+            "delete_album",
+            || Ok(vec![PlannedChange::new("delete_file", "/music/a/01.flac")]),
+            || {
+                applied = true;
+                Ok(())
+            },
+        );
+        match result {
+            Ok(DryRunResponse::Preview(preview)) => {
+                assert_eq!(preview.operation, "delete_album");
+                assert_eq!(preview.changes.len(), 1);
+            }
+            _ => panic!("expected a preview"),
+        }
+        assert!(!applied);
+
+        let result: Result<_, String> = DryRun(false).run("delete_album", || Ok(vec![]), || Ok(42));
+        assert!(matches!(result, Ok(DryRunResponse::Applied(42))));
+
+        let result: Result<DryRunResponse<()>, String> = DryRun(true).run("delete_album", || Err("not found".to_string()), || Ok(()));
+        assert_eq!(result.unwrap_err(), "not found");
+    }
+}
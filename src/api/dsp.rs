@@ -0,0 +1,48 @@
+use crate::helpers::dspprofiles::{self, DspError, DspStatus};
+use log::info;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post};
+use serde::{Deserialize, Serialize};
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn dsp_error(error: DspError) -> ApiError {
+    let status = match error {
+        DspError::UnknownProfile(_) => Status::NotFound,
+        DspError::Disabled => Status::ServiceUnavailable,
+        DspError::Backend(_) => Status::BadGateway,
+    };
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: error.to_string(),
+    }))
+}
+
+/// Get the active DSP profile and the manual override
+#[get("/")]
+pub fn get_status() -> Json<DspStatus> {
+    Json(dspprofiles::status())
+}
+
+/// Activate a profile manually, the rules apply again after `minutes`
+#[post("/override/<profile>?<minutes>")]
+pub fn set_override(profile: &str, minutes: Option<u64>) -> Result<Json<DspStatus>, ApiError> {
+    info!("API request: activate DSP profile '{}'", profile);
+    dspprofiles::set_override(profile, minutes).map(Json).map_err(dsp_error)
+}
+
+/// Remove the manual override and apply the rules again
+#[delete("/override")]
+pub fn clear_override() -> Json<DspStatus> {
+    info!("API request: clear DSP profile override");
+    Json(dspprofiles::clear_override())
+}
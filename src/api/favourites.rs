@@ -4,7 +4,7 @@ use rocket::serde::{Serialize, Deserialize};
 use log::{info, error};
 
 use crate::data::song::Song;
-use crate::helpers::favourites;
+use crate::helpers::favourites::{self, FavouriteItem, FavouriteKind};
 
 /// Request payload for adding/removing favourites
 #[derive(Deserialize)]
@@ -13,6 +13,26 @@ pub struct FavouriteRequest {
     title: String,
 }
 
+/// Request payload for adding/removing a favourite album
+#[derive(Deserialize)]
+pub struct FavouriteAlbumRequest {
+    artist: String,
+    name: String,
+}
+
+/// Request payload for adding/removing a favourite artist
+#[derive(Deserialize)]
+pub struct FavouriteArtistRequest {
+    name: String,
+}
+
+/// Response listing favourite albums or artists
+#[derive(Serialize)]
+pub struct FavouriteItemsResponse {
+    count: usize,
+    items: Vec<FavouriteItem>,
+}
+
 /// Response for favourite status check
 #[derive(Serialize)]
 pub struct FavouriteStatusResponse {
@@ -126,6 +146,98 @@ pub fn remove_favourite(request: Json<FavouriteRequest>) -> Json<Result<Favourit
     }
 }
 
+fn item_status(item: FavouriteItem) -> Json<Result<FavouriteStatusResponse, ErrorResponse>> {
+    match favourites::get_favourite_item_providers(&item) {
+        Ok((is_favourite, providers)) => Json(Ok(FavouriteStatusResponse { is_favourite, providers })),
+        Err(e) => {
+            error!("Error checking favourite status of {}: {}", item, e);
+            Json(Err(ErrorResponse { error: e.to_string() }))
+        }
+    }
+}
+
+fn update_item(item: FavouriteItem, add: bool) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    let all_providers = favourites::get_enabled_providers();
+    let result = if add {
+        favourites::add_favourite_item(&item)
+    } else {
+        favourites::remove_favourite_item(&item)
+    };
+    match result {
+        Ok(updated_providers) => {
+            let message = if add {
+                format!("Added {} to favourites", item)
+            } else {
+                format!("Removed {} from favourites", item)
+            };
+            info!("{} in providers: {:?}", message, updated_providers);
+            Json(Ok(FavouriteOperationResponse {
+                success: true,
+                message,
+                providers: all_providers,
+                updated_providers,
+            }))
+        }
+        Err(e) => {
+            error!("Error updating favourite {}: {}", item, e);
+            Json(Err(ErrorResponse { error: e.to_string() }))
+        }
+    }
+}
+
+fn list_items(kind: FavouriteKind) -> Json<FavouriteItemsResponse> {
+    let items = favourites::get_favourite_items(kind);
+    Json(FavouriteItemsResponse { count: items.len(), items })
+}
+
+/// Check if an album is favourite
+#[get("/album/is_favourite?<artist>&<name>")]
+pub fn is_favourite_album(artist: &str, name: &str) -> Json<Result<FavouriteStatusResponse, ErrorResponse>> {
+    item_status(FavouriteItem::album(artist, name))
+}
+
+/// Add an album to favourites
+#[post("/album/add", data = "<request>")]
+pub fn add_favourite_album(request: Json<FavouriteAlbumRequest>) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    update_item(FavouriteItem::album(&request.artist, &request.name), true)
+}
+
+/// Remove an album from favourites
+#[delete("/album/remove", data = "<request>")]
+pub fn remove_favourite_album(request: Json<FavouriteAlbumRequest>) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    update_item(FavouriteItem::album(&request.artist, &request.name), false)
+}
+
+/// List the favourite albums
+#[get("/albums")]
+pub fn get_favourite_albums() -> Json<FavouriteItemsResponse> {
+    list_items(FavouriteKind::Album)
+}
+
+/// Check if an artist is favourite
+#[get("/artist/is_favourite?<name>")]
+pub fn is_favourite_artist(name: &str) -> Json<Result<FavouriteStatusResponse, ErrorResponse>> {
+    item_status(FavouriteItem::artist(name))
+}
+
+/// Add an artist to favourites
+#[post("/artist/add", data = "<request>")]
+pub fn add_favourite_artist(request: Json<FavouriteArtistRequest>) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    update_item(FavouriteItem::artist(&request.name), true)
+}
+
+/// Remove an artist from favourites
+#[delete("/artist/remove", data = "<request>")]
+pub fn remove_favourite_artist(request: Json<FavouriteArtistRequest>) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    update_item(FavouriteItem::artist(&request.name), false)
+}
+
+/// List the favourite artists
+#[get("/artists")]
+pub fn get_favourite_artists() -> Json<FavouriteItemsResponse> {
+    list_items(FavouriteKind::Artist)
+}
+
 /// Get favourite provider status
 #[get("/providers")]
 pub fn get_providers() -> Json<serde_json::Value> {
@@ -141,7 +253,44 @@ pub fn get_providers() -> Json<serde_json::Value> {
     }))
 }
 
+/// Start importing the favourites of a provider into the local favourites
+///
+/// The sync runs in the background, its result is shown in the provider status.
+#[post("/providers/<name>/sync")]
+pub fn sync_provider(name: &str) -> Json<Result<FavouriteOperationResponse, ErrorResponse>> {
+    info!("Starting favourite sync for provider {}", name);
+
+    match favourites::sync_provider(name) {
+        Ok(()) => Json(Ok(FavouriteOperationResponse {
+            success: true,
+            message: format!("Sync of {} started", name),
+            providers: favourites::get_enabled_providers(),
+            updated_providers: vec![name.to_string()],
+        })),
+        Err(e) => {
+            error!("Error starting favourite sync for {}: {}", name, e);
+            Json(Err(ErrorResponse {
+                error: e.to_string(),
+            }))
+        }
+    }
+}
+
 /// Export routes for mounting in the main server
 pub fn routes() -> Vec<rocket::Route> {
-    routes![is_favourite, add_favourite, remove_favourite, get_providers]
+    routes![
+        is_favourite,
+        add_favourite,
+        remove_favourite,
+        get_providers,
+        sync_provider,
+        is_favourite_album,
+        add_favourite_album,
+        remove_favourite_album,
+        get_favourite_albums,
+        is_favourite_artist,
+        add_favourite_artist,
+        remove_favourite_artist,
+        get_favourite_artists
+    ]
 }
@@ -0,0 +1,135 @@
+use crate::api::players;
+use crate::audiocontrol::AudioController;
+use crate::helpers::device_identity;
+use crate::helpers::federation::{self, FederationError, Peer};
+use log::info;
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::response::content::RawJson;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Response structure for the peer list
+#[derive(Serialize, Deserialize)]
+pub struct PeersResponse {
+    pub enable: bool,
+    pub peers: Vec<Peer>,
+}
+
+/// Players of one device
+#[derive(Serialize, Deserialize)]
+pub struct DevicePlayers {
+    pub id: String,
+    pub name: String,
+    /// True for the device that answered the request
+    pub local: bool,
+    pub online: bool,
+    pub players: Vec<serde_json::Value>,
+}
+
+/// Response structure for the players of all devices
+#[derive(Serialize, Deserialize)]
+pub struct FederatedPlayersResponse {
+    pub devices: Vec<DevicePlayers>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ProxyResult = Result<Custom<RawJson<String>>, Custom<Json<ErrorResponse>>>;
+
+fn federation_error(error: FederationError) -> Custom<Json<ErrorResponse>> {
+    let status = match error {
+        FederationError::Disabled => Status::ServiceUnavailable,
+        FederationError::UnknownPeer(_) => Status::NotFound,
+        FederationError::Offline(_) | FederationError::Request(_) => Status::BadGateway,
+    };
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: error.to_string(),
+    }))
+}
+
+/// List the other AudioControl instances found on the network
+#[get("/peers")]
+pub fn list_peers() -> Json<PeersResponse> {
+    Json(PeersResponse {
+        enable: federation::is_enabled(),
+        peers: federation::peers(),
+    })
+}
+
+/// List the players of this device and of all peers
+#[get("/players")]
+pub fn list_players(controller: &State<Arc<AudioController>>) -> Json<FederatedPlayersResponse> {
+    let identity = device_identity::identity();
+    let local_players = serde_json::to_value(players::list_players(controller).into_inner())
+        .ok()
+        .and_then(|v| v.get("players").and_then(|p| p.as_array()).cloned())
+        .unwrap_or_default();
+
+    let mut devices = vec![DevicePlayers {
+        id: identity.id,
+        name: identity.name,
+        local: true,
+        online: true,
+        players: local_players,
+    }];
+    devices.extend(federation::peers().into_iter().map(|peer| DevicePlayers {
+        id: peer.id,
+        name: peer.name,
+        local: false,
+        online: peer.online,
+        players: peer.players,
+    }));
+    Json(FederatedPlayersResponse { devices })
+}
+
+async fn proxy(device: String, method: &'static str, path: PathBuf, origin: &Origin<'_>, body: Option<String>) -> ProxyResult {
+    let mut target = format!("/api/{}", path.to_string_lossy().replace('\\', "/"));
+    if let Some(query) = origin.query() {
+        target.push('?');
+        target.push_str(query.as_str());
+    }
+    info!("API request: forward {} {} to device {}", method, target, device);
+    // Requests to peers block until they answer or time out
+    let (status, body) = rocket::tokio::task::spawn_blocking(move || {
+        federation::forward(&device, method, &target, body.as_deref())
+    })
+    .await
+    .map_err(|e| federation_error(FederationError::Request(e.to_string())))?
+    .map_err(federation_error)?;
+    Ok(Custom(Status::from_code(status).unwrap_or(Status::BadGateway), RawJson(body)))
+}
+
+/// Forward a GET request to the API of a peer
+#[get("/proxy/<device>/<path..>")]
+pub async fn proxy_get(device: &str, path: PathBuf, origin: &Origin<'_>) -> ProxyResult {
+    proxy(device.to_string(), "GET", path, origin, None).await
+}
+
+/// Forward a POST request to the API of a peer
+#[post("/proxy/<device>/<path..>", data = "<body>")]
+pub async fn proxy_post(device: &str, path: PathBuf, origin: &Origin<'_>, body: String) -> ProxyResult {
+    proxy(device.to_string(), "POST", path, origin, Some(body)).await
+}
+
+/// Forward a PUT request to the API of a peer
+#[put("/proxy/<device>/<path..>", data = "<body>")]
+pub async fn proxy_put(device: &str, path: PathBuf, origin: &Origin<'_>, body: String) -> ProxyResult {
+    proxy(device.to_string(), "PUT", path, origin, Some(body)).await
+}
+
+/// Forward a DELETE request to the API of a peer
+#[delete("/proxy/<device>/<path..>")]
+pub async fn proxy_delete(device: &str, path: PathBuf, origin: &Origin<'_>) -> ProxyResult {
+    proxy(device.to_string(), "DELETE", path, origin, None).await
+}
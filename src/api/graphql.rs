@@ -0,0 +1,485 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{self, Identifier, LibraryInterface};
+use crate::helpers::library_search::{self, ResultKind};
+use crate::players::PlayerController;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Enum, Object, SimpleObject};
+use log::warn;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Schema of the GraphQL API, read only
+pub type AudioControlSchema = async_graphql::Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Configuration of the `graphql` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphqlConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Maximum nesting of a query, limits the work a single request can cause
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_max_depth() -> usize {
+    10
+}
+
+impl Default for GraphqlConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            max_depth: default_max_depth(),
+        }
+    }
+}
+
+/// The schema if the GraphQL API is enabled
+pub fn schema_from_config(config: &serde_json::Value, controller: Arc<AudioController>) -> Option<AudioControlSchema> {
+    let graphql_config = match get_service_config(config, "graphql").map(|c| serde_json::from_value::<GraphqlConfig>(c.clone())) {
+        Some(Ok(parsed)) => parsed,
+        Some(Err(e)) => {
+            warn!("Invalid graphql configuration, using defaults: {}", e);
+            GraphqlConfig::default()
+        }
+        None => GraphqlConfig::default(),
+    };
+    graphql_config.enable.then(|| build_schema(controller, graphql_config.max_depth))
+}
+
+fn build_schema(controller: Arc<AudioController>, max_depth: usize) -> AudioControlSchema {
+    AudioControlSchema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(controller)
+        .limit_depth(max_depth)
+        .finish()
+}
+
+fn audio_controller<'a>(ctx: &Context<'a>) -> &'a Arc<AudioController> {
+    ctx.data_unchecked::<Arc<AudioController>>()
+}
+
+/// Run `f` with the library of a player, None if the player has no library
+///
+/// Libraries can't be kept in GraphQL objects as they aren't thread safe, so they are
+/// looked up again for every field that needs them.
+fn with_library<T>(ctx: &Context<'_>, player_name: &str, f: impl FnOnce(&dyn LibraryInterface) -> T) -> Option<T> {
+    let player = audio_controller(ctx).get_player_by_name(player_name)?;
+    let library = player.read().get_library()?;
+    Some(f(library.as_ref()))
+}
+
+fn parse_identifier(id: &str) -> Identifier {
+    match id.parse::<u64>() {
+        Ok(numeric) => Identifier::Numeric(numeric),
+        Err(_) => Identifier::String(id.to_string()),
+    }
+}
+
+/// Apply offset and limit to a list sorted by name
+fn page<T>(mut items: Vec<T>, name: impl Fn(&T) -> &str, filter: Option<&str>, offset: usize, limit: usize) -> Vec<T> {
+    if let Some(filter) = filter.map(str::to_lowercase) {
+        items.retain(|item| name(item).to_lowercase().contains(&filter));
+    }
+    items.sort_by_key(|item| name(item).to_lowercase());
+    items.into_iter().skip(offset).take(limit).collect()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// All players
+    async fn players(&self, ctx: &Context<'_>) -> Vec<Player> {
+        audio_controller(ctx)
+            .list_controllers()
+            .iter()
+            .map(|player| Player { name: player.read().get_player_name() })
+            .collect()
+    }
+
+    /// A player by name
+    async fn player(&self, ctx: &Context<'_>, name: String) -> Option<Player> {
+        audio_controller(ctx).get_player_by_name(&name).map(|_| Player { name })
+    }
+
+    /// The active player
+    async fn active_player(&self, ctx: &Context<'_>) -> Option<Player> {
+        let player = audio_controller(ctx).get_active_controller()?;
+        let name = player.read().get_player_name();
+        Some(Player { name })
+    }
+
+    /// Search artists, albums and tracks in the libraries of all players
+    async fn search(
+        &self,
+        query: String,
+        types: Option<Vec<SearchResultType>>,
+        player: Option<String>,
+        #[graphql(default = 50)] limit: usize,
+    ) -> Vec<SearchResult> {
+        let kinds: Vec<ResultKind> = types.unwrap_or_default().into_iter().map(ResultKind::from).collect();
+        library_search::search(&query, &kinds, player.as_deref(), limit)
+            .into_iter()
+            .map(SearchResult::from)
+            .collect()
+    }
+}
+
+/// A player, its state is read when the fields are resolved
+pub struct Player {
+    name: String,
+}
+
+impl Player {
+    fn read<T>(&self, ctx: &Context<'_>, f: impl FnOnce(&dyn PlayerController) -> T) -> Option<T> {
+        let player = audio_controller(ctx).get_player_by_name(&self.name)?;
+        let player = player.read();
+        Some(f(player.as_ref()))
+    }
+}
+
+#[Object]
+impl Player {
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn id(&self, ctx: &Context<'_>) -> Option<String> {
+        self.read(ctx, |p| p.get_player_id())
+    }
+
+    async fn active(&self, ctx: &Context<'_>) -> bool {
+        audio_controller(ctx)
+            .get_active_controller()
+            .is_some_and(|player| player.read().get_player_name() == self.name)
+    }
+
+    /// Playback state, e.g. "playing" or "paused"
+    async fn state(&self, ctx: &Context<'_>) -> Option<String> {
+        self.read(ctx, |p| p.get_playback_state().to_string())
+    }
+
+    /// Position in the current song in seconds
+    async fn position(&self, ctx: &Context<'_>) -> Option<f64> {
+        self.read(ctx, |p| p.get_position()).flatten()
+    }
+
+    async fn shuffle(&self, ctx: &Context<'_>) -> Option<bool> {
+        self.read(ctx, |p| p.get_shuffle())
+    }
+
+    async fn loop_mode(&self, ctx: &Context<'_>) -> Option<String> {
+        self.read(ctx, |p| p.get_loop_mode().to_string())
+    }
+
+    /// The current song
+    async fn song(&self, ctx: &Context<'_>) -> Option<Song> {
+        self.read(ctx, |p| p.get_song()).flatten().map(Song::from)
+    }
+
+    async fn queue(&self, ctx: &Context<'_>) -> Vec<Track> {
+        self.read(ctx, |p| p.get_queue()).unwrap_or_default().into_iter().map(Track::from).collect()
+    }
+
+    /// The library, null if the player doesn't have one
+    async fn library(&self, ctx: &Context<'_>) -> Option<Library> {
+        with_library(ctx, &self.name, |_| Library { player_name: self.name.clone() })
+    }
+}
+
+/// The library of a player
+pub struct Library {
+    player_name: String,
+}
+
+#[Object]
+impl Library {
+    async fn loaded(&self, ctx: &Context<'_>) -> bool {
+        with_library(ctx, &self.player_name, |l| l.is_loaded()).unwrap_or(false)
+    }
+
+    /// Artists sorted by name, optionally only those whose name contains `filter`
+    async fn artists(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<String>,
+        #[graphql(default = 0)] offset: usize,
+        #[graphql(default = 100)] limit: usize,
+    ) -> Vec<Artist> {
+        let artists = with_library(ctx, &self.player_name, |l| l.get_artists()).unwrap_or_default();
+        page(artists, |a| &a.name, filter.as_deref(), offset, limit)
+            .into_iter()
+            .map(|artist| Artist { player_name: self.player_name.clone(), artist })
+            .collect()
+    }
+
+    async fn artist(&self, ctx: &Context<'_>, id: String) -> Option<Artist> {
+        let id = parse_identifier(&id);
+        let artist = with_library(ctx, &self.player_name, |l| l.get_artists().into_iter().find(|a| a.id == id)).flatten()?;
+        Some(Artist { player_name: self.player_name.clone(), artist })
+    }
+
+    /// Albums sorted by name, optionally only those whose name contains `filter`
+    async fn albums(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<String>,
+        #[graphql(default = 0)] offset: usize,
+        #[graphql(default = 100)] limit: usize,
+    ) -> Vec<Album> {
+        let albums = with_library(ctx, &self.player_name, |l| l.get_albums()).unwrap_or_default();
+        page(albums, |a| &a.name, filter.as_deref(), offset, limit)
+            .into_iter()
+            .map(|album| Album { player_name: self.player_name.clone(), album })
+            .collect()
+    }
+
+    async fn album(&self, ctx: &Context<'_>, id: String) -> Option<Album> {
+        let id = parse_identifier(&id);
+        let album = with_library(ctx, &self.player_name, |l| l.get_album_by_id(&id)).flatten()?;
+        Some(Album { player_name: self.player_name.clone(), album })
+    }
+}
+
+pub struct Artist {
+    player_name: String,
+    artist: data::Artist,
+}
+
+#[Object]
+impl Artist {
+    async fn id(&self) -> String {
+        self.artist.id.to_string()
+    }
+
+    async fn name(&self) -> &str {
+        &self.artist.name
+    }
+
+    async fn mbid(&self) -> Option<String> {
+        self.artist.metadata.as_ref().and_then(|m| m.mbid.first().cloned())
+    }
+
+    async fn albums(&self, ctx: &Context<'_>) -> Vec<Album> {
+        with_library(ctx, &self.player_name, |l| l.get_albums_by_artist_id(&self.artist.id))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|album| Album { player_name: self.player_name.clone(), album })
+            .collect()
+    }
+}
+
+pub struct Album {
+    player_name: String,
+    album: data::Album,
+}
+
+#[Object]
+impl Album {
+    async fn id(&self) -> String {
+        self.album.id.to_string()
+    }
+
+    async fn name(&self) -> &str {
+        &self.album.name
+    }
+
+    async fn artists(&self) -> Vec<String> {
+        self.album.artists.lock().clone()
+    }
+
+    /// Release date as YYYY-MM-DD
+    async fn release_date(&self) -> Option<String> {
+        self.album.release_date.map(|d| d.to_string())
+    }
+
+    async fn genres(&self) -> &[String] {
+        &self.album.genres
+    }
+
+    async fn cover_art(&self) -> Option<&str> {
+        self.album.cover_art.as_deref()
+    }
+
+    async fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    async fn tracks(&self) -> Vec<Track> {
+        self.album.tracks.lock().iter().cloned().map(Track::from).collect()
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Track {
+    name: String,
+    artist: Option<String>,
+    disc_number: Option<String>,
+    track_number: Option<u16>,
+    uri: Option<String>,
+    /// Duration in seconds
+    duration: Option<f64>,
+}
+
+impl From<data::Track> for Track {
+    fn from(track: data::Track) -> Self {
+        Self {
+            name: track.name,
+            artist: track.artist,
+            disc_number: track.disc_number,
+            track_number: track.track_number,
+            uri: track.uri,
+            duration: track.duration,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Song {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    track_number: Option<i32>,
+    /// Duration in seconds
+    duration: Option<f64>,
+    genres: Vec<String>,
+    year: Option<i32>,
+    cover_art_url: Option<String>,
+    stream_url: Option<String>,
+    source: Option<String>,
+    liked: Option<bool>,
+}
+
+impl From<data::Song> for Song {
+    fn from(song: data::Song) -> Self {
+        Self {
+            title: song.title,
+            artist: song.artist,
+            album: song.album,
+            album_artist: song.album_artist,
+            track_number: song.track_number,
+            duration: song.duration,
+            genres: song.genres,
+            year: song.year,
+            cover_art_url: song.cover_art_url,
+            stream_url: song.stream_url,
+            source: song.source,
+            liked: song.liked,
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum SearchResultType {
+    Artist,
+    Album,
+    Track,
+}
+
+impl From<SearchResultType> for ResultKind {
+    fn from(kind: SearchResultType) -> Self {
+        match kind {
+            SearchResultType::Artist => ResultKind::Artist,
+            SearchResultType::Album => ResultKind::Album,
+            SearchResultType::Track => ResultKind::Track,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SearchResult {
+    #[graphql(name = "type")]
+    kind: SearchResultType,
+    player_name: String,
+    /// Artist or album ID, the URI for tracks
+    id: String,
+    name: String,
+    artist: Option<String>,
+    album: Option<String>,
+    album_id: Option<String>,
+    score: f64,
+}
+
+impl From<library_search::SearchResult> for SearchResult {
+    fn from(result: library_search::SearchResult) -> Self {
+        Self {
+            kind: match result.kind {
+                ResultKind::Artist => SearchResultType::Artist,
+                ResultKind::Album => SearchResultType::Album,
+                ResultKind::Track => SearchResultType::Track,
+            },
+            player_name: result.player_name,
+            id: result.id,
+            name: result.name,
+            artist: result.artist,
+            album: result.album,
+            album_id: result.album_id,
+            score: result.score,
+        }
+    }
+}
+
+/// Run a GraphQL request sent as JSON
+#[post("/", data = "<request>")]
+pub async fn post_query(request: Json<async_graphql::Request>, schema: &State<AudioControlSchema>) -> Json<async_graphql::Response> {
+    Json(schema.execute(request.into_inner()).await)
+}
+
+/// Run a GraphQL query sent as query parameters, `variables` is a JSON object
+#[get("/?<query>&<operation_name>&<variables>")]
+pub async fn get_query(
+    query: String,
+    operation_name: Option<String>,
+    variables: Option<String>,
+    schema: &State<AudioControlSchema>,
+) -> Json<async_graphql::Response> {
+    let mut request = async_graphql::Request::new(query);
+    if let Some(operation_name) = operation_name {
+        request = request.operation_name(operation_name);
+    }
+    if let Some(variables) = variables {
+        match serde_json::from_str::<serde_json::Value>(&variables) {
+            Ok(variables) => request = request.variables(async_graphql::Variables::from_json(variables)),
+            Err(e) => {
+                return Json(async_graphql::Response::from_errors(vec![async_graphql::ServerError::new(
+                    format!("Invalid variables: {}", e),
+                    None,
+                )]))
+            }
+        }
+    }
+    Json(schema.execute(request).await)
+}
+
+/// The schema in GraphQL schema definition language
+#[get("/schema")]
+pub fn get_schema(schema: &State<AudioControlSchema>) -> String {
+    schema.sdl()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page() {
+        let names = vec!["beta", "Alpha", "gamma", "alphabet"];
+        assert_eq!(page(names.clone(), |n| n, None, 1, 2), vec!["alphabet", "beta"]);
+        assert_eq!(page(names, |n| n, Some("ALPHA"), 0, 10), vec!["Alpha", "alphabet"]);
+    }
+
+    #[rocket::async_test]
+    async fn test_query_players() {
+        let schema = build_schema(Arc::new(AudioController::new()), 10);
+        let response = schema.execute("{ players { name } activePlayer { name } }").await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap(), serde_json::json!({ "players": [], "activePlayer": null }));
+
+        let deep = "{ players { library { artists { albums { tracks { name } } } } } }";
+        assert!(build_schema(Arc::new(AudioController::new()), 3).execute(deep).await.is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::audiocontrol::AudioController;
+use crate::helpers::health::{self, HealthReport, HealthStatus};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use std::sync::Arc;
+
+async fn run_checks(controller: &State<Arc<AudioController>>) -> HealthReport {
+    let controller = controller.inner().clone();
+    // Services are contacted, keep it off the async workers
+    match rocket::tokio::task::spawn_blocking(move || health::check(&controller)).await {
+        Ok(report) => report,
+        Err(e) => HealthReport {
+            status: HealthStatus::Failed,
+            checks: vec![health::HealthCheck {
+                category: "health".to_string(),
+                name: "health".to_string(),
+                status: HealthStatus::Failed,
+                message: Some(e.to_string()),
+            }],
+        },
+    }
+}
+
+/// Get the status of all subsystems, 503 if any of them is degraded or failed
+#[get("/")]
+pub async fn get_health(controller: &State<Arc<AudioController>>) -> Custom<Json<HealthReport>> {
+    let report = run_checks(controller).await;
+    let status = match report.status {
+        HealthStatus::Disabled | HealthStatus::Ok => Status::Ok,
+        HealthStatus::Degraded | HealthStatus::Failed => Status::ServiceUnavailable,
+    };
+    Custom(status, Json(report))
+}
+
+/// Get the status of all subsystems, 503 only if the daemon can't do its job
+#[get("/ready")]
+pub async fn get_readiness(controller: &State<Arc<AudioController>>) -> Custom<Json<HealthReport>> {
+    let report = run_checks(controller).await;
+    let status = if report.status == HealthStatus::Failed { Status::ServiceUnavailable } else { Status::Ok };
+    Custom(status, Json(report))
+}
@@ -0,0 +1,96 @@
+use crate::helpers::listening_history::{self, ChartKind, HistoryEntry, HistoryError, PlayCount};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::get;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default number of entries of the recent history
+const DEFAULT_RECENT_LIMIT: usize = 50;
+
+/// Default number of entries of a chart
+const DEFAULT_CHART_LIMIT: usize = 20;
+
+/// Response structure for the recently played tracks
+#[derive(Serialize, Deserialize)]
+pub struct RecentHistoryResponse {
+    pub success: bool,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// Response structure for play counts
+#[derive(Serialize, Deserialize)]
+pub struct PlayCountResponse {
+    pub success: bool,
+    pub chart: ChartKind,
+    /// Only plays of the last days are counted, all plays without it
+    pub days: Option<u64>,
+    pub entries: Vec<PlayCount>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+fn history_error(error: HistoryError) -> ApiError {
+    let status = match error {
+        HistoryError::Disabled => Status::ServiceUnavailable,
+        HistoryError::Database(_) | HistoryError::Io(_) => Status::InternalServerError,
+    };
+    error_response(status, error.to_string())
+}
+
+/// Unix timestamp of the start of the last `days` days
+fn since(days: Option<u64>) -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    days.map(|d| now.saturating_sub(d * 86400)).unwrap_or(0)
+}
+
+fn play_counts(kind: ChartKind, days: Option<u64>, limit: Option<usize>) -> Result<Json<PlayCountResponse>, ApiError> {
+    let entries = listening_history::with_store(|store| store.chart(kind, since(days), limit)).map_err(history_error)?;
+    Ok(Json(PlayCountResponse {
+        success: true,
+        chart: kind,
+        days,
+        entries,
+    }))
+}
+
+/// Get the recently played tracks, the last one first
+#[get("/recent?<limit>&<offset>&<player>")]
+pub fn get_recent(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    player: Option<&str>,
+) -> Result<Json<RecentHistoryResponse>, ApiError> {
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LIMIT);
+    let entries = listening_history::with_store(|store| store.recent(limit, offset.unwrap_or(0), player))
+        .map_err(history_error)?;
+    Ok(Json(RecentHistoryResponse { success: true, entries }))
+}
+
+/// Get the number of plays of every artist, most played first
+#[get("/artists?<days>")]
+pub fn get_artist_play_counts(days: Option<u64>) -> Result<Json<PlayCountResponse>, ApiError> {
+    play_counts(ChartKind::Artists, days, None)
+}
+
+/// Get the most played artists, albums or tracks
+#[get("/top/<kind>?<days>&<limit>")]
+pub fn get_top(kind: &str, days: Option<u64>, limit: Option<usize>) -> Result<Json<PlayCountResponse>, ApiError> {
+    let kind = kind.parse::<ChartKind>().map_err(|e| error_response(Status::BadRequest, e))?;
+    play_counts(kind, days, Some(limit.unwrap_or(DEFAULT_CHART_LIMIT)))
+}
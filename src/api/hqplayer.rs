@@ -0,0 +1,96 @@
+use crate::audiocontrol::AudioController;
+use crate::players::hqplayer::{HQPlayerController, HQPlayerPipeline};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::info;
+
+/// Request to change the HQPlayer pipeline
+///
+/// All values are indices from the lists returned by the pipeline endpoint.
+#[derive(Serialize, Deserialize, Default)]
+pub struct PipelineRequest {
+    pub mode: Option<u32>,
+    pub filter: Option<u32>,
+    pub shaper: Option<u32>,
+    pub rate: Option<u32>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_with_status(status: Status, message: String) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message,
+    }))
+}
+
+/// Find an HQPlayer controller by player name or ID
+fn find_hqplayer(controller: &AudioController, player_name: &str) -> Result<HQPlayerController, ApiError> {
+    let player = controller
+        .get_player_by_name(player_name)
+        .ok_or_else(|| error_with_status(Status::NotFound, format!("No player found with name: {}", player_name)))?;
+    let player = player.read();
+    player
+        .as_any()
+        .downcast_ref::<HQPlayerController>()
+        .cloned()
+        .ok_or_else(|| error_with_status(Status::BadRequest, format!("Player {} is not an HQPlayer", player_name)))
+}
+
+fn unavailable(message: String) -> ApiError {
+    error_with_status(Status::ServiceUnavailable, message)
+}
+
+/// Get the processing pipeline of an HQPlayer instance
+#[get("/<player_name>/pipeline")]
+pub fn get_pipeline(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<HQPlayerPipeline>, ApiError> {
+    let hqplayer = find_hqplayer(controller, player_name)?;
+    hqplayer.get_pipeline().map(Json).map_err(unavailable)
+}
+
+/// Change the processing pipeline of an HQPlayer instance
+///
+/// The mode is changed first as it determines the available filters and shapers.
+/// Returns the pipeline after the change.
+#[post("/<player_name>/pipeline", data = "<request>")]
+pub fn set_pipeline(
+    player_name: &str,
+    request: Json<PipelineRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<HQPlayerPipeline>, ApiError> {
+    let hqplayer = find_hqplayer(controller, player_name)?;
+    let request = request.into_inner();
+
+    if let Some(mode) = request.mode {
+        info!("Setting HQPlayer mode to {}", mode);
+        hqplayer.set_mode(mode).map_err(unavailable)?;
+    }
+    if let Some(filter) = request.filter {
+        info!("Setting HQPlayer filter to {}", filter);
+        hqplayer.set_filter(filter).map_err(unavailable)?;
+    }
+    if let Some(shaper) = request.shaper {
+        info!("Setting HQPlayer shaper to {}", shaper);
+        hqplayer.set_shaper(shaper).map_err(unavailable)?;
+    }
+    if let Some(rate) = request.rate {
+        info!("Setting HQPlayer rate to {}", rate);
+        hqplayer.set_rate(rate).map_err(unavailable)?;
+    }
+
+    hqplayer.get_pipeline().map(Json).map_err(unavailable)
+}
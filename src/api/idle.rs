@@ -0,0 +1,18 @@
+use crate::helpers::idle::{self, IdleStatus};
+use rocket::serde::json::Json;
+use rocket::{get, post};
+
+/// Get the idle state of the system
+#[get("/status")]
+pub fn get_status() -> Json<IdleStatus> {
+    Json(idle::get_status())
+}
+
+/// Reset the inactivity timer, e.g. when a display is touched
+///
+/// Sends a `system_wake` event if the system was idle.
+#[post("/wake")]
+pub fn wake() -> Json<IdleStatus> {
+    idle::wake();
+    Json(idle::get_status())
+}
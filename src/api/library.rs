@@ -1,13 +1,26 @@
 use crate::AudioController;
-use crate::data::{Album, Artist, Identifier};
-use crate::data::library::ArtistMatchType;
+use crate::audiocontrol::library::AggregatedLibraryPlayer;
+use crate::api::dryrun::{DryRun, DryRunResponse, PlannedChange};
+use crate::data::{Album, Artist, Identifier, LibraryDiff, PlayerCommand};
+use crate::data::library::{ArtistMatchType, LibraryInterface};
+use crate::helpers::favourites::{self, FavouriteItem, FavouriteKind};
+use crate::helpers::library_export::{self, ExportFormat};
+use crate::helpers::library_search;
+use crate::players::{MPDPlayerController, PlayerController};
+use crate::players::mpd::search::{SearchError, SearchQuery, SearchTrack};
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::ContentType;
 use rocket::serde::json::Json;
 use rocket::{delete, get, post, State};
+use std::collections::HashSet;
 use std::sync::Arc;
+use parking_lot::RwLock;
 use rocket::response::status::Custom;
 use rocket::http::Status;
 use serde::Serialize;
 
+type PlayerLock = Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>;
+
 fn match_type_str(mt: &ArtistMatchType) -> String {
     match mt {
         ArtistMatchType::Exact => "exact".to_string(),
@@ -42,6 +55,14 @@ pub struct MetadataResponse {
     metadata: std::collections::HashMap<String, serde_json::Value>,
 }
 
+/// Response structure for the changes of the last library refresh
+#[derive(serde::Serialize)]
+pub struct LibraryChangesResponse {
+    player_name: String,
+    /// None if the library has not been refreshed since it was first loaded
+    changes: Option<LibraryDiff>,
+}
+
 /// Response structure for a single metadata key-value pair
 #[derive(serde::Serialize)]
 pub struct MetadataKeyResponse {
@@ -169,6 +190,7 @@ struct ArtistCustomResponse {
     is_multi: bool,
     album_count: usize,
     thumb_url: Vec<String>,
+    is_favourite: bool,
 }
 
 /// Data Transfer Object for Album to include tracks_count without modifying Album struct
@@ -187,6 +209,7 @@ struct AlbumDTO {
     genres: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     categories: Vec<String>,
+    is_favourite: bool,
 }
 
 impl From<Album> for AlbumDTO {
@@ -217,13 +240,22 @@ impl From<Album> for AlbumDTO {
             uri: album.uri,
             genres: album.genres,
             categories,
+            is_favourite: false,
         }
     }
 }
 
 /// Creates an AlbumDTO from an Album with optional track inclusion
-fn create_album_dto(album: Album, include_tracks: bool) -> AlbumDTO {
+///
+/// `favourite_albums` are the normalized favourite albums, an album is a favourite if it is
+/// one for any of its artists.
+fn create_album_dto(album: Album, include_tracks: bool, favourite_albums: &HashSet<FavouriteItem>) -> AlbumDTO {
     let mut dto = AlbumDTO::from(album);
+    dto.is_favourite = !favourite_albums.is_empty()
+        && dto
+            .artists
+            .iter()
+            .any(|artist| favourite_albums.contains(&FavouriteItem::album(artist, &dto.name).normalized()));
     
     // If we don't want to include tracks, set to None
     if !include_tracks {
@@ -233,10 +265,18 @@ fn create_album_dto(album: Album, include_tracks: bool) -> AlbumDTO {
     dto
 }
 
+/// Controllers of all players and the aggregated library of all players, named "all"
+fn library_controllers(controller: &AudioController) -> Vec<PlayerLock> {
+    let mut controllers = controller.list_controllers();
+    let aggregated: Box<dyn PlayerController + Send + Sync> = Box::new(AggregatedLibraryPlayer::new(controller));
+    controllers.push(Arc::new(RwLock::new(aggregated)));
+    controllers
+}
+
 /// List all players with library information
 #[get("/library")]
 pub fn list_libraries(controller: &State<Arc<AudioController>>) -> Json<LibraryListResponse> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     let mut players = Vec::new();
     
     // Iterate through all controllers and check their library status
@@ -265,10 +305,46 @@ pub fn list_libraries(controller: &State<Arc<AudioController>>) -> Json<LibraryL
     Json(LibraryListResponse { players })
 }
 
+/// Response structure for a search across all libraries
+#[derive(serde::Serialize)]
+pub struct LibrarySearchResponse {
+    query: String,
+    count: usize,
+    results: Vec<library_search::SearchResult>,
+}
+
+/// Search artists, albums and tracks in the libraries of all players
+///
+/// `types` is a comma separated list of "artist", "album" and "track", all types are
+/// returned by default. Results are ranked, best match first.
+#[get("/library/search?<q>&<types>&<player>&<limit>")]
+pub fn search_all_libraries(
+    q: &str,
+    types: Option<&str>,
+    player: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Json<LibrarySearchResponse>, Custom<String>> {
+    if q.trim().is_empty() {
+        return Err(Custom(Status::BadRequest, "The search query must not be empty".to_string()));
+    }
+    let mut kinds = Vec::new();
+    for kind in types.unwrap_or_default().split(',').filter(|t| !t.trim().is_empty()) {
+        let kind = library_search::ResultKind::parse(kind)
+            .ok_or_else(|| Custom(Status::BadRequest, format!("Unknown result type '{}'", kind)))?;
+        kinds.push(kind);
+    }
+    let results = library_search::search(q, &kinds, player, limit.unwrap_or(50));
+    Ok(Json(LibrarySearchResponse {
+        query: q.to_string(),
+        count: results.len(),
+        results,
+    }))
+}
+
 /// Get library information for a player
 #[get("/library/<player_name>")]
 pub fn get_library_info(player_name: &str, controller: &State<Arc<AudioController>>) -> Result<Json<LibraryResponse>, Custom<Json<LibraryResponse>>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -336,7 +412,7 @@ pub fn get_player_albums(
     player_name: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<AlbumsDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -348,8 +424,9 @@ pub fn get_player_albums(
                 let albums = library.get_albums();
 
                 // Convert albums to DTOs without including tracks
+                let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
                 let album_dtos = albums.into_iter()
-                    .map(|album| create_album_dto(album, false))
+                    .map(|album| create_album_dto(album, false, &favourite_albums))
                     .collect::<Vec<AlbumDTO>>();
 
                 return Ok(Json(AlbumsDTOResponse {
@@ -380,7 +457,7 @@ pub fn get_player_artists(
     player_name: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<serde_json::Value>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -396,6 +473,7 @@ pub fn get_player_artists(
 
                 // Create a custom JSON response with only the required fields
                 let mut artists_json = Vec::with_capacity(artists.len());
+                let favourite_artists = favourites::favourite_item_set(FavouriteKind::Artist);
 
                 for artist in &artists {
                     // Get albums for this artist by name to determine the count
@@ -414,6 +492,7 @@ pub fn get_player_artists(
                         is_multi: artist.is_multi,
                         album_count,
                         thumb_url: thumb_urls,
+                        is_favourite: favourite_artists.contains(&FavouriteItem::artist(&artist.name).normalized()),
                     };
 
                     // Convert to serde_json::Value to include in the response
@@ -456,7 +535,7 @@ pub fn get_album_by_id(
     album_id: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<AlbumDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -475,7 +554,8 @@ pub fn get_album_by_id(
                 let album_option = library.get_album_by_id(&identifier);
                 
                 // Convert album to DTO with tracks included
-                let album_dto = album_option.map(|album| create_album_dto(album, true));
+                let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
+                let album_dto = album_option.map(|album| create_album_dto(album, true, &favourite_albums));
                 
                 return Ok(Json(AlbumDTOResponse {
                     player_name: player_name.to_string(),
@@ -511,7 +591,7 @@ pub fn get_albums_by_artist(
     fuzzy: Option<bool>,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<ArtistAlbumsDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
 
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
@@ -534,8 +614,9 @@ pub fn get_albums_by_artist(
                 return match artist {
                     Some(a) => {
                         let albums = library.get_albums_by_artist_id(&a.id);
+                        let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
                         let album_dtos: Vec<AlbumDTO> = albums.into_iter()
-                            .map(|album| create_album_dto(album, false))
+                            .map(|album| create_album_dto(album, false, &favourite_albums))
                             .collect();
                         Ok(Json(ArtistAlbumsDTOResponse {
                             player_name: player_name.to_string(),
@@ -578,7 +659,7 @@ pub fn get_albums_by_artist_id(
     artist_id: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<ArtistAlbumsDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -602,8 +683,9 @@ pub fn get_albums_by_artist_id(
                 let albums = library.get_albums_by_artist_id(&artist_id_identifier);
                 
                 // Convert albums to DTOs without including tracks
+                let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
                 let album_dtos = albums.into_iter()
-                    .map(|album| create_album_dto(album, false))
+                    .map(|album| create_album_dto(album, false, &favourite_albums))
                     .collect::<Vec<AlbumDTO>>();
                 
                 // Try to find the artist name for better response
@@ -666,7 +748,7 @@ pub fn get_library_genres(
     raw: Option<bool>,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<GenresResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
@@ -693,6 +775,65 @@ pub fn get_library_genres(
     Err(Custom(Status::NotFound, format!("Player '{}' not found", player_name)))
 }
 
+/// Response structure for a database search
+#[derive(serde::Serialize)]
+pub struct SearchResponse {
+    player_name: String,
+    count: usize,
+    tracks: Vec<SearchTrack>,
+}
+
+/// Search the MPD database for tracks
+///
+/// The query is passed to MPD's `search` command, or `find` with `?exact=true`,
+/// so the library doesn't need to be loaded.
+#[allow(clippy::too_many_arguments)]
+#[get("/library/<player_name>/search?<artist>&<album>&<album_artist>&<title>&<genre>&<any>&<exact>&<limit>")]
+pub fn search_library(
+    player_name: &str,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    title: Option<String>,
+    genre: Option<String>,
+    any: Option<String>,
+    exact: Option<bool>,
+    limit: Option<u32>,
+    controller: &State<Arc<AudioController>>
+) -> Result<Json<SearchResponse>, Custom<String>> {
+    let query = SearchQuery {
+        artist,
+        album,
+        album_artist,
+        title,
+        genre,
+        any,
+        exact: exact.unwrap_or(false),
+        limit,
+    };
+    let ctrl_lock = controller.inner().get_player_by_name(player_name)
+        .ok_or_else(|| Custom(Status::NotFound, format!("Player '{}' not found", player_name)))?;
+    let ctrl = ctrl_lock.read();
+    let mpd = ctrl.as_any().downcast_ref::<MPDPlayerController>()
+        .ok_or_else(|| Custom(Status::BadRequest, format!("Player '{}' is not an MPD player", player_name)))?;
+
+    match mpd.search_songs(&query) {
+        Ok(tracks) => Ok(Json(SearchResponse {
+            player_name: player_name.to_string(),
+            count: tracks.len(),
+            tracks,
+        })),
+        Err(e) => {
+            let status = match e {
+                SearchError::EmptyQuery => Status::BadRequest,
+                SearchError::NotConnected => Status::ServiceUnavailable,
+                SearchError::Mpd(_) => Status::InternalServerError,
+            };
+            Err(Custom(status, e.to_string()))
+        }
+    }
+}
+
 /// Get all albums filtered by genre (case-insensitive)
 #[get("/library/<player_name>/albums/by-genre/<genre>")]
 pub fn get_albums_by_genre(
@@ -700,14 +841,15 @@ pub fn get_albums_by_genre(
     genre: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<AlbumsDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
             if let Some(library) = ctrl.get_library() {
                 let albums = library.get_albums_by_genre(genre);
+                let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
                 let album_dtos: Vec<AlbumDTO> = albums.into_iter()
-                    .map(|album| create_album_dto(album, false))
+                    .map(|album| create_album_dto(album, false, &favourite_albums))
                     .collect();
                 return Ok(Json(AlbumsDTOResponse {
                     player_name: player_name.to_string(),
@@ -731,7 +873,7 @@ pub fn get_library_categories(
     player_name: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<CategoriesResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
@@ -761,14 +903,15 @@ pub fn get_albums_by_category(
     category: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<AlbumsDTOResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
             if let Some(library) = ctrl.get_library() {
                 let albums = library.get_albums_by_category(category);
+                let favourite_albums = favourites::favourite_item_set(FavouriteKind::Album);
                 let album_dtos: Vec<AlbumDTO> = albums.into_iter()
-                    .map(|album| create_album_dto(album, false))
+                    .map(|album| create_album_dto(album, false, &favourite_albums))
                     .collect();
                 return Ok(Json(AlbumsDTOResponse {
                     player_name: player_name.to_string(),
@@ -793,7 +936,7 @@ pub fn get_artists_by_category(
     category: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<serde_json::Value>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
@@ -835,7 +978,7 @@ pub fn get_artists_by_genre(
     genre: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<serde_json::Value>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
@@ -873,7 +1016,7 @@ pub fn get_artists_by_genre(
 /// Refresh the library for a player
 #[get("/library/<player_name>/refresh")]
 pub fn refresh_player_library(player_name: &str, controller: &State<Arc<AudioController>>) -> Result<Json<LibraryResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -934,7 +1077,7 @@ pub fn update_player_library(
     player_name: &str, 
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<serde_json::Value>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -967,6 +1110,113 @@ pub fn update_player_library(
     ))
 }
 
+/// Response structure for the cover write-back job
+#[derive(serde::Serialize)]
+pub struct CoverWriteBackResponse {
+    player_name: String,
+    running: bool,
+    /// Report of the running or last job, if any
+    report: Option<crate::helpers::coverwriteback::CoverWriteBackReport>,
+}
+
+/// Find the MPD library of a player
+fn find_mpd_library(
+    player_name: &str,
+    controller: &AudioController,
+) -> Result<crate::players::mpd::library::MPDLibrary, Custom<String>> {
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if ctrl.get_player_name() == player_name {
+            let library = ctrl.get_library().ok_or_else(|| Custom(
+                Status::NotFound,
+                format!("Player '{}' does not have a library", player_name),
+            ))?;
+            return library
+                .as_any()
+                .downcast_ref::<crate::players::mpd::library::MPDLibrary>()
+                .cloned()
+                .ok_or_else(|| Custom(
+                    Status::BadRequest,
+                    format!("Player '{}' does not support writing covers to the library", player_name),
+                ));
+        }
+    }
+
+    Err(Custom(
+        Status::NotFound,
+        format!("Player '{}' not found", player_name),
+    ))
+}
+
+fn cover_writeback_response(player_name: &str) -> CoverWriteBackResponse {
+    CoverWriteBackResponse {
+        player_name: player_name.to_string(),
+        running: crate::helpers::coverwriteback::is_running(),
+        report: crate::helpers::coverwriteback::get_report()
+            .filter(|report| report.player_name == player_name),
+    }
+}
+
+/// Write the covers of all albums into the album directories
+///
+/// This runs as a background job. Album directories that already
+/// contain a cover file are skipped. With `?dry_run=true` the album
+/// directories that would get a cover are listed instead.
+#[post("/library/<player_name>/coverart/writeback")]
+pub fn start_cover_writeback(
+    player_name: &str,
+    dry_run: DryRun,
+    controller: &State<Arc<AudioController>>,
+) -> Result<DryRunResponse<Json<CoverWriteBackResponse>>, Custom<String>> {
+    let library = find_mpd_library(player_name, controller.inner())?;
+    if library.is_read_only() {
+        return Err(Custom(
+            Status::MethodNotAllowed,
+            format!("Library of player '{}' is read-only", player_name),
+        ));
+    }
+
+    let already_running = || Custom(
+        Status::Conflict,
+        "Cover write-back is already running".to_string(),
+    );
+
+    dry_run.run(
+        "cover_writeback",
+        || {
+            if crate::helpers::coverwriteback::is_running() {
+                return Err(already_running());
+            }
+            Ok(library
+                .get_albums()
+                .iter()
+                .filter_map(|album| {
+                    let directory = library.cover_write_back_directory(album)?;
+                    let artist = album.artists.lock().first().cloned().unwrap_or_default();
+                    Some(PlannedChange::new("write_cover", directory)
+                        .with_detail(format!("{} - {}", artist, album.name)))
+                })
+                .collect())
+        },
+        || {
+            if !crate::helpers::coverwriteback::start(player_name, library.clone()) {
+                return Err(already_running());
+            }
+            Ok(Json(cover_writeback_response(player_name)))
+        },
+    )
+}
+
+/// Get the report of the running or last cover write-back job
+#[get("/library/<player_name>/coverart/writeback")]
+pub fn get_cover_writeback(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<CoverWriteBackResponse>, Custom<String>> {
+    find_mpd_library(player_name, controller.inner())?;
+    Ok(Json(cover_writeback_response(player_name)))
+}
+
 /// Get a specific artist by name.
 ///
 /// Pass `?fuzzy=true` to enable fuzzy/flexible matching.
@@ -984,7 +1234,7 @@ pub fn get_artist_by_name(
     }
 
     // Flexible path
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     for ctrl_lock in controllers {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
@@ -1055,7 +1305,7 @@ fn get_artist_internal(
     controller: &State<Arc<AudioController>>,
     lookup_type: ArtistLookupType
 ) -> Result<Json<ArtistResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -1135,7 +1385,7 @@ pub fn get_image(
     identifier: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<(rocket::http::ContentType, Vec<u8>), Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -1184,7 +1434,7 @@ pub fn get_library_metadata(
     player_name: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<MetadataResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -1217,6 +1467,34 @@ pub fn get_library_metadata(
     ))
 }
 
+/// Get the albums added, removed and changed by the last refresh of a player's library
+#[get("/library/<player_name>/changes")]
+pub fn get_library_changes(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>
+) -> Result<Json<LibraryChangesResponse>, Custom<String>> {
+    for ctrl_lock in library_controllers(controller.inner()) {
+        let ctrl = ctrl_lock.read();
+        if ctrl.get_player_name() == player_name {
+            return match ctrl.get_library() {
+                Some(library) => Ok(Json(LibraryChangesResponse {
+                    player_name: player_name.to_string(),
+                    changes: library.get_last_diff(),
+                })),
+                None => Err(Custom(
+                    Status::NotFound,
+                    format!("Player '{}' does not have a library", player_name),
+                )),
+            };
+        }
+    }
+
+    Err(Custom(
+        Status::NotFound,
+        format!("Player '{}' not found", player_name),
+    ))
+}
+
 /// Get a specific metadata key for a player's library
 #[get("/library/<player_name>/meta/<key>")]
 pub fn get_library_metadata_key(
@@ -1224,7 +1502,7 @@ pub fn get_library_metadata_key(
     key: &str,
     controller: &State<Arc<AudioController>>
 ) -> Result<Json<MetadataKeyResponse>, Custom<String>> {
-    let controllers = controller.inner().list_controllers();
+    let controllers = library_controllers(controller.inner());
     
     // Find the controller with the matching name
     for ctrl_lock in controllers {
@@ -1263,137 +1541,329 @@ pub fn get_library_metadata_key(
 
 /// Response structure for delete operations
 #[derive(serde::Serialize)]
-pub(crate) struct DeleteResponse {
+pub struct DeleteResponse {
     success: bool,
     message: String,
 }
 
-/// Delete an album and all its tracks from the library filesystem
-#[delete("/library/<player_name>/album/<album_id>")]
-pub fn delete_library_album(
+/// Library of a player that supports deleting files
+fn find_deletable_library(
     player_name: &str,
-    album_id: &str,
-    controller: &State<Arc<AudioController>>,
-) -> Custom<Json<DeleteResponse>> {
-    let controllers = controller.inner().list_controllers();
+    controller: &AudioController,
+) -> Result<Box<dyn LibraryInterface>, Custom<Json<DeleteResponse>>> {
+    let failure = |status: Status, message: String| Custom(status, Json(DeleteResponse { success: false, message }));
 
-    for ctrl_lock in controllers {
+    for ctrl_lock in controller.list_controllers() {
         let ctrl = ctrl_lock.read();
         if ctrl.get_player_name() == player_name {
-            if let Some(library) = ctrl.get_library() {
-                if !library.supports_delete() {
-                    return Custom(
-                        Status::MethodNotAllowed,
-                        Json(DeleteResponse {
-                            success: false,
-                            message: format!("Player '{}' does not support deletion", player_name),
-                        }),
-                    );
-                }
-                let id = if let Ok(num) = album_id.parse::<u64>() {
-                    Identifier::Numeric(num)
-                } else {
-                    Identifier::String(album_id.to_string())
-                };
-                match library.delete_album(&id) {
-                    Ok(()) => return Custom(
-                        Status::Ok,
-                        Json(DeleteResponse {
-                            success: true,
-                            message: format!("Album '{}' deleted", album_id),
-                        }),
-                    ),
-                    Err(e) => return Custom(
-                        Status::InternalServerError,
-                        Json(DeleteResponse {
-                            success: false,
-                            message: format!("Failed to delete album: {}", e),
-                        }),
-                    ),
-                }
-            } else {
-                return Custom(
-                    Status::NotFound,
-                    Json(DeleteResponse {
-                        success: false,
-                        message: format!("Player '{}' does not have a library", player_name),
-                    }),
-                );
+            let library = ctrl.get_library().ok_or_else(|| failure(
+                Status::NotFound,
+                format!("Player '{}' does not have a library", player_name),
+            ))?;
+            if !library.supports_delete() {
+                return Err(failure(
+                    Status::MethodNotAllowed,
+                    format!("Player '{}' does not support deletion", player_name),
+                ));
             }
+            return Ok(library);
         }
     }
 
+    Err(failure(Status::NotFound, format!("Player '{}' not found", player_name)))
+}
+
+fn delete_failed(message: String) -> Custom<Json<DeleteResponse>> {
     Custom(
-        Status::NotFound,
+        Status::InternalServerError,
         Json(DeleteResponse {
             success: false,
-            message: format!("Player '{}' not found", player_name),
+            message,
         }),
     )
 }
 
+/// Delete an album and all its tracks from the library filesystem
+///
+/// With `?dry_run=true` the files that would be deleted are listed instead.
+#[delete("/library/<player_name>/album/<album_id>")]
+pub fn delete_library_album(
+    player_name: &str,
+    album_id: &str,
+    dry_run: DryRun,
+    controller: &State<Arc<AudioController>>,
+) -> Result<DryRunResponse<Json<DeleteResponse>>, Custom<Json<DeleteResponse>>> {
+    let library = find_deletable_library(player_name, controller.inner())?;
+    let id = if let Ok(num) = album_id.parse::<u64>() {
+        Identifier::Numeric(num)
+    } else {
+        Identifier::String(album_id.to_string())
+    };
+
+    dry_run.run(
+        "delete_album",
+        || library
+            .plan_delete_album(&id)
+            .map(|files| files.into_iter().map(|file| PlannedChange::new("delete_file", file)).collect())
+            .map_err(|e| delete_failed(format!("Failed to delete album: {}", e))),
+        || library
+            .delete_album(&id)
+            .map(|()| Json(DeleteResponse {
+                success: true,
+                message: format!("Album '{}' deleted", album_id),
+            }))
+            .map_err(|e| delete_failed(format!("Failed to delete album: {}", e))),
+    )
+}
+
 /// Delete a single track from the library filesystem by its URI
 ///
 /// The track_uri path segment is percent-encoded (standard URL encoding).
+/// With `?dry_run=true` the file that would be deleted is returned instead.
 #[delete("/library/<player_name>/track/<track_uri>")]
 pub fn delete_library_track(
     player_name: &str,
     track_uri: &str,
+    dry_run: DryRun,
     controller: &State<Arc<AudioController>>,
-) -> Custom<Json<DeleteResponse>> {
-    let controllers = controller.inner().list_controllers();
-
+) -> Result<DryRunResponse<Json<DeleteResponse>>, Custom<Json<DeleteResponse>>> {
     let decoded_uri = match urlencoding::decode(track_uri) {
         Ok(s) => s.into_owned(),
         Err(_) => track_uri.to_string(),
     };
+    let library = find_deletable_library(player_name, controller.inner())?;
+
+    dry_run.run(
+        "delete_track",
+        || library
+            .plan_delete_track(&decoded_uri)
+            .map(|file| vec![PlannedChange::new("delete_file", file)])
+            .map_err(|e| delete_failed(format!("Failed to delete track: {}", e))),
+        || library
+            .delete_track(&decoded_uri)
+            .map(|()| Json(DeleteResponse {
+                success: true,
+                message: format!("Track '{}' deleted", decoded_uri),
+            }))
+            .map_err(|e| delete_failed(format!("Failed to delete track: {}", e))),
+    )
+}
 
-    for ctrl_lock in controllers {
-        let ctrl = ctrl_lock.read();
-        if ctrl.get_player_name() == player_name {
-            if let Some(library) = ctrl.get_library() {
-                if !library.supports_delete() {
-                    return Custom(
-                        Status::MethodNotAllowed,
-                        Json(DeleteResponse {
-                            success: false,
-                            message: format!("Player '{}' does not support deletion", player_name),
-                        }),
-                    );
-                }
-                match library.delete_track(&decoded_uri) {
-                    Ok(()) => return Custom(
-                        Status::Ok,
-                        Json(DeleteResponse {
-                            success: true,
-                            message: format!("Track '{}' deleted", decoded_uri),
-                        }),
-                    ),
-                    Err(e) => return Custom(
-                        Status::InternalServerError,
-                        Json(DeleteResponse {
-                            success: false,
-                            message: format!("Failed to delete track: {}", e),
-                        }),
-                    ),
-                }
-            } else {
-                return Custom(
-                    Status::NotFound,
-                    Json(DeleteResponse {
-                        success: false,
-                        message: format!("Player '{}' does not have a library", player_name),
-                    }),
-                );
+/// Response structure for adding library items to the queue
+#[derive(serde::Serialize)]
+pub struct QueueResponse {
+    success: bool,
+    message: String,
+    player_name: String,
+    /// Number of tracks added
+    tracks: usize,
+}
+
+/// Build the command that adds tracks to the queue
+///
+/// `mode` is "append" (default), "next" to insert after the current track or
+/// "now" to replace the queue and start playback.
+fn queue_command(uris: Vec<String>, mode: Option<&str>) -> Result<PlayerCommand, Custom<String>> {
+    match mode.unwrap_or("append") {
+        "append" | "next" => Ok(PlayerCommand::QueueTracks {
+            uris,
+            insert_at_beginning: false,
+            insert_after_current: mode == Some("next"),
+            metadata: Vec::new(),
+        }),
+        "now" => Ok(PlayerCommand::PlayNow {
+            uris,
+            metadata: Vec::new(),
+            start_index: 0,
+        }),
+        other => Err(Custom(
+            Status::BadRequest,
+            format!("Invalid mode '{}', use append, next or now", other),
+        )),
+    }
+}
+
+/// Resolve library items to track URIs and add them to the queue of the same player
+fn queue_library_items(
+    player_name: &str,
+    item: &str,
+    mode: Option<&str>,
+    dry_run: DryRun,
+    controller: &AudioController,
+    resolve: impl FnOnce(&dyn LibraryInterface) -> Option<Vec<Album>>,
+) -> Result<DryRunResponse<Json<QueueResponse>>, Custom<String>> {
+    let ctrl_lock = controller
+        .get_player_by_name(player_name)
+        .ok_or_else(|| Custom(Status::NotFound, format!("Player '{}' not found", player_name)))?;
+    let library = ctrl_lock
+        .read()
+        .get_library()
+        .ok_or_else(|| Custom(Status::NotFound, format!("Player '{}' does not have a library", player_name)))?;
+    let albums = resolve(library.as_ref()).ok_or_else(|| Custom(Status::NotFound, format!("{} not found", item)))?;
+    let uris: Vec<String> = albums.iter().flat_map(|album| album.track_uris()).collect();
+    if uris.is_empty() {
+        return Err(Custom(Status::NotFound, format!("{} has no playable tracks", item)));
+    }
+    let count = uris.len();
+    let command = queue_command(uris, mode)?;
+
+    // Refuse to start playback outside of the playback limits
+    let state = ctrl_lock.read().get_playback_state();
+    controller
+        .check_command(&command, state)
+        .map_err(|reason| Custom(Status::Forbidden, reason))?;
+
+    dry_run.run(
+        "queue",
+        || {
+            let uris = match &command {
+                PlayerCommand::QueueTracks { uris, .. } | PlayerCommand::PlayNow { uris, .. } => uris.clone(),
+                _ => Vec::new(),
+            };
+            Ok(uris.into_iter().map(|uri| PlannedChange::new("add_to_queue", uri)).collect())
+        },
+        || {
+            log::info!("API request: queue {} tracks of {} on {}", count, item, player_name);
+            if !ctrl_lock.read().send_command(command.clone()) {
+                return Err(Custom(
+                    Status::InternalServerError,
+                    format!("Failed to queue tracks on player '{}'", player_name),
+                ));
             }
+            Ok(Json(QueueResponse {
+                success: true,
+                message: format!("{} tracks of {} queued", count, item),
+                player_name: player_name.to_string(),
+                tracks: count,
+            }))
+        },
+    )
+}
+
+fn parse_identifier(id: &str) -> Identifier {
+    match id.parse::<u64>() {
+        Ok(numeric) => Identifier::Numeric(numeric),
+        Err(_) => Identifier::String(id.to_string()),
+    }
+}
+
+/// Add all tracks of an album to the queue
+///
+/// With `?dry_run=true` the tracks that would be queued are listed instead.
+#[post("/library/<player_name>/album/by-id/<album_id>/queue?<mode>")]
+pub fn queue_album(
+    player_name: &str,
+    album_id: &str,
+    mode: Option<&str>,
+    dry_run: DryRun,
+    controller: &State<Arc<AudioController>>,
+) -> Result<DryRunResponse<Json<QueueResponse>>, Custom<String>> {
+    let id = parse_identifier(album_id);
+    queue_library_items(player_name, &format!("Album '{}'", album_id), mode, dry_run, controller.inner(), |library| {
+        library.get_album_by_id(&id).map(|album| vec![album])
+    })
+}
+
+/// Add all albums of an artist to the queue, oldest album first
+///
+/// With `?dry_run=true` the tracks that would be queued are listed instead.
+#[post("/library/<player_name>/artist/by-id/<artist_id>/queue?<mode>")]
+pub fn queue_artist(
+    player_name: &str,
+    artist_id: &str,
+    mode: Option<&str>,
+    dry_run: DryRun,
+    controller: &State<Arc<AudioController>>,
+) -> Result<DryRunResponse<Json<QueueResponse>>, Custom<String>> {
+    let id = parse_identifier(artist_id);
+    queue_library_items(player_name, &format!("Artist '{}'", artist_id), mode, dry_run, controller.inner(), |library| {
+        let mut albums = library.get_albums_by_artist_id(&id);
+        if albums.is_empty() {
+            return None;
         }
+        // Albums without release date last
+        albums.sort_by(|a, b| {
+            (a.release_date.is_none(), a.release_date, a.name.to_lowercase())
+                .cmp(&(b.release_date.is_none(), b.release_date, b.name.to_lowercase()))
+        });
+        Some(albums)
+    })
+}
+
+/// Maximum size of an uploaded library export
+const IMPORT_LIMIT_MIB: u64 = 64;
+
+/// Response structure for a library import
+#[derive(serde::Serialize)]
+pub struct LibraryImportResponse {
+    success: bool,
+    #[serde(flatten)]
+    summary: library_export::ImportSummary,
+}
+
+/// Export the libraries of all players with their artist metadata and the favourites
+///
+/// `format` is "json" (default) or "csv". CSV has one row per track and can't be imported.
+#[get("/library/export?<format>")]
+pub fn export_libraries(
+    format: Option<&str>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<(ContentType, String), Custom<String>> {
+    let format = format
+        .unwrap_or("json")
+        .parse::<ExportFormat>()
+        .map_err(|e| Custom(Status::BadRequest, e.to_string()))?;
+    let export = library_export::export(controller.inner());
+    match format {
+        ExportFormat::Json => serde_json::to_string(&export)
+            .map(|json| (ContentType::JSON, json))
+            .map_err(|e| Custom(Status::InternalServerError, e.to_string())),
+        ExportFormat::Csv => Ok((ContentType::CSV, library_export::to_csv(&export))),
     }
+}
 
-    Custom(
-        Status::NotFound,
-        Json(DeleteResponse {
-            success: false,
-            message: format!("Player '{}' not found", player_name),
-        }),
-    )
-}
\ No newline at end of file
+/// Import the artist metadata and favourites of a JSON library export
+///
+/// Artists that already have metadata keep it unless `overwrite` is set.
+#[post("/library/import?<overwrite>", data = "<data>")]
+pub async fn import_libraries(
+    overwrite: Option<bool>,
+    data: Data<'_>,
+) -> Result<Json<LibraryImportResponse>, Custom<String>> {
+    let body = data
+        .open(IMPORT_LIMIT_MIB.mebibytes())
+        .into_string()
+        .await
+        .map_err(|e| Custom(Status::BadRequest, format!("Failed to read the export: {}", e)))?;
+    if !body.is_complete() {
+        return Err(Custom(
+            Status::PayloadTooLarge,
+            format!("Library exports are limited to {} MiB", IMPORT_LIMIT_MIB),
+        ));
+    }
+    let export: library_export::LibraryExport = serde_json::from_str(&body)
+        .map_err(|e| Custom(Status::BadRequest, format!("Invalid library export: {}", e)))?;
+    let summary = library_export::import(&export, overwrite.unwrap_or(false))
+        .map_err(|e| Custom(Status::BadRequest, e.to_string()))?;
+    Ok(Json(LibraryImportResponse { success: true, summary }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_command() {
+        let uris = vec!["a.flac".to_string()];
+        assert!(matches!(
+            queue_command(uris.clone(), None),
+            Ok(PlayerCommand::QueueTracks { insert_after_current: false, .. })
+        ));
+        assert!(matches!(
+            queue_command(uris.clone(), Some("next")),
+            Ok(PlayerCommand::QueueTracks { insert_after_current: true, .. })
+        ));
+        assert!(matches!(queue_command(uris.clone(), Some("now")), Ok(PlayerCommand::PlayNow { .. })));
+        assert!(queue_command(uris, Some("shuffle")).is_err());
+    }
+}
@@ -1,11 +1,19 @@
 use crate::AudioController;
-use crate::helpers::lyrics::{LyricsLookup, LyricsContent};
+use crate::helpers::lyrics::{line_at, LyricsLookup, LyricsContent, TimedLyric};
+use crate::audiocontrol::eventbus::{EventBus, EventChannel};
+use crate::data::{PlaybackState, PlayerEvent};
+use crate::players::PlayerController;
+use rocket::futures::{SinkExt, StreamExt};
 use rocket::serde::json::Json;
 use rocket::{get, post, State};
+use rocket_ws::{Channel, Message, WebSocket};
+use parking_lot::RwLock;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use rocket::response::status::Custom;
 use rocket::http::Status;
 use serde::{Deserialize, Serialize};
+use log::debug;
 
 /// Request structure for lyrics lookup by metadata
 #[derive(Deserialize)]
@@ -235,3 +243,271 @@ pub fn get_lyrics_by_metadata(
         "No MPD player with library support found".to_string(),
     ))
 }
+
+/// Message of the synced lyrics stream, sent when the song or the current line changes
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LyricsLineMessage {
+    pub player_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Whether the song has timed lyrics, the other fields are empty if not
+    pub timed: bool,
+    /// Index of the current line, None before the first line
+    pub index: Option<usize>,
+    pub timestamp: Option<f64>,
+    pub text: Option<String>,
+    /// The following line, e.g. for a preview
+    pub next_text: Option<String>,
+    pub next_timestamp: Option<f64>,
+}
+
+/// Build the message for a position in the song
+fn line_message(player_name: &str, title: Option<String>, lyrics: Option<&[TimedLyric]>, position: f64) -> LyricsLineMessage {
+    let lyrics = lyrics.unwrap_or_default();
+    let index = line_at(lyrics, position);
+    let next = lyrics.get(index.map_or(0, |i| i + 1));
+    let current = index.and_then(|i| lyrics.get(i));
+    LyricsLineMessage {
+        player_name: player_name.to_string(),
+        title,
+        timed: !lyrics.is_empty(),
+        index,
+        timestamp: current.map(|l| l.timestamp),
+        text: current.map(|l| l.text.clone()),
+        next_text: next.map(|l| l.text.clone()),
+        next_timestamp: next.map(|l| l.timestamp),
+    }
+}
+
+type PlayerLock = Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>;
+
+/// Timed lyrics of a song file, only MPD has lyrics files next to the music files
+///
+/// Reading the file blocks, so call this on the blocking pool. The controller is only locked to get its library.
+fn load_timed_lyrics(ctrl_lock: &PlayerLock, file_path: &str) -> Option<Vec<TimedLyric>> {
+    let library = ctrl_lock.read().get_library()?;
+    let mpd_library = library.as_any().downcast_ref::<crate::players::mpd::library::MPDLibrary>()?;
+    match mpd_library.get_lyrics_by_url(file_path) {
+        Ok(LyricsContent::Timed(lyrics)) => Some(lyrics),
+        _ => None,
+    }
+}
+
+/// Playback position reported on the event bus, extrapolated while playing
+#[derive(Debug, Clone, Copy)]
+struct PlaybackClock {
+    position: f64,
+    updated: Instant,
+    playing: bool,
+}
+
+impl PlaybackClock {
+    fn new(position: f64, playing: bool) -> Self {
+        PlaybackClock {
+            position,
+            updated: Instant::now(),
+            playing,
+        }
+    }
+
+    fn position_at(&self, now: Instant) -> f64 {
+        if self.playing {
+            self.position + now.saturating_duration_since(self.updated).as_secs_f64()
+        } else {
+            self.position
+        }
+    }
+
+    fn position(&self) -> f64 {
+        self.position_at(Instant::now())
+    }
+
+    fn set_position(&mut self, position: f64) {
+        self.position = position;
+        self.updated = Instant::now();
+    }
+
+    fn set_playing(&mut self, playing: bool) {
+        self.set_position(self.position());
+        self.playing = playing;
+    }
+}
+
+/// The player whose lyrics are streamed, updated from its events
+struct FollowedPlayer {
+    ctrl_lock: PlayerLock,
+    player_id: String,
+    player_name: String,
+    title: Option<String>,
+    lyrics: Option<Vec<TimedLyric>>,
+    clock: PlaybackClock,
+}
+
+impl FollowedPlayer {
+    /// Read the state of a player, the lyrics are loaded after releasing the controller lock
+    fn load(ctrl_lock: PlayerLock) -> Self {
+        let (player_id, player_name, song, clock) = {
+            let ctrl = ctrl_lock.read();
+            let playing = ctrl.get_playback_state() == PlaybackState::Playing;
+            let clock = PlaybackClock::new(ctrl.get_position().unwrap_or(0.0), playing);
+            (ctrl.get_player_id(), ctrl.get_player_name(), ctrl.get_song(), clock)
+        };
+        let lyrics = song
+            .as_ref()
+            .and_then(|s| s.stream_url.as_deref())
+            .and_then(|path| load_timed_lyrics(&ctrl_lock, path));
+        FollowedPlayer {
+            ctrl_lock,
+            player_id,
+            player_name,
+            title: song.and_then(|s| s.title),
+            lyrics,
+            clock,
+        }
+    }
+
+    fn message(&self) -> LyricsLineMessage {
+        line_message(&self.player_name, self.title.clone(), self.lyrics.as_deref(), self.clock.position())
+    }
+
+    /// Apply an event of this player, the lyrics are only loaded again when the song changes
+    async fn apply(&mut self, event: PlayerEvent) {
+        match event {
+            PlayerEvent::SongChanged { song, .. } => {
+                self.title = song.as_ref().and_then(|s| s.title.clone());
+                self.clock.set_position(0.0);
+                let ctrl_lock = Arc::clone(&self.ctrl_lock);
+                self.lyrics = match song.and_then(|s| s.stream_url) {
+                    Some(path) => tokio::task::spawn_blocking(move || load_timed_lyrics(&ctrl_lock, &path))
+                        .await
+                        .ok()
+                        .flatten(),
+                    None => None,
+                };
+                debug!("Lyrics stream: {} timed lines for {:?}", self.lyrics.as_ref().map_or(0, |l| l.len()), self.title);
+            }
+            PlayerEvent::StateChanged { state, .. } => self.clock.set_playing(state == PlaybackState::Playing),
+            PlayerEvent::PositionChanged { position, .. } => self.clock.set_position(position),
+            _ => {}
+        }
+    }
+}
+
+/// Find the requested player and read its state on the blocking pool
+async fn follow(controller: &Arc<AudioController>, requested: &str) -> Option<FollowedPlayer> {
+    let controller = Arc::clone(controller);
+    let requested = requested.to_string();
+    tokio::task::spawn_blocking(move || {
+        let ctrl_lock = if requested.eq_ignore_ascii_case("active") {
+            controller.get_active_controller()
+        } else {
+            controller.get_player_by_name(&requested)
+        };
+        ctrl_lock.map(FollowedPlayer::load)
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Stream the current lyrics line of a player over a WebSocket
+///
+/// GET /api/lyrics/stream/<player_name>, "active" follows the active player.
+/// A message is sent whenever the song or the line changes. The lyrics are loaded once per song,
+/// the position is taken from the events of the player and extrapolated in between.
+#[get("/stream/<player_name>")]
+pub fn stream_lyrics(ws: WebSocket, player_name: &str, controller: &State<Arc<AudioController>>) -> Channel<'static> {
+    let controller = controller.inner().clone();
+    let requested = player_name.to_string();
+    ws.channel(move |mut stream| {
+        Box::pin(async move {
+            let follow_active = requested.eq_ignore_ascii_case("active");
+            let mut events = EventBus::instance().stream(&[EventChannel::Player, EventChannel::Position]);
+            let mut player = follow(&controller, &requested).await;
+            let mut last_sent: Option<LyricsLineMessage> = None;
+            let mut interval = tokio::time::interval(Duration::from_millis(250));
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let message = match &player {
+                            Some(player) => player.message(),
+                            None => line_message(&requested, None, None, 0.0),
+                        };
+                        if last_sent.as_ref() != Some(&message) {
+                            let json = serde_json::to_string(&message).unwrap_or_default();
+                            if stream.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                            last_sent = Some(message);
+                        }
+                    }
+                    Some(event) = events.recv() => {
+                        let switch = match (&player, &event) {
+                            (_, PlayerEvent::ActivePlayerChanged { .. }) => follow_active,
+                            // The requested player wasn't there when the stream started
+                            (None, event) => !follow_active && event.player_name().is_some_and(|name| name.eq_ignore_ascii_case(&requested)),
+                            _ => false,
+                        };
+                        if switch {
+                            player = follow(&controller, &requested).await;
+                        } else if let Some(player) = player.as_mut().filter(|p| event.player_id() == Some(p.player_id.as_str())) {
+                            player.apply(event).await;
+                        }
+                    }
+                    Some(msg_result) = stream.next() => {
+                        match msg_result {
+                            Ok(Message::Ping(data)) => stream.send(Message::Pong(data)).await?,
+                            Ok(Message::Close(_)) | Err(_) => break,
+                            Ok(_) => {}
+                        }
+                    }
+                    else => break,
+                }
+            }
+            debug!("Lyrics stream of {} closed", requested);
+            Ok(())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_message() {
+        let lyrics = vec![
+            TimedLyric::new(5.0, "first".to_string()),
+            TimedLyric::new(10.0, "second".to_string()),
+        ];
+        let before = line_message("mpd", None, Some(&lyrics), 1.0);
+        assert!(before.timed);
+        assert_eq!(before.index, None);
+        assert_eq!(before.next_text.as_deref(), Some("first"));
+
+        let message = line_message("mpd", Some("Song".to_string()), Some(&lyrics), 12.0);
+        assert_eq!(message.index, Some(1));
+        assert_eq!(message.text.as_deref(), Some("second"));
+        assert_eq!(message.next_text, None);
+
+        let untimed = line_message("mpd", None, None, 12.0);
+        assert!(!untimed.timed);
+        assert_eq!(untimed.text, None);
+    }
+
+    #[test]
+    fn test_playback_clock() {
+        let mut clock = PlaybackClock::new(10.0, true);
+        let later = clock.updated + Duration::from_millis(2500);
+        assert_eq!(clock.position_at(later), 12.5);
+
+        clock.set_playing(false);
+        let paused = clock.position();
+        assert_eq!(clock.position_at(Instant::now() + Duration::from_secs(5)), paused);
+
+        clock.set_position(30.0);
+        clock.set_playing(true);
+        assert!(clock.position() >= 30.0);
+    }
+}
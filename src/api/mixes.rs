@@ -0,0 +1,63 @@
+use crate::audiocontrol::AudioController;
+use crate::helpers::dailymix::{self, DailyMix, DailyMixError, MixSummary};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Response structure for simple operations
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<MessageResponse>>;
+
+fn error_response(e: DailyMixError) -> ApiError {
+    let status = match e {
+        DailyMixError::NotFound(_) | DailyMixError::PlayerNotFound(_) => Status::NotFound,
+        DailyMixError::InvalidMode(_) => Status::BadRequest,
+        DailyMixError::LibraryNotLoaded(_) => Status::ServiceUnavailable,
+        DailyMixError::CommandFailed(_) => Status::InternalServerError,
+    };
+    Custom(status, Json(MessageResponse {
+        success: false,
+        message: e.to_string(),
+    }))
+}
+
+/// List the generated mixes without their tracks
+#[get("/")]
+pub fn list_mixes() -> Json<Vec<MixSummary>> {
+    Json(dailymix::list_mixes())
+}
+
+/// Get a mix with its tracks
+#[get("/<id>")]
+pub fn get_mix(id: &str) -> Result<Json<DailyMix>, ApiError> {
+    dailymix::get_mix(id)
+        .map(Json)
+        .ok_or_else(|| error_response(DailyMixError::NotFound(id.to_string())))
+}
+
+/// Queue all tracks of a mix on its player
+///
+/// `mode` is "add" (end of the queue, default), "next" (after the current track)
+/// or "now" (replace the queue and play).
+#[post("/<id>/queue?<mode>")]
+pub fn queue_mix(id: &str, mode: Option<&str>, controller: &State<Arc<AudioController>>) -> Result<Json<MessageResponse>, ApiError> {
+    let count = dailymix::queue_mix(controller.inner(), id, mode.unwrap_or("add")).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("{} tracks queued", count),
+    }))
+}
+
+/// Generate new mixes now instead of waiting for the daily schedule
+#[post("/regenerate")]
+pub fn regenerate() -> Result<Json<Vec<MixSummary>>, ApiError> {
+    dailymix::generate().map(Json).map_err(error_response)
+}
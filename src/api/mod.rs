@@ -49,6 +49,9 @@ pub mod library;
 // Export the imagecache module
 pub mod imagecache;
 
+// Export the proxy module
+pub mod proxy;
+
 // Export the coverart module
 pub mod coverart;
 
@@ -91,5 +94,104 @@ pub mod backgroundjobs;
 // Export the genres module
 pub mod genres;
 
+// Export the songsplitter module
+pub mod songsplitter;
+
+// Export the streamcheck module
+pub mod streamcheck;
+
+// Export the ratelimit module
+pub mod ratelimit;
+
+// Export the stationgain module
+pub mod stationgain;
+
+// Export the mounts module
+pub mod mounts;
+
+// Export the usbstorage module
+pub mod usbstorage;
+
+// Export the idle module
+pub mod idle;
+
+// Export the system module
+pub mod system;
+
+// Export the diagnostics module
+pub mod diagnostics;
+
+// Export the notifications module
+pub mod notifications;
+
+// Export the playbacklimits module
+pub mod playbacklimits;
+
+// Export the cd module
+pub mod cd;
+
+// Export the qobuz module
+pub mod qobuz;
+
+// Export the hqplayer module
+pub mod hqplayer;
+
+// Export the artist module
+pub mod artist;
+
+// Export the album module
+pub mod album;
+
+// Export the mixes module
+pub mod mixes;
+
+// Export the suggestions module
+pub mod suggestions;
+
+// Export the playlists module
+pub mod playlists;
+
+// Export the outputs module
+pub mod outputs;
+
+// Export the priority module
+pub mod priority;
+
+// Export the webdav module
+pub mod webdav;
+
+// Export the bookmarks module
+pub mod bookmarks;
+
+// Export the history module
+pub mod history;
+
+// Export the dsp module
+pub mod dsp;
+
+// Export the tts module
+pub mod tts;
+
+// Export the device module
+pub mod device;
+
+// Export the federation module
+pub mod federation;
+
+// Export the auth module
+pub mod auth;
+
+// Export the health module
+pub mod health;
+
+// Export the openapi module
+pub mod openapi;
+
+// Export the graphql module
+pub mod graphql;
+
+// Export the dryrun module
+pub mod dryrun;
+
 // Export the server module
 pub mod server;
\ No newline at end of file
@@ -0,0 +1,139 @@
+use crate::helpers::mounts::{self, MountError, NetworkShare, ShareStatus};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post};
+use serde::{Deserialize, Serialize};
+use log::{debug, info};
+
+/// A configured share with its current health
+#[derive(Serialize, Deserialize)]
+pub struct ShareInfo {
+    pub share: NetworkShare,
+    pub status: ShareStatus,
+}
+
+/// Response structure for the share list
+#[derive(Serialize, Deserialize)]
+pub struct ShareListResponse {
+    pub success: bool,
+    pub shares: Vec<ShareInfo>,
+}
+
+/// Response structure for a single share
+#[derive(Serialize, Deserialize)]
+pub struct ShareStatusResponse {
+    pub success: bool,
+    pub status: ShareStatus,
+}
+
+/// Request structure to add a share
+#[derive(Deserialize, Serialize)]
+pub struct AddShareRequest {
+    #[serde(flatten)]
+    pub share: NetworkShare,
+    /// Password for SMB shares, it is only written to the credentials file
+    pub password: Option<String>,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(error: MountError) -> ApiError {
+    let status = match error {
+        MountError::InvalidConfig(_) => Status::BadRequest,
+        MountError::NotFound(_) => Status::NotFound,
+        MountError::AlreadyExists(_) => Status::Conflict,
+        _ => Status::InternalServerError,
+    };
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: error.to_string(),
+    }))
+}
+
+/// List all configured network shares with their health
+#[get("/list")]
+pub fn list_shares() -> Json<ShareListResponse> {
+    debug!("API request: list network shares");
+    let shares = mounts::list_shares()
+        .into_iter()
+        .map(|share| {
+            let status = mounts::share_status(&share);
+            ShareInfo { share, status }
+        })
+        .collect();
+
+    Json(ShareListResponse {
+        success: true,
+        shares,
+    })
+}
+
+/// Add a network share
+///
+/// This writes a systemd mount unit, enables it and mounts the share.
+#[post("/add", data = "<request>")]
+pub fn add_share(request: Json<AddShareRequest>) -> Result<Json<ShareStatusResponse>, ApiError> {
+    let request = request.into_inner();
+    info!("API request: add network share '{}'", request.share.name);
+
+    let status = mounts::add_share(request.share, request.password.as_deref()).map_err(error_response)?;
+    Ok(Json(ShareStatusResponse {
+        success: true,
+        status,
+    }))
+}
+
+/// Get the health of a network share
+#[get("/<name>/status")]
+pub fn get_share_status(name: &str) -> Result<Json<ShareStatusResponse>, ApiError> {
+    let share = mounts::get_share(name).ok_or_else(|| error_response(MountError::NotFound(name.to_string())))?;
+    Ok(Json(ShareStatusResponse {
+        success: true,
+        status: mounts::share_status(&share),
+    }))
+}
+
+/// Mount a network share
+#[post("/<name>/mount")]
+pub fn mount_share(name: &str) -> Result<Json<ShareStatusResponse>, ApiError> {
+    let status = mounts::mount_share(name).map_err(error_response)?;
+    Ok(Json(ShareStatusResponse {
+        success: true,
+        status,
+    }))
+}
+
+/// Unmount a network share
+#[post("/<name>/unmount")]
+pub fn unmount_share(name: &str) -> Result<Json<ShareStatusResponse>, ApiError> {
+    let status = mounts::unmount_share(name).map_err(error_response)?;
+    Ok(Json(ShareStatusResponse {
+        success: true,
+        status,
+    }))
+}
+
+/// Unmount a network share and remove its configuration
+#[delete("/<name>")]
+pub fn remove_share(name: &str) -> Result<Json<MessageResponse>, ApiError> {
+    mounts::remove_share(name).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Share '{}' removed", name),
+    }))
+}
@@ -0,0 +1,39 @@
+use crate::helpers::notifications::{self, Notification, NotificationCategory, NotificationsStatus, SinkResult};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct SendResponse {
+    /// False if notifications are disabled, no sink receives the category or it was sent recently
+    pub queued: bool,
+}
+
+/// Get the configured sinks and the time of the next weekly digest
+#[get("/status")]
+pub fn get_status() -> Json<NotificationsStatus> {
+    Json(notifications::get_status())
+}
+
+/// Send a test notification and report the result of every sink
+#[post("/test?<category>")]
+pub fn test(category: Option<&str>) -> Result<Json<Vec<SinkResult>>, Custom<String>> {
+    let category = match category {
+        Some(name) => Some(
+            NotificationCategory::parse(name)
+                .ok_or_else(|| Custom(Status::BadRequest, format!("Unknown category '{}'", name)))?,
+        ),
+        None => None,
+    };
+    Ok(Json(notifications::send_test(category)))
+}
+
+/// Send a notification, e.g. from an alarm clock
+#[post("/send", data = "<notification>")]
+pub fn send(notification: Json<Notification>) -> Json<SendResponse> {
+    Json(SendResponse {
+        queued: notifications::notify(notification.category, &notification.title, &notification.message),
+    })
+}
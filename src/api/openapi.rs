@@ -0,0 +1,268 @@
+use crate::constants::API_PREFIX;
+use rocket::serde::json::Json;
+use rocket::{get, Route, State};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+/// The OpenAPI document of all API routes, generated when the server is built
+pub struct OpenApiDocument(Value);
+
+/// Response schemas of routes that return one of the schemas below, by method and path
+///
+/// Other routes are documented without a response schema.
+const RESPONSE_SCHEMAS: [(&str, &str, &str); 9] = [
+    ("GET", "/api/version", "VersionResponse"),
+    ("GET", "/api/library/search", "LibrarySearchResponse"),
+    ("GET", "/api/health", "HealthReport"),
+    ("GET", "/api/health/ready", "HealthReport"),
+    ("GET", "/api/auth", "AuthStatusResponse"),
+    ("GET", "/api/auth/tokens", "ApiTokenList"),
+    ("POST", "/api/auth/tokens", "CreateTokenResponse"),
+    ("DELETE", "/api/auth/tokens/{id}", "ApiToken"),
+    ("GET", "/api/device", "DeviceIdentity"),
+];
+
+impl OpenApiDocument {
+    /// Document the routes below the API prefix
+    pub fn generate<'a>(routes: impl Iterator<Item = &'a Route>) -> Self {
+        let mut paths: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+        let mut operation_ids = HashSet::new();
+        for route in routes {
+            let path = openapi_path(route.uri.path());
+            if !path.starts_with(API_PREFIX) {
+                continue;
+            }
+            let method = route.method.as_str().to_lowercase();
+            let operations = paths.entry(path.clone()).or_default();
+            // Routes with a different rank or format for the same path are one operation
+            if operations.contains_key(&method) {
+                continue;
+            }
+            let tag = path.trim_start_matches(API_PREFIX).split('/').find(|s| !s.is_empty()).unwrap_or("general");
+            operations.insert(method, operation(route, &path, tag, &mut operation_ids));
+        }
+        OpenApiDocument(json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "AudioControl API",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": "REST API of AudioControl, see doc/api.md for details"
+            },
+            "paths": paths,
+            "components": {
+                "schemas": component_schemas(),
+                "securitySchemes": {
+                    "bearerAuth": { "type": "http", "scheme": "bearer" }
+                }
+            },
+            // A token is only needed if API authentication is enabled
+            "security": [{ "bearerAuth": [] }, {}]
+        }))
+    }
+}
+
+/// Convert "/api/library/<player_name>/<path..>" to "/api/library/{player_name}/{path}"
+fn openapi_path(path: &str) -> String {
+    let path = path
+        .split('/')
+        .map(|segment| match parameter_name(segment) {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Name of a dynamic segment "<name>" or "<name..>"
+fn parameter_name(segment: &str) -> Option<&str> {
+    segment
+        .strip_prefix('<')
+        .and_then(|s| s.strip_suffix('>'))
+        .map(|s| s.trim_end_matches(".."))
+}
+
+fn operation(route: &Route, path: &str, tag: &str, operation_ids: &mut HashSet<String>) -> Value {
+    let name = route.name.as_deref().unwrap_or("operation");
+    let mut operation_id = format!("{}_{}", tag.replace('-', "_"), name);
+    let mut suffix = 2;
+    while !operation_ids.insert(operation_id.clone()) {
+        operation_id = format!("{}_{}_{}", tag.replace('-', "_"), name, suffix);
+        suffix += 1;
+    }
+
+    let mut parameters: Vec<Value> = path
+        .split('/')
+        .filter_map(|s| s.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    // Query parameters are optional, the route doesn't tell whether the handler requires them
+    parameters.extend(
+        route
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|segment| !segment.ends_with("..>"))
+            .filter_map(parameter_name)
+            .map(|name| json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string" } })),
+    );
+
+    let method = route.method.as_str();
+    let content = match RESPONSE_SCHEMAS.iter().find(|(m, p, _)| *m == method && *p == path) {
+        Some((_, _, schema)) => json!({ "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", schema) } } }),
+        None => json!({ "application/json": { "schema": {} } }),
+    };
+    let mut operation = json!({
+        "operationId": operation_id,
+        "tags": [tag],
+        "parameters": parameters,
+        "responses": {
+            "200": { "description": "Success", "content": content },
+            "default": {
+                "description": "Error",
+                "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ErrorResponse" } } }
+            }
+        }
+    });
+    if matches!(method, "POST" | "PUT" | "PATCH") {
+        let media_type = route.format.as_ref().map(|f| f.to_string()).unwrap_or_else(|| "application/json".to_string());
+        operation["requestBody"] = json!({ "required": false, "content": { media_type: { "schema": {} } } });
+    }
+    operation
+}
+
+/// Hand-maintained schemas of the responses in RESPONSE_SCHEMAS
+fn component_schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let nullable_string = json!({ "type": "string", "nullable": true });
+    json!({
+        "ErrorResponse": {
+            "type": "object",
+            "description": "Most errors, some routes return a plain text message instead",
+            "properties": { "success": { "type": "boolean" }, "message": string }
+        },
+        "VersionResponse": {
+            "type": "object",
+            "required": ["version"],
+            "properties": { "version": string }
+        },
+        "LibrarySearchResponse": {
+            "type": "object",
+            "required": ["query", "count", "results"],
+            "properties": {
+                "query": string,
+                "count": { "type": "integer" },
+                "results": { "type": "array", "items": { "$ref": "#/components/schemas/LibrarySearchResult" } }
+            }
+        },
+        "LibrarySearchResult": {
+            "type": "object",
+            "required": ["type", "player_name", "id", "name", "score"],
+            "properties": {
+                "type": { "type": "string", "enum": ["artist", "album", "track"] },
+                "player_name": string,
+                "id": string,
+                "name": string,
+                "artist": string,
+                "album": string,
+                "album_id": string,
+                "score": { "type": "number" }
+            }
+        },
+        "HealthReport": {
+            "type": "object",
+            "required": ["status", "checks"],
+            "properties": {
+                "status": { "$ref": "#/components/schemas/HealthStatus" },
+                "checks": { "type": "array", "items": { "$ref": "#/components/schemas/HealthCheck" } }
+            }
+        },
+        "HealthCheck": {
+            "type": "object",
+            "required": ["category", "name", "status"],
+            "properties": {
+                "category": string,
+                "name": string,
+                "status": { "$ref": "#/components/schemas/HealthStatus" },
+                "message": nullable_string
+            }
+        },
+        "HealthStatus": { "type": "string", "enum": ["disabled", "ok", "degraded", "failed"] },
+        "Scope": { "type": "string", "enum": ["read", "control", "admin"] },
+        "AuthStatusResponse": {
+            "type": "object",
+            "required": ["enable", "allow_localhost"],
+            "properties": {
+                "enable": { "type": "boolean" },
+                "allow_localhost": { "type": "boolean" },
+                "scope": { "allOf": [{ "$ref": "#/components/schemas/Scope" }], "nullable": true }
+            }
+        },
+        "ApiToken": {
+            "type": "object",
+            "required": ["id", "name", "scope", "created"],
+            "properties": {
+                "id": string,
+                "name": string,
+                "scope": { "$ref": "#/components/schemas/Scope" },
+                "created": { "type": "integer", "description": "Unix timestamp" }
+            }
+        },
+        "ApiTokenList": { "type": "array", "items": { "$ref": "#/components/schemas/ApiToken" } },
+        "CreateTokenResponse": {
+            "allOf": [
+                { "$ref": "#/components/schemas/ApiToken" },
+                { "type": "object", "required": ["token"], "properties": { "token": string } }
+            ]
+        },
+        "DeviceIdentity": {
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": { "id": string, "name": string }
+        }
+    })
+}
+
+/// Get the OpenAPI 3 document of the API
+#[get("/openapi.json")]
+pub fn get_openapi(document: &State<OpenApiDocument>) -> Json<Value> {
+    Json(document.0.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::routes;
+
+    #[test]
+    fn test_generate() {
+        let rocket = rocket::build()
+            .mount("/api", routes![crate::api::library::search_all_libraries, crate::api::library::get_library_info])
+            .mount("/api/health", routes![crate::api::health::get_health])
+            .mount("/static", routes![get_openapi]);
+        let OpenApiDocument(document) = OpenApiDocument::generate(rocket.routes());
+
+        let paths = document["paths"].as_object().unwrap();
+        assert_eq!(paths.len(), 3, "{:?}", paths.keys().collect::<Vec<_>>());
+        let search = &paths["/api/library/search"]["get"];
+        assert_eq!(search["operationId"], "library_search_all_libraries");
+        let parameters: Vec<&str> = search["parameters"].as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(parameters, vec!["q", "types", "player", "limit"]);
+        assert_eq!(
+            search["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/LibrarySearchResponse"
+        );
+        assert_eq!(paths["/api/library/{player_name}"]["get"]["parameters"][0]["in"], "path");
+        assert!(paths.contains_key("/api/health"));
+    }
+
+    #[test]
+    fn test_openapi_path() {
+        assert_eq!(openapi_path("/api/federation/proxy/<device>/<path..>"), "/api/federation/proxy/{device}/{path}");
+        assert_eq!(openapi_path("/api/health/"), "/api/health");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::AudioController;
+use crate::players::MPDPlayerController;
+use crate::players::mpd::outputs::{MpdOutput, OutputError};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::info;
+
+/// Response structure for the audio outputs of a player
+#[derive(Serialize, Deserialize)]
+pub struct OutputListResponse {
+    pub player_name: String,
+    pub outputs: Vec<MpdOutput>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+fn output_error(error: OutputError) -> ApiError {
+    let status = match error {
+        OutputError::NotFound(_) => Status::NotFound,
+        OutputError::NotConnected => Status::ServiceUnavailable,
+        OutputError::Mpd(_) => Status::InternalServerError,
+    };
+    error_response(status, error.to_string())
+}
+
+/// Run a function with the MPD player of the given name
+fn with_mpd(
+    controller: &AudioController,
+    player_name: &str,
+    f: impl FnOnce(&MPDPlayerController) -> Result<Vec<MpdOutput>, OutputError>,
+) -> Result<Json<OutputListResponse>, ApiError> {
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if !ctrl.get_player_name().eq_ignore_ascii_case(player_name) {
+            continue;
+        }
+        return match ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            Some(mpd) => f(mpd).map_err(output_error).map(|outputs| {
+                Json(OutputListResponse {
+                    player_name: player_name.to_string(),
+                    outputs,
+                })
+            }),
+            None => Err(error_response(
+                Status::BadRequest,
+                format!("Player '{}' does not support audio outputs", player_name),
+            )),
+        };
+    }
+    Err(error_response(Status::NotFound, format!("Player '{}' not found", player_name)))
+}
+
+/// List the audio outputs of a player
+#[get("/<player_name>")]
+pub fn list_outputs(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<OutputListResponse>, ApiError> {
+    with_mpd(controller.inner(), player_name, |mpd| mpd.list_outputs())
+}
+
+/// Enable an audio output, given by id or name
+///
+/// # Parameters
+/// * `exclusive` - Disable all other outputs, default false
+#[post("/<player_name>/<output>/enable?<exclusive>")]
+pub fn enable_output(
+    player_name: &str,
+    output: &str,
+    exclusive: Option<bool>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<OutputListResponse>, ApiError> {
+    info!("API request: enable output '{}' on {}", output, player_name);
+    with_mpd(controller.inner(), player_name, |mpd| {
+        mpd.set_output_enabled(output, true, exclusive.unwrap_or(false))
+    })
+}
+
+/// Disable an audio output, given by id or name
+#[post("/<player_name>/<output>/disable")]
+pub fn disable_output(
+    player_name: &str,
+    output: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<OutputListResponse>, ApiError> {
+    info!("API request: disable output '{}' on {}", output, player_name);
+    with_mpd(controller.inner(), player_name, |mpd| mpd.set_output_enabled(output, false, false))
+}
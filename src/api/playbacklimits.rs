@@ -0,0 +1,48 @@
+use crate::audiocontrol::playbacklimits::PlaybackLimitStatus;
+use crate::audiocontrol::AudioController;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Request to lift the playback limits temporarily
+#[derive(Serialize, Deserialize)]
+pub struct OverrideRequest {
+    /// PIN configured as `override_pin`
+    pub pin: String,
+    /// Duration of the override, the configured `override_minutes` if not set
+    pub minutes: Option<u64>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Get the current effect of the playback limits
+#[get("/status")]
+pub fn get_status(controller: &State<Arc<AudioController>>) -> Json<PlaybackLimitStatus> {
+    Json(controller.get_playback_limits())
+}
+
+/// Lift the playback limits for some minutes
+#[post("/override", data = "<request>")]
+pub fn set_override(
+    request: Json<OverrideRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<PlaybackLimitStatus>, Custom<Json<ErrorResponse>>> {
+    controller
+        .override_playback_limits(&request.pin, request.minutes)
+        .map(Json)
+        .map_err(|message| Custom(Status::Unauthorized, Json(ErrorResponse { success: false, message })))
+}
+
+/// End an override before it expires
+#[delete("/override")]
+pub fn clear_override(controller: &State<Arc<AudioController>>) -> Json<PlaybackLimitStatus> {
+    Json(controller.clear_playback_limits_override())
+}
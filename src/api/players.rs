@@ -1,6 +1,8 @@
 use crate::AudioController;
-use crate::data::{PlaybackState, PlayerCommand, LoopMode, Song, Track, PlayerUpdate, PlayerCapability}; // Added PlayerCapability
+use crate::api::dryrun::{DryRun, DryRunResponse, PlannedChange};
+use crate::data::{PlaybackState, PlayerCommand, LoopMode, ReplayGainMode, SingleMode, Song, Track, PlayerUpdate, PlayerCapability}; // Added PlayerCapability
 use crate::players::PlayerController; // Fixed: Using the public re-export
+use crate::helpers::queue_estimate::{self, QueueEstimate};
 use rocket::serde::json::Json;
 use rocket::{get, post, State};
 use rocket::request::{FromRequest, Outcome};
@@ -182,6 +184,8 @@ pub struct PlayerInfo {
     loop_mode: LoopMode, // Loop mode (None, Track, Playlist)
     position: Option<f64>, // Current playback position in seconds
     capabilities: Vec<PlayerCapability>, // List of capabilities this player supports
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mirrors: Vec<String>, // Controllers merged into this player
 }
 
 /// Response for command execution
@@ -209,6 +213,16 @@ pub struct NowPlayingResponse {
 pub struct QueueResponse {
     player: String,
     queue: Vec<Track>,
+    /// The queue is managed on the sending device (AirPlay, Bluetooth, Roon)
+    remote: bool,
+    /// If false, an empty queue doesn't mean that nothing follows
+    length_known: bool,
+    /// Upcoming tracks announced by the sender of a remote queue
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    upcoming: Vec<Track>,
+    /// Remaining play time of a local queue that doesn't repeat
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimate: Option<QueueEstimate>,
 }
 
 /// Response struct for player metadata
@@ -279,11 +293,21 @@ pub fn list_players(controller: &State<Arc<AudioController>>) -> Json<PlayersLis
     let current_player_name = audio_controller.get_player_name();
     let current_player_id = audio_controller.get_player_id();
     
+    // Mirrors of merged players are shown as part of their primary player
+    let merged = audio_controller.get_merged_players();
     let players_info: Vec<PlayerInfo> = controllers.iter()
+        .filter(|ctrl_lock| {
+            let ctrl = ctrl_lock.read();
+            !audio_controller.is_mirror(&ctrl.get_player_name(), &ctrl.get_player_id())
+        })
         .map(|ctrl_lock| {
             let ctrl = ctrl_lock.read();
             let name = ctrl.get_player_name();
             let id = ctrl.get_player_id();
+            let mirrors = merged.group_of(&name, &id)
+                .filter(|group| group.is_primary(&name, &id))
+                .map(|group| group.mirrors.clone())
+                .unwrap_or_default();
 
             // Format last_seen timestamp if available
             let last_seen = ctrl.get_last_seen()
@@ -304,6 +328,7 @@ pub fn list_players(controller: &State<Arc<AudioController>>) -> Json<PlayersLis
                 loop_mode: ctrl.get_loop_mode(),
                 position: ctrl.get_position(),
                 capabilities: ctrl.get_capabilities().to_vec(),
+                mirrors,
             }
         })
         .collect();
@@ -313,6 +338,15 @@ pub fn list_players(controller: &State<Arc<AudioController>>) -> Json<PlayersLis
     })
 }
 
+/// Request body for moving a track within the queue
+#[derive(serde::Deserialize)]
+pub struct MoveTrackRequest {
+    /// Zero-based index of the track to move
+    from: usize,
+    /// Zero-based index the track ends up at
+    to: usize,
+}
+
 /// Request body for add_track command
 #[derive(serde::Deserialize)]
 pub struct AddTrackRequest {
@@ -333,14 +367,21 @@ pub struct AddTrackRequest {
 ///   - seek:<seconds> - Seek to position in seconds
 ///   - set_random:true|false - Toggle shuffle mode
 ///   - remove_track:<uri> - Remove a track from the queue
+///   - move_track:<from>:<to> - Move a track within the queue
 /// - add_track - Add a track to the queue (requires JSON body with uri field)
+/// - play_next - Insert a track after the current one (same JSON body as add_track)
+/// - play_now - Replace the queue and start playback (requires JSON body with uris field)
+///
+/// With `?dry_run=true` the queue changes the command would make are returned
+/// instead of sending the command.
 #[post("/player/<n>/command/<command>", data = "<request_data>")]
 pub fn send_command_to_player_by_name(
     n: &str,
     command: &str,
     request_data: Option<Json<serde_json::Value>>,
+    dry_run: DryRun,
     controller: &State<Arc<AudioController>>
-) -> Result<Json<CommandResponse>, Custom<Json<CommandResponse>>> {
+) -> Result<DryRunResponse<Json<CommandResponse>>, Custom<Json<CommandResponse>>> {
     let audio_controller = controller.inner();
     let player_name = if n.to_lowercase() == "active" {
         // Get the active player's name
@@ -375,16 +416,8 @@ pub fn send_command_to_player_by_name(
         }
     };
     
-    // Find the controller with the matching name
-    let controllers = audio_controller.list_controllers();
-    let mut found_controller = None;
-    for ctrl_lock in controllers {
-        let ctrl = ctrl_lock.read();
-        if ctrl.get_player_name() == player_name {
-            found_controller = Some(ctrl_lock.clone());
-            break;
-        }
-    }
+    // Find the controller with the matching name, mirrors of merged players are replaced by the primary
+    let found_controller = audio_controller.get_preferred_player_by_name(&player_name);
     
     // If no controller with the given name was found, return a 404
     let target_controller = match found_controller {
@@ -400,22 +433,67 @@ pub fn send_command_to_player_by_name(
         }
     };
     
-    // Send the command to the found player
-    let success = target_controller.read().send_command(parsed_command.clone());
-    
-    if success {
-        Ok(Json(CommandResponse {
-            success: true,
-            message: format!("Command '{}' sent successfully to player with name: {}", command, player_name),
-        }))
-    } else {
-        Err(Custom(
-            Status::InternalServerError,
+    // Refuse commands that would start playback outside of the playback limits
+    let state = target_controller.read().get_playback_state();
+    if let Err(reason) = audio_controller.check_command(&parsed_command, state) {
+        return Err(Custom(
+            Status::Forbidden,
             Json(CommandResponse {
                 success: false,
-                message: format!("Failed to send command '{}' to player with name: {}", command, player_name),
+                message: reason,
             })
-        ))
+        ));
+    }
+
+    dry_run.run(
+        "command",
+        || Ok(plan_command(&player_name, &parsed_command, &target_controller.read().get_queue())),
+        || {
+            // Send the command to the found player
+            let success = target_controller.read().send_command(parsed_command.clone());
+
+            if success {
+                Ok(Json(CommandResponse {
+                    success: true,
+                    message: format!("Command '{}' sent successfully to player with name: {}", command, player_name),
+                }))
+            } else {
+                Err(Custom(
+                    Status::InternalServerError,
+                    Json(CommandResponse {
+                        success: false,
+                        message: format!("Failed to send command '{}' to player with name: {}", command, player_name),
+                    })
+                ))
+            }
+        },
+    )
+}
+
+/// Changes a command would make to the queue of a player
+///
+/// Commands that don't change the queue are reported as a single "send_command" change.
+fn plan_command(player_name: &str, command: &PlayerCommand, queue: &[Track]) -> Vec<PlannedChange> {
+    let remove = |track: &Track| {
+        PlannedChange::new("remove_from_queue", track.uri.clone().unwrap_or_else(|| track.name.clone()))
+            .with_detail(track.name.clone())
+    };
+    let add = |uri: &String| PlannedChange::new("add_to_queue", uri.clone());
+
+    match command {
+        PlayerCommand::ClearQueue => queue.iter().map(remove).collect(),
+        PlayerCommand::RemoveTrack(index) => queue.get(*index).map(remove).into_iter().collect(),
+        PlayerCommand::MoveTrack { from, to } => queue
+            .get(*from)
+            .map(|track| {
+                PlannedChange::new("move_in_queue", track.uri.clone().unwrap_or_else(|| track.name.clone()))
+                    .with_detail(format!("{} -> {}", from, to))
+            })
+            .into_iter()
+            .collect(),
+        PlayerCommand::QueueTracks { uris, .. } => uris.iter().map(add).collect(),
+        PlayerCommand::PlayNow { uris, .. } => queue.iter().map(remove).chain(uris.iter().map(add)).collect(),
+        _ => vec![PlannedChange::new("send_command", player_name).with_detail(command.to_string())],
     }
 }
 
@@ -439,6 +517,7 @@ pub fn get_now_playing(
             loop_mode: LoopMode::None,
             position: None,
             capabilities: vec![],
+            mirrors: vec![],
         },
         song: None,
         state: PlaybackState::Unknown,
@@ -473,8 +552,8 @@ pub fn get_now_playing(
     // Get the state safely (the implementation now uses cached data)
     let state = player.get_playback_state();
     
-    // Get song data (should be cached data)
-    let mut song = player.get_song();
+    // Get song data (should be cached data), hidden while the system is idle
+    let mut song = if crate::helpers::idle::hides_now_playing() { None } else { player.get_song() };
     if let Some(song_ref) = song.as_mut() {
         rewrite_song_urls(song_ref, forwarded_prefix.0.as_deref());
     }
@@ -505,6 +584,7 @@ pub fn get_now_playing(
             loop_mode,
             position,
             capabilities: player.get_capabilities().to_vec(),
+            mirrors: vec![],
         },
         song,
         state,
@@ -544,16 +624,8 @@ pub fn get_player_queue(
         n.to_string()
     };
     
-    // Find the controller with the matching name
-    let controllers = audio_controller.list_controllers();
-    let mut found_controller = None;
-    for ctrl_lock in controllers {
-        let ctrl = ctrl_lock.read();
-        if ctrl.get_player_name() == player_name {
-            found_controller = Some(ctrl_lock.clone());
-            break;
-        }
-    }
+    // Find the controller with the matching name, mirrors of merged players are replaced by the primary
+    let found_controller = audio_controller.get_preferred_player_by_name(&player_name);
     
     // If no controller with the given name was found, return a 404
     let target_controller = match found_controller {
@@ -570,14 +642,167 @@ pub fn get_player_queue(
     };
     
     // Get the queue from the found player
-    let queue = target_controller.read().get_queue();
-    
+    let ctrl = target_controller.read();
+    let remote = ctrl.get_capabilities().has_capability(PlayerCapability::RemoteQueue);
+    let upcoming = if remote { ctrl.get_upcoming_tracks() } else { Vec::new() };
+    let queue = ctrl.get_queue();
+    let estimate = queue_estimate::estimate_player(&**ctrl, &queue);
+
     Ok(Json(QueueResponse {
         player: player_name,
         queue,
+        remote,
+        length_known: !remote,
+        upcoming,
+        estimate,
+    }))
+}
+
+/// Move a track within the queue of a player
+///
+/// The request body contains the zero-based `from` and `to` indices, e.g. after a
+/// drag-and-drop in the UI. If the player name is "active", the currently active
+/// player will be used.
+#[post("/player/<n>/queue/move", data = "<request>")]
+pub fn move_queue_track(
+    n: &str,
+    request: Json<MoveTrackRequest>,
+    controller: &State<Arc<AudioController>>
+) -> Result<Json<CommandResponse>, Custom<Json<CommandResponse>>> {
+    let error = |status: Status, message: String| Custom(status, Json(CommandResponse { success: false, message }));
+    let audio_controller = controller.inner();
+    let player_name = if n.to_lowercase() == "active" {
+        match audio_controller.get_active_controller() {
+            Some(active_ctrl) => active_ctrl.read().get_player_name(),
+            None => return Err(error(Status::NotFound, "No active player found".to_string())),
+        }
+    } else {
+        n.to_string()
+    };
+
+    let target_controller = audio_controller
+        .get_preferred_player_by_name(&player_name)
+        .ok_or_else(|| error(Status::NotFound, format!("No player found with name: {}", player_name)))?;
+
+    let MoveTrackRequest { from, to } = request.into_inner();
+    let length = target_controller.read().get_queue().len();
+    if from >= length || to >= length {
+        return Err(error(
+            Status::BadRequest,
+            format!("Invalid queue positions {} -> {}, the queue has {} tracks", from, to, length),
+        ));
+    }
+
+    let command = PlayerCommand::MoveTrack { from, to };
+    if target_controller.read().send_command(command.clone()) {
+        Ok(Json(CommandResponse {
+            success: true,
+            message: format!("Command '{}' sent successfully to player with name: {}", command, player_name),
+        }))
+    } else {
+        Err(error(
+            Status::InternalServerError,
+            format!("Failed to send command '{}' to player with name: {}", command, player_name),
+        ))
+    }
+}
+
+/// Make a player the active player
+///
+/// Uses the configured transition: the previous player is paused or stopped and, if
+/// enabled, the volume is faded out and in again. With `play=true` playback is started
+/// on the new player. The request returns when the switch is finished.
+#[post("/player/<player_name>/activate?<play>")]
+pub fn activate_player(
+    player_name: &str,
+    play: Option<bool>,
+    controller: &State<Arc<AudioController>>
+) -> Result<Json<CommandResponse>, Custom<Json<CommandResponse>>> {
+    let audio_controller = controller.inner();
+    let index = audio_controller.list_controllers().iter().position(|ctrl_lock| {
+        let ctrl = ctrl_lock.read();
+        ctrl.get_player_name().eq_ignore_ascii_case(player_name)
+            || ctrl.get_player_id().eq_ignore_ascii_case(player_name)
+    });
+
+    let Some(index) = index else {
+        return Err(Custom(
+            Status::NotFound,
+            Json(CommandResponse {
+                success: false,
+                message: format!("No player found with name: {}", player_name),
+            })
+        ));
+    };
+
+    if !audio_controller.switch_to_player(index, play.unwrap_or(false)) {
+        return Err(Custom(
+            Status::Conflict,
+            Json(CommandResponse {
+                success: false,
+                message: "Another player switch is in progress".to_string(),
+            })
+        ));
+    }
+
+    // Other players can't take over for a while
+    audio_controller.pin_player(index);
+
+    Ok(Json(CommandResponse {
+        success: true,
+        message: format!("{} is now the active player", player_name),
     }))
 }
 
+/// Request body for rating a song
+#[derive(serde::Deserialize)]
+pub struct RatingRequest {
+    /// 1 to 5 stars, 0 removes the rating
+    pub rating: u8,
+    /// URI of the song, the current song if not set
+    #[serde(default)]
+    pub uri: Option<String>,
+}
+
+/// Rate a song of an MPD player
+///
+/// Ratings are stored in MPD's sticker database and returned in the `rating` metadata
+/// value of the song.
+#[post("/player/<player_name>/rating", data = "<request>")]
+pub fn set_rating(
+    player_name: &str,
+    request: Json<RatingRequest>,
+    controller: &State<Arc<AudioController>>
+) -> Result<Json<CommandResponse>, Custom<Json<CommandResponse>>> {
+    use crate::players::MPDPlayerController;
+    use crate::players::mpd::stickers::StickerError;
+
+    let error = |status: Status, message: String| Custom(status, Json(CommandResponse { success: false, message }));
+    let Some(ctrl_lock) = controller.inner().get_player_by_name(player_name) else {
+        return Err(error(Status::NotFound, format!("No player found with name: {}", player_name)));
+    };
+    let ctrl = ctrl_lock.read();
+    let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() else {
+        return Err(error(Status::BadRequest, format!("Player '{}' does not support ratings", player_name)));
+    };
+
+    match mpd.set_rating(request.uri.as_deref(), request.rating) {
+        Ok(uri) => Ok(Json(CommandResponse {
+            success: true,
+            message: format!("Rated {} with {} stars", uri, request.rating),
+        })),
+        Err(e) => {
+            let status = match e {
+                StickerError::InvalidRating(_) | StickerError::NotInDatabase(_) => Status::BadRequest,
+                StickerError::NoSong => Status::NotFound,
+                StickerError::NotConnected => Status::ServiceUnavailable,
+                StickerError::Mpd(_) => Status::InternalServerError,
+            };
+            Err(error(status, e.to_string()))
+        }
+    }
+}
+
 /// Get all metadata for a player
 /// 
 /// If the player name is "active", the currently active player will be used.
@@ -752,6 +977,82 @@ pub fn update_player_state(
     }
 }
 
+/// Request body for play_now command
+#[derive(serde::Deserialize)]
+pub struct PlayNowRequest {
+    uris: Vec<String>,
+    #[serde(default)]
+    metadata: Vec<Option<std::collections::HashMap<String, serde_json::Value>>>,
+    #[serde(default)]
+    start_index: usize,
+}
+
+/// Build a PlayNow command from a play_now JSON body
+fn parse_play_now_request(request_data: Option<&Json<serde_json::Value>>) -> Result<PlayerCommand, String> {
+    let play_now = request_data
+        .and_then(|data| serde_json::from_value::<PlayNowRequest>(data.0.clone()).ok())
+        .ok_or_else(|| "play_now command requires JSON body with 'uris' field".to_string())?;
+
+    if play_now.uris.is_empty() {
+        return Err("play_now command requires at least one URI".to_string());
+    }
+    if play_now.start_index >= play_now.uris.len() {
+        return Err(format!("start_index {} is out of range for {} tracks",
+                           play_now.start_index, play_now.uris.len()));
+    }
+
+    let metadata = play_now.metadata.into_iter()
+        .map(|meta| meta.map(|metadata| crate::data::player_command::QueueTrackMetadata { metadata }))
+        .collect();
+
+    Ok(PlayerCommand::PlayNow {
+        uris: play_now.uris,
+        metadata,
+        start_index: play_now.start_index,
+    })
+}
+
+/// Build a MoveTrack command from a JSON body with `from` and `to`
+fn parse_move_track_request(request_data: Option<&Json<serde_json::Value>>) -> Result<PlayerCommand, String> {
+    let move_request = request_data
+        .and_then(|data| serde_json::from_value::<MoveTrackRequest>(data.0.clone()).ok())
+        .ok_or_else(|| "move_track command requires JSON body with 'from' and 'to' fields".to_string())?;
+
+    Ok(PlayerCommand::MoveTrack {
+        from: move_request.from,
+        to: move_request.to,
+    })
+}
+
+/// Build a QueueTracks command from an add_track/play_next JSON body
+///
+/// With `play_next` set, the track is inserted directly after the
+/// currently playing track instead of being appended to the queue.
+fn parse_add_track_request(cmd_str: &str, request_data: Option<&Json<serde_json::Value>>, play_next: bool) -> Result<PlayerCommand, String> {
+    let add_request = request_data
+        .and_then(|data| serde_json::from_value::<AddTrackRequest>(data.0.clone()).ok())
+        .ok_or_else(|| format!("{} command requires JSON body with 'uri' field", cmd_str))?;
+
+    debug!("Adding track to queue: uri={}, metadata={:?}, play_next={}",
+           add_request.uri, add_request.metadata, play_next);
+
+    // Create metadata if provided
+    let metadata = if let Some(meta) = add_request.metadata {
+        vec![Some(crate::data::player_command::QueueTrackMetadata {
+            metadata: meta,
+        })]
+    } else {
+        vec![None]
+    };
+
+    Ok(PlayerCommand::QueueTracks {
+        uris: vec![add_request.uri],
+        insert_at_beginning: false,
+        insert_after_current: play_next,
+        metadata,
+    })
+}
+
 /// Helper function to parse player commands
 fn parse_player_command(cmd_str: &str, request_data: Option<&Json<serde_json::Value>>) -> Result<PlayerCommand, String> {
     // Handle simple commands
@@ -764,34 +1065,10 @@ fn parse_player_command(cmd_str: &str, request_data: Option<&Json<serde_json::Va
         "previous" => return Ok(PlayerCommand::Previous),
         "kill" => return Ok(PlayerCommand::Kill),
         "clear_queue" => return Ok(PlayerCommand::ClearQueue),
-        "add_track" => {
-            // Parse URI from request body
-            if let Some(data) = request_data {
-                if let Ok(add_request) = serde_json::from_value::<AddTrackRequest>(data.0.clone()) {
-                    debug!("Adding track to queue: uri={}, metadata={:?}", 
-                           add_request.uri, add_request.metadata);
-                    
-                    // Create metadata if provided
-                    let metadata = if let Some(meta) = add_request.metadata {
-                        vec![Some(crate::data::player_command::QueueTrackMetadata {
-                            metadata: meta,
-                        })]
-                    } else {
-                        vec![None]
-                    };
-                    
-                    return Ok(PlayerCommand::QueueTracks {
-                        uris: vec![add_request.uri],
-                        insert_at_beginning: false,
-                        metadata,
-                    });
-                } else {
-                    return Err("add_track command requires JSON body with 'uri' field".to_string());
-                }
-            } else {
-                return Err("add_track command requires JSON body with 'uri' field".to_string());
-            }
-        },
+        "add_track" => return parse_add_track_request(cmd_str, request_data, false),
+        "play_next" => return parse_add_track_request(cmd_str, request_data, true),
+        "play_now" => return parse_play_now_request(request_data),
+        "move_track" => return parse_move_track_request(request_data),
         _ => {} // continue to complex command parsing
     }
     
@@ -828,6 +1105,39 @@ fn parse_player_command(cmd_str: &str, request_data: Option<&Json<serde_json::Va
                     _ => return Err(format!("Invalid random setting: {}", param))
                 }
             },
+            "set_replay_gain_mode" | "replay_gain_mode" => {
+                match ReplayGainMode::from_str(param) {
+                    Ok(mode) => return Ok(PlayerCommand::SetReplayGainMode(mode)),
+                    Err(_) => return Err(format!("Invalid ReplayGain mode: {}", param))
+                }
+            },
+            "set_consume" | "consume" => {
+                // Parse consume setting
+                match param.to_lowercase().as_str() {
+                    "true" | "on" | "1" | "yes" => return Ok(PlayerCommand::SetConsume(true)),
+                    "false" | "off" | "0" | "no" => return Ok(PlayerCommand::SetConsume(false)),
+                    _ => return Err(format!("Invalid consume setting: {}", param))
+                }
+            },
+            "set_single" | "single" => {
+                match SingleMode::from_str(param) {
+                    Ok(mode) => return Ok(PlayerCommand::SetSingle(mode)),
+                    Err(_) => return Err(format!("Invalid single mode: {}", param))
+                }
+            },
+            "set_crossfade" | "crossfade" => {
+                // Crossfade duration in seconds
+                match param.parse::<u32>() {
+                    Ok(seconds) => return Ok(PlayerCommand::SetCrossfade(seconds)),
+                    Err(_) => return Err(format!("Invalid crossfade duration: {}", param))
+                }
+            },
+            "set_mixrampdb" | "mixrampdb" => {
+                match param.parse::<f32>() {
+                    Ok(db) if db.is_finite() => return Ok(PlayerCommand::SetMixRampDb(db)),
+                    _ => return Err(format!("Invalid MixRamp threshold: {}", param))
+                }
+            },
             "remove_track" => {
                 // Parse position as usize for track removal
                 match param.parse::<usize>() {
@@ -835,6 +1145,15 @@ fn parse_player_command(cmd_str: &str, request_data: Option<&Json<serde_json::Va
                     Err(_) => return Err(format!("Invalid track position: {}", param))
                 }
             },
+            "move_track" => {
+                // Parse "<from>:<to>"
+                let positions = param.split_once(':')
+                    .and_then(|(from, to)| Some((from.parse::<usize>().ok()?, to.parse::<usize>().ok()?)));
+                match positions {
+                    Some((from, to)) => return Ok(PlayerCommand::MoveTrack { from, to }),
+                    None => return Err(format!("Invalid queue positions: {}", param))
+                }
+            },
             "play_queue_index" => {
                 // Parse index as usize for playing track at specified index in queue
                 match param.parse::<usize>() {
@@ -0,0 +1,263 @@
+use crate::AudioController;
+use crate::data::{PlayerCommand, Track};
+use crate::data::playlist::{PlaylistError, PlaylistResult, PlaylistStore, StoredPlaylist};
+use crate::helpers::playlist_store::M3uPlaylistStore;
+use crate::players::{MPDPlayerController, PlayerController};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::info;
+
+/// Response structure for the list of stored playlists
+#[derive(Serialize, Deserialize)]
+pub struct PlaylistListResponse {
+    pub player_name: String,
+    pub playlists: Vec<StoredPlaylist>,
+}
+
+/// Response structure for the tracks of a stored playlist
+#[derive(Serialize, Deserialize)]
+pub struct PlaylistTracksResponse {
+    pub player_name: String,
+    pub name: String,
+    pub tracks: Vec<Track>,
+}
+
+/// Request body for saving the queue
+#[derive(Deserialize)]
+pub struct SaveRequest {
+    pub name: String,
+    /// Replace an existing playlist with the same name
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Request body for creating a playlist
+#[derive(Deserialize)]
+pub struct CreateRequest {
+    pub name: String,
+    /// Tracks with a URI, tracks without one are skipped
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+}
+
+/// Request body for replacing or extending the tracks of a playlist
+#[derive(Deserialize)]
+pub struct TracksRequest {
+    pub tracks: Vec<Track>,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<MessageResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(MessageResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+fn playlist_error(error: PlaylistError) -> ApiError {
+    let status = match error {
+        PlaylistError::NotFound(_) => Status::NotFound,
+        PlaylistError::Exists(_) => Status::Conflict,
+        PlaylistError::InvalidName(_) => Status::BadRequest,
+        PlaylistError::NotConnected => Status::ServiceUnavailable,
+        PlaylistError::Mpd(_) | PlaylistError::Storage(_) => Status::InternalServerError,
+    };
+    error_response(status, error.to_string())
+}
+
+fn ok(message: String) -> Json<MessageResponse> {
+    Json(MessageResponse { success: true, message })
+}
+
+/// Run a function with the player of the given name
+fn with_player<T>(
+    controller: &AudioController,
+    player_name: &str,
+    f: impl FnOnce(&dyn PlayerController) -> Result<T, ApiError>,
+) -> Result<T, ApiError> {
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if ctrl.get_player_name().eq_ignore_ascii_case(player_name) {
+            return f(ctrl.as_ref());
+        }
+    }
+    Err(error_response(Status::NotFound, format!("Player '{}' not found", player_name)))
+}
+
+/// The playlists of a player, MPD stores them itself, the playlists of other players
+/// are stored as M3U files
+fn with_store<T>(
+    controller: &AudioController,
+    player_name: &str,
+    f: impl FnOnce(&dyn PlaylistStore) -> PlaylistResult<T>,
+) -> Result<T, ApiError> {
+    with_player(controller, player_name, |player| {
+        let result = match player.as_any().downcast_ref::<MPDPlayerController>() {
+            Some(mpd) => f(mpd),
+            None => f(&M3uPlaylistStore::for_player(&player.get_player_name())),
+        };
+        result.map_err(playlist_error)
+    })
+}
+
+/// List the stored playlists of a player
+#[get("/<player_name>")]
+pub fn list_playlists(
+    player_name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<PlaylistListResponse>, ApiError> {
+    let playlists = with_store(controller.inner(), player_name, |store| store.list_playlists())?;
+    Ok(Json(PlaylistListResponse {
+        player_name: player_name.to_string(),
+        playlists,
+    }))
+}
+
+/// Get the tracks of a stored playlist
+#[get("/<player_name>/<name>")]
+pub fn get_playlist(
+    player_name: &str,
+    name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<PlaylistTracksResponse>, ApiError> {
+    let tracks = with_store(controller.inner(), player_name, |store| store.get_playlist_tracks(name))?;
+    Ok(Json(PlaylistTracksResponse {
+        player_name: player_name.to_string(),
+        name: name.to_string(),
+        tracks,
+    }))
+}
+
+/// Create a playlist from tracks
+#[post("/<player_name>", data = "<request>")]
+pub fn create_playlist(
+    player_name: &str,
+    request: Json<CreateRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let request = request.into_inner();
+    info!("API request: create playlist '{}' on {}", request.name, player_name);
+    with_store(controller.inner(), player_name, |store| store.save_playlist(&request.name, &request.tracks, false))?;
+    Ok(ok(format!("Playlist '{}' created", request.name)))
+}
+
+/// Replace the tracks of a playlist, the playlist is created if it doesn't exist
+#[put("/<player_name>/<name>", data = "<request>")]
+pub fn replace_playlist(
+    player_name: &str,
+    name: &str,
+    request: Json<TracksRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: replace playlist '{}' on {}", name, player_name);
+    with_store(controller.inner(), player_name, |store| store.save_playlist(name, &request.tracks, true))?;
+    Ok(ok(format!("Playlist '{}' saved", name)))
+}
+
+/// Add tracks to the end of a playlist
+#[post("/<player_name>/<name>/tracks", data = "<request>")]
+pub fn add_to_playlist(
+    player_name: &str,
+    name: &str,
+    request: Json<TracksRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: add {} tracks to playlist '{}' on {}", request.tracks.len(), name, player_name);
+    with_store(controller.inner(), player_name, |store| store.add_to_playlist(name, &request.tracks))?;
+    Ok(ok(format!("{} tracks added to playlist '{}'", request.tracks.len(), name)))
+}
+
+/// Load a stored playlist into the queue
+///
+/// # Parameters
+/// * `replace` - Clear the queue first, default true
+/// * `play` - Start playback, default false
+#[post("/<player_name>/<name>/load?<replace>&<play>")]
+pub fn load_playlist(
+    player_name: &str,
+    name: &str,
+    replace: Option<bool>,
+    play: Option<bool>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: load playlist '{}' on {}", name, player_name);
+    let replace = replace.unwrap_or(true);
+    let play = play.unwrap_or(false);
+    with_player(controller.inner(), player_name, |player| {
+        if let Some(mpd) = player.as_any().downcast_ref::<MPDPlayerController>() {
+            return mpd.load_playlist(name, replace, play).map_err(playlist_error);
+        }
+        let store = M3uPlaylistStore::for_player(&player.get_player_name());
+        let uris: Vec<String> = store
+            .get_playlist_tracks(name)
+            .map_err(playlist_error)?
+            .into_iter()
+            .filter_map(|track| track.uri)
+            .collect();
+        if replace {
+            player.send_command(PlayerCommand::ClearQueue);
+        }
+        let queued = player.send_command(PlayerCommand::QueueTracks {
+            uris,
+            insert_at_beginning: false,
+            insert_after_current: false,
+            metadata: Vec::new(),
+        });
+        if !queued {
+            return Err(error_response(Status::BadRequest, format!("Player '{}' can't queue tracks", player_name)));
+        }
+        if play {
+            player.send_command(PlayerCommand::Play);
+        }
+        Ok(())
+    })?;
+    let action = if replace { "replaced the queue" } else { "added to the queue" };
+    Ok(ok(format!("Playlist '{}' {}", name, action)))
+}
+
+/// Save the current queue as a stored playlist
+#[post("/<player_name>/save", data = "<request>")]
+pub fn save_playlist(
+    player_name: &str,
+    request: Json<SaveRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let request = request.into_inner();
+    info!("API request: save queue of {} as playlist '{}'", player_name, request.name);
+    with_player(controller.inner(), player_name, |player| {
+        let result = match player.as_any().downcast_ref::<MPDPlayerController>() {
+            Some(mpd) => mpd.save_queue_as_playlist(&request.name, request.overwrite),
+            None => M3uPlaylistStore::for_player(&player.get_player_name()).save_playlist(
+                &request.name,
+                &player.get_queue(),
+                request.overwrite,
+            ),
+        };
+        result.map_err(playlist_error)
+    })?;
+    Ok(ok(format!("Queue saved as playlist '{}'", request.name)))
+}
+
+/// Delete a stored playlist
+#[delete("/<player_name>/<name>")]
+pub fn delete_playlist(
+    player_name: &str,
+    name: &str,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: delete playlist '{}' on {}", name, player_name);
+    with_store(controller.inner(), player_name, |store| store.delete_playlist(name))?;
+    Ok(ok(format!("Playlist '{}' deleted", name)))
+}
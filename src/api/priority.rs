@@ -0,0 +1,18 @@
+use crate::audiocontrol::priority::PriorityStatus;
+use crate::audiocontrol::AudioController;
+use rocket::{delete, get, State};
+use std::sync::Arc;
+use rocket::serde::json::Json;
+
+/// Get the source priority rules, the pinned player and the latest decisions
+#[get("/")]
+pub fn get_status(controller: &State<Arc<AudioController>>) -> Json<PriorityStatus> {
+    Json(controller.get_priority_status())
+}
+
+/// Remove the pin of a manually activated player
+#[delete("/pin")]
+pub fn delete_pin(controller: &State<Arc<AudioController>>) -> Json<PriorityStatus> {
+    controller.unpin_player();
+    Json(controller.get_priority_status())
+}
@@ -0,0 +1,30 @@
+use crate::helpers::imageproxy::{self, ImageProxyError};
+use rocket::get;
+use rocket::http::{ContentType, Status};
+use rocket::response::status::Custom;
+
+/// Fetch a remote image through the image cache
+///
+/// Artwork from external players often points to arbitrary hosts. Loading it
+/// through this endpoint avoids mixed-content and CORS issues in browser clients.
+///
+/// # Parameters
+/// * `url` - URL of the image
+/// * `size` - Optional maximum width/height in pixels
+#[get("/image?<url>&<size>")]
+pub fn get_proxied_image(url: &str, size: Option<u32>) -> Result<(ContentType, Vec<u8>), Custom<String>> {
+    log::debug!("Proxy image request for {} (size: {:?})", url, size);
+
+    match imageproxy::get_image(url, size) {
+        Ok((data, mime_type)) => {
+            let content_type = ContentType::parse_flexible(&mime_type).unwrap_or(ContentType::Binary);
+            Ok((content_type, data))
+        }
+        Err(e @ ImageProxyError::InvalidUrl(_)) => Err(Custom(Status::BadRequest, e.to_string())),
+        Err(e @ ImageProxyError::LocalAddress(_)) => Err(Custom(Status::Forbidden, e.to_string())),
+        Err(e) => {
+            log::warn!("Failed to proxy image {}: {}", url, e);
+            Err(Custom(Status::BadGateway, e.to_string()))
+        }
+    }
+}
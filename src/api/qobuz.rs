@@ -0,0 +1,227 @@
+use crate::audiocontrol::AudioController;
+use crate::data::PlayerCommand;
+use crate::helpers::qobuz::{self, QobuzAlbum, QobuzArtist, QobuzError, QobuzQuality, QobuzSearchResult, QobuzTrack};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::{info, warn};
+
+/// Response structure for the Qobuz status
+#[derive(Serialize, Deserialize)]
+pub struct QobuzStatusResponse {
+    /// Whether Qobuz is enabled and an app ID is configured
+    pub enabled: bool,
+    pub logged_in: bool,
+    pub username: Option<String>,
+    /// Default stream quality
+    pub quality: QobuzQuality,
+}
+
+/// Request structure to log in
+#[derive(Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// Response structure for a stream URL
+#[derive(Serialize, Deserialize)]
+pub struct StreamUrlResponse {
+    pub track_id: u64,
+    pub quality: QobuzQuality,
+    pub url: String,
+}
+
+/// Request structure to queue Qobuz tracks on a player
+#[derive(Serialize, Deserialize)]
+pub struct QueueRequest {
+    /// Tracks to queue
+    #[serde(default)]
+    pub track_ids: Vec<u64>,
+    /// Queue all tracks of an album, after the tracks given in `track_ids`
+    pub album_id: Option<String>,
+    /// Stream quality, the configured default if not set
+    pub quality: Option<QobuzQuality>,
+    /// "add" (end of the queue, default), "next" (after the current track) or "now" (replace the queue and play)
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(error: QobuzError) -> ApiError {
+    let status = match error {
+        QobuzError::NotConfigured(_) => Status::ServiceUnavailable,
+        QobuzError::AuthError(_) => Status::Unauthorized,
+        QobuzError::NotFound(_) => Status::NotFound,
+        QobuzError::ApiError(_) => Status::BadGateway,
+        QobuzError::StoreError(_) => Status::InternalServerError,
+    };
+    error_with_status(status, error.to_string())
+}
+
+fn error_with_status(status: Status, message: String) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message,
+    }))
+}
+
+/// Get the Qobuz configuration and login status
+#[get("/status")]
+pub fn get_status() -> Json<QobuzStatusResponse> {
+    let logged_in = qobuz::is_logged_in();
+    Json(QobuzStatusResponse {
+        enabled: qobuz::is_enabled(),
+        logged_in,
+        username: if logged_in { qobuz::get_username() } else { None },
+        quality: qobuz::default_quality(),
+    })
+}
+
+/// Log in to Qobuz, only the authentication token is stored
+#[post("/login", data = "<request>")]
+pub fn login(request: Json<LoginRequest>) -> Result<Json<MessageResponse>, ApiError> {
+    info!("API request: Qobuz login for {}", request.username);
+    qobuz::login(&request.username, &request.password).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Logged in as {}", request.username),
+    }))
+}
+
+/// Log out from Qobuz
+#[post("/logout")]
+pub fn logout() -> Result<Json<MessageResponse>, ApiError> {
+    qobuz::logout().map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: "Logged out".to_string(),
+    }))
+}
+
+/// Search albums, artists and tracks
+///
+/// # Parameters
+/// * `query` - Search string
+/// * `limit` - Maximum number of results per type (default 20)
+#[get("/search?<query>&<limit>")]
+pub fn search(query: &str, limit: Option<u32>) -> Result<Json<QobuzSearchResult>, ApiError> {
+    qobuz::search(query, limit.unwrap_or(20).clamp(1, 100)).map(Json).map_err(error_response)
+}
+
+/// Get an album with its tracks
+#[get("/album/<album_id>")]
+pub fn get_album(album_id: &str) -> Result<Json<QobuzAlbum>, ApiError> {
+    qobuz::get_album(album_id).map(Json).map_err(error_response)
+}
+
+/// Get an artist with its albums
+#[get("/artist/<artist_id>")]
+pub fn get_artist(artist_id: u64) -> Result<Json<QobuzArtist>, ApiError> {
+    qobuz::get_artist(artist_id).map(Json).map_err(error_response)
+}
+
+/// Get a single track
+#[get("/track/<track_id>")]
+pub fn get_track(track_id: u64) -> Result<Json<QobuzTrack>, ApiError> {
+    qobuz::get_track(track_id).map(Json).map_err(error_response)
+}
+
+fn parse_quality(quality: Option<&str>) -> Result<Option<QobuzQuality>, ApiError> {
+    match quality {
+        None => Ok(None),
+        Some(q) => serde_json::from_value(serde_json::Value::from(q))
+            .map(Some)
+            .map_err(|_| error_with_status(Status::BadRequest, format!("Invalid quality: {}", q))),
+    }
+}
+
+/// Get a stream URL for a track
+///
+/// # Parameters
+/// * `quality` - mp3, cd, hi_res or hi_res_max (default from the configuration)
+#[get("/track/<track_id>/url?<quality>")]
+pub fn get_stream_url(track_id: u64, quality: Option<&str>) -> Result<Json<StreamUrlResponse>, ApiError> {
+    let quality = parse_quality(quality)?.unwrap_or_else(qobuz::default_quality);
+    let url = qobuz::get_stream_url(track_id, Some(quality)).map_err(error_response)?;
+    Ok(Json(StreamUrlResponse { track_id, quality, url }))
+}
+
+/// Queue Qobuz tracks on a player
+///
+/// Stream URLs are requested for all tracks and sent to the player with the track metadata.
+/// The URLs expire after some time, so large queues may not play to the end.
+#[post("/queue/<player_name>", data = "<request>")]
+pub fn queue_tracks(
+    player_name: &str,
+    request: Json<QueueRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let request = request.into_inner();
+    let player = controller
+        .get_player_by_name(player_name)
+        .ok_or_else(|| error_with_status(Status::NotFound, format!("No player found with name: {}", player_name)))?;
+
+    let mut tracks = Vec::new();
+    for track_id in &request.track_ids {
+        tracks.push(qobuz::get_track(*track_id).map_err(error_response)?);
+    }
+    if let Some(album_id) = &request.album_id {
+        tracks.extend(qobuz::get_album(album_id).map_err(error_response)?.tracks);
+    }
+    if tracks.is_empty() {
+        return Err(error_with_status(Status::BadRequest, "No tracks given".to_string()));
+    }
+
+    let mut uris = Vec::new();
+    let mut metadata = Vec::new();
+    for track in tracks.iter().filter(|t| t.streamable) {
+        match qobuz::get_stream_url(track.id, request.quality) {
+            Ok(url) => {
+                uris.push(url);
+                metadata.push(Some(qobuz::queue_metadata(track)));
+            }
+            Err(e @ (QobuzError::AuthError(_) | QobuzError::NotConfigured(_))) => return Err(error_response(e)),
+            Err(e) => warn!("No stream URL for Qobuz track {}: {}", track.id, e),
+        }
+    }
+    if uris.is_empty() {
+        return Err(error_with_status(Status::NotFound, "None of the tracks can be streamed".to_string()));
+    }
+
+    let count = uris.len();
+    let command = match request.mode.as_deref().unwrap_or("add") {
+        "add" => PlayerCommand::QueueTracks { uris, insert_at_beginning: false, insert_after_current: false, metadata },
+        "next" => PlayerCommand::QueueTracks { uris, insert_at_beginning: false, insert_after_current: true, metadata },
+        "now" => PlayerCommand::PlayNow { uris, metadata, start_index: 0 },
+        mode => return Err(error_with_status(Status::BadRequest, format!("Invalid mode: {}", mode))),
+    };
+
+    info!("API request: queue {} Qobuz tracks on {}", count, player_name);
+    if player.read().send_command(command) {
+        Ok(Json(MessageResponse {
+            success: true,
+            message: format!("{} tracks queued on {}", count, player_name),
+        }))
+    } else {
+        Err(error_with_status(Status::InternalServerError, format!("Failed to queue tracks on {}", player_name)))
+    }
+}
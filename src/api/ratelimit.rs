@@ -0,0 +1,9 @@
+use crate::helpers::ratelimit::{self, ServiceQuota};
+use rocket::get;
+use rocket::serde::json::Json;
+
+/// Get the rate limits and the remaining daily quota of external services
+#[get("/quota")]
+pub fn get_quotas() -> Json<Vec<ServiceQuota>> {
+    Json(ratelimit::get_quotas())
+}
@@ -1,16 +1,18 @@
 use crate::AudioController;
 use crate::api::{
     players, plugins, library, imagecache, coverart, events, lastfm, spotify,
-    theaudiodb, favourites, volume, lyrics, m3u, settings, cache, backgroundjobs, genres,
+    theaudiodb, favourites, volume, lyrics, m3u, settings, cache, backgroundjobs, genres, songsplitter, streamcheck, stationgain, ratelimit, proxy, mounts, usbstorage, idle, system, diagnostics, notifications, playbacklimits, cd, qobuz, hqplayer, artist, album, mixes, suggestions, playlists, outputs, priority, webdav, bookmarks, history, dsp, tts, device, federation, auth, health, openapi, graphql,
     inputs
 };
 use crate::api::events::WebSocketManager;
 use crate::config::get_service_config;
 use crate::constants::API_PREFIX;
+use crate::helpers::tls;
 use crate::players::{player_event_update};
  
-use log::{info, warn};
-use rocket::{routes, get};
+use log::{error, info, warn};
+use rocket::{routes, get, Build, Rocket};
+use rocket::figment::Figment;
 use rocket::serde::json::Json;
 use rocket::config::Config;
 use rocket::fs::FileServer;
@@ -47,14 +49,22 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
     let host = get_service_config(config_json, "webserver")
         .and_then(|ws| ws.get("host"))
         .and_then(|h| h.as_str())
+        .map(crate::helpers::netaddr::strip_brackets)
         .unwrap_or("0.0.0.0");
+
+    // Listen on "::" instead of all IPv4 addresses, this accepts IPv4 and IPv6 connections
+    let dual_stack = get_service_config(config_json, "webserver")
+        .and_then(|ws| ws.get("dual_stack"))
+        .and_then(|d| d.as_bool())
+        .unwrap_or(false);
+    let host = if dual_stack && host == "0.0.0.0" { "::" } else { host };
         
     let port = get_service_config(config_json, "webserver")
         .and_then(|ws| ws.get("port"))
         .and_then(|p| p.as_u64())
         .unwrap_or(1080);
     
-    info!("Starting webserver on {}:{}", host, port);
+    info!("Starting webserver on {}", crate::helpers::netaddr::host_port(host, port as u16));
     
     let config = Config::figment()
         .merge(("port", port))
@@ -64,8 +74,42 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
     let ws_manager = Arc::new(WebSocketManager::new());
     events::start_prune_task(ws_manager.clone());
     
+    let http = build_rocket(config, controller.clone(), ws_manager.clone(), config_json);
+
+    // Serve HTTPS on another port, players and other clients on the LAN keep using HTTP
+    match tls::prepare_from_config(config_json) {
+        Ok(Some(tls_config)) => {
+            info!("Starting HTTPS webserver on {}", crate::helpers::netaddr::host_port(host, tls_config.port));
+            let https_config = Config::figment()
+                .merge(("port", tls_config.port))
+                .merge(("address", host))
+                .merge(("tls.certs", &tls_config.certificate))
+                .merge(("tls.key", &tls_config.key));
+            let https = build_rocket(https_config, controller, ws_manager, config_json);
+            rocket::tokio::try_join!(http.launch(), https.launch())?;
+        }
+        Ok(None) => {
+            http.launch().await?;
+        }
+        Err(e) => {
+            error!("HTTPS is not available: {}", e);
+            http.launch().await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Mount all routes and static files
+fn build_rocket(
+    config: Figment,
+    controller: Arc<AudioController>,
+    ws_manager: Arc<WebSocketManager>,
+    config_json: &serde_json::Value,
+) -> Rocket<Build> {
     let api_routes = routes![
         get_version,
+        openapi::get_openapi,
         
         // Player routes
         players::get_current_player,
@@ -73,6 +117,9 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         players::send_command_to_player_by_name,
         players::get_now_playing,
         players::get_player_queue,
+        players::move_queue_track,
+        players::activate_player,
+        players::set_rating,
         players::get_player_metadata,      
         players::get_player_metadata_key,
         players::pause_all_players,
@@ -90,13 +137,22 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         library::get_albums_by_artist_id,
         library::refresh_player_library,
         library::update_player_library,
+        library::start_cover_writeback,
+        library::get_cover_writeback,
         library::get_artist_by_name,
         library::get_artist_by_id,
         library::get_artist_by_mbid,
         library::get_image,
         library::get_library_metadata,
         library::get_library_metadata_key,
+        library::get_library_changes,
         library::get_library_genres,
+        library::search_library,
+        library::search_all_libraries,
+        library::export_libraries,
+        library::import_libraries,
+        library::queue_album,
+        library::queue_artist,
         library::get_albums_by_genre,
         library::get_artists_by_genre,
         library::get_library_categories,
@@ -110,7 +166,9 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         
         // WebSocket routes
         events::event_messages,
+        events::live_event_messages,
         events::player_event_messages,
+        events::get_event_schemas,
         
         // Generic player API endpoints
         player_event_update,
@@ -141,6 +199,10 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         coverart::get_coverart_methods,
         coverart::update_artist_image,
         coverart::get_artist_image,
+        coverart::download_album_artwork,
+        coverart::download_artist_artwork,
+        coverart::get_album_artwork_source,
+        coverart::get_artist_artwork_source,
     ];
 
     // Define Last.fm specific routes
@@ -184,6 +246,8 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         spotify::get_playback,
         spotify::spotify_currently_playing,
         spotify::spotify_search,
+        spotify::get_devices,
+        spotify::transfer_playback,
         spotify::get_access_token
     ];
     
@@ -192,6 +256,11 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         imagecache::get_image_from_cache
     ];
     
+    // Image proxy routes
+    let proxy_routes = routes![
+        proxy::get_proxied_image
+    ];
+    
     // Favourites routes
     let favourites_routes = favourites::routes();
     
@@ -199,6 +268,7 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
     let lyrics_routes = routes![
         lyrics::get_lyrics_by_id,
         lyrics::get_lyrics_by_metadata,
+        lyrics::stream_lyrics,
     ];
     
     // M3U routes
@@ -215,12 +285,20 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
     // Cache routes
     let cache_routes = routes![
         cache::get_cache_statistics,
+        cache::get_service_statistics,
+        cache::list_cache_entries,
+        cache::get_cache_entry,
+        cache::delete_cache_entry,
+        cache::delete_cache_entries,
+        cache::purge_service_cache,
+        cache::cleanup_caches,
     ];
     
     // Background jobs routes
     let backgroundjobs_routes = routes![
         backgroundjobs::get_background_jobs,
         backgroundjobs::get_background_job,
+        backgroundjobs::cancel_background_job,
     ];
 
     // Genre config routes
@@ -233,6 +311,235 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         genres::post_ignore,
         genres::delete_ignore,
     ];
+
+    // Song title splitter routes
+    let songsplitter_routes = routes![
+        songsplitter::list_splitters,
+        songsplitter::get_splitter,
+        songsplitter::override_splitter,
+        songsplitter::reset_splitter,
+    ];
+
+    // Stream URL check routes
+    let streamcheck_routes = routes![
+        streamcheck::get_stream_status,
+        streamcheck::start_stream_check,
+    ];
+
+    // Define network share routes
+    let mounts_routes = routes![
+        mounts::list_shares,
+        mounts::add_share,
+        mounts::get_share_status,
+        mounts::mount_share,
+        mounts::unmount_share,
+        mounts::remove_share,
+    ];
+
+    // Define rate limit routes
+    let ratelimit_routes = routes![
+        ratelimit::get_quotas,
+    ];
+
+    // Define radio preset gain routes
+    let stationgain_routes = routes![
+        stationgain::list_presets,
+        stationgain::set_offset,
+        stationgain::get_suggestions,
+    ];
+
+    // Define USB storage routes
+    let usbstorage_routes = routes![
+        usbstorage::list_devices,
+    ];
+
+    // Define idle routes
+    let idle_routes = routes![
+        idle::get_status,
+        idle::wake,
+    ];
+
+    // Define system telemetry routes
+    let system_routes = routes![
+        system::get_status,
+    ];
+
+    // Define network diagnostics routes
+    let diagnostics_routes = routes![
+        diagnostics::get_network_diagnostics,
+        diagnostics::get_stalls,
+    ];
+
+    // Define notification routes
+    let notifications_routes = routes![
+        notifications::get_status,
+        notifications::test,
+        notifications::send,
+    ];
+
+    // Define playback limits routes
+    let playbacklimits_routes = routes![
+        playbacklimits::get_status,
+        playbacklimits::set_override,
+        playbacklimits::clear_override,
+    ];
+
+    // Define CD routes
+    let cd_routes = routes![
+        cd::get_status,
+        cd::play,
+        cd::rip,
+        cd::eject,
+    ];
+
+    // Define Qobuz routes
+    let qobuz_routes = routes![
+        qobuz::get_status,
+        qobuz::login,
+        qobuz::logout,
+        qobuz::search,
+        qobuz::get_album,
+        qobuz::get_artist,
+        qobuz::get_track,
+        qobuz::get_stream_url,
+        qobuz::queue_tracks,
+    ];
+
+    // Define HQPlayer routes
+    let hqplayer_routes = routes![
+        hqplayer::get_pipeline,
+        hqplayer::set_pipeline,
+    ];
+
+    // Define artist details routes
+    let artist_routes = routes![
+        artist::get_artist,
+        artist::get_conflicts,
+        artist::post_resolve,
+        artist::delete_resolve,
+    ];
+
+    // Define album details routes
+    let album_routes = routes![
+        album::get_album,
+    ];
+
+    // Define daily mix routes
+    let mixes_routes = routes![
+        mixes::list_mixes,
+        mixes::get_mix,
+        mixes::queue_mix,
+        mixes::regenerate,
+    ];
+
+    // Define suggestion routes
+    let suggestions_routes = routes![
+        suggestions::get_suggestions,
+    ];
+
+    // Define stored playlist routes
+    let playlists_routes = routes![
+        playlists::list_playlists,
+        playlists::get_playlist,
+        playlists::create_playlist,
+        playlists::replace_playlist,
+        playlists::add_to_playlist,
+        playlists::load_playlist,
+        playlists::save_playlist,
+        playlists::delete_playlist,
+    ];
+
+    // Define MPD audio output routes
+    let outputs_routes = routes![
+        outputs::list_outputs,
+        outputs::enable_output,
+        outputs::disable_output,
+    ];
+
+    // Define source priority routes
+    let priority_routes = routes![
+        priority::get_status,
+        priority::delete_pin,
+    ];
+
+    // Define WebDAV source routes
+    let webdav_routes = routes![
+        webdav::list_sources,
+        webdav::add_source,
+        webdav::list_files,
+        webdav::refresh_source,
+        webdav::remove_source,
+        webdav::stream_file,
+    ];
+
+    // Define bookmark routes
+    let bookmarks_routes = routes![
+        bookmarks::list_bookmarks,
+        bookmarks::get_player_bookmarks,
+        bookmarks::add_bookmark,
+        bookmarks::resume_bookmark,
+        bookmarks::remove_player_bookmark,
+        bookmarks::remove_track_bookmark,
+    ];
+
+    // Define listening history routes
+    let history_routes = routes![
+        history::get_recent,
+        history::get_artist_play_counts,
+        history::get_top,
+    ];
+
+    // Define DSP profile routes
+    let dsp_routes = routes![
+        dsp::get_status,
+        dsp::set_override,
+        dsp::clear_override,
+    ];
+
+    // Define text-to-speech routes
+    let tts_routes = routes![
+        tts::get_status,
+        tts::render,
+        tts::get_clip,
+        tts::clear_cache,
+    ];
+
+    let device_routes = routes![
+        device::get_device,
+        device::set_name,
+        device::set_name_post,
+    ];
+
+    let federation_routes = routes![
+        federation::list_peers,
+        federation::list_players,
+        federation::proxy_get,
+        federation::proxy_post,
+        federation::proxy_put,
+        federation::proxy_delete,
+    ];
+
+    let auth_routes = routes![
+        auth::get_status,
+        auth::list_tokens,
+        auth::create_token,
+        auth::revoke_token,
+        auth::unauthorized,
+        auth::forbidden,
+    ];
+
+    let health_routes = routes![
+        health::get_health,
+        health::get_readiness,
+    ];
+
+    let graphql_routes = routes![
+        graphql::post_query,
+        graphql::get_query,
+        graphql::get_schema,
+    ];
+    // The GraphQL API is optional, its routes are only mounted if it is enabled
+    let graphql_schema = graphql::schema_from_config(config_json, controller.clone());
       let mut rocket_builder = rocket::custom(config)
         .mount(API_PREFIX, api_routes) // Use API_PREFIX here when mounting general api routes
         .mount(format!("{}/lastfm", API_PREFIX), lastfm_routes) // Mount Last.fm routes under /api/lastfm (or similar)
@@ -241,6 +548,7 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
             if spotify_api_enabled { spotify_full_routes } else { spotify_auth_routes }
         )
         .mount(format!("{}/imagecache", API_PREFIX), imagecache_routes) // Mount imagecache routes
+        .mount(format!("{}/proxy", API_PREFIX), proxy_routes) // Mount image proxy routes
         .mount(format!("{}/favourites", API_PREFIX), favourites_routes) // Mount favourites routes
         .mount(format!("{}/lyrics", API_PREFIX), lyrics_routes) // Mount lyrics routes
         .mount(format!("{}/m3u", API_PREFIX), m3u_routes) // Mount M3U routes
@@ -248,11 +556,47 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
         .mount(format!("{}/cache", API_PREFIX), cache_routes) // Mount cache routes
         .mount(format!("{}/background", API_PREFIX), backgroundjobs_routes) // Mount background jobs routes
         .mount(format!("{}/genres", API_PREFIX), genres_routes) // Mount genre config routes
+        .mount(format!("{}/songsplitter", API_PREFIX), songsplitter_routes) // Mount song title splitter routes
+        .mount(format!("{}/streamcheck", API_PREFIX), streamcheck_routes) // Mount stream URL check routes
+        .mount(format!("{}/mounts", API_PREFIX), mounts_routes) // Mount network share routes
+        .mount(format!("{}/ratelimit", API_PREFIX), ratelimit_routes) // Mount rate limit quota routes
+        .mount(format!("{}/stationgain", API_PREFIX), stationgain_routes) // Mount radio preset gain routes
+        .mount(format!("{}/usbstorage", API_PREFIX), usbstorage_routes) // Mount USB storage routes
+        .mount(format!("{}/idle", API_PREFIX), idle_routes) // Mount idle policy routes
+        .mount(format!("{}/system", API_PREFIX), system_routes) // Mount system telemetry routes
+        .mount(format!("{}/diagnostics", API_PREFIX), diagnostics_routes) // Mount network diagnostics routes
+        .mount(format!("{}/notifications", API_PREFIX), notifications_routes) // Mount notification routes
+        .mount(format!("{}/playbacklimits", API_PREFIX), playbacklimits_routes) // Mount playback limits routes
+        .mount(format!("{}/cd", API_PREFIX), cd_routes) // Mount CD routes
+        .mount(format!("{}/qobuz", API_PREFIX), qobuz_routes) // Mount Qobuz routes
+        .mount(format!("{}/hqplayer", API_PREFIX), hqplayer_routes) // Mount HQPlayer pipeline routes
+        .mount(format!("{}/artist", API_PREFIX), artist_routes) // Mount artist details routes
+        .mount(format!("{}/album", API_PREFIX), album_routes) // Mount album details routes
+        .mount(format!("{}/mixes", API_PREFIX), mixes_routes) // Mount daily mix routes
+        .mount(format!("{}/suggestions", API_PREFIX), suggestions_routes) // Mount suggestion routes
+        .mount(format!("{}/playlists", API_PREFIX), playlists_routes) // Mount stored playlist routes
+        .mount(format!("{}/outputs", API_PREFIX), outputs_routes) // Mount MPD audio output routes
+        .mount(format!("{}/priority", API_PREFIX), priority_routes) // Mount source priority routes
+        .mount(format!("{}/webdav", API_PREFIX), webdav_routes) // Mount WebDAV source routes
+        .mount(format!("{}/bookmarks", API_PREFIX), bookmarks_routes) // Mount bookmark routes
+        .mount(format!("{}/history", API_PREFIX), history_routes) // Mount listening history routes
+        .mount(format!("{}/dsp", API_PREFIX), dsp_routes) // Mount DSP profile routes
+        .mount(format!("{}/tts", API_PREFIX), tts_routes) // Mount text-to-speech routes
+        .mount(format!("{}/device", API_PREFIX), device_routes) // Mount device identity routes
+        .mount(format!("{}/federation", API_PREFIX), federation_routes) // Mount federation routes
+        .mount(format!("{}/auth", API_PREFIX), auth_routes) // Mount API token routes
+        .mount(format!("{}/health", API_PREFIX), health_routes) // Mount health check routes
         .mount(format!("{}/volume", API_PREFIX), volume_routes) // Mount volume routes
         .mount(format!("{}/inputs", API_PREFIX), inputs_routes) // Mount inputs status routes
         .mount(format!("{}/coverart", API_PREFIX), coverart_routes) // Mount coverart routes
         .manage(controller)
-        .manage(ws_manager); // Add WebSocket manager as managed state
+        .manage(ws_manager) // Add WebSocket manager as managed state
+        .attach(auth::ApiAuth); // Reject API requests without a token of the required scope
+    if let Some(schema) = graphql_schema {
+        rocket_builder = rocket_builder
+            .mount(format!("{}/graphql", API_PREFIX), graphql_routes) // Mount GraphQL routes
+            .manage(schema);
+    }
       // Check for static file routes in the configuration
     if let Some(static_routes) = get_service_config(config_json, "webserver")
         .and_then(|ws| ws.get("static_routes"))
@@ -269,8 +613,8 @@ pub async fn start_rocket_server(controller: Arc<AudioController>, config_json:
             }
         }
     }
-    
-    let _rocket = rocket_builder.launch().await?;
-    
-    Ok(())
+
+    // Document the mounted routes, this must be the last change to the routes
+    let document = openapi::OpenApiDocument::generate(rocket_builder.routes());
+    rocket_builder.manage(document)
 }
\ No newline at end of file
@@ -0,0 +1,252 @@
+use crate::AudioController;
+use crate::helpers::songsplitmanager::SongSplitManager;
+use crate::helpers::songtitlesplitter::{OrderResult, SongTitleSplitter};
+use crate::players::mpd::MPDPlayerController;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use log::{debug, info};
+
+/// Statistics and settings of a single song title splitter
+#[derive(Serialize, Deserialize)]
+pub struct SplitterInfo {
+    /// Splitter ID, this is the radio station URL
+    pub id: String,
+    pub artist_song_count: u32,
+    pub song_artist_count: u32,
+    pub unknown_count: u32,
+    pub undecided_count: u32,
+    /// Order used for all titles, either learned or set manually
+    pub default_order: Option<OrderResult>,
+    /// Separator tried first, either learned or set manually
+    pub default_separator: Option<char>,
+    /// Number of successful splits per separator
+    pub separator_stats: HashMap<char, u32>,
+}
+
+impl From<&SongTitleSplitter> for SplitterInfo {
+    fn from(splitter: &SongTitleSplitter) -> Self {
+        Self {
+            id: splitter.get_id().to_string(),
+            artist_song_count: splitter.get_artist_song_count(),
+            song_artist_count: splitter.get_song_artist_count(),
+            unknown_count: splitter.get_unknown_count(),
+            undecided_count: splitter.get_undecided_count(),
+            default_order: splitter.get_default_order(),
+            default_separator: splitter.get_default_separator(),
+            separator_stats: splitter.get_separator_stats(),
+        }
+    }
+}
+
+/// Response structure for the splitter list
+#[derive(Serialize, Deserialize)]
+pub struct SplitterListResponse {
+    pub success: bool,
+    pub splitters: Vec<SplitterInfo>,
+}
+
+/// Response structure for a single splitter
+#[derive(Serialize, Deserialize)]
+pub struct SplitterResponse {
+    pub success: bool,
+    pub splitter: SplitterInfo,
+}
+
+/// Request structure to select a splitter
+///
+/// Station URLs are sent in the body as they contain characters that are
+/// awkward in URL paths.
+#[derive(Deserialize, Serialize)]
+pub struct SplitterRequest {
+    pub id: String,
+}
+
+/// Request structure to override the learned settings of a splitter
+///
+/// Fields that are not given are left unchanged. "auto" returns to automatic detection.
+#[derive(Deserialize, Serialize)]
+pub struct SplitterOverrideRequest {
+    pub id: String,
+    /// "artist_song", "song_artist" or "auto"
+    pub order: Option<String>,
+    /// "-", "/", ":" or "auto"
+    pub separator: Option<String>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_response(status: Status, message: impl Into<String>) -> ApiError {
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: message.into(),
+    }))
+}
+
+/// Find the song split manager of the first MPD player
+fn find_split_manager(controller: &AudioController) -> Result<SongSplitManager, ApiError> {
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            return Ok(mpd.get_song_split_manager());
+        }
+    }
+    Err(error_response(Status::NotFound, "No MPD player configured, title splitting is not available"))
+}
+
+fn parse_order(order: &str) -> Result<Option<OrderResult>, ApiError> {
+    match order {
+        "artist_song" => Ok(Some(OrderResult::ArtistSong)),
+        "song_artist" => Ok(Some(OrderResult::SongArtist)),
+        "auto" => Ok(None),
+        _ => Err(error_response(
+            Status::BadRequest,
+            format!("Invalid order '{}', use artist_song, song_artist or auto", order),
+        )),
+    }
+}
+
+fn parse_separator(separator: &str) -> Result<Option<char>, ApiError> {
+    if separator == "auto" {
+        return Ok(None);
+    }
+    let mut chars = separator.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if crate::helpers::songtitlesplitter::is_supported_separator(c) => Ok(Some(c)),
+        _ => Err(error_response(
+            Status::BadRequest,
+            format!("Invalid separator '{}', use -, /, : or auto", separator),
+        )),
+    }
+}
+
+/// List all song title splitters with their statistics
+#[get("/list")]
+pub fn list_splitters(controller: &State<Arc<AudioController>>) -> Result<Json<SplitterListResponse>, ApiError> {
+    debug!("API request: list song title splitters");
+    let manager = find_split_manager(controller.inner())?;
+
+    let mut splitters: Vec<SplitterInfo> = manager
+        .get_splitter_ids()
+        .iter()
+        .filter_map(|id| manager.get_splitter(id))
+        .map(|splitter| SplitterInfo::from(&splitter))
+        .collect();
+    splitters.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(Json(SplitterListResponse {
+        success: true,
+        splitters,
+    }))
+}
+
+/// Get the statistics of the splitter for a station
+#[post("/get", data = "<request>")]
+pub fn get_splitter(
+    request: Json<SplitterRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<SplitterResponse>, ApiError> {
+    debug!("API request: get song title splitter '{}'", request.id);
+    let manager = find_split_manager(controller.inner())?;
+
+    match manager.get_splitter(&request.id) {
+        Some(splitter) => Ok(Json(SplitterResponse {
+            success: true,
+            splitter: SplitterInfo::from(&splitter),
+        })),
+        None => Err(error_response(Status::NotFound, format!("No splitter found for '{}'", request.id))),
+    }
+}
+
+/// Manually set the order and/or separator for a station
+///
+/// This can be used for stations that send "Song - Artist" titles, which
+/// would otherwise be mis-split until enough titles have been learned.
+#[post("/override", data = "<request>")]
+pub fn override_splitter(
+    request: Json<SplitterOverrideRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<SplitterResponse>, ApiError> {
+    let order = request.order.as_deref().map(parse_order).transpose()?;
+    let separator = request.separator.as_deref().map(parse_separator).transpose()?;
+    if order.is_none() && separator.is_none() {
+        return Err(error_response(Status::BadRequest, "Nothing to change, give order and/or separator"));
+    }
+
+    let manager = find_split_manager(controller.inner())?;
+    let splitter = manager
+        .update_splitter(&request.id, |splitter| {
+            if let Some(order) = order {
+                splitter.set_default_order(order);
+            }
+            if let Some(separator) = separator {
+                splitter.set_default_separator(separator);
+            }
+            splitter.clone()
+        })
+        .map_err(|e| error_response(Status::InternalServerError, e))?;
+
+    info!(
+        "Song title splitter for '{}' overridden: order={:?}, separator={:?}",
+        request.id,
+        splitter.get_default_order(),
+        splitter.get_default_separator()
+    );
+
+    Ok(Json(SplitterResponse {
+        success: true,
+        splitter: SplitterInfo::from(&splitter),
+    }))
+}
+
+/// Reset everything that has been learned for a station, including overrides
+#[post("/reset", data = "<request>")]
+pub fn reset_splitter(
+    request: Json<SplitterRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Result<Json<SplitterResponse>, ApiError> {
+    let manager = find_split_manager(controller.inner())?;
+
+    if !manager.reset_splitter(&request.id) {
+        return Err(error_response(Status::NotFound, format!("No splitter found for '{}'", request.id)));
+    }
+
+    match manager.get_splitter(&request.id) {
+        Some(splitter) => Ok(Json(SplitterResponse {
+            success: true,
+            splitter: SplitterInfo::from(&splitter),
+        })),
+        None => Err(error_response(Status::InternalServerError, "Splitter disappeared after reset")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_order() {
+        assert_eq!(parse_order("song_artist").ok(), Some(Some(OrderResult::SongArtist)));
+        assert_eq!(parse_order("auto").ok(), Some(None));
+        assert!(parse_order("unknown").is_err());
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!(parse_separator("/").ok(), Some(Some('/')));
+        assert_eq!(parse_separator("auto").ok(), Some(None));
+        assert!(parse_separator("|").is_err());
+        assert!(parse_separator("--").is_err());
+    }
+}
@@ -7,7 +7,7 @@ use log::{error, info};
 use std::time::{SystemTime, UNIX_EPOCH};
 use serde_json::json;
 
-use crate::helpers::spotify::{Spotify, SpotifyTokens};
+use crate::helpers::spotify::{Spotify, SpotifyDevice, SpotifyTokens};
 use crate::helpers::http_client::new_http_client;
 use rocket::http::{Status};
 use rocket::response::content;
@@ -45,6 +45,25 @@ pub struct CreateSessionResponse {
     session_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectDevice {
+    #[serde(flatten)]
+    pub device: SpotifyDevice,
+    /// Whether this is the librespot instance running on this system
+    pub is_local: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectDevicesResponse {
+    pub devices: Vec<ConnectDevice>,
+    /// Spotify Connect name of this system
+    pub local_device_name: String,
+    /// Where Spotify is playing: "local", "remote" or "none"
+    pub playback_location: String,
+    /// Name of the active device
+    pub active_device: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
@@ -378,6 +397,71 @@ pub fn spotify_command(command: &str, args: Json<Value>) -> Json<ApiResponse> {
     }
 }
 
+/// List the user's Spotify Connect devices
+///
+/// Shows whether Spotify is playing on this system, on another device or not at all.
+#[get("/devices")]
+pub fn get_devices() -> Result<Json<ConnectDevicesResponse>, Status> {
+    let spotify = Spotify::new();
+
+    if let Err(e) = spotify.ensure_valid_token() {
+        error!("Failed to get valid Spotify token: {}", e);
+        return Err(Status::Unauthorized);
+    }
+
+    let devices = spotify.get_devices().map_err(|e| {
+        error!("Error getting Spotify devices: {}", e);
+        Status::InternalServerError
+    })?;
+
+    let devices: Vec<ConnectDevice> = devices
+        .into_iter()
+        .map(|device| ConnectDevice {
+            is_local: spotify.is_local_device(&device),
+            device,
+        })
+        .collect();
+
+    let active = devices.iter().find(|d| d.device.is_active);
+    let playback_location = match active {
+        Some(d) if d.is_local => "local",
+        Some(_) => "remote",
+        None => "none",
+    };
+
+    Ok(Json(ConnectDevicesResponse {
+        playback_location: playback_location.to_string(),
+        active_device: active.map(|d| d.device.name.clone()),
+        local_device_name: spotify.local_device_name(),
+        devices,
+    }))
+}
+
+/// Transfer Spotify playback to another Connect device
+///
+/// # Parameters
+/// * `device_id` - ID of the target device
+/// * `play` - Start playback on the target device (default: keep the current state)
+#[post("/devices/<device_id>/transfer?<play>")]
+pub fn transfer_playback(device_id: &str, play: Option<bool>) -> Json<ApiResponse> {
+    let spotify = Spotify::new();
+    match spotify.transfer_playback(device_id, play.unwrap_or(false)) {
+        Ok(_) => Json(ApiResponse {
+            status: "success".to_string(),
+            message: format!("Playback transferred to device {}", device_id),
+            expires_at: None,
+        }),
+        Err(e) => {
+            error!("Spotify transfer error: {}", e);
+            Json(ApiResponse {
+                status: "error".to_string(),
+                message: format!("Transfer failed: {}", e),
+                expires_at: None,
+            })
+        }
+    }
+}
+
 /// Get currently playing track information
 #[get("/currently_playing")]
 pub fn spotify_currently_playing() -> Json<Value> {
@@ -0,0 +1,53 @@
+use crate::helpers::stationgain::{self, GainSuggestion, StationGain};
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use serde::{Deserialize, Serialize};
+
+/// Request to set the gain offset of a radio preset
+#[derive(Serialize, Deserialize)]
+pub struct SetOffsetRequest {
+    pub url: String,
+    pub name: Option<String>,
+    pub offset_db: f64,
+}
+
+/// Response structure for the radio preset list
+#[derive(Serialize, Deserialize)]
+pub struct PresetsResponse {
+    /// Whether gain offsets are applied
+    pub enabled: bool,
+    pub presets: Vec<StationGain>,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// List radio presets with their gain offsets
+#[get("/presets")]
+pub fn list_presets() -> Json<PresetsResponse> {
+    Json(PresetsResponse {
+        enabled: stationgain::is_enabled(),
+        presets: stationgain::list_presets(),
+    })
+}
+
+/// Set the gain offset of a radio preset
+#[post("/presets", data = "<request>")]
+pub fn set_offset(request: Json<SetOffsetRequest>) -> Result<Json<StationGain>, Custom<Json<ErrorResponse>>> {
+    let request = request.into_inner();
+    stationgain::set_offset(&request.url, request.name, request.offset_db)
+        .map(Json)
+        .map_err(|message| Custom(Status::BadRequest, Json(ErrorResponse { success: false, message })))
+}
+
+/// Suggest gain offsets from the volume levels used while listening
+#[get("/suggestions")]
+pub fn get_suggestions() -> Json<Vec<GainSuggestion>> {
+    Json(stationgain::get_suggestions())
+}
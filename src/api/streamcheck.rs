@@ -0,0 +1,65 @@
+use crate::helpers::streamcheck::{self, StreamCheckResult};
+use rocket::serde::json::Json;
+use rocket::{get, post};
+use serde::{Deserialize, Serialize};
+use log::debug;
+
+/// Response structure for stream check results
+#[derive(Serialize, Deserialize)]
+pub struct StreamCheckResponse {
+    pub success: bool,
+    /// Whether a check is currently running
+    pub running: bool,
+    pub total: usize,
+    /// Number of stream URLs that are not working
+    pub dead: usize,
+    pub streams: Vec<StreamCheckResult>,
+}
+
+/// Response structure for starting a check
+#[derive(Serialize, Deserialize)]
+pub struct StartCheckResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Get the results of the last stream URL check
+///
+/// With `dead_only=true` only streams that are not working are returned.
+#[get("/status?<dead_only>")]
+pub fn get_stream_status(dead_only: Option<bool>) -> Json<StreamCheckResponse> {
+    debug!("API request: get stream check status");
+
+    let results = streamcheck::get_results();
+    let total = results.len();
+    let dead = results.iter().filter(|r| !r.ok).count();
+    let streams = if dead_only.unwrap_or(false) {
+        results.into_iter().filter(|r| !r.ok).collect()
+    } else {
+        results
+    };
+
+    Json(StreamCheckResponse {
+        success: true,
+        running: streamcheck::is_running(),
+        total,
+        dead,
+        streams,
+    })
+}
+
+/// Start checking all saved stream URLs now
+#[post("/check")]
+pub fn start_stream_check() -> Json<StartCheckResponse> {
+    if streamcheck::start_check() {
+        Json(StartCheckResponse {
+            success: true,
+            message: "Stream check started".to_string(),
+        })
+    } else {
+        Json(StartCheckResponse {
+            success: false,
+            message: "Stream check is already running".to_string(),
+        })
+    }
+}
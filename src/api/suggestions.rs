@@ -0,0 +1,12 @@
+use crate::helpers::suggestions::{self, Suggestions};
+use rocket::get;
+use rocket::serde::json::Json;
+
+/// Get forgotten favourites and songs played on this day in earlier years
+///
+/// `months` (default 6) sets when a song counts as forgotten, `limit` (default 20)
+/// the maximum number of forgotten songs and of songs per day.
+#[get("/?<months>&<limit>")]
+pub fn get_suggestions(months: Option<u32>, limit: Option<usize>) -> Json<Suggestions> {
+    Json(suggestions::get_suggestions(months.unwrap_or(6), limit.unwrap_or(20)))
+}
@@ -0,0 +1,9 @@
+use crate::helpers::system_monitor::{self, SystemStatus};
+use rocket::get;
+use rocket::serde::json::Json;
+
+/// Get CPU temperature, throttling flags, load average and memory usage
+#[get("/")]
+pub fn get_status() -> Json<SystemStatus> {
+    Json(system_monitor::get_status())
+}
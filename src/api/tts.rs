@@ -0,0 +1,95 @@
+use crate::helpers::tts::{self, TtsCacheStats, TtsClip, TtsError};
+use log::info;
+use rocket::fs::NamedFile;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post};
+use serde::{Deserialize, Serialize};
+
+/// Request structure to render a text
+#[derive(Deserialize, Serialize)]
+pub struct RenderRequest {
+    pub text: String,
+    /// Voice, the configured default voice if not given
+    pub voice: Option<String>,
+}
+
+/// Response structure for the text-to-speech status
+#[derive(Serialize, Deserialize)]
+pub struct TtsStatusResponse {
+    pub enable: bool,
+    pub backend: String,
+    pub default_voice: String,
+    pub cache: TtsCacheStats,
+}
+
+/// Response structure for clearing the cache
+#[derive(Serialize, Deserialize)]
+pub struct ClearCacheResponse {
+    pub success: bool,
+    pub removed: usize,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn tts_error(error: TtsError) -> ApiError {
+    let status = match error {
+        TtsError::Disabled => Status::ServiceUnavailable,
+        TtsError::EmptyText | TtsError::TextTooLong(_) | TtsError::InvalidVoice(_) => Status::BadRequest,
+        TtsError::Backend(_) => Status::BadGateway,
+        TtsError::Cache(_) => Status::InternalServerError,
+    };
+    Custom(status, Json(ErrorResponse {
+        success: false,
+        message: error.to_string(),
+    }))
+}
+
+/// Get the backend, default voice and cache size
+#[get("/")]
+pub fn get_status() -> Json<TtsStatusResponse> {
+    Json(TtsStatusResponse {
+        enable: tts::is_enabled(),
+        backend: tts::backend_name().to_string(),
+        default_voice: tts::default_voice_name(),
+        cache: tts::cache_stats(),
+    })
+}
+
+/// Render a text and return the URL of the clip
+#[post("/render", data = "<request>")]
+pub async fn render(request: Json<RenderRequest>) -> Result<Json<TtsClip>, ApiError> {
+    let request = request.into_inner();
+    info!("API request: render speech {:?}", request.text);
+    // Rendering runs an engine or waits for a service, keep it off the async workers
+    rocket::tokio::task::spawn_blocking(move || tts::render(&request.text, request.voice.as_deref()))
+        .await
+        .map_err(|e| tts_error(TtsError::Backend(e.to_string())))?
+        .map(Json)
+        .map_err(tts_error)
+}
+
+/// Stream a rendered clip
+#[get("/clip/<file>")]
+pub async fn get_clip(file: &str) -> Result<NamedFile, Status> {
+    let path = tts::clip_path(file).ok_or(Status::NotFound)?;
+    NamedFile::open(path).await.map_err(|_| Status::NotFound)
+}
+
+/// Remove all rendered clips
+#[delete("/cache")]
+pub fn clear_cache() -> Json<ClearCacheResponse> {
+    info!("API request: clear speech cache");
+    Json(ClearCacheResponse {
+        success: true,
+        removed: tts::clear_cache(),
+    })
+}
@@ -0,0 +1,21 @@
+use crate::helpers::usbstorage::{self, UsbStorageDevice};
+use rocket::get;
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+/// Response structure for the USB storage device list
+#[derive(Serialize, Deserialize)]
+pub struct UsbStorageResponse {
+    /// Whether USB storage detection is enabled
+    pub enabled: bool,
+    pub devices: Vec<UsbStorageDevice>,
+}
+
+/// List connected USB storage devices and where they appear in the library
+#[get("/devices")]
+pub fn list_devices() -> Json<UsbStorageResponse> {
+    Json(UsbStorageResponse {
+        enabled: usbstorage::is_enabled(),
+        devices: usbstorage::list_devices(),
+    })
+}
@@ -1,11 +1,13 @@
+use crate::audiocontrol::AudioController;
 use crate::helpers::global_volume;
 use crate::helpers::volume::{VolumeControlInfo, DecibelRange};
 use rocket::serde::json::Json;
-use rocket::{get, post};
+use rocket::{get, post, State};
 use rocket::response::status::Custom;
 use rocket::http::Status;
 use serde::{Deserialize, Serialize};
 use log::debug;
+use std::sync::Arc;
 
 /// Response struct for volume control information
 #[derive(Serialize)]
@@ -171,8 +173,14 @@ pub fn get_volume_state() -> Result<Json<VolumeStateResponse>, Custom<Json<Volum
 }
 
 /// Set volume level
+///
+/// Percentages are capped to the maximum volume of the playback limits, decibel and raw
+/// values are reduced to it after they have been set.
 #[post("/set", data = "<request>")]
-pub fn set_volume(request: Json<SetVolumeRequest>) -> Json<VolumeOperationResponse> {
+pub fn set_volume(
+    request: Json<SetVolumeRequest>,
+    controller: &State<Arc<AudioController>>,
+) -> Json<VolumeOperationResponse> {
     debug!("API: Setting volume: {:?}", *request);
     
     if !global_volume::is_volume_control_available() {
@@ -192,7 +200,7 @@ pub fn set_volume(request: Json<SetVolumeRequest>) -> Json<VolumeOperationRespon
                 new_state: None,
             });
         }
-        global_volume::set_volume_percentage(percentage)
+        controller.set_volume(percentage)
     } else if let Some(db) = request.decibels {
         global_volume::set_volume_db(db)
     } else if let Some(raw) = request.raw_value {
@@ -206,6 +214,7 @@ pub fn set_volume(request: Json<SetVolumeRequest>) -> Json<VolumeOperationRespon
     };
     
     if result {
+        controller.enforce_volume_limit();
         let new_state = current_volume_state();
 
         Json(VolumeOperationResponse {
@@ -245,7 +254,7 @@ fn current_volume_state() -> Option<VolumeStateResponse> {
 /// "increased", "decrease" / "decreased") — passed explicitly by the caller
 /// rather than derived from `delta`'s sign, so an unrecognised value can't
 /// silently fall through to the wrong tense.
-fn adjust_and_respond(delta: f64, present: &str, past: &str) -> Json<VolumeOperationResponse> {
+fn adjust_and_respond(controller: &AudioController, delta: f64, present: &str, past: &str) -> Json<VolumeOperationResponse> {
     if !global_volume::is_volume_control_available() {
         return Json(VolumeOperationResponse {
             success: false,
@@ -254,7 +263,7 @@ fn adjust_and_respond(delta: f64, present: &str, past: &str) -> Json<VolumeOpera
         });
     }
 
-    if !global_volume::adjust_volume_percentage(delta) {
+    if !controller.adjust_volume(delta) {
         return Json(VolumeOperationResponse {
             success: false,
             message: format!("Failed to {} volume", present),
@@ -273,25 +282,25 @@ fn adjust_and_respond(delta: f64, present: &str, past: &str) -> Json<VolumeOpera
 
 /// Increase volume by a percentage amount
 #[post("/increase?<amount>")]
-pub fn increase_volume(amount: Option<f64>) -> Json<VolumeOperationResponse> {
+pub fn increase_volume(amount: Option<f64>, controller: &State<Arc<AudioController>>) -> Json<VolumeOperationResponse> {
     let increase_amount = amount.unwrap_or(5.0); // Default 5% increase
     debug!("API: Increasing volume by {}%", increase_amount);
-    adjust_and_respond(increase_amount, "increase", "increased")
+    adjust_and_respond(controller, increase_amount, "increase", "increased")
 }
 
 /// Decrease volume by a percentage amount
 #[post("/decrease?<amount>")]
-pub fn decrease_volume(amount: Option<f64>) -> Json<VolumeOperationResponse> {
+pub fn decrease_volume(amount: Option<f64>, controller: &State<Arc<AudioController>>) -> Json<VolumeOperationResponse> {
     let decrease_amount = amount.unwrap_or(5.0); // Default 5% decrease
     debug!("API: Decreasing volume by {}%", decrease_amount);
-    adjust_and_respond(-decrease_amount, "decrease", "decreased")
+    adjust_and_respond(controller, -decrease_amount, "decrease", "decreased")
 }
 
 /// Mute or unmute volume
 ///
 /// Muting saves the current level; unmuting restores it.
 #[post("/mute")]
-pub fn toggle_mute() -> Json<VolumeOperationResponse> {
+pub fn toggle_mute(controller: &State<Arc<AudioController>>) -> Json<VolumeOperationResponse> {
     debug!("API: Toggling mute");
 
     if !global_volume::is_volume_control_available() {
@@ -312,6 +321,8 @@ pub fn toggle_mute() -> Json<VolumeOperationResponse> {
         });
     }
 
+    // Unmuting restores the saved level, which may be above the current limit
+    controller.enforce_volume_limit();
     let now_muted = global_volume::is_muted();
     let new_state = current_volume_state();
     let percentage = new_state.as_ref().map(|s| s.percentage).unwrap_or(0.0);
@@ -0,0 +1,277 @@
+use crate::helpers::webdav::{self, RefreshResult, WebDavError, WebDavSource};
+use log::{debug, info};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status::Custom;
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Json;
+use rocket::{delete, get, post};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+/// Upstream headers passed on to the client of the streaming proxy
+const PROXIED_HEADERS: [&str; 6] = [
+    "Content-Type",
+    "Content-Length",
+    "Content-Range",
+    "Accept-Ranges",
+    "Last-Modified",
+    "ETag",
+];
+
+/// A configured source with the state of its index
+#[derive(Serialize, Deserialize)]
+pub struct SourceInfo {
+    #[serde(flatten)]
+    pub source: WebDavSource,
+    /// Number of indexed files
+    pub files: usize,
+    /// Time of the last index refresh
+    pub updated: Option<String>,
+}
+
+/// Response structure for the source list
+#[derive(Serialize, Deserialize)]
+pub struct SourceListResponse {
+    pub success: bool,
+    pub sources: Vec<SourceInfo>,
+}
+
+/// Request structure to add a source
+#[derive(Deserialize, Serialize)]
+pub struct AddSourceRequest {
+    #[serde(flatten)]
+    pub source: WebDavSource,
+    /// Password, it is only stored in the security store
+    pub password: Option<String>,
+}
+
+/// An indexed file with the URL MPD uses to play it
+#[derive(Serialize, Deserialize)]
+pub struct FileInfo {
+    pub path: String,
+    pub size: Option<u64>,
+    pub url: String,
+}
+
+/// Response structure for the files of a source
+#[derive(Serialize, Deserialize)]
+pub struct FileListResponse {
+    pub success: bool,
+    pub source: String,
+    pub files: Vec<FileInfo>,
+}
+
+/// Response structure for an index refresh
+#[derive(Serialize, Deserialize)]
+pub struct RefreshResponse {
+    pub success: bool,
+    pub result: RefreshResult,
+}
+
+/// Response structure for operations without data
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Response structure for error operations
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+type ApiError = Custom<Json<ErrorResponse>>;
+
+fn error_message(status: Status, message: String) -> ApiError {
+    Custom(status, Json(ErrorResponse { success: false, message }))
+}
+
+fn error_response(error: WebDavError) -> ApiError {
+    let status = match error {
+        WebDavError::InvalidConfig(_) => Status::BadRequest,
+        WebDavError::NotFound(_) | WebDavError::FileNotIndexed(_) => Status::NotFound,
+        WebDavError::AlreadyExists(_) => Status::Conflict,
+        WebDavError::Http(_) => Status::BadGateway,
+        WebDavError::Storage(_) => Status::InternalServerError,
+    };
+    error_message(status, error.to_string())
+}
+
+/// The Range header of a request, forwarded to the WebDAV server so clients can seek
+#[derive(Debug, Clone)]
+pub struct RangeHeader(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RangeHeader {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RangeHeader(request.headers().get_one("Range").map(ToOwned::to_owned)))
+    }
+}
+
+/// A response streamed from the WebDAV server
+pub struct ProxiedStream {
+    status: Status,
+    headers: Vec<(&'static str, String)>,
+    body: DuplexStream,
+}
+
+impl<'r> Responder<'r, 'static> for ProxiedStream {
+    fn respond_to(self, _request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.status(self.status).streamed_body(self.body);
+        for (name, value) in self.headers {
+            response.header(Header::new(name, value));
+        }
+        response.ok()
+    }
+}
+
+/// List all configured sources
+#[get("/list")]
+pub fn list_sources() -> Json<SourceListResponse> {
+    debug!("API request: list WebDAV sources");
+    let sources = webdav::list_sources()
+        .into_iter()
+        .map(|source| {
+            let index = webdav::get_index(&source.name);
+            SourceInfo {
+                files: index.files.len(),
+                updated: index.updated,
+                source,
+            }
+        })
+        .collect();
+
+    Json(SourceListResponse {
+        success: true,
+        sources,
+    })
+}
+
+/// Add a source, its index is built with the next refresh
+#[post("/add", data = "<request>")]
+pub fn add_source(request: Json<AddSourceRequest>) -> Result<Json<MessageResponse>, ApiError> {
+    let request = request.into_inner();
+    info!("API request: add WebDAV source '{}'", request.source.name);
+
+    let name = request.source.name.clone();
+    webdav::add_source(request.source, request.password.as_deref()).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Source '{}' added", name),
+    }))
+}
+
+/// List the indexed files of a source, optionally only those below a directory
+#[get("/<name>?<prefix>")]
+pub fn list_files(name: &str, prefix: Option<&str>) -> Result<Json<FileListResponse>, ApiError> {
+    let source = webdav::get_source(name).ok_or_else(|| error_response(WebDavError::NotFound(name.to_string())))?;
+    let prefix = prefix.map(|p| p.trim_matches('/')).unwrap_or("");
+
+    let files = webdav::get_index(name)
+        .files
+        .into_values()
+        .filter(|file| prefix.is_empty() || file.path.starts_with(&format!("{}/", prefix)))
+        .map(|file| FileInfo {
+            url: webdav::stream_url(&source, &file.path),
+            path: file.path,
+            size: file.size,
+        })
+        .collect();
+
+    Ok(Json(FileListResponse {
+        success: true,
+        source: name.to_string(),
+        files,
+    }))
+}
+
+/// Refresh the index of a source, with `full=true` all directories are scanned again
+#[post("/<name>/refresh?<full>")]
+pub async fn refresh_source(name: &str, full: Option<bool>) -> Result<Json<RefreshResponse>, ApiError> {
+    info!("API request: refresh WebDAV source '{}'", name);
+    // Scanning sends a PROPFIND for every directory with a blocking client, keep it off the async workers
+    let source = name.to_string();
+    let result = rocket::tokio::task::spawn_blocking(move || webdav::refresh_index(&source, full.unwrap_or(false)))
+        .await
+        .map_err(|e| error_message(Status::InternalServerError, e.to_string()))?
+        .map_err(error_response)?;
+    Ok(Json(RefreshResponse {
+        success: true,
+        result,
+    }))
+}
+
+/// Remove a source with its password and index
+#[delete("/<name>")]
+pub fn remove_source(name: &str) -> Result<Json<MessageResponse>, ApiError> {
+    webdav::remove_source(name).map_err(error_response)?;
+    Ok(Json(MessageResponse {
+        success: true,
+        message: format!("Source '{}' removed", name),
+    }))
+}
+
+/// Stream an indexed file from the WebDAV server
+///
+/// MPD plays these URLs, the credentials of the source are added here.
+#[get("/stream/<name>/<path..>")]
+pub async fn stream_file(name: &str, path: PathBuf, range: RangeHeader) -> Result<ProxiedStream, ApiError> {
+    let path = path.to_string_lossy().replace('\\', "/");
+    debug!("API request: stream '{}' from WebDAV source '{}'", path, name);
+    let (url, credentials) = webdav::resolve_file(name, &path).map_err(error_response)?;
+
+    let mut request = reqwest::Client::new().get(&url);
+    if let Some((username, password)) = credentials {
+        request = request.basic_auth(username, Some(password));
+    }
+    if let Some(range) = range.0 {
+        request = request.header("Range", range);
+    }
+    let mut upstream = request
+        .send()
+        .await
+        .map_err(|e| error_message(Status::BadGateway, e.to_string()))?;
+    let status = Status::from_code(upstream.status().as_u16()).unwrap_or(Status::BadGateway);
+    if !upstream.status().is_success() {
+        return Err(error_message(
+            Status::BadGateway,
+            format!("WebDAV server returned {}", upstream.status()),
+        ));
+    }
+
+    let headers = PROXIED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = upstream.headers().get(*name)?.to_str().ok()?;
+            Some((*name, value.to_string()))
+        })
+        .collect();
+
+    let (mut writer, body) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        loop {
+            match upstream.chunk().await {
+                Ok(Some(chunk)) => {
+                    // The client closed the connection
+                    if writer.write_all(&chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    debug!("WebDAV stream aborted: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(ProxiedStream { status, headers, body })
+}
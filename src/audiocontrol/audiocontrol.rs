@@ -4,19 +4,30 @@ use crate::players::{create_player_from_json, PlayerCreationError};
 use crate::plugins::ActionPlugin;
 use serde_json::Value;
 use std::sync::{Arc, Weak, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 use std::any::Any;
-use log::{debug, warn, error};
+use log::{debug, info, warn, error};
 use crate::audiocontrol::eventbus::EventBus;
+use crate::audiocontrol::transition::{fade_volume, TransitionConfig};
+use crate::audiocontrol::playbacklimits::{self, DailyUsage, PlaybackLimitStatus, PlaybackLimits, PlaybackLimitsConfig};
+use crate::audiocontrol::mergedplayers::MergedPlayersConfig;
+use crate::audiocontrol::priority::{PriorityStatus, SourcePriority, SourcePriorityConfig};
+use crate::audiocontrol::library::AggregatedLibrary;
+use crate::data::library::LibraryInterface;
 
 // Static singleton instance using OnceLock (safe, no unsafe needed)
 static AUDIO_CONTROLLER_INSTANCE: OnceLock<Arc<AudioController>> = OnceLock::new();
 
+/// A player controller shared between the AudioController and its users
+type SharedController = Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>;
+
 /// A simple AudioController that manages multiple PlayerController instances
 #[derive(Clone)]
 pub struct AudioController {
-    /// List of player controllers
-    controllers: Vec<Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>>,
+    /// List of player controllers, players can be added and removed at runtime
+    controllers: Arc<RwLock<Vec<SharedController>>>,
 
     /// Index of the active player controller in the list
     active_index: Arc<RwLock<usize>>,
@@ -27,96 +38,96 @@ pub struct AudioController {
     /// Self-reference for registering with players
     /// This is wrapped in Option because it's initialized after construction
     self_ref: Arc<RwLock<Option<Weak<AudioController>>>>,
+
+    /// Configuration for switching between players
+    transitions: Arc<RwLock<TransitionConfig>>,
+
+    /// Set while a player switch is in progress
+    switching: Arc<AtomicBool>,
+
+    /// Quiet hours and daily playback time budgets
+    limits: Arc<RwLock<PlaybackLimits>>,
+
+    /// Controllers that represent the same physical player
+    merged: Arc<RwLock<MergedPlayersConfig>>,
+
+    /// Rules deciding which player becomes active
+    priority: Arc<RwLock<SourcePriority>>,
 }
 
 // Implement PlayerController for AudioController
 impl PlayerController for AudioController {
     fn get_capabilities(&self) -> PlayerCapabilitySet {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_capabilities();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_capabilities();
         }
         PlayerCapabilitySet::empty()
     }
 
     fn get_song(&self) -> Option<Song> {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_song();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_song();
         }
         None
     }
 
     fn get_loop_mode(&self) -> LoopMode {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_loop_mode();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_loop_mode();
         }
         LoopMode::None
     }
 
     fn get_playback_state(&self) -> PlaybackState {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_playback_state();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_playback_state();
         }
         PlaybackState::Stopped
     }
 
     fn get_position(&self) -> Option<f64> {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_position();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_position();
         }
         None
     }
 
     fn get_shuffle(&self) -> bool {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_shuffle();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_shuffle();
         }
         false
     }
 
     fn get_player_name(&self) -> String {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_player_name();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_player_name();
         }
         "audiocontroller".to_string()
     }
 
     fn get_player_id(&self) -> String {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_player_id();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_player_id();
         }
         "none".to_string()
     }
 
     fn get_last_seen(&self) -> Option<std::time::SystemTime> {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_last_seen();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_last_seen();
         }
         None
     }
 
     fn send_command(&self, command: PlayerCommand) -> bool {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            debug!("Sending command to active controller [{}]: {}", active_idx, command);
-            let controller = self.controllers[*active_idx].read();
+        if let Some(controller) = self.get_active_controller() {
+            debug!("Sending command to active controller [{}]: {}", self.get_active_index(), command);
+            let controller = controller.read();
+            if let Err(reason) = self.check_command(&command, controller.get_playback_state()) {
+                warn!("Not sending {}: {}", command, reason);
+                return false;
+            }
             return controller.send_command(command);
         }
         false
@@ -129,7 +140,7 @@ impl PlayerController for AudioController {
     fn start(&self) -> bool {
         let mut success = false;
 
-        for controller_lock in &self.controllers {
+        for controller_lock in &self.list_controllers() {
             let controller = controller_lock.read();
             if controller.start() {
                 success = true;
@@ -145,7 +156,7 @@ impl PlayerController for AudioController {
     fn stop(&self) -> bool {
         let mut success = false;
 
-        for controller_lock in &self.controllers {
+        for controller_lock in &self.list_controllers() {
             let controller = controller_lock.read();
             if controller.stop() {
                 success = true;
@@ -159,13 +170,16 @@ impl PlayerController for AudioController {
     }
 
     fn get_queue(&self) -> Vec<Track> {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.get_queue();
+        if let Some(controller) = self.get_active_controller() {
+            return controller.read().get_queue();
         }
         Vec::new()
     }
+
+    /// The libraries of all players merged into one
+    fn get_library(&self) -> Option<Box<dyn LibraryInterface>> {
+        Some(Box::new(self.aggregated_library()))
+    }
 }
 
 impl Default for AudioController {
@@ -178,10 +192,15 @@ impl AudioController {
     /// Create a new AudioController with no controllers
     pub fn new() -> Self {
         Self {
-            controllers: Vec::new(),
+            controllers: Arc::new(RwLock::new(Vec::new())),
             active_index: Arc::new(RwLock::new(0)),
             action_plugins: Arc::new(RwLock::new(Vec::new())),
             self_ref: Arc::new(RwLock::new(None)),
+            transitions: Arc::new(RwLock::new(TransitionConfig::default())),
+            switching: Arc::new(AtomicBool::new(false)),
+            limits: Arc::new(RwLock::new(PlaybackLimits::new(PlaybackLimitsConfig::default()))),
+            merged: Arc::new(RwLock::new(MergedPlayersConfig::default())),
+            priority: Arc::new(RwLock::new(SourcePriority::new(SourcePriorityConfig::default()))),
         }
     }
 
@@ -231,7 +250,7 @@ impl AudioController {
     /// Add a player controller to the list
     ///
     /// If this is the first controller added, it becomes the active controller.
-    pub fn add_controller(&mut self, controller: Box<dyn PlayerController + Send + Sync>) -> usize {
+    pub fn add_controller(&self, controller: Box<dyn PlayerController + Send + Sync>) -> usize {
         // Check if we have a self reference for listener registration
         let _self_weak = {
             let self_ref = self.self_ref.read();
@@ -240,28 +259,43 @@ impl AudioController {
 
         // Wrap in Arc+RwLock and store
         let controller = Arc::new(RwLock::new(controller));
-        self.controllers.push(controller);
+        let mut controllers = self.controllers.write();
+        controllers.push(controller);
 
         // If this is the first controller, make it active
-        if self.controllers.len() == 1 {
+        if controllers.len() == 1 {
             let mut active_idx = self.active_index.write();
             *active_idx = 0;
         }
 
         // Return the index of the added controller
-        self.controllers.len() - 1
+        controllers.len() - 1
     }
 
     /// Remove a player controller from the list by index
     ///
     /// If the removed controller was active, the active_index is reset to None.
     /// Returns true if a controller was removed, false if the index was invalid.
-    pub fn remove_controller(&mut self, index: usize) -> bool {
-        if index >= self.controllers.len() {
+    pub fn remove_controller(&self, index: usize) -> bool {
+        let mut controllers = self.controllers.write();
+        if index >= controllers.len() {
             return false;
         }
+        self.remove_at(&mut controllers, index);
+        true
+    }
 
-        self.controllers.remove(index);
+    /// Remove the player controller with the given player ID
+    ///
+    /// Returns the removed controller, so it can be stopped, or None if there is no such player.
+    pub fn remove_controller_by_id(&self, player_id: &str) -> Option<SharedController> {
+        let mut controllers = self.controllers.write();
+        let index = controllers.iter().position(|ctrl_lock| ctrl_lock.read().get_player_id() == player_id)?;
+        Some(self.remove_at(&mut controllers, index))
+    }
+
+    fn remove_at(&self, controllers: &mut Vec<SharedController>, index: usize) -> SharedController {
+        let removed = controllers.remove(index);
 
         // If the active controller was removed, update active_index
         let mut active_idx = self.active_index.write();
@@ -271,34 +305,131 @@ impl AudioController {
             *active_idx -= 1;
         }
 
-        true
+        removed
     }
 
     /// Get the list of controllers
     pub fn list_controllers(&self) -> Vec<Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>> {
-        self.controllers.clone()
+        self.controllers.read().clone()
+    }
+
+    /// The libraries of all players merged into one, mirrors of other players are left out
+    pub fn aggregated_library(&self) -> AggregatedLibrary {
+        let libraries = self
+            .list_controllers()
+            .iter()
+            .filter_map(|ctrl| {
+                let ctrl = ctrl.read();
+                let (name, id) = (ctrl.get_player_name(), ctrl.get_player_id());
+                if self.is_mirror(&name, &id) {
+                    return None;
+                }
+                ctrl.get_library().map(|library| (name, library))
+            })
+            .collect();
+        AggregatedLibrary::with_libraries(libraries)
+    }
+
+    /// Get a controller by index
+    fn controller_at(&self, index: usize) -> Option<SharedController> {
+        self.controllers.read().get(index).cloned()
     }
 
     /// Get a controller by player name
     pub fn get_player_by_name(&self, player_name: &str) -> Option<Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>> {
-        for ctrl_lock in &self.controllers {
+        self.find_index(player_name).and_then(|index| self.controller_at(index))
+    }
+
+    /// Get the controller to use for a player name
+    ///
+    /// Unlike `get_player_by_name` merged players are resolved to the preferred controller of their group.
+    pub fn get_preferred_player_by_name(&self, player_name: &str) -> Option<Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>> {
+        self.find_index(player_name).and_then(|index| self.controller_at(self.preferred_index(index)))
+    }
+
+    /// Get the configuration of merged players
+    pub fn get_merged_players(&self) -> MergedPlayersConfig {
+        self.merged.read().clone()
+    }
+
+    /// Whether a controller only mirrors another configured controller
+    pub fn is_mirror(&self, name: &str, id: &str) -> bool {
+        self.merged.read().group_of(name, id).is_some_and(|group| {
+            group.is_mirror(name, id) && self.find_index(&group.primary).is_some()
+        })
+    }
+
+    /// Index of the controller to use instead of the given one
+    ///
+    /// Mirrors are replaced by the primary controller of their group. The other controllers
+    /// of the group are only used if the primary is disconnected.
+    pub fn preferred_index(&self, index: usize) -> usize {
+        let Some(ctrl_lock) = self.controller_at(index) else {
+            return index;
+        };
+        let (name, id) = {
             let ctrl = ctrl_lock.read();
-            if ctrl.get_player_name().eq_ignore_ascii_case(player_name)
-                || ctrl.get_player_id().eq_ignore_ascii_case(player_name)
-            {
-                return Some(ctrl_lock.clone());
-            }
-        }
-        None
+            (ctrl.get_player_name(), ctrl.get_player_id())
+        };
+        let merged = self.merged.read();
+        let Some(group) = merged.group_of(&name, &id) else {
+            return index;
+        };
+
+        let available = |idx: &usize| {
+            self.controller_at(*idx).is_some_and(|ctrl| ctrl.read().get_playback_state() != PlaybackState::Disconnected)
+        };
+        std::iter::once(&group.primary)
+            .chain(group.mirrors.iter())
+            .filter_map(|pattern| self.find_index(pattern))
+            .find(available)
+            .unwrap_or(index)
+    }
+
+    /// Indices of the other controllers of the same physical player
+    fn merged_partners(&self, index: usize) -> Vec<usize> {
+        let Some(ctrl_lock) = self.controller_at(index) else {
+            return Vec::new();
+        };
+        let (name, id) = {
+            let ctrl = ctrl_lock.read();
+            (ctrl.get_player_name(), ctrl.get_player_id())
+        };
+        let merged = self.merged.read();
+        let Some(group) = merged.group_of(&name, &id) else {
+            return Vec::new();
+        };
+        self.list_controllers()
+            .iter()
+            .enumerate()
+            .filter(|(idx, ctrl_lock)| {
+                let ctrl = ctrl_lock.read();
+                *idx != index && group.contains(&ctrl.get_player_name(), &ctrl.get_player_id())
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn find_index(&self, pattern: &str) -> Option<usize> {
+        self.list_controllers().iter().position(|ctrl_lock| {
+            let ctrl = ctrl_lock.read();
+            pattern.eq_ignore_ascii_case(&ctrl.get_player_name()) || pattern.eq_ignore_ascii_case(&ctrl.get_player_id())
+        })
+    }
+
+    /// Index of the active controller
+    pub fn get_active_index(&self) -> usize {
+        *self.active_index.read()
     }
 
     /// Set the active controller by index
     ///
     /// Returns true if the active controller was changed, false if the index was invalid.
     pub fn set_active_controller(&self, index: usize) -> bool {
-        if index >= self.controllers.len() {
+        if index >= self.controllers.read().len() {
             return false;
         }
+        let index = self.preferred_index(index);
 
         // Check if this is actually a change
         {
@@ -316,27 +447,338 @@ impl AudioController {
         true
     }
 
+    /// Get the configuration for switching between players
+    pub fn get_transition_config(&self) -> TransitionConfig {
+        self.transitions.read().clone()
+    }
+
+    /// Set the configuration for switching between players
+    pub fn set_transition_config(&self, config: TransitionConfig) {
+        *self.transitions.write() = config;
+    }
+
+    /// Switch the active player
+    ///
+    /// The volume is faded out if the previous player is playing, the previous player is
+    /// paused or stopped, and after the new player reports Playing the volume is faded in
+    /// again. What happens is configured per transition, see `TransitionConfig`. Fading is
+    /// only done if transitions are enabled.
+    ///
+    /// This blocks until the switch is finished. Returns false if the index is invalid or
+    /// another switch is in progress.
+    pub fn switch_to_player(&self, index: usize, start_playback: bool) -> bool {
+        let controllers = self.list_controllers();
+        if index >= controllers.len() {
+            return false;
+        }
+        let index = self.preferred_index(index);
+        if self.switching.swap(true, Ordering::SeqCst) {
+            warn!("Player switch already in progress, ignoring switch to index {}", index);
+            return false;
+        }
+
+        let old_index = *self.active_index.read();
+        let old = controllers.get(old_index).filter(|_| old_index != index).cloned();
+        let new = controllers[index].clone();
+
+        let old_name = old.as_ref().map(|c| c.read().get_player_name()).unwrap_or_default();
+        let new_name = new.read().get_player_name();
+        let (enabled, settings) = {
+            let config = self.transitions.read();
+            (config.enable, config.resolve(&old_name, &new_name))
+        };
+
+        let old_playing = old.as_ref().is_some_and(|c| c.read().get_playback_state() == PlaybackState::Playing);
+        let volume = if enabled && settings.fade && old_playing {
+            crate::helpers::global_volume::get_volume_percentage()
+        } else {
+            None
+        };
+
+        if let Some(volume) = volume {
+            info!("Switching from {} to {}, fading out", old_name, new_name);
+            fade_volume(volume, 0.0, settings.fade_out);
+        }
+
+        if let (Some(old), Some(command)) = (&old, settings.old_player.command()) {
+            debug!("Sending {} to previous player {}", command, old_name);
+            old.read().send_command(command);
+        }
+
+        self.set_active_controller(index);
+
+        if start_playback && new.read().get_playback_state() != PlaybackState::Playing {
+            match self.check_command(&PlayerCommand::Play, PlaybackState::Stopped) {
+                Ok(()) => {
+                    new.read().send_command(PlayerCommand::Play);
+                }
+                Err(reason) => warn!("Not starting playback on {}: {}", new_name, reason),
+            }
+        }
+
+        if volume.is_some() || start_playback {
+            let deadline = Instant::now() + settings.wait_timeout;
+            while new.read().get_playback_state() != PlaybackState::Playing {
+                if Instant::now() >= deadline {
+                    warn!("{} didn't start playing within {:?}", new_name, settings.wait_timeout);
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+
+        if let Some(volume) = volume {
+            fade_volume(0.0, volume, settings.fade_in);
+        }
+
+        self.switching.store(false, Ordering::SeqCst);
+        info!("Active player is now {}", new_name);
+        true
+    }
+
+    /// Evaluate the source priority rules after the state of a player changed
+    ///
+    /// A player that starts playing becomes the active player if the rules allow it,
+    /// otherwise it is paused if `pause_denied` is set. The previous player is stopped,
+    /// or handled as configured in the transitions if they are enabled.
+    pub fn handle_state_change(&self, player_name: &str, player_id: &str, state: PlaybackState) {
+        if state != PlaybackState::Playing {
+            return;
+        }
+        let Some(index) = self.list_controllers().iter().position(|ctrl_lock| {
+            let ctrl = ctrl_lock.read();
+            ctrl.get_player_name() == player_name && ctrl.get_player_id() == player_id
+        }) else {
+            warn!("Could not find player {}:{} to set active", player_name, player_id);
+            return;
+        };
+
+        // Mirrors of merged players activate their primary, nothing to do if it is already active
+        let index = self.preferred_index(index);
+        let active_index = self.get_active_index();
+        if index == active_index {
+            debug!("Player {}:{} is already active, no change needed", player_name, player_id);
+            return;
+        }
+
+        let active = self.controller_at(active_index).map(|ctrl| {
+            let ctrl = ctrl.read();
+            (ctrl.get_player_name(), ctrl.get_player_id(), ctrl.get_playback_state() == PlaybackState::Playing)
+        });
+        let decision = self.priority.write().decide(
+            (player_name, player_id),
+            active.as_ref().map(|(name, id, playing)| ((name.as_str(), id.as_str()), *playing)),
+        );
+
+        if !decision.preempt {
+            info!("{} doesn't become the active player: {}", player_name, decision.reason);
+            if self.priority.read().config().pause_denied {
+                if let Some(ctrl) = self.controller_at(index) {
+                    ctrl.read().send_command(PlayerCommand::Pause);
+                }
+            }
+            return;
+        }
+        debug!("{} becomes the active player: {}", player_name, decision.reason);
+
+        // With transitions enabled the players are faded, this takes a while
+        if self.transitions.read().enable {
+            if let Some(controller) = self.self_ref.read().as_ref().and_then(Weak::upgrade) {
+                info!("Switching to player {}:{}", player_name, player_id);
+                std::thread::spawn(move || {
+                    controller.switch_to_player(index, false);
+                });
+                return;
+            }
+        }
+
+        if self.set_active_controller(index) {
+            info!("Active player is now {}:{}", player_name, player_id);
+            // Only one source should play at a time, stop the players that are not active anymore
+            let stopped = self.send_command_to_inactives(PlayerCommand::Stop);
+            if stopped > 0 {
+                info!("Sent Stop to {} now-inactive player(s)", stopped);
+            }
+        }
+    }
+
+    /// Protect a manually activated player from being preempted for `pin_minutes`
+    pub fn pin_player(&self, index: usize) {
+        if let Some(ctrl) = self.controller_at(self.preferred_index(index)) {
+            let ctrl = ctrl.read();
+            self.priority.write().pin(&ctrl.get_player_name(), &ctrl.get_player_id());
+        }
+    }
+
+    /// Remove the pin of a manually activated player, returns false if no player was pinned
+    pub fn unpin_player(&self) -> bool {
+        self.priority.write().unpin()
+    }
+
+    /// Get the source priority rules, the pinned player and the latest decisions
+    pub fn get_priority_status(&self) -> PriorityStatus {
+        self.priority.read().status()
+    }
+
     /// Get the currently active controller, if any
     pub fn get_active_controller(&self) -> Option<Arc<RwLock<Box<dyn PlayerController + Send + Sync>>>> {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            return Some(self.controllers[*active_idx].clone());
-        }
-        None
+        self.controller_at(*self.active_index.read())
     }
 
     /// Send a command to the active player controller
     ///
+    /// If the controller fails, the command is sent to the other controllers of a merged player.
     /// Returns true if the command was sent successfully, false if there is no active controller.
     pub fn send_command(&self, command: PlayerCommand) -> bool {
-        let active_idx = self.active_index.read();
-        if *active_idx < self.controllers.len() {
-            let controller = self.controllers[*active_idx].read();
-            return controller.send_command(command);
+        let active_idx = *self.active_index.read();
+        if let Some(controller) = self.controller_at(active_idx) {
+            {
+                let controller = controller.read();
+                if let Err(reason) = self.check_command(&command, controller.get_playback_state()) {
+                    warn!("Not sending {}: {}", command, reason);
+                    return false;
+                }
+                if controller.send_command(command.clone()) {
+                    return true;
+                }
+            }
+            for controller in self.merged_partners(active_idx).into_iter().filter_map(|idx| self.controller_at(idx)) {
+                let controller = controller.read();
+                if controller.send_command(command.clone()) {
+                    debug!("Sent {} to {} as fallback", command, controller.get_player_id());
+                    return true;
+                }
+            }
         }
         false
     }
 
+    /// Check a command against the playback limits
+    ///
+    /// `state` is the playback state of the player receiving the command. Returns the
+    /// reason if the command would start playback while this isn't allowed.
+    pub fn check_command(&self, command: &PlayerCommand, state: PlaybackState) -> Result<(), String> {
+        if !playbacklimits::starts_playback(command, state) {
+            return Ok(());
+        }
+        let status = self.get_playback_limits();
+        if status.playback_allowed {
+            Ok(())
+        } else {
+            Err(status.reason.unwrap_or_else(|| "Playback is not allowed".to_string()))
+        }
+    }
+
+    /// Get the current effect of the playback limits
+    pub fn get_playback_limits(&self) -> PlaybackLimitStatus {
+        self.limits.read().status(chrono::Local::now().naive_local())
+    }
+
+    /// Lift the playback limits for some minutes, the configured default if None
+    ///
+    /// Returns the status with the override, or an error if the PIN doesn't match or
+    /// no PIN is configured.
+    pub fn override_playback_limits(&self, pin: &str, minutes: Option<u64>) -> Result<PlaybackLimitStatus, String> {
+        let now = chrono::Local::now().naive_local();
+        let mut limits = self.limits.write();
+        match limits.config().override_pin.as_deref() {
+            None => return Err("Overrides are disabled, no override_pin is configured".to_string()),
+            Some(expected) if expected != pin => return Err("Invalid PIN".to_string()),
+            Some(_) => {}
+        }
+        let minutes = minutes.unwrap_or(limits.config().override_minutes);
+        info!("Playback limits lifted for {} minutes", minutes);
+        limits.set_override(Some(now + chrono::Duration::minutes(minutes as i64)));
+        Ok(limits.status(now))
+    }
+
+    /// End an override of the playback limits
+    pub fn clear_playback_limits_override(&self) -> PlaybackLimitStatus {
+        let mut limits = self.limits.write();
+        limits.set_override(None);
+        limits.status(chrono::Local::now().naive_local())
+    }
+
+    /// Reduce a volume percentage to the maximum allowed by the playback limits
+    pub fn limit_volume(&self, percentage: f64) -> f64 {
+        match self.get_playback_limits().max_volume {
+            Some(max) => percentage.min(max),
+            None => percentage,
+        }
+    }
+
+    /// Set the global volume, respecting the playback limits
+    pub fn set_volume(&self, percentage: f64) -> bool {
+        crate::helpers::global_volume::set_volume_percentage(self.limit_volume(percentage))
+    }
+
+    /// Change the global volume by `delta` percent, respecting the playback limits
+    pub fn adjust_volume(&self, delta: f64) -> bool {
+        let max = self.get_playback_limits().max_volume;
+        match (max, crate::helpers::global_volume::get_volume_percentage()) {
+            (Some(max), Some(current)) if current + delta > max => crate::helpers::global_volume::set_volume_percentage(max),
+            _ => crate::helpers::global_volume::adjust_volume_percentage(delta),
+        }
+    }
+
+    /// Reduce the global volume if it is above the maximum allowed by the playback limits
+    ///
+    /// This catches volume changes that can't be checked in advance, e.g. from decibel
+    /// values or from outside of AudioControl.
+    pub fn enforce_volume_limit(&self) {
+        if let (Some(max), Some(current)) = (
+            self.get_playback_limits().max_volume,
+            crate::helpers::global_volume::get_volume_percentage(),
+        ) {
+            if current > max + 0.5 {
+                info!("Reducing volume from {:.0}% to the limit of {:.0}%", current, max);
+                crate::helpers::global_volume::set_volume_percentage(max);
+            }
+        }
+    }
+
+    /// Count the playback time and stop players that play while not allowed
+    ///
+    /// Called periodically with the time since the last call.
+    fn enforce_playback_limits(&self, elapsed: Duration) {
+        let playing: Vec<_> = self
+            .list_controllers()
+            .into_iter()
+            .filter(|ctrl| ctrl.read().get_playback_state() == PlaybackState::Playing)
+            .collect();
+
+        if !playing.is_empty() {
+            let now = chrono::Local::now().naive_local();
+            let (before, usage) = {
+                let mut limits = self.limits.write();
+                let before = limits.usage();
+                (before, limits.add_usage(elapsed.as_secs(), now))
+            };
+            // Store the usage once per minute, so it survives restarts
+            if before.is_none_or(|b| b.date != usage.date || b.seconds / 60 != usage.seconds / 60) {
+                if let Err(e) = crate::helpers::settingsdb::set(playbacklimits::USAGE_KEY, &usage) {
+                    warn!("Failed to store playback time: {}", e);
+                }
+            }
+        }
+
+        let status = self.get_playback_limits();
+        if !status.playback_allowed {
+            for ctrl in playing {
+                let ctrl = ctrl.read();
+                info!(
+                    "Pausing {}: {}",
+                    ctrl.get_player_name(),
+                    status.reason.as_deref().unwrap_or("playback is not allowed")
+                );
+                if !ctrl.send_command(PlayerCommand::Pause) {
+                    ctrl.send_command(PlayerCommand::Stop);
+                }
+            }
+        }
+        self.enforce_volume_limit();
+    }
+
     /// Send a command to all inactive player controllers
     ///
     /// Returns the number of controllers that successfully processed the command.
@@ -344,9 +786,11 @@ impl AudioController {
         let mut success_count = 0;
 
         let active_idx_value = *self.active_index.read();
+        // Mirrors of the active player control the active player as well
+        let partners = self.merged_partners(active_idx_value);
 
-        for (idx, controller) in self.controllers.iter().enumerate() {
-            if idx == active_idx_value {
+        for (idx, controller) in self.list_controllers().iter().enumerate() {
+            if idx == active_idx_value || partners.contains(&idx) {
                 continue;
             }
 
@@ -370,8 +814,8 @@ impl AudioController {
     ///
     /// Returns a Result with the new AudioController or an error if any player creation failed
     pub fn from_json(config: &Value) -> Result<Arc<AudioController>, PlayerCreationError> {
-        // Build the AudioController as an owned value so we can use &mut self
-        let mut controller = AudioController::new();
+        // Build the AudioController as an owned value before it is shared
+        let controller = AudioController::new();
 
         // Process player configurations if present
         if let Some(players_config) = config.get("players").and_then(|v| v.as_array()) {
@@ -406,7 +850,7 @@ impl AudioController {
                 }
             }
 
-            if controller.controllers.is_empty() {
+            if controller.controllers.read().is_empty() {
                 warn!("No valid player controllers found in configuration");
             }
         } else if let Some(players_config) = config.as_array() {
@@ -433,9 +877,61 @@ impl AudioController {
             }
         }
 
+        if let Some(transitions) = crate::config::get_service_config(config, "transitions") {
+            match serde_json::from_value::<TransitionConfig>(transitions.clone()) {
+                Ok(transitions) => *controller.transitions.write() = transitions,
+                Err(e) => warn!("Invalid transitions configuration, using defaults: {}", e),
+            }
+        }
+
+        if let Some(merged) = crate::config::get_service_config(config, "merged_players") {
+            match serde_json::from_value::<MergedPlayersConfig>(merged.clone()) {
+                Ok(merged) => *controller.merged.write() = merged,
+                Err(e) => warn!("Invalid merged_players configuration, players are not merged: {}", e),
+            }
+        }
+
+        if let Some(priority) = crate::config::get_service_config(config, "source_priority") {
+            match serde_json::from_value::<SourcePriorityConfig>(priority.clone()) {
+                Ok(priority) => *controller.priority.write() = SourcePriority::new(priority),
+                Err(e) => warn!("Invalid source_priority configuration, every player that starts playing becomes active: {}", e),
+            }
+        }
+
+        if let Some(limits) = crate::config::get_service_config(config, "playback_limits") {
+            match serde_json::from_value::<PlaybackLimitsConfig>(limits.clone()) {
+                Ok(limits) => {
+                    if let Err(e) = limits.validate() {
+                        warn!("Invalid playback limits, ignoring them: {}", e);
+                    } else {
+                        let mut playback_limits = PlaybackLimits::new(limits);
+                        if let Ok(Some(usage)) = crate::helpers::settingsdb::get::<DailyUsage>(playbacklimits::USAGE_KEY) {
+                            playback_limits.restore_usage(usage);
+                        }
+                        *controller.limits.write() = playback_limits;
+                    }
+                }
+                Err(e) => warn!("Invalid playback_limits configuration, playback is not limited: {}", e),
+            }
+        }
+
         // Wrap in Arc now that mutation is done
         let controller = Arc::new(controller);
 
+        let limits = controller.limits.read().config().clone();
+        if limits.enable {
+            info!("Playback limits enabled");
+            let interval = Duration::from_secs(limits.poll_interval_secs.max(1));
+            let weak = Arc::downgrade(&controller);
+            std::thread::spawn(move || loop {
+                std::thread::sleep(interval);
+                match weak.upgrade() {
+                    Some(controller) => controller.enforce_playback_limits(interval),
+                    None => break,
+                }
+            });
+        }
+
         // Initialize the self-reference (needs Arc)
         AudioController::initialize(&controller);
 
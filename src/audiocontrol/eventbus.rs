@@ -1,10 +1,14 @@
 use crate::data::player_event::PlayerEvent;
 use crossbeam::channel::{unbounded, Receiver, Sender};
+use log::warn;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::Mutex;
 use std::thread;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
 
 /// Defines what kinds of events a subscriber wants to receive
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -30,12 +34,21 @@ pub enum EventSubscription {
     /// Subscribe to playback position change events only
     PositionChanged,
     
-    /// Subscribe to database update events only
+    /// Subscribe to database update and library load progress events only
     DatabaseUpdating,
     
+    /// Subscribe to library change events only
+    LibraryChanged,
+
     /// Subscribe to queue change events only
     QueueChanged,
 
+    /// Subscribe to upcoming track announcements only
+    UpcomingTrack,
+
+    /// Subscribe to queue ending announcements only
+    QueueEnding,
+
     /// Subscribe to song information update events only
     SongInformationUpdate,
     
@@ -44,6 +57,21 @@ pub enum EventSubscription {
     
     /// Subscribe to volume changed events only
     VolumeChanged,
+
+    /// Subscribe to USB storage events only
+    UsbStorageChanged,
+
+    /// Subscribe to system idle and wake events only
+    SystemIdle,
+
+    /// Subscribe to network change events only
+    NetworkChanged,
+
+    /// Subscribe to system resource alerts only
+    SystemResourceAlert,
+
+    /// Subscribe to background job progress only
+    BackgroundJobProgress,
 }
 
 impl From<&PlayerEvent> for EventSubscription {
@@ -55,35 +83,120 @@ impl From<&PlayerEvent> for EventSubscription {
             PlayerEvent::RandomChanged { .. } => EventSubscription::RandomChanged,
             PlayerEvent::CapabilitiesChanged { .. } => EventSubscription::CapabilitiesChanged,
             PlayerEvent::PositionChanged { .. } => EventSubscription::PositionChanged,
-            PlayerEvent::DatabaseUpdating { .. } => EventSubscription::DatabaseUpdating,
+            PlayerEvent::DatabaseUpdating { .. } | PlayerEvent::LibraryLoadProgress { .. } => EventSubscription::DatabaseUpdating,
+            PlayerEvent::LibraryChanged { .. } => EventSubscription::LibraryChanged,
             PlayerEvent::QueueChanged { .. } => EventSubscription::QueueChanged,
+            PlayerEvent::UpcomingTrack { .. } => EventSubscription::UpcomingTrack,
+            PlayerEvent::QueueEnding { .. } => EventSubscription::QueueEnding,
             PlayerEvent::SongInformationUpdate { .. } => EventSubscription::SongInformationUpdate,
             PlayerEvent::ActivePlayerChanged { .. } => EventSubscription::ActivePlayerChanged,
             PlayerEvent::VolumeChanged { .. } => EventSubscription::VolumeChanged,
+            PlayerEvent::UsbStorageChanged { .. } => EventSubscription::UsbStorageChanged,
+            PlayerEvent::SystemIdle { .. } | PlayerEvent::SystemWake { .. } => EventSubscription::SystemIdle,
+            PlayerEvent::NetworkChanged { .. } => EventSubscription::NetworkChanged,
+            PlayerEvent::SystemResourceAlert { .. } => EventSubscription::SystemResourceAlert,
+            PlayerEvent::BackgroundJobProgress { .. } => EventSubscription::BackgroundJobProgress,
         }
     }
 }
 
+/// Groups of events that can be received from the broadcast channel of the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventChannel {
+    /// Playback state, song, modes, queue and active player changes
+    Player,
+
+    /// Playback position updates, sent several times per second while playing
+    Position,
+
+    /// Volume changes
+    Volume,
+
+    /// Database updates, library loading and library changes
+    Library,
+
+    /// Background job progress
+    Job,
+
+    /// USB storage, idle/wake, network and resource events of the system
+    System,
+}
+
+impl EventChannel {
+    /// All channels, for consumers that want to receive every event
+    pub const ALL: [EventChannel; 6] = [
+        EventChannel::Player,
+        EventChannel::Position,
+        EventChannel::Volume,
+        EventChannel::Library,
+        EventChannel::Job,
+        EventChannel::System,
+    ];
+}
+
+impl From<&PlayerEvent> for EventChannel {
+    fn from(event: &PlayerEvent) -> Self {
+        match event {
+            PlayerEvent::StateChanged { .. }
+            | PlayerEvent::SongChanged { .. }
+            | PlayerEvent::LoopModeChanged { .. }
+            | PlayerEvent::RandomChanged { .. }
+            | PlayerEvent::CapabilitiesChanged { .. }
+            | PlayerEvent::QueueChanged { .. }
+            | PlayerEvent::UpcomingTrack { .. }
+            | PlayerEvent::QueueEnding { .. }
+            | PlayerEvent::SongInformationUpdate { .. }
+            | PlayerEvent::ActivePlayerChanged { .. } => EventChannel::Player,
+            PlayerEvent::PositionChanged { .. } => EventChannel::Position,
+            PlayerEvent::VolumeChanged { .. } => EventChannel::Volume,
+            PlayerEvent::DatabaseUpdating { .. }
+            | PlayerEvent::LibraryLoadProgress { .. }
+            | PlayerEvent::LibraryChanged { .. } => EventChannel::Library,
+            PlayerEvent::BackgroundJobProgress { .. } => EventChannel::Job,
+            PlayerEvent::UsbStorageChanged { .. }
+            | PlayerEvent::SystemIdle { .. }
+            | PlayerEvent::SystemWake { .. }
+            | PlayerEvent::NetworkChanged { .. }
+            | PlayerEvent::SystemResourceAlert { .. } => EventChannel::System,
+        }
+    }
+}
+
+/// Number of events kept for broadcast receivers, receivers that fall further behind skip the oldest events
+const BROADCAST_CAPACITY: usize = 256;
+
 /// Type alias for a subscriber ID
 pub type SubscriberId = u64;
 
+/// Sender of an `EventQueue` and the channels it receives
+type QueueSender = (mpsc::UnboundedSender<PlayerEvent>, Vec<EventChannel>);
+
 /// Global singleton instance of the EventBus.
 static GLOBAL_EVENT_BUS: Lazy<EventBus> = Lazy::new(EventBus::new);
 
 /// EventBus for distributing PlayerEvents to subscribers
+///
+/// Every event is sent to the crossbeam subscribers that asked for its type, to the `EventQueue`s
+/// of its channel and to the broadcast channel, which async consumers like the API server read with
+/// an `EventStream`.
 #[derive(Clone)]
 pub struct EventBus {
     subscribers: Arc<Mutex<HashMap<SubscriberId, (Sender<PlayerEvent>, Vec<EventSubscription>)>>>,
     next_id: Arc<Mutex<SubscriberId>>,
+    broadcast: broadcast::Sender<PlayerEvent>,
+    queues: Arc<Mutex<Vec<QueueSender>>>,
 }
 
 impl EventBus {
     /// Create a new EventBus instance
     /// Note: For a global singleton, use EventBus::instance()
     pub fn new() -> Self {
+        let (broadcast, _) = broadcast::channel(BROADCAST_CAPACITY);
         EventBus {
             subscribers: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(0)),
+            broadcast,
+            queues: Arc::new(Mutex::new(Vec::new())),
         }
     }
     
@@ -117,8 +230,48 @@ impl EventBus {
         subscribers.remove(&id).is_some()
     }
     
+    /// Receive the events of the given channels from the broadcast channel
+    ///
+    /// The stream only sees events published after it was created. Dropping it is all that is
+    /// needed to unsubscribe.
+    pub fn stream(&self, channels: &[EventChannel]) -> EventStream {
+        EventStream {
+            receiver: self.broadcast.subscribe(),
+            channels: channels.to_vec(),
+        }
+    }
+
+    /// Receive all events from the broadcast channel
+    pub fn stream_all(&self) -> EventStream {
+        self.stream(&EventChannel::ALL)
+    }
+
+    /// Receive every event of the given channels, none are skipped
+    ///
+    /// Unlike an `EventStream`, the queue is unbounded: events wait until they are handled, however
+    /// slow the consumer is. For consumers that must not miss events, e.g. action plugins. Dropping
+    /// the queue ends the subscription.
+    pub fn queue(&self, channels: &[EventChannel]) -> EventQueue {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.queues.lock().push((sender, channels.to_vec()));
+        EventQueue { receiver }
+    }
+
     /// Publish an event to all relevant subscribers
     pub fn publish(&self, event: PlayerEvent) {
+        // Sending only fails if there is no stream, which isn't an error
+        let _ = self.broadcast.send(event.clone());
+
+        let channel = EventChannel::from(&event);
+        // Queues whose receiver was dropped are removed
+        self.queues.lock().retain(|(sender, channels)| {
+            if channels.contains(&channel) {
+                sender.send(event.clone()).is_ok()
+            } else {
+                !sender.is_closed()
+            }
+        });
+
         let subscribers = self.subscribers.lock();
         let event_type = EventSubscription::from(&event);
         
@@ -187,6 +340,87 @@ impl EventSubscriber {
     }
 }
 
+/// Receiver for every event of some channels of the bus, see `EventBus::queue`
+pub struct EventQueue {
+    receiver: mpsc::UnboundedReceiver<PlayerEvent>,
+}
+
+impl EventQueue {
+    /// Wait for the next event
+    ///
+    /// Returns `None` once the bus has been dropped.
+    pub async fn recv(&mut self) -> Option<PlayerEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Get the next event if one is waiting
+    pub fn try_recv(&mut self) -> Option<PlayerEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Receiver for the events of some channels of the bus
+///
+/// A stream that falls more than `BROADCAST_CAPACITY` events behind skips the oldest ones instead
+/// of slowing down the publishers.
+pub struct EventStream {
+    receiver: broadcast::Receiver<PlayerEvent>,
+    channels: Vec<EventChannel>,
+}
+
+impl EventStream {
+    /// Wait for the next event of the subscribed channels
+    ///
+    /// Returns `None` once the bus has been dropped.
+    pub async fn recv(&mut self) -> Option<PlayerEvent> {
+        loop {
+            let result = self.receiver.recv().await;
+            if let Some(event) = self.accept(result)? {
+                return Some(event);
+            }
+        }
+    }
+
+    /// Wait for the next event of the subscribed channels, blocking the current thread
+    ///
+    /// Must not be called from async code, use `recv` there.
+    pub fn blocking_recv(&mut self) -> Option<PlayerEvent> {
+        loop {
+            let result = self.receiver.blocking_recv();
+            if let Some(event) = self.accept(result)? {
+                return Some(event);
+            }
+        }
+    }
+
+    /// Get the next event of the subscribed channels if one is waiting
+    pub fn try_recv(&mut self) -> Option<PlayerEvent> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) if self.channels.contains(&EventChannel::from(&event)) => return Some(event),
+                Ok(_) => continue,
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    warn!("Event stream fell behind, skipped {} events", skipped);
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Filter a received event, `None` if the bus is closed, `Some(None)` if the event is skipped
+    fn accept(&self, result: Result<PlayerEvent, RecvError>) -> Option<Option<PlayerEvent>> {
+        match result {
+            Ok(event) if self.channels.contains(&EventChannel::from(&event)) => Some(Some(event)),
+            Ok(_) => Some(None),
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Event stream fell behind, skipped {} events", skipped);
+                Some(None)
+            }
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +484,113 @@ mod tests {
         // Should not receive the event after unsubscribing
         assert!(receiver.try_recv().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stream_channels() {
+        let bus = EventBus::new();
+        let mut volume_stream = bus.stream(&[EventChannel::Volume]);
+        let mut all_stream = bus.stream_all();
+
+        let source = PlayerSource::new("test".to_string(), "1".to_string());
+        bus.publish(PlayerEvent::StateChanged {
+            source,
+            state: PlaybackState::Playing,
+        });
+        bus.publish(PlayerEvent::VolumeChanged {
+            control_name: "Master".to_string(),
+            display_name: "Master".to_string(),
+            percentage: 50.0,
+            decibels: None,
+            raw_value: None,
+        });
+
+        assert!(matches!(volume_stream.try_recv(), Some(PlayerEvent::VolumeChanged { .. })));
+        assert!(volume_stream.try_recv().is_none());
+
+        assert!(matches!(all_stream.try_recv(), Some(PlayerEvent::StateChanged { .. })));
+        assert!(matches!(all_stream.try_recv(), Some(PlayerEvent::VolumeChanged { .. })));
+    }
+
+    #[test]
+    fn test_position_channel() {
+        let bus = EventBus::new();
+        let mut player_stream = bus.stream(&[EventChannel::Player]);
+        let mut position_stream = bus.stream(&[EventChannel::Position]);
+
+        let source = PlayerSource::new("test".to_string(), "1".to_string());
+        bus.publish(PlayerEvent::PositionChanged {
+            source,
+            position: 12.5,
+        });
+
+        assert!(player_stream.try_recv().is_none());
+        assert!(matches!(position_stream.try_recv(), Some(PlayerEvent::PositionChanged { .. })));
+    }
+
+    #[test]
+    fn test_queue_keeps_all_events() {
+        let bus = EventBus::new();
+        let mut queue = bus.queue(&[EventChannel::Player]);
+        let mut stream = bus.stream(&[EventChannel::Player]);
+
+        let source = PlayerSource::new("test".to_string(), "1".to_string());
+        // Not on the channel of the queue
+        bus.publish(PlayerEvent::PositionChanged {
+            source: source.clone(),
+            position: 1.0,
+        });
+        let count = BROADCAST_CAPACITY * 2;
+        for _ in 0..count {
+            bus.publish(PlayerEvent::StateChanged {
+                source: source.clone(),
+                state: PlaybackState::Playing,
+            });
+        }
+
+        let mut received = 0;
+        while let Some(event) = queue.try_recv() {
+            assert!(matches!(event, PlayerEvent::StateChanged { .. }));
+            received += 1;
+        }
+        assert_eq!(received, count);
+
+        // The stream skipped the oldest events
+        let mut streamed = 0;
+        while stream.try_recv().is_some() {
+            streamed += 1;
+        }
+        assert_eq!(streamed, BROADCAST_CAPACITY);
+    }
+
+    #[test]
+    fn test_queue_dropped() {
+        let bus = EventBus::new();
+        let queue = bus.queue(&EventChannel::ALL);
+        drop(queue);
+
+        let source = PlayerSource::new("test".to_string(), "1".to_string());
+        bus.publish(PlayerEvent::StateChanged {
+            source,
+            state: PlaybackState::Stopped,
+        });
+        assert!(bus.queues.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_recv() {
+        let bus = EventBus::new();
+        let mut stream = bus.stream(&[EventChannel::Player]);
+
+        let source = PlayerSource::new("test".to_string(), "1".to_string());
+        bus.publish(PlayerEvent::StateChanged {
+            source,
+            state: PlaybackState::Paused,
+        });
+
+        let received = stream.recv().await;
+        assert!(matches!(received, Some(PlayerEvent::StateChanged { state: PlaybackState::Paused, .. })));
+
+        drop(bus);
+        assert!(stream.recv().await.is_none());
+    }
+}
@@ -0,0 +1,392 @@
+use crate::audiocontrol::AudioController;
+use crate::data::library::{LibraryError, LibraryInterface};
+use crate::data::{Album, Artist, Identifier, LoopMode, PlaybackState, PlayerCapabilitySet, PlayerCommand, Song, Track};
+use crate::players::PlayerController;
+use log::{debug, warn};
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Name under which the libraries of all players are available as one
+pub const AGGREGATED_LIBRARY_NAME: &str = "all";
+
+/// An artist of the aggregated library with the artists it was merged from
+struct ArtistGroup {
+    artist: Artist,
+    /// Index of the library and ID of the artist in it
+    members: Vec<(usize, Identifier)>,
+}
+
+/// The libraries of all players merged into one collection
+///
+/// Artists are the same if they share a MusicBrainz ID or, without one, their name
+/// regardless of case. Merged artists get an ID of their own. Albums with the same
+/// name and artists are listed once, the library of the first player wins.
+pub struct AggregatedLibrary {
+    /// Player name and library, in the order of the players
+    libraries: Vec<(String, Box<dyn LibraryInterface>)>,
+    /// Merged artists, built on first use
+    groups: OnceLock<Vec<ArtistGroup>>,
+}
+
+impl AggregatedLibrary {
+    pub fn with_libraries(libraries: Vec<(String, Box<dyn LibraryInterface>)>) -> Self {
+        Self { libraries, groups: OnceLock::new() }
+    }
+
+    /// Names of the players whose libraries are merged
+    pub fn player_names(&self) -> Vec<String> {
+        self.libraries.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn artist_groups(&self) -> &[ArtistGroup] {
+        self.groups.get_or_init(|| self.merge_artists())
+    }
+
+    fn merge_artists(&self) -> Vec<ArtistGroup> {
+        let mut groups: Vec<ArtistGroup> = Vec::new();
+        let mut by_mbid: HashMap<String, usize> = HashMap::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+
+        for (library_index, (_, library)) in self.libraries.iter().enumerate() {
+            for artist in library.get_artists() {
+                let mbids: Vec<String> = artist
+                    .metadata
+                    .as_ref()
+                    .map(|meta| meta.mbid.iter().map(|m| m.to_lowercase()).collect())
+                    .unwrap_or_default();
+                let name = artist.name.trim().to_lowercase();
+                let existing = mbids
+                    .iter()
+                    .find_map(|mbid| by_mbid.get(mbid))
+                    .or_else(|| by_name.get(&name))
+                    .copied();
+
+                let group_index = match existing {
+                    Some(index) => {
+                        let group = &mut groups[index];
+                        if group.artist.metadata.is_none() {
+                            group.artist.metadata = artist.metadata.clone();
+                        }
+                        group.members.push((library_index, artist.id.clone()));
+                        index
+                    }
+                    None => {
+                        let key = mbids.first().map(|mbid| format!("mbid:{}", mbid)).unwrap_or_else(|| format!("name:{}", name));
+                        let mut merged = artist.clone();
+                        merged.id = Identifier::Numeric(hash_key(&key));
+                        groups.push(ArtistGroup {
+                            artist: merged,
+                            members: vec![(library_index, artist.id.clone())],
+                        });
+                        groups.len() - 1
+                    }
+                };
+                for mbid in mbids {
+                    by_mbid.entry(mbid).or_insert(group_index);
+                }
+                by_name.entry(name).or_insert(group_index);
+            }
+        }
+        groups
+    }
+}
+
+fn hash_key(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Albums with the same name and artists are the same album
+fn album_key(album: &Album) -> (String, String) {
+    let artists = album.artists_flat.clone().unwrap_or_else(|| album.artists.lock().join(", "));
+    (artists.trim().to_lowercase(), album.name.trim().to_lowercase())
+}
+
+/// Remove albums that are already in the list, keeps the first one
+fn dedup_albums(albums: impl IntoIterator<Item = Album>) -> Vec<Album> {
+    let mut seen = HashSet::new();
+    albums.into_iter().filter(|album| seen.insert(album_key(album))).collect()
+}
+
+impl LibraryInterface for AggregatedLibrary {
+    fn new() -> Self {
+        Self::with_libraries(Vec::new())
+    }
+
+    /// Loaded when the libraries of all players are loaded
+    fn is_loaded(&self) -> bool {
+        self.libraries.iter().all(|(_, library)| library.is_loaded())
+    }
+
+    fn refresh_library(&self) -> Result<(), LibraryError> {
+        let mut result = Ok(());
+        for (name, library) in &self.libraries {
+            if let Err(e) = library.refresh_library() {
+                warn!("Failed to refresh the library of {}: {}", name, e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    fn get_albums(&self) -> Vec<Album> {
+        dedup_albums(self.libraries.iter().flat_map(|(_, library)| library.get_albums()))
+    }
+
+    fn get_artists(&self) -> Vec<Artist> {
+        self.artist_groups().iter().map(|group| group.artist.clone()).collect()
+    }
+
+    fn get_album_by_artist_and_name(&self, artist: &str, album: &str) -> Option<Album> {
+        self.libraries
+            .iter()
+            .find_map(|(_, library)| library.get_album_by_artist_and_name(artist, album))
+    }
+
+    fn get_album_by_id(&self, id: &Identifier) -> Option<Album> {
+        self.libraries.iter().find_map(|(_, library)| library.get_album_by_id(id))
+    }
+
+    fn get_artist_by_name(&self, name: &str) -> Option<Artist> {
+        let groups = self.artist_groups();
+        let name_lower = name.trim().to_lowercase();
+        groups
+            .iter()
+            .find(|group| group.artist.name == name)
+            .or_else(|| groups.iter().find(|group| group.artist.name.to_lowercase() == name_lower))
+            .map(|group| group.artist.clone())
+    }
+
+    fn get_albums_by_artist_id(&self, artist_id: &Identifier) -> Vec<Album> {
+        let Some(group) = self.artist_groups().iter().find(|group| &group.artist.id == artist_id) else {
+            return Vec::new();
+        };
+        dedup_albums(
+            group
+                .members
+                .iter()
+                .flat_map(|(index, id)| self.libraries[*index].1.get_albums_by_artist_id(id)),
+        )
+    }
+
+    fn force_update(&self) -> bool {
+        let mut started = false;
+        for (name, library) in &self.libraries {
+            if library.force_update() {
+                debug!("Started an update of the library of {}", name);
+                started = true;
+            }
+        }
+        started
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn get_image(&self, identifier: String) -> Option<(Vec<u8>, String)> {
+        self.libraries.iter().find_map(|(_, library)| library.get_image(identifier.clone()))
+    }
+
+    fn update_artist_metadata(&self) {
+        for (_, library) in &self.libraries {
+            library.update_artist_metadata();
+        }
+    }
+
+    fn get_meta_keys(&self) -> Vec<String> {
+        vec!["players".to_string()]
+    }
+
+    fn get_metadata_value(&self, key: &str) -> Option<String> {
+        match key {
+            "players" => serde_json::to_string(&self.player_names()).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A player without playback that offers the aggregated library
+///
+/// The library API finds libraries by player name, this makes the libraries of all
+/// players available as the player "all".
+pub struct AggregatedLibraryPlayer {
+    controller: AudioController,
+}
+
+impl AggregatedLibraryPlayer {
+    pub fn new(controller: &AudioController) -> Self {
+        Self { controller: controller.clone() }
+    }
+}
+
+impl PlayerController for AggregatedLibraryPlayer {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        PlayerCapabilitySet::empty()
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        None
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        Vec::new()
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        LoopMode::None
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        PlaybackState::Stopped
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        None
+    }
+
+    fn get_shuffle(&self) -> bool {
+        false
+    }
+
+    fn get_player_name(&self) -> String {
+        AGGREGATED_LIBRARY_NAME.to_string()
+    }
+
+    fn get_player_id(&self) -> String {
+        AGGREGATED_LIBRARY_NAME.to_string()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        None
+    }
+
+    fn get_library(&self) -> Option<Box<dyn LibraryInterface>> {
+        Some(Box::new(self.controller.aggregated_library()))
+    }
+
+    /// Commands can't be sent to all players at once
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        debug!("Ignoring {} sent to the aggregated library", command);
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        true
+    }
+
+    fn stop(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ArtistMeta;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    struct TestLibrary {
+        artists: Vec<Artist>,
+        albums: Vec<Album>,
+    }
+
+    impl LibraryInterface for TestLibrary {
+        fn new() -> Self {
+            Self { artists: Vec::new(), albums: Vec::new() }
+        }
+        fn is_loaded(&self) -> bool {
+            true
+        }
+        fn refresh_library(&self) -> Result<(), LibraryError> {
+            Ok(())
+        }
+        fn get_albums(&self) -> Vec<Album> {
+            self.albums.clone()
+        }
+        fn get_artists(&self) -> Vec<Artist> {
+            self.artists.clone()
+        }
+        fn get_album_by_artist_and_name(&self, _artist: &str, _album: &str) -> Option<Album> {
+            None
+        }
+        fn get_album_by_id(&self, id: &Identifier) -> Option<Album> {
+            self.albums.iter().find(|a| &a.id == id).cloned()
+        }
+        fn get_artist_by_name(&self, name: &str) -> Option<Artist> {
+            self.artists.iter().find(|a| a.name == name).cloned()
+        }
+        fn get_albums_by_artist_id(&self, artist_id: &Identifier) -> Vec<Album> {
+            let Some(artist) = self.artists.iter().find(|a| &a.id == artist_id) else {
+                return Vec::new();
+            };
+            self.albums.iter().filter(|a| a.artists.lock().contains(&artist.name)).cloned().collect()
+        }
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+        fn get_image(&self, _identifier: String) -> Option<(Vec<u8>, String)> {
+            None
+        }
+        fn update_artist_metadata(&self) {}
+    }
+
+    fn artist(id: u64, name: &str, mbid: Option<&str>) -> Artist {
+        let metadata = mbid.map(|mbid| {
+            let mut meta = ArtistMeta::new();
+            meta.add_mbid(mbid.to_string());
+            meta
+        });
+        Artist { id: Identifier::Numeric(id), name: name.to_string(), is_multi: false, metadata }
+    }
+
+    fn album(id: u64, artist: &str, name: &str) -> Album {
+        Album {
+            id: Identifier::Numeric(id),
+            name: name.to_string(),
+            artists: Arc::new(Mutex::new(vec![artist.to_string()])),
+            artists_flat: None,
+            release_date: None,
+            tracks: Arc::new(Mutex::new(vec![Track::with_name("Track".to_string())])),
+            cover_art: None,
+            uri: None,
+            genres: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_aggregated_library() {
+        let mpd = TestLibrary {
+            artists: vec![artist(1, "The Beatles", Some("b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d")), artist(2, "Miles Davis", None)],
+            albums: vec![album(10, "The Beatles", "Abbey Road"), album(11, "Miles Davis", "Kind of Blue")],
+        };
+        let lms = TestLibrary {
+            artists: vec![artist(1, "Beatles", Some("B10BBBFC-CF9E-42E0-BE17-E2C3E1D2600D")), artist(3, "miles davis", None)],
+            albums: vec![album(20, "Beatles", "Let It Be"), album(21, "miles davis", "KIND OF BLUE")],
+        };
+        let library = AggregatedLibrary::with_libraries(vec![
+            ("mpd".to_string(), Box::new(mpd) as Box<dyn LibraryInterface>),
+            ("lms".to_string(), Box::new(lms)),
+        ]);
+
+        let artists = library.get_artists();
+        assert_eq!(artists.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["The Beatles", "Miles Davis"]);
+        assert_eq!(library.get_albums().len(), 3);
+
+        let beatles = library.get_artist_by_name("the beatles").unwrap();
+        assert_ne!(beatles.id, Identifier::Numeric(1));
+        let albums = library.get_albums_by_artist_id(&beatles.id);
+        assert_eq!(albums.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["Abbey Road", "Let It Be"]);
+        assert_eq!(library.get_album_by_id(&Identifier::Numeric(20)).unwrap().name, "Let It Be");
+        assert!(library.get_albums_by_artist_id(&Identifier::Numeric(1)).is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Controllers that represent the same physical player
+///
+/// E.g. `mpd` and `org.mpris.MediaPlayer2.mpd` both report and control MPD. Players
+/// are given by name or id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergedPlayer {
+    /// The richer backend, used for library, queue and commands
+    pub primary: String,
+
+    /// Other controllers of the same player, only used if the primary is not available
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+}
+
+/// Configuration of the `merged_players` service
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergedPlayersConfig {
+    #[serde(default)]
+    pub players: Vec<MergedPlayer>,
+}
+
+fn matches(pattern: &str, name: &str, id: &str) -> bool {
+    pattern.eq_ignore_ascii_case(name) || pattern.eq_ignore_ascii_case(id)
+}
+
+impl MergedPlayer {
+    pub fn is_primary(&self, name: &str, id: &str) -> bool {
+        matches(&self.primary, name, id)
+    }
+
+    pub fn is_mirror(&self, name: &str, id: &str) -> bool {
+        self.mirrors.iter().any(|m| matches(m, name, id))
+    }
+
+    pub fn contains(&self, name: &str, id: &str) -> bool {
+        self.is_primary(name, id) || self.is_mirror(name, id)
+    }
+}
+
+impl MergedPlayersConfig {
+    /// The group a player belongs to, as primary or as mirror
+    pub fn group_of(&self, name: &str, id: &str) -> Option<&MergedPlayer> {
+        self.players.iter().find(|p| p.contains(name, id))
+    }
+
+    /// Whether two players are the same physical player
+    pub fn same_player(&self, a: (&str, &str), b: (&str, &str)) -> bool {
+        self.group_of(a.0, a.1).is_some_and(|group| group.contains(b.0, b.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merged_players() {
+        let config: MergedPlayersConfig = serde_json::from_value(serde_json::json!({
+            "players": [{"primary": "mpd", "mirrors": ["org.mpris.MediaPlayer2.mpd"]}]
+        }))
+        .unwrap();
+
+        let group = config.group_of("mpris", "org.mpris.MediaPlayer2.mpd").unwrap();
+        assert!(group.is_mirror("mpris", "org.mpris.MediaPlayer2.mpd"));
+        assert!(group.is_primary("mpd", "localhost:6600"));
+        assert!(config.same_player(("MPD", "localhost:6600"), ("mpris", "org.mpris.MediaPlayer2.mpd")));
+        assert!(!config.same_player(("mpd", "localhost:6600"), ("spotify", "spotify")));
+        assert!(config.group_of("spotify", "spotify").is_none());
+    }
+}
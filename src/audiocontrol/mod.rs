@@ -2,8 +2,18 @@
 pub mod audiocontrol;
 // EventBus for distributing PlayerEvents to subscribers
 pub mod eventbus;
+// Orchestrated switching between players
+pub mod transition;
+// Quiet hours and daily playback time budgets
+pub mod playbacklimits;
+// Controllers representing the same physical player
+pub mod mergedplayers;
+// Rules deciding which player becomes active
+pub mod priority;
+// Libraries of all players merged into one
+pub mod library;
 
 // Re-export the AudioController
 pub use audiocontrol::AudioController;
 // Re-export the EventBus and related types
-pub use eventbus::{EventBus, EventSubscription, EventSubscriber, SubscriberId};
\ No newline at end of file
+pub use eventbus::{EventBus, EventChannel, EventQueue, EventStream, EventSubscription, EventSubscriber, SubscriberId};
\ No newline at end of file
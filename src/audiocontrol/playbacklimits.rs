@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use crate::data::{PlaybackState, PlayerCommand};
+
+/// Key in the settings database storing today's playback time
+pub const USAGE_KEY: &str = "playback_limits.usage";
+
+const TIME_FORMAT: &str = "%H:%M";
+
+fn default_true() -> bool {
+    true
+}
+
+/// A period in which playback is blocked or the volume is capped
+///
+/// Periods may cross midnight, e.g. from 21:00 to 07:00. `days` are the days on
+/// which the period starts ("mon" to "sun"), an empty list matches every day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Refuse to start playback during this period
+    #[serde(default = "default_true")]
+    pub block_playback: bool,
+    /// Maximum volume in percent during this period
+    #[serde(default)]
+    pub max_volume: Option<f64>,
+}
+
+fn weekday_name(date: NaiveDate) -> String {
+    date.weekday().to_string().to_lowercase()
+}
+
+fn matches_day(days: &[String], date: NaiveDate) -> bool {
+    let name = weekday_name(date);
+    days.is_empty() || days.iter().any(|d| d.to_lowercase().starts_with(&name))
+}
+
+impl QuietHours {
+    fn times(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let start = NaiveTime::parse_from_str(&self.start, TIME_FORMAT).ok()?;
+        let end = NaiveTime::parse_from_str(&self.end, TIME_FORMAT).ok()?;
+        Some((start, end))
+    }
+
+    /// Check whether the period is active at the given local time
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let Some((start, end)) = self.times() else {
+            return false;
+        };
+        let time = now.time();
+        let today = now.date();
+
+        if start <= end {
+            time >= start && time < end && matches_day(&self.days, today)
+        } else if time >= start {
+            matches_day(&self.days, today)
+        } else {
+            time < end && matches_day(&self.days, today - Duration::days(1))
+        }
+    }
+}
+
+/// Configuration of playback time limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackLimitsConfig {
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+    /// Minutes of playback per day
+    #[serde(default)]
+    pub daily_minutes: Option<u64>,
+    /// Minutes of playback for specific days ("mon" to "sun"), overriding `daily_minutes`
+    #[serde(default)]
+    pub daily_minutes_by_day: HashMap<String, u64>,
+    /// PIN required to lift the limits temporarily, overrides are disabled without it
+    #[serde(default)]
+    pub override_pin: Option<String>,
+    /// Default duration of an override
+    #[serde(default = "default_override_minutes")]
+    pub override_minutes: u64,
+    /// Seconds between two checks of the players
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_override_minutes() -> u64 {
+    60
+}
+
+fn default_poll_interval_secs() -> u64 {
+    10
+}
+
+impl Default for PlaybackLimitsConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            quiet_hours: Vec::new(),
+            daily_minutes: None,
+            daily_minutes_by_day: HashMap::new(),
+            override_pin: None,
+            override_minutes: default_override_minutes(),
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+impl PlaybackLimitsConfig {
+    /// Check the configuration for invalid times and day names
+    pub fn validate(&self) -> Result<(), String> {
+        for period in &self.quiet_hours {
+            if period.times().is_none() {
+                return Err(format!("Invalid quiet hours {}-{}, times must be HH:MM", period.start, period.end));
+            }
+        }
+        let days = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+        let all_days = self.quiet_hours.iter().flat_map(|p| p.days.iter()).chain(self.daily_minutes_by_day.keys());
+        for day in all_days {
+            if !days.iter().any(|d| day.to_lowercase().starts_with(d)) {
+                return Err(format!("Invalid day '{}', use mon to sun", day));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the playback budget for a day, None if playback time is unlimited
+    pub fn budget_minutes(&self, date: NaiveDate) -> Option<u64> {
+        let name = weekday_name(date);
+        self.daily_minutes_by_day
+            .iter()
+            .find(|(day, _)| day.to_lowercase().starts_with(&name))
+            .map(|(_, minutes)| *minutes)
+            .or(self.daily_minutes)
+    }
+}
+
+/// Playback time used on one day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub date: NaiveDate,
+    pub seconds: u64,
+}
+
+/// Current effect of the playback limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackLimitStatus {
+    pub enabled: bool,
+    pub playback_allowed: bool,
+    /// Why playback is not allowed
+    pub reason: Option<String>,
+    /// Maximum volume in percent
+    pub max_volume: Option<f64>,
+    pub quiet_hours_active: bool,
+    pub used_minutes: u64,
+    pub budget_minutes: Option<u64>,
+    pub remaining_minutes: Option<u64>,
+    /// Local time until which the limits are lifted
+    pub override_until: Option<NaiveDateTime>,
+}
+
+/// Playback limits with the playback time used today and an active override
+#[derive(Debug, Clone)]
+pub struct PlaybackLimits {
+    config: PlaybackLimitsConfig,
+    usage: Option<DailyUsage>,
+    override_until: Option<NaiveDateTime>,
+}
+
+impl PlaybackLimits {
+    pub fn new(config: PlaybackLimitsConfig) -> Self {
+        Self {
+            config,
+            usage: None,
+            override_until: None,
+        }
+    }
+
+    pub fn config(&self) -> &PlaybackLimitsConfig {
+        &self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enable
+    }
+
+    /// Restore the usage, e.g. after a restart. Usage of another day is ignored.
+    pub fn restore_usage(&mut self, usage: DailyUsage) {
+        self.usage = Some(usage);
+    }
+
+    pub fn usage(&self) -> Option<DailyUsage> {
+        self.usage
+    }
+
+    /// Add playback time, starting a new count when the day has changed
+    pub fn add_usage(&mut self, seconds: u64, now: NaiveDateTime) -> DailyUsage {
+        let today = now.date();
+        let usage = match self.usage {
+            Some(usage) if usage.date == today => DailyUsage { date: today, seconds: usage.seconds + seconds },
+            _ => DailyUsage { date: today, seconds },
+        };
+        self.usage = Some(usage);
+        usage
+    }
+
+    fn used_seconds(&self, date: NaiveDate) -> u64 {
+        self.usage.filter(|u| u.date == date).map(|u| u.seconds).unwrap_or(0)
+    }
+
+    /// Lift the limits until the given time
+    pub fn set_override(&mut self, until: Option<NaiveDateTime>) {
+        self.override_until = until;
+    }
+
+    pub fn status(&self, now: NaiveDateTime) -> PlaybackLimitStatus {
+        let override_until = self.override_until.filter(|until| *until > now);
+        let budget = self.config.budget_minutes(now.date());
+        let used_minutes = self.used_seconds(now.date()) / 60;
+        let remaining = budget.map(|b| b.saturating_sub(used_minutes));
+
+        let mut status = PlaybackLimitStatus {
+            enabled: self.config.enable,
+            playback_allowed: true,
+            reason: None,
+            max_volume: None,
+            quiet_hours_active: false,
+            used_minutes,
+            budget_minutes: budget,
+            remaining_minutes: remaining,
+            override_until,
+        };
+        if !self.config.enable || override_until.is_some() {
+            return status;
+        }
+
+        for period in self.config.quiet_hours.iter().filter(|p| p.contains(now)) {
+            status.quiet_hours_active = true;
+            if period.block_playback && status.playback_allowed {
+                status.playback_allowed = false;
+                status.reason = Some(format!("Quiet hours from {} to {}", period.start, period.end));
+            }
+            if let Some(max) = period.max_volume {
+                status.max_volume = Some(status.max_volume.map_or(max, |current| current.min(max)));
+            }
+        }
+
+        if status.playback_allowed && remaining == Some(0) {
+            status.playback_allowed = false;
+            status.reason = Some(format!("Daily playback time of {} minutes used up", budget.unwrap_or(0)));
+        }
+        status
+    }
+}
+
+/// Check whether a command starts playback on a player in the given state
+pub fn starts_playback(command: &PlayerCommand, state: PlaybackState) -> bool {
+    match command {
+        PlayerCommand::Play | PlayerCommand::PlayQueueIndex(_) | PlayerCommand::PlayNow { .. } => true,
+        PlayerCommand::PlayPause => state != PlaybackState::Playing,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_across_midnight() {
+        let period = QuietHours {
+            start: "21:00".to_string(),
+            end: "07:00".to_string(),
+            days: vec!["fri".to_string()],
+            block_playback: true,
+            max_volume: None,
+        };
+        // 2026-10-16 is a Friday
+        assert!(!period.contains(at("2026-10-16", "20:59")));
+        assert!(period.contains(at("2026-10-16", "21:00")));
+        assert!(period.contains(at("2026-10-17", "06:59")));
+        assert!(!period.contains(at("2026-10-17", "07:00")));
+        assert!(!period.contains(at("2026-10-17", "22:00")));
+        assert!(!period.contains(at("2026-10-16", "03:00")));
+    }
+
+    #[test]
+    fn test_status() {
+        let config: PlaybackLimitsConfig = serde_json::from_value(serde_json::json!({
+            "enable": true,
+            "daily_minutes": 60,
+            "daily_minutes_by_day": {"sat": 120},
+            "quiet_hours": [
+                {"start": "12:00", "end": "14:00", "block_playback": false, "max_volume": 40},
+                {"start": "20:00", "end": "06:00"}
+            ]
+        })).unwrap();
+        assert!(config.validate().is_ok());
+        let mut limits = PlaybackLimits::new(config);
+
+        let noon = at("2026-10-16", "12:30");
+        let status = limits.status(noon);
+        assert!(status.playback_allowed);
+        assert!(status.quiet_hours_active);
+        assert_eq!(status.max_volume, Some(40.0));
+        assert_eq!(status.remaining_minutes, Some(60));
+
+        limits.add_usage(3600, noon);
+        let status = limits.status(at("2026-10-16", "15:00"));
+        assert!(!status.playback_allowed);
+        assert_eq!(status.remaining_minutes, Some(0));
+
+        // A new day starts a new count with the budget of that day
+        let saturday = at("2026-10-17", "10:00");
+        assert_eq!(limits.add_usage(60, saturday).seconds, 60);
+        assert_eq!(limits.status(saturday).budget_minutes, Some(120));
+        assert!(!limits.status(at("2026-10-17", "21:00")).playback_allowed);
+
+        limits.set_override(Some(at("2026-10-17", "22:00")));
+        assert!(limits.status(at("2026-10-17", "21:00")).playback_allowed);
+        assert!(!limits.status(at("2026-10-17", "22:30")).playback_allowed);
+    }
+
+    #[test]
+    fn test_starts_playback() {
+        assert!(starts_playback(&PlayerCommand::Play, PlaybackState::Paused));
+        assert!(starts_playback(&PlayerCommand::PlayPause, PlaybackState::Stopped));
+        assert!(!starts_playback(&PlayerCommand::PlayPause, PlaybackState::Playing));
+        assert!(!starts_playback(&PlayerCommand::Pause, PlaybackState::Playing));
+        assert!(PlaybackLimitsConfig { quiet_hours: vec![QuietHours {
+            start: "25:00".to_string(),
+            end: "07:00".to_string(),
+            days: vec![],
+            block_playback: true,
+            max_volume: None,
+        }], ..Default::default() }.validate().is_err());
+    }
+}
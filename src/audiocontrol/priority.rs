@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of decisions kept for the debug endpoint
+const HISTORY_SIZE: usize = 20;
+
+/// A rule deciding if a player that starts playing takes over from the active player
+///
+/// E.g. `{"player": "airplay", "active": "mpd", "preempt": true}` or
+/// `{"player": "bluetooth", "preempt": false}`. Players are given by name or id, `*` matches any player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityRule {
+    /// The player that starts playing
+    pub player: String,
+
+    /// The active player that is playing
+    #[serde(default = "any_player")]
+    pub active: String,
+
+    /// Whether the player becomes the active player
+    pub preempt: bool,
+}
+
+fn any_player() -> String {
+    "*".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_pin_minutes() -> u64 {
+    30
+}
+
+/// Configuration of the `source_priority` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcePriorityConfig {
+    /// Apply the rules, otherwise every player that starts playing becomes active
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Rules in order, the first matching rule decides
+    #[serde(default)]
+    pub rules: Vec<PriorityRule>,
+
+    /// Decision if no rule matches
+    #[serde(default = "default_true")]
+    pub default_preempt: bool,
+
+    /// Minutes a player activated via the API can't be preempted, 0 disables pinning
+    #[serde(default = "default_pin_minutes")]
+    pub pin_minutes: u64,
+
+    /// Pause players that are not allowed to take over
+    #[serde(default = "default_true")]
+    pub pause_denied: bool,
+}
+
+impl Default for SourcePriorityConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            rules: Vec::new(),
+            default_preempt: true,
+            pin_minutes: default_pin_minutes(),
+            pause_denied: true,
+        }
+    }
+}
+
+fn matches(pattern: &str, player: (&str, &str)) -> bool {
+    pattern == "*" || pattern.eq_ignore_ascii_case(player.0) || pattern.eq_ignore_ascii_case(player.1)
+}
+
+/// The player the user activated manually
+#[derive(Debug, Clone)]
+struct Pin {
+    player: (String, String),
+    until: Instant,
+}
+
+/// Result of evaluating the rules for a player that started playing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriorityDecision {
+    /// Player that started playing
+    pub player: String,
+    /// Active player at that time
+    pub active: Option<String>,
+    pub preempt: bool,
+    pub reason: String,
+    /// Time of the decision, e.g. "2025-10-16T08:30:00+02:00"
+    pub time: String,
+}
+
+/// State of the rules engine, returned by the debug endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct PriorityStatus {
+    pub config: SourcePriorityConfig,
+    /// Name of the pinned player
+    pub pinned_player: Option<String>,
+    /// Seconds until the pin expires
+    pub pin_remaining_secs: Option<u64>,
+    /// Latest decisions, most recent first
+    pub decisions: Vec<PriorityDecision>,
+}
+
+/// Rules engine deciding which player becomes active when several players play
+///
+/// A player that starts playing always becomes active if the active player isn't
+/// playing. Otherwise a manually pinned player wins, then the first matching rule
+/// and finally `default_preempt`.
+#[derive(Debug)]
+pub struct SourcePriority {
+    config: SourcePriorityConfig,
+    pin: Option<Pin>,
+    history: VecDeque<PriorityDecision>,
+}
+
+impl SourcePriority {
+    pub fn new(config: SourcePriorityConfig) -> Self {
+        Self {
+            config,
+            pin: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SourcePriorityConfig {
+        &self.config
+    }
+
+    /// Pin a player that was activated manually, does nothing if pinning is disabled
+    pub fn pin(&mut self, name: &str, id: &str) {
+        if !self.config.enable || self.config.pin_minutes == 0 {
+            return;
+        }
+        self.pin = Some(Pin {
+            player: (name.to_string(), id.to_string()),
+            until: Instant::now() + Duration::from_secs(self.config.pin_minutes * 60),
+        });
+    }
+
+    /// Remove the pin, returns false if no player was pinned
+    pub fn unpin(&mut self) -> bool {
+        self.pin.take().is_some_and(|pin| pin.until > Instant::now())
+    }
+
+    fn pinned(&self) -> Option<&Pin> {
+        self.pin.as_ref().filter(|pin| pin.until > Instant::now())
+    }
+
+    /// Decide if a player that started playing takes over from the active player
+    ///
+    /// `active` is the name and id of the active player and whether it is playing.
+    pub fn decide(&mut self, player: (&str, &str), active: Option<((&str, &str), bool)>) -> PriorityDecision {
+        let (preempt, reason) = self.evaluate(player, active);
+        let decision = PriorityDecision {
+            player: player.0.to_string(),
+            active: active.map(|((name, _), _)| name.to_string()),
+            preempt,
+            reason,
+            time: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        };
+        self.history.push_front(decision.clone());
+        self.history.truncate(HISTORY_SIZE);
+        decision
+    }
+
+    fn evaluate(&self, player: (&str, &str), active: Option<((&str, &str), bool)>) -> (bool, String) {
+        let Some((active, active_playing)) = active else {
+            return (true, "no active player".to_string());
+        };
+        if !self.config.enable {
+            return (true, "priority rules are disabled".to_string());
+        }
+        if let Some(pin) = self.pinned() {
+            if pin.player.0 == active.0 && pin.player.1 == active.1 {
+                let minutes = pin.until.saturating_duration_since(Instant::now()).as_secs().div_ceil(60);
+                return (false, format!("{} is pinned for {} more minutes", active.0, minutes));
+            }
+        }
+        if !active_playing {
+            return (true, format!("{} is not playing", active.0));
+        }
+        match self
+            .config
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| matches(&rule.player, player) && matches(&rule.active, active))
+        {
+            Some((idx, rule)) => (
+                rule.preempt,
+                format!("rule {} ({} over {}: {})", idx + 1, rule.player, rule.active, rule.preempt),
+            ),
+            None => (self.config.default_preempt, "no rule matches".to_string()),
+        }
+    }
+
+    pub fn status(&self) -> PriorityStatus {
+        let pin = self.pinned();
+        PriorityStatus {
+            config: self.config.clone(),
+            pinned_player: pin.map(|pin| pin.player.0.clone()),
+            pin_remaining_secs: pin.map(|pin| pin.until.saturating_duration_since(Instant::now()).as_secs()),
+            decisions: self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> SourcePriority {
+        SourcePriority::new(
+            serde_json::from_value(serde_json::json!({
+                "enable": true,
+                "rules": [
+                    {"player": "airplay", "active": "mpd", "preempt": true},
+                    {"player": "bluetooth", "preempt": false},
+                ],
+                "default_preempt": false,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut priority = engine();
+        let mpd = Some((("mpd", "localhost:6600"), true));
+
+        assert!(priority.decide(("AirPlay", "shairport"), mpd).preempt);
+        assert!(!priority.decide(("bluetooth", "bluez"), mpd).preempt);
+        assert!(!priority.decide(("spotify", "librespot"), mpd).preempt);
+        // An active player that doesn't play is always replaced
+        assert!(priority.decide(("bluetooth", "bluez"), Some((("mpd", "localhost:6600"), false))).preempt);
+        assert!(priority.decide(("bluetooth", "bluez"), None).preempt);
+        assert_eq!(priority.status().decisions.len(), 5);
+        assert_eq!(priority.status().decisions[0].reason, "no active player");
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut priority = engine();
+        priority.pin("mpd", "localhost:6600");
+        let decision = priority.decide(("airplay", "shairport"), Some((("mpd", "localhost:6600"), false)));
+        assert!(!decision.preempt);
+        assert_eq!(decision.reason, "mpd is pinned for 30 more minutes");
+        assert_eq!(priority.status().pinned_player.as_deref(), Some("mpd"));
+
+        assert!(priority.unpin());
+        assert!(!priority.unpin());
+        assert!(priority.decide(("airplay", "shairport"), Some((("mpd", "localhost:6600"), true))).preempt);
+    }
+}
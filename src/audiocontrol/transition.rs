@@ -0,0 +1,213 @@
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::data::PlayerCommand;
+
+/// Number of volume steps used for a fade
+const FADE_STEPS: u64 = 20;
+
+/// What happens to the previous player when switching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OldPlayerAction {
+    #[default]
+    Pause,
+    Stop,
+    /// Keep the old player running, e.g. for passive sources that can't be controlled
+    None,
+}
+
+impl OldPlayerAction {
+    pub fn command(&self) -> Option<PlayerCommand> {
+        match self {
+            OldPlayerAction::Pause => Some(PlayerCommand::Pause),
+            OldPlayerAction::Stop => Some(PlayerCommand::Stop),
+            OldPlayerAction::None => None,
+        }
+    }
+}
+
+/// Overrides for switching between specific players
+///
+/// `from` and `to` are player names, a missing value matches every player.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransitionRule {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub fade: Option<bool>,
+    #[serde(default)]
+    pub fade_out_ms: Option<u64>,
+    #[serde(default)]
+    pub fade_in_ms: Option<u64>,
+    #[serde(default)]
+    pub old_player: Option<OldPlayerAction>,
+    #[serde(default)]
+    pub wait_timeout_ms: Option<u64>,
+}
+
+impl TransitionRule {
+    fn matches(&self, from: &str, to: &str) -> bool {
+        let matches = |pattern: &Option<String>, name: &str| {
+            pattern.as_ref().is_none_or(|p| p == "*" || p.eq_ignore_ascii_case(name))
+        };
+        matches(&self.from, from) && matches(&self.to, to)
+    }
+
+    /// Rules naming both players win over rules naming one of them
+    fn specificity(&self) -> usize {
+        let named = |pattern: &Option<String>| pattern.as_ref().is_some_and(|p| p != "*");
+        usize::from(named(&self.from)) + usize::from(named(&self.to))
+    }
+}
+
+/// Configuration for switching the active player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionConfig {
+    /// Orchestrate switches when another player starts playing
+    #[serde(default)]
+    pub enable: bool,
+    /// Fade the volume when switching players
+    #[serde(default = "default_fade")]
+    pub fade: bool,
+    #[serde(default = "default_fade_ms")]
+    pub fade_out_ms: u64,
+    #[serde(default = "default_fade_ms")]
+    pub fade_in_ms: u64,
+    #[serde(default)]
+    pub old_player: OldPlayerAction,
+    /// Maximum time to wait for the new player to report Playing
+    #[serde(default = "default_wait_timeout_ms")]
+    pub wait_timeout_ms: u64,
+    #[serde(default)]
+    pub rules: Vec<TransitionRule>,
+}
+
+fn default_fade() -> bool {
+    true
+}
+
+fn default_fade_ms() -> u64 {
+    500
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    5000
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            fade: default_fade(),
+            fade_out_ms: default_fade_ms(),
+            fade_in_ms: default_fade_ms(),
+            old_player: OldPlayerAction::default(),
+            wait_timeout_ms: default_wait_timeout_ms(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Settings for one switch between two players
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionSettings {
+    pub fade: bool,
+    pub fade_out: Duration,
+    pub fade_in: Duration,
+    pub old_player: OldPlayerAction,
+    pub wait_timeout: Duration,
+}
+
+impl TransitionConfig {
+    /// Get the settings for switching from one player to another
+    ///
+    /// Matching rules are applied from the least to the most specific one,
+    /// so a rule for both players overrides a rule for only the target.
+    pub fn resolve(&self, from: &str, to: &str) -> TransitionSettings {
+        let mut settings = TransitionSettings {
+            fade: self.fade,
+            fade_out: Duration::from_millis(self.fade_out_ms),
+            fade_in: Duration::from_millis(self.fade_in_ms),
+            old_player: self.old_player,
+            wait_timeout: Duration::from_millis(self.wait_timeout_ms),
+        };
+
+        let mut rules: Vec<&TransitionRule> = self.rules.iter().filter(|r| r.matches(from, to)).collect();
+        rules.sort_by_key(|r| r.specificity());
+        for rule in rules {
+            if let Some(fade) = rule.fade {
+                settings.fade = fade;
+            }
+            if let Some(ms) = rule.fade_out_ms {
+                settings.fade_out = Duration::from_millis(ms);
+            }
+            if let Some(ms) = rule.fade_in_ms {
+                settings.fade_in = Duration::from_millis(ms);
+            }
+            if let Some(action) = rule.old_player {
+                settings.old_player = action;
+            }
+            if let Some(ms) = rule.wait_timeout_ms {
+                settings.wait_timeout = Duration::from_millis(ms);
+            }
+        }
+        settings
+    }
+}
+
+/// Ramp the global volume linearly from one percentage to another
+pub fn fade_volume(from: f64, to: f64, duration: Duration) {
+    let step_time = duration / FADE_STEPS as u32;
+    for step in 1..=FADE_STEPS {
+        let volume = from + (to - from) * step as f64 / FADE_STEPS as f64;
+        crate::helpers::global_volume::set_volume_percentage(volume);
+        thread::sleep(step_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rules() {
+        let config: TransitionConfig = serde_json::from_value(serde_json::json!({
+            "enable": true,
+            "fade_out_ms": 800,
+            "rules": [
+                {"to": "spotify", "fade_in_ms": 1500},
+                {"from": "mpd", "to": "spotify", "old_player": "stop"},
+                {"from": "*", "to": "bluetooth", "fade": false}
+            ]
+        })).unwrap();
+
+        let default = config.resolve("shairport", "mpd");
+        assert!(default.fade);
+        assert_eq!(default.fade_out, Duration::from_millis(800));
+        assert_eq!(default.fade_in, Duration::from_millis(500));
+        assert_eq!(default.old_player, OldPlayerAction::Pause);
+
+        let to_spotify = config.resolve("shairport", "spotify");
+        assert_eq!(to_spotify.fade_in, Duration::from_millis(1500));
+        assert_eq!(to_spotify.old_player, OldPlayerAction::Pause);
+
+        let mpd_to_spotify = config.resolve("MPD", "spotify");
+        assert_eq!(mpd_to_spotify.fade_in, Duration::from_millis(1500));
+        assert_eq!(mpd_to_spotify.old_player, OldPlayerAction::Stop);
+
+        assert!(!config.resolve("mpd", "bluetooth").fade);
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config: TransitionConfig = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(!config.enable);
+        let settings = config.resolve("a", "b");
+        assert!(settings.fade);
+        assert_eq!(settings.wait_timeout, Duration::from_secs(5));
+        assert_eq!(OldPlayerAction::None.command(), None);
+    }
+}
@@ -135,6 +135,95 @@ pub fn merge_player_includes(config: &mut serde_json::Value, config_dir: &Path)
     }
 }
 
+/// Merge service configurations from a `services.d/` include directory.
+///
+/// Scans `<config_dir>/services.d/` for `*.json` files. The service name is
+/// the file name up to the first dot, so `lastfm.json` and
+/// `lastfm.local.json` both configure `lastfm`. Each file contains the
+/// settings of the service and is merged into `"services"` in the main
+/// config: objects are merged key by key, other values are replaced.
+/// Files are loaded in alphabetical order, later files override earlier
+/// ones and all of them override the main config.
+///
+/// If the directory does not exist, this is a no-op. Malformed files
+/// are skipped with a warning.
+pub fn merge_service_includes(config: &mut serde_json::Value, config_dir: &Path) {
+    let services_d = config_dir.join("services.d");
+    if !services_d.is_dir() {
+        debug!("No services.d directory at {}, skipping", services_d.display());
+        return;
+    }
+
+    let mut files: Vec<_> = match fs::read_dir(&services_d) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to read services.d directory: {}", e);
+            return;
+        }
+    };
+    files.sort_by_key(|e| e.file_name());
+
+    for entry in files {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let service = file_name.split('.').next().unwrap_or_default().to_string();
+        if service.is_empty() {
+            warn!("Skipping {}: no service name", path.display());
+            continue;
+        }
+        let value = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<serde_json::Value>(&content) {
+                Ok(value) if value.is_object() => value,
+                Ok(_) => {
+                    warn!("Skipping {}: not a JSON object", path.display());
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to parse {}: {}", path.display(), e);
+                    continue;
+                }
+            },
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        // A legacy top-level section is the base, it would be hidden by the services section otherwise
+        let base = get_service_config(config, &service).cloned();
+        if !config.get("services").is_some_and(|s| s.is_object()) {
+            config["services"] = serde_json::json!({});
+        }
+        let section = config["services"]
+            .as_object_mut()
+            .unwrap()
+            .entry(service.clone())
+            .or_insert_with(|| base.unwrap_or_else(|| serde_json::json!({})));
+        merge_json(section, value);
+        info!("Loaded {} configuration from {}", service, path.display());
+    }
+}
+
+/// Merge `overlay` into `base`, objects key by key, everything else is replaced
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +353,40 @@ mod tests {
         assert_eq!(players.len(), 1);
     }
 
+    #[test]
+    fn test_service_includes_override_main_config() {
+        let tmp = TempDir::new().unwrap();
+        let services_d = tmp.path().join("services.d");
+        fs::create_dir(&services_d).unwrap();
+        fs::write(services_d.join("lastfm.json"), r#"{"enable": true, "scrobble": {"min_seconds": 30}}"#).unwrap();
+        fs::write(services_d.join("lastfm.local.json"), r#"{"scrobble": {"now_playing": false}}"#).unwrap();
+        fs::write(services_d.join("theaudiodb.json"), r#"{"api_key": "abc"}"#).unwrap();
+        fs::write(services_d.join("broken.json"), "{").unwrap();
+
+        let mut config = json!({"services": {"lastfm": {"enable": false, "api_key": "xyz"}}});
+        merge_service_includes(&mut config, tmp.path());
+
+        let lastfm = &config["services"]["lastfm"];
+        assert_eq!(lastfm["enable"], true);
+        assert_eq!(lastfm["api_key"], "xyz");
+        assert_eq!(lastfm["scrobble"], json!({"min_seconds": 30, "now_playing": false}));
+        assert_eq!(config["services"]["theaudiodb"]["api_key"], "abc");
+        assert!(config["services"].get("broken").is_none());
+    }
+
+    #[test]
+    fn test_service_includes_extend_legacy_section() {
+        let tmp = TempDir::new().unwrap();
+        let services_d = tmp.path().join("services.d");
+        fs::create_dir(&services_d).unwrap();
+        fs::write(services_d.join("spotify.json"), r#"{"enable": false}"#).unwrap();
+
+        let mut config = json!({"spotify": {"enable": true, "client_id": "id"}});
+        merge_service_includes(&mut config, tmp.path());
+
+        assert_eq!(get_service_config(&config, "spotify").unwrap(), &json!({"enable": false, "client_id": "id"}));
+    }
+
     #[test]
     fn test_skips_malformed_json() {
         let tmp = TempDir::new().unwrap();
@@ -147,6 +147,12 @@ impl Album {
             }
         });
     }
+
+    /// URIs of the tracks in playing order, tracks without URI are skipped
+    pub fn track_uris(&self) -> Vec<String> {
+        self.sort_tracks();
+        self.tracks.lock().iter().filter_map(|track| track.uri.clone()).collect()
+    }
 }
 
 // Implement Hash trait to ensure the id is used as the hash
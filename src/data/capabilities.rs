@@ -56,6 +56,12 @@ pub enum PlayerCapability {
     Killable = 0x200000,
     /// Player controller supports receiving updates (song change, position, etc.)
     ReceivesUpdates = 0x400000,
+    /// Queue is managed on the sending device, its length is unknown
+    RemoteQueue = 0x800000,
+    /// Can remove tracks from the queue after playing them
+    Consume = 0x1000000,
+    /// Can stop after the current track
+    Single = 0x2000000,
 }
 
 impl PlayerCapability {
@@ -85,6 +91,9 @@ impl PlayerCapability {
             Self::DatabaseUpdate => "db_update",
             Self::Killable => "killable",
             Self::ReceivesUpdates => "receives_updates",
+            Self::RemoteQueue => "remote_queue",
+            Self::Consume => "consume",
+            Self::Single => "single",
         }
     }
 
@@ -112,7 +121,10 @@ impl PlayerCapability {
         BitFlags::from_flag(Self::Favorites) |
         BitFlags::from_flag(Self::DatabaseUpdate) |
         BitFlags::from_flag(Self::Killable) |
-        BitFlags::from_flag(Self::ReceivesUpdates)
+        BitFlags::from_flag(Self::ReceivesUpdates) |
+        BitFlags::from_flag(Self::RemoteQueue) |
+        BitFlags::from_flag(Self::Consume) |
+        BitFlags::from_flag(Self::Single)
     }
 
     /// Convert a Vec of capabilities to BitFlags
@@ -0,0 +1,588 @@
+use crate::data::{LibraryDiff, LoopMode, PlaybackState, PlayerCapability, PlayerEvent, PlayerSource, Song, Track};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+/// Version of the event payloads sent to clients, increased on incompatible changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub state: PlaybackState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub song: Option<Song>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongInformationUpdateEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub song: Song,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopModeChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub mode: LoopMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilitiesChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub capabilities: Vec<PlayerCapability>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub position: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseUpdatingEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub song: Option<String>,
+    pub percentage: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryLoadProgressEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub artists_loaded: usize,
+    pub artists_total: usize,
+    pub albums_processed: usize,
+    pub albums_total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    #[serde(flatten)]
+    pub diff: LibraryDiff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpcomingTrackEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub remaining: f64,
+    pub next: Option<Track>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEndingEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub remaining: f64,
+    pub tracks_remaining: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivePlayerChangedEvent {
+    #[serde(flatten)]
+    pub source: PlayerSource,
+    pub new_player_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeChangedEvent {
+    pub control_name: String,
+    pub display_name: String,
+    pub percentage: f64,
+    pub decibels: Option<f64>,
+    pub raw_value: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbStorageChangedEvent {
+    pub device: String,
+    pub label: String,
+    pub library_path: Option<String>,
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemIdleEvent {
+    pub inactive_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemWakeEvent {
+    pub idle_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkChangedEvent {
+    pub online: bool,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemResourceAlertEvent {
+    pub resource: String,
+    pub critical: bool,
+    pub value: f64,
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundJobProgressEvent {
+    pub id: String,
+    pub name: String,
+    pub progress: Option<String>,
+    pub completed_items: Option<usize>,
+    pub total_items: Option<usize>,
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
+/// Payload of an event as sent to clients, the variant is the `type` field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventPayload {
+    StateChanged(StateChangedEvent),
+    SongChanged(SongChangedEvent),
+    SongInformationUpdate(SongInformationUpdateEvent),
+    LoopModeChanged(LoopModeChangedEvent),
+    RandomChanged(RandomChangedEvent),
+    CapabilitiesChanged(CapabilitiesChangedEvent),
+    PositionChanged(PositionChangedEvent),
+    DatabaseUpdating(DatabaseUpdatingEvent),
+    LibraryLoadProgress(LibraryLoadProgressEvent),
+    LibraryChanged(LibraryChangedEvent),
+    QueueChanged(QueueChangedEvent),
+    UpcomingTrack(UpcomingTrackEvent),
+    QueueEnding(QueueEndingEvent),
+    ActivePlayerChanged(ActivePlayerChangedEvent),
+    VolumeChanged(VolumeChangedEvent),
+    UsbStorageChanged(UsbStorageChangedEvent),
+    SystemIdle(SystemIdleEvent),
+    SystemWake(SystemWakeEvent),
+    NetworkChanged(NetworkChangedEvent),
+    SystemResourceAlert(SystemResourceAlertEvent),
+    BackgroundJobProgress(BackgroundJobProgressEvent),
+}
+
+impl From<&PlayerEvent> for EventPayload {
+    fn from(event: &PlayerEvent) -> Self {
+        let event = event.clone();
+        match event {
+            PlayerEvent::StateChanged { source, state } => Self::StateChanged(StateChangedEvent { source, state }),
+            PlayerEvent::SongChanged { source, song } => Self::SongChanged(SongChangedEvent { source, song }),
+            PlayerEvent::SongInformationUpdate { source, song } => {
+                Self::SongInformationUpdate(SongInformationUpdateEvent { source, song })
+            }
+            PlayerEvent::LoopModeChanged { source, mode } => Self::LoopModeChanged(LoopModeChangedEvent { source, mode }),
+            PlayerEvent::RandomChanged { source, enabled } => Self::RandomChanged(RandomChangedEvent { source, enabled }),
+            PlayerEvent::CapabilitiesChanged { source, capabilities } => Self::CapabilitiesChanged(CapabilitiesChangedEvent {
+                source,
+                capabilities: capabilities.to_vec(),
+            }),
+            PlayerEvent::PositionChanged { source, position } => {
+                Self::PositionChanged(PositionChangedEvent { source, position })
+            }
+            PlayerEvent::DatabaseUpdating { source, artist, album, song, percentage } => {
+                Self::DatabaseUpdating(DatabaseUpdatingEvent { source, artist, album, song, percentage })
+            }
+            PlayerEvent::LibraryLoadProgress { source, artists_loaded, artists_total, albums_processed, albums_total } => {
+                Self::LibraryLoadProgress(LibraryLoadProgressEvent {
+                    source,
+                    artists_loaded,
+                    artists_total,
+                    albums_processed,
+                    albums_total,
+                })
+            }
+            PlayerEvent::LibraryChanged { source, diff } => Self::LibraryChanged(LibraryChangedEvent { source, diff }),
+            PlayerEvent::QueueChanged { source } => Self::QueueChanged(QueueChangedEvent { source }),
+            PlayerEvent::UpcomingTrack { source, remaining, next } => {
+                Self::UpcomingTrack(UpcomingTrackEvent { source, remaining, next })
+            }
+            PlayerEvent::QueueEnding { source, remaining, tracks_remaining } => {
+                Self::QueueEnding(QueueEndingEvent { source, remaining, tracks_remaining })
+            }
+            PlayerEvent::ActivePlayerChanged { source, player_id } => {
+                Self::ActivePlayerChanged(ActivePlayerChangedEvent { source, new_player_id: player_id })
+            }
+            PlayerEvent::VolumeChanged { control_name, display_name, percentage, decibels, raw_value } => {
+                Self::VolumeChanged(VolumeChangedEvent { control_name, display_name, percentage, decibels, raw_value })
+            }
+            PlayerEvent::UsbStorageChanged { device, label, library_path, available } => {
+                Self::UsbStorageChanged(UsbStorageChangedEvent { device, label, library_path, available })
+            }
+            PlayerEvent::SystemIdle { inactive_seconds } => Self::SystemIdle(SystemIdleEvent { inactive_seconds }),
+            PlayerEvent::SystemWake { idle_seconds } => Self::SystemWake(SystemWakeEvent { idle_seconds }),
+            PlayerEvent::NetworkChanged { online, addresses } => {
+                Self::NetworkChanged(NetworkChangedEvent { online, addresses })
+            }
+            PlayerEvent::SystemResourceAlert { resource, critical, value, threshold } => {
+                Self::SystemResourceAlert(SystemResourceAlertEvent { resource, critical, value, threshold })
+            }
+            PlayerEvent::BackgroundJobProgress { id, name, progress, completed_items, total_items, finished, cancelled } => {
+                Self::BackgroundJobProgress(BackgroundJobProgressEvent {
+                    id,
+                    name,
+                    progress,
+                    completed_items,
+                    total_items,
+                    finished,
+                    cancelled,
+                })
+            }
+        }
+    }
+}
+
+/// Player that sent an event, `player_id` is "system" for system-wide events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventOrigin {
+    pub player_name: Option<String>,
+    pub player_id: String,
+}
+
+/// An event as sent to WebSocket clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventMessage {
+    #[serde(flatten)]
+    pub payload: EventPayload,
+    /// `EVENT_SCHEMA_VERSION` of the payload
+    pub version: u32,
+    pub source: EventOrigin,
+}
+
+impl From<&PlayerEvent> for EventMessage {
+    fn from(event: &PlayerEvent) -> Self {
+        Self {
+            payload: EventPayload::from(event),
+            version: EVENT_SCHEMA_VERSION,
+            source: EventOrigin {
+                player_name: event.player_name().map(|n| n.to_string()),
+                player_id: event.player_id().unwrap_or("system").to_string(),
+            },
+        }
+    }
+}
+
+/// Messages of the WebSocket connection itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    Welcome { client_id: usize, message: String },
+    SubscriptionUpdated { message: String },
+    Error { message: String },
+}
+
+/// Who sends an event type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventScope {
+    /// Sent by a player, carries `player_name` and `player_id`
+    Player,
+    /// System-wide event
+    System,
+    /// Message of the WebSocket connection
+    Control,
+}
+
+/// A field of an event payload
+///
+/// Types are `string`, `integer`, `number`, `boolean`, `array<T>` or the name of a
+/// data type of the API, e.g. `Song`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventFieldSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    /// The field can be null
+    pub optional: bool,
+    pub description: String,
+}
+
+impl EventFieldSchema {
+    pub fn new(name: &str, field_type: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+            optional: false,
+            description: description.to_string(),
+        }
+    }
+
+    pub fn optional(name: &str, field_type: &str, description: &str) -> Self {
+        Self {
+            optional: true,
+            ..Self::new(name, field_type, description)
+        }
+    }
+}
+
+/// Description of an event type for clients and code generators
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventSchema {
+    /// Value of the `type` field
+    pub event_type: String,
+    pub version: u32,
+    pub scope: EventScope,
+    pub description: String,
+    /// Fields besides `type`, `version` and `source`
+    pub fields: Vec<EventFieldSchema>,
+}
+
+impl EventSchema {
+    /// Schema of an event type, player events get the `player_name` and `player_id` fields
+    pub fn new(event_type: &str, scope: EventScope, description: &str, fields: Vec<EventFieldSchema>) -> Self {
+        let mut all_fields = Vec::new();
+        if scope == EventScope::Player {
+            all_fields.push(EventFieldSchema::new("player_name", "string", "Name of the player"));
+            all_fields.push(EventFieldSchema::new("player_id", "string", "Unique id of the player"));
+        }
+        all_fields.extend(fields);
+        Self {
+            event_type: event_type.to_string(),
+            version: EVENT_SCHEMA_VERSION,
+            scope,
+            description: description.to_string(),
+            fields: all_fields,
+        }
+    }
+}
+
+fn builtin_schemas() -> Vec<EventSchema> {
+    use EventFieldSchema as F;
+    use EventScope::{Control, Player, System};
+
+    let album_list = "array<AlbumSummary>";
+    vec![
+        EventSchema::new("state_changed", Player, "Playback state changed", vec![
+            F::new("state", "PlaybackState", "playing, paused, stopped, killed, disconnected or unknown"),
+        ]),
+        EventSchema::new("song_changed", Player, "Current song changed", vec![
+            F::optional("song", "Song", "The new song, null if nothing is playing"),
+        ]),
+        EventSchema::new("song_information_update", Player, "Metadata of the current song has been enriched", vec![
+            F::new("song", "Song", "Title and artist identify the song, other fields are only set if updated"),
+        ]),
+        EventSchema::new("loop_mode_changed", Player, "Loop mode changed", vec![
+            F::new("mode", "LoopMode", "no, song or playlist"),
+        ]),
+        EventSchema::new("random_changed", Player, "Shuffle mode changed", vec![
+            F::new("enabled", "boolean", "Whether shuffle is on"),
+        ]),
+        EventSchema::new("capabilities_changed", Player, "Player capabilities changed", vec![
+            F::new("capabilities", "array<PlayerCapability>", "All capabilities of the player"),
+        ]),
+        EventSchema::new("position_changed", Player, "Playback position changed", vec![
+            F::new("position", "number", "Position in seconds"),
+        ]),
+        EventSchema::new("database_updating", Player, "The player's database is being updated", vec![
+            F::optional("artist", "string", "Artist being scanned"),
+            F::optional("album", "string", "Album being scanned"),
+            F::optional("song", "string", "Song being scanned"),
+            F::optional("percentage", "number", "Progress in percent"),
+        ]),
+        EventSchema::new("library_load_progress", Player, "Progress of loading the library into memory", vec![
+            F::new("artists_loaded", "integer", "Artists whose songs have been read"),
+            F::new("artists_total", "integer", "Number of artists"),
+            F::new("albums_processed", "integer", "Complete albums"),
+            F::new("albums_total", "integer", "Albums found so far"),
+        ]),
+        EventSchema::new("library_changed", Player, "A library refresh added, removed or changed albums", vec![
+            F::new("timestamp", "integer", "Unix timestamp of the refresh"),
+            F::new("added", album_list, "Albums added"),
+            F::new("removed", album_list, "Albums removed"),
+            F::new("changed", album_list, "Albums with changed tracks or tags"),
+        ]),
+        EventSchema::new("queue_changed", Player, "Queue content changed", vec![]),
+        EventSchema::new("upcoming_track", Player, "The current track ends soon", vec![
+            F::new("remaining", "number", "Seconds until the current track ends"),
+            F::optional("next", "Track", "The next queue entry, null if it is not known"),
+        ]),
+        EventSchema::new("queue_ending", Player, "The last track of the queue ends soon", vec![
+            F::new("remaining", "number", "Seconds until the queue runs dry"),
+            F::new("tracks_remaining", "integer", "Tracks after the current one"),
+        ]),
+        EventSchema::new("active_player_changed", Player, "Another player became the active player", vec![
+            F::new("new_player_id", "string", "Id of the new active player"),
+        ]),
+        EventSchema::new("volume_changed", System, "Volume of a volume control changed", vec![
+            F::new("control_name", "string", "Name of the volume control"),
+            F::new("display_name", "string", "Display name of the control"),
+            F::new("percentage", "number", "Volume in percent (0-100)"),
+            F::optional("decibels", "number", "Volume in dB, if supported"),
+            F::optional("raw_value", "integer", "Raw control value"),
+        ]),
+        EventSchema::new("usb_storage_changed", System, "USB storage with music has been connected or removed", vec![
+            F::new("device", "string", "Device node, e.g. /dev/sda1"),
+            F::new("label", "string", "File system label or device name"),
+            F::optional("library_path", "string", "Path in the MPD music directory"),
+            F::new("available", "boolean", "true if added to the library, false if removed"),
+        ]),
+        EventSchema::new("system_idle", System, "No playback for the configured time", vec![
+            F::new("inactive_seconds", "integer", "Seconds without playback"),
+        ]),
+        EventSchema::new("system_wake", System, "Playback resumed after the system has been idle", vec![
+            F::new("idle_seconds", "integer", "Seconds the system has been idle"),
+        ]),
+        EventSchema::new("network_changed", System, "Network interfaces or addresses changed", vec![
+            F::new("online", "boolean", "Whether any interface except loopback has an address"),
+            F::new("addresses", "array<string>", "Addresses, e.g. \"wlan0: 192.168.1.20\""),
+        ]),
+        EventSchema::new("system_resource_alert", System, "A system resource exceeded its critical threshold or is back to normal", vec![
+            F::new("resource", "string", "temperature, throttling, load or memory"),
+            F::new("critical", "boolean", "true if the threshold is exceeded, false if back to normal"),
+            F::new("value", "number", "Current value: °C, throttling flags, load per CPU or available memory in MB"),
+            F::new("threshold", "number", "Critical threshold in the same unit"),
+        ]),
+        EventSchema::new("background_job_progress", System, "A background job started, made progress or finished", vec![
+            F::new("id", "string", "Id of the job"),
+            F::new("name", "string", "Name of the job"),
+            F::optional("progress", "string", "Description of the current step"),
+            F::optional("completed_items", "integer", "Items processed so far"),
+            F::optional("total_items", "integer", "Items to process"),
+            F::new("finished", "boolean", "true if the job has finished"),
+            F::new("cancelled", "boolean", "true if the job has been cancelled, it is finished once it has stopped"),
+        ]),
+        EventSchema::new("welcome", Control, "Sent after connecting", vec![
+            F::new("client_id", "integer", "Id of the connection"),
+            F::new("message", "string", "Greeting"),
+        ]),
+        EventSchema::new("subscription_updated", Control, "The subscription has been changed", vec![
+            F::new("message", "string", "Confirmation"),
+        ]),
+        EventSchema::new("error", Control, "A client message could not be processed", vec![
+            F::new("message", "string", "Error description"),
+        ]),
+    ]
+}
+
+/// Schemas of events that are not built in, e.g. sent by plugins
+static REGISTERED: Lazy<RwLock<Vec<EventSchema>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Register the schema of an additional event type
+///
+/// A schema with the same event type replaces the previous one.
+pub fn register_event_schema(schema: EventSchema) {
+    let mut registered = REGISTERED.write();
+    registered.retain(|s| s.event_type != schema.event_type);
+    registered.push(schema);
+}
+
+/// Schemas of all event types, built-in types first
+pub fn event_schemas() -> Vec<EventSchema> {
+    let mut schemas = builtin_schemas();
+    for schema in REGISTERED.read().iter() {
+        schemas.retain(|s| s.event_type != schema.event_type);
+        schemas.push(schema.clone());
+    }
+    schemas
+}
+
+pub fn event_schema(event_type: &str) -> Option<EventSchema> {
+    event_schemas().into_iter().find(|s| s.event_type == event_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::PlayerCapabilitySet;
+
+    fn sample_events() -> Vec<PlayerEvent> {
+        let source = PlayerSource::new("mpd".to_string(), "localhost:6600".to_string());
+        vec![
+            PlayerEvent::StateChanged { source: source.clone(), state: PlaybackState::Playing },
+            PlayerEvent::SongChanged { source: source.clone(), song: None },
+            PlayerEvent::SongInformationUpdate { source: source.clone(), song: Song::default() },
+            PlayerEvent::LoopModeChanged { source: source.clone(), mode: LoopMode::Track },
+            PlayerEvent::RandomChanged { source: source.clone(), enabled: true },
+            PlayerEvent::CapabilitiesChanged { source: source.clone(), capabilities: PlayerCapabilitySet::empty() },
+            PlayerEvent::PositionChanged { source: source.clone(), position: 1.5 },
+            PlayerEvent::DatabaseUpdating { source: source.clone(), artist: None, album: None, song: None, percentage: Some(50.0) },
+            PlayerEvent::LibraryLoadProgress { source: source.clone(), artists_loaded: 1, artists_total: 2, albums_processed: 3, albums_total: 4 },
+            PlayerEvent::LibraryChanged { source: source.clone(), diff: LibraryDiff::default() },
+            PlayerEvent::QueueChanged { source: source.clone() },
+            PlayerEvent::UpcomingTrack { source: source.clone(), remaining: 9.5, next: Some(Track::with_name("Next".to_string())) },
+            PlayerEvent::QueueEnding { source: source.clone(), remaining: 58.0, tracks_remaining: 1 },
+            PlayerEvent::ActivePlayerChanged { source, player_id: "spotify".to_string() },
+            PlayerEvent::VolumeChanged {
+                control_name: "Master".to_string(),
+                display_name: "Master".to_string(),
+                percentage: 50.0,
+                decibels: None,
+                raw_value: None,
+            },
+            PlayerEvent::UsbStorageChanged { device: "/dev/sda1".to_string(), label: "USB".to_string(), library_path: None, available: true },
+            PlayerEvent::SystemIdle { inactive_seconds: 600 },
+            PlayerEvent::SystemWake { idle_seconds: 60 },
+            PlayerEvent::NetworkChanged { online: true, addresses: vec![] },
+            PlayerEvent::SystemResourceAlert { resource: "temperature".to_string(), critical: true, value: 81.5, threshold: 80.0 },
+            PlayerEvent::BackgroundJobProgress {
+                id: "library_scan".to_string(),
+                name: "Library scan".to_string(),
+                progress: Some("Loading albums".to_string()),
+                completed_items: Some(10),
+                total_items: Some(100),
+                finished: false,
+                cancelled: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_messages_match_schemas() {
+        for event in sample_events() {
+            let json = serde_json::to_value(EventMessage::from(&event)).unwrap();
+            let object = json.as_object().unwrap();
+            assert_eq!(object["type"], event.event_type());
+            assert_eq!(object["version"], EVENT_SCHEMA_VERSION);
+
+            let schema = event_schema(event.event_type()).unwrap();
+            assert_eq!(schema.scope == EventScope::Player, event.source().is_some());
+            let mut keys: Vec<&str> = object.keys().map(|k| k.as_str()).filter(|k| !["type", "version", "source"].contains(k)).collect();
+            let mut fields: Vec<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+            keys.sort();
+            fields.sort();
+            assert_eq!(keys, fields, "fields of {}", event.event_type());
+
+            // Clients can read the messages back into the typed payloads
+            let parsed: EventMessage = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(parsed).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_register_event_schema() {
+        register_event_schema(EventSchema::new("plugin_test_event", EventScope::System, "Test", vec![
+            EventFieldSchema::new("value", "integer", "A value"),
+        ]));
+        assert_eq!(event_schema("plugin_test_event").unwrap().fields.len(), 1);
+        assert!(event_schemas().iter().any(|s| s.event_type == "state_changed"));
+    }
+}
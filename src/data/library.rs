@@ -129,6 +129,13 @@ pub trait LibraryInterface {
         false
     }
 
+    /// Get the albums added, removed and changed by the last refresh
+    ///
+    /// The first load of a library has no diff. The default implementation returns None.
+    fn get_last_diff(&self) -> Option<crate::data::LibraryDiff> {
+        None
+    }
+
     /// Whether this library supports deleting albums and tracks from disk.
     /// Default is false; only backends with direct filesystem access should override.
     fn supports_delete(&self) -> bool {
@@ -151,6 +158,19 @@ pub trait LibraryInterface {
         Err(LibraryError::InternalError("Delete not supported by this library".to_string()))
     }
 
+    /// Files `delete_album` would remove, without removing anything.
+    /// Returns Err in the cases `delete_album` would fail before touching the filesystem.
+    fn plan_delete_album(&self, album_id: &Identifier) -> Result<Vec<String>, LibraryError> {
+        let _ = album_id;
+        Err(LibraryError::InternalError("Delete not supported by this library".to_string()))
+    }
+
+    /// File `delete_track` would remove, without removing it.
+    fn plan_delete_track(&self, track_uri: &str) -> Result<String, LibraryError> {
+        let _ = track_uri;
+        Err(LibraryError::InternalError("Delete not supported by this library".to_string()))
+    }
+
     /// Get all unique raw genres from album tags, sorted alphabetically (no cleanup applied)
     fn get_raw_album_genres(&self) -> Vec<String> {
         let mut seen = std::collections::HashSet::new();
@@ -0,0 +1,152 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use serde::{Serialize, Deserialize};
+use crate::data::{Album, Identifier};
+
+/// Short description of an album in a library diff
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlbumSummary {
+    pub id: Identifier,
+    pub name: String,
+    pub artists: Vec<String>,
+}
+
+/// State of the albums of a library, used to compute the changes of a refresh
+#[derive(Debug, Clone, Default)]
+pub struct LibrarySnapshot {
+    /// Summary and content fingerprint by album ID
+    albums: HashMap<Identifier, (AlbumSummary, u64)>,
+}
+
+impl LibrarySnapshot {
+    pub fn from_albums<'a>(albums: impl IntoIterator<Item = &'a Album>) -> Self {
+        let albums = albums
+            .into_iter()
+            .map(|album| {
+                let summary = AlbumSummary {
+                    id: album.id.clone(),
+                    name: album.name.clone(),
+                    artists: album.artists.lock().clone(),
+                };
+                (album.id.clone(), (summary, fingerprint(album)))
+            })
+            .collect();
+        Self { albums }
+    }
+
+    pub fn len(&self) -> usize {
+        self.albums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.albums.is_empty()
+    }
+}
+
+/// Hash of the album data shown to users, changes if tracks, artists or tags change
+fn fingerprint(album: &Album) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    album.name.hash(&mut hasher);
+    album.artists.lock().hash(&mut hasher);
+    album.release_date.hash(&mut hasher);
+    album.cover_art.hash(&mut hasher);
+    album.genres.hash(&mut hasher);
+    for track in album.tracks.lock().iter() {
+        track.disc_number.hash(&mut hasher);
+        track.track_number.hash(&mut hasher);
+        track.name.hash(&mut hasher);
+        track.artist.hash(&mut hasher);
+        track.uri.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Albums added, removed and changed by a library refresh
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LibraryDiff {
+    /// Unix timestamp of the refresh
+    pub timestamp: u64,
+    pub added: Vec<AlbumSummary>,
+    pub removed: Vec<AlbumSummary>,
+    pub changed: Vec<AlbumSummary>,
+}
+
+impl LibraryDiff {
+    /// Compare the albums of a library before and after a refresh
+    pub fn between(before: &LibrarySnapshot, after: &LibrarySnapshot) -> Self {
+        let mut diff = LibraryDiff {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..Default::default()
+        };
+
+        for (id, (summary, hash)) in &after.albums {
+            match before.albums.get(id) {
+                None => diff.added.push(summary.clone()),
+                Some((_, old_hash)) if old_hash != hash => diff.changed.push(summary.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .albums
+            .iter()
+            .filter(|(id, _)| !after.albums.contains_key(id))
+            .map(|(_, (summary, _))| summary.clone())
+            .collect();
+
+        for list in [&mut diff.added, &mut diff.removed, &mut diff.changed] {
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use parking_lot::Mutex;
+    use crate::data::Track;
+
+    fn album(id: u64, name: &str, tracks: &[&str]) -> Album {
+        Album {
+            id: Identifier::Numeric(id),
+            name: name.to_string(),
+            artists: Arc::new(Mutex::new(vec!["Artist".to_string()])),
+            artists_flat: None,
+            release_date: None,
+            tracks: Arc::new(Mutex::new(tracks.iter().map(|t| Track::new(None, None, t.to_string())).collect())),
+            cover_art: None,
+            uri: None,
+            genres: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_library_diff() {
+        let before = LibrarySnapshot::from_albums(&[
+            album(1, "Kept", &["One"]),
+            album(2, "Removed", &["One"]),
+            album(3, "Changed", &["One"]),
+        ]);
+        let after = LibrarySnapshot::from_albums(&[
+            album(1, "Kept", &["One"]),
+            album(3, "Changed", &["One", "Two"]),
+            album(4, "Added", &["One"]),
+        ]);
+
+        let diff = LibraryDiff::between(&before, &after);
+        let names = |list: &[AlbumSummary]| list.iter().map(|a| a.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.added), vec!["Added"]);
+        assert_eq!(names(&diff.removed), vec!["Removed"]);
+        assert_eq!(names(&diff.changed), vec!["Changed"]);
+        assert!(LibraryDiff::between(&after, &after).is_empty());
+    }
+}
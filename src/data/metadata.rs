@@ -1,5 +1,28 @@
+use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
+/// Where the value of an artist metadata field came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldSource {
+    /// Provider of the value, e.g. "MusicBrainz", "LastFM" or "TheAudioDB"
+    pub provider: String,
+    /// True if the provider was queried with the MusicBrainz ID, false if with the artist name
+    pub mbid_verified: bool,
+    /// Confidence that the value belongs to this artist, from 0.0 to 1.0
+    pub confidence: f64,
+}
+
+/// A value of a provider that disagrees with the one in use, e.g. the MBID of a same-name artist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetadataConflict {
+    /// Field with conflicting values, e.g. "mbid"
+    pub field: String,
+    /// Provider of the rejected value
+    pub provider: String,
+    /// The rejected value
+    pub value: String,
+}
+
 /// Metadata for Artists including external IDs and image URLs
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ArtistMeta {
@@ -26,10 +49,26 @@ pub struct ArtistMeta {
     /// Musical genres associated with this artist
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub genres: Vec<String>,
+
+    /// Popularity from 0 to 100, from Spotify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub popularity: Option<u32>,
     
     /// Indicates if this is a partial match (only some artists in a multi-artist name found)
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub is_partial_match: bool,
+
+    /// Provider of each field, e.g. of "mbid", "biography" or "genres"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sources: HashMap<String, FieldSource>,
+
+    /// Confidence that the metadata belongs to the right artist, from 0.0 to 1.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+
+    /// Values of providers that disagree with the ones in use
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<MetadataConflict>,
 }
 
 impl ArtistMeta {
@@ -42,7 +81,11 @@ impl ArtistMeta {
             biography: None,
             biography_source: None,
             genres: Vec::new(),
+            popularity: None,
             is_partial_match: false,
+            sources: HashMap::new(),
+            confidence: None,
+            conflicts: Vec::new(),
         }
     }
     
@@ -74,6 +117,36 @@ impl ArtistMeta {
         }
     }
     
+    /// Record the provider of a field
+    pub fn set_source(&mut self, field: &str, provider: &str, mbid_verified: bool, confidence: f64) {
+        self.sources.insert(field.to_string(), FieldSource {
+            provider: provider.to_string(),
+            mbid_verified,
+            confidence,
+        });
+    }
+
+    /// Record a value of a provider that disagrees with the one in use
+    pub fn add_conflict(&mut self, field: &str, provider: &str, value: &str) {
+        let conflict = MetadataConflict {
+            field: field.to_string(),
+            provider: provider.to_string(),
+            value: value.to_string(),
+        };
+        if !self.conflicts.contains(&conflict) {
+            self.conflicts.push(conflict);
+        }
+    }
+
+    /// Set the overall confidence to the lowest confidence of the fields
+    pub fn update_confidence(&mut self) {
+        self.confidence = self
+            .sources
+            .values()
+            .map(|source| source.confidence)
+            .reduce(f64::min);
+    }
+
     /// Check if this metadata contains any actual data
     pub fn is_empty(&self) -> bool {
         self.mbid.is_empty() && 
@@ -82,6 +155,7 @@ impl ArtistMeta {
         self.biography.is_none() &&
         self.biography_source.is_none() &&
         self.genres.is_empty() &&
+        self.popularity.is_none() &&
         !self.is_partial_match
     }
     
@@ -93,7 +167,11 @@ impl ArtistMeta {
         self.biography = None;
         self.biography_source = None;
         self.genres.clear();
+        self.popularity = None;
         self.is_partial_match = false;
+        self.sources.clear();
+        self.confidence = None;
+        self.conflicts.clear();
     }
 }
 
@@ -130,4 +208,24 @@ mod tests {
         assert!(meta.biography_source.is_none());
         assert!(meta.is_empty());
     }
+
+    #[test]
+    fn test_confidence() {
+        let mut meta = ArtistMeta::new();
+        assert!(meta.confidence.is_none());
+
+        meta.set_source("mbid", "MusicBrainz", false, 0.7);
+        meta.set_source("biography", "TheAudioDB", true, 0.7);
+        meta.set_source("genres", "LastFM", false, 0.4);
+        meta.update_confidence();
+        assert_eq!(meta.confidence, Some(0.4));
+
+        meta.add_conflict("mbid", "LastFM", "b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d");
+        meta.add_conflict("mbid", "LastFM", "b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d");
+        assert_eq!(meta.conflicts.len(), 1);
+
+        // Old cache entries without provenance still deserialize
+        let old: ArtistMeta = serde_json::from_str(r#"{"mbid":["a"],"genres":["rock"]}"#).unwrap();
+        assert!(old.sources.is_empty() && old.conflicts.is_empty());
+    }
 }
\ No newline at end of file
@@ -5,6 +5,8 @@ pub mod album_artists;
 pub mod artist;
 pub mod capabilities;
 pub mod loop_mode;
+pub mod replay_gain_mode;
+pub mod single_mode;
 pub mod player;
 pub mod player_command;
 pub mod player_event;
@@ -14,9 +16,12 @@ pub mod song;
 pub mod song_update;
 pub mod stream_details;
 pub mod library;
+pub mod library_diff;
 pub mod track;
+pub mod playlist;
 pub mod metadata;
 pub mod system_event;
+pub mod events;
 
 use std::fmt;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
@@ -123,6 +128,8 @@ pub use album_artists::*;
 pub use artist::*;
 pub use capabilities::*;
 pub use loop_mode::*;
+pub use replay_gain_mode::*;
+pub use single_mode::*;
 pub use player::*;
 pub use player_command::*;
 pub use player_event::*;
@@ -132,6 +139,9 @@ pub use song::*;
 pub use song_update::*;
 pub use stream_details::*;
 pub use library::*;
+pub use playlist::*;
+pub use library_diff::*;
 pub use track::*;
 pub use metadata::*;
-pub use system_event::*;
\ No newline at end of file
+pub use system_event::*;
+pub use events::*;
@@ -1,7 +1,7 @@
 /// Player commands that can be sent to media players
 use serde::{Serialize, Deserialize};
 use strum_macros::EnumString;
-use super::LoopMode;
+use super::{LoopMode, ReplayGainMode, SingleMode};
 
 /// Metadata for tracks being added to the queue
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -43,6 +43,26 @@ pub enum PlayerCommand {
     #[serde(rename = "set_random")]
     SetRandom(bool),
 
+    /// Playback options of MPD
+    #[serde(rename = "set_replay_gain_mode")]
+    SetReplayGainMode(ReplayGainMode),
+
+    /// Remove tracks from the queue after they have been played
+    #[serde(rename = "set_consume")]
+    SetConsume(bool),
+
+    /// Stop after the current track
+    #[serde(rename = "set_single")]
+    SetSingle(SingleMode),
+
+    /// Crossfade duration in seconds, 0 disables crossfading
+    #[serde(rename = "set_crossfade")]
+    SetCrossfade(u32),
+
+    /// MixRamp threshold in dB
+    #[serde(rename = "set_mixrampdb")]
+    SetMixRampDb(f32),
+
     /// Kill (forcefully terminate) the player
     #[serde(rename = "kill")]
     Kill,
@@ -54,18 +74,42 @@ pub enum PlayerCommand {
         uris: Vec<String>,
         /// Whether to insert at beginning (true) or append at end (false)
         insert_at_beginning: bool,
+        /// Insert directly after the currently playing track ("play next").
+        /// Takes precedence over `insert_at_beginning` when set.
+        #[serde(default)]
+        insert_after_current: bool,
         /// Optional metadata for each URI (title and cover art URL)
         #[serde(default)]
         metadata: Vec<Option<QueueTrackMetadata>>,
     },
       #[serde(rename = "remove_track")]
     RemoveTrack(usize), // Changed from String to usize for position-based removal
+
+    /// Move the track at queue index `from` so that it ends up at index `to`
+    #[serde(rename = "move_track")]
+    MoveTrack { from: usize, to: usize },
     
     #[serde(rename = "clear_queue")]
     ClearQueue,
     
     #[serde(rename = "play_queue_index")]
     PlayQueueIndex(usize), // Play specific track in the queue by its index
+
+    /// Replace the queue with the given tracks and start playback
+    ///
+    /// Players handle this as a single operation, so clients do not need to
+    /// send clear/add/play separately.
+    #[serde(rename = "play_now")]
+    PlayNow {
+        /// Track URIs that replace the current queue
+        uris: Vec<String>,
+        /// Optional metadata for each URI
+        #[serde(default)]
+        metadata: Vec<Option<QueueTrackMetadata>>,
+        /// Queue index to start playback at
+        #[serde(default)]
+        start_index: usize,
+    },
 }
 
 
@@ -81,16 +125,25 @@ impl std::fmt::Display for PlayerCommand {
             PlayerCommand::SetLoopMode(mode) => write!(f, "set_loop:{}", mode),
             PlayerCommand::Seek(position) => write!(f, "seek:{}", position),
             PlayerCommand::SetRandom(enabled) => write!(f, "set_random:{}", if *enabled { "on" } else { "off" }),
+            PlayerCommand::SetReplayGainMode(mode) => write!(f, "set_replay_gain_mode:{}", mode),
+            PlayerCommand::SetConsume(enabled) => write!(f, "set_consume:{}", if *enabled { "on" } else { "off" }),
+            PlayerCommand::SetSingle(mode) => write!(f, "set_single:{}", mode),
+            PlayerCommand::SetCrossfade(seconds) => write!(f, "set_crossfade:{}", seconds),
+            PlayerCommand::SetMixRampDb(db) => write!(f, "set_mixrampdb:{}", db),
             PlayerCommand::Kill => write!(f, "kill"),
-            PlayerCommand::QueueTracks { insert_at_beginning, .. } => {
-                if *insert_at_beginning {
+            PlayerCommand::QueueTracks { insert_at_beginning, insert_after_current, .. } => {
+                if *insert_after_current {
+                    write!(f, "queue_tracks_next")
+                } else if *insert_at_beginning {
                     write!(f, "queue_tracks_beginning")
                 } else {
                     write!(f, "queue_tracks_end")
                 }
             },            PlayerCommand::RemoveTrack(position) => write!(f, "remove_track:{}", position),
+            PlayerCommand::MoveTrack { from, to } => write!(f, "move_track:{}:{}", from, to),
             PlayerCommand::ClearQueue => write!(f, "clear_queue"),
             PlayerCommand::PlayQueueIndex(index) => write!(f, "play_queue_index:{}", index),
+            PlayerCommand::PlayNow { start_index, .. } => write!(f, "play_now:{}", start_index),
         }
     }
 }
\ No newline at end of file
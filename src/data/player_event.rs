@@ -1,4 +1,4 @@
-use crate::data::{PlaybackState, Song, LoopMode, PlayerCapabilitySet};
+use crate::data::{PlaybackState, Song, LoopMode, PlayerCapabilitySet, LibraryDiff, Track};
 use serde::{Serialize, Deserialize};
 use std::fmt; // Added for Display
 
@@ -95,11 +95,46 @@ pub enum PlayerEvent {
         percentage: Option<f32>,
     },
 
+    /// Progress of loading the library into memory
+    LibraryLoadProgress {
+        source: PlayerSource,
+        /// Artists whose songs have been read from the player
+        artists_loaded: usize,
+        artists_total: usize,
+        /// Albums that are complete, the total grows while artists are loaded
+        albums_processed: usize,
+        albums_total: usize,
+    },
+
+    /// A library refresh added, removed or changed albums
+    LibraryChanged {
+        source: PlayerSource,
+        diff: LibraryDiff,
+    },
+
     /// Queue content has changed
     QueueChanged {
         source: PlayerSource,
     },
 
+    /// The current track ends soon
+    UpcomingTrack {
+        source: PlayerSource,
+        /// Seconds until the current track ends
+        remaining: f64,
+        /// The next queue entry, None if it is not known, e.g. in shuffle mode
+        next: Option<Track>,
+    },
+
+    /// The last track of the queue ends soon
+    QueueEnding {
+        source: PlayerSource,
+        /// Seconds until the queue runs dry
+        remaining: f64,
+        /// Tracks after the current one
+        tracks_remaining: usize,
+    },
+
     /// Active player has changed
     ActivePlayerChanged {
         source: PlayerSource,
@@ -120,6 +155,64 @@ pub enum PlayerEvent {
         raw_value: Option<i64>,
     },
 
+    /// USB storage with music has been connected or removed (system-wide event)
+    UsbStorageChanged {
+        /// Device node, e.g. /dev/sda1
+        device: String,
+        /// File system label or device name
+        label: String,
+        /// Path of the device in the MPD music directory
+        library_path: Option<String>,
+        /// true if the device has been added to the library, false if it has been removed
+        available: bool,
+    },
+
+    /// No playback on any player for the configured time (system-wide event)
+    SystemIdle {
+        /// Seconds without playback before the system became idle
+        inactive_seconds: u64,
+    },
+
+    /// Playback resumed after the system has been idle (system-wide event)
+    SystemWake {
+        /// Seconds the system has been idle
+        idle_seconds: u64,
+    },
+
+    /// Network interfaces or addresses changed (system-wide event)
+    NetworkChanged {
+        /// true if any interface except loopback has an address
+        online: bool,
+        /// Addresses of all interfaces except loopback, e.g. "wlan0: 192.168.1.20"
+        addresses: Vec<String>,
+    },
+
+    /// A system resource exceeded its critical threshold or is back to normal (system-wide event)
+    SystemResourceAlert {
+        /// "temperature", "throttling", "load" or "memory"
+        resource: String,
+        /// true if the threshold is exceeded, false if the value is back to normal
+        critical: bool,
+        /// Current value: °C, throttling flags, load per CPU or available memory in MB
+        value: f64,
+        /// Critical threshold in the same unit
+        threshold: f64,
+    },
+
+    /// A background job started, made progress or finished (system-wide event)
+    BackgroundJobProgress {
+        /// ID of the job as listed by the background jobs API
+        id: String,
+        name: String,
+        /// Description of the current step
+        progress: Option<String>,
+        completed_items: Option<usize>,
+        total_items: Option<usize>,
+        finished: bool,
+        /// Cancellation was requested, the job is finished once it has stopped
+        cancelled: bool,
+    },
+
 }
 
 impl PlayerEvent {
@@ -133,10 +226,20 @@ impl PlayerEvent {
             PlayerEvent::CapabilitiesChanged { source, .. } => Some(source),
             PlayerEvent::PositionChanged { source, .. } => Some(source),
             PlayerEvent::DatabaseUpdating { source, .. } => Some(source),
+            PlayerEvent::LibraryLoadProgress { source, .. } => Some(source),
+            PlayerEvent::LibraryChanged { source, .. } => Some(source),
             PlayerEvent::QueueChanged { source } => Some(source),
+            PlayerEvent::UpcomingTrack { source, .. } => Some(source),
+            PlayerEvent::QueueEnding { source, .. } => Some(source),
             PlayerEvent::SongInformationUpdate { source, .. } => Some(source),
             PlayerEvent::ActivePlayerChanged { source, .. } => Some(source),
             PlayerEvent::VolumeChanged { .. } => None, // Volume events are system-wide
+            PlayerEvent::UsbStorageChanged { .. } => None,
+            PlayerEvent::SystemIdle { .. } => None,
+            PlayerEvent::SystemWake { .. } => None,
+            PlayerEvent::NetworkChanged { .. } => None,
+            PlayerEvent::SystemResourceAlert { .. } => None,
+            PlayerEvent::BackgroundJobProgress { .. } => None,
         }
     }
     
@@ -160,10 +263,20 @@ impl PlayerEvent {
             PlayerEvent::CapabilitiesChanged { .. } => "capabilities_changed",
             PlayerEvent::PositionChanged { .. } => "position_changed",
             PlayerEvent::DatabaseUpdating { .. } => "database_updating",
+            PlayerEvent::LibraryLoadProgress { .. } => "library_load_progress",
+            PlayerEvent::LibraryChanged { .. } => "library_changed",
             PlayerEvent::QueueChanged { .. } => "queue_changed",
+            PlayerEvent::UpcomingTrack { .. } => "upcoming_track",
+            PlayerEvent::QueueEnding { .. } => "queue_ending",
             PlayerEvent::SongInformationUpdate { .. } => "song_information_update",
             PlayerEvent::ActivePlayerChanged { .. } => "active_player_changed",
             PlayerEvent::VolumeChanged { .. } => "volume_changed",
+            PlayerEvent::UsbStorageChanged { .. } => "usb_storage_changed",
+            PlayerEvent::SystemIdle { .. } => "system_idle",
+            PlayerEvent::SystemWake { .. } => "system_wake",
+            PlayerEvent::NetworkChanged { .. } => "network_changed",
+            PlayerEvent::SystemResourceAlert { .. } => "system_resource_alert",
+            PlayerEvent::BackgroundJobProgress { .. } => "background_job_progress",
         }
     }
 }
@@ -208,9 +321,24 @@ impl fmt::Display for PlayerEvent {
                     details.push_str(&format!("Song: {} ", s_song));
                 }
                 write!(f, "Player {} database updating {}", source, details.trim())
+            }
+            PlayerEvent::LibraryLoadProgress { source, artists_loaded, artists_total, albums_processed, albums_total } => {
+                write!(f, "Player {} library loading: {}/{} artists, {}/{} albums",
+                    source, artists_loaded, artists_total, albums_processed, albums_total)
+            }
+            PlayerEvent::LibraryChanged { source, diff } => {
+                write!(f, "Player {} library changed: {} albums added, {} removed, {} changed",
+                    source, diff.added.len(), diff.removed.len(), diff.changed.len())
             }            PlayerEvent::QueueChanged { source } => {
                 write!(f, "Player {} queue changed", source)
             }
+            PlayerEvent::UpcomingTrack { source, remaining, next } => match next {
+                Some(track) => write!(f, "Player {} plays '{}' in {:.1}s", source, track.name, remaining),
+                None => write!(f, "Player {} track ends in {:.1}s", source, remaining),
+            },
+            PlayerEvent::QueueEnding { source, remaining, tracks_remaining } => {
+                write!(f, "Player {} queue ends in {:.1}s after {} more tracks", source, remaining, tracks_remaining)
+            }
             PlayerEvent::SongInformationUpdate { source, song } => {
                 write!(f, "Player {} song information updated for '{}'", source, song)
             }
@@ -224,6 +352,30 @@ impl fmt::Display for PlayerEvent {
                     write!(f, "Volume control '{}' changed to {:.1}%", control_name, percentage)
                 }
             }
+            PlayerEvent::UsbStorageChanged { device, label, available, .. } => {
+                write!(f, "USB storage '{}' ({}) {}", label, device, if *available { "available" } else { "removed" })
+            }
+            PlayerEvent::SystemIdle { inactive_seconds } => {
+                write!(f, "System idle after {}s without playback", inactive_seconds)
+            }
+            PlayerEvent::SystemWake { idle_seconds } => {
+                write!(f, "System woke up after being idle for {}s", idle_seconds)
+            }
+            PlayerEvent::NetworkChanged { online, addresses } => {
+                write!(f, "Network {} ({})", if *online { "online" } else { "offline" }, addresses.join(", "))
+            }
+            PlayerEvent::SystemResourceAlert { resource, critical, value, threshold } => {
+                write!(f, "System {} {}: {} (threshold {})", resource, if *critical { "critical" } else { "normal" }, value, threshold)
+            }
+            PlayerEvent::BackgroundJobProgress { name, progress, finished, cancelled, .. } => {
+                if *finished && *cancelled {
+                    write!(f, "Background job '{}' cancelled", name)
+                } else if *finished {
+                    write!(f, "Background job '{}' finished", name)
+                } else {
+                    write!(f, "Background job '{}': {}", name, progress.as_deref().unwrap_or("started"))
+                }
+            }
         }
     }
 }
\ No newline at end of file
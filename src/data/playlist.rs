@@ -0,0 +1,86 @@
+use crate::data::Track;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors of playlist operations
+#[derive(Debug, Error)]
+pub enum PlaylistError {
+    #[error("Playlist '{0}' not found")]
+    NotFound(String),
+
+    #[error("Playlist '{0}' already exists")]
+    Exists(String),
+
+    #[error("Invalid playlist name '{0}'")]
+    InvalidName(String),
+
+    #[error("MPD is not connected")]
+    NotConnected,
+
+    #[error("MPD error: {0}")]
+    Mpd(String),
+
+    #[error("Failed to access the playlist store: {0}")]
+    Storage(String),
+}
+
+pub type PlaylistResult<T> = std::result::Result<T, PlaylistError>;
+
+/// A stored playlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPlaylist {
+    pub name: String,
+    /// Time of the last change, e.g. "2025-10-16T08:30:00Z"
+    pub last_modified: String,
+}
+
+/// Storage of named playlists
+///
+/// Implemented by players with native playlist support, e.g. MPD, and by the internal
+/// playlist store for all other players.
+pub trait PlaylistStore {
+    /// List the playlists, sorted by name
+    fn list_playlists(&self) -> PlaylistResult<Vec<StoredPlaylist>>;
+
+    /// Get the tracks of a playlist
+    fn get_playlist_tracks(&self, name: &str) -> PlaylistResult<Vec<Track>>;
+
+    /// Store tracks as a playlist
+    ///
+    /// An existing playlist with the same name is only replaced with `overwrite`.
+    fn save_playlist(&self, name: &str, tracks: &[Track], overwrite: bool) -> PlaylistResult<()>;
+
+    /// Add tracks to the end of an existing playlist
+    fn add_to_playlist(&self, name: &str, tracks: &[Track]) -> PlaylistResult<()> {
+        let mut playlist = self.get_playlist_tracks(name)?;
+        playlist.extend_from_slice(tracks);
+        self.save_playlist(name, &playlist, true)
+    }
+
+    /// Delete a playlist
+    fn delete_playlist(&self, name: &str) -> PlaylistResult<()>;
+}
+
+/// Check a playlist name, playlists are stored as files
+pub fn check_playlist_name(name: &str) -> PlaylistResult<()> {
+    if name.trim().is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\n', '\r']) {
+        return Err(PlaylistError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_playlist_name() {
+        assert!(check_playlist_name("Sunday Morning").is_ok());
+        assert!(check_playlist_name("Jazz: Best of 1959").is_ok());
+        assert!(matches!(check_playlist_name(""), Err(PlaylistError::InvalidName(_))));
+        assert!(matches!(check_playlist_name("  "), Err(PlaylistError::InvalidName(_))));
+        assert!(matches!(check_playlist_name("../etc/passwd"), Err(PlaylistError::InvalidName(_))));
+        assert!(matches!(check_playlist_name(".hidden"), Err(PlaylistError::InvalidName(_))));
+        assert!(matches!(check_playlist_name("a\nb"), Err(PlaylistError::InvalidName(_))));
+    }
+}
@@ -0,0 +1,31 @@
+/// ReplayGain mode enumeration for playback
+use serde::{Serialize, Deserialize};
+use strum_macros::EnumString;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[derive(Default)]
+pub enum ReplayGainMode {
+    /// Don't apply ReplayGain
+    #[default]
+    Off,
+    /// Use the track gain
+    Track,
+    /// Use the album gain
+    Album,
+    /// Use the album gain when playing an album in order, the track gain otherwise
+    Auto,
+}
+
+
+impl std::fmt::Display for ReplayGainMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayGainMode::Off => write!(f, "off"),
+            ReplayGainMode::Track => write!(f, "track"),
+            ReplayGainMode::Album => write!(f, "album"),
+            ReplayGainMode::Auto => write!(f, "auto"),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+/// Single mode enumeration for playback
+use serde::{Serialize, Deserialize};
+use strum_macros::EnumString;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[derive(Default)]
+pub enum SingleMode {
+    /// Continue with the next track
+    #[default]
+    Off,
+    /// Stop after the current track, or repeat it if looping is enabled
+    On,
+    /// Stop after the current track once, then switch back to off
+    Oneshot,
+}
+
+
+impl std::fmt::Display for SingleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SingleMode::Off => write!(f, "off"),
+            SingleMode::On => write!(f, "on"),
+            SingleMode::Oneshot => write!(f, "oneshot"),
+        }
+    }
+}
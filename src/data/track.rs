@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 use super::Identifier;
 
 /// Represents a Track in an album
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Track {
     // ID might be used by some backends
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -23,6 +23,9 @@ pub struct Track {
     /// URI/filename of the track (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uri: Option<String>,
+    /// Duration in seconds (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
 }
 
 impl Track {
@@ -35,6 +38,7 @@ impl Track {
             name,
             artist: None,
             uri: None,
+            duration: None,
         }
     }
     
@@ -47,6 +51,7 @@ impl Track {
             name,
             artist: None,
             uri: None,
+            duration: None,
         }
     }
     
@@ -72,6 +77,7 @@ impl Track {
             name,
             artist: track_artist,
             uri: None,
+            duration: None,
         }
     }
       /// Set the URI/filename for this track
@@ -80,6 +86,12 @@ impl Track {
         self
     }
     
+    /// Set the duration in seconds for this track
+    pub fn with_duration(mut self, duration: f64) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
     /// Set the ID for this track
     pub fn with_id(mut self, id: crate::data::Identifier) -> Self {
         self.id = Some(id);
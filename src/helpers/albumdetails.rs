@@ -0,0 +1,202 @@
+use crate::audiocontrol::AudioController;
+use crate::data::{Album, Identifier, PlayerCapability, Track};
+use crate::helpers::url_encoding::encode_url_safe;
+use chrono::Datelike;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A track of the album with its favourite state
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    pub is_favourite: bool,
+}
+
+/// One of the images available for the album
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumArtwork {
+    /// Player name for library covers, "cache" or the provider name for cached covers
+    pub source: String,
+    pub url: String,
+}
+
+/// The album in the library of one player
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumPlayer {
+    pub player_name: String,
+    pub player_id: String,
+    /// Album ID in the library of this player
+    pub album_id: Identifier,
+    pub track_count: usize,
+    /// The player can queue the tracks of the album
+    pub playable: bool,
+}
+
+/// Everything known about an album, merged from all libraries and caches
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumDetails {
+    pub name: String,
+    pub artists: Vec<String>,
+    pub release_date: Option<chrono::NaiveDate>,
+    pub year: Option<i32>,
+    pub genres: Vec<String>,
+    pub tracks: Vec<AlbumTrack>,
+    pub disc_count: usize,
+    /// Sum of the known track durations in seconds
+    pub total_duration: Option<f64>,
+    pub artwork: Vec<AlbumArtwork>,
+    pub favourite_count: usize,
+    pub players: Vec<AlbumPlayer>,
+}
+
+/// Collect the details of an album from the loaded libraries
+///
+/// The track list comes from the first library that has the album, durations missing there
+/// are taken from the other libraries. Returns None if no library has the album.
+pub fn get_album_details(controller: &AudioController, artist: &str, name: &str) -> Option<AlbumDetails> {
+    let mut details: Option<AlbumDetails> = None;
+
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        let Some(library) = ctrl.get_library() else {
+            continue;
+        };
+        if !library.is_loaded() {
+            continue;
+        }
+        let album = library.get_album_by_artist_and_name(artist, name).or_else(|| {
+            let artist = library.get_artist_by_name(artist)?;
+            library
+                .get_albums_by_artist_id(&artist.id)
+                .into_iter()
+                .find(|a| a.name.eq_ignore_ascii_case(name))
+        });
+        let Some(album) = album else {
+            continue;
+        };
+
+        let tracks = album.tracks.lock().clone();
+        let player = AlbumPlayer {
+            player_name: ctrl.get_player_name(),
+            player_id: ctrl.get_player_id(),
+            album_id: album.id.clone(),
+            track_count: tracks.len(),
+            playable: ctrl.get_capabilities().has_capability(PlayerCapability::Queue)
+                && tracks.iter().any(|t| t.uri.is_some()),
+        };
+
+        let details = details.get_or_insert_with(|| new_details(&album, artist, tracks.clone()));
+        if details.release_date.is_none() {
+            details.release_date = album.release_date;
+        }
+        for genre in &album.genres {
+            if !details.genres.contains(genre) {
+                details.genres.push(genre.clone());
+            }
+        }
+        fill_durations(&mut details.tracks, &tracks);
+        if let Some(url) = &album.cover_art {
+            details.artwork.push(AlbumArtwork {
+                source: player.player_name.clone(),
+                url: url.clone(),
+            });
+        }
+        details.players.push(player);
+    }
+
+    let mut details = details?;
+    details.year = details.release_date.map(|d| d.year());
+    details.total_duration = total_duration(&details.tracks);
+    details.favourite_count = details.tracks.iter().filter(|t| t.is_favourite).count();
+
+    let album_artist = details.artists.first().cloned().unwrap_or_else(|| artist.to_string());
+    if crate::helpers::imagecache::get_album_cover(&album_artist, &details.name, details.year).is_ok() {
+        let cache_key = crate::helpers::local_coverart::album_cache_key(&album_artist, &details.name, details.year);
+        let source = crate::helpers::coverart::get_artwork_source("album", &cache_key)
+            .map(|s| s.provider)
+            .unwrap_or_else(|| "cache".to_string());
+        let mut url = format!(
+            "{}/coverart/album/{}/{}/download",
+            crate::constants::API_PREFIX,
+            encode_url_safe(&details.name),
+            encode_url_safe(&album_artist)
+        );
+        if let Some(year) = details.year {
+            url.push_str(&format!("?year={}", year));
+        }
+        details.artwork.push(AlbumArtwork { source, url });
+    }
+
+    Some(details)
+}
+
+fn new_details(album: &Album, artist: &str, tracks: Vec<Track>) -> AlbumDetails {
+    let artists = album.artists.lock().clone();
+    let tracks: Vec<AlbumTrack> = tracks
+        .into_iter()
+        .map(|track| {
+            let track_artist = track.artist.as_deref().or(artists.first().map(|a| a.as_str())).unwrap_or(artist);
+            let is_favourite = crate::helpers::settingsdb::is_favourite_song(track_artist, &track.name).unwrap_or(false);
+            AlbumTrack { track, is_favourite }
+        })
+        .collect();
+    let discs: HashSet<&str> = tracks.iter().filter_map(|t| t.track.disc_number.as_deref()).collect();
+
+    AlbumDetails {
+        name: album.name.clone(),
+        disc_count: discs.len().max(1),
+        artists,
+        release_date: None,
+        year: None,
+        genres: Vec::new(),
+        tracks,
+        total_duration: None,
+        artwork: Vec::new(),
+        favourite_count: 0,
+        players: Vec::new(),
+    }
+}
+
+/// Take durations missing in the track list from the same tracks of another library
+fn fill_durations(tracks: &mut [AlbumTrack], other: &[Track]) {
+    for album_track in tracks.iter_mut().filter(|t| t.track.duration.is_none()) {
+        album_track.track.duration = other
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(&album_track.track.name) && t.disc_number == album_track.track.disc_number)
+            .and_then(|t| t.duration);
+    }
+}
+
+fn total_duration(tracks: &[AlbumTrack]) -> Option<f64> {
+    let durations: Vec<f64> = tracks.iter().filter_map(|t| t.track.duration).collect();
+    if durations.is_empty() {
+        None
+    } else {
+        Some(durations.iter().sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album_track(name: &str, duration: Option<f64>) -> AlbumTrack {
+        let mut track = Track::new(Some("1".to_string()), None, name.to_string());
+        track.duration = duration;
+        AlbumTrack { track, is_favourite: false }
+    }
+
+    #[test]
+    fn test_fill_durations_and_total() {
+        let mut tracks = vec![album_track("One", Some(100.0)), album_track("Two", None), album_track("Three", None)];
+        assert_eq!(total_duration(&tracks), Some(100.0));
+
+        let other = vec![Track::new(Some("1".to_string()), None, "two".to_string()).with_duration(50.5)];
+        fill_durations(&mut tracks, &other);
+        assert_eq!(tracks[1].track.duration, Some(50.5));
+        assert_eq!(tracks[2].track.duration, None);
+        assert_eq!(total_duration(&tracks), Some(150.5));
+        assert_eq!(total_duration(&[]), None);
+    }
+}
@@ -25,7 +25,7 @@ pub fn load_cached_genres(album_id: &str) -> Option<Vec<String>> {
 }
 
 /// Persist genres for an album to the attribute cache.
-fn store_cached_genres(album_id: &str, genres: &[String]) {
+pub fn store_cached_genres(album_id: &str, genres: &[String]) {
     let genres_vec = genres.to_vec();
     match crate::helpers::attributecache::set(&cache_key(album_id), &genres_vec) {
         Ok(_) => debug!("Stored genres for album {} in attribute cache", album_id),
@@ -59,111 +59,13 @@ pub fn fetch_album_genres(album_id: &str, artist: &str, album_name: &str) -> Vec
     genres
 }
 
-/// Start a background thread to update genre tags for all albums in the library.
+/// Queue genre lookups for all albums in the library.
 ///
-/// For each album that has no genres, fetches genres from MusicBrainz and stores
-/// them in the album struct and in the attribute cache.
+/// For each album that has no genres, the metadata queue fetches genres from
+/// MusicBrainz and stores them in the album struct and in the attribute cache.
 pub fn update_library_albums_genres_in_background(
     albums_collection: Arc<RwLock<HashMap<String, Album>>>,
 ) {
-    debug!("Starting background thread to update album genres");
-
-    std::thread::spawn(move || {
-        let job_id = "album_genre_update".to_string();
-        let job_name = "Album Genre Update".to_string();
-
-        if let Err(e) = crate::helpers::backgroundjobs::register_job(job_id.clone(), job_name) {
-            warn!("Failed to register album genre background job: {}", e);
-            return;
-        }
-
-        info!("Album genre update thread started");
-
-        // Collect albums that need genre lookup
-        let albums_snapshot: Vec<(String, String, Vec<String>)> = {
-            let map = albums_collection.read();
-            map.values()
-                .filter(|a| a.genres.is_empty())
-                .map(|a| {
-                    let id = a.id.to_string();
-                    let name = a.name.clone();
-                    let artists = a.artists.lock().clone();
-                    (id, name, artists)
-                })
-                .collect()
-        };
-
-        let total = albums_snapshot.len();
-        info!("Updating genres for {} albums without genre tags", total);
-
-        let _ = crate::helpers::backgroundjobs::update_job(
-            &job_id,
-            Some(format!("Starting genre update for {} albums", total)),
-            Some(0),
-            Some(total),
-        );
-
-        let mut updated = 0usize;
-
-        for (index, (album_id, album_name, artists)) in albums_snapshot.into_iter().enumerate() {
-            let artist = artists.first().cloned().unwrap_or_default();
-
-            let _ = crate::helpers::backgroundjobs::update_job(
-                &job_id,
-                Some(format!("Processing: {}", album_name)),
-                Some(index),
-                Some(total),
-            );
-
-            // Skip if already cached with empty result (avoid repeated API calls)
-            if let Some(cached) = load_cached_genres(&album_id) {
-                if cached.is_empty() {
-                    debug!("Skipping album '{}' — cached empty result", album_name);
-                    continue;
-                }
-                // Has cached genres — apply them to the album
-                let mut map = albums_collection.write();
-                if let Some(album) = map.get_mut(&album_name) {
-                    if album.genres.is_empty() {
-                        album.genres = cached;
-                        updated += 1;
-                    }
-                }
-                continue;
-            }
-
-            if artist.is_empty() || album_name.is_empty() {
-                store_cached_genres(&album_id, &[]);
-                continue;
-            }
-
-            let genres = fetch_album_genres(&album_id, &artist, &album_name);
-
-            if !genres.is_empty() {
-                let mut map = albums_collection.write();
-                if let Some(album) = map.get_mut(&album_name) {
-                    album.genres = genres;
-                    updated += 1;
-                }
-            }
-
-            let count = index + 1;
-            if count % 50 == 0 || count == total {
-                info!("Album genre update: {}/{} processed, {} updated", count, total, updated);
-                let _ = crate::helpers::backgroundjobs::update_job(
-                    &job_id,
-                    Some(format!("Processed {}/{} albums", count, total)),
-                    Some(count),
-                    Some(total),
-                );
-            }
-
-            // Rate limiting: MusicBrainz allows 1 req/sec; the ratelimit helper handles
-            // per-request limiting but we add a small sleep to be polite.
-            std::thread::sleep(std::time::Duration::from_millis(50));
-        }
-
-        info!("Album genre update complete: {}/{} albums updated", updated, total);
-        let _ = crate::helpers::backgroundjobs::complete_job(&job_id);
-    });
+    debug!("Queueing genre lookups for library albums");
+    crate::helpers::metadataqueue::enqueue_album_genres(&albums_collection);
 }
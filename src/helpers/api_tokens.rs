@@ -0,0 +1,365 @@
+use crate::config::get_service_config;
+use crate::helpers::security_store::SecurityStore;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix of the security store keys holding the tokens
+const TOKEN_KEY_PREFIX: &str = "api_token.";
+
+/// Prefix of the tokens handed out to clients, followed by the ID and the secret
+const TOKEN_PREFIX: &str = "acr";
+
+/// Requests forwarded to a peer, followed by the device ID and the path on the peer without `/api`
+const FEDERATION_PROXY_PREFIX: &str = "/api/federation/proxy/";
+
+/// GET requests that return credentials or complete a login, they need the admin scope
+const CREDENTIAL_PATHS: [&str; 4] = [
+    "/api/spotify/access_token",
+    "/api/spotify/poll",
+    "/api/lastfm/auth",
+    "/api/lastfm/complete_auth",
+];
+
+/// Media that players fetch by URL, e.g. MPD from 127.0.0.1, they can't send a token
+const PLAYER_MEDIA_PATHS: [&str; 2] = ["/api/webdav/stream", "/api/tts/clip"];
+
+/// What a token may do, each scope includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Read state, e.g. players, queue and library
+    Read,
+    /// Control playback and change user data
+    Control,
+    /// Change the configuration and manage tokens
+    Admin,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Control => write!(f, "control"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Errors of the token management
+#[derive(Debug, thiserror::Error)]
+pub enum ApiTokenError {
+    #[error("A token needs a name")]
+    EmptyName,
+    #[error("Token {0} not found")]
+    NotFound(String),
+    #[error("Failed to access the security store: {0}")]
+    Storage(String),
+}
+
+/// Configuration of the `api_auth` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiAuthConfig {
+    /// Require a token for API requests
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Requests from this device don't need a token, e.g. the local web UI and plugins
+    ///
+    /// Off by default: requests forwarded by a reverse proxy on the device come from localhost too.
+    #[serde(default)]
+    pub allow_localhost: bool,
+
+    /// Paths that can only be changed with the admin scope, reading them needs the read scope
+    #[serde(default = "default_admin_paths")]
+    pub admin_paths: Vec<String>,
+}
+
+fn default_admin_paths() -> Vec<String> {
+    ["/api/settings", "/api/cache", "/api/mounts", "/api/webdav", "/api/genres", "/api/songsplitter"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+impl Default for ApiAuthConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            allow_localhost: false,
+            admin_paths: default_admin_paths(),
+        }
+    }
+}
+
+/// A token as stored in the security store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    info: ApiToken,
+    secret: String,
+}
+
+/// Description of a token, the secret is only returned when the token is created
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scope: Scope,
+    /// Unix timestamp
+    pub created: u64,
+}
+
+static CONFIG: Lazy<RwLock<ApiAuthConfig>> = Lazy::new(|| RwLock::new(ApiAuthConfig::default()));
+static TOKENS: Lazy<RwLock<HashMap<String, StoredToken>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Initialize API authentication from the `api_auth` service configuration
+///
+/// Needs the security store, so it is initialized after it.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(c) = get_service_config(config, "api_auth") {
+        match serde_json::from_value::<ApiAuthConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid api_auth configuration, using defaults: {}", e),
+        }
+    }
+    load_tokens();
+    let config = CONFIG.read();
+    if config.enable {
+        info!(
+            "API authentication enabled with {} tokens{}",
+            TOKENS.read().len(),
+            if config.allow_localhost { ", local requests are allowed" } else { "" }
+        );
+    } else {
+        debug!("API authentication is disabled");
+    }
+}
+
+pub fn config() -> ApiAuthConfig {
+    CONFIG.read().clone()
+}
+
+fn load_tokens() {
+    let keys = match SecurityStore::get_all_keys() {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!("Failed to load API tokens: {}", e);
+            return;
+        }
+    };
+    let mut tokens = TOKENS.write();
+    tokens.clear();
+    for key in keys.iter().filter(|k| k.starts_with(TOKEN_KEY_PREFIX)) {
+        match SecurityStore::get(key).map(|value| serde_json::from_str::<StoredToken>(&value)) {
+            Ok(Ok(token)) => {
+                tokens.insert(token.info.id.clone(), token);
+            }
+            Ok(Err(e)) => warn!("Ignoring invalid API token {}: {}", key, e),
+            Err(e) => warn!("Failed to read API token {}: {}", key, e),
+        }
+    }
+}
+
+/// All tokens without their secrets, oldest first
+pub fn list_tokens() -> Vec<ApiToken> {
+    let mut tokens: Vec<ApiToken> = TOKENS.read().values().map(|t| t.info.clone()).collect();
+    tokens.sort_by_key(|t| t.created);
+    tokens
+}
+
+/// Create a token, returns its description and the token to send with requests
+pub fn create_token(name: &str, scope: Scope) -> Result<(ApiToken, String), ApiTokenError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ApiTokenError::EmptyName);
+    }
+    let token = StoredToken {
+        info: ApiToken {
+            id: random_hex(4),
+            name: name.to_string(),
+            scope,
+            created: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        },
+        secret: random_hex(24),
+    };
+    let value = serde_json::to_string(&token).map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+    SecurityStore::set(&format!("{}{}", TOKEN_KEY_PREFIX, token.info.id), &value)
+        .map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+    info!("Created API token {} '{}' with scope {}", token.info.id, token.info.name, scope);
+
+    let secret = format!("{}_{}_{}", TOKEN_PREFIX, token.info.id, token.secret);
+    let info = token.info.clone();
+    TOKENS.write().insert(info.id.clone(), token);
+    Ok((info, secret))
+}
+
+/// Revoke a token, requests with it are rejected right away
+pub fn revoke_token(id: &str) -> Result<ApiToken, ApiTokenError> {
+    let token = TOKENS.write().remove(id).ok_or_else(|| ApiTokenError::NotFound(id.to_string()))?;
+    SecurityStore::remove(&format!("{}{}", TOKEN_KEY_PREFIX, id)).map_err(|e| ApiTokenError::Storage(e.to_string()))?;
+    info!("Revoked API token {} '{}'", id, token.info.name);
+    Ok(token.info)
+}
+
+/// The scope of a token sent by a client, None if it is not valid
+pub fn authorize(token: &str) -> Option<Scope> {
+    let (id, secret) = split_token(token)?;
+    let tokens = TOKENS.read();
+    let stored = tokens.get(id)?;
+    constant_time_eq(stored.secret.as_bytes(), secret.as_bytes()).then_some(stored.info.scope)
+}
+
+/// Path of a request as Rocket routes it, from its percent-decoded path segments
+///
+/// Rocket decodes the segments and skips empty ones, so `/api/%73ettings/set` and `//api/settings/set`
+/// reach the same route as `/api/settings/set`. Scopes must be checked on this path, not the raw one.
+pub fn normalize_path<'a>(segments: impl IntoIterator<Item = &'a str>) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        for part in segment.split('/').filter(|part| !part.is_empty()) {
+            path.push('/');
+            path.push_str(part);
+        }
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+/// True if the path is the prefix or below it, only whole segments match
+fn is_under(path: &str, prefix: &str) -> bool {
+    path == prefix || path.starts_with(&format!("{}/", prefix.trim_end_matches('/')))
+}
+
+/// Requests that don't need a token, the path must be normalized with `normalize_path`
+///
+/// Only reading the media players stream from, players get a plain URL and can't authenticate.
+pub fn is_public(method: &str, path: &str) -> bool {
+    matches!(method, "GET" | "HEAD") && PLAYER_MEDIA_PATHS.iter().any(|prefix| is_under(path, prefix))
+}
+
+/// Scope needed for a request to an API path, the path must be normalized with `normalize_path`
+pub fn required_scope(method: &str, path: &str, admin_paths: &[String]) -> Scope {
+    // A forwarded request needs the scope the peer requires for it
+    if let Some((_, forwarded)) = path.strip_prefix(FEDERATION_PROXY_PREFIX).and_then(|rest| rest.split_once('/')) {
+        return required_scope(method, &format!("/api/{}", forwarded), admin_paths);
+    }
+    let under = |prefix: &str| is_under(path, prefix);
+    let reading = matches!(method, "GET" | "HEAD" | "OPTIONS");
+    if CREDENTIAL_PATHS.iter().any(|p| under(p)) {
+        return Scope::Admin;
+    }
+    // Clients may read their own scope, everything else of the token management is for admins
+    if under("/api/auth") && !(reading && path.trim_end_matches('/') == "/api/auth") {
+        return Scope::Admin;
+    }
+    // GraphQL queries are sent with POST, but the schema has no mutations
+    if reading || under("/api/graphql") {
+        return Scope::Read;
+    }
+    if admin_paths.iter().any(|p| under(p)) {
+        Scope::Admin
+    } else {
+        Scope::Control
+    }
+}
+
+/// Split "acr_<id>_<secret>" into ID and secret
+fn split_token(token: &str) -> Option<(&str, &str)> {
+    let mut parts = token.trim().splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(TOKEN_PREFIX), Some(id), Some(secret)) if !id.is_empty() && !secret.is_empty() => Some((id, secret)),
+        _ => None,
+    }
+}
+
+/// Compare secrets without leaking the position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buffer);
+    hex::encode(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        let admin_paths = default_admin_paths();
+        assert_eq!(required_scope("GET", "/api/players", &admin_paths), Scope::Read);
+        assert_eq!(required_scope("POST", "/api/player/active/command/play", &admin_paths), Scope::Control);
+        assert_eq!(required_scope("GET", "/api/settings/get", &admin_paths), Scope::Read);
+        assert_eq!(required_scope("POST", "/api/settings/set", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("GET", "/api/auth/tokens", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("GET", "/api/auth", &admin_paths), Scope::Read);
+        assert_eq!(required_scope("POST", "/api/graphql", &admin_paths), Scope::Read);
+        // Only whole path segments match
+        assert_eq!(required_scope("POST", "/api/settingsfoo", &admin_paths), Scope::Control);
+        // Requests to peers need the scope of the forwarded request
+        assert_eq!(required_scope("GET", "/api/federation/proxy/abc/auth/tokens", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("POST", "/api/federation/proxy/abc/settings/set", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("POST", "/api/federation/proxy/abc/player/active/command/pause", &admin_paths), Scope::Control);
+        assert_eq!(required_scope("GET", "/api/federation/proxy/abc/players", &admin_paths), Scope::Read);
+        // Reading credentials
+        assert_eq!(required_scope("GET", "/api/spotify/access_token", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("GET", "/api/spotify/poll/abc", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("GET", "/api/federation/proxy/abc/spotify/access_token", &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("GET", "/api/spotify/status", &admin_paths), Scope::Read);
+        assert!(Scope::Admin > Scope::Control && Scope::Control > Scope::Read);
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(is_public("GET", "/api/webdav/stream/cloud/Miles%20Davis/So%20What.flac"));
+        assert!(is_public("HEAD", "/api/tts/clip/5f1d7e0c.wav"));
+        assert!(!is_public("DELETE", "/api/tts/clip/5f1d7e0c.wav"));
+        assert!(!is_public("GET", "/api/webdav/cloud"));
+        assert!(!is_public("GET", "/api/webdav/streams"));
+        assert!(!is_public("GET", "/api/federation/proxy/abc/tts/clip/5f1d7e0c.wav"));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        let admin_paths = default_admin_paths();
+        let path = |uri: &str| {
+            let origin = rocket::http::uri::Origin::parse(uri).unwrap();
+            normalize_path(origin.path().segments())
+        };
+
+        assert_eq!(path("/api/settings/set"), "/api/settings/set");
+        assert_eq!(path("/api/%73ettings/set"), "/api/settings/set");
+        assert_eq!(path("//api//settings/set/"), "/api/settings/set");
+        assert_eq!(path("/api/settings%2Fset"), "/api/settings/set");
+        assert_eq!(path("/"), "/");
+        assert_eq!(required_scope("POST", &path("/api/%73ettings/set"), &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("POST", &path("//api/settings/set"), &admin_paths), Scope::Admin);
+        assert_eq!(required_scope("DELETE", &path("/api/%61uth/tokens/x"), &admin_paths), Scope::Admin);
+    }
+
+    #[test]
+    fn test_allow_localhost_default() {
+        let config: ApiAuthConfig = serde_json::from_str(r#"{"enable": true}"#).unwrap();
+        assert!(!config.allow_localhost);
+        assert!(!ApiAuthConfig::default().allow_localhost);
+    }
+
+    #[test]
+    fn test_split_token() {
+        assert_eq!(split_token("acr_1a2b3c4d_secret"), Some(("1a2b3c4d", "secret")));
+        assert_eq!(split_token("acr_1a2b3c4d_"), None);
+        assert_eq!(split_token("xyz_1a2b3c4d_secret"), None);
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
@@ -5,7 +5,7 @@ use std::io::Read;
 use log::{debug, info, warn};
 use once_cell::sync::Lazy;
 use crate::data::artist::Artist;
-use crate::helpers::coverart::get_coverart_manager;
+use crate::helpers::coverart::{get_artwork_source, get_coverart_manager, store_artwork_source, ArtworkSource};
 use crate::helpers::musicbrainz::{search_mbids_for_artist, MusicBrainzSearchResult};
 
 /// Result of an artist image operation
@@ -251,10 +251,11 @@ impl ArtistStore {
     /// * `artist_name` - The name of the artist
     /// * `url` - URL of the image to download
     /// * `image_type` - Type of image ("custom", "cover", etc.)
+    /// * `provider` - Where the URL came from, e.g. "theaudiodb" or "custom", recorded as source of the image
     /// 
     /// # Returns
     /// ArtistImageResult with the user path if successfully downloaded and stored
-    pub fn download_and_store_user_image(&mut self, artist_name: &str, url: &str, image_type: &str) -> ArtistImageResult {
+    pub fn download_and_store_user_image(&mut self, artist_name: &str, url: &str, image_type: &str, provider: &str) -> ArtistImageResult {
         debug!("Downloading image for artist {} from URL to user directory: {}", artist_name, url);
 
         // Check if already downloading
@@ -276,6 +277,7 @@ impl ArtistStore {
                 match self.store_image(&user_path, &image_data) {
                     Ok(_) => {
                         info!("Downloaded and stored {} image for artist {} in user directory", image_type, artist_name);
+                        store_artwork_source("artist", artist_name, &ArtworkSource::new(provider, Some(url)));
                         // Also cache the path for quick access
                         self.image_cache.insert(artist_name.to_string(), user_path.clone());
                         ArtistImageResult::Found { cache_path: user_path }
@@ -325,7 +327,14 @@ impl ArtistStore {
             if let Ok(Some(custom_url)) = crate::helpers::settingsdb::get_string(&custom_url_key) {
                 if !custom_url.is_empty() {
                     debug!("Found custom image URL for artist {}: {}", artist_name, custom_url);
-                    return self.download_and_cache_image(artist_name, &custom_url, "custom");
+                    let result = self.download_and_cache_image(artist_name, &custom_url, "custom");
+                    // Keep the provider recorded when the URL was set
+                    let recorded = get_artwork_source("artist", artist_name)
+                        .is_some_and(|source| source.url.as_deref() == Some(custom_url.as_str()));
+                    if matches!(result, ArtistImageResult::Found { .. }) && !recorded {
+                        store_artwork_source("artist", artist_name, &ArtworkSource::new("custom", Some(&custom_url)));
+                    }
+                    return result;
                 }
             }
         }
@@ -342,7 +351,7 @@ impl ArtistStore {
         }
 
         // Find the highest-rated image across all providers
-        let mut best_image: Option<&crate::helpers::coverart::ImageInfo> = None;
+        let mut best_image: Option<(&crate::helpers::coverart::ImageInfo, &str)> = None;
         let mut best_grade = -10; // Start lower to allow grade -1 images
 
         for result in &results {
@@ -350,14 +359,18 @@ impl ArtistStore {
                 let grade = image.grade.unwrap_or(0);
                 if grade > best_grade {
                     best_grade = grade;
-                    best_image = Some(image);
+                    best_image = Some((image, &result.provider.name));
                 }
             }
         }
 
-        if let Some(best_image) = best_image {
-            debug!("Found best image for artist {} with grade {}: {}", artist_name, best_grade, best_image.url);
-            self.download_and_cache_image(artist_name, &best_image.url, "cover")
+        if let Some((best_image, provider)) = best_image {
+            debug!("Found best image for artist {} with grade {} from {}: {}", artist_name, best_grade, provider, best_image.url);
+            let result = self.download_and_cache_image(artist_name, &best_image.url, "cover");
+            if matches!(result, ArtistImageResult::Found { .. }) {
+                store_artwork_source("artist", artist_name, &ArtworkSource::new(provider, Some(&best_image.url)));
+            }
+            result
         } else {
             debug!("No images with valid grades found for artist {}", artist_name);
             ArtistImageResult::NotFound
@@ -0,0 +1,132 @@
+use crate::data::{Artist, ArtistMeta, Identifier, MetadataConflict};
+use crate::helpers::{attributecache, settingsdb};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::thread;
+
+/// Settings key prefix of the MusicBrainz IDs chosen by the user
+const RESOLUTION_KEY_PREFIX: &str = "artist::resolution::";
+
+/// Attribute cache key prefix of the artist metadata
+const METADATA_KEY_PREFIX: &str = "artist::metadata::";
+
+/// An artist whose providers disagree about who the artist is
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistConflict {
+    pub artist: String,
+    /// MusicBrainz IDs in use
+    pub mbid: Vec<String>,
+    pub confidence: Option<f64>,
+    pub conflicts: Vec<MetadataConflict>,
+    /// MusicBrainz ID chosen by the user, if the conflict has been resolved
+    pub resolved_mbid: Option<String>,
+}
+
+/// Check if a string looks like a MusicBrainz ID (a UUID)
+pub fn is_valid_mbid(mbid: &str) -> bool {
+    mbid.len() == 36
+        && mbid.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Get the MusicBrainz ID the user chose for an artist
+pub fn get_resolution(artist: &str) -> Option<String> {
+    match settingsdb::get::<String>(&format!("{}{}", RESOLUTION_KEY_PREFIX, artist)) {
+        Ok(mbid) => mbid,
+        Err(e) => {
+            warn!("Failed to read MusicBrainz ID resolution for artist {}: {}", artist, e);
+            None
+        }
+    }
+}
+
+/// Persist the MusicBrainz ID of an ambiguous artist and look up its metadata again
+pub fn resolve(artist: &str, mbid: &str) -> Result<(), String> {
+    let mbid = mbid.trim().to_lowercase();
+    if !is_valid_mbid(&mbid) {
+        return Err(format!("'{}' is not a valid MusicBrainz ID", mbid));
+    }
+    settingsdb::set(&format!("{}{}", RESOLUTION_KEY_PREFIX, artist), &mbid)?;
+    info!("Resolved artist '{}' to MusicBrainz ID {}", artist, mbid);
+    refresh(artist);
+    Ok(())
+}
+
+/// Forget the MusicBrainz ID chosen by the user, returns false if there was none
+pub fn clear_resolution(artist: &str) -> Result<bool, String> {
+    let removed = settingsdb::remove(&format!("{}{}", RESOLUTION_KEY_PREFIX, artist))?;
+    if removed {
+        info!("Removed MusicBrainz ID resolution of artist '{}'", artist);
+        refresh(artist);
+    }
+    Ok(removed)
+}
+
+/// Drop the cached metadata of an artist and look it up again in the background
+fn refresh(artist: &str) {
+    if let Err(e) = attributecache::remove(&format!("{}{}", METADATA_KEY_PREFIX, artist)) {
+        warn!("Failed to remove cached metadata of artist {}: {}", artist, e);
+    }
+    let artist = Artist {
+        id: Identifier::String(artist.to_string()),
+        name: artist.to_string(),
+        is_multi: false,
+        metadata: None,
+    };
+    thread::spawn(move || {
+        // Stores the result in the attribute cache
+        crate::helpers::artistupdater::update_data_for_artist(artist);
+    });
+}
+
+/// List the artists with conflicting metadata, least confident first
+pub fn list_conflicts() -> Vec<ArtistConflict> {
+    let keys = match attributecache::list_keys(Some(METADATA_KEY_PREFIX)) {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!("Failed to list cached artist metadata: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut conflicts: Vec<ArtistConflict> = keys
+        .iter()
+        .filter_map(|key| {
+            let artist = key.strip_prefix(METADATA_KEY_PREFIX)?;
+            let meta = attributecache::get::<ArtistMeta>(key).ok()??;
+            if meta.conflicts.is_empty() {
+                return None;
+            }
+            Some(ArtistConflict {
+                artist: artist.to_string(),
+                mbid: meta.mbid,
+                confidence: meta.confidence,
+                conflicts: meta.conflicts,
+                resolved_mbid: get_resolution(artist),
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| {
+        a.confidence
+            .unwrap_or(0.0)
+            .total_cmp(&b.confidence.unwrap_or(0.0))
+            .then_with(|| a.artist.cmp(&b.artist))
+    });
+    debug!("Found {} artists with conflicting metadata", conflicts.len());
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_mbid() {
+        assert!(is_valid_mbid("a74b1b7f-71a5-4011-9441-d0b5e4122711"));
+        assert!(!is_valid_mbid("a74b1b7f71a5-4011-9441-d0b5e4122711-"));
+        assert!(!is_valid_mbid("radiohead"));
+        assert!(!is_valid_mbid(""));
+    }
+}
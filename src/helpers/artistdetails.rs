@@ -0,0 +1,316 @@
+use crate::audiocontrol::AudioController;
+use crate::data::{Album, Artist, ArtistMeta, Identifier};
+use crate::helpers::attributecache;
+use crate::helpers::lastfm::LastfmClient;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::thread;
+
+const SIMILAR_KEY_PREFIX: &str = "artist::similar::";
+const TOP_TRACKS_KEY_PREFIX: &str = "artist::toptracks::";
+
+/// Number of top tracks requested from Last.fm
+const TOP_TRACK_LIMIT: usize = 10;
+
+/// Similar artists and top tracks are fetched again after a week
+const PROVIDER_CACHE_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// An artist similar to the requested one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarArtist {
+    pub name: String,
+    pub url: String,
+    /// The artist is in one of the libraries
+    #[serde(default)]
+    pub in_library: bool,
+}
+
+/// One of the most played tracks of the artist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopTrack {
+    pub name: String,
+    pub playcount: Option<u64>,
+    /// A track with this name is in one of the libraries
+    #[serde(default)]
+    pub in_library: bool,
+    #[serde(default)]
+    pub is_favourite: bool,
+}
+
+/// Albums of the artist in the library of one player
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryAlbums {
+    pub player_name: String,
+    pub albums: Vec<Album>,
+}
+
+/// Everything known about an artist, merged from the libraries, caches and providers
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistDetails {
+    pub name: String,
+    pub in_library: bool,
+    /// MusicBrainz IDs, images, biography and genres
+    #[serde(flatten)]
+    pub metadata: ArtistMeta,
+    pub libraries: Vec<LibraryAlbums>,
+    pub similar_artists: Vec<SimilarArtist>,
+    pub top_tracks: Vec<TopTrack>,
+    /// Library tracks of the artist marked as favourite
+    pub favourite_tracks: Vec<String>,
+    pub is_favourite: bool,
+    /// Parts that are not known yet and are looked up in the background
+    pub pending: Vec<String>,
+}
+
+/// Background lookups, true once finished. Metadata is looked up once per artist,
+/// provider data again when its cache entry expires.
+static FILLING: Lazy<Mutex<HashMap<String, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Collect the details of an artist without waiting for external services
+///
+/// Missing metadata, similar artists and top tracks are looked up in the background
+/// and listed in `pending`, a later request returns them from the cache.
+pub fn get_artist_details(controller: &AudioController, name: &str) -> ArtistDetails {
+    let mut details = ArtistDetails {
+        name: name.to_string(),
+        in_library: false,
+        metadata: ArtistMeta::new(),
+        libraries: Vec::new(),
+        similar_artists: Vec::new(),
+        top_tracks: Vec::new(),
+        favourite_tracks: Vec::new(),
+        is_favourite: false,
+        pending: Vec::new(),
+    };
+    let mut library_artists = HashSet::new();
+    let mut library_tracks = HashSet::new();
+
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        let Some(library) = ctrl.get_library() else {
+            continue;
+        };
+        if !library.is_loaded() {
+            continue;
+        }
+        library_artists.extend(library.get_artists().into_iter().map(|a| a.name.to_lowercase()));
+
+        let artist = library.get_artist_by_name(name).or_else(|| {
+            library
+                .find_artist_fuzzy(name)
+                .filter(|m| !matches!(m.match_type, crate::data::library::ArtistMatchType::Fuzzy))
+                .map(|m| m.artist)
+        });
+        let Some(artist) = artist else {
+            continue;
+        };
+
+        details.in_library = true;
+        if let Some(meta) = &artist.metadata {
+            merge_metadata(&mut details.metadata, meta);
+        }
+        let albums = library.get_albums_by_artist_id(&artist.id);
+        for album in &albums {
+            for track in album.tracks.lock().iter() {
+                library_tracks.insert(track.name.to_lowercase());
+                let artist_name = track.artist.as_deref().unwrap_or(name);
+                if is_favourite(artist_name, &track.name) && !details.favourite_tracks.contains(&track.name) {
+                    details.favourite_tracks.push(track.name.clone());
+                }
+            }
+        }
+        details.libraries.push(LibraryAlbums {
+            player_name: ctrl.get_player_name(),
+            albums,
+        });
+    }
+    details.is_favourite = !details.favourite_tracks.is_empty();
+
+    // Metadata cached by earlier lookups fills the gaps of the library data
+    if let Ok(Some(cached)) = attributecache::get::<ArtistMeta>(&format!("artist::metadata::{}", name)) {
+        merge_metadata(&mut details.metadata, &cached);
+    }
+    // The MusicBrainz ID chosen by the user wins over the library tags
+    if let Some(mbid) = crate::helpers::artistconflicts::get_resolution(name) {
+        details.metadata.mbid = vec![mbid];
+        details.metadata.conflicts.clear();
+    }
+    let metadata_key = format!("metadata:{}", name);
+    let metadata_done = FILLING.lock().get(&metadata_key).copied().unwrap_or(false);
+    if details.metadata.mbid.is_empty() && details.metadata.biography.is_none() && !metadata_done {
+        details.pending.push("metadata".to_string());
+        if fill_in_background(metadata_key) {
+            let artist_name = name.to_string();
+            thread::spawn(move || {
+                let artist = Artist {
+                    id: Identifier::String(artist_name.clone()),
+                    name: artist_name.clone(),
+                    is_multi: false,
+                    metadata: None,
+                };
+                // Stores the result in the attribute cache
+                crate::helpers::artistupdater::update_data_for_artist(artist);
+                FILLING.lock().insert(format!("metadata:{}", artist_name), true);
+            });
+        }
+    }
+
+    match load_provider_data(name) {
+        Some((similar, top_tracks)) => {
+            details.similar_artists = similar
+                .into_iter()
+                .map(|mut a| {
+                    a.in_library = library_artists.contains(&a.name.to_lowercase());
+                    a
+                })
+                .collect();
+            details.top_tracks = top_tracks
+                .into_iter()
+                .map(|mut t| {
+                    t.in_library = library_tracks.contains(&t.name.to_lowercase());
+                    t.is_favourite = is_favourite(name, &t.name);
+                    t
+                })
+                .collect();
+        }
+        None if LastfmClient::get_instance().is_ok() => {
+            details.pending.push("similar_artists".to_string());
+            details.pending.push("top_tracks".to_string());
+            if fill_in_background(format!("provider:{}", name)) {
+                let artist_name = name.to_string();
+                thread::spawn(move || {
+                    fetch_provider_data(&artist_name);
+                    FILLING.lock().remove(&format!("provider:{}", artist_name));
+                });
+            }
+        }
+        None => debug!("Last.fm is not available, no similar artists or top tracks for {}", name),
+    }
+
+    details
+}
+
+/// Add metadata of another source, keeping values that are already set
+fn merge_metadata(target: &mut ArtistMeta, source: &ArtistMeta) {
+    for mbid in &source.mbid {
+        target.add_mbid(mbid.clone());
+    }
+    for url in &source.thumb_url {
+        target.add_thumb_url(url.clone());
+    }
+    for url in &source.banner_url {
+        target.add_banner_url(url.clone());
+    }
+    for genre in &source.genres {
+        target.add_genre(genre.clone());
+    }
+    if target.biography.is_none() {
+        target.biography = source.biography.clone();
+        target.biography_source = source.biography_source.clone();
+    }
+    for (field, field_source) in &source.sources {
+        target.sources.entry(field.clone()).or_insert_with(|| field_source.clone());
+    }
+    for conflict in &source.conflicts {
+        target.add_conflict(&conflict.field, &conflict.provider, &conflict.value);
+    }
+    if !target.sources.is_empty() {
+        target.update_confidence();
+    }
+}
+
+fn is_favourite(artist: &str, title: &str) -> bool {
+    crate::helpers::settingsdb::is_favourite_song(artist, title).unwrap_or(false)
+}
+
+/// Mark a background lookup as running, returns false if it is running or done
+fn fill_in_background(key: String) -> bool {
+    let mut filling = FILLING.lock();
+    if filling.contains_key(&key) {
+        return false;
+    }
+    filling.insert(key, false);
+    true
+}
+
+/// Get cached similar artists and top tracks, None if they have not been fetched yet
+fn load_provider_data(name: &str) -> Option<(Vec<SimilarArtist>, Vec<TopTrack>)> {
+    let similar = attributecache::get::<Vec<SimilarArtist>>(&format!("{}{}", SIMILAR_KEY_PREFIX, name)).ok()??;
+    let top_tracks = attributecache::get::<Vec<TopTrack>>(&format!("{}{}", TOP_TRACKS_KEY_PREFIX, name)).ok()??;
+    Some((similar, top_tracks))
+}
+
+/// Fetch similar artists and top tracks from Last.fm and cache them
+fn fetch_provider_data(name: &str) {
+    let client = match LastfmClient::get_instance() {
+        Ok(client) => client,
+        Err(e) => {
+            debug!("Last.fm not available for artist {}: {}", name, e);
+            return;
+        }
+    };
+
+    // Failed lookups are cached as empty lists to avoid repeating them on every request
+    let similar: Vec<SimilarArtist> = match client.get_artist_info(name) {
+        Ok(info) => info
+            .similar
+            .map(|s| s.artists)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|a| SimilarArtist { name: a.name, url: a.url, in_library: false })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to get similar artists of {} from Last.fm: {}", name, e);
+            Vec::new()
+        }
+    };
+    let top_tracks: Vec<TopTrack> = match client.get_artist_top_tracks(name, TOP_TRACK_LIMIT) {
+        Ok(tracks) => tracks
+            .into_iter()
+            .map(|t| TopTrack {
+                name: t.name,
+                playcount: t.playcount.parse().ok(),
+                in_library: false,
+                is_favourite: false,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to get top tracks of {} from Last.fm: {}", name, e);
+            Vec::new()
+        }
+    };
+
+    info!("Fetched {} similar artists and {} top tracks for {}", similar.len(), top_tracks.len(), name);
+    if let Err(e) = attributecache::set_with_ttl(&format!("{}{}", SIMILAR_KEY_PREFIX, name), &similar, PROVIDER_CACHE_TTL_SECS) {
+        warn!("Failed to cache similar artists of {}: {}", name, e);
+    }
+    if let Err(e) = attributecache::set_with_ttl(&format!("{}{}", TOP_TRACKS_KEY_PREFIX, name), &top_tracks, PROVIDER_CACHE_TTL_SECS) {
+        warn!("Failed to cache top tracks of {}: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_metadata() {
+        let mut target = ArtistMeta::new();
+        target.add_mbid("a".to_string());
+        target.biography = Some("Library".to_string());
+
+        let mut cached = ArtistMeta::new();
+        cached.add_mbid("a".to_string());
+        cached.add_mbid("b".to_string());
+        cached.add_genre("Jazz".to_string());
+        cached.biography = Some("Cache".to_string());
+
+        merge_metadata(&mut target, &cached);
+        assert_eq!(target.mbid, vec!["a", "b"]);
+        assert_eq!(target.genres, vec!["Jazz"]);
+        assert_eq!(target.biography.as_deref(), Some("Library"));
+    }
+}
@@ -1,11 +1,21 @@
 use log::{debug, info, warn};
 use crate::data::artist::Artist;
+use crate::data::metadata::ArtistMeta;
 use crate::helpers::musicbrainz::{search_mbids_for_artist, MusicBrainzSearchResult};
 use crate::helpers::ArtistUpdater;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
+/// Confidence of a MusicBrainz ID chosen by the user
+const CONFIDENCE_USER: f64 = 1.0;
+/// Confidence of data that came with the library or was looked up by MusicBrainz ID
+const CONFIDENCE_CONFIRMED: f64 = 0.9;
+/// Confidence of data that was looked up by artist name only
+const CONFIDENCE_NAME_MATCH: f64 = 0.7;
+/// Confidence of data when providers disagree about the MusicBrainz ID
+const CONFIDENCE_CONFLICT: f64 = 0.4;
+
 /// Looks up MusicBrainz IDs for an artist and returns them if found
 /// 
 /// This function searches for MusicBrainz IDs associated with the given artist name.
@@ -74,6 +84,24 @@ fn update_artist_with_coverart(artist: Artist) -> Artist {
 /// The updated artist
 pub fn update_data_for_artist(mut artist: Artist) -> Artist {
     debug!("Updating data for artist: {}", artist.name);
+
+    // A MusicBrainz ID chosen by the user replaces all others
+    if let Some(mbid) = crate::helpers::artistconflicts::get_resolution(&artist.name) {
+        debug!("Using MusicBrainz ID {} chosen by the user for artist {}", mbid, artist.name);
+        artist.ensure_metadata();
+        if let Some(meta) = &mut artist.metadata {
+            meta.mbid = vec![mbid];
+            meta.conflicts.clear();
+            meta.is_partial_match = false;
+            meta.set_source("mbid", "User", true, CONFIDENCE_USER);
+        }
+        artist.is_multi = false;
+    } else if let Some(meta) = &mut artist.metadata {
+        // MusicBrainz IDs without a source come from the tags of the library
+        if !meta.mbid.is_empty() && !meta.sources.contains_key("mbid") {
+            meta.set_source("mbid", "Library", true, CONFIDENCE_CONFIRMED);
+        }
+    }
     
     // Check if the artist already has MusicBrainz IDs set
     let has_mbid = match &artist.metadata {
@@ -101,6 +129,9 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
         } else if mbid_count > 0 {
             info!("Updated artist '{}' with MusicBrainz data: {} ID(s)", artist.name, mbid_count);
             debug!("Added MusicBrainz ID(s) to artist {}", artist.name);
+            if let Some(meta) = &mut artist.metadata {
+                meta.set_source("mbid", "MusicBrainz", false, CONFIDENCE_NAME_MATCH);
+            }
         }
         
         // Record if this is a partial match in the artist metadata
@@ -137,10 +168,14 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
         // Track what we had before updating
         let had_biography_before = artist.metadata.as_ref().is_some_and(|meta| meta.biography.is_some());
         let genres_count_before = artist.metadata.as_ref().map_or(0, |meta| meta.genres.len());
+        let mbids_before = artist.metadata.as_ref().map(|meta| meta.mbid.clone()).unwrap_or_default();
         
         // Try LastFM first for biography and genres (usually has good data)
         let lastfm_updater = crate::helpers::lastfm::LastfmUpdater;
         artist = lastfm_updater.update_artist(artist);
+        if let Some(meta) = &mut artist.metadata {
+            record_lastfm_sources(meta, &mbids_before, genres_count_before);
+        }
         
         // Check what we got from LastFM
         let has_biography_after_lastfm = artist.metadata.as_ref().is_some_and(|meta| meta.biography.is_some());
@@ -158,9 +193,13 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
         let still_needs_biography = artist.metadata.as_ref().is_none_or(|meta| meta.biography.is_none());
         let still_needs_genres = artist.metadata.as_ref().is_none_or(|meta| meta.genres.is_empty());
         let has_mbid = artist.metadata.as_ref().is_some_and(|meta| !meta.mbid.is_empty());
+        // A biography found by name is replaced by one looked up by MusicBrainz ID
+        let biography_unverified = artist.metadata.as_ref()
+            .and_then(|meta| meta.sources.get("biography"))
+            .is_some_and(|source| !source.mbid_verified);
         
         // If we still need data and have MusicBrainz ID, try TheAudioDB
-        if (still_needs_biography || still_needs_genres) && has_mbid {
+        if (still_needs_biography || still_needs_genres || biography_unverified) && has_mbid {
             debug!("Artist {} still needs biography or genres and has MBID, trying TheAudioDB", artist.name);
             
             // Track what we have before TheAudioDB
@@ -169,6 +208,9 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
             
             let theaudiodb_updater = crate::helpers::theaudiodb::TheAudioDbUpdater;
             artist = theaudiodb_updater.update_artist(artist);
+            if let Some(meta) = &mut artist.metadata {
+                record_theaudiodb_sources(meta, genres_count_before_tadb);
+            }
             
             // Check what we got from TheAudioDB
             let has_biography_after_tadb = artist.metadata.as_ref().is_some_and(|meta| meta.biography.is_some());
@@ -183,6 +225,22 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
             }
         }
         
+        // Wikipedia fills the gaps, its biographies are looked up by MusicBrainz ID
+        let needs_verified_biography = artist.metadata.as_ref().is_some_and(|meta| {
+            meta.biography.is_none() || meta.sources.get("biography").is_some_and(|source| !source.mbid_verified)
+        });
+        if needs_verified_biography && has_mbid {
+            debug!("Artist {} still needs a biography, trying Wikipedia", artist.name);
+            artist = crate::helpers::wikipedia::WikipediaUpdater.update_artist(artist);
+            if let Some(meta) = &mut artist.metadata {
+                if meta.biography_source.as_deref() == Some("Wikipedia") {
+                    let confidence = mbid_confidence(meta);
+                    meta.set_source("biography", "Wikipedia", true, confidence);
+                    info!("Downloaded biography for artist '{}' from Wikipedia", artist.name);
+                }
+            }
+        }
+
         // FanArt.tv updater no longer provides metadata - all image handling is done by CoverartProvider
         if has_mbid {
             debug!("Artist {} has MBID - FanArt.tv images will be handled by CoverartProvider", artist.name);
@@ -190,6 +248,16 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
     } else {
         debug!("Artist {} already has biography and genre data", artist.name);
     }
+
+    // Spotify adds the popularity, images and genres if a Spotify account is connected
+    if !artist.is_multi {
+        let genres_count_before = artist.metadata.as_ref().map_or(0, |meta| meta.genres.len());
+        let had_popularity_before = artist.metadata.as_ref().is_some_and(|meta| meta.popularity.is_some());
+        artist = crate::helpers::spotify::SpotifyArtistUpdater.update_artist(artist);
+        if let Some(meta) = &mut artist.metadata {
+            record_spotify_sources(meta, genres_count_before, had_popularity_before);
+        }
+    }
     
     // Handle artists without MusicBrainz IDs but with existing thumbnails
     if artist.metadata.as_ref().is_some_and(|meta| meta.mbid.is_empty()) {
@@ -204,6 +272,10 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
         }
     }
 
+    if let Some(meta) = &mut artist.metadata {
+        meta.update_confidence();
+    }
+
     // Store the updated metadata in cache
     if let Some(metadata) = &artist.metadata {
         // Create a cache key using the artist's name
@@ -228,132 +300,113 @@ pub fn update_data_for_artist(mut artist: Artist) -> Artist {
     artist
 }
 
-/// Start a background thread to update metadata for all artists in the library sequentially
-///
-/// This function updates artist metadata using the update_data_for_artist method in a background process.
-/// It takes an Arc to the artists collection for direct updating and reading.
-///
-/// # Arguments
-/// * `artists_collection` - Arc to the artists collection for updating
-pub fn update_library_artists_metadata_in_background(
-    artists_collection: Arc<RwLock<HashMap<String, Artist>>>
-) {
-    debug!("Starting background thread to update artist metadata");
-    
-    // Spawn a new thread to handle the metadata updates
-    use std::thread;
-    thread::spawn(move || {
-        let job_id = "artist_metadata_update".to_string();
-        let job_name = "Artist Metadata Update".to_string();
-        
-        // Register the background job
-        if let Err(e) = crate::helpers::backgroundjobs::register_job(job_id.clone(), job_name) {
-            warn!("Failed to register background job: {}", e);
-            return;
-        }
-        
-        info!("Artist metadata update thread started");
+/// Confidence of data looked up by MusicBrainz ID, which is only as good as the ID itself
+fn mbid_confidence(meta: &ArtistMeta) -> f64 {
+    meta.sources.get("mbid").map_or(CONFIDENCE_NAME_MATCH, |source| source.confidence)
+}
 
-        // Get all artists from the collection
-        let artists = {
-            let artists_map = artists_collection.read();
-            // Clone all artists for processing
-            artists_map.values().cloned().collect::<Vec<_>>()
-        };
+/// Record the provenance of the data Last.fm added and detect MusicBrainz ID conflicts
+///
+/// Last.fm looks artists up by name. If it reports a MusicBrainz ID other than the known
+/// ones, it most likely matched a different artist with the same name: its ID is dropped
+/// and recorded as conflict, and its biography and genres get a low confidence.
+fn record_lastfm_sources(meta: &mut ArtistMeta, mbids_before: &[String], genres_count: usize) {
+    let new_mbids: Vec<String> = meta.mbid.iter()
+        .filter(|mbid| !mbids_before.contains(mbid))
+        .cloned()
+        .collect();
 
-        let total = artists.len();
-        info!("Processing metadata for {} artists", total);
-        
-        // Update the job with total count
-        if let Err(e) = crate::helpers::backgroundjobs::update_job(
-            &job_id,
-            Some(format!("Starting metadata update for {} artists", total)),
-            Some(0),
-            Some(total)
-        ) {
-            warn!("Failed to update background job: {}", e);
+    let confidence = if new_mbids.is_empty() {
+        CONFIDENCE_NAME_MATCH
+    } else if mbids_before.is_empty() {
+        meta.set_source("mbid", "LastFM", false, CONFIDENCE_NAME_MATCH);
+        CONFIDENCE_NAME_MATCH
+    } else {
+        meta.mbid.retain(|mbid| mbids_before.contains(mbid));
+        for mbid in &new_mbids {
+            warn!("Last.fm reports MusicBrainz ID {} for an artist known as {:?}", mbid, mbids_before);
+            meta.add_conflict("mbid", "LastFM", mbid);
         }
-
-        for (index, artist) in artists.into_iter().enumerate() {
-            let artist_name = artist.name.clone();
-            debug!("Updating metadata for artist: {}", artist_name);
-            
-            // Update progress in background job
-            let completed = index;
-            let progress_message = format!("Processing artist: {}", artist_name);
-            if let Err(e) = crate::helpers::backgroundjobs::update_job(
-                &job_id,
-                Some(progress_message),
-                Some(completed),
-                Some(total)
-            ) {
-                warn!("Failed to update background job progress: {}", e);
+        // A choice of the user is not questioned
+        if let Some(source) = meta.sources.get_mut("mbid") {
+            if source.provider != "User" {
+                source.confidence = source.confidence.min(CONFIDENCE_CONFLICT);
             }
+        }
+        CONFIDENCE_CONFLICT
+    };
 
-            // Use the synchronous version of update_data_for_artist
-            let updated_artist = update_data_for_artist(artist);
+    if meta.biography_source.as_deref() == Some("LastFM") {
+        meta.set_source("biography", "LastFM", false, confidence);
+    }
+    if meta.genres.len() > genres_count {
+        meta.set_source("genres", "LastFM", false, confidence);
+    }
+}
 
-            // Check if we found new metadata to log appropriately
-            let has_new_metadata = {
-                let original_metadata = {
-                    let artists_map = artists_collection.read();
-                    artists_map.get(&artist_name).and_then(|a| a.metadata.clone())
-                };
+/// Record the provenance of the data TheAudioDB added by MusicBrainz ID
+fn record_theaudiodb_sources(meta: &mut ArtistMeta, genres_count: usize) {
+    let confidence = mbid_confidence(meta);
+    if meta.biography_source.as_deref() == Some("TheAudioDB") {
+        meta.set_source("biography", "TheAudioDB", true, confidence);
+    }
+    if meta.genres.len() > genres_count && !meta.sources.contains_key("genres") {
+        meta.set_source("genres", "TheAudioDB", true, confidence);
+    }
+}
 
-                if let Some(new_metadata) = &updated_artist.metadata {
-                    if !new_metadata.mbid.is_empty() {
-                        match original_metadata {
-                            Some(old_meta) if !old_meta.mbid.is_empty() => false,
-                            _ => {
-                                info!("Adding MusicBrainz ID(s) to artist {}", artist_name);
-                                true
-                            }
-                        }
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            };
+fn record_spotify_sources(meta: &mut ArtistMeta, genres_count: usize, had_popularity: bool) {
+    // Spotify is queried by name
+    if meta.genres.len() > genres_count && !meta.sources.contains_key("genres") {
+        meta.set_source("genres", "Spotify", false, CONFIDENCE_NAME_MATCH);
+    }
+    if meta.popularity.is_some() && !had_popularity {
+        meta.set_source("popularity", "Spotify", false, CONFIDENCE_NAME_MATCH);
+    }
+}
 
-            // Update the artist in the collection
-            {
-                let mut artists_map = artists_collection.write();
-                artists_map.insert(artist_name.clone(), updated_artist);
+/// Queue metadata updates for all artists in the library
+///
+/// The metadata queue updates the artists using update_data_for_artist. Artists
+/// that are also part of another library are only looked up once, artists that
+/// have been looked up recently are skipped.
+///
+/// # Arguments
+/// * `artists_collection` - Arc to the artists collection for updating
+pub fn update_library_artists_metadata_in_background(
+    artists_collection: Arc<RwLock<HashMap<String, Artist>>>
+) {
+    debug!("Queueing metadata updates for library artists");
+    crate::helpers::metadataqueue::enqueue_artists(&artists_collection);
+}
 
-                if has_new_metadata {
-                    debug!("Successfully updated artist {} in library collection", artist_name);
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Log progress periodically
-            let count = index + 1;
-            if count % 10 == 0 || count == total {
-                info!("Processed {}/{} artists for metadata", count, total);
-                
-                // Update background job with milestone progress
-                if let Err(e) = crate::helpers::backgroundjobs::update_job(
-                    &job_id,
-                    Some(format!("Processed {}/{} artists", count, total)),
-                    Some(count),
-                    Some(total)
-                ) {
-                    warn!("Failed to update background job milestone: {}", e);
-                }
-            }
-            
-            // Sleep between updates to avoid overwhelming external services
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
+    #[test]
+    fn test_record_lastfm_sources() {
+        let known = "a74b1b7f-71a5-4011-9441-d0b5e4122711".to_string();
+        let other = "0b7e5b8c-3b1c-4f0b-9a64-6d2a2f8c5a11".to_string();
 
-        info!("Artist metadata update process completed");
-        
-        // Complete and remove the background job
-        if let Err(e) = crate::helpers::backgroundjobs::complete_job(&job_id) {
-            warn!("Failed to complete background job: {}", e);
-        }
-    });
+        let mut meta = ArtistMeta::new();
+        meta.add_mbid(known.clone());
+        meta.set_source("mbid", "MusicBrainz", false, CONFIDENCE_NAME_MATCH);
+        let before = meta.mbid.clone();
+
+        // Last.fm matched a different artist with the same name
+        meta.add_mbid(other.clone());
+        meta.biography = Some("Someone else".to_string());
+        meta.biography_source = Some("LastFM".to_string());
+        meta.add_genre("Polka".to_string());
+        record_lastfm_sources(&mut meta, &before, 0);
 
-    info!("Background artist metadata update initiated");
-}
\ No newline at end of file
+        assert_eq!(meta.mbid, vec![known]);
+        assert_eq!(meta.conflicts.len(), 1);
+        assert_eq!(meta.conflicts[0].value, other);
+        assert_eq!(meta.sources["mbid"].confidence, CONFIDENCE_CONFLICT);
+        assert!(!meta.sources["biography"].mbid_verified);
+        meta.update_confidence();
+        assert_eq!(meta.confidence, Some(CONFIDENCE_CONFLICT));
+    }
+}
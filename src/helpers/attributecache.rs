@@ -7,6 +7,10 @@ use std::sync::Arc;
 use rusqlite::{Connection, params};
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crate::helpers::sharedcache::{SharedCache, SharedCacheConfig, SharedStore};
 
 /// Parse a size string that can be:
 /// - A simple number (bytes)
@@ -78,6 +82,140 @@ pub struct CacheStats {
     pub memory_entries: usize,
     pub memory_bytes: usize,
     pub memory_limit_bytes: usize,
+    /// Writes that are not stored in the database yet
+    pub pending_writes: usize,
+    /// Backend of the cache shared with other devices, if configured
+    pub shared_cache: Option<String>,
+}
+
+/// A rule that assigns a time to live to all keys matching a glob pattern
+///
+/// Patterns support `*` (any sequence of characters) and `?` (a single character),
+/// matching the semantics of SQLite's GLOB operator.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TtlRule {
+    pub pattern: String,
+    pub ttl_seconds: u64,
+}
+
+/// Default TTL for negative lookups (7 days)
+pub const DEFAULT_NOT_FOUND_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Default number of buffered writes that triggers a flush
+pub const DEFAULT_MAX_PENDING_WRITES: usize = 500;
+
+/// Default interval between flushes of buffered writes
+pub const DEFAULT_FLUSH_INTERVAL_SECONDS: u64 = 5;
+
+/// Default interval between sweeps for expired entries (1 hour)
+pub const DEFAULT_SWEEP_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// TTL rules that are used if the configuration doesn't define any
+pub fn default_ttl_rules() -> Vec<TtlRule> {
+    vec![TtlRule {
+        pattern: "*not_found::*".to_string(),
+        ttl_seconds: DEFAULT_NOT_FOUND_TTL_SECONDS,
+    }]
+}
+
+/// Check if a key matches a glob pattern with `*` and `?` wildcards
+pub fn glob_match(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+
+    let (mut p, mut k) = (0, 0);
+    let mut star: Option<usize> = None;
+    let mut star_k = 0;
+
+    while k < key.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == key[k]) {
+            p += 1;
+            k += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            star_k = k;
+            p += 1;
+        } else if let Some(star_p) = star {
+            // Let the last star consume one more character
+            p = star_p + 1;
+            star_k += 1;
+            k = star_k;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn now_timestamp() -> Result<i64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get current time: {}", e))?
+        .as_secs() as i64)
+}
+
+/// A value found in the shared cache with its serialized data, to be kept locally
+struct SharedHit<T> {
+    value: T,
+    data: Arc<Vec<u8>>,
+    expires_at: Option<i64>,
+}
+
+/// Look up a key that isn't cached locally in the shared cache
+fn fetch_shared<T: for<'de> Deserialize<'de>>(shared: &SharedStore, key: &str) -> Result<Option<SharedHit<T>>, String> {
+    let Some((data, ttl_seconds)) = shared.get(key) else {
+        return Ok(None);
+    };
+    let value = match serde_json::from_slice(&data) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Ignoring invalid value of '{}' in shared cache: {}", key, e);
+            return Ok(None);
+        }
+    };
+    let expires_at = match ttl_seconds {
+        Some(ttl) => Some(now_timestamp()? + ttl as i64),
+        None => None,
+    };
+    Ok(Some(SharedHit {
+        value,
+        data: Arc::new(data),
+        expires_at,
+    }))
+}
+
+// Whether the background sweep for expired entries is running
+static SWEEP_STARTED: AtomicBool = AtomicBool::new(false);
+
+// Whether the background flush of buffered writes is running
+static FLUSH_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// A write that is buffered until the next flush
+struct PendingWrite {
+    data: Arc<Vec<u8>>,
+    updated_at: i64,
+    expires_at: Option<i64>,
+}
+
+/// A cache entry with its value, used to back up and restore the cache
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CacheRecord {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// Number and size of the cache entries of a service
+///
+/// The service is the first segment of the key, e.g. `theaudiodb` for `theaudiodb::mbid::<mbid>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStats {
+    pub service: String,
+    pub entries: usize,
+    pub size_bytes: usize,
 }
 
 // Global singleton for the attribute cache
@@ -99,6 +237,14 @@ pub struct AttributeCache {
     max_memory_bytes: usize,
     /// Current memory usage of the memory cache in bytes
     current_memory_bytes: usize,
+    /// TTL rules applied to keys stored without an explicit expiry
+    ttl_rules: Vec<TtlRule>,
+    /// Writes that are not stored in the database yet
+    pending_writes: HashMap<String, PendingWrite>,
+    /// Number of buffered writes that triggers a flush, 0 writes every key immediately
+    max_pending_writes: usize,
+    /// Cache shared with other devices
+    shared: Option<Arc<SharedStore>>,
 }
 
 impl Default for AttributeCache {
@@ -107,6 +253,14 @@ impl Default for AttributeCache {
     }
 }
 
+impl Drop for AttributeCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("Failed to store {} pending attribute cache writes: {}", self.pending_writes.len(), e);
+        }
+    }
+}
+
 impl AttributeCache {
     /// Create a new attribute cache with default settings
     pub fn new() -> Self {
@@ -149,6 +303,10 @@ impl AttributeCache {
             memory_cache: LruCache::new(NonZeroUsize::new(1000000).unwrap()), // Large number since we'll limit by memory
             max_memory_bytes,
             current_memory_bytes: 0,
+            ttl_rules: default_ttl_rules(),
+            pending_writes: HashMap::new(),
+            max_pending_writes: 0,
+            shared: None,
         }
     }
 
@@ -292,10 +450,60 @@ impl AttributeCache {
             50 * 1024 * 1024
         };
 
+        // Parse TTL rules, keeping the defaults if none are configured
+        let ttl_rules = match config.get("ttl_rules") {
+            Some(rules) => serde_json::from_value::<Vec<TtlRule>>(rules.clone())
+                .map_err(|e| format!("Invalid ttl_rules: {}", e))?,
+            None => default_ttl_rules(),
+        };
+
+        let sweep_interval = config.get("sweep_interval_seconds")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_SWEEP_INTERVAL_SECONDS);
+
+        let max_pending_writes = config.get("max_pending_writes")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_MAX_PENDING_WRITES);
+        let flush_interval = config.get("flush_interval_seconds")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_FLUSH_INTERVAL_SECONDS);
+
         info!("Initializing attribute cache with {}MB memory limit", memory_limit / 1024 / 1024);
         
         Self::initialize_global_with_memory_limit(db_path, memory_limit)?;
 
+        {
+            let mut cache = get_attribute_cache();
+            cache.set_ttl_rules(ttl_rules);
+            cache.set_max_pending_writes(max_pending_writes);
+            match cache.apply_ttl_rules() {
+                Ok(count) if count > 0 => info!("Applied TTL rules to {} existing cache entries", count),
+                Ok(_) => {},
+                Err(e) => warn!("Failed to apply TTL rules to existing cache entries: {}", e),
+            }
+        }
+
+        if sweep_interval > 0 {
+            start_expiry_sweep(Duration::from_secs(sweep_interval));
+        }
+
+        if max_pending_writes > 0 {
+            start_write_behind(Duration::from_secs(flush_interval.max(1)));
+        }
+
+        // Share lookups with other devices
+        if let Some(shared_config) = config.get("shared") {
+            match SharedCacheConfig::from_json(shared_config) {
+                Ok(Some(shared_config)) => match shared_config.create_cache() {
+                    Ok(shared) => get_attribute_cache().set_shared_cache(Some(shared), shared_config.prefixes),
+                    Err(e) => warn!("Not using a shared attribute cache: {}", e),
+                },
+                Ok(None) => debug!("Shared attribute cache is disabled"),
+                Err(e) => warn!("Not using a shared attribute cache: {}", e),
+            }
+        }
+
         // Handle preload_prefixes if specified
         if let Some(prefixes_value) = config.get("preload_prefixes") {
             if let Some(prefixes_array) = prefixes_value.as_array() {
@@ -327,6 +535,12 @@ impl AttributeCache {
     /// Reconfigure the attribute cache with a new directory
     /// This will close the existing database and open a new one
     fn reconfigure_with_directory<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), String> {
+        // Pending writes belong to the current database
+        if let Err(e) = self.flush() {
+            warn!("Discarding {} pending attribute cache writes: {}", self.pending_writes.len(), e);
+            self.pending_writes.clear();
+        }
+
         let cache_dir = dir.as_ref().to_path_buf();
         let db_file = cache_dir.join("attributes.db");
         
@@ -353,6 +567,12 @@ impl AttributeCache {
     /// Reconfigure the attribute cache with a new database file and memory limit
     /// This will close the existing database and open a new one with a new memory cache
     fn reconfigure_with_file_and_memory_limit<P: AsRef<Path>>(&mut self, db_file: P, max_memory_bytes: usize) -> Result<(), String> {
+        // Pending writes belong to the current database
+        if let Err(e) = self.flush() {
+            warn!("Discarding {} pending attribute cache writes: {}", self.pending_writes.len(), e);
+            self.pending_writes.clear();
+        }
+
         let db_path = db_file.as_ref().to_path_buf();
         
         // Try to ensure the directory exists
@@ -443,13 +663,102 @@ impl AttributeCache {
         key.len() + data.len() + 64 // 64 bytes overhead for Arc and metadata
     }
 
+    /// Set the number of buffered writes that triggers a flush
+    ///
+    /// With 0, every write is stored in the database immediately.
+    pub fn set_max_pending_writes(&mut self, max_pending_writes: usize) {
+        self.max_pending_writes = max_pending_writes;
+        if max_pending_writes == 0 {
+            if let Err(e) = self.flush() {
+                warn!("Failed to store pending attribute cache writes: {}", e);
+            }
+        }
+    }
+
+    /// Number of writes that are not stored in the database yet
+    pub fn pending_write_count(&self) -> usize {
+        self.pending_writes.len()
+    }
+
+    /// Store all buffered writes in the database in a single transaction
+    ///
+    /// Returns the number of stored entries. If storing fails, the writes stay buffered.
+    pub fn flush(&mut self) -> Result<usize, String> {
+        if self.pending_writes.is_empty() {
+            return Ok(0);
+        }
+
+        let db = match &mut self.db {
+            Some(db) => db,
+            None => return Err("Database not available".to_string()),
+        };
+
+        let tx = db.transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        {
+            // Keep created_at of existing records, like single writes do
+            let mut stmt = tx.prepare(
+                "INSERT INTO cache (key, value, created_at, updated_at, expires_at)
+                 VALUES (?1, ?2, ?3, ?3, ?4)
+                 ON CONFLICT(key) DO UPDATE SET
+                     value = excluded.value,
+                     updated_at = excluded.updated_at,
+                     expires_at = excluded.expires_at"
+            ).map_err(|e| format!("Failed to prepare flush statement: {}", e))?;
+
+            for (key, write) in &self.pending_writes {
+                stmt.execute(params![key, write.data.as_slice(), write.updated_at, write.expires_at])
+                    .map_err(|e| format!("Failed to store '{}': {}", key, e))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        let count = self.pending_writes.len();
+        self.pending_writes.clear();
+        debug!("Stored {} pending writes in SQLite cache", count);
+        Ok(count)
+    }
+
+    /// Set the TTL rules for keys stored without an explicit expiry
+    pub fn set_ttl_rules(&mut self, rules: Vec<TtlRule>) {
+        self.ttl_rules = rules;
+    }
+
+    /// Get the configured TTL rules
+    pub fn ttl_rules(&self) -> &[TtlRule] {
+        &self.ttl_rules
+    }
+
+    /// Get the TTL in seconds for a key from the first matching rule
+    pub fn ttl_for_key(&self, key: &str) -> Option<u64> {
+        self.ttl_rules.iter()
+            .find(|rule| glob_match(&rule.pattern, key))
+            .map(|rule| rule.ttl_seconds)
+    }
+
     /// Store a serializable value in the cache
+    ///
+    /// If a TTL rule matches the key, the entry expires after the rule's TTL,
+    /// otherwise it is stored without expiry.
     pub fn set<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), String> {
-        self.set_with_expiry(key, value, None)
+        match self.ttl_for_key(key) {
+            Some(ttl_seconds) => self.set_with_ttl(key, value, ttl_seconds),
+            None => self.set_with_expiry(key, value, None),
+        }
     }
 
     /// Store a serializable value in the cache with an optional expiry time (Unix timestamp)
     pub fn set_with_expiry<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, expires_at: Option<i64>) -> Result<(), String> {
+        let data = self.set_local(key, value, expires_at)?;
+        if let Some(shared) = self.shared_for(key) {
+            shared.set(key, &data, expires_at);
+        }
+        Ok(())
+    }
+
+    /// Store a serializable value in the memory cache and the database, returns the serialized data
+    fn set_local<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, expires_at: Option<i64>) -> Result<Arc<Vec<u8>>, String> {
         if !self.is_enabled() {
             return Err("Cache is disabled".to_string());
         }
@@ -459,8 +768,31 @@ impl AttributeCache {
             Err(e) => return Err(format!("Failed to serialize value: {}", e)),
         };
 
+        let data = Arc::new(serialized);
+        self.store_local(key, data.clone(), expires_at)?;
+        Ok(data)
+    }
+
+    /// Store serialized data in the memory cache and the database
+    fn store_local(&mut self, key: &str, data: Arc<Vec<u8>>, expires_at: Option<i64>) -> Result<(), String> {
         // Store in memory cache using memory management
-        self.add_to_memory_cache(key.to_string(), Arc::new(serialized.clone()));
+        self.add_to_memory_cache(key.to_string(), data.clone());
+
+        // Buffer the write, it is stored with the next flush
+        if self.max_pending_writes > 0 {
+            if self.db.is_none() {
+                return Err("Database not available".to_string());
+            }
+            self.pending_writes.insert(key.to_string(), PendingWrite {
+                data,
+                updated_at: now_timestamp()?,
+                expires_at,
+            });
+            if self.pending_writes.len() >= self.max_pending_writes {
+                self.flush()?;
+            }
+            return Ok(());
+        }
 
         // Store in SQLite database
         match &mut self.db {
@@ -475,7 +807,7 @@ impl AttributeCache {
                          value = excluded.value,
                          updated_at = strftime('%s', 'now'),
                          expires_at = excluded.expires_at",
-                    params![key, serialized, expires_at],
+                    params![key, data.as_slice(), expires_at],
                 ) {
                     return Err(format!("Failed to store in database: {}", e));
                 }
@@ -487,23 +819,71 @@ impl AttributeCache {
         }
     }
 
+    /// Use a cache shared with other devices for keys with the given prefixes
+    pub fn set_shared_cache(&mut self, shared: Option<Box<dyn SharedCache>>, prefixes: Vec<String>) {
+        if let Some(ref cache) = shared {
+            info!("Sharing attribute cache entries with prefixes {:?} using {}", prefixes, cache.name());
+        }
+        self.shared = shared.map(|cache| Arc::new(SharedStore::new(cache, prefixes)));
+    }
+
+    /// The shared cache if the key is stored in it
+    ///
+    /// The global functions use it after releasing the cache lock, the server may take a while to answer.
+    fn shared_for(&self, key: &str) -> Option<Arc<SharedStore>> {
+        self.shared.as_ref().filter(|shared| shared.is_shared(key)).cloned()
+    }
+
+    /// Keep a value found in the shared cache locally
+    fn keep_shared<T>(&mut self, key: &str, found: Option<SharedHit<T>>) -> Result<Option<T>, String> {
+        let Some(found) = found else {
+            return Ok(None);
+        };
+        self.store_local(key, found.data, found.expires_at)?;
+        debug!("Retrieved key '{}' from shared cache", key);
+        Ok(Some(found.value))
+    }
+
     /// Store a serializable value in the cache with a TTL (time to live) in seconds
     pub fn set_with_ttl<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, ttl_seconds: u64) -> Result<(), String> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("Failed to get current time: {}", e))?
-            .as_secs() as i64;
-        let expires_at = now + ttl_seconds as i64;
+        let expires_at = now_timestamp()? + ttl_seconds as i64;
         self.set_with_expiry(key, value, Some(expires_at))
     }
 
     /// Get a value from the cache and deserialize it
-    /// This method automatically removes expired entries when they are accessed
+    ///
+    /// Keys that aren't cached locally are looked up in the shared cache, if configured.
     pub fn get<T: for<'de> Deserialize<'de>>(&mut self, key: &str) -> Result<Option<T>, String> {
+        if let Some(value) = self.get_local(key)? {
+            return Ok(Some(value));
+        }
+        let found = match self.shared_for(key) {
+            Some(shared) => fetch_shared(&shared, key)?,
+            None => None,
+        };
+        self.keep_shared(key, found)
+    }
+
+    /// Get a value from the memory cache or the database
+    /// This method automatically removes expired entries when they are accessed
+    fn get_local<T: for<'de> Deserialize<'de>>(&mut self, key: &str) -> Result<Option<T>, String> {
         if !self.is_enabled() {
             return Err("Cache is disabled".to_string());
         }
 
+        // Buffered writes are newer than the database
+        if let Some(write) = self.pending_writes.get(key) {
+            let now = now_timestamp()?;
+            if write.expires_at.is_none_or(|expires_at| expires_at > now) {
+                return serde_json::from_slice(&write.data)
+                    .map(Some)
+                    .map_err(|e| format!("Failed to deserialize pending write: {}", e));
+            }
+            debug!("Removing expired cache entry: {}", key);
+            let _ = self.remove_local(key);
+            return Ok(None);
+        }
+
         // Check database first to validate expiry before returning from memory cache
         let is_expired = match &mut self.db {
             Some(db) => {
@@ -521,20 +901,25 @@ impl AttributeCache {
                             .duration_since(std::time::UNIX_EPOCH)
                             .map_err(|e| format!("Failed to get current time: {}", e))?
                             .as_secs() as i64;
-                        expires_at <= now
+                        Some(expires_at <= now)
                     },
-                    Ok(None) => false, // No expiry set
-                    Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None), // Key doesn't exist
+                    Ok(None) => Some(false), // No expiry set
+                    Err(rusqlite::Error::QueryReturnedNoRows) => None, // Key doesn't exist
                     Err(e) => return Err(format!("Database error checking expiry: {}", e)),
                 }
             },
             None => return Err("Database not available".to_string()),
         };
 
-        // If expired, remove it and return None
+        let is_expired = match is_expired {
+            Some(is_expired) => is_expired,
+            None => return Ok(None),
+        };
+
+        // If expired, remove it, another device might have a newer value
         if is_expired {
             debug!("Removing expired cache entry: {}", key);
-            let _ = self.remove(key); // Ignore errors during cleanup
+            let _ = self.remove_local(key); // Ignore errors during cleanup
             return Ok(None);
         }
 
@@ -582,8 +967,17 @@ impl AttributeCache {
         }
     }
 
-    /// Remove an item from the cache
+    /// Remove an item from the cache, including the shared cache
     pub fn remove(&mut self, key: &str) -> Result<bool, String> {
+        let removed = self.remove_local(key)?;
+        if let Some(shared) = self.shared_for(key) {
+            shared.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// Remove an item from the memory cache and the database
+    fn remove_local(&mut self, key: &str) -> Result<bool, String> {
         if !self.is_enabled() {
             return Err("Cache is disabled".to_string());
         }
@@ -593,13 +987,14 @@ impl AttributeCache {
             let item_size = key.len() + removed_value.len();
             self.current_memory_bytes = self.current_memory_bytes.saturating_sub(item_size);
         }
+        let was_pending = self.pending_writes.remove(key).is_some();
 
         // Remove from database
         match &mut self.db {
             Some(db) => {
                 match db.execute("DELETE FROM cache WHERE key = ?1", params![key]) {
                     Ok(affected_rows) => {
-                        let removed = affected_rows > 0;
+                        let removed = affected_rows > 0 || was_pending;
                         if removed {
                             debug!("Removed key '{}' from SQLite cache", key);
                         }
@@ -621,6 +1016,7 @@ impl AttributeCache {
         // Clear memory cache
         self.memory_cache.clear();
         self.current_memory_bytes = 0;
+        self.pending_writes.clear();
 
         // Clear database
         match &mut self.db {
@@ -643,6 +1039,8 @@ impl AttributeCache {
             return Err("Cache is disabled".to_string());
         }
 
+        self.flush()?;
+
         match &mut self.db {
             Some(db) => {
                 // Calculate the cutoff timestamp (current time - max_age_days)
@@ -671,6 +1069,76 @@ impl AttributeCache {
         }
     }
 
+    /// Remove all entries whose expiry time has passed
+    pub fn remove_expired(&mut self) -> Result<usize, String> {
+        if !self.is_enabled() {
+            return Err("Cache is disabled".to_string());
+        }
+
+        self.flush()?;
+
+        let now = now_timestamp()?;
+
+        let expired_keys: Vec<String> = match &mut self.db {
+            Some(db) => {
+                let tx = db.transaction()
+                    .map_err(|e| format!("Failed to start transaction: {}", e))?;
+                let keys = {
+                    let mut stmt = tx.prepare("SELECT key FROM cache WHERE expires_at IS NOT NULL AND expires_at <= ?1")
+                        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+                    let rows = stmt.query_map(params![now], |row| row.get::<_, String>(0))
+                        .map_err(|e| format!("Failed to query expired entries: {}", e))?;
+                    rows.collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| format!("Failed to read expired entries: {}", e))?
+                };
+                tx.execute("DELETE FROM cache WHERE expires_at IS NOT NULL AND expires_at <= ?1", params![now])
+                    .map_err(|e| format!("Failed to remove expired entries: {}", e))?;
+                tx.commit()
+                    .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+                keys
+            },
+            None => return Err("Database not available".to_string()),
+        };
+
+        for key in &expired_keys {
+            if let Some(removed_value) = self.memory_cache.pop(key) {
+                let item_size = key.len() + removed_value.len();
+                self.current_memory_bytes = self.current_memory_bytes.saturating_sub(item_size);
+            }
+        }
+
+        if !expired_keys.is_empty() {
+            debug!("Removed {} expired entries from attribute cache", expired_keys.len());
+        }
+        Ok(expired_keys.len())
+    }
+
+    /// Apply the TTL rules to existing entries that don't have an expiry yet
+    ///
+    /// The expiry is calculated from the last update of the entry, so entries that
+    /// are older than the TTL are removed by the next sweep.
+    pub fn apply_ttl_rules(&mut self) -> Result<usize, String> {
+        if !self.is_enabled() {
+            return Err("Cache is disabled".to_string());
+        }
+
+        self.flush()?;
+
+        match &mut self.db {
+            Some(db) => {
+                let mut updated = 0;
+                for rule in &self.ttl_rules {
+                    updated += db.execute(
+                        "UPDATE cache SET expires_at = updated_at + ?1 WHERE expires_at IS NULL AND key GLOB ?2",
+                        params![rule.ttl_seconds as i64, rule.pattern],
+                    ).map_err(|e| format!("Failed to apply TTL rule '{}': {}", rule.pattern, e))?;
+                }
+                Ok(updated)
+            },
+            None => Err("Database not available".to_string()),
+        }
+    }
+
     /// Get the created_at and updated_at timestamps for a key
     /// Returns (created_at, updated_at) as Unix timestamps, or None if key doesn't exist
     pub fn get_timestamps(&mut self, key: &str) -> Result<Option<(i64, i64)>, String> {
@@ -678,6 +1146,8 @@ impl AttributeCache {
             return Err("Cache is disabled".to_string());
         }
 
+        self.flush()?;
+
         match &mut self.db {
             Some(db) => {
                 let mut stmt = match db.prepare("SELECT created_at, updated_at FROM cache WHERE key = ?1") {
@@ -730,7 +1200,9 @@ impl AttributeCache {
     }
 
     /// List all cache keys, optionally filtered by prefix
-    pub fn list_keys(&self, prefix_filter: Option<&str>) -> Result<Vec<String>, String> {
+    pub fn list_keys(&mut self, prefix_filter: Option<&str>) -> Result<Vec<String>, String> {
+        self.flush()?;
+
         let db = self.db.as_ref()
             .ok_or_else(|| "Database connection is not available".to_string())?;
         let mut keys = Vec::new();
@@ -774,6 +1246,8 @@ impl AttributeCache {
             return Ok(Vec::new());
         }
 
+        self.flush()?;
+
         let db = self.db.as_ref()
             .ok_or_else(|| "Database connection is not available".to_string())?;
         let mut entries = Vec::new();
@@ -823,22 +1297,31 @@ impl AttributeCache {
         Ok(entries)
     }
 
-    /// Remove all cache entries matching a prefix
+    /// Remove all cache entries matching a prefix, including those in the shared cache
     pub fn remove_by_prefix(&mut self, prefix: &str) -> Result<usize, String> {
+        let deleted = self.remove_by_prefix_local(prefix)?;
+        if let Some(shared) = self.shared.clone() {
+            shared.remove_by_prefix(prefix);
+        }
+        Ok(deleted)
+    }
+
+    /// Remove all entries matching a prefix from the memory cache and the database
+    fn remove_by_prefix_local(&mut self, prefix: &str) -> Result<usize, String> {
         if !self.enabled {
             return Ok(0);
         }
 
+        self.flush()?;
+
         let db = self.db.as_ref()
             .ok_or_else(|| "Database connection is not available".to_string())?;
 
-        let pattern = format!("{}%", prefix);
-
-        // First, get the keys to remove from memory cache
-        let mut stmt = db.prepare("SELECT key FROM cache WHERE key LIKE ?1")
+        // Compare the prefix literally, LIKE would treat '_' as a wildcard and ignore the case
+        let mut stmt = db.prepare("SELECT key FROM cache WHERE substr(key, 1, length(?1)) = ?1")
             .map_err(|e| format!("Failed to prepare select statement: {}", e))?;
         
-        let rows = stmt.query_map(params![pattern], |row| {
+        let rows = stmt.query_map(params![prefix], |row| {
             row.get::<_, String>(0)
         }).map_err(|e| format!("Failed to execute select query: {}", e))?;
 
@@ -847,20 +1330,135 @@ impl AttributeCache {
             let key = row.map_err(|e| format!("Failed to read row: {}", e))?;
             keys_to_remove.push(key);
         }
+        drop(stmt);
+
+        // Remove from database
+        let deleted = db.execute("DELETE FROM cache WHERE substr(key, 1, length(?1)) = ?1", params![prefix])
+            .map_err(|e| format!("Failed to delete from database: {}", e))?;
 
         // Remove from memory cache
         for key in &keys_to_remove {
-            self.memory_cache.pop(key);
+            if let Some(removed_value) = self.memory_cache.pop(key) {
+                let item_size = key.len() + removed_value.len();
+                self.current_memory_bytes = self.current_memory_bytes.saturating_sub(item_size);
+            }
         }
 
-        // Remove from database
-        let deleted = db.execute("DELETE FROM cache WHERE key LIKE ?1", params![pattern])
-            .map_err(|e| format!("Failed to delete from database: {}", e))?;
-
         debug!("Removed {} cache entries with prefix '{}'", deleted, prefix);
         Ok(deleted)
     }
 
+    /// Get all entries that are not expired with their values, ordered by key
+    pub fn export_records(&mut self) -> Result<Vec<CacheRecord>, String> {
+        if !self.is_enabled() {
+            return Err("Cache is disabled".to_string());
+        }
+
+        self.flush()?;
+
+        let db = self.db.as_ref()
+            .ok_or_else(|| "Database connection is not available".to_string())?;
+        let now = now_timestamp()?;
+
+        let mut stmt = db.prepare(
+            "SELECT key, value, created_at, updated_at, expires_at FROM cache
+             WHERE expires_at IS NULL OR expires_at > ?1 ORDER BY key"
+        ).map_err(|e| format!("Failed to prepare export statement: {}", e))?;
+
+        let rows = stmt.query_map(params![now], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Vec<u8>>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        }).map_err(|e| format!("Failed to execute export query: {}", e))?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (key, data, created_at, updated_at, expires_at) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+            match serde_json::from_slice(&data) {
+                Ok(value) => records.push(CacheRecord { key, value, created_at, updated_at, expires_at }),
+                Err(e) => warn!("Skipping cache entry '{}' with invalid value: {}", key, e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Store records with their original timestamps, replacing entries with the same key
+    ///
+    /// Expired records are skipped. Returns the number of stored records.
+    pub fn import_records(&mut self, records: &[CacheRecord]) -> Result<usize, String> {
+        if !self.is_enabled() {
+            return Err("Cache is disabled".to_string());
+        }
+
+        self.flush()?;
+
+        let now = now_timestamp()?;
+        let imported = match &mut self.db {
+            Some(db) => {
+                let tx = db.transaction()
+                    .map_err(|e| format!("Failed to start transaction: {}", e))?;
+                let mut imported = 0;
+                {
+                    let mut stmt = tx.prepare(
+                        "INSERT OR REPLACE INTO cache (key, value, created_at, updated_at, expires_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5)"
+                    ).map_err(|e| format!("Failed to prepare import statement: {}", e))?;
+
+                    for record in records.iter().filter(|r| r.expires_at.is_none_or(|t| t > now)) {
+                        let data = serde_json::to_vec(&record.value)
+                            .map_err(|e| format!("Failed to serialize value: {}", e))?;
+                        stmt.execute(params![record.key, data, record.created_at, record.updated_at, record.expires_at])
+                            .map_err(|e| format!("Failed to store '{}': {}", record.key, e))?;
+                        imported += 1;
+                    }
+                }
+                tx.commit()
+                    .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+                imported
+            },
+            None => return Err("Database not available".to_string()),
+        };
+
+        // Values in memory might be outdated now
+        self.memory_cache.clear();
+        self.current_memory_bytes = 0;
+
+        Ok(imported)
+    }
+
+    /// Get the number and size of the entries of each service, ordered by service name
+    pub fn get_service_stats(&mut self) -> Result<Vec<ServiceStats>, String> {
+        if !self.enabled {
+            return Ok(Vec::new());
+        }
+
+        self.flush()?;
+
+        let db = self.db.as_ref()
+            .ok_or_else(|| "Database connection is not available".to_string())?;
+
+        let mut stmt = db.prepare(
+            "SELECT CASE WHEN instr(key, '::') > 0 THEN substr(key, 1, instr(key, '::') - 1) ELSE key END AS service,
+                    COUNT(*), SUM(length(value))
+             FROM cache GROUP BY service ORDER BY service"
+        ).map_err(|e| format!("Failed to prepare statistics statement: {}", e))?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(ServiceStats {
+                service: row.get(0)?,
+                entries: row.get::<_, i64>(1)? as usize,
+                size_bytes: row.get::<_, Option<i64>>(2)?.unwrap_or(0) as usize,
+            })
+        }).map_err(|e| format!("Failed to execute statistics query: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read row: {}", e))
+    }
+
     /// Preload all cache entries matching a prefix into the LRU memory cache
     /// 
     /// This function loads all database entries with the given prefix into the LRU cache
@@ -931,6 +1529,8 @@ impl AttributeCache {
                 memory_entries: 0,
                 memory_bytes: 0,
                 memory_limit_bytes: self.max_memory_bytes,
+                pending_writes: 0,
+                shared_cache: None,
             });
         }
 
@@ -961,6 +1561,8 @@ impl AttributeCache {
             memory_entries: self.memory_cache.len(),
             memory_bytes: self.current_memory_bytes,
             memory_limit_bytes: self.max_memory_bytes,
+            pending_writes: self.pending_writes.len(),
+            shared_cache: self.shared.as_ref().map(|shared| shared.name().to_string()),
         })
     }
 }
@@ -972,29 +1574,61 @@ pub fn get_attribute_cache() -> parking_lot::MutexGuard<'static, AttributeCache>
     ATTRIBUTE_CACHE.lock()
 }
 
+// The shared cache is only used after the lock of the global cache has been released, so other threads
+// can use the cache while a request to the shared cache is in progress.
+
 /// Store a value in the attribute cache
 pub fn set<T: Serialize + ?Sized>(key: &str, value: &T) -> Result<(), String> {
-    get_attribute_cache().set(key, value)
+    let ttl_seconds = get_attribute_cache().ttl_for_key(key);
+    match ttl_seconds {
+        Some(ttl_seconds) => set_with_ttl(key, value, ttl_seconds),
+        None => set_with_expiry(key, value, None),
+    }
 }
 
 /// Store a value in the attribute cache with an optional expiry time (Unix timestamp)
 pub fn set_with_expiry<T: Serialize + ?Sized>(key: &str, value: &T, expires_at: Option<i64>) -> Result<(), String> {
-    get_attribute_cache().set_with_expiry(key, value, expires_at)
+    let (data, shared) = {
+        let mut cache = get_attribute_cache();
+        (cache.set_local(key, value, expires_at)?, cache.shared_for(key))
+    };
+    if let Some(shared) = shared {
+        shared.set(key, &data, expires_at);
+    }
+    Ok(())
 }
 
 /// Store a value in the attribute cache with a TTL (time to live) in seconds
 pub fn set_with_ttl<T: Serialize + ?Sized>(key: &str, value: &T, ttl_seconds: u64) -> Result<(), String> {
-    get_attribute_cache().set_with_ttl(key, value, ttl_seconds)
+    set_with_expiry(key, value, Some(now_timestamp()? + ttl_seconds as i64))
 }
 
 /// Get a value from the attribute cache
 pub fn get<T: for<'de> Deserialize<'de>>(key: &str) -> Result<Option<T>, String> {
-    get_attribute_cache().get(key)
+    let shared = {
+        let mut cache = get_attribute_cache();
+        if let Some(value) = cache.get_local(key)? {
+            return Ok(Some(value));
+        }
+        cache.shared_for(key)
+    };
+    let Some(shared) = shared else {
+        return Ok(None);
+    };
+    let found = fetch_shared(&shared, key)?;
+    get_attribute_cache().keep_shared(key, found)
 }
 
 /// Remove a value from the attribute cache
 pub fn remove(key: &str) -> Result<bool, String> {
-    get_attribute_cache().remove(key)
+    let (removed, shared) = {
+        let mut cache = get_attribute_cache();
+        (cache.remove_local(key)?, cache.shared_for(key))
+    };
+    if let Some(shared) = shared {
+        shared.remove(key);
+    }
+    Ok(removed)
 }
 
 /// Clear the entire attribute cache
@@ -1007,6 +1641,52 @@ pub fn cleanup() -> Result<usize, String> {
     get_attribute_cache().cleanup()
 }
 
+/// Remove all expired entries from the attribute cache
+pub fn remove_expired() -> Result<usize, String> {
+    get_attribute_cache().remove_expired()
+}
+
+/// Store all buffered writes of the attribute cache in the database
+pub fn flush() -> Result<usize, String> {
+    get_attribute_cache().flush()
+}
+
+/// Start a background thread that periodically stores buffered writes
+///
+/// Only one flush thread is started, further calls are ignored.
+pub fn start_write_behind(interval: Duration) {
+    if FLUSH_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    info!("Storing buffered attribute cache writes every {} seconds", interval.as_secs());
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        if let Err(e) = flush() {
+            warn!("Failed to store buffered attribute cache writes: {}", e);
+        }
+    });
+}
+
+/// Start a background thread that periodically removes expired entries
+///
+/// Only one sweep thread is started, further calls are ignored.
+pub fn start_expiry_sweep(interval: Duration) {
+    if SWEEP_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    info!("Removing expired attribute cache entries every {} seconds", interval.as_secs());
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        match remove_expired() {
+            Ok(count) if count > 0 => info!("Removed {} expired entries from attribute cache", count),
+            Ok(_) => {},
+            Err(e) => debug!("Failed to remove expired attribute cache entries: {}", e),
+        }
+    });
+}
+
 /// List all cache keys, optionally filtered by prefix
 pub fn list_keys(prefix_filter: Option<&str>) -> Result<Vec<String>, String> {
     get_attribute_cache().list_keys(prefix_filter)
@@ -1019,7 +1699,19 @@ pub fn list_entries(prefix_filter: Option<&str>) -> Result<Vec<CacheEntry>, Stri
 
 /// Remove all cache entries matching a prefix
 pub fn remove_by_prefix(prefix: &str) -> Result<usize, String> {
-    get_attribute_cache().remove_by_prefix(prefix)
+    let (deleted, shared) = {
+        let mut cache = get_attribute_cache();
+        (cache.remove_by_prefix_local(prefix)?, cache.shared.clone())
+    };
+    if let Some(shared) = shared {
+        shared.remove_by_prefix(prefix);
+    }
+    Ok(deleted)
+}
+
+/// Get the number and size of the entries of each service
+pub fn get_service_stats() -> Result<Vec<ServiceStats>, String> {
+    get_attribute_cache().get_service_stats()
 }
 
 /// Preload all cache entries matching a prefix into the LRU memory cache
@@ -2215,4 +2907,280 @@ mod tests {
         assert!(entries[0].expires_at.is_some());
         assert_eq!(entries[0].expires_at.unwrap(), future_time);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*not_found::*", "theaudiodb::not_found::abc"));
+        assert!(glob_match("*not_found::*", "artist::mbid_not_found::Adele"));
+        assert!(glob_match("spotify::*", "spotify::artist::Adele"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*not_found::*", "artist::mbid::Adele"));
+        assert!(!glob_match("a?c", "abbc"));
+        assert!(!glob_match("spotify::*", "theaudiodb::spotify::x"));
+    }
+
+    #[test]
+    fn test_ttl_rules_on_set() {
+        let (mut cache, _temp_dir) = create_test_cache();
+        cache.set_ttl_rules(vec![TtlRule { pattern: "volatile::*".to_string(), ttl_seconds: 3600 }]);
+
+        cache.set("volatile::key", "value1").unwrap();
+        cache.set("stable::key", "value2").unwrap();
+
+        let entries = cache.list_entries(None).unwrap();
+        let volatile = entries.iter().find(|e| e.key == "volatile::key").unwrap();
+        let stable = entries.iter().find(|e| e.key == "stable::key").unwrap();
+
+        assert!(volatile.expires_at.is_some());
+        assert_eq!(stable.expires_at, None);
+    }
+
+    #[test]
+    fn test_default_not_found_ttl() {
+        let (mut cache, _temp_dir) = create_test_cache();
+
+        cache.set("theaudiodb::not_found::1234", &true).unwrap();
+
+        let entries = cache.list_entries(None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].expires_at.is_some());
+    }
+
+    #[test]
+    fn test_remove_expired() {
+        let (mut cache, _temp_dir) = create_test_cache();
+        let past = now_timestamp().unwrap() - 10;
+
+        cache.set_with_expiry("expired", "value1", Some(past)).unwrap();
+        cache.set_with_ttl("valid", "value2", 3600).unwrap();
+        cache.set("permanent", "value3").unwrap();
+
+        assert_eq!(cache.remove_expired().unwrap(), 1);
+
+        let keys = cache.list_keys(None).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(!keys.contains(&"expired".to_string()));
+        assert!(cache.memory_cache.peek("expired").is_none());
+    }
+
+    #[test]
+    fn test_apply_ttl_rules_to_existing_entries() {
+        let (mut cache, _temp_dir) = create_test_cache();
+        cache.set_ttl_rules(Vec::new());
+
+        cache.set("wikipedia::not_found::abc::en", &true).unwrap();
+        cache.set("wikipedia::biography::abc::en", "text").unwrap();
+
+        cache.set_ttl_rules(default_ttl_rules());
+        assert_eq!(cache.apply_ttl_rules().unwrap(), 1);
+
+        let entries = cache.list_entries(None).unwrap();
+        let not_found = entries.iter().find(|e| e.key.contains("not_found")).unwrap();
+        let biography = entries.iter().find(|e| e.key.contains("biography")).unwrap();
+        assert_eq!(not_found.expires_at, Some(not_found.updated_at + DEFAULT_NOT_FOUND_TTL_SECONDS as i64));
+        assert_eq!(biography.expires_at, None);
+    }
+
+    #[test]
+    fn test_remove_by_prefix() {
+        let (mut cache, _temp_dir) = create_test_cache();
+
+        cache.set("theaudiodb::mbid::1", "value1").unwrap();
+        cache.set("theaudiodb::mbid::2", "value2").unwrap();
+        cache.set("TheAudioDB::mbid::3", "value3").unwrap();
+        cache.set("theaudiodbXmbid::4", "value4").unwrap();
+        cache.set("wikipedia::biography::1", "value5").unwrap();
+
+        assert_eq!(cache.remove_by_prefix("theaudiodb::").unwrap(), 2);
+        assert_eq!(cache.list_keys(None).unwrap().len(), 3);
+        assert_eq!(cache.get::<String>("theaudiodb::mbid::1").unwrap(), None);
+
+        // '_' is not a wildcard
+        cache.set("artist::mbidXnot_found::a", "value6").unwrap();
+        assert_eq!(cache.remove_by_prefix("artist::mbid_").unwrap(), 0);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.current_memory_bytes, 0);
+    }
+
+    #[test]
+    fn test_service_stats() {
+        let (mut cache, _temp_dir) = create_test_cache();
+
+        cache.set("theaudiodb::mbid::1", "value1").unwrap();
+        cache.set("theaudiodb::not_found::2", &true).unwrap();
+        cache.set("wikipedia::biography::1", "value3").unwrap();
+        cache.set("plain", "value4").unwrap();
+
+        let stats = cache.get_service_stats().unwrap();
+        let services: Vec<(&str, usize)> = stats.iter().map(|s| (s.service.as_str(), s.entries)).collect();
+        assert_eq!(services, vec![("plain", 1), ("theaudiodb", 2), ("wikipedia", 1)]);
+        assert_eq!(stats[0].size_bytes, "\"value4\"".len());
+    }
+
+    #[test]
+    fn test_export_import_records() {
+        let (mut source, _source_dir) = create_test_cache();
+        let past = now_timestamp().unwrap() - 10;
+
+        source.set("artist::mbid::Adele", &vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493"]).unwrap();
+        source.set_with_ttl("theaudiodb::not_found::1", &true, 3600).unwrap();
+        source.set_with_expiry("expired", "value", Some(past)).unwrap();
+
+        let records = source.export_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].key, "artist::mbid::Adele");
+
+        let (mut target, _target_dir) = create_test_cache();
+        target.set("artist::mbid::Adele", &vec!["outdated"]).unwrap();
+        assert_eq!(target.import_records(&records).unwrap(), 2);
+
+        assert_eq!(target.export_records().unwrap(), records);
+        assert_eq!(
+            target.get::<Vec<String>>("artist::mbid::Adele").unwrap(),
+            Some(vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493".to_string()])
+        );
+    }
+
+    fn count_rows(db_file: &Path) -> i64 {
+        let conn = Connection::open(db_file).unwrap();
+        conn.query_row("SELECT COUNT(*) FROM cache", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_write_behind() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let db_file = temp_dir.path().join("write_behind.db");
+        let mut cache = AttributeCache::with_database_file(&db_file);
+        cache.set_max_pending_writes(3);
+
+        cache.set("key1", "value1").unwrap();
+        cache.set("key2", "value2").unwrap();
+        assert_eq!(cache.pending_write_count(), 2);
+        assert_eq!(count_rows(&db_file), 0);
+
+        // Buffered values are visible, even if they are not in the memory cache anymore
+        cache.memory_cache.clear();
+        assert_eq!(cache.get::<String>("key1").unwrap(), Some("value1".to_string()));
+
+        // Removing a buffered write also drops it from the buffer
+        assert!(cache.remove("key2").unwrap());
+        assert_eq!(cache.pending_write_count(), 1);
+
+        // Reaching the limit stores everything
+        cache.set("key3", "value3").unwrap();
+        cache.set("key4", "value4").unwrap();
+        assert_eq!(cache.pending_write_count(), 0);
+        assert_eq!(count_rows(&db_file), 3);
+
+        // Reading from the database includes buffered writes
+        cache.set("key5", "value5").unwrap();
+        assert_eq!(cache.list_keys(None).unwrap().len(), 4);
+        assert_eq!(cache.pending_write_count(), 0);
+    }
+
+    #[test]
+    fn test_write_behind_keeps_created_at() {
+        let (mut cache, _temp_dir) = create_test_cache();
+        cache.set("key", "value1").unwrap();
+        let (created_at, _) = cache.get_timestamps("key").unwrap().unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        cache.set_max_pending_writes(10);
+        cache.set("key", "value2").unwrap();
+        assert_eq!(cache.flush().unwrap(), 1);
+
+        let (created_after, updated_after) = cache.get_timestamps("key").unwrap().unwrap();
+        assert_eq!(created_after, created_at);
+        assert!(updated_after > created_at);
+        assert_eq!(cache.get::<String>("key").unwrap(), Some("value2".to_string()));
+    }
+
+    #[test]
+    fn test_write_behind_flush_on_drop() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let db_file = temp_dir.path().join("drop.db");
+        {
+            let mut cache = AttributeCache::with_database_file(&db_file);
+            cache.set_max_pending_writes(100);
+            cache.set("key", "value").unwrap();
+            assert_eq!(count_rows(&db_file), 0);
+        }
+        assert_eq!(count_rows(&db_file), 1);
+    }
+
+    use crate::helpers::sharedcache::SharedValue;
+
+    /// Shared cache in memory, the map is shared so tests can inspect it
+    struct MemorySharedCache {
+        values: Arc<Mutex<HashMap<String, SharedValue>>>,
+    }
+
+    impl SharedCache for MemorySharedCache {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn get(&mut self, key: &str) -> Result<Option<SharedValue>, String> {
+            Ok(self.values.lock().get(key).cloned())
+        }
+
+        fn set(&mut self, key: &str, value: &[u8], ttl_seconds: Option<u64>) -> Result<(), String> {
+            self.values.lock().insert(key.to_string(), (value.to_vec(), ttl_seconds));
+            Ok(())
+        }
+
+        fn remove(&mut self, key: &str) -> Result<(), String> {
+            self.values.lock().remove(key);
+            Ok(())
+        }
+
+        fn remove_by_prefix(&mut self, prefix: &str) -> Result<usize, String> {
+            let mut values = self.values.lock();
+            let before = values.len();
+            values.retain(|key, _| !key.starts_with(prefix));
+            Ok(before - values.len())
+        }
+    }
+
+    #[test]
+    fn test_shared_cache() {
+        let shared = Arc::new(Mutex::new(HashMap::new()));
+        let prefixes = vec!["artist::".to_string()];
+
+        let (mut device1, _dir1) = create_test_cache();
+        device1.set_shared_cache(Some(Box::new(MemorySharedCache { values: shared.clone() })), prefixes.clone());
+        let (mut device2, _dir2) = create_test_cache();
+        device2.set_shared_cache(Some(Box::new(MemorySharedCache { values: shared.clone() })), prefixes);
+
+        device1.set("artist::mbid::Adele", &vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493"]).unwrap();
+        device1.set_with_ttl("artist::mbid_not_found::Nobody", &true, 3600).unwrap();
+        device1.set("image_meta::/local/file.jpg", "local").unwrap();
+
+        // Only keys with shared prefixes are shared, with their TTL
+        assert_eq!(shared.lock().len(), 2);
+        assert!(shared.lock()["artist::mbid_not_found::Nobody"].1.is_some());
+
+        // The other device finds the value and keeps it locally
+        assert_eq!(
+            device2.get::<Vec<String>>("artist::mbid::Adele").unwrap(),
+            Some(vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493".to_string()])
+        );
+        assert_eq!(device2.get::<String>("image_meta::/local/file.jpg").unwrap(), None);
+        assert!(device2.list_keys(None).unwrap().contains(&"artist::mbid::Adele".to_string()));
+        let entries = device2.list_entries(Some("artist::mbid_not_found::")).unwrap();
+        assert_eq!(device2.get::<bool>("artist::mbid_not_found::Nobody").unwrap(), Some(true));
+        assert!(entries.is_empty());
+        assert!(device2.list_entries(Some("artist::mbid_not_found::")).unwrap()[0].expires_at.is_some());
+
+        // Removing a key removes it everywhere
+        device2.remove("artist::mbid::Adele").unwrap();
+        assert!(shared.lock().get("artist::mbid::Adele").is_none());
+        assert_eq!(device2.remove_by_prefix("artist::").unwrap(), 1);
+        assert!(shared.lock().is_empty());
+
+        let stats = device1.get_cache_stats().unwrap();
+        assert_eq!(stats.shared_cache, Some("memory".to_string()));
+    }
+}
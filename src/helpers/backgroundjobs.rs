@@ -1,9 +1,48 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
 use parking_lot::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
-use log::debug;
+use log::{debug, info};
+use thiserror::Error;
+use crate::audiocontrol::eventbus::EventBus;
+use crate::data::PlayerEvent;
+
+/// Minimum time between two progress events of a job, jobs may update for every item
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Errors when cancelling a job
+#[derive(Debug, Error, PartialEq)]
+pub enum CancelError {
+    #[error("Job with ID '{0}' not found")]
+    NotFound(String),
+
+    #[error("Job '{0}' can't be cancelled")]
+    NotCancellable(String),
+
+    #[error("Job '{0}' has already finished")]
+    Finished(String),
+}
+
+/// Cancellation request of a job, checked by the job between its steps
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the job should stop as soon as possible
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
 
 /// Represents a background job with its current status
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +56,12 @@ pub struct BackgroundJob {
     pub completed_items: Option<usize>,
     pub finished: bool,
     pub finish_time: Option<u64>,
+    /// Whether the job can be cancelled through the API
+    #[serde(default)]
+    pub cancellable: bool,
+    /// Whether the job has been cancelled, it is finished once it has stopped
+    #[serde(default)]
+    pub cancelled: bool,
 }
 
 impl BackgroundJob {
@@ -37,6 +82,8 @@ impl BackgroundJob {
             completed_items: None,
             finished: false,
             finish_time: None,
+            cancellable: false,
+            cancelled: false,
         }
     }
     
@@ -75,6 +122,19 @@ impl BackgroundJob {
         
         debug!("Marked background job '{}' as finished", self.id);
     }
+
+    /// Event announcing the current state of the job
+    pub fn progress_event(&self) -> PlayerEvent {
+        PlayerEvent::BackgroundJobProgress {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            progress: self.progress.clone(),
+            completed_items: self.completed_items,
+            total_items: self.total_items,
+            finished: self.finished,
+            cancelled: self.cancelled,
+        }
+    }
     
     /// Get the duration since the job started in seconds
     pub fn duration_seconds(&self) -> u64 {
@@ -98,6 +158,10 @@ impl BackgroundJob {
 /// Singleton manager for background jobs
 pub struct BackgroundJobs {
     jobs: Arc<Mutex<HashMap<String, BackgroundJob>>>,
+    /// Time of the last progress event of each job
+    last_events: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Cancellation requests of the running cancellable jobs
+    tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
 }
 
 impl BackgroundJobs {
@@ -105,7 +169,25 @@ impl BackgroundJobs {
     fn new() -> Self {
         Self {
             jobs: Arc::new(Mutex::new(HashMap::new())),
+            last_events: Arc::new(Mutex::new(HashMap::new())),
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Publish the state of a job, progress updates are rate limited
+    fn publish(&self, job: &BackgroundJob, force: bool) {
+        let now = Instant::now();
+        let mut last_events = self.last_events.lock();
+        if !should_publish(last_events.get(&job.id).copied(), now, force) {
+            return;
         }
+        if job.finished {
+            last_events.remove(&job.id);
+        } else {
+            last_events.insert(job.id.clone(), now);
+        }
+        drop(last_events);
+        EventBus::instance().publish(job.progress_event());
     }
     
     /// Get the global singleton instance
@@ -116,16 +198,35 @@ impl BackgroundJobs {
     
     /// Register a new background job
     pub fn register_job(&self, id: String, name: String) -> Result<(), String> {
-        let job = BackgroundJob::new(id.clone(), name);
+        self.tokens.lock().remove(&id);
+        self.insert_job(BackgroundJob::new(id, name));
+        Ok(())
+    }
+
+    /// Register a new background job that can be cancelled
+    ///
+    /// The job has to check the returned token regularly, stop when it is cancelled
+    /// and call `complete_job` as usual.
+    pub fn register_cancellable_job(&self, id: String, name: String) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.tokens.lock().insert(id.clone(), token.clone());
+        let mut job = BackgroundJob::new(id, name);
+        job.cancellable = true;
+        self.insert_job(job);
+        token
+    }
 
+    fn insert_job(&self, job: BackgroundJob) {
+        let id = job.id.clone();
         let mut jobs = self.jobs.lock();
         if jobs.contains_key(&id) {
             debug!("Overwriting existing job with ID '{}' with new job", id);
         } else {
             debug!("Registering new background job: {}", id);
         }
-        jobs.insert(id.clone(), job);
-        Ok(())
+        jobs.insert(id, job.clone());
+        drop(jobs);
+        self.publish(&job, true);
     }
     
     /// Update progress for an existing job
@@ -133,18 +234,49 @@ impl BackgroundJobs {
         let mut jobs = self.jobs.lock();
         if let Some(job) = jobs.get_mut(id) {
             job.update_progress(progress, completed, total);
+            let job = job.clone();
+            drop(jobs);
+            self.publish(&job, false);
             Ok(())
         } else {
             Err(format!("Job with ID '{}' not found", id))
         }
     }
     
+    /// Request the cancellation of a running job
+    ///
+    /// The job stops at its next check of the cancellation token and is finished then.
+    pub fn cancel_job(&self, id: &str) -> Result<BackgroundJob, CancelError> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs.get_mut(id).ok_or_else(|| CancelError::NotFound(id.to_string()))?;
+        if job.finished {
+            return Err(CancelError::Finished(id.to_string()));
+        }
+        let token = self.tokens.lock().get(id).cloned();
+        let Some(token) = token else {
+            return Err(CancelError::NotCancellable(id.to_string()));
+        };
+
+        token.cancel();
+        job.cancelled = true;
+        job.update_progress(Some("Cancelling".to_string()), None, None);
+        let job = job.clone();
+        drop(jobs);
+        info!("Cancelling background job '{}'", id);
+        self.publish(&job, true);
+        Ok(job)
+    }
+
     /// Mark a job as completed/finished
     pub fn complete_job(&self, id: &str) -> Result<(), String> {
+        self.tokens.lock().remove(id);
         let mut jobs = self.jobs.lock();
         if let Some(job) = jobs.get_mut(id) {
             job.mark_finished();
             debug!("Marked background job as finished: {}", id);
+            let job = job.clone();
+            drop(jobs);
+            self.publish(&job, true);
             Ok(())
         } else {
             Err(format!("Job with ID '{}' not found", id))
@@ -156,6 +288,11 @@ impl BackgroundJobs {
         Ok(self.jobs.lock().values().cloned().collect())
     }
     
+    /// Get the jobs that haven't finished yet
+    pub fn get_active_jobs(&self) -> Vec<BackgroundJob> {
+        self.jobs.lock().values().filter(|job| !job.finished).cloned().collect()
+    }
+
     /// Get a specific job by ID
     pub fn get_job(&self, id: &str) -> Result<Option<BackgroundJob>, String> {
         Ok(self.jobs.lock().get(id).cloned())
@@ -176,10 +313,22 @@ pub fn update_job(id: &str, progress: Option<String>, completed: Option<usize>,
     BackgroundJobs::instance().update_job(id, progress, completed, total)
 }
 
+pub fn register_cancellable_job(id: String, name: String) -> CancellationToken {
+    BackgroundJobs::instance().register_cancellable_job(id, name)
+}
+
 pub fn complete_job(id: &str) -> Result<(), String> {
     BackgroundJobs::instance().complete_job(id)
 }
 
+pub fn cancel_job(id: &str) -> Result<BackgroundJob, CancelError> {
+    BackgroundJobs::instance().cancel_job(id)
+}
+
+pub fn get_active_jobs() -> Vec<BackgroundJob> {
+    BackgroundJobs::instance().get_active_jobs()
+}
+
 pub fn get_all_jobs() -> Result<Vec<BackgroundJob>, String> {
     BackgroundJobs::instance().get_all_jobs()
 }
@@ -191,3 +340,44 @@ pub fn get_job(id: &str) -> Result<Option<BackgroundJob>, String> {
 pub fn job_count() -> usize {
     BackgroundJobs::instance().job_count()
 }
+
+/// Whether a progress event is due, the start and the end of a job are always published
+fn should_publish(last_event: Option<Instant>, now: Instant, force: bool) -> bool {
+    force || last_event.is_none_or(|last| now.duration_since(last) >= PROGRESS_EVENT_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_publish() {
+        let now = Instant::now();
+        assert!(should_publish(None, now, false));
+        assert!(!should_publish(Some(now), now + Duration::from_millis(100), false));
+        assert!(should_publish(Some(now), now + Duration::from_millis(100), true));
+        assert!(should_publish(Some(now), now + PROGRESS_EVENT_INTERVAL, false));
+    }
+
+    #[test]
+    fn test_cancel_job() {
+        let jobs = BackgroundJobs::new();
+        let token = jobs.register_cancellable_job("scan".to_string(), "Scan".to_string());
+        jobs.register_job("update".to_string(), "Update".to_string()).unwrap();
+
+        assert_eq!(jobs.cancel_job("update").unwrap_err(), CancelError::NotCancellable("update".to_string()));
+        assert_eq!(jobs.cancel_job("other").unwrap_err(), CancelError::NotFound("other".to_string()));
+        assert_eq!(jobs.get_active_jobs().len(), 2);
+
+        assert!(!token.is_cancelled());
+        let job = jobs.cancel_job("scan").unwrap();
+        assert!(job.cancelled);
+        assert!(token.is_cancelled());
+
+        jobs.complete_job("scan").unwrap();
+        assert_eq!(jobs.cancel_job("scan").unwrap_err(), CancelError::Finished("scan".to_string()));
+        let job = jobs.get_job("scan").unwrap().unwrap();
+        assert!(job.finished && job.cancelled);
+        assert_eq!(jobs.get_active_jobs().len(), 1);
+    }
+}
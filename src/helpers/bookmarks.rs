@@ -0,0 +1,340 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::config::get_service_config;
+use crate::data::{PlaybackState, PlayerEvent, Song};
+use crate::helpers::playback_progress::PlayerProgress;
+use crate::AudioController;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Weak;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Bookmarks are stored per track, e.g. "bookmarks::http://podcast.example.com/episode-12.mp3"
+const KEY_PREFIX: &str = "bookmarks::";
+
+/// Name of the bookmark saved automatically when playback stops
+pub const AUTO_BOOKMARK: &str = "auto";
+
+/// Positions closer to the start aren't saved automatically
+const MIN_AUTO_POSITION: f64 = 30.0;
+
+/// Positions closer to the end count as finished, the automatic bookmark is removed
+const END_MARGIN: f64 = 30.0;
+
+/// Errors of bookmark operations
+#[derive(Debug, Error)]
+pub enum BookmarkError {
+    #[error("No song is playing")]
+    NoSong,
+
+    #[error("Bookmark '{0}' not found")]
+    NotFound(String),
+
+    #[error("Invalid bookmark: {0}")]
+    Invalid(String),
+
+    #[error("Failed to store bookmark: {0}")]
+    Storage(String),
+}
+
+pub type Result<T> = std::result::Result<T, BookmarkError>;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_duration() -> u64 {
+    20
+}
+
+/// Configuration of the `bookmarks` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarksConfig {
+    /// Save the position of long tracks when playback stops
+    #[serde(default = "default_true")]
+    pub auto_save: bool,
+
+    /// Minimum track length in minutes for automatic bookmarks
+    #[serde(default = "default_min_duration")]
+    pub min_duration_minutes: u64,
+}
+
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self {
+            auto_save: true,
+            min_duration_minutes: default_min_duration(),
+        }
+    }
+}
+
+/// A named position in a track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    /// Position in seconds
+    pub position: f64,
+    /// Unix timestamp
+    pub created: u64,
+}
+
+/// The bookmarks of a track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackBookmarks {
+    /// Key identifying the track, the stream URL if the player reports it
+    pub key: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<f64>,
+    /// Sorted by position
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl TrackBookmarks {
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.name == name)
+    }
+}
+
+/// What to do with the automatic bookmark when playback stops
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AutoSave {
+    Save,
+    /// The track was played to the end
+    Clear,
+    Ignore,
+}
+
+/// Serializes read-modify-write cycles of the stored bookmarks
+static BOOKMARKS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Identify a track, None if the player doesn't report enough information
+pub fn track_key(song: &Song) -> Option<String> {
+    if let Some(url) = song.stream_url.as_deref().filter(|u| !u.is_empty()) {
+        return Some(url.to_string());
+    }
+    let title = song.title.as_deref().filter(|t| !t.is_empty())?;
+    Some(format!(
+        "{}|{}|{}",
+        song.artist.as_deref().unwrap_or(""),
+        song.album.as_deref().unwrap_or(""),
+        title
+    ))
+}
+
+fn storage_key(key: &str) -> String {
+    format!("{}{}", KEY_PREFIX, key)
+}
+
+/// Get the bookmarks of a track
+pub fn get_bookmarks(key: &str) -> Option<TrackBookmarks> {
+    match crate::helpers::settingsdb::get::<TrackBookmarks>(&storage_key(key)) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            warn!("Failed to read bookmarks of {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Get the bookmarks of all tracks
+pub fn list_bookmarks() -> Vec<TrackBookmarks> {
+    crate::helpers::settingsdb::get_all_keys()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|key| key.strip_prefix(KEY_PREFIX))
+        .filter_map(get_bookmarks)
+        .collect()
+}
+
+fn store(track: &TrackBookmarks) -> Result<()> {
+    let key = storage_key(&track.key);
+    if track.bookmarks.is_empty() {
+        crate::helpers::settingsdb::remove(&key).map(|_| ()).map_err(BookmarkError::Storage)
+    } else {
+        crate::helpers::settingsdb::set(&key, track).map_err(BookmarkError::Storage)
+    }
+}
+
+/// Save a position of a song, a bookmark with the same name is replaced
+pub fn set_bookmark(song: &Song, name: &str, position: f64) -> Result<TrackBookmarks> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(BookmarkError::Invalid("name must not be empty".to_string()));
+    }
+    if !position.is_finite() || position < 0.0 {
+        return Err(BookmarkError::Invalid(format!("invalid position {}", position)));
+    }
+    let key = track_key(song).ok_or(BookmarkError::NoSong)?;
+
+    let _guard = BOOKMARKS_LOCK.lock();
+    let mut track = get_bookmarks(&key).unwrap_or_else(|| TrackBookmarks {
+        key: key.clone(),
+        title: None,
+        artist: None,
+        album: None,
+        duration: None,
+        bookmarks: Vec::new(),
+    });
+    track.title = song.title.clone();
+    track.artist = song.artist.clone();
+    track.album = song.album.clone();
+    track.duration = song.duration.or(track.duration);
+    track.bookmarks.retain(|b| b.name != name);
+    track.bookmarks.push(Bookmark {
+        name: name.to_string(),
+        position,
+        created: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    });
+    track.bookmarks.sort_by(|a, b| a.position.total_cmp(&b.position));
+    store(&track)?;
+    debug!("Saved bookmark '{}' at {:.0}s of {}", name, position, key);
+    Ok(track)
+}
+
+/// Remove a bookmark of a track
+pub fn remove_bookmark(key: &str, name: &str) -> Result<()> {
+    let _guard = BOOKMARKS_LOCK.lock();
+    let mut track = get_bookmarks(key).ok_or_else(|| BookmarkError::NotFound(name.to_string()))?;
+    let count = track.bookmarks.len();
+    track.bookmarks.retain(|b| b.name != name);
+    if track.bookmarks.len() == count {
+        return Err(BookmarkError::NotFound(name.to_string()));
+    }
+    store(&track)
+}
+
+fn auto_save_action(config: &BookmarksConfig, duration: Option<f64>, position: f64) -> AutoSave {
+    let Some(duration) = duration else {
+        return AutoSave::Ignore;
+    };
+    if duration < (config.min_duration_minutes * 60) as f64 {
+        AutoSave::Ignore
+    } else if position >= duration - END_MARGIN {
+        AutoSave::Clear
+    } else if position < MIN_AUTO_POSITION {
+        AutoSave::Ignore
+    } else {
+        AutoSave::Save
+    }
+}
+
+/// Song and position of a player, tracked from its events
+#[derive(Default)]
+struct TrackedPlayer {
+    song: Option<Song>,
+    progress: PlayerProgress,
+}
+
+fn player_position(controller: &Weak<AudioController>, player_id: &str) -> Option<f64> {
+    let controller = controller.upgrade()?;
+    let player = controller.get_player_by_name(player_id)?;
+    let position = player.read().get_position();
+    position
+}
+
+/// Save or clear the automatic bookmark of the song a player stopped
+fn auto_save(config: &BookmarksConfig, song: &Song, position: f64) {
+    match auto_save_action(config, song.duration, position) {
+        AutoSave::Save => match set_bookmark(song, AUTO_BOOKMARK, position) {
+            Ok(_) => info!("Saved position {:.0}s of {}", position, song),
+            Err(e) => warn!("Failed to save position of {}: {}", song, e),
+        },
+        AutoSave::Clear => {
+            if let Some(key) = track_key(song) {
+                if remove_bookmark(&key, AUTO_BOOKMARK).is_ok() {
+                    debug!("{} was played to the end, removed its automatic bookmark", song);
+                }
+            }
+        }
+        AutoSave::Ignore => {}
+    }
+}
+
+/// Initialize bookmarks and save the position of long tracks when playback stops
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let config = match get_service_config(config, "bookmarks") {
+        Some(c) => serde_json::from_value::<BookmarksConfig>(c.clone()).unwrap_or_else(|e| {
+            warn!("Invalid bookmarks configuration, using defaults: {}", e);
+            BookmarksConfig::default()
+        }),
+        None => BookmarksConfig::default(),
+    };
+    if !config.auto_save {
+        debug!("Automatic bookmarks are disabled");
+        return;
+    }
+    info!("Saving the position of tracks longer than {} minutes on stop", config.min_duration_minutes);
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![
+        EventSubscription::StateChanged,
+        EventSubscription::SongChanged,
+        EventSubscription::PositionChanged,
+    ]);
+    let mut players: HashMap<String, TrackedPlayer> = HashMap::new();
+    bus.spawn_worker(id, receiver, move |event| {
+        let Some(player_id) = event.player_id().map(|id| id.to_string()) else {
+            return;
+        };
+        let player = players.entry(player_id.clone()).or_default();
+        match event {
+            PlayerEvent::SongChanged { song, .. } => {
+                player.song = song;
+                player.progress.set_position(0.0);
+            }
+            PlayerEvent::PositionChanged { position, .. } => player.progress.set_position(position),
+            PlayerEvent::StateChanged { state, .. } => match state {
+                PlaybackState::Playing => {
+                    if let Some(position) = player_position(&controller, &player_id) {
+                        player.progress.set_position(position);
+                    }
+                    player.progress.set_playing(true);
+                }
+                PlaybackState::Stopped => {
+                    player.progress.set_playing(false);
+                    if let Some(song) = &player.song {
+                        auto_save(&config, song, player.progress.get_position());
+                    }
+                    player.progress.reset();
+                }
+                _ => player.progress.set_playing(false),
+            },
+            _ => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_save_action() {
+        let config = BookmarksConfig::default();
+        let mix = Some(3600.0);
+        assert_eq!(auto_save_action(&config, mix, 1800.0), AutoSave::Save);
+        assert_eq!(auto_save_action(&config, mix, 10.0), AutoSave::Ignore);
+        assert_eq!(auto_save_action(&config, mix, 3590.0), AutoSave::Clear);
+        // Short songs and streams without a duration aren't bookmarked
+        assert_eq!(auto_save_action(&config, Some(240.0), 120.0), AutoSave::Ignore);
+        assert_eq!(auto_save_action(&config, None, 1800.0), AutoSave::Ignore);
+    }
+
+    #[test]
+    fn test_track_key() {
+        let mut song = Song {
+            title: Some("Episode 12".to_string()),
+            artist: Some("Podcast".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(track_key(&song).as_deref(), Some("Podcast||Episode 12"));
+        song.stream_url = Some("http://podcast.example.com/12.mp3".to_string());
+        assert_eq!(track_key(&song).as_deref(), Some("http://podcast.example.com/12.mp3"));
+        assert_eq!(track_key(&Song::default()), None);
+    }
+}
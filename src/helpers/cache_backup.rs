@@ -0,0 +1,155 @@
+use crate::helpers::attributecache::{AttributeCache, CacheRecord};
+use crate::helpers::settingsdb::SettingsDb;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Version of the backup format, increased on incompatible changes
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Errors of cache backups and restores
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("Failed to access {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid backup: {0}")]
+    Format(#[from] serde_json::Error),
+
+    #[error("Unsupported backup version {0}, this version reads up to {BACKUP_VERSION}")]
+    Version(u32),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+/// Contents of the attribute cache and the settings database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheBackup {
+    pub version: u32,
+    /// Unix timestamp of the backup
+    pub created: u64,
+    pub attribute_cache: Vec<CacheRecord>,
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// Number of restored entries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub cache_entries: usize,
+    pub settings: usize,
+}
+
+/// Collect all entries of the attribute cache and the settings database
+pub fn backup(cache: &mut AttributeCache, settings: &mut SettingsDb) -> Result<CacheBackup, BackupError> {
+    let attribute_cache = cache.export_records().map_err(BackupError::Database)?;
+
+    let mut values = BTreeMap::new();
+    for key in settings.get_all_keys().map_err(BackupError::Database)? {
+        match settings.get::<serde_json::Value>(&key) {
+            Ok(Some(value)) => {
+                values.insert(key, value);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Skipping setting '{}': {}", key, e),
+        }
+    }
+
+    info!("Backing up {} cache entries and {} settings", attribute_cache.len(), values.len());
+    Ok(CacheBackup {
+        version: BACKUP_VERSION,
+        created: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        attribute_cache,
+        settings: values,
+    })
+}
+
+/// Restore a backup, entries with the same key are replaced, all others are kept
+pub fn restore(
+    backup: &CacheBackup,
+    cache: &mut AttributeCache,
+    settings: &mut SettingsDb,
+) -> Result<RestoreSummary, BackupError> {
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::Version(backup.version));
+    }
+
+    let cache_entries = cache.import_records(&backup.attribute_cache).map_err(BackupError::Database)?;
+    for (key, value) in &backup.settings {
+        settings.set(key, value).map_err(BackupError::Database)?;
+    }
+
+    info!("Restored {} cache entries and {} settings", cache_entries, backup.settings.len());
+    Ok(RestoreSummary {
+        cache_entries,
+        settings: backup.settings.len(),
+    })
+}
+
+/// Write a backup to a JSON file
+pub fn write_backup(backup: &CacheBackup, path: &Path) -> Result<(), BackupError> {
+    let file = std::fs::File::create(path).map_err(|e| BackupError::Io(path.display().to_string(), e))?;
+    serde_json::to_writer(BufWriter::new(file), backup)?;
+    Ok(())
+}
+
+/// Read a backup from a JSON file
+pub fn read_backup(path: &Path) -> Result<CacheBackup, BackupError> {
+    let file = std::fs::File::open(path).map_err(|e| BackupError::Io(path.display().to_string(), e))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backup_and_restore() {
+        let source_dir = TempDir::new().unwrap();
+        let mut cache = AttributeCache::with_database_file(source_dir.path().join("attributes.db"));
+        let mut settings = SettingsDb::with_directory(source_dir.path());
+        cache.set("artist::mbid::Adele", &vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493"]).unwrap();
+        settings.set("device::name", &"Living Room").unwrap();
+        settings.set("volume::limit", &80).unwrap();
+
+        let path = source_dir.path().join("backup.json");
+        write_backup(&backup(&mut cache, &mut settings).unwrap(), &path).unwrap();
+
+        let target_dir = TempDir::new().unwrap();
+        let mut cache = AttributeCache::with_database_file(target_dir.path().join("attributes.db"));
+        let mut settings = SettingsDb::with_directory(target_dir.path());
+        settings.set("device::name", &"New Device").unwrap();
+        settings.set("other", &true).unwrap();
+
+        let summary = restore(&read_backup(&path).unwrap(), &mut cache, &mut settings).unwrap();
+        assert_eq!(summary.cache_entries, 1);
+        assert_eq!(summary.settings, 2);
+
+        assert_eq!(
+            cache.get::<Vec<String>>("artist::mbid::Adele").unwrap(),
+            Some(vec!["cc2c9c3c-b7bc-4b8b-84d8-4fbd8779e493".to_string()])
+        );
+        assert_eq!(settings.get::<String>("device::name").unwrap(), Some("Living Room".to_string()));
+        assert_eq!(settings.get::<i64>("volume::limit").unwrap(), Some(80));
+        assert_eq!(settings.get::<bool>("other").unwrap(), Some(true));
+    }
+
+    #[test]
+    fn test_newer_version() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = AttributeCache::with_database_file(dir.path().join("attributes.db"));
+        let mut settings = SettingsDb::with_directory(dir.path());
+        let backup = CacheBackup {
+            version: BACKUP_VERSION + 1,
+            created: 0,
+            attribute_cache: Vec::new(),
+            settings: BTreeMap::new(),
+        };
+        assert!(matches!(restore(&backup, &mut cache, &mut settings), Err(BackupError::Version(_))));
+    }
+}
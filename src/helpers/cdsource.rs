@@ -0,0 +1,663 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::helpers::musicbrainz::{self, DiscRelease};
+use crate::players::mpd::library::MPDLibrary;
+use crate::players::mpd::MPDPlayerController;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const JOB_ID: &str = "cd_rip";
+
+/// CD frames per second, TOC offsets are given in frames
+const FRAMES_PER_SECOND: u32 = 75;
+
+/// Errors that can occur when playing or ripping a CD
+#[derive(Debug, Error)]
+pub enum CdError {
+    #[error("No CD drive found")]
+    NoDrive,
+
+    #[error("No disc in the drive")]
+    NoDisc,
+
+    #[error("Track {0} not found on the disc")]
+    InvalidTrack(u32),
+
+    #[error("A rip is already running")]
+    AlreadyRunning,
+
+    #[error("No MPD player available: {0}")]
+    NoPlayer(String),
+
+    #[error("Command failed: {0}")]
+    Command(String),
+}
+
+pub type Result<T> = std::result::Result<T, CdError>;
+
+/// Configuration of the CD source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdConfig {
+    /// Watch for CDs
+    #[serde(default)]
+    pub enable: bool,
+
+    /// CD drive to use, the first drive (preferring USB drives) is used if not set
+    #[serde(default)]
+    pub device: Option<String>,
+
+    /// Directory inside the MPD music directory in which ripped CDs are stored
+    #[serde(default = "default_rip_dir")]
+    pub rip_dir: String,
+
+    /// Seconds between two checks for an inserted disc
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_rip_dir() -> String {
+    "CD".to_string()
+}
+
+fn default_poll_interval_secs() -> u64 {
+    3
+}
+
+impl Default for CdConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            device: None,
+            rip_dir: default_rip_dir(),
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+/// Table of contents of an audio CD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscToc {
+    pub first_track: u32,
+    pub last_track: u32,
+    /// Offset of the lead-out in frames
+    pub leadout: u32,
+    /// Start offsets of the tracks in frames, including the 2 second lead-in
+    pub offsets: Vec<u32>,
+}
+
+impl DiscToc {
+    /// Parse the output of `cd-discid --musicbrainz`: track count, track offsets, lead-out offset
+    pub fn from_cd_discid(output: &str) -> Option<Self> {
+        let values: Vec<u32> = output
+            .split_whitespace()
+            .map(|v| v.parse().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        let (&count, rest) = values.split_first()?;
+        if count == 0 || count > 99 || rest.len() != count as usize + 1 {
+            return None;
+        }
+        Some(Self {
+            first_track: 1,
+            last_track: count,
+            leadout: rest[count as usize],
+            offsets: rest[..count as usize].to_vec(),
+        })
+    }
+
+    /// Calculate the MusicBrainz disc ID
+    pub fn disc_id(&self) -> String {
+        let mut input = format!("{:02X}{:02X}{:08X}", self.first_track, self.last_track, self.leadout);
+        for index in 0..99 {
+            input.push_str(&format!("{:08X}", self.offsets.get(index).copied().unwrap_or(0)));
+        }
+        let digest = Sha1::digest(input.as_bytes());
+        // MusicBrainz uses a URL-safe base64 variant with its own characters
+        STANDARD
+            .encode(digest)
+            .replace('+', ".")
+            .replace('/', "_")
+            .replace('=', "-")
+    }
+
+    /// TOC in the format used by the MusicBrainz web service
+    pub fn musicbrainz_toc(&self) -> String {
+        let mut toc = format!("{} {} {}", self.first_track, self.last_track, self.leadout);
+        for offset in &self.offsets {
+            toc.push_str(&format!(" {}", offset));
+        }
+        toc
+    }
+
+    /// Duration of a track (1-based) in seconds
+    pub fn track_duration(&self, number: u32) -> Option<f64> {
+        let index = number.checked_sub(self.first_track)? as usize;
+        let start = *self.offsets.get(index)?;
+        let end = self.offsets.get(index + 1).copied().unwrap_or(self.leadout);
+        Some(end.saturating_sub(start) as f64 / FRAMES_PER_SECOND as f64)
+    }
+}
+
+/// A track of the inserted CD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration: f64,
+    /// MPD URI of the track
+    pub uri: String,
+}
+
+/// The inserted CD with metadata from MusicBrainz if the disc is known
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdDisc {
+    pub device: String,
+    pub disc_id: String,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub date: Option<String>,
+    /// MusicBrainz release ID
+    pub release_id: Option<String>,
+    pub tracks: Vec<CdTrack>,
+}
+
+impl CdDisc {
+    fn new(device: &str, toc: &DiscToc, release: Option<DiscRelease>) -> Self {
+        let tracks = (toc.first_track..=toc.last_track)
+            .map(|number| {
+                let release_track = release
+                    .as_ref()
+                    .and_then(|r| r.tracks.iter().find(|t| t.number == number));
+                CdTrack {
+                    number,
+                    title: release_track.map(|t| t.title.clone()),
+                    artist: release_track.map(|t| t.artist.clone()),
+                    duration: toc.track_duration(number).unwrap_or(0.0),
+                    uri: format!("cdda://{}/{}", device, number),
+                }
+            })
+            .collect();
+
+        Self {
+            device: device.to_string(),
+            disc_id: toc.disc_id(),
+            album: release.as_ref().map(|r| r.title.clone()),
+            artist: release.as_ref().map(|r| r.artist.clone()),
+            date: release.as_ref().and_then(|r| r.date.clone()),
+            release_id: release.map(|r| r.release_id),
+            tracks,
+        }
+    }
+}
+
+/// Report of a rip
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RipReport {
+    pub disc_id: String,
+    /// Directory the tracks are written to
+    pub directory: String,
+    pub started_at: u64,
+    /// Not set while the rip is running
+    pub finished_at: Option<u64>,
+    pub total_tracks: usize,
+    pub ripped_tracks: usize,
+    /// Tracks that already existed in the directory
+    pub skipped_tracks: usize,
+    pub failed_tracks: usize,
+    pub error: Option<String>,
+}
+
+static CONFIG: Lazy<RwLock<CdConfig>> = Lazy::new(|| RwLock::new(CdConfig::default()));
+static DISC: Lazy<RwLock<Option<CdDisc>>> = Lazy::new(|| RwLock::new(None));
+static RIPPING: AtomicBool = AtomicBool::new(false);
+static LAST_RIP: Lazy<RwLock<Option<RipReport>>> = Lazy::new(|| RwLock::new(None));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+fn now_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Initialize the CD source from the `cd` service configuration
+///
+/// If enabled, a background thread checks for inserted discs and looks them up on MusicBrainz.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let cd_config = match get_service_config(config, "cd") {
+        Some(c) => match serde_json::from_value::<CdConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid cd configuration, CD support disabled: {}", e);
+                return;
+            }
+        },
+        None => CdConfig::default(),
+    };
+    *CONFIG.write() = cd_config.clone();
+
+    if !cd_config.enable {
+        debug!("CD support is disabled");
+        return;
+    }
+
+    info!("Watching for audio CDs");
+    let interval = Duration::from_secs(cd_config.poll_interval_secs.max(1));
+    thread::spawn(move || {
+        // A disc that couldn't be read isn't retried until it has been removed
+        let mut failed = false;
+        loop {
+            let medium = find_drive(Path::new("/sys")).filter(|drive| has_medium(Path::new("/sys"), drive));
+            match medium {
+                Some(drive) if DISC.read().is_none() && !failed => {
+                    let device = format!("/dev/{}", drive);
+                    match read_disc(&device) {
+                        Ok(disc) => {
+                            info!(
+                                "Audio CD inserted: {} tracks, disc ID {}{}",
+                                disc.tracks.len(),
+                                disc.disc_id,
+                                disc.album.as_ref().map(|a| format!(" ({})", a)).unwrap_or_default()
+                            );
+                            *DISC.write() = Some(disc);
+                        }
+                        Err(e) => {
+                            warn!("Failed to read disc in {}: {}", device, e);
+                            failed = true;
+                        }
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    if DISC.write().take().is_some() {
+                        info!("Audio CD removed");
+                    }
+                    failed = false;
+                }
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// Check whether the CD source is enabled
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+/// Get the CD drive, e.g. "/dev/sr0"
+pub fn get_drive() -> Option<String> {
+    find_drive(Path::new("/sys")).map(|drive| format!("/dev/{}", drive))
+}
+
+/// Get the inserted disc
+pub fn get_disc() -> Option<CdDisc> {
+    DISC.read().clone()
+}
+
+/// Check if a rip is running
+pub fn is_ripping() -> bool {
+    RIPPING.load(Ordering::SeqCst)
+}
+
+/// Get the report of the running or last rip
+pub fn get_rip_report() -> Option<RipReport> {
+    LAST_RIP.read().clone()
+}
+
+/// Find the CD drive in sysfs
+///
+/// The configured device is used if set. Otherwise USB drives are preferred over internal ones.
+///
+/// # Returns
+/// The device name, e.g. "sr0"
+fn find_drive(sys_root: &Path) -> Option<String> {
+    if let Some(device) = &CONFIG.read().device {
+        return Some(device.trim_start_matches("/dev/").to_string());
+    }
+
+    let mut drives: Vec<(bool, String)> = std::fs::read_dir(sys_root.join("block"))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("sr"))
+        .map(|name| {
+            let usb = std::fs::canonicalize(sys_root.join("block").join(&name))
+                .map(|p| p.to_string_lossy().contains("/usb"))
+                .unwrap_or(false);
+            (!usb, name)
+        })
+        .collect();
+    drives.sort();
+    drives.into_iter().next().map(|(_, name)| name)
+}
+
+/// Optical drives report a size of 0 without disc
+fn has_medium(sys_root: &Path, drive: &str) -> bool {
+    std::fs::read_to_string(sys_root.join("block").join(drive).join("size"))
+        .map(|size| size.trim() != "0")
+        .unwrap_or(false)
+}
+
+/// Read the TOC of the disc and look it up on MusicBrainz
+fn read_disc(device: &str) -> Result<CdDisc> {
+    let output = Command::new("cd-discid")
+        .arg("--musicbrainz")
+        .arg(device)
+        .output()
+        .map_err(|e| CdError::Command(format!("cd-discid: {}", e)))?;
+    if !output.status.success() {
+        // Data discs have no audio TOC
+        return Err(CdError::Command(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    let toc = DiscToc::from_cd_discid(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| CdError::Command("Unexpected cd-discid output".to_string()))?;
+    let disc_id = toc.disc_id();
+
+    let release = match musicbrainz::lookup_disc(&disc_id, Some(&toc.musicbrainz_toc())) {
+        Ok(release) => release,
+        Err(e) => {
+            info!("No MusicBrainz release found for disc ID {}: {}", disc_id, e);
+            None
+        }
+    };
+
+    Ok(CdDisc::new(device, &toc, release))
+}
+
+/// Find the first MPD player
+fn with_mpd<T>(f: impl FnOnce(&MPDPlayerController) -> T) -> Result<T> {
+    let controller = CONTROLLER
+        .read()
+        .as_ref()
+        .and_then(|c| c.upgrade())
+        .ok_or_else(|| CdError::NoPlayer("Audio controller not available".to_string()))?;
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            return Ok(f(mpd));
+        }
+    }
+    Err(CdError::NoPlayer("No MPD player configured".to_string()))
+}
+
+/// Replace the MPD queue with the tracks of the disc and start playback
+///
+/// MPD needs the cdio_paranoia input plugin to play CDs.
+///
+/// # Arguments
+/// * `track` - Track number to start with, the first track if not set
+pub fn play(track: Option<u32>) -> Result<()> {
+    let disc = get_disc().ok_or(CdError::NoDisc)?;
+    let start = match track {
+        Some(number) => disc
+            .tracks
+            .iter()
+            .position(|t| t.number == number)
+            .ok_or(CdError::InvalidTrack(number))?,
+        None => 0,
+    };
+    let uris: Vec<String> = disc.tracks.iter().map(|t| t.uri.clone()).collect();
+
+    info!("Playing CD from track {}", disc.tracks[start].number);
+    with_mpd(|mpd| mpd.replace_queue_and_play(&uris, start))?.map_err(CdError::Command)
+}
+
+/// Eject the disc
+pub fn eject() -> Result<()> {
+    let device = get_drive().ok_or(CdError::NoDrive)?;
+    let output = Command::new("eject")
+        .arg(&device)
+        .output()
+        .map_err(|e| CdError::Command(format!("eject: {}", e)))?;
+    if !output.status.success() {
+        return Err(CdError::Command(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    DISC.write().take();
+    Ok(())
+}
+
+/// Make a name usable as a file or directory name
+fn path_component(name: &str, fallback: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').trim().to_string();
+    if cleaned.is_empty() {
+        fallback.to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Directory of a disc relative to the MPD music directory
+fn rip_directory(rip_dir: &str, disc: &CdDisc) -> String {
+    format!(
+        "{}/{}/{}",
+        rip_dir.trim_matches('/'),
+        path_component(disc.artist.as_deref().unwrap_or(""), "Unknown Artist"),
+        path_component(disc.album.as_deref().unwrap_or(""), &format!("CD {}", disc.disc_id))
+    )
+}
+
+fn track_file_name(track: &CdTrack) -> String {
+    let title = path_component(track.title.as_deref().unwrap_or(""), &format!("Track {}", track.number));
+    format!("{:02} - {}.flac", track.number, title)
+}
+
+/// Start ripping the inserted disc to FLAC files in the MPD music directory
+///
+/// Tracks that already exist are skipped, so an interrupted rip can be resumed.
+/// Needs cdparanoia and flac.
+pub fn start_rip() -> Result<RipReport> {
+    let disc = get_disc().ok_or(CdError::NoDisc)?;
+    let (music_dir, library) = with_mpd(|mpd| (mpd.get_effective_music_directory(), mpd.get_library()))?;
+    let music_dir = music_dir.ok_or_else(|| CdError::NoPlayer("MPD music directory unknown".to_string()))?;
+
+    if RIPPING.swap(true, Ordering::SeqCst) {
+        return Err(CdError::AlreadyRunning);
+    }
+
+    let relative_dir = rip_directory(&CONFIG.read().rip_dir, &disc);
+    let report = RipReport {
+        disc_id: disc.disc_id.clone(),
+        directory: Path::new(&music_dir).join(&relative_dir).to_string_lossy().to_string(),
+        started_at: now_timestamp(),
+        total_tracks: disc.tracks.len(),
+        ..Default::default()
+    };
+    *LAST_RIP.write() = Some(report.clone());
+
+    thread::spawn(move || {
+        rip(&disc, Path::new(&music_dir), &relative_dir, library);
+        RIPPING.store(false, Ordering::SeqCst);
+    });
+    Ok(report)
+}
+
+fn update_report(f: impl FnOnce(&mut RipReport)) {
+    if let Some(report) = LAST_RIP.write().as_mut() {
+        f(report);
+    }
+}
+
+fn rip(disc: &CdDisc, music_dir: &Path, relative_dir: &str, library: Option<MPDLibrary>) {
+    if let Err(e) = crate::helpers::backgroundjobs::register_job(JOB_ID.to_string(), "CD Rip".to_string()) {
+        warn!("Failed to register CD rip background job: {}", e);
+    }
+
+    let directory = music_dir.join(relative_dir);
+    info!("Ripping CD {} to {}", disc.disc_id, directory.display());
+
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        warn!("Failed to create directory {}: {}", directory.display(), e);
+        update_report(|r| {
+            r.error = Some(e.to_string());
+            r.finished_at = Some(now_timestamp());
+        });
+        let _ = crate::helpers::backgroundjobs::update_job(JOB_ID, Some(format!("Failed: {}", e)), None, None);
+        let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+        return;
+    }
+
+    let total = disc.tracks.len();
+    for (index, track) in disc.tracks.iter().enumerate() {
+        let file_name = track_file_name(track);
+        let _ = crate::helpers::backgroundjobs::update_job(
+            JOB_ID,
+            Some(format!("Ripping track {}/{}: {}", track.number, total, file_name)),
+            Some(index),
+            Some(total),
+        );
+
+        let target = directory.join(&file_name);
+        if target.exists() {
+            debug!("{} already exists, skipping", target.display());
+            update_report(|r| r.skipped_tracks += 1);
+            continue;
+        }
+
+        match rip_track(disc, track, &target) {
+            Ok(()) => {
+                info!("Ripped track {} to {}", track.number, target.display());
+                update_report(|r| r.ripped_tracks += 1);
+            }
+            Err(e) => {
+                warn!("Failed to rip track {}: {}", track.number, e);
+                update_report(|r| {
+                    r.failed_tracks += 1;
+                    r.error = Some(e.to_string());
+                });
+            }
+        }
+    }
+
+    if let Some(library) = library {
+        library.update_database(Some(relative_dir));
+    }
+
+    let summary = match LAST_RIP.write().as_mut() {
+        Some(report) => {
+            report.finished_at = Some(now_timestamp());
+            format!(
+                "{} tracks ripped, {} skipped, {} failed",
+                report.ripped_tracks, report.skipped_tracks, report.failed_tracks
+            )
+        }
+        None => String::new(),
+    };
+    info!("CD rip complete: {}", summary);
+    let _ = crate::helpers::backgroundjobs::update_job(JOB_ID, Some(summary), Some(total), Some(total));
+    let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+}
+
+/// Read a track with cdparanoia and encode it with flac
+fn rip_track(disc: &CdDisc, track: &CdTrack, target: &Path) -> Result<()> {
+    let wav: PathBuf = target.with_file_name(format!(".track{:02}.wav", track.number));
+
+    let status = Command::new("cdparanoia")
+        .args(["-q", "-d", &disc.device, &track.number.to_string()])
+        .arg(&wav)
+        .status()
+        .map_err(|e| CdError::Command(format!("cdparanoia: {}", e)))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&wav);
+        return Err(CdError::Command(format!("cdparanoia failed with {}", status)));
+    }
+
+    let mut tags = vec![format!("TRACKNUMBER={}", track.number)];
+    let optional_tags = [
+        ("TITLE", track.title.as_ref()),
+        ("ARTIST", track.artist.as_ref()),
+        ("ALBUM", disc.album.as_ref()),
+        ("ALBUMARTIST", disc.artist.as_ref()),
+        ("DATE", disc.date.as_ref()),
+        ("MUSICBRAINZ_ALBUMID", disc.release_id.as_ref()),
+    ];
+    for (name, value) in optional_tags {
+        if let Some(value) = value {
+            tags.push(format!("{}={}", name, value));
+        }
+    }
+
+    let mut command = Command::new("flac");
+    command.args(["--silent", "--force", "-o"]).arg(target);
+    for tag in &tags {
+        command.arg("-T").arg(tag);
+    }
+    let status = command
+        .arg(&wav)
+        .status()
+        .map_err(|e| CdError::Command(format!("flac: {}", e)));
+    let _ = std::fs::remove_file(&wav);
+
+    match status? {
+        status if status.success() => Ok(()),
+        status => {
+            let _ = std::fs::remove_file(target);
+            Err(CdError::Command(format!("flac failed with {}", status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_toc() -> DiscToc {
+        DiscToc::from_cd_discid(
+            "22 150 9700 25887 39297 53795 63735 77517 94877 107270 123552 135522 148422 \
+             161197 174790 192022 205545 218010 228700 239590 250977 260605 285077 303602",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_disc_id() {
+        let disc_id = test_toc().disc_id();
+        assert_eq!(disc_id, "7nPdPaaCtwxL58mIgmB.HijWihI-");
+        // 20 byte SHA-1 in base64 without '+', '/' and '='
+        assert_eq!(disc_id.len(), 28);
+        assert!(!disc_id.contains(['+', '/', '=']));
+    }
+
+    #[test]
+    fn test_parse_toc() {
+        let toc = test_toc();
+        assert_eq!((toc.first_track, toc.last_track, toc.leadout), (1, 22, 303602));
+        assert_eq!(toc.track_duration(1), Some((9700.0 - 150.0) / 75.0));
+        assert_eq!(toc.track_duration(22), Some((303602.0 - 285077.0) / 75.0));
+        assert_eq!(toc.track_duration(23), None);
+        assert!(toc.musicbrainz_toc().starts_with("1 22 303602 150 9700"));
+
+        assert!(DiscToc::from_cd_discid("2 150 1000").is_none());
+        assert!(DiscToc::from_cd_discid("").is_none());
+    }
+
+    #[test]
+    fn test_rip_paths() {
+        let toc = DiscToc::from_cd_discid("2 150 1000 2000").unwrap();
+        let mut disc = CdDisc::new("/dev/sr0", &toc, None);
+        assert_eq!(disc.tracks[1].uri, "cdda:///dev/sr0/2");
+        assert_eq!(rip_directory("CD", &disc), format!("CD/Unknown Artist/CD {}", disc.disc_id));
+        assert_eq!(track_file_name(&disc.tracks[0]), "01 - Track 1.flac");
+
+        disc.artist = Some("AC/DC".to_string());
+        disc.album = Some("..Back in Black".to_string());
+        disc.tracks[0].title = Some("What?".to_string());
+        assert_eq!(rip_directory("/CD/", &disc), "CD/AC_DC/Back in Black");
+        assert_eq!(track_file_name(&disc.tracks[0]), "01 - What_.flac");
+    }
+}
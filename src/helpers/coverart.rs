@@ -372,4 +372,43 @@ static COVERART_MANAGER: Lazy<Arc<Mutex<CoverartManager>>> = Lazy::new(|| {
 /// Get a reference to the global coverart manager
 pub fn get_coverart_manager() -> Arc<Mutex<CoverartManager>> {
     COVERART_MANAGER.clone()
-}
\ No newline at end of file
+}
+
+/// Where a cached image came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtworkSource {
+    /// Provider name, e.g. "fanarttv", "mpd" or "custom"
+    pub provider: String,
+    /// Original URL if the image was downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl ArtworkSource {
+    pub fn new(provider: &str, url: Option<&str>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            url: url.map(|u| u.to_string()),
+        }
+    }
+}
+
+fn artwork_source_key(kind: &str, id: &str) -> String {
+    format!("artwork_source::{}::{}", kind, id)
+}
+
+/// Remember where the cached image of an artist or album came from
+///
+/// # Arguments
+/// * `kind` - "artist" or "album"
+/// * `id` - Artist name or album cache key
+pub fn store_artwork_source(kind: &str, id: &str, source: &ArtworkSource) {
+    if let Err(e) = crate::helpers::attributecache::set(&artwork_source_key(kind, id), source) {
+        debug!("Failed to store artwork source for {} '{}': {}", kind, id, e);
+    }
+}
+
+/// Get the source of the cached image of an artist or album
+pub fn get_artwork_source(kind: &str, id: &str) -> Option<ArtworkSource> {
+    crate::helpers::attributecache::get::<ArtworkSource>(&artwork_source_key(kind, id)).ok().flatten()
+}
@@ -0,0 +1,195 @@
+use crate::data::library::LibraryInterface;
+use crate::players::mpd::library::MPDLibrary;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const JOB_ID: &str = "cover_write_back";
+
+/// Result of writing back the cover of a single album
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverWriteBackStatus {
+    /// The cover has been written into the album directory
+    Written,
+    /// The album directory already contains a cover file, it has not been touched
+    Conflict,
+    /// No cover could be found for the album
+    NoCover,
+    /// The album directory could not be found on the local filesystem
+    NoDirectory,
+    /// The cover has an image format that can't be stored as cover.jpg/cover.png
+    Unsupported,
+    /// Writing the file failed
+    Failed,
+}
+
+/// Write-back result of a single album
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverWriteBackEntry {
+    pub album: String,
+    pub artist: String,
+    /// Full path of the album directory
+    pub directory: Option<String>,
+    pub status: CoverWriteBackStatus,
+    /// File that has been written or that caused the conflict, or the error
+    pub detail: Option<String>,
+}
+
+impl CoverWriteBackEntry {
+    pub fn new(album: &str, artist: &str, directory: Option<String>, status: CoverWriteBackStatus, detail: Option<String>) -> Self {
+        Self {
+            album: album.to_string(),
+            artist: artist.to_string(),
+            directory,
+            status,
+            detail,
+        }
+    }
+}
+
+/// Report of a write-back run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverWriteBackReport {
+    pub player_name: String,
+    pub started_at: u64,
+    /// Not set while the job is running
+    pub finished_at: Option<u64>,
+    pub total: usize,
+    pub written: usize,
+    pub conflicts: usize,
+    pub no_cover: usize,
+    pub no_directory: usize,
+    pub unsupported: usize,
+    pub failed: usize,
+    /// All albums that have been written, skipped because of a conflict or failed.
+    /// Albums without cover or directory are only counted.
+    pub entries: Vec<CoverWriteBackEntry>,
+}
+
+impl CoverWriteBackReport {
+    fn add(&mut self, entry: CoverWriteBackEntry) {
+        match entry.status {
+            CoverWriteBackStatus::Written => self.written += 1,
+            CoverWriteBackStatus::Conflict => self.conflicts += 1,
+            CoverWriteBackStatus::NoCover => self.no_cover += 1,
+            CoverWriteBackStatus::NoDirectory => self.no_directory += 1,
+            CoverWriteBackStatus::Unsupported => self.unsupported += 1,
+            CoverWriteBackStatus::Failed => self.failed += 1,
+        }
+        if !matches!(entry.status, CoverWriteBackStatus::NoCover | CoverWriteBackStatus::NoDirectory) {
+            self.entries.push(entry);
+        }
+    }
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static LAST_REPORT: Lazy<RwLock<Option<CoverWriteBackReport>>> = Lazy::new(|| RwLock::new(None));
+
+fn now_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Check if a write-back job is running
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Get the report of the running or last write-back job
+pub fn get_report() -> Option<CoverWriteBackReport> {
+    LAST_REPORT.read().clone()
+}
+
+/// Start writing album covers into the album directories of the whole library
+///
+/// Album directories that already contain a cover file are never modified.
+///
+/// # Returns
+/// `false` if a write-back job is already running
+pub fn start(player_name: &str, library: MPDLibrary) -> bool {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        debug!("Cover write-back already running");
+        return false;
+    }
+
+    let player_name = player_name.to_string();
+    thread::spawn(move || {
+        run(&player_name, &library);
+        RUNNING.store(false, Ordering::SeqCst);
+    });
+    true
+}
+
+fn run(player_name: &str, library: &MPDLibrary) {
+    if let Err(e) = crate::helpers::backgroundjobs::register_job(JOB_ID.to_string(), "Cover Write-Back".to_string()) {
+        warn!("Failed to register cover write-back background job: {}", e);
+    }
+
+    let albums = library.get_albums();
+    let total = albums.len();
+    info!("Writing back covers for {} albums", total);
+
+    *LAST_REPORT.write() = Some(CoverWriteBackReport {
+        player_name: player_name.to_string(),
+        started_at: now_timestamp(),
+        total,
+        ..Default::default()
+    });
+
+    for (index, album) in albums.iter().enumerate() {
+        let _ = crate::helpers::backgroundjobs::update_job(
+            JOB_ID,
+            Some(format!("Processing: {}", album.name)),
+            Some(index),
+            Some(total),
+        );
+
+        let entry = library.write_back_album_cover(album);
+        match entry.status {
+            CoverWriteBackStatus::Written => info!("Wrote cover for album '{}' to {:?}", album.name, entry.directory),
+            CoverWriteBackStatus::Failed => warn!("Failed to write cover for album '{}': {:?}", album.name, entry.detail),
+            _ => debug!("Cover write-back for album '{}': {:?}", album.name, entry.status),
+        }
+
+        if let Some(report) = LAST_REPORT.write().as_mut() {
+            report.add(entry);
+        }
+    }
+
+    let summary = match LAST_REPORT.write().as_mut() {
+        Some(report) => {
+            report.finished_at = Some(now_timestamp());
+            format!(
+                "{} covers written, {} conflicts skipped, {} failed",
+                report.written, report.conflicts, report.failed
+            )
+        }
+        None => String::new(),
+    };
+
+    info!("Cover write-back complete: {}", summary);
+    let _ = crate::helpers::backgroundjobs::update_job(JOB_ID, Some(summary), Some(total), Some(total));
+    let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_add() {
+        let mut report = CoverWriteBackReport::default();
+        report.add(CoverWriteBackEntry::new("A", "X", Some("/music/a".to_string()), CoverWriteBackStatus::Written, Some("cover.jpg".to_string())));
+        report.add(CoverWriteBackEntry::new("B", "X", Some("/music/b".to_string()), CoverWriteBackStatus::Conflict, Some("folder.jpg".to_string())));
+        report.add(CoverWriteBackEntry::new("C", "X", None, CoverWriteBackStatus::NoDirectory, None));
+        report.add(CoverWriteBackEntry::new("D", "X", Some("/music/d".to_string()), CoverWriteBackStatus::NoCover, None));
+
+        assert_eq!((report.written, report.conflicts, report.no_directory, report.no_cover), (1, 1, 1, 1));
+        // Albums without directory or cover are only counted
+        assert_eq!(report.entries.len(), 2);
+    }
+}
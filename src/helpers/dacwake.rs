@@ -0,0 +1,367 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{PlaybackState, PlayerCapability, PlayerCommand, PlayerEvent};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Configuration of the `dac_wake` service
+///
+/// Some DACs mute the first second after they wake up or the sample rate changes. The
+/// beginning of the track is then played again after `pregap_ms`, optionally with the
+/// output held by `hold_command` meanwhile. A keepalive stream of silence can prevent
+/// the DAC from going to sleep between tracks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DacWakeConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Time the DAC needs to unmute, playback is restarted from where it started after this
+    #[serde(default = "default_pregap_ms")]
+    pub pregap_ms: u64,
+
+    /// Restart when playback starts
+    #[serde(default = "default_true")]
+    pub on_start: bool,
+
+    /// Restart when the sample rate changes
+    #[serde(default = "default_true")]
+    pub on_rate_change: bool,
+
+    /// Only restart if playback started within this many seconds of the track's beginning
+    #[serde(default = "default_max_position_secs")]
+    pub max_position_secs: f64,
+
+    /// Run when the pre-gap starts, e.g. to mute a DSP, `{rate}` is replaced by the sample rate
+    #[serde(default)]
+    pub hold_command: Option<String>,
+
+    /// Run when the pre-gap ends
+    #[serde(default)]
+    pub release_command: Option<String>,
+
+    /// Plays silence while nothing plays, e.g.
+    /// `aplay -q -D default -t raw -f S16_LE -c 2 -r {rate} /dev/zero`
+    #[serde(default)]
+    pub keepalive_command: Option<String>,
+
+    /// Seconds the keepalive runs after playback stopped, 0 keeps it running
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u64,
+
+    /// Milliseconds between checks of the sample rate
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_pregap_ms() -> u64 {
+    1000
+}
+
+fn default_max_position_secs() -> f64 {
+    3.0
+}
+
+fn default_keepalive_secs() -> u64 {
+    300
+}
+
+fn default_poll_interval_ms() -> u64 {
+    250
+}
+
+impl Default for DacWakeConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            pregap_ms: default_pregap_ms(),
+            on_start: true,
+            on_rate_change: true,
+            max_position_secs: default_max_position_secs(),
+            hold_command: None,
+            release_command: None,
+            keepalive_command: None,
+            keepalive_secs: default_keepalive_secs(),
+            poll_interval_ms: default_poll_interval_ms(),
+        }
+    }
+}
+
+/// Sample rate used for the keepalive before any rate was detected
+const DEFAULT_RATE: u32 = 44100;
+
+/// Why the pre-gap is applied
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trigger {
+    Start,
+    RateChange,
+}
+
+/// Detects sample rate changes of the active player
+#[derive(Debug, Default)]
+struct RateTracker {
+    player: Option<String>,
+    rate: Option<u32>,
+}
+
+impl RateTracker {
+    /// Record the rate of the playing player, true if it changed
+    fn update(&mut self, player: &str, rate: Option<u32>) -> bool {
+        let Some(rate) = rate else {
+            return false;
+        };
+        let same_player = self.player.as_deref() == Some(player);
+        let changed = same_player && self.rate.is_some_and(|r| r != rate);
+        if !same_player {
+            self.player = Some(player.to_string());
+        }
+        self.rate = Some(rate);
+        changed
+    }
+}
+
+static CONFIG: Lazy<RwLock<DacWakeConfig>> = Lazy::new(|| RwLock::new(DacWakeConfig::default()));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+static RATES: Lazy<Mutex<RateTracker>> = Lazy::new(|| Mutex::new(RateTracker::default()));
+static KEEPALIVE: Lazy<Mutex<Option<Child>>> = Lazy::new(|| Mutex::new(None));
+
+/// Set while a pre-gap is running, further triggers are ignored
+static PREGAP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// End of the last pre-gap, players may report the seek as another start
+static LAST_PREGAP: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+/// Triggers this soon after a pre-gap are ignored
+const PREGAP_COOLDOWN: Duration = Duration::from_secs(2);
+
+fn get_controller() -> Option<std::sync::Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+/// Initialize DAC wake handling from the `dac_wake` service configuration
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let wake_config = match get_service_config(config, "dac_wake") {
+        Some(c) => serde_json::from_value::<DacWakeConfig>(c.clone()).unwrap_or_else(|e| {
+            warn!("Invalid dac_wake configuration, using defaults: {}", e);
+            DacWakeConfig::default()
+        }),
+        None => DacWakeConfig::default(),
+    };
+    if !wake_config.enable {
+        debug!("DAC wake handling is disabled");
+        return;
+    }
+    info!(
+        "DAC wake handling enabled, pre-gap {} ms{}",
+        wake_config.pregap_ms,
+        if wake_config.keepalive_command.is_some() { " with keepalive" } else { "" }
+    );
+    *CONFIG.write() = wake_config.clone();
+    *CONTROLLER.write() = Some(controller);
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::StateChanged]);
+    bus.spawn_worker(id, receiver, |event| {
+        if let PlayerEvent::StateChanged { state, .. } = &event {
+            handle_state(event.player_id(), *state);
+        }
+    });
+
+    let interval = Duration::from_millis(wake_config.poll_interval_ms.max(50));
+    thread::spawn(move || {
+        let mut stopped_since: Option<Instant> = Some(Instant::now());
+        loop {
+            check_active_player(&mut stopped_since);
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// Handle a state change of a player, only the active player is considered
+fn handle_state(player_id: Option<&str>, state: PlaybackState) {
+    let Some(controller) = get_controller() else {
+        return;
+    };
+    let Some(active) = controller.get_active_controller() else {
+        return;
+    };
+    if player_id.is_some_and(|id| id != active.read().get_player_id()) {
+        return;
+    }
+    if state == PlaybackState::Playing {
+        // The player needs the output, the keepalive must not hold it
+        stop_keepalive();
+        if CONFIG.read().on_start {
+            apply_pregap(Trigger::Start);
+        }
+    }
+}
+
+/// Detect sample rate changes and run the keepalive while nothing plays
+fn check_active_player(stopped_since: &mut Option<Instant>) {
+    let config = CONFIG.read().clone();
+    let active = get_controller().and_then(|c| c.get_active_controller());
+    let (playing, player_id, rate) = match &active {
+        Some(player) => {
+            let player = player.read();
+            (
+                player.get_playback_state() == PlaybackState::Playing,
+                player.get_player_id(),
+                player.get_stream_details().and_then(|d| d.sample_rate),
+            )
+        }
+        None => (false, String::new(), None),
+    };
+
+    if playing {
+        *stopped_since = None;
+        stop_keepalive();
+        let changed = RATES.lock().update(&player_id, rate);
+        if changed && config.on_rate_change {
+            apply_pregap(Trigger::RateChange);
+        }
+        return;
+    }
+
+    let since = *stopped_since.get_or_insert_with(Instant::now);
+    let Some(template) = &config.keepalive_command else {
+        return;
+    };
+    let expired = config.keepalive_secs > 0 && since.elapsed() >= Duration::from_secs(config.keepalive_secs);
+    if expired {
+        stop_keepalive();
+    } else {
+        let rate = RATES.lock().rate.unwrap_or(DEFAULT_RATE);
+        ensure_keepalive(template, rate);
+    }
+}
+
+/// Split a command template and replace `{rate}`, no shell is involved
+fn command_args(template: &str, rate: u32) -> Vec<String> {
+    template
+        .split_whitespace()
+        .map(|arg| arg.replace("{rate}", &rate.to_string()))
+        .collect()
+}
+
+fn spawn_command(template: &str, rate: u32) -> Option<Child> {
+    let args = command_args(template, rate);
+    let (program, args) = args.split_first()?;
+    match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => Some(child),
+        Err(e) => {
+            warn!("Failed to run {}: {}", program, e);
+            None
+        }
+    }
+}
+
+fn run_command(template: &str, rate: u32) {
+    if let Some(mut child) = spawn_command(template, rate) {
+        let _ = child.wait();
+    }
+}
+
+fn ensure_keepalive(template: &str, rate: u32) {
+    let mut keepalive = KEEPALIVE.lock();
+    if let Some(child) = keepalive.as_mut() {
+        match child.try_wait() {
+            Ok(None) => return,
+            // The output may still be busy, e.g. while the player is paused
+            _ => debug!("DAC keepalive exited, restarting it"),
+        }
+    }
+    *keepalive = spawn_command(template, rate);
+}
+
+fn stop_keepalive() {
+    if let Some(mut child) = KEEPALIVE.lock().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+        debug!("Stopped DAC keepalive");
+    }
+}
+
+/// Play the beginning again once the DAC is awake
+fn apply_pregap(trigger: Trigger) {
+    let config = CONFIG.read().clone();
+    let cooling_down = LAST_PREGAP.lock().is_some_and(|t| t.elapsed() < PREGAP_COOLDOWN);
+    if config.pregap_ms == 0 || cooling_down || PREGAP_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let Some(player) = get_controller().and_then(|c| c.get_active_controller()) else {
+        PREGAP_RUNNING.store(false, Ordering::SeqCst);
+        return;
+    };
+    let (position, can_seek, rate) = {
+        let player = player.read();
+        (
+            player.get_position().unwrap_or(0.0),
+            player.get_capabilities().has_capability(PlayerCapability::Seek),
+            player.get_stream_details().and_then(|d| d.sample_rate),
+        )
+    };
+    if !can_seek || position > config.max_position_secs {
+        PREGAP_RUNNING.store(false, Ordering::SeqCst);
+        return;
+    }
+
+    debug!("Applying {} ms pre-gap after {:?} at {:.1}s", config.pregap_ms, trigger, position);
+    let rate = rate.unwrap_or(DEFAULT_RATE);
+    thread::spawn(move || {
+        if let Some(command) = &config.hold_command {
+            run_command(command, rate);
+        }
+        thread::sleep(Duration::from_millis(config.pregap_ms));
+        if !player.read().send_command(PlayerCommand::Seek(position)) {
+            warn!("Failed to seek back after the DAC pre-gap");
+        }
+        if let Some(command) = &config.release_command {
+            run_command(command, rate);
+        }
+        *LAST_PREGAP.lock() = Some(Instant::now());
+        PREGAP_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_tracker() {
+        let mut tracker = RateTracker::default();
+        assert!(!tracker.update("mpd", Some(44100)));
+        assert!(!tracker.update("mpd", Some(44100)));
+        assert!(tracker.update("mpd", Some(96000)));
+        // Unknown rates and player switches don't count as changes
+        assert!(!tracker.update("mpd", None));
+        assert!(!tracker.update("spotify", Some(44100)));
+        assert_eq!(tracker.rate, Some(44100));
+    }
+
+    #[test]
+    fn test_command_args() {
+        assert_eq!(
+            command_args("aplay -q -t raw -f S16_LE -c 2 -r {rate} /dev/zero", 48000),
+            vec!["aplay", "-q", "-t", "raw", "-f", "S16_LE", "-c", "2", "-r", "48000", "/dev/zero"]
+        );
+    }
+}
@@ -0,0 +1,558 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::PlayerCommand;
+use crate::helpers::lastfm::LastfmClient;
+use crate::helpers::listening_history::{self, PlayedSong};
+use crate::helpers::settingsdb::normalize_favourite_name;
+use chrono::{Local, Timelike};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const MIXES_KEY: &str = "daily_mixes";
+
+/// How often the scheduler checks whether the mixes are due
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Tracks of one artist in a mix
+const MAX_TRACKS_PER_ARTIST: usize = 3;
+
+/// Number of most played artists used as seeds for Last.fm recommendations
+const LASTFM_SEED_ARTISTS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum DailyMixError {
+    #[error("Mix {0} not found")]
+    NotFound(String),
+
+    #[error("Player {0} not found")]
+    PlayerNotFound(String),
+
+    #[error("Library of player {0} is not loaded")]
+    LibraryNotLoaded(String),
+
+    #[error("Invalid mode: {0}")]
+    InvalidMode(String),
+
+    #[error("Player {0} did not accept the tracks")]
+    CommandFailed(String),
+}
+
+/// Configuration of the `daily_mix` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyMixConfig {
+    #[serde(default = "default_true")]
+    pub enable: bool,
+
+    /// Player whose library the mixes are built from and that plays them
+    #[serde(default = "default_player")]
+    pub player: String,
+
+    /// Number of mixes, the first one is built from favourites and most played artists,
+    /// the others from the genres played most
+    #[serde(default = "default_mix_count")]
+    pub mix_count: usize,
+
+    #[serde(default = "default_tracks_per_mix")]
+    pub tracks_per_mix: usize,
+
+    /// Plays of this many days are taken into account
+    #[serde(default = "default_history_days")]
+    pub history_days: u64,
+
+    /// Local hour after which the mixes of a new day are generated
+    #[serde(default = "default_regenerate_hour")]
+    pub regenerate_hour: u32,
+
+    /// Add library artists similar to the most played ones, needs Last.fm
+    #[serde(default = "default_true")]
+    pub lastfm_seed: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_player() -> String {
+    "mpd".to_string()
+}
+
+fn default_mix_count() -> usize {
+    4
+}
+
+fn default_tracks_per_mix() -> usize {
+    30
+}
+
+fn default_history_days() -> u64 {
+    30
+}
+
+fn default_regenerate_hour() -> u32 {
+    4
+}
+
+impl Default for DailyMixConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            player: default_player(),
+            mix_count: default_mix_count(),
+            tracks_per_mix: default_tracks_per_mix(),
+            history_days: default_history_days(),
+            regenerate_hour: default_regenerate_hour(),
+            lastfm_seed: true,
+        }
+    }
+}
+
+/// A library track in a mix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixTrack {
+    pub uri: String,
+    pub title: String,
+    pub artist: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+}
+
+/// A generated playlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyMix {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub player_name: String,
+    /// Unix timestamp of the generation
+    pub generated: u64,
+    pub tracks: Vec<MixTrack>,
+}
+
+/// A mix without its tracks
+#[derive(Debug, Clone, Serialize)]
+pub struct MixSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub player_name: String,
+    pub generated: u64,
+    pub track_count: usize,
+}
+
+impl From<&DailyMix> for MixSummary {
+    fn from(mix: &DailyMix) -> Self {
+        Self {
+            id: mix.id.clone(),
+            name: mix.name.clone(),
+            description: mix.description.clone(),
+            player_name: mix.player_name.clone(),
+            generated: mix.generated,
+            track_count: mix.tracks.len(),
+        }
+    }
+}
+
+/// A library track that can be picked for a mix
+#[derive(Debug, Clone)]
+struct Candidate {
+    track: MixTrack,
+    genre: Option<String>,
+    favourite: bool,
+}
+
+static CONFIG: Lazy<RwLock<DailyMixConfig>> = Lazy::new(|| RwLock::new(DailyMixConfig::default()));
+static MIXES: Lazy<RwLock<Option<Vec<DailyMix>>>> = Lazy::new(|| RwLock::new(None));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+/// Unix timestamp of the last generation, also if it produced no mixes
+static LAST_RUN: Lazy<RwLock<Option<u64>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize daily mixes from the `daily_mix` service configuration
+///
+/// If enabled, a background thread generates the mixes once a day after
+/// `regenerate_hour` and as soon as the library has loaded if there are none yet.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    if let Some(c) = get_service_config(config, "daily_mix") {
+        match serde_json::from_value::<DailyMixConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid daily_mix configuration, using defaults: {}", e),
+        }
+    }
+
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        debug!("Daily mixes are disabled");
+        return;
+    }
+    info!("Generating {} daily mixes from the library of {}", config.mix_count, config.player);
+
+    thread::spawn(|| loop {
+        if is_due() {
+            if let Err(e) = generate() {
+                debug!("Daily mixes not generated: {}", e);
+            }
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+/// Summaries of the current mixes
+pub fn list_mixes() -> Vec<MixSummary> {
+    with_mixes(|mixes| mixes.iter().map(MixSummary::from).collect())
+}
+
+pub fn get_mix(id: &str) -> Option<DailyMix> {
+    with_mixes(|mixes| mixes.iter().find(|m| m.id == id).cloned())
+}
+
+/// Generate new mixes now
+pub fn generate() -> Result<Vec<MixSummary>, DailyMixError> {
+    let config = CONFIG.read().clone();
+    let controller = get_controller().ok_or_else(|| DailyMixError::PlayerNotFound(config.player.clone()))?;
+    let candidates = collect_candidates(&controller, &config.player)?;
+
+    let since = now().saturating_sub(config.history_days * 24 * 3600);
+    let plays = listening_history::get_plays_since(since);
+    let similar = if config.lastfm_seed { similar_artists(&plays) } else { HashSet::new() };
+
+    let mut rng = StdRng::seed_from_u64(now());
+    let mixes = build_mixes(&candidates, &plays, &similar, &config, &mut rng);
+    *LAST_RUN.write() = Some(now());
+    info!("Generated {} daily mixes from {} library tracks", mixes.len(), candidates.len());
+
+    if let Err(e) = crate::helpers::settingsdb::set(MIXES_KEY, &mixes) {
+        warn!("Failed to store daily mixes: {}", e);
+    }
+    let summaries = mixes.iter().map(MixSummary::from).collect();
+    *MIXES.write() = Some(mixes);
+    Ok(summaries)
+}
+
+/// Queue the tracks of a mix on the player it was built for
+///
+/// # Arguments
+/// * `mode` - "add" (end of the queue), "next" (after the current track) or "now" (replace the queue and play)
+pub fn queue_mix(controller: &AudioController, id: &str, mode: &str) -> Result<usize, DailyMixError> {
+    let mix = get_mix(id).ok_or_else(|| DailyMixError::NotFound(id.to_string()))?;
+    let player = controller
+        .get_player_by_name(&mix.player_name)
+        .ok_or_else(|| DailyMixError::PlayerNotFound(mix.player_name.clone()))?;
+
+    let uris: Vec<String> = mix.tracks.iter().map(|t| t.uri.clone()).collect();
+    let count = uris.len();
+    let command = match mode {
+        "add" => PlayerCommand::QueueTracks { uris, insert_at_beginning: false, insert_after_current: false, metadata: Vec::new() },
+        "next" => PlayerCommand::QueueTracks { uris, insert_at_beginning: false, insert_after_current: true, metadata: Vec::new() },
+        "now" => PlayerCommand::PlayNow { uris, metadata: Vec::new(), start_index: 0 },
+        mode => return Err(DailyMixError::InvalidMode(mode.to_string())),
+    };
+
+    info!("Queueing {} tracks of mix '{}' on {}", count, mix.name, mix.player_name);
+    if player.read().send_command(command) {
+        Ok(count)
+    } else {
+        Err(DailyMixError::CommandFailed(mix.player_name))
+    }
+}
+
+fn with_mixes<T>(f: impl FnOnce(&[DailyMix]) -> T) -> T {
+    if MIXES.read().is_none() {
+        let stored = crate::helpers::settingsdb::get::<Vec<DailyMix>>(MIXES_KEY).ok().flatten().unwrap_or_default();
+        MIXES.write().get_or_insert(stored);
+    }
+    f(MIXES.read().as_deref().unwrap_or_default())
+}
+
+/// Mixes are due if there are none or the last ones are from before today's regeneration hour
+fn is_due() -> bool {
+    let generated = with_mixes(|mixes| mixes.iter().map(|m| m.generated).max()).max(*LAST_RUN.read());
+    let Some(generated) = generated else {
+        return true;
+    };
+    let now = Local::now();
+    let regenerate_hour = CONFIG.read().regenerate_hour.min(23);
+    let Some(due_at) = now.date_naive().and_hms_opt(regenerate_hour, 0, 0).and_then(|t| t.and_local_timezone(Local).earliest()) else {
+        return false;
+    };
+    now.hour() >= regenerate_hour && (generated as i64) < due_at.timestamp()
+}
+
+fn get_controller() -> Option<std::sync::Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Collect the library tracks of a player that have a URI
+fn collect_candidates(controller: &AudioController, player_name: &str) -> Result<Vec<Candidate>, DailyMixError> {
+    let player = controller
+        .get_player_by_name(player_name)
+        .ok_or_else(|| DailyMixError::PlayerNotFound(player_name.to_string()))?;
+    let player = player.read();
+    let library = player
+        .get_library()
+        .filter(|l| l.is_loaded())
+        .ok_or_else(|| DailyMixError::LibraryNotLoaded(player_name.to_string()))?;
+
+    let favourites: HashSet<(String, String)> = crate::helpers::settingsdb::get_all_favourite_songs()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(artist, title)| (normalize_favourite_name(&artist), normalize_favourite_name(&title)))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for album in library.get_albums() {
+        let album_artist = album.artists.lock().first().cloned().unwrap_or_default();
+        let genre = album.genres.first().cloned();
+        for track in album.tracks.lock().iter() {
+            let Some(uri) = &track.uri else {
+                continue;
+            };
+            let artist = track.artist.clone().unwrap_or_else(|| album_artist.clone());
+            let favourite = favourites.contains(&(normalize_favourite_name(&artist), normalize_favourite_name(&track.name)));
+            candidates.push(Candidate {
+                track: MixTrack {
+                    uri: uri.clone(),
+                    title: track.name.clone(),
+                    artist,
+                    album: Some(album.name.clone()),
+                    duration: track.duration,
+                },
+                genre: genre.clone(),
+                favourite,
+            });
+        }
+    }
+    Ok(candidates)
+}
+
+/// Artists similar to the most played ones from Last.fm, names in lower case
+fn similar_artists(plays: &[PlayedSong]) -> HashSet<String> {
+    let Ok(client) = LastfmClient::get_instance() else {
+        return HashSet::new();
+    };
+    let mut counts: Vec<(String, usize)> = listening_history::artist_play_counts(plays).into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut similar = HashSet::new();
+    for (artist, _) in counts.into_iter().take(LASTFM_SEED_ARTISTS) {
+        match client.get_artist_info(&artist) {
+            Ok(info) => similar.extend(info.similar.map(|s| s.artists).unwrap_or_default().into_iter().map(|a| a.name.to_lowercase())),
+            Err(e) => debug!("No similar artists for {}: {}", artist, e),
+        }
+    }
+    similar
+}
+
+/// Build the mixes from the library tracks
+///
+/// The first mix combines favourites, the most played artists and similar artists, the
+/// others the genres with the most plays and favourites. Recently played songs are skipped.
+fn build_mixes(
+    candidates: &[Candidate],
+    plays: &[PlayedSong],
+    similar: &HashSet<String>,
+    config: &DailyMixConfig,
+    rng: &mut StdRng,
+) -> Vec<DailyMix> {
+    let artist_weights: HashMap<String, f64> = listening_history::artist_play_counts(plays)
+        .into_iter()
+        .map(|(artist, count)| (artist, (1.0 + count as f64).ln()))
+        .collect();
+    let recent_cutoff = plays.last().map(|p| p.timestamp.saturating_sub(24 * 3600)).unwrap_or(0);
+    let recent: HashSet<(String, String)> = plays
+        .iter()
+        .filter(|p| p.timestamp >= recent_cutoff)
+        .map(|p| (p.artist.to_lowercase(), p.title.to_lowercase()))
+        .collect();
+    let fresh: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|c| !recent.contains(&(c.track.artist.to_lowercase(), c.track.title.to_lowercase())))
+        .collect();
+
+    let score = |c: &Candidate| {
+        let artist = c.track.artist.to_lowercase();
+        artist_weights.get(&artist).copied().unwrap_or(0.0)
+            + if c.favourite { 2.0 } else { 0.0 }
+            + if similar.contains(&artist) { 1.0 } else { 0.0 }
+    };
+
+    let generated = now();
+    let mut mixes = Vec::new();
+    if config.mix_count == 0 {
+        return mixes;
+    }
+
+    let personal: Vec<&Candidate> = fresh
+        .iter()
+        .copied()
+        .filter(|c| c.favourite || artist_weights.contains_key(&c.track.artist.to_lowercase()) || similar.contains(&c.track.artist.to_lowercase()))
+        .collect();
+    let tracks = pick_tracks(&personal, config.tracks_per_mix, score, rng);
+    if !tracks.is_empty() {
+        mixes.push(DailyMix {
+            id: "daily".to_string(),
+            name: "Daily Mix".to_string(),
+            description: "Favourites, artists you played recently and similar artists".to_string(),
+            player_name: config.player.clone(),
+            generated,
+            tracks,
+        });
+    }
+
+    let mut genres: HashMap<&str, (f64, usize)> = HashMap::new();
+    for c in &fresh {
+        if let Some(genre) = &c.genre {
+            let entry = genres.entry(genre.as_str()).or_insert((0.0, 0));
+            entry.0 += artist_weights.get(&c.track.artist.to_lowercase()).copied().unwrap_or(0.0) + if c.favourite { 1.0 } else { 0.0 };
+            entry.1 += 1;
+        }
+    }
+    // Genres with too few tracks for a mix are skipped, without history the largest genres are used
+    let min_tracks = (config.tracks_per_mix / 2).max(1);
+    let mut genres: Vec<(&str, f64, usize)> = genres
+        .into_iter()
+        .filter(|(_, (_, count))| *count >= min_tracks)
+        .map(|(genre, (weight, count))| (genre, weight, count))
+        .collect();
+    genres.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.2.cmp(&a.2)).then_with(|| a.0.cmp(b.0)));
+
+    for (genre, _, _) in genres.into_iter().take(config.mix_count - mixes.len()) {
+        let in_genre: Vec<&Candidate> = fresh.iter().copied().filter(|c| c.genre.as_deref() == Some(genre)).collect();
+        let tracks = pick_tracks(&in_genre, config.tracks_per_mix, score, rng);
+        mixes.push(DailyMix {
+            id: format!("genre-{}", genre.to_lowercase().replace(|c: char| !c.is_alphanumeric(), "-")),
+            name: format!("{} Mix", genre),
+            description: format!("{} from your library", genre),
+            player_name: config.player.clone(),
+            generated,
+            tracks,
+        });
+    }
+    mixes
+}
+
+/// Pick the best scoring tracks with a limit per artist and shuffle them
+///
+/// A random value below 1 is added to the scores, so mixes differ from day to day.
+fn pick_tracks(
+    candidates: &[&Candidate],
+    count: usize,
+    score: impl Fn(&Candidate) -> f64,
+    rng: &mut StdRng,
+) -> Vec<MixTrack> {
+    let mut scored: Vec<(f64, &Candidate)> = candidates.iter().map(|c| (score(c) + rng.gen_range(0.0..1.0), *c)).collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut per_artist: HashMap<String, usize> = HashMap::new();
+    let mut tracks = Vec::new();
+    for (_, candidate) in scored {
+        if tracks.len() >= count {
+            break;
+        }
+        let artist_count = per_artist.entry(candidate.track.artist.to_lowercase()).or_insert(0);
+        if *artist_count >= MAX_TRACKS_PER_ARTIST {
+            continue;
+        }
+        *artist_count += 1;
+        tracks.push(candidate.track.clone());
+    }
+    tracks.shuffle(rng);
+    tracks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(artist: &str, title: &str, genre: &str, favourite: bool) -> Candidate {
+        Candidate {
+            track: MixTrack {
+                uri: format!("{}/{}.flac", artist, title),
+                title: title.to_string(),
+                artist: artist.to_string(),
+                album: None,
+                duration: None,
+            },
+            genre: Some(genre.to_string()),
+            favourite,
+        }
+    }
+
+    fn play(artist: &str, title: &str, timestamp: u64) -> PlayedSong {
+        PlayedSong { artist: artist.to_string(), title: title.to_string(), album: None, timestamp }
+    }
+
+    #[test]
+    fn test_build_mixes() {
+        let mut candidates = Vec::new();
+        for i in 0..5 {
+            candidates.push(candidate("Played", &format!("P{}", i), "Jazz", false));
+            candidates.push(candidate("Other", &format!("O{}", i), "Rock", false));
+            candidates.push(candidate("Similar", &format!("S{}", i), "Rock", false));
+        }
+        candidates.push(candidate("Loved", "Favourite", "Pop", true));
+        let plays = vec![play("Played", "Old", 1000), play("Played", "P0", 200_000)];
+        let similar: HashSet<String> = ["similar".to_string()].into_iter().collect();
+        let config = DailyMixConfig { mix_count: 3, tracks_per_mix: 6, ..Default::default() };
+
+        let mixes = build_mixes(&candidates, &plays, &similar, &config, &mut StdRng::seed_from_u64(1));
+        assert_eq!(mixes.len(), 3);
+
+        let daily = &mixes[0];
+        assert_eq!(daily.id, "daily");
+        assert_eq!(daily.tracks.len(), 6);
+        assert!(daily.tracks.iter().all(|t| t.artist != "Other"));
+        assert!(daily.tracks.iter().any(|t| t.title == "Favourite"));
+        // Recently played, max. 3 tracks per artist
+        assert!(daily.tracks.iter().all(|t| t.title != "P0"));
+        assert!(daily.tracks.iter().filter(|t| t.artist == "Played").count() <= MAX_TRACKS_PER_ARTIST);
+
+        // Jazz has the plays, Pop has too few tracks
+        assert_eq!(mixes[1].id, "genre-jazz");
+        assert_eq!(mixes[2].id, "genre-rock");
+        assert_eq!(mixes[2].tracks.len(), 6);
+    }
+
+    #[test]
+    fn test_build_mixes_from_history() {
+        let store = listening_history::seeded_store(&[
+            ("Played", "P0", 1000),
+            ("Played", "P1", 2000),
+            ("Other", "O0", 3000),
+            ("Played", "P2", 200_000),
+        ]);
+        let plays = listening_history::with_test_store(store, || listening_history::get_plays_since(1500));
+        assert_eq!(plays.len(), 3);
+
+        let mut candidates = Vec::new();
+        for i in 0..5 {
+            candidates.push(candidate("Played", &format!("P{}", i), "Jazz", false));
+            candidates.push(candidate("Unplayed", &format!("U{}", i), "Rock", false));
+        }
+        let config = DailyMixConfig { mix_count: 2, tracks_per_mix: 4, ..Default::default() };
+        let mixes = build_mixes(&candidates, &plays, &HashSet::new(), &config, &mut StdRng::seed_from_u64(1));
+
+        let daily = &mixes[0];
+        assert_eq!(daily.id, "daily");
+        assert!(!daily.tracks.is_empty());
+        // Only the artists of the history, without the song played last
+        assert!(daily.tracks.iter().all(|t| t.artist == "Played" && t.title != "P2"));
+        assert_eq!(mixes[1].id, "genre-jazz");
+    }
+}
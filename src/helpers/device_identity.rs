@@ -0,0 +1,127 @@
+use crate::config::get_service_config;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Settings database key of the generated device ID
+const DEVICE_ID_KEY: &str = "device_id";
+
+/// Settings database key of the friendly name set with the API
+const DEVICE_NAME_KEY: &str = "device_name";
+
+/// Longest accepted friendly name
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Configuration of the `device` service
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Friendly name, the host name if not set. A name set with the API takes precedence.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Stable identity of this instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    /// Generated on the first start and kept in the settings database
+    pub id: String,
+    /// Friendly name shown to users, e.g. "Living Room"
+    pub name: String,
+}
+
+static CONFIG: Lazy<RwLock<DeviceConfig>> = Lazy::new(|| RwLock::new(DeviceConfig::default()));
+static IDENTITY: Lazy<RwLock<Option<DeviceIdentity>>> = Lazy::new(|| RwLock::new(None));
+
+/// Load or create the device identity
+///
+/// Needs the settings database, so it is initialized after it.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(c) = get_service_config(config, "device") {
+        match serde_json::from_value::<DeviceConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid device configuration, using defaults: {}", e),
+        }
+    }
+    let identity = load();
+    info!("Device identity: {} ({})", identity.name, identity.id);
+    *IDENTITY.write() = Some(identity);
+}
+
+/// The identity of this instance
+pub fn identity() -> DeviceIdentity {
+    if let Some(identity) = IDENTITY.read().clone() {
+        return identity;
+    }
+    IDENTITY.write().get_or_insert_with(load).clone()
+}
+
+/// Set the friendly name, an empty name switches back to the configured name
+pub fn set_name(name: &str) -> Result<DeviceIdentity, String> {
+    let name = name.trim();
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("The name is longer than {} characters", MAX_NAME_LENGTH));
+    }
+    if name.is_empty() {
+        crate::helpers::settingsdb::remove(DEVICE_NAME_KEY)?;
+    } else {
+        crate::helpers::settingsdb::set_string(DEVICE_NAME_KEY, name)?;
+    }
+    let identity = load();
+    info!("Device name set to {}", identity.name);
+    *IDENTITY.write() = Some(identity.clone());
+    Ok(identity)
+}
+
+fn load() -> DeviceIdentity {
+    let id = match crate::helpers::settingsdb::get_string(DEVICE_ID_KEY) {
+        Ok(Some(id)) if !id.is_empty() => id,
+        _ => {
+            let id = generate_id();
+            if let Err(e) = crate::helpers::settingsdb::set_string(DEVICE_ID_KEY, &id) {
+                warn!("Failed to store the device ID, it changes with the next start: {}", e);
+            }
+            id
+        }
+    };
+    let stored = crate::helpers::settingsdb::get_string(DEVICE_NAME_KEY).ok().flatten();
+    let name = choose_name(stored, CONFIG.read().name.clone(), &hostname());
+    DeviceIdentity { id, name }
+}
+
+/// Random 128 bit ID in hex
+fn generate_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// The name set with the API, the configured name or the host name
+fn choose_name(stored: Option<String>, configured: Option<String>, hostname: &str) -> String {
+    [stored, configured]
+        .into_iter()
+        .flatten()
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| if hostname.is_empty() { "audiocontrol".to_string() } else { hostname.to_string() })
+}
+
+pub(crate) fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname").unwrap_or_default().trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_name() {
+        let name = |s: &str| Some(s.to_string());
+        assert_eq!(choose_name(name("Kitchen"), name("Living Room"), "hifiberry"), "Kitchen");
+        assert_eq!(choose_name(name(" "), name("Living Room"), "hifiberry"), "Living Room");
+        assert_eq!(choose_name(None, None, "hifiberry"), "hifiberry");
+        assert_eq!(choose_name(None, None, ""), "audiocontrol");
+        assert_eq!(generate_id().len(), 32);
+    }
+}
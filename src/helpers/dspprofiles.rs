@@ -0,0 +1,486 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::config::get_service_config;
+use crate::AudioController;
+use chrono::{Local, NaiveTime};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Errors of DSP profile switching
+#[derive(Debug, Error)]
+pub enum DspError {
+    #[error("Unknown DSP profile '{0}'")]
+    UnknownProfile(String),
+
+    #[error("DSP profile switching is disabled")]
+    Disabled,
+
+    #[error("Failed to switch DSP profile: {0}")]
+    Backend(String),
+}
+
+pub type Result<T> = std::result::Result<T, DspError>;
+
+/// How profiles are activated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DspBackend {
+    /// CamillaDSP websocket API, profiles are paths of CamillaDSP configuration files
+    Camilladsp {
+        #[serde(default = "default_camilladsp_url")]
+        url: String,
+    },
+    /// Run a command with `{profile}` replaced by the profile, e.g.
+    /// `dsptoolkit install-profile {profile}` for HiFiBerry DSP boards
+    Command { command: String },
+}
+
+fn default_camilladsp_url() -> String {
+    "ws://127.0.0.1:1234".to_string()
+}
+
+impl Default for DspBackend {
+    fn default() -> Self {
+        DspBackend::Camilladsp {
+            url: default_camilladsp_url(),
+        }
+    }
+}
+
+/// A rule selecting a profile, all given conditions must match
+///
+/// E.g. `{"genre": "jazz", "profile": "warm"}`, `{"player": "spotify", "profile": "loudness"}` or
+/// `{"from": "22:00", "to": "07:00", "profile": "night"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DspRule {
+    /// Genre of the playing song, compared case-insensitively
+    #[serde(default)]
+    pub genre: Option<String>,
+
+    /// Name or id of the active player
+    #[serde(default)]
+    pub player: Option<String>,
+
+    /// Start of the time window, e.g. "22:00"
+    #[serde(default)]
+    pub from: Option<String>,
+
+    /// End of the time window, e.g. "07:00", windows can span midnight
+    #[serde(default)]
+    pub to: Option<String>,
+
+    pub profile: String,
+}
+
+fn default_override_minutes() -> u64 {
+    120
+}
+
+fn default_check_interval_secs() -> u64 {
+    60
+}
+
+/// Configuration of the `dsp_profiles` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DspProfilesConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    #[serde(default)]
+    pub backend: DspBackend,
+
+    /// Profile names and their backend values, e.g. "night": "/etc/camilladsp/night.yml"
+    #[serde(default)]
+    pub profiles: BTreeMap<String, String>,
+
+    /// Profile used if no rule matches
+    #[serde(default)]
+    pub default_profile: Option<String>,
+
+    /// Rules in order, the first matching rule decides
+    #[serde(default)]
+    pub rules: Vec<DspRule>,
+
+    /// Minutes a profile selected via the API stays active
+    #[serde(default = "default_override_minutes")]
+    pub override_minutes: u64,
+
+    /// Seconds between checks of the time rules
+    #[serde(default = "default_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+impl Default for DspProfilesConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            backend: DspBackend::default(),
+            profiles: BTreeMap::new(),
+            default_profile: None,
+            rules: Vec::new(),
+            override_minutes: default_override_minutes(),
+            check_interval_secs: default_check_interval_secs(),
+        }
+    }
+}
+
+impl DspProfilesConfig {
+    /// Check that rules only use configured profiles and valid times
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let profiles = self.rules.iter().map(|r| &r.profile).chain(self.default_profile.iter());
+        for profile in profiles {
+            if !self.profiles.contains_key(profile) {
+                return Err(format!("profile '{}' is not configured", profile));
+            }
+        }
+        for time in self.rules.iter().flat_map(|r| r.from.iter().chain(r.to.iter())) {
+            if parse_time(time).is_none() {
+                return Err(format!("invalid time '{}', use HH:MM", time));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// What the rules are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct DspContext {
+    /// Name and id of the active player
+    pub player: Option<(String, String)>,
+    /// Genres of the playing song
+    pub genres: Vec<String>,
+    pub time: NaiveTime,
+}
+
+fn in_window(from: Option<&str>, to: Option<&str>, time: NaiveTime) -> bool {
+    let from = from.and_then(parse_time).unwrap_or(NaiveTime::MIN);
+    match to.and_then(parse_time) {
+        Some(to) if from <= to => from <= time && time < to,
+        // The window spans midnight
+        Some(to) => time >= from || time < to,
+        None => time >= from,
+    }
+}
+
+fn rule_matches(rule: &DspRule, context: &DspContext) -> bool {
+    let genre_ok = rule
+        .genre
+        .as_ref()
+        .is_none_or(|genre| context.genres.iter().any(|g| g.eq_ignore_ascii_case(genre)));
+    let player_ok = rule.player.as_ref().is_none_or(|player| {
+        context
+            .player
+            .as_ref()
+            .is_some_and(|(name, id)| player.eq_ignore_ascii_case(name) || player.eq_ignore_ascii_case(id))
+    });
+    let time_ok = (rule.from.is_none() && rule.to.is_none())
+        || in_window(rule.from.as_deref(), rule.to.as_deref(), context.time);
+    genre_ok && player_ok && time_ok
+}
+
+/// Select the profile for a context, returns the profile and the reason
+pub fn decide(config: &DspProfilesConfig, context: &DspContext) -> Option<(String, String)> {
+    match config.rules.iter().enumerate().find(|(_, rule)| rule_matches(rule, context)) {
+        Some((idx, rule)) => Some((rule.profile.clone(), format!("rule {}", idx + 1))),
+        None => config
+            .default_profile
+            .clone()
+            .map(|profile| (profile, "no rule matches".to_string())),
+    }
+}
+
+/// State of the profile switching, returned by the API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DspStatus {
+    pub enable: bool,
+    /// Configured profile names
+    pub profiles: Vec<String>,
+    /// Profile that was activated last
+    pub active_profile: Option<String>,
+    /// Why the active profile was selected, e.g. "rule 2" or "manual"
+    pub reason: Option<String>,
+    /// Profile selected via the API
+    pub override_profile: Option<String>,
+    /// Seconds until the rules apply again
+    pub override_remaining_secs: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct DspState {
+    active: Option<String>,
+    reason: Option<String>,
+    manual: Option<(String, Instant)>,
+    last_error: Option<String>,
+}
+
+impl DspState {
+    fn manual_profile(&self) -> Option<&(String, Instant)> {
+        self.manual.as_ref().filter(|(_, until)| *until > Instant::now())
+    }
+}
+
+static CONFIG: Lazy<RwLock<DspProfilesConfig>> = Lazy::new(|| RwLock::new(DspProfilesConfig::default()));
+static STATE: Lazy<Mutex<DspState>> = Lazy::new(|| Mutex::new(DspState::default()));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize DSP profile switching from the `dsp_profiles` service configuration
+///
+/// If enabled, profiles are switched when the song or the active player changes and
+/// every `check_interval_secs` for time based rules.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let dsp_config = match get_service_config(config, "dsp_profiles") {
+        Some(c) => match serde_json::from_value::<DspProfilesConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid dsp_profiles configuration, DSP profile switching disabled: {}", e);
+                return;
+            }
+        },
+        None => DspProfilesConfig::default(),
+    };
+    if let Err(e) = dsp_config.validate() {
+        warn!("Invalid dsp_profiles configuration, DSP profile switching disabled: {}", e);
+        return;
+    }
+    *CONFIG.write() = dsp_config.clone();
+
+    if !dsp_config.enable {
+        debug!("DSP profile switching is disabled");
+        return;
+    }
+    info!("Switching DSP profiles with {} rules", dsp_config.rules.len());
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::SongChanged, EventSubscription::ActivePlayerChanged]);
+    bus.spawn_worker(id, receiver, |_| evaluate());
+
+    let interval = Duration::from_secs(dsp_config.check_interval_secs.max(1));
+    thread::spawn(move || loop {
+        evaluate();
+        thread::sleep(interval);
+    });
+}
+
+/// Build the context from the active player and its song
+fn current_context() -> DspContext {
+    let mut context = DspContext {
+        time: Local::now().time(),
+        ..Default::default()
+    };
+    let Some(controller) = CONTROLLER.read().as_ref().and_then(|c| c.upgrade()) else {
+        return context;
+    };
+    if let Some(active) = controller.get_active_controller() {
+        let player = active.read();
+        context.player = Some((player.get_player_name(), player.get_player_id()));
+        if let Some(song) = player.get_song() {
+            context.genres = song.genre.into_iter().chain(song.genres).collect();
+        }
+    }
+    context
+}
+
+/// Activate the profile the rules or the manual override select, if it isn't active yet
+pub fn evaluate() {
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        return;
+    }
+    let mut state = STATE.lock();
+    let selected = match state.manual_profile() {
+        Some((profile, _)) => Some((profile.clone(), "manual".to_string())),
+        None => decide(&config, &current_context()),
+    };
+    let Some((profile, reason)) = selected else {
+        return;
+    };
+    if state.active.as_ref() == Some(&profile) {
+        state.reason = Some(reason);
+        return;
+    }
+    activate(&config, &mut state, &profile, reason);
+}
+
+fn activate(config: &DspProfilesConfig, state: &mut DspState, profile: &str, reason: String) {
+    let Some(value) = config.profiles.get(profile) else {
+        return;
+    };
+    match apply_profile(&config.backend, value) {
+        Ok(()) => {
+            info!("Activated DSP profile '{}' ({})", profile, reason);
+            state.active = Some(profile.to_string());
+            state.reason = Some(reason);
+            state.last_error = None;
+        }
+        Err(e) => {
+            // Only log repeated failures once
+            if state.last_error.as_deref() != Some(&e.to_string()) {
+                warn!("{}", e);
+            }
+            state.last_error = Some(e.to_string());
+        }
+    }
+}
+
+/// Send a request to the CamillaDSP websocket and check its result
+fn camilladsp_request(
+    socket: &mut tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>,
+    command: &str,
+    request: serde_json::Value,
+) -> Result<()> {
+    socket
+        .send(tungstenite::Message::Text(request.to_string()))
+        .map_err(|e| DspError::Backend(e.to_string()))?;
+    let reply = socket.read().map_err(|e| DspError::Backend(e.to_string()))?;
+    let reply: serde_json::Value = serde_json::from_str(reply.to_text().unwrap_or_default())
+        .map_err(|e| DspError::Backend(format!("invalid CamillaDSP reply: {}", e)))?;
+    match reply[command]["result"].as_str() {
+        Some("Ok") => Ok(()),
+        other => Err(DspError::Backend(format!("CamillaDSP {} failed: {}", command, other.unwrap_or("no result")))),
+    }
+}
+
+/// Activate a profile with the backend
+fn apply_profile(backend: &DspBackend, value: &str) -> Result<()> {
+    match backend {
+        DspBackend::Camilladsp { url } => {
+            let (mut socket, _) = tungstenite::connect(url.as_str())
+                .map_err(|e| DspError::Backend(format!("CamillaDSP at {}: {}", url, e)))?;
+            camilladsp_request(&mut socket, "SetConfigFilePath", serde_json::json!({ "SetConfigFilePath": value }))?;
+            camilladsp_request(&mut socket, "Reload", serde_json::json!("Reload"))?;
+            let _ = socket.close(None);
+            Ok(())
+        }
+        DspBackend::Command { command } => {
+            // Split before replacing, so profile values can't add arguments
+            let args: Vec<String> = command
+                .split_whitespace()
+                .map(|arg| arg.replace("{profile}", value))
+                .collect();
+            let (program, args) = args.split_first().ok_or_else(|| DspError::Backend("empty command".to_string()))?;
+            let output = std::process::Command::new(program)
+                .args(args)
+                .output()
+                .map_err(|e| DspError::Backend(format!("{}: {}", program, e)))?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(DspError::Backend(format!(
+                    "{} exited with {}: {}",
+                    program,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )))
+            }
+        }
+    }
+}
+
+/// Activate a profile manually, the rules apply again after `minutes` or `override_minutes`
+pub fn set_override(profile: &str, minutes: Option<u64>) -> Result<DspStatus> {
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        return Err(DspError::Disabled);
+    }
+    if !config.profiles.contains_key(profile) {
+        return Err(DspError::UnknownProfile(profile.to_string()));
+    }
+    {
+        let mut state = STATE.lock();
+        let minutes = minutes.unwrap_or(config.override_minutes);
+        state.manual = Some((profile.to_string(), Instant::now() + Duration::from_secs(minutes * 60)));
+        activate(&config, &mut state, profile, "manual".to_string());
+        if let Some(error) = state.last_error.clone().filter(|_| state.active.as_deref() != Some(profile)) {
+            state.manual = None;
+            return Err(DspError::Backend(error));
+        }
+    }
+    Ok(status())
+}
+
+/// Remove the manual override and apply the rules again
+pub fn clear_override() -> DspStatus {
+    STATE.lock().manual = None;
+    evaluate();
+    status()
+}
+
+pub fn status() -> DspStatus {
+    let config = CONFIG.read();
+    let state = STATE.lock();
+    let manual = state.manual_profile();
+    DspStatus {
+        enable: config.enable,
+        profiles: config.profiles.keys().cloned().collect(),
+        active_profile: state.active.clone(),
+        reason: state.reason.clone(),
+        override_profile: manual.map(|(profile, _)| profile.clone()),
+        override_remaining_secs: manual.map(|(_, until)| until.saturating_duration_since(Instant::now()).as_secs()),
+        last_error: state.last_error.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DspProfilesConfig {
+        serde_json::from_value(serde_json::json!({
+            "enable": true,
+            "profiles": {"night": "night.yml", "warm": "warm.yml", "flat": "flat.yml"},
+            "default_profile": "flat",
+            "rules": [
+                {"from": "22:00", "to": "07:00", "profile": "night"},
+                {"genre": "jazz", "player": "mpd", "profile": "warm"},
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn context(genre: &str, time: &str) -> DspContext {
+        DspContext {
+            player: Some(("mpd".to_string(), "localhost:6600".to_string())),
+            genres: vec![genre.to_string()],
+            time: parse_time(time).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let config = config();
+        assert!(config.validate().is_ok());
+        assert_eq!(decide(&config, &context("Jazz", "23:30")).unwrap().0, "night");
+        assert_eq!(decide(&config, &context("Jazz", "06:59")).unwrap().0, "night");
+        assert_eq!(decide(&config, &context("Jazz", "12:00")), Some(("warm".to_string(), "rule 2".to_string())));
+        assert_eq!(decide(&config, &context("Rock", "12:00")), Some(("flat".to_string(), "no rule matches".to_string())));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = config();
+        config.rules.push(DspRule {
+            genre: None,
+            player: Some("spotify".to_string()),
+            from: None,
+            to: None,
+            profile: "loud".to_string(),
+        });
+        assert_eq!(config.validate().unwrap_err(), "profile 'loud' is not configured");
+        config.rules.pop();
+        config.rules[0].from = Some("10pm".to_string());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::data::PlayerSource;
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Song-level metadata enrichment of a player
+///
+/// Sources that don't play music, e.g. the TV audio on a Bluetooth input, can turn
+/// these off to avoid pointless lookups and scrobbles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnrichmentSettings {
+    /// Look up artist and album artwork of the playing song first
+    #[serde(default = "default_true")]
+    pub artwork: bool,
+
+    /// Check for lyrics of the playing song
+    #[serde(default = "default_true")]
+    pub lyrics: bool,
+
+    /// Clean up the genres of the playing song
+    #[serde(default = "default_true")]
+    pub genre_cleanup: bool,
+
+    /// Send the songs to scrobbling services
+    #[serde(default = "default_true")]
+    pub scrobble: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for EnrichmentSettings {
+    fn default() -> Self {
+        Self::all(true)
+    }
+}
+
+impl EnrichmentSettings {
+    pub fn all(enabled: bool) -> Self {
+        Self {
+            artwork: enabled,
+            lyrics: enabled,
+            genre_cleanup: enabled,
+            scrobble: enabled,
+        }
+    }
+
+    /// Parse the `enrichment` option of a player configuration
+    ///
+    /// Accepts `false`/`true` to switch everything off or on, or an object with
+    /// the single settings. Returns None if the option is not set.
+    pub fn from_player_config(config: &serde_json::Value) -> Result<Option<Self>, String> {
+        match config.get("enrichment") {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::Bool(enabled)) => Ok(Some(Self::all(*enabled))),
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|e| format!("Invalid enrichment configuration: {}", e)),
+        }
+    }
+}
+
+/// Settings by player name or player id
+static SETTINGS: Lazy<RwLock<HashMap<String, EnrichmentSettings>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Set the enrichment settings of a player
+///
+/// The settings are stored for the player name and the player id, players without
+/// settings use the defaults.
+pub fn set_player_settings(player_name: &str, player_id: &str, settings: EnrichmentSettings) {
+    debug!("Metadata enrichment for {} ({}): {:?}", player_name, player_id, settings);
+    let mut all = SETTINGS.write();
+    all.insert(player_name.to_string(), settings);
+    all.insert(player_id.to_string(), settings);
+}
+
+/// Enrichment settings of the player that sent an event
+pub fn settings_for(source: &PlayerSource) -> EnrichmentSettings {
+    settings_for_player(source.player_name(), source.player_id())
+}
+
+pub fn settings_for_player(player_name: &str, player_id: &str) -> EnrichmentSettings {
+    let all = SETTINGS.read();
+    all.get(player_id)
+        .or_else(|| all.get(player_name))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_player_config() {
+        assert_eq!(EnrichmentSettings::from_player_config(&json!({})).unwrap(), None);
+        assert_eq!(
+            EnrichmentSettings::from_player_config(&json!({"enrichment": false})).unwrap(),
+            Some(EnrichmentSettings::all(false))
+        );
+        let settings = EnrichmentSettings::from_player_config(&json!({"enrichment": {"scrobble": false}}))
+            .unwrap()
+            .unwrap();
+        assert!(settings.artwork && settings.lyrics && settings.genre_cleanup);
+        assert!(!settings.scrobble);
+        assert!(EnrichmentSettings::from_player_config(&json!({"enrichment": "no"})).is_err());
+    }
+}
@@ -72,11 +72,7 @@ pub fn initialize_from_config(config: &serde_json::Value) {
         }
         
         // Register rate limit - default to 2 requests per second (500ms)
-        let rate_limit_ms = fanarttv_config.get("rate_limit_ms")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(500);
-            
-        ratelimit::register_service("fanarttv", rate_limit_ms);
+        let rate_limit_ms = ratelimit::register_from_config("fanarttv", fanarttv_config, 500);
         info!("FanArt.tv rate limit set to {} ms", rate_limit_ms);
         
         let status = if enabled { "enabled" } else { "disabled" };
@@ -167,6 +163,12 @@ pub fn get_artist_thumbnails(artist_mbid: &str, max_images: Option<usize>) -> Ve
 
     let mut thumbnail_urls = Vec::new();
     
+    // Apply rate limiting before making the request
+    if !ratelimit::acquire("fanarttv") {
+        debug!("Daily FanArt.tv request quota reached");
+        return Vec::new();
+    }
+
     let client = http_client();
     match client.get_text(&url) {
         Ok(response_text) => {
@@ -251,6 +253,12 @@ pub fn get_artist_banners(artist_mbid: &str) -> Vec<String> {
 
     let mut banner_urls = Vec::new();
     
+    // Apply rate limiting before making the request
+    if !ratelimit::acquire("fanarttv") {
+        debug!("Daily FanArt.tv request quota reached");
+        return Vec::new();
+    }
+
     let client = http_client();
     match client.get_text(&url) {
         Ok(response_text) => {
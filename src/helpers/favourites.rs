@@ -1,8 +1,12 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::data::song::Song;
 use parking_lot::Mutex;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
 // Global favourite manager instance
 static GLOBAL_FAVOURITE_MANAGER: Lazy<Mutex<FavouriteManager>> = Lazy::new(|| Mutex::new(FavouriteManager::new()));
@@ -20,6 +24,10 @@ pub enum FavouriteError {
     NotConfigured(String),
     /// Invalid song data (missing artist or title)
     InvalidSong(String),
+    /// Invalid album or artist (missing name)
+    InvalidItem(String),
+    /// The provider doesn't support the operation
+    Unsupported(String),
     /// Generic error
     Other(String),
 }
@@ -32,6 +40,8 @@ impl fmt::Display for FavouriteError {
             FavouriteError::AuthError(msg) => write!(f, "Authentication error: {}", msg),
             FavouriteError::NotConfigured(msg) => write!(f, "Not configured: {}", msg),
             FavouriteError::InvalidSong(msg) => write!(f, "Invalid song: {}", msg),
+            FavouriteError::InvalidItem(msg) => write!(f, "Invalid favourite: {}", msg),
+            FavouriteError::Unsupported(msg) => write!(f, "Not supported: {}", msg),
             FavouriteError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
@@ -39,6 +49,103 @@ impl fmt::Display for FavouriteError {
 
 impl Error for FavouriteError {}
 
+/// How long checking the liked state of a song takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LookupLatency {
+    /// Local storage
+    #[default]
+    Local,
+    /// A request to an online service
+    Network,
+}
+
+/// What a favourite provider supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+pub struct ProviderCapabilities {
+    /// Favourites can only be read, adding and removing is done elsewhere
+    pub read_only: bool,
+    /// The provider can import its favourites into the local favourites
+    pub sync: bool,
+    pub lookup_latency: LookupLatency,
+    /// Albums and artists can be favourites, not only songs
+    pub albums_and_artists: bool,
+}
+
+/// Kind of a favourite album or artist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FavouriteKind {
+    Album,
+    Artist,
+}
+
+/// A favourite album or artist
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FavouriteItem {
+    Album { artist: String, name: String },
+    Artist { name: String },
+}
+
+impl FavouriteItem {
+    pub fn album(artist: &str, name: &str) -> Self {
+        FavouriteItem::Album { artist: artist.to_string(), name: name.to_string() }
+    }
+
+    pub fn artist(name: &str) -> Self {
+        FavouriteItem::Artist { name: name.to_string() }
+    }
+
+    pub fn kind(&self) -> FavouriteKind {
+        match self {
+            FavouriteItem::Album { .. } => FavouriteKind::Album,
+            FavouriteItem::Artist { .. } => FavouriteKind::Artist,
+        }
+    }
+
+    /// The item with trimmed, lowercase names, favourites are compared in this form
+    pub fn normalized(&self) -> Self {
+        let normalize = |s: &str| s.trim().to_lowercase();
+        match self {
+            FavouriteItem::Album { artist, name } => FavouriteItem::Album { artist: normalize(artist), name: normalize(name) },
+            FavouriteItem::Artist { name } => FavouriteItem::Artist { name: normalize(name) },
+        }
+    }
+
+    fn validate(&self) -> Result<(), FavouriteError> {
+        match self {
+            FavouriteItem::Album { artist, .. } if artist.trim().is_empty() => {
+                Err(FavouriteError::InvalidItem("Album artist cannot be empty".to_string()))
+            }
+            FavouriteItem::Album { name, .. } | FavouriteItem::Artist { name } if name.trim().is_empty() => {
+                Err(FavouriteError::InvalidItem("Name cannot be empty".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for FavouriteItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FavouriteItem::Album { artist, name } => write!(f, "album '{}' by '{}'", name, artist),
+            FavouriteItem::Artist { name } => write!(f, "artist '{}'", name),
+        }
+    }
+}
+
+/// Result of the last sync of a provider
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncStatus {
+    pub running: bool,
+    /// Unix timestamp of the last finished sync
+    pub last_sync: Option<u64>,
+    /// Number of favourites imported by the last sync
+    pub last_count: Option<usize>,
+    pub last_error: Option<String>,
+}
+
 /// Trait for services that can manage favourite songs
 pub trait FavouriteProvider {
     /// Check if a song is marked as favourite
@@ -86,8 +193,44 @@ pub trait FavouriteProvider {
     /// Check if this provider is currently active (e.g., user logged in for remote providers)
     /// This is different from is_enabled - a provider can be enabled but not active
     fn is_active(&self) -> bool;
+
+    /// What this provider supports
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    /// Import the favourites of this provider into the local favourites
+    ///
+    /// Only called for providers with the `sync` capability. Returns the number of imported favourites.
+    fn sync(&self) -> Result<usize, FavouriteError> {
+        Err(FavouriteError::Unsupported(format!("{} can't sync favourites", self.provider_name())))
+    }
+
+    /// Check if an album or artist is marked as favourite
+    ///
+    /// Only called for providers with the `albums_and_artists` capability.
+    fn is_favourite_item(&self, _item: &FavouriteItem) -> Result<bool, FavouriteError> {
+        Err(FavouriteError::Unsupported(format!("{} has no favourite albums and artists", self.provider_name())))
+    }
+
+    /// Add an album or artist to favourites
+    fn add_favourite_item(&self, _item: &FavouriteItem) -> Result<(), FavouriteError> {
+        Err(FavouriteError::Unsupported(format!("{} has no favourite albums and artists", self.provider_name())))
+    }
+
+    /// Remove an album or artist from favourites
+    fn remove_favourite_item(&self, _item: &FavouriteItem) -> Result<(), FavouriteError> {
+        Err(FavouriteError::Unsupported(format!("{} has no favourite albums and artists", self.provider_name())))
+    }
+
+    /// Get all favourite albums or artists
+    fn get_favourite_items(&self, _kind: FavouriteKind) -> Result<Vec<FavouriteItem>, FavouriteError> {
+        Err(FavouriteError::Unsupported(format!("{} has no favourite albums and artists", self.provider_name())))
+    }
 }
 
+type SharedProvider = Arc<dyn FavouriteProvider + Send + Sync>;
+
 /// Validate that a song has both artist and title
 fn validate_song(song: &Song) -> Result<(), FavouriteError> {
     let artist = song.artist.as_ref()
@@ -107,7 +250,8 @@ fn validate_song(song: &Song) -> Result<(), FavouriteError> {
 
 /// Multi-provider favourite manager
 pub struct FavouriteManager {
-    providers: Vec<Box<dyn FavouriteProvider + Send + Sync>>,
+    providers: Vec<SharedProvider>,
+    sync_status: HashMap<String, SyncStatus>,
 }
 
 impl FavouriteManager {
@@ -115,20 +259,44 @@ impl FavouriteManager {
     pub fn new() -> Self {
         Self {
             providers: Vec::new(),
+            sync_status: HashMap::new(),
         }
     }
 
     /// Add a provider to the manager
     pub fn add_provider(&mut self, provider: Box<dyn FavouriteProvider + Send + Sync>) {
-        self.providers.push(provider);
+        self.register_provider(Arc::from(provider));
+    }
+
+    /// Register a provider, replacing a registered provider with the same name
+    pub fn register_provider(&mut self, provider: SharedProvider) {
+        let name = provider.provider_name();
+        match self.providers.iter().position(|p| p.provider_name() == name) {
+            Some(index) => self.providers[index] = provider,
+            None => self.providers.push(provider),
+        }
+    }
+
+    /// Remove a provider, returns false if no provider with this name is registered
+    pub fn unregister_provider(&mut self, name: &str) -> bool {
+        let count = self.providers.len();
+        self.providers.retain(|p| p.provider_name() != name);
+        self.sync_status.remove(name);
+        self.providers.len() != count
+    }
+
+    pub fn get_provider(&self, name: &str) -> Option<SharedProvider> {
+        self.providers.iter().find(|p| p.provider_name() == name).cloned()
     }
 
     /// Check if a song is favourite in any of the providers
-    /// Returns true if the song is favourite in at least one provider
+    /// Returns true if the song is favourite in at least one provider, local providers are asked first
     pub fn is_favourite(&self, song: &Song) -> Result<bool, FavouriteError> {
         validate_song(song)?;
 
-        for provider in &self.providers {
+        let mut providers: Vec<&SharedProvider> = self.providers.iter().collect();
+        providers.sort_by_key(|p| p.capabilities().lookup_latency);
+        for provider in providers {
             if !provider.is_enabled() {
                 continue;
             }
@@ -185,7 +353,7 @@ impl FavouriteManager {
         let mut successful_providers = Vec::new();
 
         for provider in &self.providers {
-            if !provider.is_enabled() {
+            if !provider.is_enabled() || provider.capabilities().read_only {
                 continue;
             }
 
@@ -221,7 +389,7 @@ impl FavouriteManager {
         let mut successful_providers = Vec::new();
 
         for provider in &self.providers {
-            if !provider.is_enabled() {
+            if !provider.is_enabled() || provider.capabilities().read_only {
                 continue;
             }
 
@@ -248,6 +416,100 @@ impl FavouriteManager {
         Ok(successful_providers)
     }
 
+    /// Enabled providers that support favourite albums and artists
+    fn item_providers(&self) -> impl Iterator<Item = &SharedProvider> {
+        self.providers.iter().filter(|p| p.is_enabled() && p.capabilities().albums_and_artists)
+    }
+
+    /// Check which providers have an album or artist marked as favourite (with display names)
+    pub fn get_favourite_item_providers(&self, item: &FavouriteItem) -> Result<(bool, Vec<String>), FavouriteError> {
+        item.validate()?;
+
+        let mut display_names = Vec::new();
+        for provider in self.item_providers() {
+            match provider.is_favourite_item(item) {
+                Ok(true) => display_names.push(provider.display_name().to_string()),
+                Ok(false) => continue,
+                Err(e) => log::warn!("Error checking favourite {} in provider {}: {}", item, provider.provider_name(), e),
+            }
+        }
+        Ok((!display_names.is_empty(), display_names))
+    }
+
+    /// Add an album or artist as favourite in all enabled providers that support it
+    /// Returns a list of providers that were successfully updated
+    pub fn add_favourite_item(&self, item: &FavouriteItem) -> Result<Vec<String>, FavouriteError> {
+        item.validate()?;
+        self.update_item_providers(item, "add", |provider| provider.add_favourite_item(item))
+    }
+
+    /// Remove an album or artist from favourites in all enabled providers that support it
+    /// Returns a list of providers that were successfully updated
+    pub fn remove_favourite_item(&self, item: &FavouriteItem) -> Result<Vec<String>, FavouriteError> {
+        item.validate()?;
+        self.update_item_providers(item, "remove", |provider| provider.remove_favourite_item(item))
+    }
+
+    fn update_item_providers(
+        &self,
+        item: &FavouriteItem,
+        operation: &str,
+        update: impl Fn(&SharedProvider) -> Result<(), FavouriteError>,
+    ) -> Result<Vec<String>, FavouriteError> {
+        let mut errors = Vec::new();
+        let mut successful_providers = Vec::new();
+        for provider in self.item_providers().filter(|p| !p.capabilities().read_only) {
+            match update(provider) {
+                Ok(()) => {
+                    log::info!("Successfully updated favourite {} in {}", item, provider.provider_name());
+                    successful_providers.push(provider.provider_name().to_string());
+                }
+                Err(e) => {
+                    log::error!("Failed to {} favourite {} in provider {}: {}", operation, item, provider.provider_name(), e);
+                    errors.push(format!("{}: {}", provider.provider_name(), e));
+                }
+            }
+        }
+
+        if successful_providers.is_empty() {
+            return Err(if errors.is_empty() {
+                FavouriteError::NotConfigured("No provider supports favourite albums and artists".to_string())
+            } else {
+                FavouriteError::Other(format!("Failed to {} favourite in all providers: {}", operation, errors.join(", ")))
+            });
+        }
+        Ok(successful_providers)
+    }
+
+    /// Get the favourite albums or artists of all enabled providers, without duplicates
+    pub fn get_favourite_items(&self, kind: FavouriteKind) -> Vec<FavouriteItem> {
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+        for provider in self.item_providers() {
+            match provider.get_favourite_items(kind) {
+                Ok(provider_items) => {
+                    items.extend(provider_items.into_iter().filter(|item| seen.insert(item.normalized())));
+                }
+                Err(e) => log::warn!("Error listing favourites of provider {}: {}", provider.provider_name(), e),
+            }
+        }
+        items
+    }
+
+    /// Normalized favourite albums or artists of the local providers
+    ///
+    /// Used to flag library items, providers that need network requests are skipped.
+    pub fn favourite_item_set(&self, kind: FavouriteKind) -> HashSet<FavouriteItem> {
+        let mut set = HashSet::new();
+        for provider in self.item_providers().filter(|p| p.capabilities().lookup_latency == LookupLatency::Local) {
+            match provider.get_favourite_items(kind) {
+                Ok(items) => set.extend(items.iter().map(FavouriteItem::normalized)),
+                Err(e) => log::warn!("Error listing favourites of provider {}: {}", provider.provider_name(), e),
+            }
+        }
+        set
+    }
+
     /// Get list of enabled providers
     pub fn get_enabled_providers(&self) -> Vec<&str> {
         self.providers
@@ -277,7 +539,9 @@ impl FavouriteManager {
                     "display_name": provider.display_name(),
                     "enabled": provider.is_enabled(),
                     "active": provider.is_active(),
-                    "favourite_count": provider.get_favourite_count()
+                    "favourite_count": provider.get_favourite_count(),
+                    "capabilities": provider.capabilities(),
+                    "sync_status": self.sync_status.get(provider.provider_name()).cloned().unwrap_or_default()
                 })
             })
             .collect()
@@ -336,6 +600,31 @@ pub fn remove_favourite(song: &Song) -> Result<Vec<String>, FavouriteError> {
     get_favourite_manager().remove_favourite(song)
 }
 
+/// Get which providers have an album or artist marked as favourite using the global manager
+pub fn get_favourite_item_providers(item: &FavouriteItem) -> Result<(bool, Vec<String>), FavouriteError> {
+    get_favourite_manager().get_favourite_item_providers(item)
+}
+
+/// Add an album or artist to favourites using the global manager
+pub fn add_favourite_item(item: &FavouriteItem) -> Result<Vec<String>, FavouriteError> {
+    get_favourite_manager().add_favourite_item(item)
+}
+
+/// Remove an album or artist from favourites using the global manager
+pub fn remove_favourite_item(item: &FavouriteItem) -> Result<Vec<String>, FavouriteError> {
+    get_favourite_manager().remove_favourite_item(item)
+}
+
+/// Get the favourite albums or artists using the global manager
+pub fn get_favourite_items(kind: FavouriteKind) -> Vec<FavouriteItem> {
+    get_favourite_manager().get_favourite_items(kind)
+}
+
+/// Get the normalized favourite albums or artists of local providers using the global manager
+pub fn favourite_item_set(kind: FavouriteKind) -> HashSet<FavouriteItem> {
+    get_favourite_manager().favourite_item_set(kind)
+}
+
 /// Get enabled providers from the global manager
 pub fn get_enabled_providers() -> Vec<String> {
     get_favourite_manager().get_enabled_providers().into_iter().map(|s| s.to_string()).collect()
@@ -351,3 +640,160 @@ pub fn get_provider_count() -> (usize, usize) {
 pub fn get_provider_details() -> Vec<serde_json::Value> {
     get_favourite_manager().get_provider_details()
 }
+
+/// Register a provider with the global manager at runtime, e.g. from a plugin
+///
+/// A provider with the same name is replaced.
+pub fn register_provider(provider: Arc<dyn FavouriteProvider + Send + Sync>) {
+    log::info!("Registering favourite provider {}", provider.provider_name());
+    get_favourite_manager().register_provider(provider);
+}
+
+/// Remove a provider from the global manager
+pub fn unregister_provider(name: &str) -> bool {
+    get_favourite_manager().unregister_provider(name)
+}
+
+/// Start importing the favourites of a provider in the background
+///
+/// Fails if the provider doesn't exist, can't sync or a sync is already running.
+pub fn sync_provider(name: &str) -> Result<(), FavouriteError> {
+    let provider = {
+        let mut manager = get_favourite_manager();
+        let provider = manager.get_provider(name)
+            .ok_or_else(|| FavouriteError::NotConfigured(format!("No favourite provider {}", name)))?;
+        if !provider.capabilities().sync {
+            return Err(FavouriteError::Unsupported(format!("{} can't sync favourites", name)));
+        }
+        if !provider.is_active() {
+            return Err(FavouriteError::NotConfigured(format!("{} is not active", name)));
+        }
+        let status = manager.sync_status.entry(name.to_string()).or_default();
+        if status.running {
+            return Err(FavouriteError::Other(format!("{} is already syncing", name)));
+        }
+        status.running = true;
+        provider
+    };
+
+    let name = name.to_string();
+    std::thread::spawn(move || {
+        log::info!("Syncing favourites from {}", name);
+        let result = provider.sync();
+        let mut manager = get_favourite_manager();
+        let status = manager.sync_status.entry(name.clone()).or_default();
+        status.running = false;
+        status.last_sync = SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+        match result {
+            Ok(count) => {
+                log::info!("Synced {} favourites from {}", count, name);
+                status.last_count = Some(count);
+                status.last_error = None;
+            }
+            Err(e) => {
+                log::warn!("Syncing favourites from {} failed: {}", name, e);
+                status.last_error = Some(e.to_string());
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestProvider {
+        name: &'static str,
+        read_only: bool,
+        favourite: bool,
+        items: Mutex<Vec<FavouriteItem>>,
+    }
+
+    fn provider(name: &'static str, read_only: bool, favourite: bool) -> Arc<TestProvider> {
+        Arc::new(TestProvider { name, read_only, favourite, items: Mutex::new(Vec::new()) })
+    }
+
+    impl FavouriteProvider for TestProvider {
+        fn is_favourite(&self, _song: &Song) -> Result<bool, FavouriteError> {
+            Ok(self.favourite)
+        }
+        fn add_favourite(&self, _song: &Song) -> Result<(), FavouriteError> {
+            Ok(())
+        }
+        fn remove_favourite(&self, _song: &Song) -> Result<(), FavouriteError> {
+            Ok(())
+        }
+        fn get_favourite_count(&self) -> Option<usize> {
+            None
+        }
+        fn provider_name(&self) -> &'static str {
+            self.name
+        }
+        fn display_name(&self) -> &'static str {
+            self.name
+        }
+        fn is_enabled(&self) -> bool {
+            true
+        }
+        fn is_active(&self) -> bool {
+            true
+        }
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities { read_only: self.read_only, albums_and_artists: true, ..Default::default() }
+        }
+        fn is_favourite_item(&self, item: &FavouriteItem) -> Result<bool, FavouriteError> {
+            Ok(self.items.lock().contains(item))
+        }
+        fn add_favourite_item(&self, item: &FavouriteItem) -> Result<(), FavouriteError> {
+            self.items.lock().push(item.clone());
+            Ok(())
+        }
+        fn remove_favourite_item(&self, item: &FavouriteItem) -> Result<(), FavouriteError> {
+            self.items.lock().retain(|i| i != item);
+            Ok(())
+        }
+        fn get_favourite_items(&self, kind: FavouriteKind) -> Result<Vec<FavouriteItem>, FavouriteError> {
+            Ok(self.items.lock().iter().filter(|i| i.kind() == kind).cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_register_provider() {
+        let song = Song { artist: Some("Artist".to_string()), title: Some("Title".to_string()), ..Default::default() };
+        let mut manager = FavouriteManager::new();
+        manager.register_provider(provider("local", false, false));
+        manager.register_provider(provider("remote", true, false));
+        assert_eq!(manager.add_favourite(&song).unwrap(), vec!["local"]);
+        assert!(!manager.is_favourite(&song).unwrap());
+
+        // Registering the same name replaces the provider
+        manager.register_provider(provider("remote", true, true));
+        assert_eq!(manager.provider_count(), 2);
+        assert!(manager.is_favourite(&song).unwrap());
+
+        assert!(manager.unregister_provider("remote"));
+        assert!(!manager.unregister_provider("remote"));
+        assert!(!manager.is_favourite(&song).unwrap());
+    }
+
+    #[test]
+    fn test_favourite_items() {
+        let mut manager = FavouriteManager::new();
+        manager.register_provider(provider("local", false, false));
+        manager.register_provider(provider("remote", true, false));
+
+        let album = FavouriteItem::album("Miles Davis", "Kind of Blue");
+        assert_eq!(manager.add_favourite_item(&album).unwrap(), vec!["local"]);
+        manager.add_favourite_item(&FavouriteItem::artist("Miles Davis")).unwrap();
+        assert!(manager.get_favourite_item_providers(&album).unwrap().0);
+        assert_eq!(manager.get_favourite_items(FavouriteKind::Album), vec![album.clone()]);
+        assert!(manager
+            .favourite_item_set(FavouriteKind::Artist)
+            .contains(&FavouriteItem::artist(" MILES DAVIS").normalized()));
+
+        assert!(matches!(manager.add_favourite_item(&FavouriteItem::artist("  ")), Err(FavouriteError::InvalidItem(_))));
+        manager.remove_favourite_item(&album).unwrap();
+        assert!(manager.get_favourite_items(FavouriteKind::Album).is_empty());
+    }
+}
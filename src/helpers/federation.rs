@@ -0,0 +1,418 @@
+use crate::config::get_service_config;
+use crate::helpers::device_identity;
+use crate::helpers::mdns::{self, MdnsService};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// mDNS service type announced by AudioControl instances
+pub const SERVICE_TYPE: &str = "_audiocontrol._tcp.local";
+
+/// Peers that haven't been seen for this long are dropped
+const PEER_EXPIRY_SECS: u64 = 3600;
+
+/// Errors of the federation
+#[derive(Debug, thiserror::Error)]
+pub enum FederationError {
+    #[error("Federation is disabled")]
+    Disabled,
+    #[error("Unknown device: {0}")]
+    UnknownPeer(String),
+    #[error("Device {0} is offline")]
+    Offline(String),
+    #[error("Request to peer failed: {0}")]
+    Request(String),
+}
+
+/// Configuration of the `federation` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Announce this instance with an Avahi service file
+    #[serde(default = "default_true")]
+    pub announce: bool,
+
+    /// Avahi service file that announces this instance
+    #[serde(default = "default_avahi_service_file")]
+    pub avahi_service_file: String,
+
+    /// Peers that are not found with mDNS, e.g. "http://192.168.1.20:1080"
+    #[serde(default)]
+    pub peers: Vec<String>,
+
+    /// Seconds between two searches for peers
+    #[serde(default = "default_discovery_interval_secs")]
+    pub discovery_interval_secs: u64,
+
+    /// Timeout of requests to peers
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// API tokens for peers with authentication enabled, by their URL in `peers`
+    #[serde(default)]
+    pub peer_tokens: HashMap<String, String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_avahi_service_file() -> String {
+    "/etc/avahi/services/audiocontrol.service".to_string()
+}
+
+fn default_discovery_interval_secs() -> u64 {
+    60
+}
+
+fn default_timeout_secs() -> u64 {
+    3
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            announce: true,
+            avahi_service_file: default_avahi_service_file(),
+            peers: Vec::new(),
+            discovery_interval_secs: default_discovery_interval_secs(),
+            timeout_secs: default_timeout_secs(),
+            peer_tokens: HashMap::new(),
+        }
+    }
+}
+
+/// Another AudioControl instance on the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Peer {
+    pub id: String,
+    pub name: String,
+    /// Base URL of the peer's API server, e.g. "http://192.168.1.20:1080"
+    pub url: String,
+    pub online: bool,
+    /// Unix timestamp of the last successful request
+    pub last_seen: u64,
+    /// Players of the peer as returned by its `/api/players`
+    pub players: Vec<serde_json::Value>,
+}
+
+static CONFIG: Lazy<RwLock<FederationConfig>> = Lazy::new(|| RwLock::new(FederationConfig::default()));
+static PEERS: Lazy<RwLock<Vec<Peer>>> = Lazy::new(|| RwLock::new(Vec::new()));
+static PORT: Lazy<RwLock<u16>> = Lazy::new(|| RwLock::new(1080));
+
+/// Initialize the federation from the `federation` service configuration
+///
+/// `port` is the port of the API server, it is announced to the peers.
+pub fn initialize_from_config(config: &serde_json::Value, port: u16) {
+    if let Some(c) = get_service_config(config, "federation") {
+        match serde_json::from_value::<FederationConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid federation configuration, using defaults: {}", e),
+        }
+    }
+    *PORT.write() = port;
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        debug!("Federation is disabled");
+        return;
+    }
+    info!("Federation enabled, searching for peers every {}s", config.discovery_interval_secs);
+    announce();
+
+    let interval = Duration::from_secs(config.discovery_interval_secs.max(5));
+    thread::spawn(move || loop {
+        refresh();
+        thread::sleep(interval);
+    });
+}
+
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+/// Write the Avahi service file, called again when the friendly name changes
+pub fn announce() {
+    let config = CONFIG.read().clone();
+    if !config.enable || !config.announce {
+        return;
+    }
+    let identity = device_identity::identity();
+    let xml = avahi_service_xml(&identity.name, &identity.id, *PORT.read());
+    if std::fs::read_to_string(&config.avahi_service_file).ok().as_deref() == Some(xml.as_str()) {
+        return;
+    }
+    match std::fs::write(&config.avahi_service_file, xml) {
+        Ok(()) => info!("Announcing {} via {}", identity.name, config.avahi_service_file),
+        Err(e) => warn!("Failed to write {}, peers won't find this device: {}", config.avahi_service_file, e),
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn avahi_service_xml(name: &str, id: &str, port: u16) -> String {
+    let service_type = SERVICE_TYPE.trim_end_matches(".local");
+    format!(
+        r#"<?xml version="1.0" standalone='no'?>
+<!DOCTYPE service-group SYSTEM "avahi-service.dtd">
+<service-group>
+  <name>{name}</name>
+  <service>
+    <type>{service_type}</type>
+    <port>{port}</port>
+    <txt-record>id={id}</txt-record>
+    <txt-record>name={name}</txt-record>
+  </service>
+</service-group>
+"#,
+        name = xml_escape(name),
+        id = xml_escape(id),
+    )
+}
+
+/// Known peers
+pub fn peers() -> Vec<Peer> {
+    PEERS.read().clone()
+}
+
+/// Search for peers and update their player lists
+pub fn refresh() {
+    let config = CONFIG.read().clone();
+    let own_id = device_identity::identity().id;
+    let timeout = Duration::from_secs(config.timeout_secs.max(1));
+
+    // URLs with the device ID announced via mDNS, configured peers are identified by their answer
+    let mut candidates: Vec<(String, Option<String>)> = config.peers.iter()
+        .map(|url| (url.trim_end_matches('/').to_string(), None))
+        .collect();
+    match mdns::browse(SERVICE_TYPE, timeout) {
+        Ok(services) => candidates.extend(services.iter().filter_map(|service| {
+            let url = mdns_peer_url(service)?;
+            Some((url, service.txt.get("id").cloned()))
+        })),
+        Err(e) => debug!("mDNS search for peers failed: {}", e),
+    }
+    candidates.sort();
+    candidates.dedup_by(|a, b| a.0 == b.0);
+
+    let now = now();
+    let mut seen = Vec::new();
+    for (url, announced_id) in candidates {
+        let token = peer_token(&config, &url);
+        match fetch_peer(&url, announced_id.as_deref(), token.as_deref(), timeout) {
+            // This instance, or a peer found under another address
+            Ok(peer) if peer.id == own_id || seen.iter().any(|p: &Peer| p.id == peer.id) => {}
+            Ok(peer) => seen.push(peer),
+            Err(e) => debug!("Peer {} not reachable: {}", url, e),
+        }
+    }
+    let mut peers = PEERS.write();
+    *peers = merge_peers(&peers, seen, now);
+}
+
+/// Update the peer list with the peers found now, missing peers are kept offline for a while
+fn merge_peers(previous: &[Peer], seen: Vec<Peer>, now: u64) -> Vec<Peer> {
+    let mut peers: Vec<Peer> = previous
+        .iter()
+        .filter(|old| !seen.iter().any(|p| p.id == old.id))
+        .filter(|old| now.saturating_sub(old.last_seen) < PEER_EXPIRY_SECS)
+        .map(|old| Peer { online: false, ..old.clone() })
+        .collect();
+    peers.extend(seen);
+    peers.sort_by_key(|p| p.name.to_lowercase());
+    peers
+}
+
+/// URL of a peer found with mDNS
+///
+/// Anyone on the network can answer mDNS queries, so only peers announcing the address they answered
+/// from are accepted. Otherwise an answer could point requests, and the tokens sent with them, anywhere.
+fn mdns_peer_url(service: &MdnsService) -> Option<String> {
+    let sender = service.sender?;
+    match service.host.parse::<IpAddr>() {
+        Ok(host) if host == sender => Some(format!("http://{}", crate::helpers::netaddr::host_port(&service.host, service.port))),
+        _ => {
+            debug!("Ignoring peer {} announcing {} from {}", service.instance, service.host, sender);
+            None
+        }
+    }
+}
+
+/// Token for requests to a peer URL
+///
+/// Tokens are only sent to configured peers. A host found with mDNS could announce any device ID, so it never
+/// gets a token, even if it reports the ID of a peer with a token.
+fn peer_token(config: &FederationConfig, url: &str) -> Option<String> {
+    let normalize = |u: &str| u.trim_end_matches('/').to_string();
+    if !config.peers.iter().any(|peer| normalize(peer) == url) {
+        return None;
+    }
+    config
+        .peer_tokens
+        .iter()
+        .find(|(peer, _)| normalize(peer) == url)
+        .map(|(_, token)| token.clone())
+}
+
+fn fetch_peer(url: &str, announced_id: Option<&str>, token: Option<&str>, timeout: Duration) -> Result<Peer, FederationError> {
+    let get = |path: &str| -> Result<serde_json::Value, FederationError> {
+        let mut request = ureq::get(&format!("{}{}", url, path)).timeout(timeout);
+        if let Some(token) = token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let body = request
+            .call()
+            .map_err(|e| FederationError::Request(e.to_string()))?
+            .into_string()
+            .map_err(|e| FederationError::Request(e.to_string()))?;
+        serde_json::from_str(&body).map_err(|e| FederationError::Request(e.to_string()))
+    };
+    let identity: device_identity::DeviceIdentity =
+        serde_json::from_value(get("/api/device")?).map_err(|e| FederationError::Request(e.to_string()))?;
+    if let Some(id) = announced_id.filter(|id| *id != identity.id) {
+        return Err(FederationError::Request(format!("announced as {}, but is {}", id, identity.id)));
+    }
+    let players = get("/api/players")?
+        .get("players")
+        .and_then(|p| p.as_array())
+        .cloned()
+        .unwrap_or_default();
+    Ok(Peer {
+        id: identity.id,
+        name: identity.name,
+        url: url.to_string(),
+        online: true,
+        last_seen: now(),
+        players,
+    })
+}
+
+/// Forward an API request to a peer
+///
+/// `path` is the path on the peer including the query, e.g. "/api/player/active/command/play".
+/// The request is authorized with the token configured for the peer's URL. The client's own
+/// credentials are never passed on, peers without a token get the request without authorization.
+/// Returns the status code and the body of the peer's response.
+pub fn forward(peer_id: &str, method: &str, path: &str, body: Option<&str>) -> Result<(u16, String), FederationError> {
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        return Err(FederationError::Disabled);
+    }
+    let peer = peers()
+        .into_iter()
+        .find(|p| p.id == peer_id)
+        .ok_or_else(|| FederationError::UnknownPeer(peer_id.to_string()))?;
+    if !peer.online {
+        return Err(FederationError::Offline(peer.name));
+    }
+    debug!("Forwarding {} {} to {}", method, path, peer.name);
+
+    let mut request = ureq::request(method, &format!("{}{}", peer.url, path))
+        .timeout(Duration::from_secs(config.timeout_secs.max(1)));
+    if let Some(token) = peer_token(&config, &peer.url) {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let result = match body {
+        Some(body) => request.set("Content-Type", "application/json").send_string(body),
+        None => request.call(),
+    };
+    let response = match result {
+        Ok(response) => response,
+        // Errors of the peer are passed on to the client
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(FederationError::Request(e.to_string())),
+    };
+    let status = response.status();
+    let body = response.into_string().map_err(|e| FederationError::Request(e.to_string()))?;
+    Ok((status, body))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(id: &str, name: &str, last_seen: u64) -> Peer {
+        Peer {
+            id: id.to_string(),
+            name: name.to_string(),
+            url: format!("http://{}:1080", id),
+            online: true,
+            last_seen,
+            players: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_peers() {
+        let previous = vec![peer("a", "Kitchen", 100), peer("b", "Bedroom", 100), peer("c", "Garage", 100)];
+        let merged = merge_peers(&previous, vec![peer("a", "Kitchen", 200)], 200);
+        let names: Vec<(&str, bool)> = merged.iter().map(|p| (p.name.as_str(), p.online)).collect();
+        assert_eq!(names, vec![("Bedroom", false), ("Garage", false), ("Kitchen", true)]);
+
+        // Peers gone for too long are dropped
+        assert_eq!(merge_peers(&merged, Vec::new(), 100 + PEER_EXPIRY_SECS).len(), 1);
+    }
+
+    fn service(host: &str, sender: Option<&str>) -> MdnsService {
+        MdnsService {
+            instance: "Kitchen._audiocontrol._tcp.local".to_string(),
+            host: host.to_string(),
+            port: 1080,
+            txt: HashMap::new(),
+            sender: sender.map(|s| s.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_mdns_peer_url() {
+        assert_eq!(mdns_peer_url(&service("192.168.1.21", Some("192.168.1.21"))), Some("http://192.168.1.21:1080".to_string()));
+        // Answers pointing somewhere else, e.g. to this device or the router
+        assert_eq!(mdns_peer_url(&service("127.0.0.1", Some("192.168.1.21"))), None);
+        assert_eq!(mdns_peer_url(&service("192.168.1.1", Some("192.168.1.21"))), None);
+        assert_eq!(mdns_peer_url(&service("kitchen.local", Some("192.168.1.21"))), None);
+        assert_eq!(mdns_peer_url(&service("192.168.1.21", None)), None);
+    }
+
+    #[test]
+    fn test_peer_token() {
+        let config = FederationConfig {
+            peers: vec!["http://192.168.2.20:1080/".to_string(), "http://192.168.2.21:1080".to_string()],
+            peer_tokens: [("http://192.168.2.20:1080", "acr_2_b"), ("http://192.168.1.22:1080", "acr_3_c")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..FederationConfig::default()
+        };
+        assert_eq!(peer_token(&config, "http://192.168.2.20:1080").as_deref(), Some("acr_2_b"));
+        // Configured without a token
+        assert_eq!(peer_token(&config, "http://192.168.2.21:1080"), None);
+        // Only found with mDNS, even with a token for its URL
+        assert_eq!(peer_token(&config, "http://192.168.1.22:1080"), None);
+    }
+
+    #[test]
+    fn test_avahi_service_xml() {
+        let xml = avahi_service_xml("Tom & Jerry's <room>", "abc", 1080);
+        assert!(xml.contains("<name>Tom &amp; Jerry's &lt;room&gt;</name>"));
+        assert!(xml.contains("<type>_audiocontrol._tcp</type>"));
+        assert!(xml.contains("<port>1080</port>"));
+        assert!(xml.contains("<txt-record>id=abc</txt-record>"));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::audiocontrol::AudioController;
+use crate::data::PlaybackState;
+use crate::helpers::backgroundjobs;
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Reachability of a service is checked again after this time
+const REACHABILITY_TTL: Duration = Duration::from_secs(60);
+
+/// Timeout of the connection to a service
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Running jobs without progress for this long are considered stalled
+const JOB_STALL_SECS: u64 = 600;
+
+/// Status of a check, the overall status is the worst of all checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Not configured, doesn't affect the overall status
+    Disabled,
+    Ok,
+    /// Part of the functionality is not available
+    Degraded,
+    /// The daemon can't do its job
+    Failed,
+}
+
+/// Result of one check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// "player", "cache", "service" or "background_jobs"
+    pub category: String,
+    pub name: String,
+    pub status: HealthStatus,
+    pub message: Option<String>,
+}
+
+impl HealthCheck {
+    fn new(category: &str, name: &str, status: HealthStatus, message: Option<String>) -> Self {
+        Self {
+            category: category.to_string(),
+            name: name.to_string(),
+            status,
+            message,
+        }
+    }
+}
+
+/// Status of all subsystems
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// External services with the host used to check the connection
+const SERVICES: [(&str, &str); 6] = [
+    ("musicbrainz", "musicbrainz.org"),
+    ("theaudiodb", "www.theaudiodb.com"),
+    ("wikipedia", "www.wikidata.org"),
+    ("fanarttv", "webservice.fanart.tv"),
+    ("qobuz", "www.qobuz.com"),
+    ("lastfm", "ws.audioscrobbler.com"),
+];
+
+static REACHABILITY: Lazy<Mutex<HashMap<String, (Instant, bool)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Check all subsystems
+///
+/// Blocks while services are contacted, at most for the connection timeout.
+pub fn check(controller: &AudioController) -> HealthReport {
+    let mut checks = check_players(controller);
+    checks.extend(check_caches());
+    checks.extend(check_services());
+    checks.push(check_background_jobs());
+    HealthReport {
+        status: overall_status(&checks),
+        checks,
+    }
+}
+
+fn check_players(controller: &AudioController) -> Vec<HealthCheck> {
+    let players: Vec<(String, PlaybackState)> = controller
+        .list_controllers()
+        .iter()
+        .map(|player| {
+            let player = player.read();
+            (player.get_player_name(), player.get_playback_state())
+        })
+        .collect();
+    if players.is_empty() {
+        return vec![HealthCheck::new("player", "players", HealthStatus::Failed, Some("No players configured".to_string()))];
+    }
+    let connected = players
+        .iter()
+        .filter(|(_, state)| !matches!(state, PlaybackState::Disconnected | PlaybackState::Killed))
+        .count();
+    players
+        .into_iter()
+        .map(|(name, state)| {
+            let status = match state {
+                // Without any connected player nothing can be played
+                PlaybackState::Disconnected | PlaybackState::Killed if connected == 0 => HealthStatus::Failed,
+                PlaybackState::Disconnected | PlaybackState::Killed => HealthStatus::Degraded,
+                _ => HealthStatus::Ok,
+            };
+            HealthCheck::new("player", &name, status, Some(state.to_string()))
+        })
+        .collect()
+}
+
+fn check_caches() -> Vec<HealthCheck> {
+    let cache = |name: &str, available: bool, critical: bool| {
+        let status = match (available, critical) {
+            (true, _) => HealthStatus::Ok,
+            (false, true) => HealthStatus::Failed,
+            (false, false) => HealthStatus::Degraded,
+        };
+        HealthCheck::new("cache", name, status, (!available).then(|| "Not available".to_string()))
+    };
+    vec![
+        // Settings, favourites and the device identity are kept here
+        cache("settingsdb", crate::helpers::settingsdb::get_settings_db().is_enabled(), true),
+        cache("attributecache", crate::helpers::attributecache::get_attribute_cache().is_enabled(), false),
+        cache("imagecache", crate::helpers::imagecache::get_image_cache().is_enabled(), false),
+    ]
+}
+
+fn service_enabled(name: &str) -> bool {
+    match name {
+        "musicbrainz" => crate::helpers::musicbrainz::is_enabled(),
+        "theaudiodb" => crate::helpers::theaudiodb::is_enabled(),
+        "wikipedia" => crate::helpers::wikipedia::is_enabled(),
+        "fanarttv" => crate::helpers::fanarttv::is_enabled(),
+        "qobuz" => crate::helpers::qobuz::is_enabled(),
+        "lastfm" => crate::helpers::lastfm::LastfmClient::get_instance().is_ok(),
+        _ => false,
+    }
+}
+
+fn check_services() -> Vec<HealthCheck> {
+    // Contact the services in parallel, a health check must not take long
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = SERVICES
+            .iter()
+            .map(|(name, host)| {
+                scope.spawn(move || {
+                    if !service_enabled(name) {
+                        return HealthCheck::new("service", name, HealthStatus::Disabled, None);
+                    }
+                    if is_reachable(host) {
+                        HealthCheck::new("service", name, HealthStatus::Ok, None)
+                    } else {
+                        HealthCheck::new("service", name, HealthStatus::Degraded, Some(format!("{} not reachable", host)))
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok()).collect()
+    })
+}
+
+/// Whether a TCP connection to the HTTPS port of a host can be opened, cached for a while
+fn is_reachable(host: &str) -> bool {
+    if let Some((checked, reachable)) = REACHABILITY.lock().get(host) {
+        if checked.elapsed() < REACHABILITY_TTL {
+            return *reachable;
+        }
+    }
+    let reachable = (host, 443)
+        .to_socket_addrs()
+        .map(|addrs| addrs.into_iter().any(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).is_ok()))
+        .unwrap_or(false);
+    debug!("Health check: {} is {}reachable", host, if reachable { "" } else { "not " });
+    REACHABILITY.lock().insert(host.to_string(), (Instant::now(), reachable));
+    reachable
+}
+
+fn check_background_jobs() -> HealthCheck {
+    let jobs = backgroundjobs::get_all_jobs().unwrap_or_default();
+    let running: Vec<_> = jobs.iter().filter(|job| !job.finished).collect();
+    let stalled: Vec<&str> = running
+        .iter()
+        .filter(|job| job.time_since_last_update() > JOB_STALL_SECS)
+        .map(|job| job.name.as_str())
+        .collect();
+    if stalled.is_empty() {
+        HealthCheck::new("background_jobs", "background_jobs", HealthStatus::Ok, Some(format!("{} running", running.len())))
+    } else {
+        HealthCheck::new(
+            "background_jobs",
+            "background_jobs",
+            HealthStatus::Degraded,
+            Some(format!("No progress for {} minutes: {}", JOB_STALL_SECS / 60, stalled.join(", "))),
+        )
+    }
+}
+
+/// The worst status of all checks, disabled checks are ignored
+fn overall_status(checks: &[HealthCheck]) -> HealthStatus {
+    checks
+        .iter()
+        .map(|check| check.status)
+        .filter(|status| *status != HealthStatus::Disabled)
+        .max()
+        .unwrap_or(HealthStatus::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_status() {
+        let check = |status| HealthCheck::new("service", "test", status, None);
+        assert_eq!(overall_status(&[]), HealthStatus::Ok);
+        assert_eq!(overall_status(&[check(HealthStatus::Disabled), check(HealthStatus::Ok)]), HealthStatus::Ok);
+        assert_eq!(overall_status(&[check(HealthStatus::Degraded), check(HealthStatus::Ok)]), HealthStatus::Degraded);
+        assert_eq!(overall_status(&[check(HealthStatus::Degraded), check(HealthStatus::Failed)]), HealthStatus::Failed);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::audiocontrol::eventbus::EventBus;
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{PlaybackState, PlayerCommand, PlayerEvent, PlayerSource};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Configuration of the idle policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleConfig {
+    /// Watch for inactivity
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Minutes without playback on any player before the system is idle
+    #[serde(default = "default_timeout_minutes")]
+    pub timeout_minutes: u64,
+
+    /// Send Stop to all players when the system becomes idle, e.g. to end paused sessions
+    #[serde(default)]
+    pub stop_players: bool,
+
+    /// Report no song in now-playing while idle
+    #[serde(default = "default_true")]
+    pub clear_now_playing: bool,
+
+    /// Shell command run when the system becomes idle, e.g. to switch off the amplifier trigger
+    #[serde(default)]
+    pub amp_off_command: Option<String>,
+
+    /// Shell command run when playback resumes after being idle
+    #[serde(default)]
+    pub amp_on_command: Option<String>,
+
+    /// Seconds between two checks of the players
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout_minutes() -> u64 {
+    30
+}
+
+fn default_poll_interval_secs() -> u64 {
+    5
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            timeout_minutes: default_timeout_minutes(),
+            stop_players: false,
+            clear_now_playing: true,
+            amp_off_command: None,
+            amp_on_command: None,
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+/// Change of the idle state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleTransition {
+    /// No playback for the configured time
+    Idle,
+    /// Playback resumed after the given time being idle
+    Wake(Duration),
+}
+
+/// Tracks playback activity and decides when the system becomes idle or wakes up
+#[derive(Debug, Clone)]
+pub struct IdleTracker {
+    timeout: Duration,
+    last_activity: Instant,
+    idle_since: Option<Instant>,
+}
+
+impl IdleTracker {
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout,
+            last_activity: now,
+            idle_since: None,
+        }
+    }
+
+    /// Record the current playback activity and return a state change, if any
+    pub fn update(&mut self, playing: bool, now: Instant) -> Option<IdleTransition> {
+        if playing {
+            self.last_activity = now;
+            return self
+                .idle_since
+                .take()
+                .map(|since| IdleTransition::Wake(now.saturating_duration_since(since)));
+        }
+
+        if self.idle_since.is_none() && now.saturating_duration_since(self.last_activity) >= self.timeout {
+            self.idle_since = Some(now);
+            return Some(IdleTransition::Idle);
+        }
+        None
+    }
+
+    /// Count an activity other than playback, e.g. a display being touched
+    ///
+    /// Returns the wake transition if the system was idle.
+    pub fn touch(&mut self, now: Instant) -> Option<IdleTransition> {
+        self.update(true, now)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle_since.is_some()
+    }
+
+    /// Time since the last activity
+    pub fn inactive_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_activity)
+    }
+}
+
+/// Current idle state as reported by the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleStatus {
+    pub enabled: bool,
+    pub idle: bool,
+    /// Seconds since the last playback or wake request
+    pub inactive_seconds: u64,
+    pub timeout_minutes: u64,
+    /// Unix timestamp at which the system became idle
+    pub idle_since: Option<u64>,
+}
+
+static CONFIG: Lazy<RwLock<IdleConfig>> = Lazy::new(|| RwLock::new(IdleConfig::default()));
+static TRACKER: Lazy<RwLock<IdleTracker>> =
+    Lazy::new(|| RwLock::new(IdleTracker::new(Duration::from_secs(default_timeout_minutes() * 60), Instant::now())));
+static IDLE_SINCE: Lazy<RwLock<Option<SystemTime>>> = Lazy::new(|| RwLock::new(None));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize the idle policy from the `idle` service configuration
+///
+/// If enabled, a background thread checks the playback state of all players.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let idle_config = match get_service_config(config, "idle") {
+        Some(c) => match serde_json::from_value::<IdleConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid idle configuration, idle policy disabled: {}", e);
+                return;
+            }
+        },
+        None => IdleConfig::default(),
+    };
+    *CONFIG.write() = idle_config.clone();
+
+    if !idle_config.enable {
+        debug!("Idle policy is disabled");
+        return;
+    }
+
+    info!("System becomes idle after {} minutes without playback", idle_config.timeout_minutes);
+    *TRACKER.write() = IdleTracker::new(Duration::from_secs(idle_config.timeout_minutes * 60), Instant::now());
+
+    let interval = Duration::from_secs(idle_config.poll_interval_secs.max(1));
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        check_activity();
+    });
+}
+
+/// Check whether the idle policy is enabled
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+/// Check whether the system is idle
+pub fn is_idle() -> bool {
+    is_enabled() && TRACKER.read().is_idle()
+}
+
+/// Check whether now-playing information should be hidden
+pub fn hides_now_playing() -> bool {
+    is_idle() && CONFIG.read().clear_now_playing
+}
+
+pub fn get_status() -> IdleStatus {
+    let config = CONFIG.read();
+    let tracker = TRACKER.read();
+    IdleStatus {
+        enabled: config.enable,
+        idle: config.enable && tracker.is_idle(),
+        inactive_seconds: tracker.inactive_for(Instant::now()).as_secs(),
+        timeout_minutes: config.timeout_minutes,
+        idle_since: IDLE_SINCE
+            .read()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+    }
+}
+
+/// Reset the inactivity timer, waking the system up if it is idle
+pub fn wake() {
+    if !is_enabled() {
+        return;
+    }
+    let transition = TRACKER.write().touch(Instant::now());
+    if let Some(transition) = transition {
+        apply_transition(transition);
+    }
+}
+
+fn get_controller() -> Option<std::sync::Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+/// Check all players for playback and update the idle state
+fn check_activity() {
+    let Some(controller) = get_controller() else {
+        return;
+    };
+    let playing = controller
+        .list_controllers()
+        .iter()
+        .any(|ctrl| ctrl.read().get_playback_state() == PlaybackState::Playing);
+
+    let transition = TRACKER.write().update(playing, Instant::now());
+    if let Some(transition) = transition {
+        apply_transition(transition);
+    }
+}
+
+fn apply_transition(transition: IdleTransition) {
+    let config = CONFIG.read().clone();
+    match transition {
+        IdleTransition::Idle => {
+            info!("No playback for {} minutes, system is idle", config.timeout_minutes);
+            *IDLE_SINCE.write() = Some(SystemTime::now());
+
+            if let Some(controller) = get_controller() {
+                if config.stop_players {
+                    for ctrl in controller.list_controllers() {
+                        ctrl.read().send_command(PlayerCommand::Stop);
+                    }
+                }
+                if config.clear_now_playing {
+                    if let Some(active) = controller.get_active_controller() {
+                        let player = active.read();
+                        EventBus::instance().publish(PlayerEvent::SongChanged {
+                            source: PlayerSource::new(player.get_player_name(), player.get_player_id()),
+                            song: None,
+                        });
+                    }
+                }
+            }
+
+            if let Some(command) = &config.amp_off_command {
+                run_command(command);
+            }
+            EventBus::instance().publish(PlayerEvent::SystemIdle {
+                inactive_seconds: config.timeout_minutes * 60,
+            });
+        }
+        IdleTransition::Wake(idle_for) => {
+            info!("Activity after being idle for {} seconds, waking up", idle_for.as_secs());
+            *IDLE_SINCE.write() = None;
+            if let Some(command) = &config.amp_on_command {
+                run_command(command);
+            }
+            EventBus::instance().publish(PlayerEvent::SystemWake {
+                idle_seconds: idle_for.as_secs(),
+            });
+        }
+    }
+}
+
+fn run_command(command: &str) {
+    debug!("Running idle command: {}", command);
+    match Command::new("sh").arg("-c").arg(command).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("Idle command '{}' failed with {}", command, status),
+        Err(e) => warn!("Failed to run idle command '{}': {}", command, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_after_timeout() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(Duration::from_secs(600), start);
+
+        assert_eq!(tracker.update(false, start + Duration::from_secs(300)), None);
+        assert_eq!(tracker.update(true, start + Duration::from_secs(400)), None);
+        // The timer restarts with the last playback
+        assert_eq!(tracker.update(false, start + Duration::from_secs(700)), None);
+        assert_eq!(tracker.update(false, start + Duration::from_secs(1000)), Some(IdleTransition::Idle));
+        assert!(tracker.is_idle());
+        // Idle is only reported once
+        assert_eq!(tracker.update(false, start + Duration::from_secs(2000)), None);
+    }
+
+    #[test]
+    fn test_wake_on_playback() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(Duration::from_secs(60), start);
+
+        assert_eq!(tracker.update(true, start + Duration::from_secs(10)), None);
+        assert_eq!(tracker.update(false, start + Duration::from_secs(70)), Some(IdleTransition::Idle));
+        assert_eq!(
+            tracker.update(true, start + Duration::from_secs(100)),
+            Some(IdleTransition::Wake(Duration::from_secs(30)))
+        );
+        assert!(!tracker.is_idle());
+        assert_eq!(tracker.touch(start + Duration::from_secs(110)), None);
+        assert_eq!(tracker.inactive_for(start + Duration::from_secs(120)), Duration::from_secs(10));
+    }
+}
@@ -1,18 +1,22 @@
 use std::path::{Path, PathBuf};
 use std::fs::{self, File, read_dir};
-use std::io::{Write, Read};
+use std::io::Read;
 use parking_lot::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
 use log::{info, error, debug};
 use serde::{Serialize, Deserialize};
+use sha1::{Digest, Sha1};
 use crate::helpers::attributecache;
 
 // Constants for cache keys
 const IMAGECACHE_METADATA_PREFIX: &str = "imagecache:metadata:";
 const IMAGECACHE_STATS_KEY: &str = "imagecache:stats";
 
+/// Directory below the cache directory that stores image contents by their hash
+const OBJECTS_DIR: &str = ".objects";
+
 /// Metadata for a cached image
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageMetadata {
@@ -35,6 +39,9 @@ pub struct ImageCacheStats {
     pub total_images: usize,
     /// Total size of all cached images in bytes
     pub total_size: u64,
+    /// Size on disk in bytes, identical images are only stored once
+    #[serde(default)]
+    pub stored_size: u64,
     /// Last time statistics were updated
     pub last_updated: u64,
 }
@@ -50,6 +57,7 @@ impl ImageCacheStats {
         Self {
             total_images: 0,
             total_size: 0,
+            stored_size: 0,
             last_updated: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap_or_default()
@@ -82,6 +90,38 @@ impl ImageExpiryMetadata {
     }
 }
 
+/// Stored content of one or more cached images
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContentObject {
+    /// Size of the content in bytes
+    pub size: u64,
+    /// Number of image paths that are linked to the content
+    pub references: u32,
+}
+
+/// Index of the content-addressed image storage
+///
+/// The content of every image is stored once in `.objects`, named by its SHA-1 hash, and
+/// the image paths are hard links to it. Artwork shared by several albums or artists,
+/// e.g. of compilations, only takes space once.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ContentIndex {
+    /// Map of image path to the hash of its content
+    pub paths: HashMap<String, String>,
+    /// Map of content hash to the stored object
+    pub objects: HashMap<String, ContentObject>,
+}
+
+impl ContentIndex {
+    /// Bytes saved because identical images are stored once
+    pub fn saved_bytes(&self) -> u64 {
+        self.objects
+            .values()
+            .map(|object| object.size * u64::from(object.references.saturating_sub(1)))
+            .sum()
+    }
+}
+
 /// A cache for storing image files
 pub struct ImageCache {
     /// Base directory for storing images
@@ -90,6 +130,8 @@ pub struct ImageCache {
     enabled: bool,
     /// Path to the expiry metadata file
     expiry_metadata_path: PathBuf,
+    /// Path to the index of the content-addressed storage
+    content_index_path: PathBuf,
 }
 
 impl Default for ImageCache {
@@ -110,6 +152,7 @@ impl ImageCache {
     pub fn with_directory<P: AsRef<Path>>(dir: P) -> Self {
         let base_path = dir.as_ref().to_path_buf();
         let expiry_metadata_path = base_path.join(".expiry_metadata.json");
+        let content_index_path = base_path.join(".content_index.json");
         
         // Ensure the directory exists
         if let Err(e) = fs::create_dir_all(&base_path) {
@@ -122,6 +165,7 @@ impl ImageCache {
             base_path,
             enabled: true,
             expiry_metadata_path,
+            content_index_path,
         }
     }
 
@@ -129,6 +173,7 @@ impl ImageCache {
     pub fn with_custom_expiry_path<P: AsRef<Path>, E: AsRef<Path>>(dir: P, expiry_path: E) -> Self {
         let base_path = dir.as_ref().to_path_buf();
         let expiry_metadata_path = expiry_path.as_ref().to_path_buf();
+        let content_index_path = base_path.join(".content_index.json");
         
         // Ensure the directory exists
         if let Err(e) = fs::create_dir_all(&base_path) {
@@ -141,6 +186,7 @@ impl ImageCache {
             base_path,
             enabled: true,
             expiry_metadata_path,
+            content_index_path,
         }
     }
 
@@ -170,6 +216,7 @@ impl ImageCache {
         // Update the instance
         self.base_path = base_path.clone();
         self.expiry_metadata_path = base_path.join(".expiry_metadata.json");
+        self.content_index_path = base_path.join(".content_index.json");
         info!("Image cache reconfigured with directory: {:?}", self.base_path);
         
         Ok(())
@@ -221,6 +268,229 @@ impl ImageCache {
         }
     }
 
+    /// Load the index of the content-addressed storage from disk
+    fn load_content_index(&self) -> ContentIndex {
+        if !self.content_index_path.exists() {
+            return ContentIndex::default();
+        }
+
+        match fs::read_to_string(&self.content_index_path) {
+            Ok(content) => match serde_json::from_str::<ContentIndex>(&content) {
+                Ok(index) => index,
+                Err(e) => {
+                    error!("Failed to parse image content index: {}", e);
+                    ContentIndex::default()
+                }
+            },
+            Err(e) => {
+                error!("Failed to read image content index: {}", e);
+                ContentIndex::default()
+            }
+        }
+    }
+
+    /// Save the index of the content-addressed storage to disk
+    fn save_content_index(&self, index: &ContentIndex) -> Result<(), String> {
+        let content = serde_json::to_string(index)
+            .map_err(|e| format!("Failed to serialize image content index: {}", e))?;
+        fs::write(&self.content_index_path, content)
+            .map_err(|e| format!("Failed to write image content index: {}", e))
+    }
+
+    /// Path of the stored content with the given hash
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.base_path.join(OBJECTS_DIR).join(&hash[..2]).join(hash)
+    }
+
+    /// Write image data to a path of the cache
+    fn write_content(&self, path: &str, data: &[u8]) -> Result<(), String> {
+        let mut index = self.load_content_index();
+        self.link_content(&mut index, path, data)?;
+        self.save_content_index(&index)
+    }
+
+    /// Store the content once and make the path a hard link to it
+    ///
+    /// If the path can't be linked, e.g. because it is on another file system, a copy is written.
+    fn link_content(&self, index: &mut ContentIndex, path: &str, data: &[u8]) -> Result<(), String> {
+        let full_path = self.get_full_path(path);
+        if let Some(parent) = full_path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+        }
+
+        let hash = hex::encode(Sha1::digest(data));
+        if index.paths.get(path) == Some(&hash) && full_path.exists() {
+            debug!("Image {} is unchanged", path);
+            return Ok(());
+        }
+
+        let object_path = self.object_path(&hash);
+        if !object_path.exists() {
+            if let Some(parent) = object_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+            // Write to a temporary file first, so there are never partial objects
+            let temp_path = object_path.with_extension("tmp");
+            fs::write(&temp_path, data).map_err(|e| format!("Failed to write image data: {}", e))?;
+            fs::rename(&temp_path, &object_path).map_err(|e| format!("Failed to write image data: {}", e))?;
+        }
+        let size = data.len() as u64;
+        index.objects.entry(hash.clone()).or_insert(ContentObject { size, references: 0 });
+
+        // Replace the previous image at this path
+        let previous = index.paths.remove(path);
+        if full_path.exists() {
+            fs::remove_file(&full_path).map_err(|e| format!("Failed to replace image: {}", e))?;
+        }
+
+        match fs::hard_link(&object_path, &full_path) {
+            Ok(()) => {
+                if let Some(object) = index.objects.get_mut(&hash) {
+                    object.references += 1;
+                }
+                index.paths.insert(path.to_string(), hash.clone());
+                debug!("Stored image at {} as {}", full_path.display(), hash);
+            }
+            Err(e) => {
+                debug!("Can't link {} to its content, storing a copy: {}", full_path.display(), e);
+                fs::write(&full_path, data).map_err(|e| format!("Failed to write image data: {}", e))?;
+            }
+        }
+
+        if let Some(previous) = previous {
+            self.drop_reference(index, &previous);
+        }
+        self.remove_if_unreferenced(index, &hash);
+        Ok(())
+    }
+
+    /// Remove the reference of a path to its content
+    ///
+    /// Returns true if the content isn't used anymore and was removed.
+    fn release_path(&self, index: &mut ContentIndex, path: &str) -> bool {
+        match index.paths.remove(path) {
+            Some(hash) => self.drop_reference(index, &hash),
+            None => false,
+        }
+    }
+
+    fn drop_reference(&self, index: &mut ContentIndex, hash: &str) -> bool {
+        if let Some(object) = index.objects.get_mut(hash) {
+            object.references = object.references.saturating_sub(1);
+        }
+        self.remove_if_unreferenced(index, hash)
+    }
+
+    fn remove_if_unreferenced(&self, index: &mut ContentIndex, hash: &str) -> bool {
+        if index.objects.get(hash).is_some_and(|object| object.references > 0) {
+            return false;
+        }
+        index.objects.remove(hash);
+        match fs::remove_file(self.object_path(hash)) {
+            Ok(()) => {
+                debug!("Removed unused image content {}", hash);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Remove stored contents that no image uses anymore
+    ///
+    /// Images deleted outside of the cache release their content as well.
+    ///
+    /// # Returns
+    /// * `Result<usize, String>` - Number of removed contents or error message
+    pub fn collect_garbage(&self) -> Result<usize, String> {
+        if !self.is_enabled() {
+            return Err("Image cache is disabled".to_string());
+        }
+
+        let mut index = self.load_content_index();
+        let mut removed = 0;
+
+        let missing: Vec<String> = index.paths
+            .keys()
+            .filter(|path| !self.get_full_path(path).exists())
+            .cloned()
+            .collect();
+        for path in missing {
+            if self.release_path(&mut index, &path) {
+                removed += 1;
+            }
+        }
+
+        // Objects that are not in the index, e.g. left over from an interrupted write
+        let objects_dir = self.base_path.join(OBJECTS_DIR);
+        for entry in read_dir(&objects_dir).into_iter().flatten().flatten() {
+            for object in read_dir(entry.path()).into_iter().flatten().flatten() {
+                let name = object.file_name().to_string_lossy().to_string();
+                if !index.objects.contains_key(&name) && fs::remove_file(object.path()).is_ok() {
+                    debug!("Removed unknown image content {}", name);
+                    removed += 1;
+                }
+            }
+        }
+        index.objects.retain(|hash, _| self.object_path(hash).exists());
+
+        self.save_content_index(&index)?;
+        info!("Removed {} unused image contents from cache", removed);
+        Ok(removed)
+    }
+
+    /// Move images that were stored before content addressing to the content storage
+    ///
+    /// # Returns
+    /// * `Result<usize, String>` - Number of images that were duplicates or error message
+    pub fn deduplicate(&self) -> Result<usize, String> {
+        if !self.is_enabled() {
+            return Err("Image cache is disabled".to_string());
+        }
+
+        fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+            for entry in read_dir(dir).into_iter().flatten().flatten() {
+                // Skip the objects and the metadata files
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let path = entry.path();
+                if path.is_dir() {
+                    collect_files(&path, files);
+                } else if path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        collect_files(&self.base_path, &mut files);
+
+        let mut index = self.load_content_index();
+        let mut duplicates = 0;
+        for file in files {
+            let Some(path) = file.strip_prefix(&self.base_path).ok().and_then(|p| p.to_str()).map(|p| p.to_string()) else {
+                continue;
+            };
+            if index.paths.contains_key(&path) {
+                continue;
+            }
+            let data = fs::read(&file).map_err(|e| format!("Failed to read image {}: {}", file.display(), e))?;
+            let known = index.objects.contains_key(&hex::encode(Sha1::digest(&data)));
+            self.link_content(&mut index, &path, &data)?;
+            if known {
+                duplicates += 1;
+            }
+        }
+        self.save_content_index(&index)?;
+
+        info!("Found {} duplicate images in cache, {} bytes saved in total", duplicates, index.saved_bytes());
+        Ok(duplicates)
+    }
+
     /// Set expiry time for an image
     /// 
     /// # Arguments
@@ -313,10 +583,13 @@ impl ImageCache {
             }
         }
 
-        // Remove expired entries from metadata
+        // Remove expired entries from metadata and release their content
+        let mut index = self.load_content_index();
         for path in paths_to_remove {
+            self.release_path(&mut index, &path);
             metadata.expiry_map.remove(&path);
         }
+        self.save_content_index(&index)?;
 
         // Save updated metadata
         self.save_expiry_metadata(&metadata)?;
@@ -355,30 +628,12 @@ impl ImageCache {
         }
 
         let path_ref = path.as_ref();
-        let full_path = self.get_full_path(path_ref);
-        
-        // Ensure parent directory exists
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    return Err(format!("Failed to create directory {}: {}", parent.display(), e));
-                }
-            }
-        }
-        
-        // Write the image data to file
-        match File::create(&full_path) {
-            Ok(mut file) => {
-                if let Err(e) = file.write_all(data) {
-                    return Err(format!("Failed to write image data: {}", e));
-                }
-                debug!("Stored image at {}", full_path.display());
-            },
-            Err(e) => return Err(format!("Failed to create image file: {}", e)),
-        }
+        let path_str = path_ref.to_string_lossy().to_string();
+
+        // Write the image data, identical images share their content
+        self.write_content(&path_str, data)?;
 
         // Create and store metadata
-        let path_str = path_ref.to_string_lossy().to_string();
         let expires_at = expiry_time.map(|t| {
             t.duration_since(UNIX_EPOCH)
                 .unwrap_or_default()
@@ -443,27 +698,8 @@ impl ImageCache {
         let path_str = path.as_ref().to_string_lossy().to_string();
         let path_with_extension = format!("{}.{}", path_str, extension);
         
-        let full_path = self.get_full_path(&path_with_extension);
-        
-        // Ensure parent directory exists
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    return Err(format!("Failed to create directory {}: {}", parent.display(), e));
-                }
-            }
-        }
-        
-        // Write the image data to file
-        match File::create(&full_path) {
-            Ok(mut file) => {
-                if let Err(e) = file.write_all(&data) {
-                    return Err(format!("Failed to write image data: {}", e));
-                }
-                debug!("Stored image at {}", full_path.display());
-            },
-            Err(e) => return Err(format!("Failed to create image file: {}", e)),
-        }
+        // Write the image data, identical images share their content
+        self.write_content(&path_with_extension, &data)?;
 
         // Create and store metadata
         let expires_at = expiry_time.map(|t| {
@@ -528,16 +764,26 @@ impl ImageCache {
         let path_str = path.as_ref().to_string_lossy().to_string();
         let full_path = self.get_full_path(&path);
         
-        if !full_path.exists() {
+        let existed = full_path.exists();
+        if existed {
+            if let Err(e) = fs::remove_file(&full_path) {
+                return Err(format!("Failed to delete image: {}", e));
+            }
+        }
+
+        // Release the content, it is removed if no other image uses it
+        let mut index = self.load_content_index();
+        if index.paths.contains_key(&path_str) {
+            self.release_path(&mut index, &path_str);
+            self.save_content_index(&index)?;
+        }
+
+        if !existed {
             // If the file doesn't exist, still try to remove metadata
             let _ = self.remove_image_metadata(&path_str);
             return Ok(());
         }
         
-        if let Err(e) = fs::remove_file(&full_path) {
-            return Err(format!("Failed to delete image: {}", e));
-        }
-        
         // Remove metadata from attribute cache
         self.remove_image_metadata(&path_str)?;
         
@@ -592,6 +838,7 @@ impl ImageCache {
                 return self.scan_filesystem_for_stats();
             }
         }
+        stats.stored_size = stats.total_size.saturating_sub(self.load_content_index().saved_bytes());
 
         // Store updated stats
         attributecache::set(IMAGECACHE_STATS_KEY, &stats)
@@ -623,7 +870,8 @@ impl ImageCache {
                                     }
                                 }
                             }
-                        } else if path.is_dir() {
+                        } else if path.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+                            // The objects directory only holds the contents of the images
                             scan_directory(&path, stats)?;
                         }
                     }
@@ -636,6 +884,7 @@ impl ImageCache {
         }
 
         scan_directory(&self.base_path, &mut stats)?;
+        stats.stored_size = stats.total_size.saturating_sub(self.load_content_index().saved_bytes());
         
         // Store scanned stats
         attributecache::set(IMAGECACHE_STATS_KEY, &stats)
@@ -907,6 +1156,16 @@ pub fn expire_images() -> Result<usize, String> {
     get_image_cache().expire_images()
 }
 
+/// Remove stored contents that no image uses anymore
+pub fn collect_garbage() -> Result<usize, String> {
+    get_image_cache().collect_garbage()
+}
+
+/// Move images that were stored before content addressing to the content storage
+pub fn deduplicate() -> Result<usize, String> {
+    get_image_cache().deduplicate()
+}
+
 /// Get image cache statistics
 /// 
 /// # Returns
@@ -1217,6 +1476,64 @@ mod tests {
         assert!(metadata1_after.is_none());
     }
 
+    #[test]
+    #[serial]
+    fn test_content_deduplication() {
+        init_test_attribute_cache();
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().to_str().unwrap();
+        let expiry_path = temp_dir.path().join("expiry.json");
+        let cache = ImageCache::with_custom_expiry_path(cache_path, &expiry_path);
+
+        let cover = b"greatest hits cover";
+        cache.store_image("albums/a/cover.jpg", cover).unwrap();
+        cache.store_image("albums/b/cover.jpg", cover).unwrap();
+        cache.store_image("albums/c/cover.jpg", b"other cover").unwrap();
+
+        let index = cache.load_content_index();
+        assert_eq!(index.objects.len(), 2);
+        assert_eq!(index.paths["albums/a/cover.jpg"], index.paths["albums/b/cover.jpg"]);
+        assert_eq!(index.saved_bytes(), cover.len() as u64);
+        let object = cache.object_path(&index.paths["albums/a/cover.jpg"]);
+
+        // The content stays until the last image that uses it is gone
+        cache.delete_image("albums/a/cover.jpg").unwrap();
+        assert!(object.exists());
+        assert_eq!(cache.get_image_data("albums/b/cover.jpg").unwrap(), cover);
+
+        // Replacing an image releases the old content
+        cache.store_image("albums/b/cover.jpg", b"new cover").unwrap();
+        assert!(!object.exists());
+        assert_eq!(cache.get_image_data("albums/b/cover.jpg").unwrap(), b"new cover");
+
+        // Images deleted outside of the cache are found by the garbage collection
+        fs::remove_file(temp_dir.path().join("albums/c/cover.jpg")).unwrap();
+        assert_eq!(cache.collect_garbage().unwrap(), 1);
+        assert_eq!(cache.load_content_index().objects.len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_deduplicate_existing_images() {
+        init_test_attribute_cache();
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().to_str().unwrap();
+        let expiry_path = temp_dir.path().join(".expiry.json");
+        fs::create_dir_all(temp_dir.path().join("artists/x")).unwrap();
+        fs::write(temp_dir.path().join("artists/x/cover.jpg"), b"same").unwrap();
+        fs::write(temp_dir.path().join("cover.jpg"), b"same").unwrap();
+
+        let cache = ImageCache::with_custom_expiry_path(cache_path, &expiry_path);
+        assert_eq!(cache.deduplicate().unwrap(), 1);
+        assert_eq!(cache.load_content_index().objects.len(), 1);
+        assert_eq!(cache.get_image_data("artists/x/cover.jpg").unwrap(), b"same");
+
+        // Already stored images are skipped
+        assert_eq!(cache.deduplicate().unwrap(), 0);
+    }
+
     #[test]
     #[serial]
     fn test_global_cache_statistics_functions() {
@@ -0,0 +1,302 @@
+use crate::helpers::imagecache;
+use crate::helpers::network::is_link_local;
+use image::imageops::FilterType;
+use image::ImageFormat;
+use log::{debug, warn};
+use std::io::{self, Cursor, Read};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// Proxied images are refetched after this time
+const PROXY_CACHE_DAYS: u64 = 30;
+
+/// Images larger than this are not proxied
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Timeout of fetching an image
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Smallest and largest size that can be requested
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 2048;
+
+/// Errors that can occur when proxying an image
+#[derive(Debug, Error)]
+pub enum ImageProxyError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Images can't be fetched from local addresses: {0}")]
+    LocalAddress(String),
+
+    #[error("Failed to fetch image: {0}")]
+    FetchError(String),
+
+    #[error("Not an image: {0}")]
+    NotAnImage(String),
+
+    #[error("Failed to resize image: {0}")]
+    ResizeError(String),
+}
+
+/// Base path in the image cache for a proxied URL
+fn cache_base_path(url: &str) -> String {
+    format!("proxy/{:x}", md5::compute(url.as_bytes()))
+}
+
+/// Strip parameters like charset from a content type
+fn normalize_mime_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_lowercase()
+}
+
+/// Image types that are proxied
+///
+/// Only raster formats, SVG can contain scripts that would run in the origin of the API.
+const ALLOWED_MIME_TYPES: [&str; 5] = ["image/jpeg", "image/png", "image/gif", "image/webp", "image/avif"];
+
+fn is_allowed_mime_type(mime_type: &str) -> bool {
+    ALLOWED_MIME_TYPES.contains(&mime_type)
+}
+
+fn cache_expiry() -> SystemTime {
+    SystemTime::now() + Duration::from_secs(PROXY_CACHE_DAYS * 24 * 3600)
+}
+
+/// Get an image from a remote URL through the image cache
+///
+/// The original image is cached, resized versions are cached separately.
+/// Images are only scaled down, never up.
+///
+/// # Arguments
+/// * `url` - HTTP(S) URL of the image
+/// * `size` - Optional maximum width/height in pixels
+///
+/// # Returns
+/// * `Result<(Vec<u8>, String), ImageProxyError>` - Image data and MIME type
+pub fn get_image(url: &str, size: Option<u32>) -> Result<(Vec<u8>, String), ImageProxyError> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ImageProxyError::InvalidUrl(url.to_string()));
+    }
+
+    let base_path = cache_base_path(url);
+    let original = get_original(url, &base_path)?;
+
+    let Some(size) = size else {
+        return Ok(original);
+    };
+    let size = size.clamp(MIN_SIZE, MAX_SIZE);
+
+    let resized_path = format!("{}/{}", base_path, size);
+    if let Ok(cached) = imagecache::get_image_with_mime_type(&resized_path) {
+        debug!("Serving resized proxy image for {} ({}px) from cache", url, size);
+        return Ok(cached);
+    }
+
+    let (data, mime_type) = original;
+    match resize_image(&data, &mime_type, size)? {
+        Some((resized, resized_mime)) => {
+            if let Err(e) = imagecache::store_image_from_data_with_expiry(
+                &resized_path,
+                resized.clone(),
+                resized_mime.clone(),
+                Some(cache_expiry()),
+            ) {
+                warn!("Failed to cache resized proxy image for {}: {}", url, e);
+            }
+            Ok((resized, resized_mime))
+        }
+        // Already small enough
+        None => Ok((data, mime_type)),
+    }
+}
+
+fn get_original(url: &str, base_path: &str) -> Result<(Vec<u8>, String), ImageProxyError> {
+    let original_path = format!("{}/original", base_path);
+    if let Ok(cached) = imagecache::get_image_with_mime_type(&original_path) {
+        if is_allowed_mime_type(&cached.1) {
+            debug!("Serving proxy image for {} from cache", url);
+            return Ok(cached);
+        }
+    }
+
+    check_target(url)?;
+    debug!("Fetching proxy image from {}", url);
+    let (data, mime_type) = fetch(url)?;
+
+    if let Err(e) = imagecache::store_image_from_data_with_expiry(
+        &original_path,
+        data.clone(),
+        mime_type.clone(),
+        Some(cache_expiry()),
+    ) {
+        warn!("Failed to cache proxy image for {}: {}", url, e);
+    }
+
+    Ok((data, mime_type))
+}
+
+/// Refuse URLs of hosts that only resolve to local addresses
+///
+/// `fetch` checks the addresses again when connecting, this only gives a clear error up front.
+fn check_target(url: &str) -> Result<(), ImageProxyError> {
+    let parsed = url::Url::parse(url).map_err(|e| ImageProxyError::InvalidUrl(format!("{}: {}", url, e)))?;
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return Err(ImageProxyError::InvalidUrl(url.to_string()));
+    };
+    match resolve_remote(&format!("{}:{}", host, port)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(ImageProxyError::LocalAddress(host.to_string())),
+        Err(e) => Err(ImageProxyError::FetchError(e.to_string())),
+    }
+}
+
+/// Resolve a host for fetching images, without loopback and link-local addresses
+///
+/// Otherwise the proxy could be used to read services that only listen on this device, or the
+/// metadata endpoints of cloud hosts. Used for every connection, so redirects are checked too.
+fn resolve_remote(netloc: &str) -> io::Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+    let remote: Vec<SocketAddr> = addrs.iter().copied().filter(|addr| is_remote(&addr.ip())).collect();
+    if remote.is_empty() && !addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is a local address", netloc)));
+    }
+    Ok(remote)
+}
+
+fn is_remote(ip: &IpAddr) -> bool {
+    // e.g. ::ffff:127.0.0.1
+    let ip = ip.to_canonical();
+    !ip.is_loopback() && !ip.is_unspecified() && !is_link_local(&ip)
+}
+
+/// Fetch an image, returns the data and the MIME type
+fn fetch(url: &str) -> Result<(Vec<u8>, String), ImageProxyError> {
+    let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).resolver(resolve_remote).build();
+    let response = agent.get(url).call().map_err(|e| ImageProxyError::FetchError(e.to_string()))?;
+
+    let mime_type = normalize_mime_type(response.header("Content-Type").unwrap_or("application/octet-stream"));
+    if !is_allowed_mime_type(&mime_type) {
+        return Err(ImageProxyError::NotAnImage(mime_type));
+    }
+    let content_length = response.header("Content-Length").and_then(|length| length.parse().ok());
+    let data = read_image(response.into_reader(), content_length)?;
+    Ok((data, mime_type))
+}
+
+/// Read an image of at most MAX_IMAGE_BYTES
+///
+/// Larger images are refused by their Content-Length, or once more has been read if it is missing or wrong.
+fn read_image(reader: impl Read, content_length: Option<usize>) -> Result<Vec<u8>, ImageProxyError> {
+    let too_large = || ImageProxyError::FetchError(format!("Image larger than {} bytes", MAX_IMAGE_BYTES));
+    if content_length.is_some_and(|length| length > MAX_IMAGE_BYTES) {
+        return Err(too_large());
+    }
+    let mut data = Vec::with_capacity(content_length.unwrap_or(0));
+    reader
+        .take(MAX_IMAGE_BYTES as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| ImageProxyError::FetchError(e.to_string()))?;
+    if data.len() > MAX_IMAGE_BYTES {
+        return Err(too_large());
+    }
+    Ok(data)
+}
+
+/// Scale an image down so that it fits into a size x size box
+///
+/// PNG images are kept as PNG to preserve transparency, everything else is encoded as JPEG.
+///
+/// # Returns
+/// * `Ok(None)` if the image already fits
+fn resize_image(data: &[u8], mime_type: &str, size: u32) -> Result<Option<(Vec<u8>, String)>, ImageProxyError> {
+    let format = ImageFormat::from_mime_type(mime_type)
+        .ok_or_else(|| ImageProxyError::ResizeError(format!("Unsupported image type {}", mime_type)))?;
+    let img = image::load_from_memory_with_format(data, format)
+        .map_err(|e| ImageProxyError::ResizeError(e.to_string()))?;
+
+    if img.width() <= size && img.height() <= size {
+        return Ok(None);
+    }
+
+    let resized = img.resize(size, size, FilterType::Lanczos3);
+    let (output_format, output_mime) = if format == ImageFormat::Png {
+        (ImageFormat::Png, "image/png")
+    } else {
+        (ImageFormat::Jpeg, "image/jpeg")
+    };
+
+    // JPEG can't store an alpha channel
+    let resized = if output_format == ImageFormat::Jpeg {
+        image::DynamicImage::ImageRgb8(resized.to_rgb8())
+    } else {
+        resized
+    };
+
+    let mut output = Cursor::new(Vec::new());
+    resized
+        .write_to(&mut output, output_format)
+        .map_err(|e| ImageProxyError::ResizeError(e.to_string()))?;
+
+    Ok(Some((output.into_inner(), output_mime.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_image(width: u32, height: u32) -> Vec<u8> {
+        let img = image::DynamicImage::new_rgba8(width, height);
+        let mut output = Cursor::new(Vec::new());
+        img.write_to(&mut output, ImageFormat::Png).unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_resize_image() {
+        let (resized, mime_type) = resize_image(&png_image(400, 200), "image/png", 100).unwrap().unwrap();
+        assert_eq!(mime_type, "image/png");
+        let img = image::load_from_memory(&resized).unwrap();
+        assert_eq!((img.width(), img.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_no_upscaling() {
+        assert!(resize_image(&png_image(50, 50), "image/png", 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        assert!(matches!(get_image("file:///etc/passwd", None), Err(ImageProxyError::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn test_local_addresses() {
+        for url in ["http://127.0.0.1/cover.jpg", "http://[::1]:8080/cover.jpg", "http://169.254.169.254/latest/meta-data", "http://0.0.0.0/", "http://[::ffff:127.0.0.1]/"] {
+            assert!(matches!(check_target(url), Err(ImageProxyError::LocalAddress(_))), "{}", url);
+        }
+        assert!(check_target("http://192.168.1.10:9000/music/1/cover.jpg").is_ok());
+        assert!(check_target("https://[2001:db8::1]/cover.jpg").is_ok());
+    }
+
+    #[test]
+    fn test_read_image() {
+        assert_eq!(read_image(&[1u8, 2, 3][..], Some(3)).unwrap(), vec![1, 2, 3]);
+        assert!(read_image(&[0u8; 16][..], Some(MAX_IMAGE_BYTES + 1)).is_err());
+        // Stops reading a body without an end
+        assert!(read_image(io::repeat(0), None).is_err());
+    }
+
+    #[test]
+    fn test_normalize_mime_type() {
+        assert_eq!(normalize_mime_type("Image/JPEG; charset=binary"), "image/jpeg");
+    }
+
+    #[test]
+    fn test_allowed_mime_types() {
+        assert!(is_allowed_mime_type("image/jpeg"));
+        assert!(is_allowed_mime_type("image/webp"));
+        assert!(!is_allowed_mime_type("image/svg+xml"));
+        assert!(!is_allowed_mime_type("text/html"));
+    }
+}
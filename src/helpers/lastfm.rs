@@ -67,6 +67,20 @@ impl fmt::Display for LastfmError {
 
 impl Error for LastfmError {}
 
+/// Last.fm error code of a session key that was revoked or expired
+const LASTFM_INVALID_SESSION: i32 = 9;
+
+/// Tell the owner that scrobbling stopped because the session isn't valid anymore
+fn report_session_error(code: i32) {
+    if code == LASTFM_INVALID_SESSION {
+        crate::helpers::notifications::notify(
+            crate::helpers::notifications::NotificationCategory::AuthExpiry,
+            "Last.fm login expired",
+            "Last.fm doesn't accept the session of this device anymore. Log in to Last.fm again to continue scrobbling.",
+        );
+    }
+}
+
 // Auth token response
 #[derive(Debug, Deserialize)]
 struct TokenResponse {
@@ -172,6 +186,44 @@ pub struct LastfmSimilar {
     pub artists: Vec<LastfmSimilarArtist>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct LastfmTopTrack {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub playcount: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastfmTopTracks {
+    #[serde(default, rename = "track")]
+    tracks: Vec<LastfmTopTrack>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastfmTopTracksResponse {
+    toptracks: LastfmTopTracks,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastfmLovedTracksResponse {
+    lovedtracks: LastfmLovedTracks,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastfmLovedTracks {
+    #[serde(default, rename = "track")]
+    tracks: Vec<LovedTrack>,
+    #[serde(rename = "@attr")]
+    attr: LastfmPageAttr,
+}
+
+#[derive(Deserialize, Debug)]
+struct LastfmPageAttr {
+    #[serde(rename = "totalPages")]
+    total_pages: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct LastfmArtistDetails {
     pub name: String,
@@ -544,6 +596,7 @@ impl LastfmClient {
                 if let Ok(error_response) = serde_json::from_str::<LastfmErrorResponse>(&body) {
                     // It's a Last.fm API error (e.g. token not authorized, invalid params)
                     debug!("Last.fm API returned an error: code={}, message='{}'", error_response.error, error_response.message);
+                    report_session_error(error_response.error);
                     return Err(LastfmError::ApiError(error_response.message, error_response.error));
                 }
 
@@ -556,6 +609,7 @@ impl LastfmClient {
                 error!("Last.fm API HTTP error: {} - Body: {}", code, error_body);
                 // Try to parse error_body as LastfmErrorResponse as well, as Last.fm might return structured errors on HTTP error codes
                 if let Ok(error_response) = serde_json::from_str::<LastfmErrorResponse>(&error_body) {
+                     report_session_error(error_response.error);
                      Err(LastfmError::ApiError(error_response.message, error_response.error))
                 } else {
                      Err(LastfmError::NetworkError(format!("HTTP error {} with unparseable body: {}", code, error_body)))
@@ -750,6 +804,39 @@ impl LastfmClient {
         }
     }
 
+    /// Get the most played tracks of an artist from Last.fm
+    ///
+    /// # Arguments
+    /// * `artist` - The artist name.
+    /// * `limit` - Maximum number of tracks.
+    pub fn get_artist_top_tracks(&self, artist: &str, limit: usize) -> Result<Vec<LastfmTopTrack>, LastfmError> {
+        ratelimit::rate_limit("lastfm");
+
+        let limit = limit.to_string();
+        let params = vec![
+            ("method", "artist.getTopTracks"),
+            ("artist", artist),
+            ("limit", limit.as_str()),
+            ("autocorrect", "0"),
+        ];
+
+        debug!("Requesting artist.getTopTracks for artist: {}", artist);
+        let response_body = self.make_api_request(params, false)?;
+
+        match serde_json::from_str::<LastfmTopTracksResponse>(&response_body) {
+            Ok(parsed_response) => Ok(parsed_response.toptracks.tracks),
+            Err(e) => {
+                error!(
+                    "Failed to parse artist.getTopTracks response for artist '{}'. Error: {}, Body: {}",
+                    artist, e, response_body
+                );
+                Err(LastfmError::ParsingError(format!(
+                    "Failed to parse artist.getTopTracks response: {}. Body: {}", e, response_body
+                )))
+            }
+        }
+    }
+
     /// Submit a track scrobble to Last.fm
     /// 
     /// # Arguments
@@ -952,6 +1039,37 @@ impl LastfmClient {
         Ok(())
     }
 
+    /// Get all tracks the user loved on Last.fm
+    pub fn get_loved_tracks(&self) -> Result<Vec<LovedTrack>, LastfmError> {
+        let username = self.get_username()
+            .ok_or_else(|| LastfmError::AuthError("Authentication required to get loved tracks".to_string()))?;
+
+        let mut tracks = Vec::new();
+        let mut page = 1;
+        loop {
+            ratelimit::rate_limit("lastfm");
+            let page_str = page.to_string();
+            let params = vec![
+                ("method", "user.getLovedTracks"),
+                ("user", username.as_str()),
+                ("limit", "200"),
+                ("page", page_str.as_str()),
+            ];
+            let response_body = self.make_api_request(params, false)?;
+            let response: LastfmLovedTracksResponse = serde_json::from_str(&response_body)
+                .map_err(|e| LastfmError::ParsingError(format!("Failed to parse user.getLovedTracks response: {}", e)))?;
+
+            let total_pages = response.lovedtracks.attr.total_pages.parse::<u32>().unwrap_or(1);
+            tracks.extend(response.lovedtracks.tracks);
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+        debug!("Got {} loved tracks for {}", tracks.len(), username);
+        Ok(tracks)
+    }
+
     /// Check if a track is loved on Last.fm
     pub fn is_track_loved(&self, artist: &str, track: &str) -> Result<bool, LastfmError> {
         if !self.is_authenticated() {
@@ -1254,6 +1372,38 @@ impl crate::helpers::favourites::FavouriteProvider for LastfmFavouriteProvider {
         }
     }
 
+    fn capabilities(&self) -> crate::helpers::favourites::ProviderCapabilities {
+        crate::helpers::favourites::ProviderCapabilities {
+            read_only: false,
+            sync: true,
+            lookup_latency: crate::helpers::favourites::LookupLatency::Network,
+            albums_and_artists: false,
+        }
+    }
+
+    /// Copy the loved tracks to the local favourites
+    fn sync(&self) -> Result<usize, crate::helpers::favourites::FavouriteError> {
+        let tracks = LastfmClient::get_instance()
+            .and_then(|client| client.get_loved_tracks())
+            .map_err(|e| match e {
+                LastfmError::AuthError(msg) => crate::helpers::favourites::FavouriteError::AuthError(msg),
+                LastfmError::NetworkError(msg) => crate::helpers::favourites::FavouriteError::NetworkError(msg),
+                LastfmError::ConfigError(msg) => crate::helpers::favourites::FavouriteError::NotConfigured(msg),
+                e => crate::helpers::favourites::FavouriteError::Other(e.to_string()),
+            })?;
+
+        let mut imported = 0;
+        for track in &tracks {
+            if crate::helpers::settingsdb::is_favourite_song(&track.artist.name, &track.name).unwrap_or(false) {
+                continue;
+            }
+            crate::helpers::settingsdb::add_favourite_song(&track.artist.name, &track.name)
+                .map_err(crate::helpers::favourites::FavouriteError::StorageError)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
     fn is_active(&self) -> bool {
         // For Last.fm, active means the user is currently logged in (authenticated)
         // This is the same as is_enabled for now, but conceptually different:
@@ -0,0 +1,364 @@
+use crate::data::{Album, Artist, ArtistMeta};
+use crate::helpers::attributecache;
+use crate::helpers::favourites::{self, FavouriteItem, FavouriteKind};
+use crate::AudioController;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Version of the export format, increased on incompatible changes
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Attribute cache key prefix of the artist metadata
+const METADATA_KEY_PREFIX: &str = "artist::metadata::";
+
+/// Attribute cache key prefix of the MusicBrainz IDs of artists
+const MBID_KEY_PREFIX: &str = "artist::mbid::";
+
+/// Errors of library exports and imports
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Failed to access {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid library export: {0}")]
+    Format(#[from] serde_json::Error),
+
+    #[error("Unsupported export version {0}, this version reads up to {EXPORT_VERSION}")]
+    Version(u32),
+
+    #[error("Unknown export format '{0}', use json or csv")]
+    UnknownFormat(String),
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Everything, can be imported again
+    Json,
+    /// One row per track, e.g. for spreadsheets
+    Csv,
+}
+
+impl ExportFormat {
+    /// The format of a file, CSV for files ending with .csv, JSON otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// The library of one player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerLibrary {
+    pub player_name: String,
+    pub artists: Vec<Artist>,
+    pub albums: Vec<Album>,
+}
+
+/// Libraries of all players with the metadata looked up for them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryExport {
+    pub version: u32,
+    /// Unix timestamp of the export
+    pub exported: u64,
+    pub players: Vec<PlayerLibrary>,
+    /// Metadata of all artists, by artist name
+    #[serde(default)]
+    pub artist_metadata: BTreeMap<String, ArtistMeta>,
+    #[serde(default)]
+    pub favourites: Vec<FavouriteItem>,
+}
+
+/// What an import changed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Artists whose metadata was stored
+    pub artists_imported: usize,
+    /// Artists that already had metadata
+    pub artists_skipped: usize,
+    /// Favourites that were added
+    pub favourites_imported: usize,
+}
+
+/// Export the libraries of all players, their artist metadata and the favourite albums and artists
+///
+/// Players whose library hasn't been loaded yet are left out.
+pub fn export(controller: &AudioController) -> LibraryExport {
+    let mut players = Vec::new();
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        let Some(library) = ctrl.get_library() else {
+            continue;
+        };
+        if !library.is_loaded() {
+            debug!("Not exporting the library of {}, it isn't loaded", ctrl.get_player_name());
+            continue;
+        }
+        players.push(PlayerLibrary {
+            player_name: ctrl.get_player_name(),
+            artists: library.get_artists(),
+            albums: library.get_albums(),
+        });
+    }
+
+    // Metadata of the libraries first, the cache has the metadata of artists that were removed since
+    let mut artist_metadata = cached_artist_metadata();
+    for artist in players.iter().flat_map(|p| &p.artists) {
+        if let Some(meta) = &artist.metadata {
+            artist_metadata.entry(artist.name.clone()).or_insert_with(|| meta.clone());
+        }
+    }
+
+    let mut favourites = favourites::get_favourite_items(FavouriteKind::Artist);
+    favourites.extend(favourites::get_favourite_items(FavouriteKind::Album));
+
+    LibraryExport {
+        version: EXPORT_VERSION,
+        exported: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        players,
+        artist_metadata,
+        favourites,
+    }
+}
+
+/// Artist metadata stored in the attribute cache, by artist name
+fn cached_artist_metadata() -> BTreeMap<String, ArtistMeta> {
+    let keys = match attributecache::list_keys(Some(METADATA_KEY_PREFIX)) {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!("Failed to list cached artist metadata: {}", e);
+            return BTreeMap::new();
+        }
+    };
+    keys.into_iter()
+        .filter_map(|key| {
+            let name = key.strip_prefix(METADATA_KEY_PREFIX)?.to_string();
+            match attributecache::get::<ArtistMeta>(&key) {
+                Ok(meta) => meta.map(|meta| (name, meta)),
+                Err(e) => {
+                    warn!("Failed to read cached metadata of artist {}: {}", name, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Store the artist metadata and favourites of an export
+///
+/// Artists that already have metadata keep it unless `overwrite` is set. The libraries
+/// themselves aren't imported, they are loaded from the players. Metadata is used when
+/// a library loads, so libraries that are loaded already need a refresh.
+pub fn import(export: &LibraryExport, overwrite: bool) -> Result<ImportSummary, ExportError> {
+    if export.version > EXPORT_VERSION {
+        return Err(ExportError::Version(export.version));
+    }
+    let mut summary = ImportSummary::default();
+
+    // Exports of older versions may only have the metadata in the library artists
+    let mut artist_metadata = export.artist_metadata.clone();
+    for artist in export.players.iter().flat_map(|p| &p.artists) {
+        if let Some(meta) = &artist.metadata {
+            artist_metadata.entry(artist.name.clone()).or_insert_with(|| meta.clone());
+        }
+    }
+
+    for (name, meta) in &artist_metadata {
+        let key = format!("{}{}", METADATA_KEY_PREFIX, name);
+        if !overwrite && matches!(attributecache::get::<ArtistMeta>(&key), Ok(Some(_))) {
+            summary.artists_skipped += 1;
+            continue;
+        }
+        if let Err(e) = attributecache::set(&key, meta) {
+            warn!("Failed to import metadata of artist {}: {}", name, e);
+            continue;
+        }
+        if !meta.mbid.is_empty() {
+            if let Err(e) = attributecache::set(&format!("{}{}", MBID_KEY_PREFIX, name), &meta.mbid) {
+                warn!("Failed to import MusicBrainz IDs of artist {}: {}", name, e);
+            }
+        }
+        summary.artists_imported += 1;
+    }
+
+    let existing: Vec<FavouriteItem> = [FavouriteKind::Artist, FavouriteKind::Album]
+        .into_iter()
+        .flat_map(|kind| favourites::favourite_item_set(kind).into_iter())
+        .collect();
+    for item in &export.favourites {
+        if existing.contains(&item.normalized()) {
+            continue;
+        }
+        match favourites::add_favourite_item(item) {
+            Ok(_) => summary.favourites_imported += 1,
+            Err(e) => warn!("Failed to import favourite {}: {}", item, e),
+        }
+    }
+
+    info!(
+        "Imported metadata of {} artists, skipped {}, imported {} favourites",
+        summary.artists_imported, summary.artists_skipped, summary.favourites_imported
+    );
+    Ok(summary)
+}
+
+/// One row per track with the player, album and track details
+pub fn to_csv(export: &LibraryExport) -> String {
+    let mut csv = String::from("player,album_artist,album,release_date,genres,disc,track,title,artist,duration,uri\n");
+    for player in &export.players {
+        for album in &player.albums {
+            let album_artist = album.artists_flat.clone().unwrap_or_else(|| csv_list(&album.artists.lock()));
+            let release_date = album.release_date.map(|d| d.to_string()).unwrap_or_default();
+            let genres = csv_list(&album.genres);
+            for track in album.tracks.lock().iter() {
+                let fields = [
+                    player.player_name.clone(),
+                    album_artist.clone(),
+                    album.name.clone(),
+                    release_date.clone(),
+                    genres.clone(),
+                    track.disc_number.clone().unwrap_or_default(),
+                    track.track_number.map(|n| n.to_string()).unwrap_or_default(),
+                    track.name.clone(),
+                    track.artist.clone().unwrap_or_else(|| album_artist.clone()),
+                    track.duration.map(|d| format!("{:.0}", d)).unwrap_or_default(),
+                    track.uri.clone().unwrap_or_default(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+        }
+    }
+    csv
+}
+
+/// Join the items of a list in one field, items containing a separator are quoted so they stay one item
+fn csv_list(items: &[String]) -> String {
+    items.iter().map(|item| csv_field(item)).collect::<Vec<_>>().join(", ")
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write an export to a file
+pub fn write_export(export: &LibraryExport, path: &Path, format: ExportFormat) -> Result<(), ExportError> {
+    let content = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(export)?,
+        ExportFormat::Csv => to_csv(export),
+    };
+    std::fs::write(path, content).map_err(|e| ExportError::Io(path.display().to_string(), e))
+}
+
+/// Read a JSON export from a file
+pub fn read_export(path: &Path) -> Result<LibraryExport, ExportError> {
+    let content = std::fs::read_to_string(path).map_err(|e| ExportError::Io(path.display().to_string(), e))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Wait until the libraries of all players are loaded, returns false on timeout
+pub fn wait_for_libraries(controller: &AudioController, timeout: Duration) -> bool {
+    let start = Instant::now();
+    loop {
+        let loading = controller
+            .list_controllers()
+            .iter()
+            .filter_map(|ctrl| ctrl.read().get_library())
+            .filter(|library| !library.is_loaded())
+            .count();
+        if loading == 0 {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            warn!("{} libraries are still loading after {}s", loading, timeout.as_secs());
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Identifier, Track};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    fn export() -> LibraryExport {
+        let mut track = Track::new(None, Some(1), "So What".to_string());
+        track.uri = Some("Jazz/Kind of Blue/01.flac".to_string());
+        track.duration = Some(562.4);
+        let album = Album {
+            id: Identifier::Numeric(1),
+            name: "Kind of Blue".to_string(),
+            artists: Arc::new(Mutex::new(vec!["Miles Davis".to_string()])),
+            artists_flat: None,
+            release_date: None,
+            tracks: Arc::new(Mutex::new(vec![track])),
+            cover_art: None,
+            uri: None,
+            genres: vec!["Jazz".to_string(), "Modal, Cool".to_string()],
+        };
+        let mut meta = ArtistMeta::new();
+        meta.add_mbid("561d854a-6a28-4aa7-8c99-323e6ce46c2a".to_string());
+        LibraryExport {
+            version: EXPORT_VERSION,
+            exported: 1760600000,
+            players: vec![PlayerLibrary {
+                player_name: "mpd".to_string(),
+                artists: Vec::new(),
+                albums: vec![album],
+            }],
+            artist_metadata: BTreeMap::from([("Miles Davis".to_string(), meta)]),
+            favourites: vec![FavouriteItem::artist("Miles Davis")],
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = to_csv(&export());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            "mpd,Miles Davis,Kind of Blue,,\"Jazz, \"\"Modal, Cool\"\"\",,1,So What,Miles Davis,562,Jazz/Kind of Blue/01.flac"
+        );
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        assert_eq!(ExportFormat::from_path(&path), ExportFormat::Json);
+        write_export(&export(), &path, ExportFormat::Json).unwrap();
+        let read = read_export(&path).unwrap();
+        assert_eq!(read.players[0].albums[0].tracks.lock()[0].name, "So What");
+        assert_eq!(read.artist_metadata["Miles Davis"].mbid.len(), 1);
+        assert_eq!(read.favourites, vec![FavouriteItem::artist("Miles Davis")]);
+        assert_eq!(ExportFormat::from_path(Path::new("library.CSV")), ExportFormat::Csv);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::data::{Album, Artist};
+use deunicode::deunicode;
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Type of a search result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultKind {
+    Artist,
+    Album,
+    Track,
+}
+
+impl ResultKind {
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind.trim().to_lowercase().as_str() {
+            "artist" | "artists" => Some(ResultKind::Artist),
+            "album" | "albums" => Some(ResultKind::Album),
+            "track" | "tracks" => Some(ResultKind::Track),
+            _ => None,
+        }
+    }
+
+    /// Artists rank before albums and albums before tracks with the same match
+    fn boost(&self) -> f64 {
+        match self {
+            ResultKind::Artist => 3.0,
+            ResultKind::Album => 2.0,
+            ResultKind::Track => 0.0,
+        }
+    }
+}
+
+/// A matching artist, album or track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    #[serde(rename = "type")]
+    pub kind: ResultKind,
+    pub player_name: String,
+    /// Artist or album ID, the URI for tracks
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    /// Album of a track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<String>,
+    pub score: f64,
+}
+
+/// An indexed artist, album or track
+#[derive(Debug, Clone)]
+struct IndexEntry {
+    result: SearchResult,
+    /// Normalized name
+    name: String,
+}
+
+/// Search index of the library of one player
+#[derive(Debug, Default)]
+struct PlayerIndex {
+    entries: Vec<IndexEntry>,
+    /// Normalized words of names, artists and albums with the entries containing them
+    words: BTreeMap<String, Vec<usize>>,
+}
+
+impl PlayerIndex {
+    fn add(&mut self, result: SearchResult) {
+        let index = self.entries.len();
+        let text = [Some(&result.name), result.artist.as_ref(), result.album.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let words: HashSet<String> = words(&text).into_iter().collect();
+        for word in words {
+            self.words.entry(word).or_default().push(index);
+        }
+        self.entries.push(IndexEntry {
+            name: normalize(&result.name),
+            result,
+        });
+    }
+
+    /// Entries containing a word starting with `prefix`
+    fn matching(&self, prefix: &str) -> HashSet<usize> {
+        self.words
+            .range(prefix.to_string()..)
+            .take_while(|(word, _)| word.starts_with(prefix))
+            .flat_map(|(_, entries)| entries.iter().copied())
+            .collect()
+    }
+
+    /// Entries containing all words of the query, the last word may be incomplete
+    fn search(&self, query: &[String]) -> Vec<SearchResult> {
+        let mut candidates: Option<HashSet<usize>> = None;
+        for word in query {
+            let matching = self.matching(word);
+            candidates = Some(match candidates {
+                Some(c) => c.intersection(&matching).copied().collect(),
+                None => matching,
+            });
+        }
+        let query_text = query.join(" ");
+        candidates
+            .unwrap_or_default()
+            .into_iter()
+            .map(|i| {
+                let entry = &self.entries[i];
+                let mut result = entry.result.clone();
+                result.score = score(&entry.name, &query_text, query, result.kind);
+                result
+            })
+            .collect()
+    }
+}
+
+static INDEX: Lazy<RwLock<HashMap<String, PlayerIndex>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Build the search index of a player's library, called when the library has been loaded
+pub fn index_library<'a>(
+    player_name: &str,
+    albums: impl IntoIterator<Item = &'a Album>,
+    artists: impl IntoIterator<Item = &'a Artist>,
+) {
+    let mut index = PlayerIndex::default();
+    for artist in artists {
+        index.add(SearchResult {
+            kind: ResultKind::Artist,
+            player_name: player_name.to_string(),
+            id: artist.id.to_string(),
+            name: artist.name.clone(),
+            artist: None,
+            album: None,
+            album_id: None,
+            score: 0.0,
+        });
+    }
+    for album in albums {
+        let album_artist = album.artists_flat.clone().or_else(|| {
+            let artists = album.artists.lock();
+            (!artists.is_empty()).then(|| artists.join(", "))
+        });
+        let album_id = album.id.to_string();
+        index.add(SearchResult {
+            kind: ResultKind::Album,
+            player_name: player_name.to_string(),
+            id: album_id.clone(),
+            name: album.name.clone(),
+            artist: album_artist.clone(),
+            album: None,
+            album_id: None,
+            score: 0.0,
+        });
+        for track in album.tracks.lock().iter() {
+            let Some(uri) = &track.uri else {
+                continue;
+            };
+            index.add(SearchResult {
+                kind: ResultKind::Track,
+                player_name: player_name.to_string(),
+                id: uri.clone(),
+                name: track.name.clone(),
+                artist: track.artist.clone().or_else(|| album_artist.clone()),
+                album: Some(album.name.clone()),
+                album_id: Some(album_id.clone()),
+                score: 0.0,
+            });
+        }
+    }
+    debug!("Indexed {} library entries of {} for search", index.entries.len(), player_name);
+    INDEX.write().insert(player_name.to_string(), index);
+}
+
+/// Search the libraries of all players, or only of `player_name`
+///
+/// Results are ranked by how well the name matches, best first.
+pub fn search(query: &str, kinds: &[ResultKind], player_name: Option<&str>, limit: usize) -> Vec<SearchResult> {
+    let query = words(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let index = INDEX.read();
+    let mut results: Vec<SearchResult> = index
+        .iter()
+        .filter(|(name, _)| player_name.is_none_or(|p| p == name.as_str()))
+        .flat_map(|(_, player_index)| player_index.search(&query))
+        .filter(|result| kinds.is_empty() || kinds.contains(&result.kind))
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    results.truncate(limit);
+    results
+}
+
+/// Players with a search index
+pub fn indexed_players() -> Vec<String> {
+    let mut players: Vec<String> = INDEX.read().keys().cloned().collect();
+    players.sort();
+    players
+}
+
+fn normalize(text: &str) -> String {
+    words(text).join(" ")
+}
+
+/// Lower case words without accents and punctuation
+fn words(text: &str) -> Vec<String> {
+    deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+/// Rank a match, names equal to the query first, then names starting with it,
+/// then names containing all words, then matches of the artist or album only
+fn score(name: &str, query_text: &str, query: &[String], kind: ResultKind) -> f64 {
+    let name_words: Vec<&str> = name.split(' ').collect();
+    let base = if name == query_text {
+        100.0
+    } else if name.starts_with(query_text) {
+        80.0
+    } else if query.iter().all(|q| name_words.iter().any(|w| w.starts_with(q.as_str()))) {
+        60.0
+    } else {
+        40.0
+    };
+    // Shorter names are closer matches
+    base + kind.boost() - (name.len() as f64 / 100.0).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Identifier, Track};
+
+    fn album(name: &str, artist: &str, tracks: &[&str]) -> Album {
+        let mut album = Album {
+            id: Identifier::Numeric(name.len() as u64),
+            name: name.to_string(),
+            artists: Default::default(),
+            artists_flat: Some(artist.to_string()),
+            release_date: None,
+            tracks: Default::default(),
+            cover_art: None,
+            uri: None,
+            genres: Vec::new(),
+        };
+        let tracks: Vec<Track> = tracks
+            .iter()
+            .map(|t| {
+                let mut track = Track::with_name(t.to_string());
+                track.uri = Some(format!("{}/{}.flac", name, t));
+                track
+            })
+            .collect();
+        album.tracks = std::sync::Arc::new(parking_lot::Mutex::new(tracks));
+        album
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let albums = vec![
+            album("Abbey Road", "The Beatles", &["Come Together", "Something"]),
+            album("Something Else", "The Kinks", &["David Watts"]),
+        ];
+        let artists = vec![Artist {
+            id: Identifier::Numeric(1),
+            name: "The Beatles".to_string(),
+            is_multi: false,
+            metadata: None,
+        }];
+        index_library("test_search_ranking", &albums, &artists);
+        let search = |q: &str, kinds: &[ResultKind]| search(q, kinds, Some("test_search_ranking"), 10);
+
+        let names: Vec<String> = search("something", &[]).into_iter().map(|r| r.name).collect();
+        // "David Watts" is found by its album name and ranks last
+        assert_eq!(names, vec!["Something", "Something Else", "David Watts"]);
+
+        // All words must match, the last one may be incomplete, artists rank first
+        let results = search("beatles abb", &[]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].kind, ResultKind::Album);
+        assert_eq!(search("beatles", &[])[0].kind, ResultKind::Artist);
+
+        // Tracks are found by their album artist
+        let tracks = search("beatles", &[ResultKind::Track]);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].album.as_deref(), Some("Abbey Road"));
+        assert!(search("zeppelin", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("Sigur Rós – Ágætis byrjun"), vec!["sigur", "ros", "agaetis", "byrjun"]);
+        assert_eq!(ResultKind::parse("Albums"), Some(ResultKind::Album));
+    }
+}
@@ -0,0 +1,680 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::config::get_service_config;
+use crate::data::{PlaybackState, PlayerEvent, Song};
+use chrono::{Local, NaiveDate};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Errors of the listening history
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("The listening history is disabled")]
+    Disabled,
+
+    #[error("Listening history database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Failed to create the listening history directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, HistoryError>;
+
+/// Configuration of the `history` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_enable")]
+    pub enable: bool,
+
+    /// SQLite database of the played tracks
+    #[serde(default = "default_dbfile")]
+    pub dbfile: String,
+
+    /// Tracks played for a shorter time, e.g. skipped ones, aren't recorded
+    #[serde(default = "default_min_seconds")]
+    pub min_seconds: u64,
+}
+
+fn default_enable() -> bool {
+    true
+}
+
+fn default_dbfile() -> String {
+    "/var/lib/audiocontrol/history.db".to_string()
+}
+
+fn default_min_seconds() -> u64 {
+    30
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enable: default_enable(),
+            dbfile: default_dbfile(),
+            min_seconds: default_min_seconds(),
+        }
+    }
+}
+
+/// A track that was played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub player_name: String,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub uri: Option<String>,
+    /// Track length in seconds
+    pub duration: Option<f64>,
+    /// Unix timestamp of the start of the track
+    pub started: u64,
+    /// Unix timestamp of the end of the track
+    pub ended: u64,
+    /// Seconds the track was actually playing, pauses don't count
+    pub listened: u64,
+}
+
+/// A played song as used by the daily mixes, the suggestions and the weekly digest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayedSong {
+    pub artist: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    /// Unix timestamp of the start of the track
+    pub timestamp: u64,
+}
+
+impl From<HistoryEntry> for PlayedSong {
+    fn from(entry: HistoryEntry) -> Self {
+        Self {
+            artist: entry.artist,
+            title: entry.title,
+            album: entry.album,
+            timestamp: entry.started,
+        }
+    }
+}
+
+/// What a chart ranks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    Artists,
+    Albums,
+    Tracks,
+}
+
+impl std::str::FromStr for ChartKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "artists" => Ok(ChartKind::Artists),
+            "albums" => Ok(ChartKind::Albums),
+            "tracks" => Ok(ChartKind::Tracks),
+            _ => Err(format!("Unknown chart '{}', use artists, albums or tracks", s)),
+        }
+    }
+}
+
+/// Number of plays of an artist, album or track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayCount {
+    /// The album artist for albums
+    pub artist: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub plays: u64,
+    /// Seconds listened in total
+    pub listened: u64,
+    /// Unix timestamp of the end of the last play
+    pub last_played: u64,
+}
+
+/// Played tracks stored in SQLite
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    /// Open the database, it is created if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::setup(Connection::open(path)?)
+    }
+
+    /// A database that is lost when the store is dropped
+    pub fn open_in_memory() -> Result<Self> {
+        Self::setup(Connection::open_in_memory()?)
+    }
+
+    fn setup(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS plays (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                player_name TEXT NOT NULL,
+                artist TEXT NOT NULL,
+                title TEXT NOT NULL,
+                album TEXT,
+                album_artist TEXT,
+                uri TEXT,
+                duration REAL,
+                started INTEGER NOT NULL,
+                ended INTEGER NOT NULL,
+                listened INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS plays_started ON plays (started);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Store a played track, returns its id
+    pub fn add(&self, entry: &HistoryEntry) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO plays (player_name, artist, title, album, album_artist, uri, duration, started, ended, listened)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                entry.player_name,
+                entry.artist,
+                entry.title,
+                entry.album,
+                entry.album_artist,
+                entry.uri,
+                entry.duration,
+                entry.started as i64,
+                entry.ended as i64,
+                entry.listened as i64
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Played tracks, the last one first, optionally of a single player
+    pub fn recent(&self, limit: usize, offset: usize, player_name: Option<&str>) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, player_name, artist, title, album, album_artist, uri, duration, started, ended, listened
+             FROM plays WHERE ?1 IS NULL OR player_name = ?1
+             ORDER BY ended DESC, id DESC LIMIT ?2 OFFSET ?3",
+        )?;
+        let entries = stmt
+            .query_map(params![player_name, limit as i64, offset as i64], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Tracks that started between two Unix timestamps, the end excluded, oldest first
+    pub fn between(&self, from: u64, to: u64) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, player_name, artist, title, album, album_artist, uri, duration, started, ended, listened
+             FROM plays WHERE started >= ?1 AND started < ?2 ORDER BY started, id",
+        )?;
+        let entries = stmt
+            .query_map(params![from as i64, to.min(i64::MAX as u64) as i64], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Local days with plays, oldest first
+    pub fn days(&self) -> Result<Vec<NaiveDate>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT date(started, 'unixepoch', 'localtime') FROM plays ORDER BY 1")?;
+        let days = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(days.iter().filter_map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()).collect())
+    }
+
+    /// Most played artists, albums or tracks since a Unix timestamp, most plays first
+    ///
+    /// Names are compared without regard to case. Without a limit all are returned.
+    pub fn chart(&self, kind: ChartKind, since: u64, limit: Option<usize>) -> Result<Vec<PlayCount>> {
+        let (columns, filter, group) = match kind {
+            ChartKind::Artists => ("MAX(artist), NULL, NULL", "", "lower(artist)"),
+            ChartKind::Albums => (
+                "MAX(COALESCE(album_artist, artist)), MAX(album), NULL",
+                "AND album IS NOT NULL AND album != ''",
+                "lower(COALESCE(album_artist, artist)), lower(album)",
+            ),
+            ChartKind::Tracks => ("MAX(artist), NULL, MAX(title)", "", "lower(artist), lower(title)"),
+        };
+        let sql = format!(
+            "SELECT {}, COUNT(*), SUM(listened), MAX(ended) FROM plays WHERE started >= ?1 {}
+             GROUP BY {} ORDER BY COUNT(*) DESC, MAX(ended) DESC LIMIT ?2",
+            columns, filter, group
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        // A negative limit is no limit in SQLite
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let counts = stmt
+            .query_map(params![since as i64, limit], |row| {
+                Ok(PlayCount {
+                    artist: row.get(0)?,
+                    album: row.get(1)?,
+                    title: row.get(2)?,
+                    plays: row.get::<_, i64>(3)? as u64,
+                    listened: row.get::<_, i64>(4)? as u64,
+                    last_played: row.get::<_, i64>(5)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(counts)
+    }
+
+    /// Number of stored plays
+    pub fn count(&self) -> Result<u64> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM plays", [], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        player_name: row.get(1)?,
+        artist: row.get(2)?,
+        title: row.get(3)?,
+        album: row.get(4)?,
+        album_artist: row.get(5)?,
+        uri: row.get(6)?,
+        duration: row.get(7)?,
+        started: row.get::<_, i64>(8)? as u64,
+        ended: row.get::<_, i64>(9)? as u64,
+        listened: row.get::<_, i64>(10)? as u64,
+    })
+}
+
+static STORE: Lazy<Mutex<Option<HistoryStore>>> = Lazy::new(|| Mutex::new(None));
+
+/// Run a function with the global store
+pub fn with_store<T>(f: impl FnOnce(&HistoryStore) -> Result<T>) -> Result<T> {
+    match STORE.lock().as_ref() {
+        Some(store) => f(store),
+        None => Err(HistoryError::Disabled),
+    }
+}
+
+/// A store with plays of 300s of the given artist, title and start time
+#[cfg(test)]
+pub(crate) fn seeded_store(plays: &[(&str, &str, u64)]) -> HistoryStore {
+    let store = HistoryStore::open_in_memory().unwrap();
+    for (artist, title, started) in plays {
+        store
+            .add(&HistoryEntry {
+                id: 0,
+                player_name: "mpd".to_string(),
+                artist: artist.to_string(),
+                title: title.to_string(),
+                album: None,
+                album_artist: None,
+                uri: None,
+                duration: Some(300.0),
+                started: *started,
+                ended: started + 300,
+                listened: 300,
+            })
+            .unwrap();
+    }
+    store
+}
+
+/// Run a test with the store as global store, tests using it run one at a time
+#[cfg(test)]
+pub(crate) fn with_test_store<T>(store: HistoryStore, f: impl FnOnce() -> T) -> T {
+    static TEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    let _guard = TEST_LOCK.lock();
+    *STORE.lock() = Some(store);
+    let result = f();
+    *STORE.lock() = None;
+    result
+}
+
+/// Read from the global store, nothing if the history is disabled or can't be read
+fn read<T: Default>(f: impl FnOnce(&HistoryStore) -> Result<T>) -> T {
+    match with_store(f) {
+        Ok(value) => value,
+        Err(HistoryError::Disabled) => T::default(),
+        Err(e) => {
+            warn!("Failed to read the listening history: {}", e);
+            T::default()
+        }
+    }
+}
+
+/// Plays since the given Unix timestamp, oldest first
+pub fn get_plays_since(since: u64) -> Vec<PlayedSong> {
+    read(|store| store.between(since, u64::MAX)).into_iter().map(PlayedSong::from).collect()
+}
+
+/// Plays of a single local day, oldest first
+pub fn get_plays_on(day: NaiveDate) -> Vec<PlayedSong> {
+    let (Some(from), Some(to)) = (local_midnight(day), day.succ_opt().and_then(local_midnight)) else {
+        return Vec::new();
+    };
+    read(|store| store.between(from, to)).into_iter().map(PlayedSong::from).collect()
+}
+
+/// Local days with plays, oldest first
+pub fn get_days() -> Vec<NaiveDate> {
+    read(|store| store.days())
+}
+
+/// Number of plays per artist, artist names in lower case
+pub fn artist_play_counts(plays: &[PlayedSong]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for play in plays {
+        *counts.entry(play.artist.to_lowercase()).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn local_midnight(day: NaiveDate) -> Option<u64> {
+    let midnight = day.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest()?;
+    Some(midnight.timestamp().max(0) as u64)
+}
+
+/// Plays were stored per local day in the settings database before, e.g. "play_history::2025-06-01"
+const LEGACY_KEY_PREFIX: &str = "play_history::";
+
+/// Move plays from the settings database to the store
+///
+/// They were recorded when a song started, so the time listened is unknown.
+fn import_legacy_plays(store: &HistoryStore) {
+    let keys: Vec<String> = crate::helpers::settingsdb::get_all_keys()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|key| key.starts_with(LEGACY_KEY_PREFIX))
+        .collect();
+    let mut imported = 0;
+    for key in keys {
+        let plays = match crate::helpers::settingsdb::get::<Vec<PlayedSong>>(&key) {
+            Ok(plays) => plays.unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to read play history {}: {}", key, e);
+                continue;
+            }
+        };
+        let result = plays.iter().try_for_each(|play| {
+            store
+                .add(&HistoryEntry {
+                    id: 0,
+                    player_name: String::new(),
+                    artist: play.artist.clone(),
+                    title: play.title.clone(),
+                    album: play.album.clone(),
+                    album_artist: None,
+                    uri: None,
+                    duration: None,
+                    started: play.timestamp,
+                    ended: play.timestamp,
+                    listened: 0,
+                })
+                .map(|_| ())
+        });
+        match result {
+            Ok(()) => {
+                imported += plays.len();
+                if let Err(e) = crate::helpers::settingsdb::remove(&key) {
+                    warn!("Failed to remove play history {}: {}", key, e);
+                }
+            }
+            Err(e) => warn!("Failed to import play history {}: {}", key, e),
+        }
+    }
+    if imported > 0 {
+        info!("Imported {} plays from the settings database into the listening history", imported);
+    }
+}
+
+/// A track a player is playing
+struct CurrentPlay {
+    player_name: String,
+    song: Song,
+    started: u64,
+    /// Playing time before the last pause
+    listened: Duration,
+    /// Start of the current playing period
+    playing_since: Option<Instant>,
+}
+
+impl CurrentPlay {
+    fn listened(&self, now: Instant) -> Duration {
+        self.listened + self.playing_since.map(|since| now.duration_since(since)).unwrap_or_default()
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.listened = self.listened(now);
+        self.playing_since = None;
+    }
+}
+
+#[derive(Default)]
+struct TrackedPlayer {
+    playing: bool,
+    current: Option<CurrentPlay>,
+}
+
+/// Follows the events of all players and decides which tracks have been played
+struct HistoryTracker {
+    min_seconds: u64,
+    players: HashMap<String, TrackedPlayer>,
+}
+
+impl HistoryTracker {
+    fn new(min_seconds: u64) -> Self {
+        Self {
+            min_seconds,
+            players: HashMap::new(),
+        }
+    }
+
+    /// Handle an event, returns the track that finished playing if it counts as played
+    ///
+    /// `timestamp` is the Unix time of `now`.
+    fn handle(&mut self, event: &PlayerEvent, now: Instant, timestamp: u64) -> Option<HistoryEntry> {
+        let min_seconds = self.min_seconds;
+        match event {
+            PlayerEvent::SongChanged { source, song } => {
+                let player = self.players.entry(source.player_id().to_string()).or_default();
+                let song = song.as_ref().filter(|s| !crate::helpers::songdedup::is_duplicate(s));
+                if let (Some(current), Some(song)) = (&player.current, song) {
+                    // Players report the same song again, e.g. when metadata is updated
+                    if current.song.artist == song.artist && current.song.title == song.title {
+                        return None;
+                    }
+                }
+                let finished = player.current.take().and_then(|play| finish(play, min_seconds, now, timestamp));
+                player.current = song.filter(|s| s.artist.is_some() && s.title.is_some()).map(|song| CurrentPlay {
+                    player_name: source.player_name().to_string(),
+                    song: song.clone(),
+                    started: timestamp,
+                    listened: Duration::ZERO,
+                    playing_since: player.playing.then_some(now),
+                });
+                finished
+            }
+            PlayerEvent::StateChanged { source, state } => {
+                let player = self.players.entry(source.player_id().to_string()).or_default();
+                player.playing = *state == PlaybackState::Playing;
+                match (&mut player.current, state) {
+                    (Some(current), PlaybackState::Playing) => {
+                        current.playing_since.get_or_insert(now);
+                        None
+                    }
+                    (Some(_), PlaybackState::Stopped) => {
+                        player.current.take().and_then(|play| finish(play, min_seconds, now, timestamp))
+                    }
+                    (Some(current), _) => {
+                        current.pause(now);
+                        None
+                    }
+                    (None, _) => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The history entry of a play that ended, None if it was too short
+fn finish(play: CurrentPlay, min_seconds: u64, now: Instant, timestamp: u64) -> Option<HistoryEntry> {
+    let listened = play.listened(now).as_secs();
+    if listened < min_seconds {
+        debug!("Not recording {}, only played for {}s", play.song, listened);
+        return None;
+    }
+    let song = play.song;
+    Some(HistoryEntry {
+        id: 0,
+        player_name: play.player_name,
+        artist: song.artist.unwrap_or_default(),
+        title: song.title.unwrap_or_default(),
+        album: song.album,
+        album_artist: song.album_artist,
+        uri: song.stream_url,
+        duration: song.duration,
+        started: play.started,
+        ended: timestamp,
+        listened,
+    })
+}
+
+/// Open the listening history from the `history` service configuration and record played tracks
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let config = match get_service_config(config, "history") {
+        Some(c) => serde_json::from_value::<HistoryConfig>(c.clone()).unwrap_or_else(|e| {
+            warn!("Invalid history configuration, using defaults: {}", e);
+            HistoryConfig::default()
+        }),
+        None => HistoryConfig::default(),
+    };
+    if !config.enable {
+        debug!("Listening history is disabled");
+        return;
+    }
+    match HistoryStore::open(Path::new(&config.dbfile)) {
+        Ok(store) => {
+            import_legacy_plays(&store);
+            *STORE.lock() = Some(store);
+        }
+        Err(e) => {
+            warn!("Failed to open listening history {}: {}", config.dbfile, e);
+            return;
+        }
+    }
+    info!("Recording tracks played for at least {}s in {}", config.min_seconds, config.dbfile);
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::StateChanged, EventSubscription::SongChanged]);
+    let mut tracker = HistoryTracker::new(config.min_seconds);
+    bus.spawn_worker(id, receiver, move |event| {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        if let Some(entry) = tracker.handle(&event, Instant::now(), timestamp) {
+            match with_store(|store| store.add(&entry)) {
+                Ok(_) => debug!("Recorded '{}' by '{}' on {}", entry.title, entry.artist, entry.player_name),
+                Err(e) => warn!("Failed to record '{}' by '{}': {}", entry.title, entry.artist, e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::PlayerSource;
+
+    fn entry(artist: &str, title: &str, album: Option<&str>, ended: u64) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            player_name: "mpd".to_string(),
+            artist: artist.to_string(),
+            title: title.to_string(),
+            album: album.map(|a| a.to_string()),
+            album_artist: None,
+            uri: None,
+            duration: Some(300.0),
+            started: ended - 300,
+            ended,
+            listened: 300,
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        store.add(&entry("Miles Davis", "So What", Some("Kind of Blue"), 1000)).unwrap();
+        store.add(&entry("Miles Davis", "Blue in Green", Some("Kind of Blue"), 2000)).unwrap();
+        store.add(&entry("John Coltrane", "Naima", None, 3000)).unwrap();
+        store.add(&entry("miles davis", "So What", Some("Kind of Blue"), 4000)).unwrap();
+        assert_eq!(store.count().unwrap(), 4);
+
+        let recent = store.recent(2, 0, None).unwrap();
+        assert_eq!(recent.iter().map(|e| e.ended).collect::<Vec<_>>(), vec![4000, 3000]);
+        assert_eq!(store.recent(10, 0, Some("spotify")).unwrap().len(), 0);
+
+        let artists = store.chart(ChartKind::Artists, 0, None).unwrap();
+        assert_eq!(artists.len(), 2);
+        assert_eq!((artists[0].plays, artists[0].listened, artists[0].last_played), (3, 900, 4000));
+        let tracks = store.chart(ChartKind::Tracks, 0, Some(1)).unwrap();
+        assert_eq!(tracks[0].title.as_deref(), Some("So What"));
+        assert_eq!(tracks[0].plays, 2);
+        let albums = store.chart(ChartKind::Albums, 1000, None).unwrap();
+        assert_eq!(albums.len(), 1);
+        assert_eq!((albums[0].album.as_deref(), albums[0].plays), (Some("Kind of Blue"), 2));
+
+        let plays = store.between(1000, 3700).unwrap();
+        assert_eq!(plays.iter().map(|e| e.started).collect::<Vec<_>>(), vec![1700, 2700]);
+        assert_eq!(PlayedSong::from(plays[0].clone()).timestamp, 1700);
+        let mut days: Vec<NaiveDate> = [700, 1700, 2700, 3700]
+            .iter()
+            .map(|t| chrono::DateTime::from_timestamp(*t, 0).unwrap().with_timezone(&Local).date_naive())
+            .collect();
+        days.dedup();
+        assert_eq!(store.days().unwrap(), days);
+    }
+
+    #[test]
+    fn test_tracker() {
+        let source = PlayerSource::new("mpd".to_string(), "mpd".to_string());
+        let song = |title: &str| Song {
+            artist: Some("Miles Davis".to_string()),
+            title: Some(title.to_string()),
+            ..Default::default()
+        };
+        let song_changed = |title: &str| PlayerEvent::SongChanged { source: source.clone(), song: Some(song(title)) };
+        let state_changed = |state| PlayerEvent::StateChanged { source: source.clone(), state };
+        let mut tracker = HistoryTracker::new(30);
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        assert_eq!(tracker.handle(&state_changed(PlaybackState::Playing), at(0), 0), None);
+        assert_eq!(tracker.handle(&song_changed("So What"), at(0), 0), None);
+        // A pause doesn't count as listening time
+        tracker.handle(&state_changed(PlaybackState::Paused), at(100), 100);
+        tracker.handle(&state_changed(PlaybackState::Playing), at(500), 500);
+        assert_eq!(tracker.handle(&song_changed("So What"), at(520), 520), None);
+        let played = tracker.handle(&song_changed("Freddie Freeloader"), at(600), 600).unwrap();
+        assert_eq!((played.title.as_str(), played.started, played.ended, played.listened), ("So What", 0, 600, 200));
+
+        // Skipped after 10 seconds
+        assert_eq!(tracker.handle(&song_changed("Blue in Green"), at(610), 610), None);
+        let played = tracker.handle(&state_changed(PlaybackState::Stopped), at(700), 700).unwrap();
+        assert_eq!((played.title.as_str(), played.listened), ("Blue in Green", 90));
+        assert_eq!(tracker.handle(&state_changed(PlaybackState::Stopped), at(800), 800), None);
+    }
+}
@@ -3,6 +3,19 @@ use std::fs::File;
 use std::io::{Read, Write};
 use log::debug;
 
+/// File names of cover images in album directories, in order of preference
+pub const STANDARD_COVER_FILES: [&str; 8] = ["cover.jpg", "cover.png", "folder.jpg", "folder.png", "album.jpg", "album.png", "front.jpg", "front.png"];
+
+/// Find an existing cover image file in a directory
+///
+/// Returns the file name of the first standard cover file that exists
+pub fn find_cover_file(dir_path: &str) -> Option<&'static str> {
+    STANDARD_COVER_FILES
+        .iter()
+        .find(|name| Path::new(dir_path).join(name).is_file())
+        .copied()
+}
+
 /// Extracts cover art from music files in a directory
 pub fn extract_cover_from_music_files(dir_path: &str) -> Option<(Vec<u8>, String)> {
     use walkdir::WalkDir;
@@ -79,9 +92,7 @@ pub fn extract_cover_from_music_files(dir_path: &str) -> Option<(Vec<u8>, String
 
     debug!("No embedded cover art found in {} audio files, checking for standard cover files", audio_file_count);
     // Also check for standard cover files in the directory
-    let standard_covers = ["cover.jpg", "cover.png", "folder.jpg", "folder.png", "album.jpg", "album.png", "front.jpg", "front.png"];
-    
-    for cover_name in standard_covers.iter() {
+    for cover_name in STANDARD_COVER_FILES.iter() {
         let cover_path = format!("{}/{}", dir_path, cover_name);
         let path = Path::new(&cover_path);
         
@@ -241,6 +252,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_cover_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().to_string_lossy().to_string();
+        assert_eq!(find_cover_file(&dir_path), None);
+
+        fs::write(dir.path().join("folder.png"), b"png").unwrap();
+        assert_eq!(find_cover_file(&dir_path), Some("folder.png"));
+
+        fs::write(dir.path().join("cover.jpg"), b"jpg").unwrap();
+        assert_eq!(find_cover_file(&dir_path), Some("cover.jpg"));
+    }
+
     #[test]
     fn test_extract_cover_from_standard_files() {
         let test_path = get_test_data_path();
@@ -0,0 +1,233 @@
+use crate::config::get_service_config;
+use chrono::{Datelike, NaiveDate};
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Language of display strings in API responses
+///
+/// The raw values (timestamps, seconds) stay in the responses, the localized
+/// strings are added next to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    /// Parse a language code or locale name, e.g. "de", "de_DE" or "de-DE.UTF-8"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or_default().to_lowercase();
+        match code.as_str() {
+            "en" | "c" | "posix" => Ok(Language::En),
+            "de" => Ok(Language::De),
+            "fr" => Ok(Language::Fr),
+            _ => Err(format!("Unsupported locale '{}'", s)),
+        }
+    }
+}
+
+/// Words of a language
+struct Words {
+    never: &'static str,
+    just_now: &'static str,
+    /// Relative time, {} is replaced by the amount and unit
+    ago: &'static str,
+    /// (singular, plural) of seconds, minutes, hours, days, months and years
+    units: [(&'static str, &'static str); 6],
+    /// Abbreviated units for durations
+    short_units: [&'static str; 3],
+    months: [&'static str; 12],
+}
+
+static ENGLISH: Words = Words {
+    never: "never",
+    just_now: "just now",
+    ago: "{} ago",
+    units: [
+        ("second", "seconds"), ("minute", "minutes"), ("hour", "hours"),
+        ("day", "days"), ("month", "months"), ("year", "years"),
+    ],
+    short_units: ["h", "min", "s"],
+    months: [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December",
+    ],
+};
+
+static GERMAN: Words = Words {
+    never: "nie",
+    just_now: "gerade eben",
+    ago: "vor {}",
+    // Dative plural after "vor"
+    units: [
+        ("Sekunde", "Sekunden"), ("Minute", "Minuten"), ("Stunde", "Stunden"),
+        ("Tag", "Tagen"), ("Monat", "Monaten"), ("Jahr", "Jahren"),
+    ],
+    short_units: ["Std.", "Min.", "Sek."],
+    months: [
+        "Januar", "Februar", "März", "April", "Mai", "Juni",
+        "Juli", "August", "September", "Oktober", "November", "Dezember",
+    ],
+};
+
+static FRENCH: Words = Words {
+    never: "jamais",
+    just_now: "à l'instant",
+    ago: "il y a {}",
+    units: [
+        ("seconde", "secondes"), ("minute", "minutes"), ("heure", "heures"),
+        ("jour", "jours"), ("mois", "mois"), ("an", "ans"),
+    ],
+    short_units: ["h", "min", "s"],
+    months: [
+        "janvier", "février", "mars", "avril", "mai", "juin",
+        "juillet", "août", "septembre", "octobre", "novembre", "décembre",
+    ],
+};
+
+static LANGUAGE: Lazy<RwLock<Language>> = Lazy::new(|| RwLock::new(Language::default()));
+
+impl Language {
+    /// ISO 639-1 code, e.g. "de"
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Fr => "fr",
+        }
+    }
+
+    fn words(self) -> &'static Words {
+        match self {
+            Language::En => &ENGLISH,
+            Language::De => &GERMAN,
+            Language::Fr => &FRENCH,
+        }
+    }
+
+    /// "3 minutes ago" for a time that many seconds in the past
+    pub fn relative_time(self, seconds_ago: u64) -> String {
+        let words = self.words();
+        if seconds_ago < 10 {
+            return words.just_now.to_string();
+        }
+        const DAY: u64 = 86400;
+        let (amount, unit) = match seconds_ago {
+            s if s < 60 => (s, 0),
+            s if s < 3600 => (s / 60, 1),
+            s if s < DAY => (s / 3600, 2),
+            s if s < 30 * DAY => (s / DAY, 3),
+            s if s < 365 * DAY => (s / (30 * DAY), 4),
+            s => (s / (365 * DAY), 5),
+        };
+        let (singular, plural) = words.units[unit];
+        let unit = if amount == 1 { singular } else { plural };
+        words.ago.replace("{}", &format!("{} {}", amount, unit))
+    }
+
+    /// Relative time of a Unix timestamp, "never" for None
+    pub fn relative_timestamp(self, timestamp: Option<u64>) -> String {
+        match timestamp {
+            Some(timestamp) => self.relative_time(now().saturating_sub(timestamp)),
+            None => self.words().never.to_string(),
+        }
+    }
+
+    /// Duration with the two largest units, e.g. "1 h 5 min" or "3 min 20 s"
+    pub fn format_duration(self, seconds: u64) -> String {
+        let [h, min, s] = self.words().short_units;
+        let (hours, minutes, secs) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+        if hours > 0 {
+            format!("{} {} {} {}", hours, h, minutes, min)
+        } else if minutes > 0 {
+            format!("{} {} {} {}", minutes, min, secs, s)
+        } else {
+            format!("{} {}", secs, s)
+        }
+    }
+
+    /// Long date, e.g. "March 5, 2024", "5. März 2024" or "5 mars 2024"
+    pub fn format_date(self, date: NaiveDate) -> String {
+        let month = self.words().months[date.month0() as usize];
+        match self {
+            Language::En => format!("{} {}, {}", month, date.day(), date.year()),
+            Language::De => format!("{}. {} {}", date.day(), month, date.year()),
+            Language::Fr => format!("{} {} {}", date.day(), month, date.year()),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Read the `locale` service configuration, e.g. `"locale": { "language": "de" }`
+///
+/// Without configuration the LANG environment variable is used, if it names a
+/// supported language.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let configured = get_service_config(config, "locale")
+        .and_then(|c| c.get("language"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let language = match &configured {
+        Some(name) => name.parse().unwrap_or_else(|e| {
+            warn!("{}, using English", e);
+            Language::En
+        }),
+        None => std::env::var("LANG").ok().and_then(|lang| lang.parse().ok()).unwrap_or_default(),
+    };
+    info!("Display strings use language {:?}", language);
+    set_language(language);
+}
+
+pub fn set_language(language: Language) {
+    *LANGUAGE.write() = language;
+}
+
+/// Language of the display strings
+pub fn language() -> Language {
+    *LANGUAGE.read()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_time() {
+        assert_eq!(Language::En.relative_time(5), "just now");
+        assert_eq!(Language::En.relative_time(60), "1 minute ago");
+        assert_eq!(Language::En.relative_time(180), "3 minutes ago");
+        assert_eq!(Language::De.relative_time(7200), "vor 2 Stunden");
+        assert_eq!(Language::De.relative_time(86400 * 3), "vor 3 Tagen");
+        assert_eq!(Language::Fr.relative_time(45), "il y a 45 secondes");
+        assert_eq!(Language::En.relative_time(86400 * 240), "8 months ago");
+        assert_eq!(Language::De.relative_time(86400 * 400), "vor 1 Jahr");
+        assert_eq!(Language::De.relative_timestamp(None), "nie");
+    }
+
+    #[test]
+    fn test_format_duration_and_date() {
+        assert_eq!(Language::En.format_duration(3900), "1 h 5 min");
+        assert_eq!(Language::De.format_duration(200), "3 Min. 20 Sek.");
+        assert_eq!(Language::Fr.format_duration(42), "42 s");
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert_eq!(Language::En.format_date(date), "March 5, 2024");
+        assert_eq!(Language::De.format_date(date), "5. März 2024");
+        assert_eq!(Language::Fr.format_date(date), "5 mars 2024");
+
+        assert_eq!("de_DE.UTF-8".parse::<Language>(), Ok(Language::De));
+        assert!("xx".parse::<Language>().is_err());
+    }
+}
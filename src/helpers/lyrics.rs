@@ -294,6 +294,13 @@ pub fn parse_lrc_content(content: &str) -> LyricsResult<Vec<TimedLyric>> {
     Ok(timed_lyrics)
 }
 
+/// Index of the line sung at `position` seconds, None before the first line
+///
+/// The lines must be sorted by timestamp, as returned by `parse_lrc_content`.
+pub fn line_at(lyrics: &[TimedLyric], position: f64) -> Option<usize> {
+    lyrics.partition_point(|lyric| lyric.timestamp <= position).checked_sub(1)
+}
+
 /// MPD-specific lyrics provider that looks for .lrc files alongside music files
 pub struct MPDLyricsProvider {
     /// MPD music directory path
@@ -765,4 +772,17 @@ mod tests {
             assert_eq!(lyric.format_timestamp(), expected, "Failed for timestamp {}", timestamp);
         }
     }
+    #[test]
+    fn test_line_at() {
+        let lyrics = vec![
+            TimedLyric::new(5.0, "first".to_string()),
+            TimedLyric::new(10.0, "second".to_string()),
+            TimedLyric::new(15.5, "third".to_string()),
+        ];
+        assert_eq!(line_at(&lyrics, 0.0), None);
+        assert_eq!(line_at(&lyrics, 5.0), Some(0));
+        assert_eq!(line_at(&lyrics, 15.4), Some(1));
+        assert_eq!(line_at(&lyrics, 300.0), Some(2));
+        assert_eq!(line_at(&[], 10.0), None);
+    }
 }
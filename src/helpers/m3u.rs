@@ -38,6 +38,10 @@ pub struct M3UEntry {
     
     /// Optional additional info from #EXTINF directive
     pub info: Option<String>,
+
+    /// Optional artist from #EXTART directive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
 }
 
 /// Represents a parsed M3U playlist
@@ -55,8 +59,14 @@ pub struct M3UPlaylist {
 
 /// M3U Parser with HTTP download capability
 pub struct M3UParser {
-    /// HTTP client for downloading playlists
-    client: reqwest::blocking::Client,
+    /// HTTP client for downloading playlists, created for the first download
+    ///
+    /// A blocking client can't be dropped in an async context, parsing content
+    /// must work there.
+    client: once_cell::sync::OnceCell<reqwest::blocking::Client>,
+
+    /// Timeout of downloads
+    timeout: Duration,
 }
 
 impl Default for M3UParser {
@@ -68,24 +78,25 @@ impl Default for M3UParser {
 impl M3UParser {
     /// Create a new M3U parser with default HTTP client settings
     pub fn new() -> Self {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("HiFiBerry-AudioControl/0.6.7")
-            .build()
-            .expect("Failed to create HTTP client");
-            
-        Self { client }
+        Self::with_timeout(30)
     }
     
     /// Create a new M3U parser with custom timeout
     pub fn with_timeout(timeout_secs: u64) -> Self {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
-            .user_agent("HiFiBerry-AudioControl/0.6.7")
-            .build()
-            .expect("Failed to create HTTP client");
-            
-        Self { client }
+        Self {
+            client: once_cell::sync::OnceCell::new(),
+            timeout: Duration::from_secs(timeout_secs),
+        }
+    }
+
+    fn client(&self) -> &reqwest::blocking::Client {
+        self.client.get_or_init(|| {
+            reqwest::blocking::Client::builder()
+                .timeout(self.timeout)
+                .user_agent("HiFiBerry-AudioControl/0.6.7")
+                .build()
+                .expect("Failed to create HTTP client")
+        })
     }
     
     /// Parse an M3U playlist from a URL
@@ -106,7 +117,7 @@ impl M3UParser {
         }
         
         // Download the playlist content
-        let response = self.client.get(url).send()?.error_for_status()?;
+        let response = self.client().get(url).send()?.error_for_status()?;
         
         let content = response.text()?;
         debug!("Downloaded {} bytes of playlist content", content.len());
@@ -136,6 +147,7 @@ impl M3UParser {
         let mut entries = Vec::new();
         let mut is_extended = false;
         let mut current_extinf: Option<(Option<f64>, Option<String>)> = None;
+        let mut current_artist: Option<String> = None;
         
         for (line_num, line) in lines.iter().enumerate() {
             let trimmed = line.trim();
@@ -156,6 +168,8 @@ impl M3UParser {
                     if current_extinf.is_some() {
                         debug!("Parsed EXTINF directive on line {}", line_num + 1);
                     }
+                } else if let Some(artist) = trimmed.strip_prefix("#EXTART:") {
+                    current_artist = Some(artist.trim().to_string()).filter(|a| !a.is_empty());
                 }
                 // Skip other comments and directives
                 continue;
@@ -171,6 +185,7 @@ impl M3UParser {
                     title,
                     duration,
                     info: None,
+                    artist: current_artist.take(),
                 }
             } else {
                 M3UEntry {
@@ -178,6 +193,7 @@ impl M3UParser {
                     title: None,
                     duration: None,
                     info: None,
+                    artist: current_artist.take(),
                 }
             };
             
@@ -295,6 +311,26 @@ impl M3UParser {
     }
 }
 
+/// Write entries as an extended M3U playlist
+///
+/// Titles and durations are written as #EXTINF, artists as #EXTART directives.
+pub fn write_m3u(entries: &[M3UEntry]) -> String {
+    let single_line = |s: &str| s.replace(['\r', '\n'], " ");
+    let mut content = String::from("#EXTM3U\n");
+    for entry in entries {
+        if entry.title.is_some() || entry.duration.is_some() {
+            let duration = entry.duration.map(|d| d.round() as i64).unwrap_or(-1);
+            content.push_str(&format!("#EXTINF:{},{}\n", duration, single_line(entry.title.as_deref().unwrap_or_default())));
+        }
+        if let Some(artist) = &entry.artist {
+            content.push_str(&format!("#EXTART:{}\n", single_line(artist)));
+        }
+        content.push_str(&single_line(&entry.url));
+        content.push('\n');
+    }
+    content
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -451,6 +487,33 @@ http://example.com/song2.mp3"#;
         assert!(!parser.is_valid_url("relative/path"));
     }
 
+    #[test]
+    fn test_write_m3u() {
+        let entries = vec![
+            M3UEntry {
+                url: "Jazz/So What.flac".to_string(),
+                title: Some("So What".to_string()),
+                duration: Some(562.4),
+                info: None,
+                artist: Some("Miles Davis".to_string()),
+            },
+            M3UEntry {
+                url: "http://example.com/stream".to_string(),
+                title: None,
+                duration: None,
+                info: None,
+                artist: None,
+            },
+        ];
+        let content = write_m3u(&entries);
+        assert_eq!(content, "#EXTM3U\n#EXTINF:562,So What\n#EXTART:Miles Davis\nJazz/So What.flac\nhttp://example.com/stream\n");
+
+        let parsed = M3UParser::new().parse_content(&content, None).unwrap();
+        assert_eq!(parsed.entries[0].artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(parsed.entries[0].title.as_deref(), Some("So What"));
+        assert_eq!(parsed.entries[1].artist, None);
+    }
+
     #[test]
     fn test_m3u_entry_serialization() {
         let entry = M3UEntry {
@@ -458,6 +521,7 @@ http://example.com/song2.mp3"#;
             title: Some("Test Song".to_string()),
             duration: Some(180.0),
             info: None,
+            artist: None,
         };
         
         let json = serde_json::to_string(&entry).unwrap();
@@ -475,6 +539,7 @@ http://example.com/song2.mp3"#;
                     title: Some("Song 1".to_string()),
                     duration: Some(180.0),
                     info: None,
+                    artist: None,
                 }
             ],
             count: 1,
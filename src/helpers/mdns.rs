@@ -0,0 +1,244 @@
+use log::debug;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const MDNS_ADDRESS: &str = "224.0.0.251:5353";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+
+/// A service instance found with an mDNS query
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdnsService {
+    /// Instance name, e.g. "kitchen-abc._googlecast._tcp.local"
+    pub instance: String,
+    /// IP address, or the mDNS host name if no address was sent
+    pub host: String,
+    pub port: u16,
+    /// TXT records, the keys are lower case
+    pub txt: HashMap<String, String>,
+    /// Address the answer was sent from
+    pub sender: Option<IpAddr>,
+}
+
+/// Find instances of a service type with an mDNS query
+///
+/// The query is sent from an ephemeral port, so devices answer directly to us
+/// and no multicast group has to be joined.
+pub fn browse(service: &str, timeout: Duration) -> Result<Vec<MdnsService>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to open mDNS socket: {}", e))?;
+    socket
+        .send_to(&build_query(service), MDNS_ADDRESS)
+        .map_err(|e| format!("Failed to send mDNS query: {}", e))?;
+
+    let mut found: Vec<MdnsService> = Vec::new();
+    let mut buffer = [0u8; 9000];
+    let deadline = Instant::now() + timeout;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+        socket.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+        let (len, sender) = match socket.recv_from(&mut buffer) {
+            Ok((len, sender)) => (len, sender.ip()),
+            Err(_) => break,
+        };
+        for mut instance in parse_response(&buffer[..len], service) {
+            instance.sender = Some(sender);
+            if !found.iter().any(|s| s.host == instance.host && s.port == instance.port) {
+                debug!("Found {} at {}:{}", instance.instance, instance.host, instance.port);
+                found.push(instance);
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn build_query(service: &str) -> Vec<u8> {
+    // Header: id 0, standard query, one question
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in service.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]))
+}
+
+/// Read a possibly compressed name, returns the name and the position after it
+fn read_name(data: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *data.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xc0 == 0xc0 {
+            end.get_or_insert(pos + 2);
+            jumps += 1;
+            if jumps > 32 {
+                return None;
+            }
+            pos = ((len & 0x3f) << 8) | *data.get(pos + 1)? as usize;
+            continue;
+        }
+        labels.push(String::from_utf8_lossy(data.get(pos + 1..pos + 1 + len)?).into_owned());
+        pos += 1 + len;
+    }
+    Some((labels.join("."), end.unwrap_or(pos)))
+}
+
+fn parse_txt(rdata: &[u8]) -> HashMap<String, String> {
+    let mut entries = HashMap::new();
+    let mut pos = 0;
+    while let Some(len) = rdata.get(pos).map(|l| *l as usize) {
+        if let Some(entry) = rdata.get(pos + 1..pos + 1 + len) {
+            let entry = String::from_utf8_lossy(entry);
+            if let Some((key, value)) = entry.split_once('=') {
+                entries.insert(key.to_lowercase(), value.to_string());
+            }
+        }
+        pos += 1 + len;
+    }
+    entries
+}
+
+/// Service instances announced in an mDNS response
+///
+/// Devices send PTR, SRV, TXT and address records in one packet.
+fn parse_response(data: &[u8], service: &str) -> Vec<MdnsService> {
+    let mut instances = Vec::new();
+    let service = service.to_lowercase();
+    let mut services: HashMap<String, (String, u16)> = HashMap::new();
+    let mut txt: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut addresses: HashMap<String, Vec<IpAddr>> = HashMap::new();
+
+    let (Some(questions), Some(answers), Some(authority), Some(additional)) =
+        (read_u16(data, 4), read_u16(data, 6), read_u16(data, 8), read_u16(data, 10))
+    else {
+        return Vec::new();
+    };
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        let Some((_, next)) = read_name(data, pos) else { return Vec::new() };
+        pos = next + 4;
+    }
+
+    for _ in 0..(answers as usize + authority as usize + additional as usize) {
+        let Some((name, next)) = read_name(data, pos) else { break };
+        let (Some(record_type), Some(rdlength)) = (read_u16(data, next), read_u16(data, next + 8)) else { break };
+        let rdata_start = next + 10;
+        let Some(rdata) = data.get(rdata_start..rdata_start + rdlength as usize) else { break };
+        pos = rdata_start + rdlength as usize;
+
+        let name = name.to_lowercase();
+        match record_type {
+            TYPE_PTR if name == service => {
+                if let Some((instance, _)) = read_name(data, rdata_start) {
+                    instances.push(instance.to_lowercase());
+                }
+            }
+            TYPE_SRV => {
+                if let (Some(port), Some((target, _))) = (read_u16(data, rdata_start + 4), read_name(data, rdata_start + 6)) {
+                    services.insert(name, (target.to_lowercase(), port));
+                }
+            }
+            TYPE_TXT => {
+                txt.insert(name, parse_txt(rdata));
+            }
+            TYPE_A if rdata.len() == 4 => {
+                let ip = Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]);
+                addresses.entry(name).or_default().push(IpAddr::V4(ip));
+            }
+            TYPE_AAAA if rdata.len() == 16 => {
+                let octets: [u8; 16] = rdata.try_into().unwrap_or_default();
+                addresses.entry(name).or_default().push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+    }
+
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (target, port) = services.get(&instance)?.clone();
+            let properties = txt.remove(&instance).unwrap_or_default();
+            // Prefer IPv4, link-local IPv6 addresses would need a scope id
+            let host = addresses
+                .get(&target)
+                .and_then(|ips| ips.iter().find(|ip| ip.is_ipv4()).or(ips.first()))
+                .map(|ip| ip.to_string())
+                .unwrap_or(target);
+            Some(MdnsService { instance, host, port, txt: properties, sender: None })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        for label in name.split('.') {
+            data.push(label.len() as u8);
+            data.extend_from_slice(label.as_bytes());
+        }
+        data.push(0);
+        data
+    }
+
+    fn record(owner: &[u8], record_type: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut data = owner.to_vec();
+        data.extend_from_slice(&record_type.to_be_bytes());
+        data.extend_from_slice(&[0x80, 0x01, 0, 0, 0x11, 0x94]);
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend_from_slice(rdata);
+        data
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let service = "_googlecast._tcp.local";
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 3];
+        // PTR _googlecast._tcp.local -> Kitchen-abc._googlecast._tcp.local, the instance
+        // name points back to the service name at offset 12
+        let service_offset = packet.len();
+        let mut instance = vec![11];
+        instance.extend_from_slice(b"Kitchen-abc");
+        instance.extend_from_slice(&[0xc0, service_offset as u8]);
+        packet.extend(record(&name(service), TYPE_PTR, &instance));
+
+        let mut srv = vec![0, 0, 0, 0, 0x1f, 0x49];
+        srv.extend(name("abc.local"));
+        packet.extend(record(&name("Kitchen-abc._googlecast._tcp.local"), TYPE_SRV, &srv));
+
+        let mut txt = Vec::new();
+        for entry in ["id=abc123", "md=Chromecast Audio", "FN=Kitchen"] {
+            txt.push(entry.len() as u8);
+            txt.extend_from_slice(entry.as_bytes());
+        }
+        packet.extend(record(&name("Kitchen-abc._googlecast._tcp.local"), TYPE_TXT, &txt));
+        packet.extend(record(&name("abc.local"), TYPE_A, &[192, 168, 1, 30]));
+
+        let found = parse_response(&packet, service);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].instance, "kitchen-abc._googlecast._tcp.local");
+        assert_eq!(found[0].host, "192.168.1.30");
+        assert_eq!(found[0].port, 8009);
+        assert_eq!(found[0].txt.get("fn").map(String::as_str), Some("Kitchen"));
+        assert!(parse_response(&packet, "_other._tcp.local").is_empty());
+        assert!(parse_response(&packet[..20], service).is_empty());
+    }
+}
@@ -0,0 +1,662 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::config::get_service_config;
+use crate::data::album::Album;
+use crate::data::artist::Artist;
+use crate::data::PlayerEvent;
+use crate::helpers::backgroundjobs::CancellationToken;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+const JOB_ID: &str = "metadata_enrichment";
+const JOB_NAME: &str = "Metadata Enrichment";
+
+/// Attribute cache prefix of the markers for artists that have been looked up
+const DONE_KEY_PREFIX: &str = "metadataqueue::done::";
+
+/// How often idle workers check if the daily quotas allow deferred lookups again
+/// and if the job has been cancelled
+const DEFERRED_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+type ArtistCollection = RwLock<HashMap<String, Artist>>;
+type AlbumCollection = RwLock<HashMap<String, Album>>;
+
+/// Configuration of the metadata enrichment queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataQueueConfig {
+    /// Number of worker threads looking up metadata in parallel
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// Artists looked up within this many days are skipped when a library is loaded again, 0 disables this
+    #[serde(default = "default_refresh_days")]
+    pub refresh_days: u64,
+    /// Hold back lookups while the daily quota of a service is used up
+    #[serde(default = "default_defer_on_quota")]
+    pub defer_on_quota: bool,
+}
+
+fn default_workers() -> usize {
+    2
+}
+
+fn default_refresh_days() -> u64 {
+    30
+}
+
+fn default_defer_on_quota() -> bool {
+    true
+}
+
+impl Default for MetadataQueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_workers(),
+            refresh_days: default_refresh_days(),
+            defer_on_quota: default_defer_on_quota(),
+        }
+    }
+}
+
+/// A metadata lookup with the library collections that receive the result
+pub enum MetadataTask {
+    /// Look up MusicBrainz IDs, images, biography and genres of an artist
+    Artist {
+        name: String,
+        targets: Vec<Weak<ArtistCollection>>,
+    },
+    /// Look up the genres of an album
+    AlbumGenres {
+        album_id: String,
+        album_name: String,
+        artist: String,
+        targets: Vec<Weak<AlbumCollection>>,
+    },
+}
+
+impl MetadataTask {
+    /// Key identifying the same lookup across libraries
+    pub fn key(&self) -> String {
+        match self {
+            MetadataTask::Artist { name, .. } => artist_key(name),
+            MetadataTask::AlbumGenres { album_name, artist, .. } => album_key(artist, album_name),
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            MetadataTask::Artist { name, .. } => format!("artist {}", name),
+            MetadataTask::AlbumGenres { album_name, .. } => format!("album {}", album_name),
+        }
+    }
+
+    /// Add the targets of another request for the same lookup
+    fn merge(&mut self, other: MetadataTask) {
+        match (self, other) {
+            (MetadataTask::Artist { targets, .. }, MetadataTask::Artist { targets: more, .. }) => {
+                targets.extend(more);
+            }
+            (MetadataTask::AlbumGenres { targets, .. }, MetadataTask::AlbumGenres { targets: more, .. }) => {
+                targets.extend(more);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn artist_key(name: &str) -> String {
+    format!("artist:{}", name.to_lowercase())
+}
+
+fn album_key(artist: &str, album_name: &str) -> String {
+    format!("album:{}/{}", artist.to_lowercase(), album_name.to_lowercase())
+}
+
+/// Pending lookups in processing order, without duplicates
+#[derive(Default)]
+pub struct TaskQueue {
+    order: VecDeque<String>,
+    tasks: HashMap<String, MetadataTask>,
+    active: HashSet<String>,
+    /// Lookups held back until the daily quotas allow requests again
+    deferred: Vec<String>,
+    completed: usize,
+}
+
+impl TaskQueue {
+    /// Add a lookup, returns false if the same lookup is already waiting or deferred
+    ///
+    /// A duplicate lookup only adds its targets to the waiting one.
+    pub fn push(&mut self, task: MetadataTask) -> bool {
+        let key = task.key();
+        if let Some(existing) = self.tasks.get_mut(&key) {
+            existing.merge(task);
+            return false;
+        }
+        self.order.push_back(key.clone());
+        self.tasks.insert(key, task);
+        true
+    }
+
+    /// Move waiting lookups to the front of the queue
+    pub fn prioritize(&mut self, keys: &[String]) {
+        for key in keys.iter().rev() {
+            if let Some(pos) = self.order.iter().position(|k| k == key) {
+                if let Some(key) = self.order.remove(pos) {
+                    debug!("Prioritizing metadata lookup {}", key);
+                    self.order.push_front(key);
+                }
+            }
+        }
+    }
+
+    /// Take the next lookup and mark it as active
+    pub fn pop(&mut self) -> Option<MetadataTask> {
+        let key = self.order.pop_front()?;
+        let task = self.tasks.remove(&key)?;
+        self.active.insert(key);
+        Some(task)
+    }
+
+    /// Hold back an active lookup until `resume_deferred` is called
+    pub fn defer(&mut self, task: MetadataTask) {
+        let key = task.key();
+        self.active.remove(&key);
+        match self.tasks.get_mut(&key) {
+            // Requested again while it was active
+            Some(existing) => {
+                existing.merge(task);
+                self.order.retain(|k| k != &key);
+            }
+            None => {
+                self.tasks.insert(key.clone(), task);
+            }
+        }
+        self.deferred.push(key);
+    }
+
+    /// Move the deferred lookups back to the end of the queue, returns their number
+    pub fn resume_deferred(&mut self) -> usize {
+        let count = self.deferred.len();
+        self.order.extend(self.deferred.drain(..));
+        count
+    }
+
+    pub fn deferred(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Drop all waiting and deferred lookups, returns their number
+    pub fn clear_waiting(&mut self) -> usize {
+        let count = self.tasks.len();
+        self.order.clear();
+        self.deferred.clear();
+        self.tasks.clear();
+        count
+    }
+
+    /// Mark an active lookup as done
+    pub fn finish(&mut self, key: &str) {
+        if self.active.remove(key) {
+            self.completed += 1;
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether all lookups are done
+    pub fn is_idle(&self) -> bool {
+        self.order.is_empty() && self.active.is_empty() && self.deferred.is_empty()
+    }
+
+    /// Lookups done and the total since the queue was last idle
+    pub fn progress(&self) -> (usize, usize) {
+        (self.completed, self.completed + self.active.len() + self.order.len() + self.deferred.len())
+    }
+
+    fn reset_progress(&mut self) {
+        self.completed = 0;
+    }
+}
+
+static CONFIG: Lazy<RwLock<MetadataQueueConfig>> = Lazy::new(|| RwLock::new(MetadataQueueConfig::default()));
+static QUEUE: Lazy<Mutex<TaskQueue>> = Lazy::new(|| Mutex::new(TaskQueue::default()));
+static WORK_AVAILABLE: Condvar = Condvar::new();
+static WORKERS_STARTED: AtomicBool = AtomicBool::new(false);
+/// Cancellation of the background job, set while the job is running
+static JOB: Lazy<Mutex<Option<CancellationToken>>> = Lazy::new(|| Mutex::new(None));
+
+/// Initialize the queue from the `metadata_queue` service configuration
+///
+/// Lookups for the artist and album of a song that starts playing are moved to
+/// the front of the queue.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(c) = get_service_config(config, "metadata_queue") {
+        match serde_json::from_value::<MetadataQueueConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid metadata_queue configuration, using defaults: {}", e),
+        }
+    }
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::SongChanged]);
+    bus.spawn_worker(id, receiver, |event| {
+        if let PlayerEvent::SongChanged { song: Some(song), source } = event {
+            if !crate::helpers::enrichment::settings_for(&source).artwork {
+                return;
+            }
+            let mut keys = Vec::new();
+            for artist in [&song.artist, &song.album_artist].into_iter().flatten() {
+                if let Some(album) = &song.album {
+                    keys.push(album_key(artist, album));
+                }
+                keys.push(artist_key(artist));
+            }
+            QUEUE.lock().prioritize(&keys);
+        }
+    });
+}
+
+/// Queue metadata lookups for all artists of a library collection
+///
+/// Artists that have metadata and were looked up within `refresh_days` are skipped,
+/// so an interrupted enrichment continues with the remaining artists after a restart.
+pub fn enqueue_artists(collection: &Arc<ArtistCollection>) {
+    let artists: Vec<(String, bool)> = collection
+        .read()
+        .iter()
+        .map(|(name, artist)| (name.clone(), artist.metadata.is_some()))
+        .collect();
+    let total = artists.len();
+    let names: Vec<String> = if CONFIG.read().refresh_days > 0 {
+        artists
+            .into_iter()
+            .filter(|(name, has_metadata)| !(*has_metadata && is_recently_done(name)))
+            .map(|(name, _)| name)
+            .collect()
+    } else {
+        artists.into_iter().map(|(name, _)| name).collect()
+    };
+    if names.len() < total {
+        info!("Skipping {} artists that have been looked up recently", total - names.len());
+    }
+    let target = Arc::downgrade(collection);
+    enqueue(names.into_iter().map(|name| MetadataTask::Artist {
+        name,
+        targets: vec![target.clone()],
+    }));
+}
+
+/// Queue genre lookups for all albums of a library collection that have no genres
+pub fn enqueue_album_genres(collection: &Arc<AlbumCollection>) {
+    let albums: Vec<(String, String, String)> = collection
+        .read()
+        .values()
+        .filter(|a| a.genres.is_empty())
+        .map(|a| (a.id.to_string(), a.name.clone(), a.artists.lock().first().cloned().unwrap_or_default()))
+        .collect();
+    let target = Arc::downgrade(collection);
+    enqueue(albums.into_iter().map(|(album_id, album_name, artist)| MetadataTask::AlbumGenres {
+        album_id,
+        album_name,
+        artist,
+        targets: vec![target.clone()],
+    }));
+}
+
+fn enqueue(tasks: impl Iterator<Item = MetadataTask>) {
+    let (added, total) = {
+        let mut queue = QUEUE.lock();
+        let added = add_tasks(&mut queue, &mut JOB.lock(), tasks);
+        (added, queue.progress().1)
+    };
+    info!("Queued {} metadata lookups, {} in total", added, total);
+
+    ensure_workers();
+    WORK_AVAILABLE.notify_all();
+}
+
+/// Add lookups to the queue and make sure a job that hasn't been cancelled tracks them
+///
+/// If the running job has been cancelled, its waiting lookups are dropped and it is completed here,
+/// otherwise the workers would drop the new lookups as well. The new lookups get a new job and token.
+fn add_tasks(queue: &mut TaskQueue, job: &mut Option<CancellationToken>, tasks: impl Iterator<Item = MetadataTask>) -> usize {
+    if job.as_ref().is_some_and(|token| token.is_cancelled()) {
+        let dropped = queue.clear_waiting();
+        if dropped > 0 {
+            info!("Metadata enrichment cancelled, dropped {} lookups", dropped);
+        }
+        if queue.is_idle() {
+            queue.reset_progress();
+        }
+        job.take();
+        let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+    }
+
+    let added = tasks.fold(0, |added, task| added + usize::from(queue.push(task)));
+    if job.is_none() {
+        *job = Some(crate::helpers::backgroundjobs::register_cancellable_job(
+            JOB_ID.to_string(),
+            JOB_NAME.to_string(),
+        ));
+    }
+    added
+}
+
+fn ensure_workers() {
+    if WORKERS_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let workers = CONFIG.read().workers.max(1);
+    info!("Starting {} metadata enrichment workers", workers);
+    for index in 0..workers {
+        let spawned = thread::Builder::new()
+            .name(format!("metadata-{}", index))
+            .spawn(worker);
+        if let Err(e) = spawned {
+            warn!("Failed to start metadata enrichment worker: {}", e);
+        }
+    }
+}
+
+/// Whether the running background job has been cancelled
+fn is_cancelled() -> bool {
+    JOB.lock().as_ref().is_some_and(|token| token.is_cancelled())
+}
+
+/// Complete the background job, must be called while holding the queue
+fn finish_job() {
+    if JOB.lock().take().is_some() {
+        let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+    }
+}
+
+fn done_key(name: &str) -> String {
+    format!("{}{}", DONE_KEY_PREFIX, name.to_lowercase())
+}
+
+/// Whether the artist has been looked up within the configured refresh period
+fn is_recently_done(name: &str) -> bool {
+    matches!(crate::helpers::attributecache::get::<bool>(&done_key(name)), Ok(Some(true)))
+}
+
+/// Remember that an artist has been looked up, the marker expires after `refresh_days`
+fn mark_done(name: &str) {
+    let refresh_days = CONFIG.read().refresh_days;
+    if refresh_days == 0 {
+        return;
+    }
+    if let Err(e) = crate::helpers::attributecache::set_with_ttl(&done_key(name), &true, refresh_days * 86400) {
+        warn!("Failed to store lookup state of artist {}: {}", name, e);
+    }
+}
+
+/// Services whose daily quota is used up
+fn exhausted_services() -> Vec<String> {
+    crate::helpers::ratelimit::get_quotas()
+        .into_iter()
+        .filter(|q| q.remaining_today == Some(0))
+        .map(|q| q.service)
+        .collect()
+}
+
+fn worker() {
+    loop {
+        let task = {
+            let mut queue = QUEUE.lock();
+            loop {
+                if is_cancelled() {
+                    let dropped = queue.clear_waiting();
+                    if dropped > 0 {
+                        info!("Metadata enrichment cancelled, dropped {} lookups", dropped);
+                    }
+                    if queue.is_idle() {
+                        queue.reset_progress();
+                        finish_job();
+                    }
+                }
+                if queue.deferred() > 0 && exhausted_services().is_empty() {
+                    let resumed = queue.resume_deferred();
+                    info!("Daily quotas available again, resuming {} metadata lookups", resumed);
+                }
+                if let Some(task) = queue.pop() {
+                    break task;
+                }
+                if queue.deferred() > 0 {
+                    WORK_AVAILABLE.wait_for(&mut queue, DEFERRED_CHECK_INTERVAL);
+                } else {
+                    WORK_AVAILABLE.wait(&mut queue);
+                }
+            }
+        };
+
+        let key = task.key();
+        let description = task.description();
+
+        if CONFIG.read().defer_on_quota {
+            let exhausted = exhausted_services();
+            if !exhausted.is_empty() {
+                let mut queue = QUEUE.lock();
+                queue.defer(task);
+                let (completed, total) = queue.progress();
+                drop(queue);
+                debug!("Deferring lookup of {}, daily quota used up: {}", description, exhausted.join(", "));
+                let _ = crate::helpers::backgroundjobs::update_job(
+                    JOB_ID,
+                    Some(format!("Waiting for the daily quota of {}", exhausted.join(", "))),
+                    Some(completed),
+                    Some(total),
+                );
+                continue;
+            }
+        }
+
+        process(task);
+
+        let mut queue = QUEUE.lock();
+        queue.finish(&key);
+        let cancelled = is_cancelled();
+        if cancelled {
+            queue.clear_waiting();
+        }
+        let (completed, total) = queue.progress();
+        if queue.is_idle() {
+            queue.reset_progress();
+            finish_job();
+            drop(queue);
+            if cancelled {
+                info!("Metadata enrichment cancelled after {} lookups", completed);
+            } else {
+                info!("Metadata enrichment complete, {} lookups", completed);
+            }
+        } else {
+            drop(queue);
+            let _ = crate::helpers::backgroundjobs::update_job(
+                JOB_ID,
+                Some(format!("Processed {}", description)),
+                Some(completed),
+                Some(total),
+            );
+        }
+    }
+}
+
+fn process(task: MetadataTask) {
+    match task {
+        MetadataTask::Artist { name, targets } => process_artist(&name, targets),
+        MetadataTask::AlbumGenres { album_id, album_name, artist, targets } => {
+            process_album_genres(&album_id, &album_name, &artist, targets)
+        }
+    }
+}
+
+fn process_artist(name: &str, targets: Vec<Weak<ArtistCollection>>) {
+    let targets: Vec<Arc<ArtistCollection>> = targets.iter().filter_map(Weak::upgrade).collect();
+    let Some(artist) = targets.iter().find_map(|t| t.read().get(name).cloned()) else {
+        debug!("Artist {} is no longer in any library, skipping", name);
+        return;
+    };
+
+    let updated = crate::helpers::artistupdater::update_data_for_artist(artist);
+    for target in targets {
+        if let Some(artist) = target.write().get_mut(name) {
+            artist.metadata = updated.metadata.clone();
+            artist.is_multi = updated.is_multi;
+        }
+    }
+
+    // A quota used up during the lookup might have left gaps, look the artist up again next time
+    if exhausted_services().is_empty() {
+        mark_done(name);
+    }
+}
+
+fn process_album_genres(album_id: &str, album_name: &str, artist: &str, targets: Vec<Weak<AlbumCollection>>) {
+    use crate::helpers::albumupdater::{fetch_album_genres, load_cached_genres, store_cached_genres};
+
+    let genres = match load_cached_genres(album_id) {
+        // An empty cached result means the lookup failed before, don't retry
+        Some(cached) if cached.is_empty() => return,
+        Some(cached) => cached,
+        None if artist.is_empty() || album_name.is_empty() => {
+            store_cached_genres(album_id, &[]);
+            return;
+        }
+        None => fetch_album_genres(album_id, artist, album_name),
+    };
+    if genres.is_empty() {
+        return;
+    }
+
+    for target in targets.iter().filter_map(Weak::upgrade) {
+        if let Some(album) = target.write().get_mut(album_name) {
+            if album.genres.is_empty() {
+                album.genres = genres.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist_task(name: &str, target: &Arc<ArtistCollection>) -> MetadataTask {
+        MetadataTask::Artist {
+            name: name.to_string(),
+            targets: vec![Arc::downgrade(target)],
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_across_libraries() {
+        let mpd = Arc::new(RwLock::new(HashMap::new()));
+        let lms = Arc::new(RwLock::new(HashMap::new()));
+        let mut queue = TaskQueue::default();
+
+        assert!(queue.push(artist_task("Miles Davis", &mpd)));
+        assert!(!queue.push(artist_task("miles davis", &lms)));
+        assert_eq!(queue.pending(), 1);
+
+        match queue.pop() {
+            Some(MetadataTask::Artist { targets, .. }) => assert_eq!(targets.len(), 2),
+            _ => panic!("Expected an artist task"),
+        }
+        assert!(!queue.is_idle());
+        queue.finish("artist:miles davis");
+        assert!(queue.is_idle());
+        assert_eq!(queue.progress(), (1, 1));
+    }
+
+    #[test]
+    fn test_prioritize() {
+        let library = Arc::new(RwLock::new(HashMap::new()));
+        let mut queue = TaskQueue::default();
+        for name in ["A", "B", "C", "D"] {
+            queue.push(artist_task(name, &library));
+        }
+
+        queue.prioritize(&["artist:c".to_string(), "artist:d".to_string(), "artist:x".to_string()]);
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|t| t.key()).collect();
+        assert_eq!(order, vec!["artist:c", "artist:d", "artist:a", "artist:b"]);
+        assert_eq!(queue.progress(), (0, 4));
+    }
+
+    #[test]
+    fn test_defer_and_resume() {
+        let mpd = Arc::new(RwLock::new(HashMap::new()));
+        let lms = Arc::new(RwLock::new(HashMap::new()));
+        let mut queue = TaskQueue::default();
+        queue.push(artist_task("A", &mpd));
+        queue.push(artist_task("B", &mpd));
+
+        let task = queue.pop().unwrap();
+        queue.defer(task);
+        assert_eq!(queue.deferred(), 1);
+        assert_eq!(queue.progress(), (0, 2));
+
+        // Requests for a deferred lookup are merged into it
+        assert!(!queue.push(artist_task("a", &lms)));
+
+        assert_eq!(queue.pop().map(|t| t.key()), Some("artist:b".to_string()));
+        queue.finish("artist:b");
+        assert!(queue.pop().is_none());
+        assert!(!queue.is_idle());
+
+        assert_eq!(queue.resume_deferred(), 1);
+        match queue.pop() {
+            Some(MetadataTask::Artist { name, targets }) => {
+                assert_eq!(name, "A");
+                assert_eq!(targets.len(), 2);
+            }
+            _ => panic!("Expected an artist task"),
+        }
+        queue.finish("artist:a");
+        assert!(queue.is_idle());
+        assert_eq!(queue.progress(), (2, 2));
+    }
+
+    #[test]
+    fn test_clear_waiting() {
+        let library = Arc::new(RwLock::new(HashMap::new()));
+        let mut queue = TaskQueue::default();
+        for name in ["A", "B", "C"] {
+            queue.push(artist_task(name, &library));
+        }
+        let active = queue.pop().unwrap();
+        let deferred = queue.pop().unwrap();
+        queue.defer(deferred);
+
+        assert_eq!(queue.clear_waiting(), 2);
+        assert!(queue.pop().is_none());
+        assert!(!queue.is_idle());
+        queue.finish(&active.key());
+        assert!(queue.is_idle());
+    }
+
+    #[test]
+    fn test_enqueue_after_cancel() {
+        let library = Arc::new(RwLock::new(HashMap::new()));
+        let mut queue = TaskQueue::default();
+        let mut job = None;
+
+        add_tasks(&mut queue, &mut job, std::iter::once(artist_task("A", &library)));
+        let cancelled = job.clone().unwrap();
+        crate::helpers::backgroundjobs::cancel_job(JOB_ID).unwrap();
+        assert!(cancelled.is_cancelled());
+
+        // New lookups get a new job instead of being dropped with the cancelled one
+        assert_eq!(add_tasks(&mut queue, &mut job, std::iter::once(artist_task("B", &library))), 1);
+        assert!(job.as_ref().is_some_and(|token| !token.is_cancelled()));
+        assert_eq!(queue.pop().map(|t| t.key()), Some("artist:b".to_string()));
+        assert!(queue.pop().is_none());
+        assert_eq!(queue.progress(), (0, 1));
+    }
+}
@@ -1,25 +1,44 @@
 pub mod attributecache;
 pub mod imagecache;
+pub mod imageproxy;
 pub mod image_meta;
 pub mod image_grader;
 pub mod artistupdater;
+pub mod artistdetails;
+pub mod artistconflicts;
+pub mod albumdetails;
+pub mod listening_history;
+pub mod bookmarks;
+pub mod dailymix;
+pub mod dspprofiles;
+pub mod dacwake;
+pub mod suggestions;
+pub mod network;
+pub mod locale;
+pub mod netaddr;
+pub mod enrichment;
+pub mod songdedup;
 pub mod albumupdater;
+pub mod metadataqueue;
 pub mod artist_store;
 pub mod artistsplitter;
 pub mod backgroundjobs;
 pub mod coverart;
 pub mod coverart_providers;
 pub mod local_coverart;
+pub mod coverwriteback;
 pub mod fanarttv;
 pub mod memory_report;
 pub mod stream_helper;
 pub mod musicbrainz;
 pub mod theaudiodb;
+pub mod wikipedia;
 pub mod sanitize;
 pub mod macaddress;
 pub mod http_client;
 pub mod ratelimit;
 pub mod lastfm;
+pub mod scrobble;
 pub mod security_store;
 pub mod settingsdb;
 pub mod spotify;
@@ -37,6 +56,33 @@ pub mod lyrics;
 pub mod songtitlesplitter;
 pub mod songsplitmanager;
 pub mod m3u;
+pub mod streamcheck;
+pub mod stationgain;
+pub mod mounts;
+pub mod webdav;
+pub mod usbstorage;
+pub mod idle;
+pub mod upcoming_track;
+pub mod mdns;
+pub mod device_identity;
+pub mod federation;
+pub mod api_tokens;
+pub mod tls;
+pub mod health;
+pub mod library_search;
+pub mod library_export;
+pub mod cache_backup;
+pub mod sharedcache;
+pub mod playlist_store;
+pub mod queue_estimate;
+pub mod system_monitor;
+pub mod network_diagnostics;
+pub mod notifications;
+pub mod notification_sinks;
+pub mod mqtt;
+pub mod tts;
+pub mod cdsource;
+pub mod qobuz;
 pub mod bluez;
 #[cfg(unix)]
 pub mod mpris;
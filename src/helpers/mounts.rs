@@ -0,0 +1,576 @@
+use crate::config::get_service_config;
+use crate::helpers::systemd::{SystemdError, SystemdHelper};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Settings database key for the configured shares
+const SHARES_KEY: &str = "mounts.shares";
+
+/// Accessing a share that doesn't answer within this time is considered offline.
+/// Stale NFS/SMB mounts can block file system calls for a long time.
+const ACCESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors that can occur when managing network shares
+#[derive(Debug, Error)]
+pub enum MountError {
+    #[error("Invalid share configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Share '{0}' not found")]
+    NotFound(String),
+
+    #[error("Share '{0}' already exists")]
+    AlreadyExists(String),
+
+    #[error("Failed to write file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("systemd error: {0}")]
+    Systemd(#[from] SystemdError),
+
+    #[error("Failed to store share configuration: {0}")]
+    Storage(String),
+}
+
+pub type Result<T> = std::result::Result<T, MountError>;
+
+/// Configuration of the mount management
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountsConfig {
+    /// Directory where the systemd mount units are written
+    #[serde(default = "default_unit_dir")]
+    pub unit_dir: String,
+
+    /// Directory for SMB credential files
+    #[serde(default = "default_credentials_dir")]
+    pub credentials_dir: String,
+
+    /// Shares are mounted on directories directly below this one
+    #[serde(default = "default_mount_base")]
+    pub mount_base: String,
+}
+
+fn default_unit_dir() -> String {
+    "/etc/systemd/system".to_string()
+}
+
+fn default_credentials_dir() -> String {
+    "/etc/audiocontrol/credentials".to_string()
+}
+
+fn default_mount_base() -> String {
+    "/data/library".to_string()
+}
+
+impl Default for MountsConfig {
+    fn default() -> Self {
+        Self {
+            unit_dir: default_unit_dir(),
+            credentials_dir: default_credentials_dir(),
+            mount_base: default_mount_base(),
+        }
+    }
+}
+
+/// Type of a network share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareType {
+    Smb,
+    Nfs,
+}
+
+/// A network share with music files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkShare {
+    /// Unique name, only lower case letters, digits, "-" and "_"
+    pub name: String,
+    #[serde(rename = "type")]
+    pub share_type: ShareType,
+    /// Host name or IP address of the server
+    pub server: String,
+    /// Share name (SMB) or exported path (NFS)
+    pub share: String,
+    /// Local directory the share is mounted on, `<mount_base>/<name>` if not set
+    #[serde(default)]
+    pub mount_point: String,
+    /// User name for SMB shares, guest access is used if not set
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Additional mount options
+    #[serde(default)]
+    pub options: Option<String>,
+}
+
+/// Health of a configured share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareStatus {
+    pub name: String,
+    pub mount_point: String,
+    /// Name of the systemd mount unit
+    pub unit: String,
+    /// State of the mount unit as reported by systemd
+    pub unit_status: Option<String>,
+    /// Whether the share is listed in /proc/mounts
+    pub mounted: bool,
+    /// Whether the mount point could be read in time
+    pub accessible: bool,
+    pub error: Option<String>,
+}
+
+impl ShareStatus {
+    pub fn is_online(&self) -> bool {
+        self.mounted && self.accessible
+    }
+}
+
+static CONFIG: Lazy<RwLock<MountsConfig>> = Lazy::new(|| RwLock::new(MountsConfig::default()));
+
+/// Read the mount configuration from the "mounts" service
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(mounts_config) = get_service_config(config, "mounts") {
+        match serde_json::from_value::<MountsConfig>(mounts_config.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid mounts configuration, using defaults: {}", e),
+        }
+    }
+    debug!("Mount management configuration: {:?}", CONFIG.read());
+}
+
+/// Get all configured shares
+pub fn list_shares() -> Vec<NetworkShare> {
+    match crate::helpers::settingsdb::get::<Vec<NetworkShare>>(SHARES_KEY) {
+        Ok(Some(shares)) => shares,
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            warn!("Failed to read network shares from settings database: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn save_shares(shares: &[NetworkShare]) -> Result<()> {
+    crate::helpers::settingsdb::set(SHARES_KEY, &shares.to_vec()).map_err(MountError::Storage)
+}
+
+/// Get a configured share by name
+pub fn get_share(name: &str) -> Option<NetworkShare> {
+    list_shares().into_iter().find(|share| share.name == name)
+}
+
+/// Line breaks would start a new entry in mount units and credential files
+fn has_line_break(value: &str) -> bool {
+    value.contains(['\r', '\n'])
+}
+
+fn validate_share(share: &NetworkShare, mount_base: &Path) -> Result<()> {
+    let invalid = |msg: &str| Err(MountError::InvalidConfig(msg.to_string()));
+
+    if share.name.is_empty()
+        || !share.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return invalid("name may only contain lower case letters, digits, '-' and '_'");
+    }
+    if share.server.is_empty() || share.server.contains(|c: char| c.is_whitespace() || c == '/' || c == ',') {
+        return invalid("server must be a host name or IP address");
+    }
+    if share.share.is_empty() || has_line_break(&share.share) || share.share.contains(',') {
+        return invalid("share must not be empty or contain ','");
+    }
+    if share.share_type == ShareType::Nfs && !share.share.starts_with('/') {
+        return invalid("NFS export path must be absolute");
+    }
+    // A single directory below the base, mounting elsewhere could hide system directories
+    let mount_point = Path::new(&share.mount_point);
+    let below_base = mount_point.strip_prefix(mount_base).ok().map(|rest| rest.components().collect::<Vec<_>>());
+    if !matches!(below_base.as_deref(), Some([std::path::Component::Normal(_)])) {
+        return invalid(&format!("mount_point must be a directory in {}", mount_base.display()));
+    }
+    if has_line_break(&share.mount_point) {
+        return invalid("mount_point must not contain line breaks");
+    }
+    if share.options.as_deref().is_some_and(has_line_break) {
+        return invalid("options must not contain line breaks");
+    }
+    if share.username.as_deref().is_some_and(has_line_break) {
+        return invalid("username must not contain line breaks");
+    }
+    Ok(())
+}
+
+/// Escape a path the way `systemd-escape --path` does
+///
+/// systemd requires mount units to be named after their mount point, e.g.
+/// /mnt/music becomes mnt-music.mount
+pub fn unit_name_for_path(path: &str) -> String {
+    let trimmed = path.trim_matches('/');
+    let mut escaped = String::new();
+    for (index, c) in trimmed.chars().enumerate() {
+        match c {
+            '/' => escaped.push('-'),
+            '.' if index == 0 => escaped.push_str("\\x2e"),
+            c if c.is_ascii_alphanumeric() || c == ':' || c == '_' || c == '.' => escaped.push(c),
+            c => {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    escaped.push_str(&format!("\\x{:02x}", byte));
+                }
+            }
+        }
+    }
+    if escaped.is_empty() {
+        escaped.push('-');
+    }
+    format!("{}.mount", escaped)
+}
+
+fn credentials_path(name: &str) -> PathBuf {
+    Path::new(&CONFIG.read().credentials_dir).join(format!("smb-{}", name))
+}
+
+fn unit_path(share: &NetworkShare) -> PathBuf {
+    Path::new(&CONFIG.read().unit_dir).join(unit_name_for_path(&share.mount_point))
+}
+
+/// Create the content of the systemd mount unit for a share
+fn mount_unit_content(share: &NetworkShare, credentials: Option<&Path>) -> String {
+    let (what, fs_type, mut options) = match share.share_type {
+        ShareType::Smb => {
+            let auth = match credentials {
+                Some(path) => format!("credentials={}", path.display()),
+                None => "guest".to_string(),
+            };
+            (
+                format!("//{}/{}", share.server, share.share.trim_start_matches('/')),
+                "cifs",
+                format!("{},iocharset=utf8,_netdev", auth),
+            )
+        }
+        ShareType::Nfs => (
+            format!("{}:{}", share.server, share.share),
+            "nfs",
+            // soft: don't block forever if the server goes away
+            "soft,timeo=150,retrans=3,_netdev".to_string(),
+        ),
+    };
+    if let Some(extra) = share.options.as_deref().filter(|o| !o.is_empty()) {
+        options.push(',');
+        options.push_str(extra);
+    }
+
+    format!(
+        "# Created by AudioControl, changes will be overwritten\n\
+         [Unit]\n\
+         Description=AudioControl network share {name}\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Mount]\n\
+         What={what}\n\
+         Where={where_}\n\
+         Type={fs_type}\n\
+         Options={options}\n\
+         TimeoutSec=30\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        name = share.name,
+        what = what,
+        where_ = share.mount_point,
+        fs_type = fs_type,
+        options = options,
+    )
+}
+
+fn write_credentials(name: &str, username: &str, password: &str) -> Result<PathBuf> {
+    if has_line_break(username) || has_line_break(password) {
+        return Err(MountError::InvalidConfig("username and password must not contain line breaks".to_string()));
+    }
+    let path = credentials_path(name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    std::io::Write::write_all(&mut file, format!("username={}\npassword={}\n", username, password).as_bytes())?;
+    Ok(path)
+}
+
+/// Write the mount unit (and credentials) of a share and enable it
+fn install_unit(share: &NetworkShare, password: Option<&str>) -> Result<()> {
+    let credentials = match (share.share_type, &share.username) {
+        (ShareType::Smb, Some(username)) => Some(write_credentials(&share.name, username, password.unwrap_or(""))?),
+        _ => None,
+    };
+
+    // Don't follow a link out of the base directory
+    if std::fs::symlink_metadata(&share.mount_point).is_ok_and(|m| m.file_type().is_symlink()) {
+        return Err(MountError::InvalidConfig(format!("{} is a symbolic link", share.mount_point)));
+    }
+    std::fs::create_dir_all(&share.mount_point)?;
+    let path = unit_path(share);
+    std::fs::write(&path, mount_unit_content(share, credentials.as_deref()))?;
+    info!("Wrote mount unit {}", path.display());
+
+    let systemd = SystemdHelper::new();
+    systemd.daemon_reload()?;
+    systemd.enable_unit(&unit_name_for_path(&share.mount_point))?;
+    Ok(())
+}
+
+/// Add a new share, write its mount unit and mount it
+///
+/// # Arguments
+/// * `share` - The share to add, it is mounted on `<mount_base>/<name>` if it has no mount point
+/// * `password` - Password for SMB shares with a user name
+pub fn add_share(mut share: NetworkShare, password: Option<&str>) -> Result<ShareStatus> {
+    let mount_base = PathBuf::from(&CONFIG.read().mount_base);
+    if share.mount_point.is_empty() {
+        share.mount_point = mount_base.join(&share.name).to_string_lossy().into_owned();
+    }
+    validate_share(&share, &mount_base)?;
+
+    let mut shares = list_shares();
+    if shares.iter().any(|s| s.name == share.name) {
+        return Err(MountError::AlreadyExists(share.name));
+    }
+    if shares.iter().any(|s| s.mount_point == share.mount_point) {
+        return Err(MountError::InvalidConfig(format!("{} is already used by another share", share.mount_point)));
+    }
+
+    install_unit(&share, password)?;
+    shares.push(share.clone());
+    save_shares(&shares)?;
+
+    if let Err(e) = SystemdHelper::new().start_unit(&unit_name_for_path(&share.mount_point)) {
+        // The share is configured, it can be mounted later
+        warn!("Failed to mount share '{}': {}", share.name, e);
+    }
+    Ok(share_status(&share))
+}
+
+/// Unmount a share and remove its configuration
+pub fn remove_share(name: &str) -> Result<()> {
+    let mut shares = list_shares();
+    let index = shares.iter().position(|s| s.name == name).ok_or_else(|| MountError::NotFound(name.to_string()))?;
+    let share = shares.remove(index);
+
+    let unit = unit_name_for_path(&share.mount_point);
+    let systemd = SystemdHelper::new();
+    if let Err(e) = systemd.stop_unit(&unit) {
+        warn!("Failed to unmount share '{}': {}", name, e);
+    }
+    if let Err(e) = systemd.disable_unit(&unit) {
+        warn!("Failed to disable mount unit {}: {}", unit, e);
+    }
+
+    for path in [unit_path(&share), credentials_path(&share.name)] {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    if let Err(e) = systemd.daemon_reload() {
+        warn!("Failed to reload systemd after removing share '{}': {}", name, e);
+    }
+
+    save_shares(&shares)?;
+    info!("Removed network share '{}'", name);
+    Ok(())
+}
+
+/// Mount a configured share
+pub fn mount_share(name: &str) -> Result<ShareStatus> {
+    let share = get_share(name).ok_or_else(|| MountError::NotFound(name.to_string()))?;
+    SystemdHelper::new().start_unit(&unit_name_for_path(&share.mount_point))?;
+    Ok(share_status(&share))
+}
+
+/// Unmount a configured share
+pub fn unmount_share(name: &str) -> Result<ShareStatus> {
+    let share = get_share(name).ok_or_else(|| MountError::NotFound(name.to_string()))?;
+    SystemdHelper::new().stop_unit(&unit_name_for_path(&share.mount_point))?;
+    Ok(share_status(&share))
+}
+
+/// Check if a path is a mount point according to /proc/mounts
+fn is_mounted(mount_point: &str) -> bool {
+    let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+    mounts_contain(&mounts, mount_point)
+}
+
+fn mounts_contain(mounts: &str, mount_point: &str) -> bool {
+    // Spaces in mount points are encoded as \040 in /proc/mounts
+    let encoded = mount_point.trim_end_matches('/').replace(' ', "\\040");
+    mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .any(|target| target == encoded)
+}
+
+/// Try to read a directory, giving up after ACCESS_TIMEOUT
+fn check_access(path: &str) -> std::result::Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let path_owned = path.to_string();
+    // The thread might hang on a stale mount, it's not joined
+    thread::spawn(move || {
+        let result = std::fs::read_dir(&path_owned).map(|mut entries| {
+            let _ = entries.next();
+        });
+        let _ = tx.send(result.map_err(|e| e.to_string()));
+    });
+    match rx.recv_timeout(ACCESS_TIMEOUT) {
+        Ok(result) => result,
+        Err(_) => Err(format!("No response within {} seconds", ACCESS_TIMEOUT.as_secs())),
+    }
+}
+
+/// Get the health of a share
+pub fn share_status(share: &NetworkShare) -> ShareStatus {
+    let unit = unit_name_for_path(&share.mount_point);
+    let unit_status = SystemdHelper::new().get_unit_status(&unit).ok().map(|s| s.to_string());
+    let mounted = is_mounted(&share.mount_point);
+
+    let (accessible, error) = if mounted {
+        match check_access(&share.mount_point) {
+            Ok(()) => (true, None),
+            Err(e) => (false, Some(e)),
+        }
+    } else {
+        (false, Some("Not mounted".to_string()))
+    };
+
+    ShareStatus {
+        name: share.name.clone(),
+        mount_point: share.mount_point.clone(),
+        unit,
+        unit_status,
+        mounted,
+        accessible,
+        error,
+    }
+}
+
+/// Find configured shares that are offline and contain or are contained in a path
+///
+/// Used to pause library updates: if MPD updates its database while the share
+/// with the music files is offline, it would remove all these files.
+///
+/// # Returns
+/// Names of the offline shares
+pub fn offline_shares_for_path(path: &str) -> Vec<String> {
+    list_shares()
+        .iter()
+        .filter(|share| paths_overlap(&share.mount_point, path))
+        .filter(|share| !share_status(share).is_online())
+        .map(|share| share.name.clone())
+        .collect()
+}
+
+fn paths_overlap(a: &str, b: &str) -> bool {
+    let a = Path::new(a);
+    let b = Path::new(b);
+    a.starts_with(b) || b.starts_with(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smb_share() -> NetworkShare {
+        NetworkShare {
+            name: "nas".to_string(),
+            share_type: ShareType::Smb,
+            server: "192.168.1.10".to_string(),
+            share: "music".to_string(),
+            mount_point: "/data/library/nas music".to_string(),
+            username: Some("user".to_string()),
+            options: Some("vers=3.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_unit_name_for_path() {
+        assert_eq!(unit_name_for_path("/mnt/music"), "mnt-music.mount");
+        assert_eq!(unit_name_for_path("/mnt/nas music/"), "mnt-nas\\x20music.mount");
+        assert_eq!(unit_name_for_path("/data/my-music"), "data-my\\x2dmusic.mount");
+        assert_eq!(unit_name_for_path("/.hidden"), "\\x2ehidden.mount");
+    }
+
+    #[test]
+    fn test_validate_share() {
+        let base = Path::new("/data/library");
+        assert!(validate_share(&smb_share(), base).is_ok());
+
+        let mut share = smb_share();
+        share.name = "My NAS".to_string();
+        assert!(validate_share(&share, base).is_err());
+
+        // Only directories directly in the base directory
+        for mount_point in ["/data/library/../../etc", "/data/library", "/data/library/nas/music", "/mnt/music", "data/library/nas"] {
+            let mut share = smb_share();
+            share.mount_point = mount_point.to_string();
+            assert!(validate_share(&share, base).is_err(), "{}", mount_point);
+        }
+
+        // Line breaks would add entries to the mount unit or the credentials file
+        let mut share = smb_share();
+        share.username = Some("user\rpassword=x".to_string());
+        assert!(validate_share(&share, base).is_err());
+        let mut share = smb_share();
+        share.options = Some("vers=3.0\nExecStart=/bin/sh".to_string());
+        assert!(validate_share(&share, base).is_err());
+        assert!(write_credentials("nas", "user", "secret\nusername=root").is_err());
+
+        let mut share = smb_share();
+        share.share_type = ShareType::Nfs;
+        assert!(validate_share(&share, base).is_err());
+        share.share = "/export/music".to_string();
+        assert!(validate_share(&share, base).is_ok());
+    }
+
+    #[test]
+    fn test_mount_unit_content() {
+        let content = mount_unit_content(&smb_share(), Some(Path::new("/etc/audiocontrol/credentials/smb-nas")));
+        assert!(content.contains("What=//192.168.1.10/music\n"));
+        assert!(content.contains("Where=/data/library/nas music\n"));
+        assert!(content.contains("Type=cifs\n"));
+        assert!(content.contains("Options=credentials=/etc/audiocontrol/credentials/smb-nas,iocharset=utf8,_netdev,vers=3.0\n"));
+
+        let mut share = smb_share();
+        share.share_type = ShareType::Nfs;
+        share.share = "/export/music".to_string();
+        share.options = None;
+        let content = mount_unit_content(&share, None);
+        assert!(content.contains("What=192.168.1.10:/export/music\n"));
+        assert!(content.contains("Type=nfs\n"));
+    }
+
+    #[test]
+    fn test_mounts_contain() {
+        let mounts = "//nas/music /mnt/nas\\040music cifs rw 0 0\n/dev/root / ext4 rw 0 0\n";
+        assert!(mounts_contain(mounts, "/mnt/nas music"));
+        assert!(mounts_contain(mounts, "/mnt/nas music/"));
+        assert!(!mounts_contain(mounts, "/mnt/nas"));
+    }
+
+    #[test]
+    fn test_paths_overlap() {
+        assert!(paths_overlap("/mnt/nas", "/mnt/nas/music"));
+        assert!(paths_overlap("/mnt/nas/music", "/mnt/nas"));
+        assert!(!paths_overlap("/mnt/nas", "/mnt/nas2"));
+    }
+}
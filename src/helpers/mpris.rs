@@ -372,6 +372,37 @@ pub fn retrieve_mpris_metadata(proxy: &Proxy<'_, &Connection>) -> Option<dbus::a
     get_dbus_property(proxy, "org.mpris.MediaPlayer2.Player", "Metadata")
 }
 
+/// Retrieve all properties of the MPRIS player interface with one call
+pub fn retrieve_player_properties(proxy: &Proxy<'_, &Connection>) -> Option<dbus::arg::PropMap> {
+    proxy.method_call("org.freedesktop.DBus.Properties", "GetAll", ("org.mpris.MediaPlayer2.Player",))
+        .map(|(properties,): (dbus::arg::PropMap,)| properties)
+        .ok()
+}
+
+/// Retrieve the metadata of all tracks from the TrackList interface
+///
+/// Returns None if the player doesn't advertise the interface with HasTrackList.
+pub fn retrieve_track_list(proxy: &Proxy<'_, &Connection>) -> Option<Vec<Song>> {
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+
+    if !get_bool_property(proxy, "org.mpris.MediaPlayer2", "HasTrackList").unwrap_or(false) {
+        return None;
+    }
+    let tracks: Vec<dbus::Path<'static>> = proxy.get("org.mpris.MediaPlayer2.TrackList", "Tracks").ok()?;
+    if tracks.is_empty() {
+        return Some(Vec::new());
+    }
+    let (metadata,): (Vec<dbus::arg::PropMap>,) = proxy
+        .method_call("org.mpris.MediaPlayer2.TrackList", "GetTracksMetadata", (tracks,))
+        .ok()?;
+    Some(
+        metadata
+            .into_iter()
+            .filter_map(|m| extract_song_from_mpris_metadata(&dbus::arg::Variant(Box::new(m) as Box<dyn RefArg>)))
+            .collect(),
+    )
+}
+
 /// Extract song information from MPRIS metadata variant
 pub fn extract_song_from_mpris_metadata(metadata_variant: &dbus::arg::Variant<Box<dyn RefArg>>) -> Option<Song> {
     let metadata = extract_metadata_robust(metadata_variant);
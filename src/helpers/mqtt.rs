@@ -0,0 +1,456 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{PlaybackState, PlayerCommand, PlayerEvent};
+use crate::helpers::device_identity;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// Configuration of the `mqtt` service
+///
+/// The state of every player and the volume are published below
+/// `<topic_prefix>/<device id>`, commands are received on the `command` topics.
+/// With `discovery` enabled, Home Assistant finds the players as `media_player` entities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Host name or address of the broker
+    #[serde(default = "default_host")]
+    pub host: String,
+
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// First level of all state and command topics
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String,
+
+    /// Publish Home Assistant MQTT Discovery payloads
+    #[serde(default = "default_true")]
+    pub discovery: bool,
+
+    /// Discovery prefix configured in Home Assistant
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+
+    /// Seconds between keepalive pings, the broker marks the device offline after 1.5 times this
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic_prefix() -> String {
+    "audiocontrol".to_string()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_string()
+}
+
+fn default_keepalive_secs() -> u64 {
+    30
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            host: default_host(),
+            port: default_port(),
+            username: None,
+            password: None,
+            topic_prefix: default_topic_prefix(),
+            discovery: true,
+            discovery_prefix: default_discovery_prefix(),
+            keepalive_secs: default_keepalive_secs(),
+        }
+    }
+}
+
+/// Payload of the availability topic while connected
+const ONLINE: &str = "online";
+
+/// Payload of the availability topic after the connection was lost, sent by the broker
+const OFFLINE: &str = "offline";
+
+/// Wait before reconnecting after a connection error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Commands a media player entity sends, the payload is the command name
+const ENTITY_COMMANDS: [&str; 6] = ["play", "pause", "playpause", "stop", "next", "previous"];
+
+static CONFIG: Lazy<RwLock<MqttConfig>> = Lazy::new(|| RwLock::new(MqttConfig::default()));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+static CLIENT: Lazy<RwLock<Option<Client>>> = Lazy::new(|| RwLock::new(None));
+
+fn get_controller() -> Option<Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+/// Topics of this device
+#[derive(Debug, Clone, PartialEq)]
+struct Topics {
+    base: String,
+}
+
+impl Topics {
+    fn new(prefix: &str, device_id: &str) -> Self {
+        Self {
+            base: format!("{}/{}", prefix.trim_end_matches('/'), slug(device_id)),
+        }
+    }
+
+    fn availability(&self) -> String {
+        format!("{}/availability", self.base)
+    }
+
+    fn volume(&self) -> String {
+        format!("{}/volume", self.base)
+    }
+
+    fn volume_set(&self) -> String {
+        format!("{}/volume/set", self.base)
+    }
+
+    /// Commands for the active player
+    fn command(&self) -> String {
+        format!("{}/command", self.base)
+    }
+
+    /// State value of a player, e.g. `state` or `title`
+    fn player(&self, player: &str, value: &str) -> String {
+        format!("{}/{}/{}", self.base, slug(player), value)
+    }
+
+    /// Subscriptions for all command topics
+    fn command_filters(&self) -> Vec<String> {
+        vec![self.command(), format!("{}/+/command", self.base), self.volume_set()]
+    }
+}
+
+/// Topic level for a name, lowercase letters, digits and underscores
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let slug = slug.trim_matches('_').to_string();
+    if slug.is_empty() {
+        "player".to_string()
+    } else {
+        slug
+    }
+}
+
+/// State of a media player entity for a playback state
+fn entity_state(state: PlaybackState) -> &'static str {
+    match state {
+        PlaybackState::Playing => "playing",
+        PlaybackState::Paused => "paused",
+        PlaybackState::Stopped | PlaybackState::Unknown => "idle",
+        PlaybackState::Killed | PlaybackState::Disconnected => "off",
+    }
+}
+
+/// Parse the payload of a command topic, a command name like `next` or a JSON player command
+fn parse_command(payload: &str) -> Option<PlayerCommand> {
+    let payload = payload.trim();
+    if payload.starts_with('{') || payload.starts_with('"') {
+        serde_json::from_str(payload).ok()
+    } else {
+        serde_json::from_value(serde_json::Value::String(payload.to_lowercase())).ok()
+    }
+}
+
+/// Home Assistant MQTT Discovery topic and payload of a player
+fn discovery_config(config: &MqttConfig, topics: &Topics, device: &device_identity::DeviceIdentity, player: &str) -> (String, serde_json::Value) {
+    let object_id = format!("{}_{}", slug(&device.id), slug(player));
+    let topic = format!("{}/media_player/{}/config", config.discovery_prefix.trim_end_matches('/'), object_id);
+
+    let mut payload = json!({
+        "name": player,
+        "unique_id": format!("audiocontrol_{}", object_id),
+        "availability_topic": topics.availability(),
+        "device": {
+            "identifiers": [format!("audiocontrol_{}", slug(&device.id))],
+            "name": device.name,
+            "manufacturer": "HiFiBerry",
+            "model": "AudioControl",
+            "sw_version": env!("CARGO_PKG_VERSION"),
+        },
+        "state_state_topic": topics.player(player, "state"),
+        "state_title_topic": topics.player(player, "title"),
+        "state_artist_topic": topics.player(player, "artist"),
+        "state_album_topic": topics.player(player, "album"),
+        "state_duration_topic": topics.player(player, "duration"),
+        "state_position_topic": topics.player(player, "position"),
+        "state_volume_topic": topics.volume(),
+        "command_volume_topic": topics.volume_set(),
+    });
+    for command in ENTITY_COMMANDS {
+        payload[format!("command_{}_topic", command)] = json!(topics.player(player, "command"));
+        payload[format!("command_{}_payload", command)] = json!(command);
+    }
+    (topic, payload)
+}
+
+/// Initialize the MQTT integration from the `mqtt` service configuration
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let mqtt_config = match get_service_config(config, "mqtt") {
+        Some(c) => serde_json::from_value::<MqttConfig>(c.clone()).unwrap_or_else(|e| {
+            warn!("Invalid mqtt configuration, using defaults: {}", e);
+            MqttConfig::default()
+        }),
+        None => MqttConfig::default(),
+    };
+    if !mqtt_config.enable {
+        debug!("MQTT integration is disabled");
+        return;
+    }
+
+    let device = device_identity::identity();
+    let topics = Topics::new(&mqtt_config.topic_prefix, &device.id);
+    info!(
+        "MQTT integration enabled, broker {}:{}, topics below {}",
+        mqtt_config.host, mqtt_config.port, topics.base
+    );
+
+    let mut options = MqttOptions::new(format!("audiocontrol-{}", slug(&device.id)), mqtt_config.host.clone(), mqtt_config.port);
+    options.set_keep_alive(Duration::from_secs(mqtt_config.keepalive_secs.max(5)));
+    options.set_last_will(LastWill::new(topics.availability(), OFFLINE, QoS::AtLeastOnce, true));
+    if let Some(username) = &mqtt_config.username {
+        options.set_credentials(username.clone(), mqtt_config.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut connection) = Client::new(options, 256);
+    *CONFIG.write() = mqtt_config;
+    *CONTROLLER.write() = Some(controller);
+    *CLIENT.write() = Some(client);
+
+    // The connection reconnects by itself when it is polled again after an error
+    let connection_topics = topics.clone();
+    thread::spawn(move || {
+        for notification in connection.iter() {
+            match notification {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to MQTT broker");
+                    // Publish from another thread, this one has to send the queued messages
+                    let topics = connection_topics.clone();
+                    thread::spawn(move || on_connected(&topics));
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let payload = String::from_utf8_lossy(&publish.payload);
+                    handle_message(&connection_topics, &publish.topic, &payload);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("MQTT connection error: {}", e);
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            }
+        }
+    });
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![
+        EventSubscription::StateChanged,
+        EventSubscription::SongChanged,
+        EventSubscription::PositionChanged,
+        EventSubscription::VolumeChanged,
+    ]);
+    bus.spawn_worker(id, receiver, move |event| match &event {
+        PlayerEvent::VolumeChanged { percentage, .. } => {
+            publish(&topics.volume(), format!("{:.0}", percentage), true);
+        }
+        PlayerEvent::PositionChanged { position, .. } => {
+            if let Some(player) = event.player_name() {
+                publish(&topics.player(player, "position"), format!("{:.1}", position), false);
+            }
+        }
+        _ => {
+            if let Some(player) = event.player_name() {
+                publish_player(&topics, player);
+            }
+        }
+    });
+}
+
+/// Publish a message, dropped with a log message if the client can't queue it
+fn publish(topic: &str, payload: String, retain: bool) {
+    if let Some(client) = CLIENT.read().as_ref() {
+        if let Err(e) = client.try_publish(topic, QoS::AtLeastOnce, retain, payload) {
+            debug!("Failed to publish MQTT message to {}: {}", topic, e);
+        }
+    }
+}
+
+/// Subscribe to the command topics and publish availability, discovery and state after (re)connecting
+fn on_connected(topics: &Topics) {
+    if let Some(client) = CLIENT.read().as_ref() {
+        for filter in topics.command_filters() {
+            if let Err(e) = client.try_subscribe(filter.clone(), QoS::AtLeastOnce) {
+                warn!("Failed to subscribe to {}: {}", filter, e);
+            }
+        }
+    }
+    publish(&topics.availability(), ONLINE.to_string(), true);
+
+    let Some(controller) = get_controller() else {
+        return;
+    };
+    let players: Vec<String> = controller
+        .list_controllers()
+        .iter()
+        .map(|player| player.read().get_player_name())
+        .collect();
+
+    let config = CONFIG.read().clone();
+    if config.discovery {
+        let device = device_identity::identity();
+        for player in &players {
+            let (topic, payload) = discovery_config(&config, topics, &device, player);
+            publish(&topic, payload.to_string(), true);
+        }
+    }
+
+    for player in &players {
+        publish_player(topics, player);
+    }
+    if let Some(volume) = crate::helpers::global_volume::get_volume_percentage() {
+        publish(&topics.volume(), format!("{:.0}", volume), true);
+    }
+}
+
+/// Publish the state and the current song of a player
+fn publish_player(topics: &Topics, name: &str) {
+    let Some(controller) = get_controller() else {
+        return;
+    };
+    let Some(player) = controller.get_player_by_name(name) else {
+        return;
+    };
+    let player = player.read();
+    let song = player.get_song().unwrap_or_default();
+
+    publish(&topics.player(name, "state"), entity_state(player.get_playback_state()).to_string(), true);
+    publish(&topics.player(name, "title"), song.title.unwrap_or_default(), true);
+    publish(&topics.player(name, "artist"), song.artist.unwrap_or_default(), true);
+    publish(&topics.player(name, "album"), song.album.unwrap_or_default(), true);
+    publish(&topics.player(name, "duration"), song.duration.map(|d| format!("{:.0}", d)).unwrap_or_default(), true);
+    publish(&topics.player(name, "albumart_url"), song.cover_art_url.unwrap_or_default(), true);
+    if let Some(position) = player.get_position() {
+        publish(&topics.player(name, "position"), format!("{:.1}", position), false);
+    }
+}
+
+/// Handle a message on one of the command topics
+fn handle_message(topics: &Topics, topic: &str, payload: &str) {
+    let Some(controller) = get_controller() else {
+        return;
+    };
+
+    if topic == topics.volume_set() {
+        match payload.trim().parse::<f64>() {
+            Ok(volume) => {
+                controller.set_volume(volume.clamp(0.0, 100.0));
+            }
+            Err(_) => warn!("Invalid MQTT volume: {}", payload),
+        }
+        return;
+    }
+
+    let Some(command) = parse_command(payload) else {
+        warn!("Invalid MQTT command on {}: {}", topic, payload);
+        return;
+    };
+
+    if topic == topics.command() {
+        debug!("MQTT command for the active player: {}", command);
+        controller.send_command(command);
+        return;
+    }
+
+    // <base>/<player>/command
+    let player_name = controller.list_controllers().into_iter()
+        .map(|player| player.read().get_player_name())
+        .find(|name| topics.player(name, "command") == topic);
+    // Resolved like API commands, so merged players get the command through their preferred controller
+    let Some(player) = player_name.as_deref().and_then(|name| controller.get_preferred_player_by_name(name)) else {
+        warn!("MQTT command for unknown player on {}", topic);
+        return;
+    };
+    let player = player.read();
+    // Same playback limits as for API commands, e.g. quiet hours
+    if let Err(reason) = controller.check_command(&command, player.get_playback_state()) {
+        warn!("Not sending MQTT command {} to {}: {}", command, player.get_player_name(), reason);
+        return;
+    }
+    debug!("MQTT command for {}: {}", player.get_player_name(), command);
+    player.send_command(command);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics() {
+        let topics = Topics::new("audiocontrol/", "A1B2-c3");
+        assert_eq!(topics.availability(), "audiocontrol/a1b2_c3/availability");
+        assert_eq!(topics.player("Spotify Connect", "state"), "audiocontrol/a1b2_c3/spotify_connect/state");
+        assert_eq!(slug("--"), "player");
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("next"), Some(PlayerCommand::Next));
+        assert_eq!(parse_command(" PLAYPAUSE\n"), Some(PlayerCommand::PlayPause));
+        assert_eq!(parse_command(r#"{"seek": 30.0}"#), Some(PlayerCommand::Seek(30.0)));
+        assert_eq!(parse_command("louder"), None);
+    }
+
+    #[test]
+    fn test_discovery_config() {
+        let config = MqttConfig::default();
+        let topics = Topics::new(&config.topic_prefix, "abc");
+        let device = device_identity::DeviceIdentity {
+            id: "abc".to_string(),
+            name: "Living Room".to_string(),
+        };
+
+        let (topic, payload) = discovery_config(&config, &topics, &device, "mpd");
+        assert_eq!(topic, "homeassistant/media_player/abc_mpd/config");
+        assert_eq!(payload["unique_id"], "audiocontrol_abc_mpd");
+        assert_eq!(payload["device"]["name"], "Living Room");
+        assert_eq!(payload["availability_topic"], "audiocontrol/abc/availability");
+        assert_eq!(payload["state_title_topic"], "audiocontrol/abc/mpd/title");
+        assert_eq!(payload["command_next_topic"], "audiocontrol/abc/mpd/command");
+        assert_eq!(payload["command_next_payload"], "next");
+    }
+}
@@ -6,7 +6,7 @@ use crate::config::get_service_config;
 use log::{info, error, debug, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
 use deunicode::deunicode;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use urlencoding::encode;
 
 /// Global flag to indicate if MusicBrainz lookups are enabled
@@ -18,7 +18,7 @@ pub const ARTIST_MBID_PARTIAL_CACHE_PREFIX: &str = "artist::mbid_partial::";
 pub const ARTIST_NOT_FOUND_CACHE_PREFIX: &str = "artist::mbid_not_found::";
 
 // Cache timeout for not found entries (48 hours in seconds)
-const NOT_FOUND_CACHE_TIMEOUT_SECONDS: i64 = 48 * 60 * 60;
+const NOT_FOUND_CACHE_TIMEOUT_SECONDS: u64 = 48 * 60 * 60;
 
 // MusicBrainz API Constants
 const MUSICBRAINZ_API_BASE: &str = "https://musicbrainz.org/ws/2";
@@ -442,7 +442,7 @@ fn search_musicbrainz_for_artist(artist_name: &str, cache_only: bool) -> MusicBr
             error!("Failed to execute MusicBrainz API request: {}", e);
             // Add to negative cache with 48-hour expiry before returning
             let not_found_cache_key = format!("{}{}", ARTIST_NOT_FOUND_CACHE_PREFIX, artist_name);
-            if let Err(cache_err) = attributecache::set_with_expiry(&not_found_cache_key, &true, Some(NOT_FOUND_CACHE_TIMEOUT_SECONDS)) {
+            if let Err(cache_err) = attributecache::set_with_ttl(&not_found_cache_key, &true, NOT_FOUND_CACHE_TIMEOUT_SECONDS) {
                 debug!("Failed to cache API failure for '{}': {}", artist_name, cache_err);
             } else {
                 debug!("Cached API failure for '{}' with 48-hour expiry", artist_name);
@@ -494,7 +494,7 @@ fn search_musicbrainz_for_artist(artist_name: &str, cache_only: bool) -> MusicBr
                     // No matching artist found, add to negative cache with 48-hour expiry
                     debug!("Found artist but names don't match: '{}' vs '{}'", artist_name, response_name);
                     let not_found_cache_key = format!("{}{}", ARTIST_NOT_FOUND_CACHE_PREFIX, artist_name);
-                    if let Err(cache_err) = attributecache::set_with_expiry(&not_found_cache_key, &true, Some(NOT_FOUND_CACHE_TIMEOUT_SECONDS)) {
+                    if let Err(cache_err) = attributecache::set_with_ttl(&not_found_cache_key, &true, NOT_FOUND_CACHE_TIMEOUT_SECONDS) {
                         debug!("Failed to cache name mismatch for '{}': {}", artist_name, cache_err);
                     } else {
                         debug!("Cached name mismatch for '{}' with 48-hour expiry", artist_name);
@@ -504,7 +504,7 @@ fn search_musicbrainz_for_artist(artist_name: &str, cache_only: bool) -> MusicBr
                 // No results found, add to negative cache with 48-hour expiry
                 debug!("No results found for artist '{}'", artist_name);
                 let not_found_cache_key = format!("{}{}", ARTIST_NOT_FOUND_CACHE_PREFIX, artist_name);
-                if let Err(cache_err) = attributecache::set_with_expiry(&not_found_cache_key, &true, Some(NOT_FOUND_CACHE_TIMEOUT_SECONDS)) {
+                if let Err(cache_err) = attributecache::set_with_ttl(&not_found_cache_key, &true, NOT_FOUND_CACHE_TIMEOUT_SECONDS) {
                     debug!("Failed to cache no results for '{}': {}", artist_name, cache_err);
                 } else {
                     debug!("Cached no results for '{}' with 48-hour expiry", artist_name);
@@ -520,7 +520,7 @@ fn search_musicbrainz_for_artist(artist_name: &str, cache_only: bool) -> MusicBr
             debug!("Full response JSON: {}", response);
             // Add to negative cache with 48-hour expiry before returning
             let not_found_cache_key = format!("{}{}", ARTIST_NOT_FOUND_CACHE_PREFIX, artist_name);
-            if let Err(cache_err) = attributecache::set_with_expiry(&not_found_cache_key, &true, Some(NOT_FOUND_CACHE_TIMEOUT_SECONDS)) {
+            if let Err(cache_err) = attributecache::set_with_ttl(&not_found_cache_key, &true, NOT_FOUND_CACHE_TIMEOUT_SECONDS) {
                 debug!("Failed to cache parse error for '{}': {}", artist_name, cache_err);
             } else {
                 debug!("Cached parse error for '{}' with 48-hour expiry", artist_name);
@@ -681,7 +681,7 @@ pub fn search_mbids_for_artist(artist_name: &str, allow_multiple: bool,
             // If we reached here, the artist was not found. Cache this result if requested.
             if cache_failures {
                 let not_found_cache_key = format!("{}{}", ARTIST_NOT_FOUND_CACHE_PREFIX, artist_name);
-                match attributecache::set_with_expiry(&not_found_cache_key, &true, Some(NOT_FOUND_CACHE_TIMEOUT_SECONDS)) {
+                match attributecache::set_with_ttl(&not_found_cache_key, &true, NOT_FOUND_CACHE_TIMEOUT_SECONDS) {
                     Ok(_) => {
                         debug!("Cached '{}' as not found with 48-hour expiry to prevent future lookups", artist_name);
                     },
@@ -874,3 +874,122 @@ pub fn search_release_group_genres(artist: &str, album: &str) -> Vec<String> {
     genres
 }
 
+
+/// A release found by the disc ID of a CD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscRelease {
+    pub release_id: String,
+    pub title: String,
+    pub artist: String,
+    pub date: Option<String>,
+    pub tracks: Vec<DiscReleaseTrack>,
+}
+
+/// A track of a release found by disc ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscReleaseTrack {
+    pub number: u32,
+    pub title: String,
+    pub artist: String,
+    pub length_ms: Option<u64>,
+}
+
+/// Look up a CD on MusicBrainz by its disc ID
+///
+/// If the disc ID is unknown, MusicBrainz uses the TOC to find releases with a similar track layout.
+///
+/// # Arguments
+/// * `disc_id` - The MusicBrainz disc ID
+/// * `toc` - Optional TOC in MusicBrainz format ("first last leadout offset1 offset2 ...")
+///
+/// # Returns
+/// * `Ok(None)` if no release has been found
+pub fn lookup_disc(disc_id: &str, toc: Option<&str>) -> Result<Option<DiscRelease>, String> {
+    if !is_enabled() {
+        debug!("MusicBrainz lookups are disabled, skipping disc lookup");
+        return Ok(None);
+    }
+
+    let mut url = format!("{}/discid/{}?inc=artist-credits+recordings&fmt=json", MUSICBRAINZ_API_BASE, encode(disc_id));
+    if let Some(toc) = toc {
+        url.push_str(&format!("&toc={}", toc.replace(' ', "+")));
+    }
+
+    ratelimit::rate_limit("musicbrainz");
+    let body = musicbrainz_api_get(&url)?;
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse MusicBrainz disc response: {}", e))?;
+
+    Ok(parse_disc_release(&json, disc_id))
+}
+
+/// Join the names of an artist-credit list
+fn artist_credit_name(credit: &serde_json::Value) -> String {
+    credit
+        .as_array()
+        .map(|credits| {
+            credits
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{}{}",
+                        c.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                        c.get("joinphrase").and_then(|j| j.as_str()).unwrap_or("")
+                    )
+                })
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the first release of a disc ID response, using the medium that contains the disc
+fn parse_disc_release(json: &serde_json::Value, disc_id: &str) -> Option<DiscRelease> {
+    let release = json.get("releases")?.as_array()?.first()?;
+    let artist = artist_credit_name(release.get("artist-credit").unwrap_or(&serde_json::Value::Null));
+
+    let media = release.get("media")?.as_array()?;
+    let medium = media
+        .iter()
+        .find(|m| {
+            m.get("discs")
+                .and_then(|d| d.as_array())
+                .is_some_and(|discs| discs.iter().any(|d| d.get("id").and_then(|id| id.as_str()) == Some(disc_id)))
+        })
+        .or_else(|| media.first())?;
+
+    let tracks = medium
+        .get("tracks")
+        .and_then(|t| t.as_array())
+        .map(|tracks| {
+            tracks
+                .iter()
+                .enumerate()
+                .map(|(index, track)| {
+                    let track_artist = track
+                        .get("artist-credit")
+                        .map(artist_credit_name)
+                        .filter(|a| !a.is_empty())
+                        .unwrap_or_else(|| artist.clone());
+                    DiscReleaseTrack {
+                        number: track
+                            .get("position")
+                            .and_then(|p| p.as_u64())
+                            .map(|p| p as u32)
+                            .unwrap_or(index as u32 + 1),
+                        title: track.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+                        artist: track_artist,
+                        length_ms: track.get("length").and_then(|l| l.as_u64()),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(DiscRelease {
+        release_id: release.get("id")?.as_str()?.to_string(),
+        title: release.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+        artist,
+        date: release.get("date").and_then(|d| d.as_str()).filter(|d| !d.is_empty()).map(|d| d.to_string()),
+        tracks,
+    })
+}
@@ -0,0 +1,116 @@
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Interface to use for outgoing connections, by host name
+static BIND_INTERFACES: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Remove the brackets around an IPv6 literal, e.g. "[::1]" -> "::1"
+pub fn strip_brackets(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Host as it must appear in "host:port" strings and URLs, IPv6 literals are put in brackets
+pub fn url_host(host: &str) -> String {
+    let host = strip_brackets(host);
+    if host.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()) {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// "host:port" that also works for IPv6 literals
+pub fn host_port(host: &str, port: u16) -> String {
+    format!("{}:{}", url_host(host), port)
+}
+
+/// Use the given network interface for all outgoing connections to a host
+///
+/// Multi-homed devices otherwise pick the interface from the routing table,
+/// which is not always the one the server can be reached on.
+pub fn set_bind_interface(host: &str, interface: &str) {
+    let host = strip_brackets(host).to_string();
+    if interface.is_empty() {
+        BIND_INTERFACES.write().remove(&host);
+    } else {
+        debug!("Connections to {} will use interface {}", host, interface);
+        BIND_INTERFACES.write().insert(host, interface.to_string());
+    }
+}
+
+pub fn get_bind_interface(host: &str) -> Option<String> {
+    BIND_INTERFACES.read().get(strip_brackets(host)).cloned()
+}
+
+/// Connect to a host name, IPv4 or IPv6 literal
+///
+/// All resolved addresses are tried in order. The interface set with
+/// `set_bind_interface` for the host is used if there is one.
+pub fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    connect_with_timeout(host, port, None)
+}
+
+pub fn connect_with_timeout(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let interface = get_bind_interface(host);
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("{} could not be resolved", host));
+    for addr in (strip_brackets(host), port).to_socket_addrs()? {
+        let result = match &interface {
+            Some(interface) => connect_via(&addr, interface, timeout),
+            None => match timeout {
+                Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+                None => TcpStream::connect(addr),
+            },
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                debug!("Connection to {} failed: {}", addr, e);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn connect_via(addr: &SocketAddr, interface: &str, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    use socket2::{Domain, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(*addr), Type::STREAM, None)?;
+    socket.bind_device(Some(interface.as_bytes()))?;
+    match timeout {
+        Some(timeout) => socket.connect_timeout(&(*addr).into(), timeout)?,
+        None => socket.connect(&(*addr).into())?,
+    }
+    Ok(socket.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn connect_via(addr: &SocketAddr, interface: &str, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    log::warn!("Binding to interface {} is not supported on this platform, ignoring it", interface);
+    match timeout {
+        Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+        None => TcpStream::connect(addr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_port() {
+        assert_eq!(host_port("localhost", 6600), "localhost:6600");
+        assert_eq!(host_port("192.168.1.2", 6600), "192.168.1.2:6600");
+        assert_eq!(host_port("fd00::2", 6600), "[fd00::2]:6600");
+        assert_eq!(host_port("[fd00::2]", 9000), "[fd00::2]:9000");
+        assert_eq!(strip_brackets("[::1]"), "::1");
+    }
+}
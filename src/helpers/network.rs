@@ -0,0 +1,283 @@
+use crate::audiocontrol::eventbus::EventBus;
+use crate::config::get_service_config;
+use crate::data::PlayerEvent;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Configuration of the `network_monitor` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkMonitorConfig {
+    #[serde(default = "default_true")]
+    pub enable: bool,
+
+    /// Wait this long after a change for further changes before checking the addresses
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+
+    /// Check interval if netlink is not available
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_debounce_ms() -> u64 {
+    2000
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+impl Default for NetworkMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            debounce_ms: default_debounce_ms(),
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+/// Addresses of the network interfaces, loopback and link-local addresses are ignored
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkState {
+    /// "interface: address", sorted
+    pub addresses: Vec<String>,
+}
+
+impl NetworkState {
+    pub fn current() -> Self {
+        let interfaces = match get_if_addrs::get_if_addrs() {
+            Ok(interfaces) => interfaces,
+            Err(e) => {
+                warn!("Failed to list network interfaces: {}", e);
+                return Self::default();
+            }
+        };
+        Self::from_addresses(interfaces.into_iter().map(|i| (i.name.clone(), i.ip())))
+    }
+
+    fn from_addresses(addresses: impl IntoIterator<Item = (String, IpAddr)>) -> Self {
+        let mut addresses: Vec<String> = addresses
+            .into_iter()
+            .filter(|(_, ip)| !ip.is_loopback() && !is_link_local(ip))
+            .map(|(name, ip)| format!("{}: {}", name, ip))
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+        Self { addresses }
+    }
+
+    pub fn is_online(&self) -> bool {
+        !self.addresses.is_empty()
+    }
+}
+
+/// Link-local addresses, 169.254.0.0/16 and fe80::/10
+pub fn is_link_local(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+type ReconnectHandler = Arc<dyn Fn() + Send + Sync>;
+
+static HANDLERS: Lazy<Mutex<HashMap<String, ReconnectHandler>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static STATE: Lazy<RwLock<NetworkState>> = Lazy::new(|| RwLock::new(NetworkState::default()));
+
+/// Register a function that drops and re-establishes network connections
+///
+/// Handlers are called when the network comes back or the addresses change, so clients
+/// don't wait for TCP timeouts on connections that are dead after a Wi-Fi drop or router reboot.
+/// A handler registered with the same name replaces the previous one.
+pub fn register_reconnect_handler(name: &str, handler: impl Fn() + Send + Sync + 'static) {
+    HANDLERS.lock().insert(name.to_string(), Arc::new(handler));
+}
+
+pub fn unregister_reconnect_handler(name: &str) {
+    HANDLERS.lock().remove(name);
+}
+
+/// Start watching the network interfaces from the `network_monitor` service configuration
+///
+/// Changes are detected with netlink, polling is used if netlink is not available.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let monitor_config = match get_service_config(config, "network_monitor") {
+        Some(c) => match serde_json::from_value::<NetworkMonitorConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid network_monitor configuration, using defaults: {}", e);
+                NetworkMonitorConfig::default()
+            }
+        },
+        None => NetworkMonitorConfig::default(),
+    };
+    if !monitor_config.enable {
+        debug!("Network monitor is disabled");
+        return;
+    }
+
+    *STATE.write() = NetworkState::current();
+    info!("Watching network changes, current addresses: {:?}", STATE.read().addresses);
+
+    thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        match netlink::NetlinkSocket::open() {
+            Ok(socket) => {
+                let debounce = Duration::from_millis(monitor_config.debounce_ms);
+                loop {
+                    if let Err(e) = socket.wait_for_change(debounce) {
+                        warn!("Netlink monitoring failed, polling network interfaces instead: {}", e);
+                        break;
+                    }
+                    check_network();
+                }
+            }
+            Err(e) => warn!("Netlink not available, polling network interfaces instead: {}", e),
+        }
+
+        let interval = Duration::from_secs(monitor_config.poll_interval_secs.max(1));
+        loop {
+            thread::sleep(interval);
+            check_network();
+        }
+    });
+}
+
+/// Compare the addresses with the last check, send an event and reconnect clients on changes
+fn check_network() {
+    let current = NetworkState::current();
+    let previous = std::mem::replace(&mut *STATE.write(), current.clone());
+    if current == previous {
+        return;
+    }
+
+    info!("Network changed: {:?} -> {:?}", previous.addresses, current.addresses);
+    EventBus::instance().publish(PlayerEvent::NetworkChanged {
+        online: current.is_online(),
+        addresses: current.addresses.clone(),
+    });
+
+    if current.is_online() {
+        let handlers: Vec<(String, ReconnectHandler)> = HANDLERS.lock().iter().map(|(n, h)| (n.clone(), h.clone())).collect();
+        for (name, handler) in handlers {
+            debug!("Reconnecting {} after network change", name);
+            handler();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod netlink {
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::Duration;
+
+    /// Socket receiving link and address changes from the kernel
+    pub struct NetlinkSocket {
+        fd: OwnedFd,
+    }
+
+    impl NetlinkSocket {
+        pub fn open() -> io::Result<Self> {
+            // SAFETY: plain socket calls, the address is a zeroed sockaddr_nl of the size passed
+            unsafe {
+                let fd = libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_ROUTE);
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let fd = OwnedFd::from_raw_fd(fd);
+
+                let mut addr: libc::sockaddr_nl = std::mem::zeroed();
+                addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+                addr.nl_groups = (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+                let result = libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                );
+                if result < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(Self { fd })
+            }
+        }
+
+        /// Block until the kernel reports a change and no further change follows within `debounce`
+        ///
+        /// The messages are not parsed, the caller reads the resulting addresses.
+        pub fn wait_for_change(&self, debounce: Duration) -> io::Result<()> {
+            self.wait(None)?;
+            self.drain();
+            while self.wait(Some(debounce))? {
+                self.drain();
+            }
+            Ok(())
+        }
+
+        /// Wait until a message is available, returns false on timeout
+        fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout_ms = timeout.map(|t| t.as_millis().min(i32::MAX as u128) as i32).unwrap_or(-1);
+            loop {
+                // SAFETY: pollfd is valid for the duration of the call
+                let result = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+                if result >= 0 {
+                    return Ok(result > 0);
+                }
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+        }
+
+        fn drain(&self) {
+            let mut buffer = [0u8; 8192];
+            loop {
+                // SAFETY: the buffer is valid for its length
+                let received = unsafe {
+                    libc::recv(self.fd.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len(), libc::MSG_DONTWAIT)
+                };
+                if received <= 0 {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_state_ignores_local_addresses() {
+        let state = NetworkState::from_addresses(vec![
+            ("wlan0".to_string(), "192.168.1.20".parse().unwrap()),
+            ("lo".to_string(), "127.0.0.1".parse().unwrap()),
+            ("lo".to_string(), "::1".parse().unwrap()),
+            ("wlan0".to_string(), "fe80::1".parse().unwrap()),
+            ("eth0".to_string(), "169.254.3.4".parse().unwrap()),
+            ("eth0".to_string(), "2001:db8::2".parse().unwrap()),
+        ]);
+        assert_eq!(state.addresses, vec!["eth0: 2001:db8::2", "wlan0: 192.168.1.20"]);
+        assert!(state.is_online());
+        assert!(!NetworkState::from_addresses(vec![("lo".to_string(), "127.0.0.1".parse().unwrap())]).is_online());
+    }
+}
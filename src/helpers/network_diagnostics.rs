@@ -0,0 +1,559 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::PlaybackState;
+use crate::helpers::netaddr;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SPOTIFY_AP_RESOLVER: &str = "https://apresolve.spotify.com/?type=accesspoint";
+const SPOTIFY_AP_FALLBACK: &str = "ap.spotify.com:443";
+
+/// Port probed on the default gateway. A refused connection is answered as fast as an
+/// accepted one, so the port doesn't need to be open.
+const GATEWAY_PORT: u16 = 53;
+
+/// Configuration of the `network_diagnostics` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkDiagnosticsConfig {
+    /// Additional endpoints, as URL or "host:port"
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+
+    /// Connection attempts per endpoint
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+
+    /// Timeout of a single attempt
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Watch playing players for stalls
+    #[serde(default = "default_true")]
+    pub detect_stalls: bool,
+
+    /// Seconds the position must stand still during playback to count as a stall
+    #[serde(default = "default_stall_threshold_secs")]
+    pub stall_threshold_secs: u64,
+
+    /// Stalls older than this are not reported
+    #[serde(default = "default_stall_window_minutes")]
+    pub stall_window_minutes: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_attempts() -> u32 {
+    5
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_stall_threshold_secs() -> u64 {
+    5
+}
+
+fn default_stall_window_minutes() -> u64 {
+    60
+}
+
+impl Default for NetworkDiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            attempts: default_attempts(),
+            timeout_ms: default_timeout_ms(),
+            detect_stalls: true,
+            stall_threshold_secs: default_stall_threshold_secs(),
+            stall_window_minutes: default_stall_window_minutes(),
+        }
+    }
+}
+
+/// An endpoint to measure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Endpoint {
+    /// "gateway", "lms", "spotify", "stream" or "custom"
+    pub kind: String,
+    /// Description, e.g. the player playing a stream
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    pub fn new(kind: &str, name: &str, host: &str, port: u16) -> Self {
+        Self {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            host: netaddr::strip_brackets(host).to_string(),
+            port,
+        }
+    }
+
+    /// Endpoint from a URL or "host:port", the port defaults to the one of the URL scheme
+    pub fn parse(kind: &str, name: &str, target: &str) -> Option<Self> {
+        if let Ok(url) = url::Url::parse(target) {
+            if let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) {
+                return Some(Self::new(kind, name, host, port));
+            }
+        }
+        let (host, port) = target.rsplit_once(':')?;
+        Some(Self::new(kind, name, host, port.parse().ok()?))
+    }
+}
+
+/// Latency statistics of the successful attempts, in ms
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    /// Mean difference between consecutive attempts
+    pub jitter: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let jitter = if samples.len() > 1 {
+            samples.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (samples.len() - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Self {
+            min: samples.iter().cloned().fold(f64::INFINITY, f64::min),
+            avg: samples.iter().sum::<f64>() / samples.len() as f64,
+            max: samples.iter().cloned().fold(0.0, f64::max),
+            jitter,
+        })
+    }
+}
+
+/// Measurement of a single endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointResult {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    /// Resolved address that was measured
+    pub address: Option<String>,
+    /// Time for resolving the host name, in ms
+    pub dns_ms: Option<f64>,
+    pub attempts: u32,
+    pub received: u32,
+    pub packet_loss_percent: f64,
+    pub latency_ms: Option<LatencyStats>,
+    pub error: Option<String>,
+}
+
+impl EndpointResult {
+    fn reachable(&self) -> bool {
+        self.received > 0
+    }
+}
+
+/// Overall assessment of the measurements
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Assessment {
+    /// All endpoints answered without loss
+    Ok,
+    /// The gateway can't be reached or loses packets, e.g. weak Wi-Fi
+    LocalNetwork,
+    /// The local network works, but some endpoints don't answer or lose packets
+    Service,
+    /// Nothing could be measured
+    Unknown,
+}
+
+/// Playback stalls of a player, where it was playing but the position didn't advance
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerStalls {
+    pub player_name: String,
+    /// Stalls within the stall window
+    pub count: usize,
+    /// Unix timestamp of the last stall
+    pub last_stall: Option<u64>,
+}
+
+/// Result of a diagnostics run
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkDiagnostics {
+    pub assessment: Assessment,
+    pub endpoints: Vec<EndpointResult>,
+    pub stalls: Vec<PlayerStalls>,
+    pub stall_window_minutes: u64,
+}
+
+/// Measure an endpoint with TCP connections, ICMP needs privileges AudioControl doesn't have
+///
+/// A refused connection counts as answered, the round trip is the same.
+pub fn probe(endpoint: &Endpoint, attempts: u32, timeout: Duration) -> EndpointResult {
+    let mut result = EndpointResult {
+        endpoint: endpoint.clone(),
+        address: None,
+        dns_ms: None,
+        attempts,
+        received: 0,
+        packet_loss_percent: 100.0,
+        latency_ms: None,
+        error: None,
+    };
+
+    let start = Instant::now();
+    let address: Option<SocketAddr> = match (endpoint.host.as_str(), endpoint.port).to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        Err(e) => {
+            result.error = Some(format!("Can't resolve {}: {}", endpoint.host, e));
+            return result;
+        }
+    };
+    result.dns_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+    let Some(address) = address else {
+        result.error = Some(format!("No address for {}", endpoint.host));
+        return result;
+    };
+    result.address = Some(address.to_string());
+
+    let mut samples = Vec::new();
+    for attempt in 0..attempts {
+        if attempt > 0 {
+            thread::sleep(Duration::from_millis(200));
+        }
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => samples.push(start.elapsed().as_secs_f64() * 1000.0),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => samples.push(start.elapsed().as_secs_f64() * 1000.0),
+            Err(e) => {
+                debug!("Probe {} of {} failed: {}", attempt + 1, address, e);
+                result.error = Some(e.to_string());
+            }
+        }
+    }
+
+    result.received = samples.len() as u32;
+    if attempts > 0 {
+        result.packet_loss_percent = 100.0 * (attempts - result.received) as f64 / attempts as f64;
+    }
+    result.latency_ms = LatencyStats::from_samples(&samples);
+    if result.received == attempts {
+        result.error = None;
+    }
+    result
+}
+
+/// Decide whether problems are caused by the local network or by the services
+pub fn assess(results: &[EndpointResult]) -> Assessment {
+    if results.iter().all(|r| r.address.is_none()) {
+        return Assessment::Unknown;
+    }
+    if let Some(gateway) = results.iter().find(|r| r.endpoint.kind == "gateway") {
+        if gateway.packet_loss_percent > 0.0 {
+            return Assessment::LocalNetwork;
+        }
+    }
+    if results.iter().all(|r| r.reachable()) && results.iter().all(|r| r.packet_loss_percent == 0.0) {
+        return Assessment::Ok;
+    }
+    let gateway_known = results.iter().any(|r| r.endpoint.kind == "gateway");
+    if gateway_known || results.iter().any(|r| r.reachable() && r.packet_loss_percent == 0.0) {
+        Assessment::Service
+    } else {
+        Assessment::LocalNetwork
+    }
+}
+
+/// Default IPv4 gateway from the routing table
+fn default_gateway() -> Option<IpAddr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    parse_default_gateway(&routes)
+}
+
+fn parse_default_gateway(routes: &str) -> Option<IpAddr> {
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok().filter(|g| *g != 0)?;
+        // The address is printed as the network order bytes read as a native integer
+        Some(IpAddr::V4(Ipv4Addr::from(gateway.to_ne_bytes())))
+    })
+}
+
+/// First Spotify access point, as used by librespot
+fn spotify_access_point() -> String {
+    let resolved = ureq::get(SPOTIFY_AP_RESOLVER)
+        .timeout(Duration::from_secs(3))
+        .call()
+        .ok()
+        .and_then(|response| response.into_string().ok())
+        .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+        .and_then(|json| json["accesspoint"].get(0).and_then(|ap| ap.as_str()).map(|ap| ap.to_string()));
+    resolved.unwrap_or_else(|| SPOTIFY_AP_FALLBACK.to_string())
+}
+
+/// Endpoints to measure: gateway, LMS server, Spotify, streams that are playing and configured endpoints
+fn collect_endpoints(config: &NetworkDiagnosticsConfig) -> Vec<Endpoint> {
+    let mut endpoints = Vec::new();
+    if let Some(gateway) = default_gateway() {
+        endpoints.push(Endpoint::new("gateway", "Default gateway", &gateway.to_string(), GATEWAY_PORT));
+    }
+    if let Some(server) = crate::players::lms::lmsserver::get_connected_server() {
+        endpoints.push(Endpoint::new("lms", &server.name, &server.ip.to_string(), server.port));
+    }
+    if let Some(endpoint) = Endpoint::parse("spotify", "Spotify access point", &spotify_access_point()) {
+        endpoints.push(endpoint);
+    }
+    if let Some(controller) = get_controller() {
+        for ctrl in controller.list_controllers() {
+            let player = ctrl.read();
+            let stream_url = player.get_song().and_then(|s| s.stream_url);
+            if let Some(url) = stream_url.filter(|u| u.starts_with("http://") || u.starts_with("https://")) {
+                if let Some(endpoint) = Endpoint::parse("stream", &player.get_player_name(), &url) {
+                    endpoints.push(endpoint);
+                }
+            }
+        }
+    }
+    for target in &config.endpoints {
+        match Endpoint::parse("custom", target, target) {
+            Some(endpoint) => endpoints.push(endpoint),
+            None => warn!("Invalid diagnostics endpoint '{}', use a URL or host:port", target),
+        }
+    }
+    let mut seen = Vec::new();
+    endpoints.retain(|e| {
+        let key = (e.host.clone(), e.port);
+        let new = !seen.contains(&key);
+        seen.push(key);
+        new
+    });
+    endpoints
+}
+
+/// Measure all endpoints in parallel and report the recent playback stalls
+pub fn run_diagnostics(attempts: Option<u32>) -> NetworkDiagnostics {
+    let config = CONFIG.read().clone();
+    let attempts = attempts.unwrap_or(config.attempts).clamp(1, 20);
+    let timeout = Duration::from_millis(config.timeout_ms.max(100));
+    let endpoints = collect_endpoints(&config);
+    info!("Running network diagnostics for {} endpoints", endpoints.len());
+
+    let results: Vec<EndpointResult> = thread::scope(|scope| {
+        let handles: Vec<_> = endpoints.iter().map(|e| scope.spawn(move || probe(e, attempts, timeout))).collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    NetworkDiagnostics {
+        assessment: assess(&results),
+        endpoints: results,
+        stalls: get_stalls(),
+        stall_window_minutes: config.stall_window_minutes,
+    }
+}
+
+/// Detects stalls from successive position readings of a player
+#[derive(Debug, Clone)]
+pub struct StallDetector {
+    threshold: Duration,
+    last_position: Option<f64>,
+    last_advance: Option<Instant>,
+    /// The position has been seen advancing, players that don't report positions are ignored
+    advancing: bool,
+    stalled: bool,
+}
+
+impl StallDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last_position: None,
+            last_advance: None,
+            advancing: false,
+            stalled: false,
+        }
+    }
+
+    /// Record a reading, returns true when a new stall starts
+    pub fn update(&mut self, playing: bool, position: Option<f64>, now: Instant) -> bool {
+        let (true, Some(position)) = (playing, position) else {
+            *self = Self::new(self.threshold);
+            return false;
+        };
+        let advanced = self.last_position.is_some_and(|last| position > last);
+        let jumped = self.last_position.is_some_and(|last| position < last);
+        self.last_position = Some(position);
+        if advanced || jumped || self.last_advance.is_none() {
+            // A jump back is a seek or the next song, it doesn't count as progress
+            self.advancing |= advanced;
+            self.last_advance = Some(now);
+            self.stalled = false;
+            return false;
+        }
+        let since_advance = self.last_advance.map(|t| now.saturating_duration_since(t)).unwrap_or_default();
+        if self.advancing && !self.stalled && since_advance >= self.threshold {
+            self.stalled = true;
+            return true;
+        }
+        false
+    }
+}
+
+static CONFIG: Lazy<RwLock<NetworkDiagnosticsConfig>> = Lazy::new(|| RwLock::new(NetworkDiagnosticsConfig::default()));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+/// Times of stalls by player name
+static STALLS: Lazy<RwLock<HashMap<String, VecDeque<SystemTime>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn get_controller() -> Option<std::sync::Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+/// Stalls within the stall window, by player
+pub fn get_stalls() -> Vec<PlayerStalls> {
+    let window = Duration::from_secs(CONFIG.read().stall_window_minutes * 60);
+    let now = SystemTime::now();
+    let mut stalls: Vec<PlayerStalls> = STALLS
+        .read()
+        .iter()
+        .map(|(player_name, times)| {
+            let recent: Vec<&SystemTime> = times
+                .iter()
+                .filter(|t| now.duration_since(**t).map(|age| age <= window).unwrap_or(true))
+                .collect();
+            PlayerStalls {
+                player_name: player_name.clone(),
+                count: recent.len(),
+                last_stall: recent.last().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()),
+            }
+        })
+        .collect();
+    stalls.sort_by(|a, b| a.player_name.cmp(&b.player_name));
+    stalls
+}
+
+fn record_stall(player_name: &str) {
+    warn!("Playback on {} stalled, the stream may be buffering", player_name);
+    let mut stalls = STALLS.write();
+    let times = stalls.entry(player_name.to_string()).or_default();
+    times.push_back(SystemTime::now());
+    // Keep the memory bounded, older stalls are outside of any sensible window
+    while times.len() > 1000 {
+        times.pop_front();
+    }
+}
+
+/// Read the `network_diagnostics` service configuration and start the stall detection
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+    let diagnostics_config = match get_service_config(config, "network_diagnostics") {
+        Some(c) => match serde_json::from_value::<NetworkDiagnosticsConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid network_diagnostics configuration, using defaults: {}", e);
+                NetworkDiagnosticsConfig::default()
+            }
+        },
+        None => NetworkDiagnosticsConfig::default(),
+    };
+    *CONFIG.write() = diagnostics_config.clone();
+    if !diagnostics_config.detect_stalls {
+        debug!("Playback stall detection is disabled");
+        return;
+    }
+
+    let threshold = Duration::from_secs(diagnostics_config.stall_threshold_secs.max(2));
+    thread::spawn(move || {
+        let mut detectors: HashMap<String, StallDetector> = HashMap::new();
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            let Some(controller) = get_controller() else {
+                continue;
+            };
+            let now = Instant::now();
+            for ctrl in controller.list_controllers() {
+                let player = ctrl.read();
+                let playing = player.get_playback_state() == PlaybackState::Playing;
+                let position = if playing { player.get_position() } else { None };
+                let name = player.get_player_name();
+                let detector = detectors.entry(name.clone()).or_insert_with(|| StallDetector::new(threshold));
+                if detector.update(playing, position, now) {
+                    record_stall(&name);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(kind: &str, attempts: u32, received: u32) -> EndpointResult {
+        EndpointResult {
+            endpoint: Endpoint::new(kind, kind, "192.0.2.1", 80),
+            address: Some("192.0.2.1:80".to_string()),
+            dns_ms: Some(0.1),
+            attempts,
+            received,
+            packet_loss_percent: 100.0 * (attempts - received) as f64 / attempts as f64,
+            latency_ms: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_endpoints_and_assessment() {
+        assert_eq!(Endpoint::parse("stream", "mpd", "http://radio.example.com/live.mp3").unwrap().port, 80);
+        assert_eq!(Endpoint::parse("spotify", "ap", "ap-gew4.spotify.com:4070").unwrap().host, "ap-gew4.spotify.com");
+        assert_eq!(Endpoint::parse("custom", "nas", "[fd00::2]:9000").unwrap().host, "fd00::2");
+        assert!(Endpoint::parse("custom", "bad", "no-port").is_none());
+
+        let routes = "Iface\tDestination\tGateway \tFlags\n\
+                      wlan0\t00000000\t0101A8C0\t0003\n\
+                      wlan0\t0001A8C0\t00000000\t0001\n";
+        assert_eq!(parse_default_gateway(routes), Some("192.168.1.1".parse().unwrap()));
+
+        assert_eq!(assess(&[result("gateway", 5, 5), result("stream", 5, 5)]), Assessment::Ok);
+        assert_eq!(assess(&[result("gateway", 5, 3), result("stream", 5, 3)]), Assessment::LocalNetwork);
+        assert_eq!(assess(&[result("gateway", 5, 5), result("stream", 5, 0)]), Assessment::Service);
+
+        let stats = LatencyStats::from_samples(&[10.0, 20.0, 15.0]).unwrap();
+        assert_eq!((stats.min, stats.avg, stats.max, stats.jitter), (10.0, 15.0, 20.0, 7.5));
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut detector = StallDetector::new(Duration::from_secs(5));
+
+        assert!(!detector.update(true, Some(10.0), at(0)));
+        assert!(!detector.update(true, Some(11.0), at(1)));
+        assert!(!detector.update(true, Some(11.0), at(4)));
+        assert!(detector.update(true, Some(11.0), at(6)));
+        // A stall is counted once
+        assert!(!detector.update(true, Some(11.0), at(8)));
+        assert!(!detector.update(true, Some(12.0), at(9)));
+
+        // Players that never report a moving position are ignored
+        let mut detector = StallDetector::new(Duration::from_secs(5));
+        assert!(!detector.update(true, Some(0.0), at(0)));
+        assert!(!detector.update(true, Some(0.0), at(10)));
+        // Pausing resets the detector
+        assert!(!detector.update(false, None, at(11)));
+    }
+}
@@ -0,0 +1,367 @@
+use crate::helpers::notifications::{Notification, NotificationCategory};
+use base64::Engine;
+use log::debug;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Timeout for connecting to and talking to a notification service
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A service that delivers notifications to the owner of the device
+pub trait NotificationSink: Send + Sync {
+    /// Type of the sink as used in the configuration, e.g. "ntfy"
+    fn sink_type(&self) -> &'static str;
+
+    /// Deliver a notification, blocks until the service accepted it
+    fn send(&self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Configuration of a sink, `type` selects the implementation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    Ntfy {
+        #[serde(default = "default_ntfy_server")]
+        server: String,
+        topic: String,
+        /// Access token for protected topics
+        #[serde(default)]
+        token: Option<String>,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+    },
+    Email {
+        host: String,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        security: SmtpSecurity,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+impl SinkConfig {
+    pub fn build(&self) -> Box<dyn NotificationSink> {
+        match self.clone() {
+            SinkConfig::Ntfy { server, topic, token } => Box::new(NtfySink { server, topic, token }),
+            SinkConfig::Telegram { bot_token, chat_id } => Box::new(TelegramSink { bot_token, chat_id }),
+            SinkConfig::Email { host, port, security, username, password, from, to } => Box::new(EmailSink {
+                port: port.unwrap_or(security.default_port()),
+                host,
+                security,
+                credentials: username.zip(password),
+                from,
+                to,
+            }),
+        }
+    }
+}
+
+/// Publishes to a topic of an ntfy server, https://ntfy.sh or self-hosted
+pub struct NtfySink {
+    server: String,
+    topic: String,
+    token: Option<String>,
+}
+
+impl NotificationSink for NtfySink {
+    fn sink_type(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), String> {
+        let (priority, tag) = match notification.category {
+            NotificationCategory::Errors => (4, "warning"),
+            NotificationCategory::AuthExpiry => (4, "key"),
+            NotificationCategory::WeeklyDigest => (2, "notes"),
+            NotificationCategory::Alarm => (5, "alarm_clock"),
+        };
+        // JSON publishing allows non-ASCII titles, which headers don't
+        let payload = serde_json::json!({
+            "topic": self.topic,
+            "title": notification.title,
+            "message": notification.message,
+            "priority": priority,
+            "tags": [tag],
+        });
+        let mut request = ureq::post(self.server.trim_end_matches('/'))
+            .timeout(TIMEOUT)
+            .set("Content-Type", "application/json");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        request
+            .send_string(&payload.to_string())
+            .map(|_| ())
+            .map_err(|e| format!("ntfy: {}", e))
+    }
+}
+
+/// Sends messages with a Telegram bot to a chat
+pub struct TelegramSink {
+    bot_token: String,
+    chat_id: String,
+}
+
+impl NotificationSink for TelegramSink {
+    fn sink_type(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), String> {
+        let payload = serde_json::json!({
+            "chat_id": self.chat_id,
+            "text": format!("{}\n\n{}", notification.title, notification.message),
+        });
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        match ureq::post(&url)
+            .timeout(TIMEOUT)
+            .set("Content-Type", "application/json")
+            .send_string(&payload.to_string())
+        {
+            Ok(_) => Ok(()),
+            // The body explains what's wrong, e.g. "chat not found"
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                let description = serde_json::from_str::<serde_json::Value>(&body)
+                    .ok()
+                    .and_then(|v| v.get("description").and_then(|d| d.as_str()).map(|d| d.to_string()))
+                    .unwrap_or(body);
+                Err(format!("Telegram: HTTP {}: {}", code, description))
+            }
+            // Don't log the URL, it contains the bot token
+            Err(ureq::Error::Transport(e)) => Err(format!("Telegram: {}", e.kind())),
+        }
+    }
+}
+
+/// Encryption of the connection to the mail server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS, usually port 587
+    #[default]
+    StartTls,
+    /// TLS from the start, usually port 465
+    Tls,
+    /// Unencrypted, only for mail servers in the local network
+    None,
+}
+
+impl SmtpSecurity {
+    fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+/// Sends e-mails through an SMTP server
+pub struct EmailSink {
+    host: String,
+    port: u16,
+    security: SmtpSecurity,
+    credentials: Option<(String, String)>,
+    from: String,
+    to: Vec<String>,
+}
+
+/// An SMTP session on a plain or TLS connection
+struct Smtp<S: Read + Write> {
+    stream: BufReader<S>,
+}
+
+impl<S: Read + Write> Smtp<S> {
+    fn new(stream: S) -> Self {
+        Self { stream: BufReader::new(stream) }
+    }
+
+    /// Read a reply, multi-line replies have a "-" after the code on all but the last line
+    fn reply(&mut self, expected: u16) -> Result<String, String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("Connection closed by the mail server".to_string());
+            }
+            text.push_str(&line);
+            if line.len() < 4 || line.as_bytes()[3] != b'-' {
+                break;
+            }
+        }
+        let code: u16 = text.get(..3).and_then(|c| c.parse().ok()).unwrap_or(0);
+        if code != expected {
+            return Err(format!("Mail server: {}", text.trim_end()));
+        }
+        Ok(text)
+    }
+
+    fn command(&mut self, command: &str, expected: u16) -> Result<String, String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).and_then(|_| stream.write_all(b"\r\n")).map_err(|e| e.to_string())?;
+        self.reply(expected)
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+}
+
+impl EmailSink {
+    fn connect(&self) -> Result<TcpStream, String> {
+        let address = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Can't resolve {}: {}", self.host, e))?
+            .next()
+            .ok_or_else(|| format!("Can't resolve {}", self.host))?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|e| format!("Can't connect to {}: {}", address, e))?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+        Ok(stream)
+    }
+
+    fn tls(&self, stream: TcpStream) -> Result<native_tls::TlsStream<TcpStream>, String> {
+        native_tls::TlsConnector::new()
+            .map_err(|e| e.to_string())?
+            .connect(&self.host, stream)
+            .map_err(|e| format!("TLS connection to {} failed: {}", self.host, e))
+    }
+
+    /// Authenticate and transfer the message after the greeting and EHLO
+    fn deliver<S: Read + Write>(&self, smtp: &mut Smtp<S>, message: &str) -> Result<(), String> {
+        if let Some((username, password)) = &self.credentials {
+            let plain = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", username, password));
+            smtp.command(&format!("AUTH PLAIN {}", plain), 235)?;
+        }
+        smtp.command(&format!("MAIL FROM:<{}>", self.from), 250)?;
+        for recipient in &self.to {
+            smtp.command(&format!("RCPT TO:<{}>", recipient), 250)?;
+        }
+        smtp.command("DATA", 354)?;
+        smtp.command(&format!("{}\r\n.", message), 250)?;
+        // The message is accepted, a failing QUIT doesn't matter
+        let _ = smtp.command("QUIT", 221);
+        Ok(())
+    }
+}
+
+fn ehlo() -> String {
+    let hostname = std::fs::read_to_string("/etc/hostname").unwrap_or_default();
+    let hostname = hostname.trim();
+    format!("EHLO {}", if hostname.is_empty() { "audiocontrol" } else { hostname })
+}
+
+/// Encode a header value with non-ASCII characters as RFC 2047 encoded word
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value))
+    }
+}
+
+/// Build the message with headers, CRLF line endings and dot-stuffed lines
+pub fn format_message(from: &str, to: &[String], subject: &str, body: &str, date: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from,
+        to.join(", "),
+        encode_header(subject),
+        date
+    );
+    for line in body.lines() {
+        // A line with a single dot ends the message, so leading dots are doubled
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.truncate(message.trim_end_matches("\r\n").len());
+    message
+}
+
+impl NotificationSink for EmailSink {
+    fn sink_type(&self) -> &'static str {
+        "email"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), String> {
+        if self.to.is_empty() {
+            return Err("No recipients configured".to_string());
+        }
+        let message = format_message(
+            &self.from,
+            &self.to,
+            &notification.title,
+            &notification.message,
+            &chrono::Local::now().to_rfc2822(),
+        );
+        debug!("Sending e-mail to {:?} via {}:{}", self.to, self.host, self.port);
+
+        let stream = self.connect()?;
+        match self.security {
+            SmtpSecurity::Tls => {
+                let mut smtp = Smtp::new(self.tls(stream)?);
+                smtp.reply(220)?;
+                smtp.command(&ehlo(), 250)?;
+                self.deliver(&mut smtp, &message)
+            }
+            SmtpSecurity::StartTls => {
+                let mut smtp = Smtp::new(stream);
+                smtp.reply(220)?;
+                smtp.command(&ehlo(), 250)?;
+                smtp.command("STARTTLS", 220)?;
+                let mut smtp = Smtp::new(self.tls(smtp.into_inner())?);
+                smtp.command(&ehlo(), 250)?;
+                self.deliver(&mut smtp, &message)
+            }
+            SmtpSecurity::None => {
+                let mut smtp = Smtp::new(stream);
+                smtp.reply(220)?;
+                smtp.command(&ehlo(), 250)?;
+                self.deliver(&mut smtp, &message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_message() {
+        let message = format_message(
+            "hifiberry@example.com",
+            &["owner@example.com".to_string()],
+            "Wöchentliche Übersicht",
+            "42 songs\n.hidden\nend\n",
+            "Sun, 1 Jun 2025 18:00:00 +0200",
+        );
+        assert!(message.starts_with("From: hifiberry@example.com\r\nTo: owner@example.com\r\n"));
+        assert!(message.contains("Subject: =?UTF-8?B?V8O2Y2hlbnRsaWNoZSDDnGJlcnNpY2h0?=\r\n"));
+        assert!(message.ends_with("\r\n\r\n42 songs\r\n..hidden\r\nend"));
+
+        let config: SinkConfig = serde_json::from_value(serde_json::json!({
+            "type": "email", "host": "smtp.example.com", "security": "tls", "from": "a@example.com", "to": ["b@example.com"]
+        })).unwrap();
+        assert!(matches!(config, SinkConfig::Email { security: SmtpSecurity::Tls, .. }));
+        assert_eq!(config.build().sink_type(), "email");
+    }
+}
@@ -0,0 +1,436 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::config::get_service_config;
+use crate::data::PlayerEvent;
+use crate::helpers::listening_history::{self, PlayedSong};
+use crate::helpers::notification_sinks::{NotificationSink, SinkConfig};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone, Weekday};
+use crossbeam::channel::{unbounded, Sender};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Unix timestamp of the last weekly digest
+const LAST_DIGEST_KEY: &str = "notifications::last_digest";
+
+/// Number of artists and songs listed in the weekly digest
+const DIGEST_TOP_COUNT: usize = 5;
+
+/// What a notification is about, sinks can be limited to some categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    /// Critical system resources and other failures
+    Errors,
+    /// A login of a streaming or scrobbling service expired
+    AuthExpiry,
+    /// Summary of the music played in the last week
+    WeeklyDigest,
+    /// An alarm went off
+    Alarm,
+}
+
+impl NotificationCategory {
+    pub fn all() -> Vec<Self> {
+        vec![Self::Errors, Self::AuthExpiry, Self::WeeklyDigest, Self::Alarm]
+    }
+
+    /// Parse the configuration name, e.g. "auth_expiry"
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    /// The same notification isn't sent again within this time
+    fn repeat_interval(&self) -> Option<Duration> {
+        match self {
+            Self::Errors => Some(Duration::from_secs(3600)),
+            Self::AuthExpiry => Some(Duration::from_secs(24 * 3600)),
+            Self::WeeklyDigest | Self::Alarm => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub category: NotificationCategory,
+    pub title: String,
+    pub message: String,
+}
+
+/// A sink with the categories it receives
+#[derive(Debug, Clone, Deserialize)]
+pub struct SinkEntry {
+    #[serde(flatten)]
+    pub sink: SinkConfig,
+    #[serde(default = "NotificationCategory::all")]
+    pub categories: Vec<NotificationCategory>,
+}
+
+/// Configuration of the `notifications` service
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationsConfig {
+    #[serde(default = "default_true")]
+    pub enable: bool,
+
+    #[serde(default)]
+    pub sinks: Vec<SinkEntry>,
+
+    /// Day of the weekly digest, e.g. "sun"
+    #[serde(default = "default_digest_weekday")]
+    pub digest_weekday: String,
+
+    /// Local hour of the weekly digest
+    #[serde(default = "default_digest_hour")]
+    pub digest_hour: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_digest_weekday() -> String {
+    "sun".to_string()
+}
+
+fn default_digest_hour() -> u32 {
+    18
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            sinks: Vec::new(),
+            digest_weekday: default_digest_weekday(),
+            digest_hour: default_digest_hour(),
+        }
+    }
+}
+
+/// A configured sink as shown by the API, without credentials
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkInfo {
+    #[serde(rename = "type")]
+    pub sink_type: String,
+    pub categories: Vec<NotificationCategory>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationsStatus {
+    pub enabled: bool,
+    pub sinks: Vec<SinkInfo>,
+    /// Next weekly digest, None if no sink receives it
+    pub next_digest: Option<DateTime<Local>>,
+}
+
+/// Result of sending to one sink
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkResult {
+    #[serde(rename = "type")]
+    pub sink_type: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Sink {
+    sink: Box<dyn NotificationSink>,
+    categories: Vec<NotificationCategory>,
+}
+
+static CONFIG: Lazy<RwLock<NotificationsConfig>> = Lazy::new(|| RwLock::new(NotificationsConfig::default()));
+
+static SINKS: Lazy<RwLock<Vec<Arc<Sink>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Queue of the sender thread, sending can take seconds
+static QUEUE: Lazy<RwLock<Option<Sender<Notification>>>> = Lazy::new(|| RwLock::new(None));
+
+/// When a notification with this category and title was sent last
+static LAST_SENT: Lazy<Mutex<HashMap<(NotificationCategory, String), Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn sinks_for(category: NotificationCategory) -> Vec<Arc<Sink>> {
+    SINKS.read().iter().filter(|s| s.categories.contains(&category)).cloned().collect()
+}
+
+/// Send a notification to all sinks of its category in the background
+///
+/// Repeated errors and auth expiry notifications with the same title are suppressed for a while.
+pub fn notify(category: NotificationCategory, title: &str, message: &str) -> bool {
+    let Some(queue) = QUEUE.read().clone() else {
+        debug!("Notifications are disabled, dropping '{}'", title);
+        return false;
+    };
+    if sinks_for(category).is_empty() {
+        debug!("No sink for {:?} notifications, dropping '{}'", category, title);
+        return false;
+    }
+    if let Some(interval) = category.repeat_interval() {
+        let mut last_sent = LAST_SENT.lock();
+        let key = (category, title.to_string());
+        if last_sent.get(&key).is_some_and(|t| t.elapsed() < interval) {
+            debug!("Notification '{}' was sent recently, not sending it again", title);
+            return false;
+        }
+        last_sent.insert(key, Instant::now());
+    }
+    queue
+        .send(Notification { category, title: title.to_string(), message: message.to_string() })
+        .is_ok()
+}
+
+/// Send a notification right away and report the result of every sink
+pub fn send_now(notification: &Notification) -> Vec<SinkResult> {
+    sinks_for(notification.category)
+        .iter()
+        .map(|s| {
+            let result = s.sink.send(notification);
+            SinkResult {
+                sink_type: s.sink.sink_type().to_string(),
+                success: result.is_ok(),
+                error: result.err(),
+            }
+        })
+        .collect()
+}
+
+/// Send a test notification to the sinks of a category, or to all sinks
+pub fn send_test(category: Option<NotificationCategory>) -> Vec<SinkResult> {
+    let sinks: Vec<Arc<Sink>> = match category {
+        Some(category) => sinks_for(category),
+        None => SINKS.read().clone(),
+    };
+    sinks
+        .iter()
+        .map(|s| {
+            let notification = Notification {
+                category: category.or_else(|| s.categories.first().copied()).unwrap_or(NotificationCategory::Errors),
+                title: "AudioControl test notification".to_string(),
+                message: "Notifications from this device reach you.".to_string(),
+            };
+            let result = s.sink.send(&notification);
+            SinkResult {
+                sink_type: s.sink.sink_type().to_string(),
+                success: result.is_ok(),
+                error: result.err(),
+            }
+        })
+        .collect()
+}
+
+pub fn get_status() -> NotificationsStatus {
+    let config = CONFIG.read().clone();
+    let digest = !sinks_for(NotificationCategory::WeeklyDigest).is_empty();
+    NotificationsStatus {
+        enabled: QUEUE.read().is_some(),
+        sinks: SINKS
+            .read()
+            .iter()
+            .map(|s| SinkInfo { sink_type: s.sink.sink_type().to_string(), categories: s.categories.clone() })
+            .collect(),
+        next_digest: digest
+            .then(|| digest_weekday(&config))
+            .flatten()
+            .map(|weekday| last_digest_time(Local::now(), weekday, config.digest_hour) + ChronoDuration::days(7)),
+    }
+}
+
+fn digest_weekday(config: &NotificationsConfig) -> Option<Weekday> {
+    match config.digest_weekday.parse() {
+        Ok(weekday) => Some(weekday),
+        Err(_) => {
+            warn!("Invalid digest_weekday '{}'", config.digest_weekday);
+            None
+        }
+    }
+}
+
+/// The latest scheduled digest time at or before `now`
+fn last_digest_time(now: DateTime<Local>, weekday: Weekday, hour: u32) -> DateTime<Local> {
+    let days_back = (7 + now.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    let time = NaiveTime::from_hms_opt(hour.min(23), 0, 0).unwrap_or_default();
+    let day = now.date_naive() - ChronoDuration::days(days_back as i64);
+    let scheduled = Local
+        .from_local_datetime(&day.and_time(time))
+        .earliest()
+        .unwrap_or(now);
+    if scheduled > now {
+        scheduled - ChronoDuration::days(7)
+    } else {
+        scheduled
+    }
+}
+
+/// The digest is due if it wasn't sent since the last scheduled time, up to a day late
+fn digest_due(now: DateTime<Local>, last_sent: Option<DateTime<Local>>, weekday: Weekday, hour: u32) -> bool {
+    let scheduled = last_digest_time(now, weekday, hour);
+    now - scheduled < ChronoDuration::days(1) && last_sent.is_none_or(|sent| sent < scheduled)
+}
+
+/// Summarize the plays of a week, None if nothing was played
+pub fn weekly_digest(plays: &[PlayedSong]) -> Option<String> {
+    if plays.is_empty() {
+        return None;
+    }
+    let top = |counts: HashMap<String, usize>| {
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(DIGEST_TOP_COUNT);
+        counts
+    };
+
+    // Keep the spelling of the first play, counted case-insensitive
+    let mut artist_names: HashMap<String, String> = HashMap::new();
+    let mut artist_counts: HashMap<String, usize> = HashMap::new();
+    let mut song_counts: HashMap<String, usize> = HashMap::new();
+    for play in plays {
+        let key = play.artist.to_lowercase();
+        artist_names.entry(key.clone()).or_insert_with(|| play.artist.clone());
+        *artist_counts.entry(key).or_insert(0) += 1;
+        *song_counts.entry(format!("{} - {}", play.artist, play.title)).or_insert(0) += 1;
+    }
+
+    let mut digest = format!(
+        "{} songs played by {} artists in the last 7 days.\n\nTop artists:\n",
+        plays.len(),
+        artist_counts.len()
+    );
+    for (artist, count) in top(artist_counts) {
+        digest.push_str(&format!("- {} ({})\n", artist_names.get(&artist).unwrap_or(&artist), count));
+    }
+    digest.push_str("\nTop songs:\n");
+    for (song, count) in top(song_counts) {
+        digest.push_str(&format!("- {} ({})\n", song, count));
+    }
+    Some(digest.trim_end().to_string())
+}
+
+fn send_digest_if_due(config: &NotificationsConfig, weekday: Weekday) {
+    let now = Local::now();
+    let last_sent = crate::helpers::settingsdb::get_int(LAST_DIGEST_KEY)
+        .ok()
+        .flatten()
+        .and_then(|t| DateTime::from_timestamp(t, 0))
+        .map(|t| t.with_timezone(&Local));
+    if !digest_due(now, last_sent, weekday, config.digest_hour) {
+        return;
+    }
+    let since = (now - ChronoDuration::days(7)).timestamp().max(0) as u64;
+    match weekly_digest(&listening_history::get_plays_since(since)) {
+        Some(digest) => {
+            info!("Sending weekly digest");
+            notify(NotificationCategory::WeeklyDigest, "Your week in music", &digest);
+        }
+        None => debug!("Nothing played this week, no digest"),
+    }
+    if let Err(e) = crate::helpers::settingsdb::set_int(LAST_DIGEST_KEY, now.timestamp()) {
+        warn!("Failed to store the time of the weekly digest: {}", e);
+    }
+}
+
+/// Start the notification sinks from the `notifications` service configuration
+///
+/// Critical system resource alerts are sent as errors, the weekly digest is
+/// sent at the configured time if a sink receives it.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let notifications_config = match get_service_config(config, "notifications") {
+        Some(c) => match serde_json::from_value::<NotificationsConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid notifications configuration, using defaults: {}", e);
+                NotificationsConfig::default()
+            }
+        },
+        None => NotificationsConfig::default(),
+    };
+    *CONFIG.write() = notifications_config.clone();
+    if !notifications_config.enable || notifications_config.sinks.is_empty() {
+        debug!("No notification sinks configured");
+        return;
+    }
+
+    *SINKS.write() = notifications_config
+        .sinks
+        .iter()
+        .map(|entry| Arc::new(Sink { sink: entry.sink.build(), categories: entry.categories.clone() }))
+        .collect();
+    info!(
+        "Sending notifications to {}",
+        SINKS.read().iter().map(|s| s.sink.sink_type()).collect::<Vec<_>>().join(", ")
+    );
+
+    let (sender, receiver) = unbounded::<Notification>();
+    *QUEUE.write() = Some(sender);
+    thread::spawn(move || {
+        for notification in receiver {
+            for result in send_now(&notification) {
+                if let Some(error) = result.error {
+                    warn!("Failed to send notification '{}' to {}: {}", notification.title, result.sink_type, error);
+                }
+            }
+        }
+    });
+
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::SystemResourceAlert]);
+    bus.spawn_worker(id, receiver, |event| {
+        if let PlayerEvent::SystemResourceAlert { resource, critical: true, value, threshold } = event {
+            notify(
+                NotificationCategory::Errors,
+                &format!("System {} critical", resource),
+                &format!("The {} is at {} (threshold {}).", resource, value, threshold),
+            );
+        }
+    });
+
+    if sinks_for(NotificationCategory::WeeklyDigest).is_empty() {
+        return;
+    }
+    if let Some(weekday) = digest_weekday(&notifications_config) {
+        thread::spawn(move || loop {
+            send_digest_if_due(&notifications_config, weekday);
+            // Checking every 10 minutes is exact enough and survives clock changes
+            thread::sleep(Duration::from_secs(600));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(s: &str) -> DateTime<Local> {
+        Local.from_local_datetime(&chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_digest_schedule() {
+        // 2025-06-01 is a Sunday
+        assert_eq!(last_digest_time(local("2025-06-04 10:00"), Weekday::Sun, 18), local("2025-06-01 18:00"));
+        assert_eq!(last_digest_time(local("2025-06-01 17:59"), Weekday::Sun, 18), local("2025-05-25 18:00"));
+        assert!(digest_due(local("2025-06-01 18:05"), None, Weekday::Sun, 18));
+        assert!(!digest_due(local("2025-06-01 18:15"), Some(local("2025-06-01 18:05")), Weekday::Sun, 18));
+        // Not sent days late, e.g. after the device was off
+        assert!(!digest_due(local("2025-06-04 10:00"), None, Weekday::Sun, 18));
+        assert_eq!("sun".parse::<Weekday>().ok(), Some(Weekday::Sun));
+        assert_eq!(NotificationCategory::parse("auth_expiry"), Some(NotificationCategory::AuthExpiry));
+    }
+
+    #[test]
+    fn test_weekly_digest() {
+        let play = |artist: &str, title: &str| PlayedSong {
+            artist: artist.to_string(),
+            title: title.to_string(),
+            album: None,
+            timestamp: 0,
+        };
+        let plays = vec![play("Nina Simone", "Sinnerman"), play("nina simone", "Feeling Good"), play("Air", "La femme d'argent")];
+        let digest = weekly_digest(&plays).unwrap();
+        assert!(digest.starts_with("3 songs played by 2 artists"));
+        assert!(digest.contains("Top artists:\n- Nina Simone (2)\n- Air (1)"));
+        assert!(weekly_digest(&[]).is_none());
+    }
+}
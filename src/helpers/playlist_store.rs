@@ -0,0 +1,210 @@
+use crate::config::get_service_config;
+use crate::data::playlist::{check_playlist_name, PlaylistError, PlaylistResult, PlaylistStore, StoredPlaylist};
+use crate::data::Track;
+use crate::helpers::m3u::{write_m3u, M3UEntry, M3UError, M3UParser};
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Extension of the playlist files
+const EXTENSION: &str = "m3u";
+
+/// Configuration of the `playlists` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistStoreConfig {
+    /// Playlists of players without native playlists are stored here, in a directory per player
+    #[serde(default = "default_directory")]
+    pub directory: String,
+}
+
+fn default_directory() -> String {
+    "/var/lib/audiocontrol/playlists".to_string()
+}
+
+impl Default for PlaylistStoreConfig {
+    fn default() -> Self {
+        Self {
+            directory: default_directory(),
+        }
+    }
+}
+
+static CONFIG: Lazy<RwLock<PlaylistStoreConfig>> = Lazy::new(|| RwLock::new(PlaylistStoreConfig::default()));
+
+/// Initialize the playlist store from the `playlists` service configuration
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(c) = get_service_config(config, "playlists") {
+        match serde_json::from_value::<PlaylistStoreConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid playlists configuration, using defaults: {}", e),
+        }
+    }
+    debug!("Playlists of players without native playlists are stored in {}", CONFIG.read().directory);
+}
+
+/// Playlists stored as M3U files
+pub struct M3uPlaylistStore {
+    directory: PathBuf,
+}
+
+impl M3uPlaylistStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// The store of a player in the configured directory
+    pub fn for_player(player_name: &str) -> Self {
+        let player_directory: String = player_name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        Self::new(Path::new(&CONFIG.read().directory).join(player_directory))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{}.{}", name, EXTENSION))
+    }
+}
+
+fn storage_error(error: impl std::fmt::Display) -> PlaylistError {
+    PlaylistError::Storage(error.to_string())
+}
+
+fn track_from_entry(entry: M3UEntry) -> Track {
+    // Without a title the file name is the best name there is
+    let name = entry.title.unwrap_or_else(|| {
+        let file = entry.url.rsplit('/').next().unwrap_or(&entry.url);
+        file.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(file).to_string()
+    });
+    let mut track = Track::with_name(name);
+    track.artist = entry.artist;
+    track.duration = entry.duration;
+    track.uri = Some(entry.url);
+    track
+}
+
+fn entry_from_track(track: &Track) -> Option<M3UEntry> {
+    Some(M3UEntry {
+        url: track.uri.clone()?,
+        title: Some(track.name.clone()),
+        duration: track.duration,
+        info: None,
+        artist: track.artist.clone(),
+    })
+}
+
+impl PlaylistStore for M3uPlaylistStore {
+    fn list_playlists(&self) -> PlaylistResult<Vec<StoredPlaylist>> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            // Nothing has been stored yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(storage_error(e)),
+        };
+        let mut playlists: Vec<StoredPlaylist> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                    return None;
+                }
+                let name = path.file_stem()?.to_str()?.to_string();
+                let last_modified = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .map(|time| DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true))
+                    .unwrap_or_default();
+                Some(StoredPlaylist { name, last_modified })
+            })
+            .collect();
+        playlists.sort_by_key(|p| p.name.to_lowercase());
+        Ok(playlists)
+    }
+
+    fn get_playlist_tracks(&self, name: &str) -> PlaylistResult<Vec<Track>> {
+        check_playlist_name(name)?;
+        let content = match std::fs::read_to_string(self.path(name)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(PlaylistError::NotFound(name.to_string())),
+            Err(e) => return Err(storage_error(e)),
+        };
+        match M3UParser::new().parse_content(&content, None) {
+            Ok(playlist) => Ok(playlist.entries.into_iter().map(track_from_entry).collect()),
+            Err(M3UError::EmptyPlaylist) => Ok(Vec::new()),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    /// Tracks without URI are skipped
+    fn save_playlist(&self, name: &str, tracks: &[Track], overwrite: bool) -> PlaylistResult<()> {
+        check_playlist_name(name)?;
+        let path = self.path(name);
+        if !overwrite && path.exists() {
+            return Err(PlaylistError::Exists(name.to_string()));
+        }
+        std::fs::create_dir_all(&self.directory).map_err(storage_error)?;
+        let entries: Vec<M3UEntry> = tracks.iter().filter_map(entry_from_track).collect();
+        // Write to a temporary file first, a failed write must not destroy the playlist
+        let temporary = self.directory.join(format!(".{}.{}.tmp", name, EXTENSION));
+        std::fs::write(&temporary, write_m3u(&entries)).map_err(storage_error)?;
+        std::fs::rename(&temporary, &path).map_err(storage_error)?;
+        info!("Saved {} tracks as playlist '{}' in {}", entries.len(), name, self.directory.display());
+        Ok(())
+    }
+
+    fn delete_playlist(&self, name: &str) -> PlaylistResult<()> {
+        check_playlist_name(name)?;
+        match std::fs::remove_file(self.path(name)) {
+            Ok(()) => {
+                info!("Deleted playlist '{}' in {}", name, self.directory.display());
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(PlaylistError::NotFound(name.to_string())),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(name: &str, uri: Option<&str>) -> Track {
+        let mut track = Track::with_name(name.to_string());
+        track.uri = uri.map(|u| u.to_string());
+        track.artist = Some("Miles Davis".to_string());
+        track.duration = Some(562.0);
+        track
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = M3uPlaylistStore::new(directory.path().join("spotify"));
+        assert!(store.list_playlists().unwrap().is_empty());
+
+        let tracks = vec![track("So What", Some("spotify:track:1")), track("No URI", None)];
+        store.save_playlist("Sunday Morning", &tracks, false).unwrap();
+        assert!(matches!(store.save_playlist("Sunday Morning", &tracks, false), Err(PlaylistError::Exists(_))));
+        store.add_to_playlist("Sunday Morning", &[track("Blue in Green", Some("spotify:track:3"))]).unwrap();
+
+        let loaded = store.get_playlist_tracks("Sunday Morning").unwrap();
+        let names: Vec<&str> = loaded.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["So What", "Blue in Green"]);
+        assert_eq!(loaded[0].artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(loaded[0].uri.as_deref(), Some("spotify:track:1"));
+
+        let playlists = store.list_playlists().unwrap();
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists[0].name, "Sunday Morning");
+
+        store.delete_playlist("Sunday Morning").unwrap();
+        assert!(matches!(store.get_playlist_tracks("Sunday Morning"), Err(PlaylistError::NotFound(_))));
+        assert!(matches!(store.delete_playlist("../escape"), Err(PlaylistError::InvalidName(_))));
+    }
+}
@@ -0,0 +1,460 @@
+use crate::config::get_service_config;
+use crate::data::player_command::QueueTrackMetadata;
+use crate::helpers::http_client::{new_http_client, HttpClientError};
+use crate::helpers::security_store::{SecurityStore, SecurityStoreError};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const QOBUZ_API_BASE: &str = "https://www.qobuz.com/api.json/0.2";
+const QOBUZ_TOKEN_STORE: &str = "qobuz_user_auth_token";
+const QOBUZ_USERNAME_STORE: &str = "qobuz_username";
+
+/// Errors that can occur when using the Qobuz API
+#[derive(Debug, Error)]
+pub enum QobuzError {
+    #[error("Qobuz is not configured: {0}")]
+    NotConfigured(String),
+
+    #[error("Authentication error: {0}")]
+    AuthError(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("API error: {0}")]
+    ApiError(String),
+
+    #[error("Security store error: {0}")]
+    StoreError(#[from] SecurityStoreError),
+}
+
+pub type Result<T> = std::result::Result<T, QobuzError>;
+
+/// Stream quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QobuzQuality {
+    /// MP3 320 kbit/s
+    Mp3,
+    /// FLAC 16 bit/44.1 kHz
+    #[default]
+    Cd,
+    /// FLAC 24 bit up to 96 kHz
+    HiRes,
+    /// FLAC 24 bit up to 192 kHz
+    HiResMax,
+}
+
+impl QobuzQuality {
+    /// Format ID used by the Qobuz API
+    pub fn format_id(&self) -> u32 {
+        match self {
+            QobuzQuality::Mp3 => 5,
+            QobuzQuality::Cd => 6,
+            QobuzQuality::HiRes => 7,
+            QobuzQuality::HiResMax => 27,
+        }
+    }
+}
+
+/// Qobuz configuration from the `qobuz` service section
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QobuzConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// Application ID registered with Qobuz
+    #[serde(default)]
+    pub app_id: String,
+    /// Application secret, needed to request stream URLs
+    #[serde(default)]
+    pub app_secret: String,
+    /// Default stream quality
+    #[serde(default)]
+    pub quality: QobuzQuality,
+}
+
+/// A Qobuz track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QobuzTrack {
+    pub id: u64,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_id: Option<String>,
+    pub track_number: Option<u32>,
+    /// Duration in seconds
+    pub duration: Option<u64>,
+    pub image: Option<String>,
+    /// Whether the track is available in hi-res
+    pub hires: bool,
+    /// Whether the track can be streamed
+    pub streamable: bool,
+}
+
+/// A Qobuz album, tracks are only included in album lookups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QobuzAlbum {
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub artist_id: Option<u64>,
+    pub image: Option<String>,
+    pub release_date: Option<String>,
+    pub tracks_count: Option<u32>,
+    pub hires: bool,
+    #[serde(default)]
+    pub tracks: Vec<QobuzTrack>,
+}
+
+/// A Qobuz artist, albums are only included in artist lookups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QobuzArtist {
+    pub id: u64,
+    pub name: String,
+    pub image: Option<String>,
+    pub albums_count: Option<u32>,
+    #[serde(default)]
+    pub albums: Vec<QobuzAlbum>,
+}
+
+/// Search results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QobuzSearchResult {
+    pub albums: Vec<QobuzAlbum>,
+    pub artists: Vec<QobuzArtist>,
+    pub tracks: Vec<QobuzTrack>,
+}
+
+static CONFIG: Lazy<RwLock<QobuzConfig>> = Lazy::new(|| RwLock::new(QobuzConfig::default()));
+
+/// Initialize the Qobuz module from the `qobuz` service configuration
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let Some(qobuz_config) = get_service_config(config, "qobuz") else {
+        debug!("No Qobuz configuration found");
+        return;
+    };
+    match serde_json::from_value::<QobuzConfig>(qobuz_config.clone()) {
+        Ok(parsed) => {
+            if parsed.enable && parsed.app_id.is_empty() {
+                warn!("Qobuz is enabled, but no app_id is configured");
+            } else if parsed.enable {
+                info!("Qobuz integration enabled (quality: {:?})", parsed.quality);
+            }
+            *CONFIG.write() = parsed;
+        }
+        Err(e) => warn!("Invalid Qobuz configuration, Qobuz disabled: {}", e),
+    }
+}
+
+/// Check if Qobuz is enabled and configured
+pub fn is_enabled() -> bool {
+    let config = CONFIG.read();
+    config.enable && !config.app_id.is_empty()
+}
+
+/// Get the configured default quality
+pub fn default_quality() -> QobuzQuality {
+    CONFIG.read().quality
+}
+
+/// Check if a Qobuz user is logged in
+pub fn is_logged_in() -> bool {
+    SecurityStore::contains_key(QOBUZ_TOKEN_STORE).unwrap_or(false)
+}
+
+/// Get the name of the logged in user
+pub fn get_username() -> Option<String> {
+    SecurityStore::get(QOBUZ_USERNAME_STORE).ok()
+}
+
+/// Log in to Qobuz
+///
+/// Only the user authentication token is stored in the security store, the password is not saved.
+pub fn login(username: &str, password: &str) -> Result<()> {
+    let password_hash = format!("{:x}", md5::compute(password.as_bytes()));
+    let response = api_get(
+        "user/login",
+        &[("username", username.to_string()), ("password", password_hash)],
+        false,
+    )
+    .map_err(|e| match e {
+        QobuzError::ApiError(msg) | QobuzError::NotFound(msg) => QobuzError::AuthError(msg),
+        e => e,
+    })?;
+
+    let token = response
+        .get("user_auth_token")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| QobuzError::AuthError("No token in login response".to_string()))?;
+
+    SecurityStore::set(QOBUZ_TOKEN_STORE, token)?;
+    SecurityStore::set(QOBUZ_USERNAME_STORE, username)?;
+    info!("Logged in to Qobuz as {}", username);
+    Ok(())
+}
+
+/// Log out and remove the stored token
+pub fn logout() -> Result<()> {
+    SecurityStore::remove(QOBUZ_TOKEN_STORE)?;
+    SecurityStore::remove(QOBUZ_USERNAME_STORE)?;
+    info!("Logged out from Qobuz");
+    Ok(())
+}
+
+/// Send a GET request to the Qobuz API
+///
+/// # Arguments
+/// * `endpoint` - API endpoint, e.g. "album/get"
+/// * `params` - Query parameters, the app ID is added automatically
+/// * `authenticated` - Send the user authentication token
+fn api_get(endpoint: &str, params: &[(&str, String)], authenticated: bool) -> Result<Value> {
+    let app_id = CONFIG.read().app_id.clone();
+    if app_id.is_empty() {
+        return Err(QobuzError::NotConfigured("No app_id configured".to_string()));
+    }
+
+    let token = if authenticated {
+        Some(SecurityStore::get(QOBUZ_TOKEN_STORE).map_err(|_| QobuzError::AuthError("Not logged in".to_string()))?)
+    } else {
+        None
+    };
+
+    let mut url = format!("{}/{}?app_id={}", QOBUZ_API_BASE, endpoint, urlencoding::encode(&app_id));
+    for (name, value) in params {
+        url.push_str(&format!("&{}={}", name, urlencoding::encode(value)));
+    }
+
+    let mut headers = vec![("X-App-Id", app_id.as_str())];
+    if let Some(token) = token.as_deref() {
+        headers.push(("X-User-Auth-Token", token));
+    }
+
+    debug!("Qobuz API request: {}", endpoint);
+    new_http_client(10)
+        .get_json_with_headers(&url, &headers)
+        .map_err(|e| match e {
+            HttpClientError::ServerError(msg) if msg.contains("HTTP 401") => QobuzError::AuthError(msg),
+            HttpClientError::ServerError(msg) if msg.contains("HTTP 404") => QobuzError::NotFound(msg),
+            e => QobuzError::ApiError(e.to_string()),
+        })
+}
+
+/// Search albums, artists and tracks
+pub fn search(query: &str, limit: u32) -> Result<QobuzSearchResult> {
+    let response = api_get("catalog/search", &[("query", query.to_string()), ("limit", limit.to_string())], true)?;
+    Ok(parse_search_result(&response))
+}
+
+/// Get an album with its tracks
+pub fn get_album(album_id: &str) -> Result<QobuzAlbum> {
+    let response = api_get("album/get", &[("album_id", album_id.to_string())], true)?;
+    parse_album(&response).ok_or_else(|| QobuzError::NotFound(format!("Album {}", album_id)))
+}
+
+/// Get an artist with its albums
+pub fn get_artist(artist_id: u64) -> Result<QobuzArtist> {
+    let response = api_get(
+        "artist/get",
+        &[("artist_id", artist_id.to_string()), ("extra", "albums".to_string()), ("limit", "100".to_string())],
+        true,
+    )?;
+    parse_artist(&response).ok_or_else(|| QobuzError::NotFound(format!("Artist {}", artist_id)))
+}
+
+/// Get a single track
+pub fn get_track(track_id: u64) -> Result<QobuzTrack> {
+    let response = api_get("track/get", &[("track_id", track_id.to_string())], true)?;
+    parse_track(&response, None).ok_or_else(|| QobuzError::NotFound(format!("Track {}", track_id)))
+}
+
+/// Signature of a track/getFileUrl request
+fn request_signature(track_id: u64, format_id: u32, timestamp: u64, app_secret: &str) -> String {
+    let input = format!(
+        "trackgetFileUrlformat_id{}intentstreamtrack_id{}{}{}",
+        format_id, track_id, timestamp, app_secret
+    );
+    format!("{:x}", md5::compute(input.as_bytes()))
+}
+
+/// Get a stream URL for a track
+///
+/// Stream URLs expire after some time, they should be requested right before queueing.
+///
+/// # Arguments
+/// * `track_id` - Qobuz track ID
+/// * `quality` - Quality to request, the configured default if not set
+pub fn get_stream_url(track_id: u64, quality: Option<QobuzQuality>) -> Result<String> {
+    let app_secret = CONFIG.read().app_secret.clone();
+    if app_secret.is_empty() {
+        return Err(QobuzError::NotConfigured("No app_secret configured".to_string()));
+    }
+
+    let format_id = quality.unwrap_or_else(default_quality).format_id();
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let response = api_get(
+        "track/getFileUrl",
+        &[
+            ("track_id", track_id.to_string()),
+            ("format_id", format_id.to_string()),
+            ("intent", "stream".to_string()),
+            ("request_ts", timestamp.to_string()),
+            ("request_sig", request_signature(track_id, format_id, timestamp, &app_secret)),
+        ],
+        true,
+    )?;
+
+    response
+        .get("url")
+        .and_then(|u| u.as_str())
+        .map(|u| u.to_string())
+        .ok_or_else(|| QobuzError::NotFound(format!("No stream available for track {}", track_id)))
+}
+
+/// Metadata shown by the player while a Qobuz stream URL is playing
+pub fn queue_metadata(track: &QobuzTrack) -> QueueTrackMetadata {
+    let mut metadata = HashMap::new();
+    metadata.insert("title".to_string(), Value::from(track.title.clone()));
+    if let Some(artist) = &track.artist {
+        metadata.insert("artist".to_string(), Value::from(artist.clone()));
+    }
+    if let Some(album) = &track.album {
+        metadata.insert("album".to_string(), Value::from(album.clone()));
+    }
+    if let Some(image) = &track.image {
+        metadata.insert("coverart_url".to_string(), Value::from(image.clone()));
+    }
+    if let Some(duration) = track.duration {
+        metadata.insert("duration".to_string(), Value::from(duration as f64));
+    }
+    metadata.insert("source".to_string(), Value::from("qobuz"));
+    QueueTrackMetadata { metadata }
+}
+
+fn str_field(value: &Value, field: &str) -> Option<String> {
+    value.get(field).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_string())
+}
+
+fn image_url(value: &Value) -> Option<String> {
+    let image = value.get("image")?;
+    str_field(image, "large").or_else(|| str_field(image, "small")).or_else(|| str_field(image, "thumbnail"))
+}
+
+fn items<'a>(value: &'a Value, field: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(field)
+        .and_then(|v| v.get("items"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+}
+
+/// Parse a track, album information is taken from the surrounding album if the track has none
+fn parse_track(value: &Value, album: Option<&Value>) -> Option<QobuzTrack> {
+    let track_album = value.get("album").or(album);
+    let artist = value
+        .get("performer")
+        .and_then(|p| str_field(p, "name"))
+        .or_else(|| track_album.and_then(|a| a.get("artist")).and_then(|a| str_field(a, "name")));
+
+    Some(QobuzTrack {
+        id: value.get("id")?.as_u64()?,
+        title: match (str_field(value, "title"), str_field(value, "version")) {
+            (Some(title), Some(version)) => format!("{} ({})", title, version),
+            (title, _) => title?,
+        },
+        artist,
+        album: track_album.and_then(|a| str_field(a, "title")),
+        album_id: track_album.and_then(|a| str_field(a, "id")),
+        track_number: value.get("track_number").and_then(|n| n.as_u64()).map(|n| n as u32),
+        duration: value.get("duration").and_then(|d| d.as_u64()),
+        image: track_album.and_then(image_url),
+        hires: value.get("hires").and_then(|h| h.as_bool()).unwrap_or(false),
+        streamable: value.get("streamable").and_then(|s| s.as_bool()).unwrap_or(true),
+    })
+}
+
+fn parse_album(value: &Value) -> Option<QobuzAlbum> {
+    let artist = value.get("artist");
+    Some(QobuzAlbum {
+        id: str_field(value, "id")?,
+        title: str_field(value, "title")?,
+        artist: artist.and_then(|a| str_field(a, "name")),
+        artist_id: artist.and_then(|a| a.get("id")).and_then(|id| id.as_u64()),
+        image: image_url(value),
+        release_date: str_field(value, "release_date_original"),
+        tracks_count: value.get("tracks_count").and_then(|n| n.as_u64()).map(|n| n as u32),
+        hires: value.get("hires").and_then(|h| h.as_bool()).unwrap_or(false),
+        tracks: items(value, "tracks").filter_map(|t| parse_track(t, Some(value))).collect(),
+    })
+}
+
+fn parse_artist(value: &Value) -> Option<QobuzArtist> {
+    Some(QobuzArtist {
+        id: value.get("id")?.as_u64()?,
+        name: str_field(value, "name")?,
+        image: image_url(value),
+        albums_count: value.get("albums_count").and_then(|n| n.as_u64()).map(|n| n as u32),
+        albums: items(value, "albums").filter_map(parse_album).collect(),
+    })
+}
+
+fn parse_search_result(value: &Value) -> QobuzSearchResult {
+    QobuzSearchResult {
+        albums: items(value, "albums").filter_map(parse_album).collect(),
+        artists: items(value, "artists").filter_map(parse_artist).collect(),
+        tracks: items(value, "tracks").filter_map(|t| parse_track(t, None)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_signature() {
+        let expected = format!(
+            "{:x}",
+            md5::compute("trackgetFileUrlformat_id6intentstreamtrack_id12345671700000000secret".as_bytes())
+        );
+        assert_eq!(request_signature(1234567, 6, 1700000000, "secret"), expected);
+    }
+
+    #[test]
+    fn test_parse_album() {
+        let album = parse_album(&serde_json::json!({
+            "id": "0060254728697",
+            "title": "Abbey Road",
+            "artist": {"id": 26390, "name": "The Beatles"},
+            "image": {"small": "https://example.com/s.jpg", "large": "https://example.com/l.jpg"},
+            "release_date_original": "1969-09-26",
+            "tracks_count": 17,
+            "hires": true,
+            "tracks": {"items": [
+                {"id": 1, "title": "Come Together", "version": "Remastered", "track_number": 1, "duration": 259},
+                {"id": 2, "title": "Something", "performer": {"name": "George Harrison"}, "track_number": 2}
+            ]}
+        }))
+        .unwrap();
+
+        assert_eq!(album.artist.as_deref(), Some("The Beatles"));
+        assert_eq!(album.image.as_deref(), Some("https://example.com/l.jpg"));
+        assert_eq!(album.tracks.len(), 2);
+        assert_eq!(album.tracks[0].title, "Come Together (Remastered)");
+        // Track information falls back to the album
+        assert_eq!(album.tracks[0].artist.as_deref(), Some("The Beatles"));
+        assert_eq!(album.tracks[0].album_id.as_deref(), Some("0060254728697"));
+        assert_eq!(album.tracks[1].artist.as_deref(), Some("George Harrison"));
+    }
+
+    #[test]
+    fn test_quality() {
+        assert_eq!(serde_json::from_value::<QobuzQuality>(serde_json::json!("hi_res_max")).unwrap().format_id(), 27);
+        assert_eq!(QobuzQuality::default().format_id(), 6);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::audiocontrol::eventbus::{EventBus, EventSubscription};
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{LoopMode, PlaybackState, PlayerCapability, PlayerEvent, PlayerSource, Song, Track};
+use crate::helpers::dailymix;
+use crate::helpers::upcoming_track::{current_index, Announcer};
+use crate::players::PlayerController;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Configuration of the `queue_ending` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEndingConfig {
+    /// Send queue_ending events
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Seconds before the last track of the queue ends the event is sent
+    #[serde(default = "default_seconds_before")]
+    pub seconds_before: f64,
+
+    /// Seconds between two checks of the active player
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: f64,
+
+    /// Daily mix that is added to the queue when it runs dry, e.g. "daily"
+    #[serde(default)]
+    pub auto_dj_mix: Option<String>,
+}
+
+fn default_seconds_before() -> f64 {
+    60.0
+}
+
+fn default_poll_interval() -> f64 {
+    1.0
+}
+
+impl Default for QueueEndingConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            seconds_before: default_seconds_before(),
+            poll_interval: default_poll_interval(),
+            auto_dj_mix: None,
+        }
+    }
+}
+
+/// Estimated remaining play time of a queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueEstimate {
+    /// Tracks after the current one
+    pub tracks_remaining: usize,
+    /// Seconds until the last track ends, including the rest of the current track
+    pub remaining_seconds: f64,
+    /// Unix timestamp when the last track ends
+    pub finishes_at: u64,
+    /// False if some tracks have no duration, the estimate is then too short
+    pub complete: bool,
+}
+
+/// Estimate when the queue runs dry
+///
+/// There is no estimate if the queue repeats, the order is shuffled or the current
+/// song isn't found in the queue.
+pub fn estimate(
+    queue: &[Track],
+    song: &Song,
+    position: Option<f64>,
+    loop_mode: LoopMode,
+    shuffle: bool,
+    now: u64,
+) -> Option<QueueEstimate> {
+    if shuffle || loop_mode != LoopMode::None {
+        return None;
+    }
+    let index = current_index(queue, song)?;
+    let current = song.duration.or(queue[index].duration);
+    let following = &queue[index + 1..];
+
+    let mut complete = current.is_some();
+    let mut remaining = current.map_or(0.0, |d| (d - position.unwrap_or(0.0)).max(0.0));
+    for track in following {
+        match track.duration {
+            Some(duration) => remaining += duration,
+            None => complete = false,
+        }
+    }
+    Some(QueueEstimate {
+        tracks_remaining: following.len(),
+        remaining_seconds: remaining,
+        finishes_at: now + remaining.round() as u64,
+        complete,
+    })
+}
+
+/// Estimate the remaining play time of a player's local queue
+///
+/// `queue` is the player's queue, it is passed in because reading it can be expensive.
+pub fn estimate_player(player: &dyn PlayerController, queue: &[Track]) -> Option<QueueEstimate> {
+    if player.get_capabilities().has_capability(PlayerCapability::RemoteQueue) {
+        return None;
+    }
+    let song = player.get_song()?;
+    estimate(queue, &song, player.get_position(), player.get_loop_mode(), player.get_shuffle(), now())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Start watching the queue of the active player
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let ending_config = match get_service_config(config, "queue_ending") {
+        Some(c) => match serde_json::from_value::<QueueEndingConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid queue_ending configuration, using defaults: {}", e);
+                QueueEndingConfig::default()
+            }
+        },
+        None => QueueEndingConfig::default(),
+    };
+    if !ending_config.enable {
+        debug!("Queue ending announcements are disabled");
+        return;
+    }
+
+    info!("Announcing the end of the queue {}s before it runs dry", ending_config.seconds_before);
+    if let Some(mix) = ending_config.auto_dj_mix.clone() {
+        start_auto_dj(mix, controller.clone());
+    }
+
+    let interval = Duration::from_secs_f64(ending_config.poll_interval.max(0.1));
+    let mut announcer = Announcer::new(ending_config.seconds_before);
+    thread::spawn(move || {
+        while let Some(controller) = controller.upgrade() {
+            check_active_player(&controller, &mut announcer);
+            drop(controller);
+            thread::sleep(interval);
+        }
+    });
+}
+
+fn check_active_player(controller: &AudioController, announcer: &mut Announcer) {
+    let Some(active) = controller.get_active_controller() else {
+        return;
+    };
+    let player = active.read();
+    if player.get_playback_state() != PlaybackState::Playing {
+        return;
+    }
+    let queue = player.get_queue();
+    let Some(estimate) = estimate_player(&**player, &queue) else {
+        return;
+    };
+
+    // Adding tracks changes the last entry, its end is announced again
+    let key = format!(
+        "{}|{}|{}",
+        player.get_player_id(),
+        queue.len(),
+        queue.last().map(|t| t.uri.clone().unwrap_or_else(|| t.name.clone())).unwrap_or_default()
+    );
+    if announcer.check(&key, 0.0, estimate.remaining_seconds).is_none() {
+        return;
+    }
+    debug!("Queue of {} ends in {:.1}s", player.get_player_name(), estimate.remaining_seconds);
+    EventBus::instance().publish(PlayerEvent::QueueEnding {
+        source: PlayerSource::new(player.get_player_name(), player.get_player_id()),
+        remaining: estimate.remaining_seconds,
+        tracks_remaining: estimate.tracks_remaining,
+    });
+}
+
+/// Add a daily mix to the queue when it is about to run dry
+fn start_auto_dj(mix_id: String, controller: Weak<AudioController>) {
+    info!("Auto-DJ adds mix '{}' when the queue runs dry", mix_id);
+    let bus = EventBus::instance();
+    let (id, receiver) = bus.subscribe(vec![EventSubscription::QueueEnding]);
+    bus.spawn_worker(id, receiver, move |event| {
+        let Some(controller) = controller.upgrade() else {
+            return;
+        };
+        let Some(mix) = dailymix::get_mix(&mix_id) else {
+            debug!("Auto-DJ mix '{}' has not been generated yet", mix_id);
+            return;
+        };
+        // The mix is built from the library of one player
+        if event.player_name() != Some(mix.player_name.as_str()) {
+            return;
+        }
+        match dailymix::queue_mix(&controller, &mix_id, "add") {
+            Ok(count) => info!("Auto-DJ added {} tracks of mix '{}'", count, mix.name),
+            Err(e) => warn!("Auto-DJ could not add mix '{}': {}", mix_id, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(name: &str, duration: Option<f64>) -> Track {
+        Track { duration, ..Track::with_name(name.to_string()) }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let queue = vec![track("A", Some(200.0)), track("B", Some(180.0)), track("C", Some(240.0))];
+        let song = Song { title: Some("B".to_string()), duration: Some(180.0), ..Default::default() };
+
+        let result = estimate(&queue, &song, Some(30.0), LoopMode::None, false, 1000).unwrap();
+        assert_eq!(result.tracks_remaining, 1);
+        assert_eq!(result.remaining_seconds, 390.0);
+        assert_eq!(result.finishes_at, 1390);
+        assert!(result.complete);
+
+        // Repeating or shuffled queues don't end predictably
+        assert!(estimate(&queue, &song, Some(30.0), LoopMode::Playlist, false, 1000).is_none());
+        assert!(estimate(&queue, &song, Some(30.0), LoopMode::None, true, 1000).is_none());
+    }
+
+    #[test]
+    fn test_estimate_unknown_durations() {
+        let queue = vec![track("A", None), track("B", None), track("C", Some(240.0))];
+        let song = Song { title: Some("A".to_string()), ..Default::default() };
+
+        let result = estimate(&queue, &song, None, LoopMode::None, false, 0).unwrap();
+        assert_eq!(result.tracks_remaining, 2);
+        assert_eq!(result.remaining_seconds, 240.0);
+        assert!(!result.complete);
+    }
+}
@@ -1,28 +1,119 @@
 use std::collections::HashMap;
 use parking_lot::Mutex;
 use std::time::{Duration, Instant};
+use chrono::NaiveDate;
 use once_cell::sync::Lazy;
-use log::debug;
+use serde::{Deserialize, Serialize};
+use log::{debug, warn};
 
 const DEFAULT_RATE_LIMIT_MS: u64 = 500; // Default to 500ms (2 requests per second)
 
-/// Stores the last access time for a specific service
+/// Prefix of the settings database keys storing the daily request counts
+const QUOTA_KEY_PREFIX: &str = "ratelimit.quota.";
+
+/// Requests made to a service on one day (UTC)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct DailyCount {
+    date: NaiveDate,
+    count: u64,
+}
+
+/// Token bucket and daily quota of a specific service
+///
+/// The bucket holds up to `burst` tokens and gains one token every
+/// `minimum_delay_ms`. With a burst of 1 this is a fixed delay between requests.
 struct ServiceLimit {
-    /// Last time this service was accessed
-    last_access: Instant,
-    /// Minimum delay between requests in milliseconds
+    /// Minimum delay between requests in milliseconds, i.e. the sustained rate
     minimum_delay_ms: u64,
+    /// Number of requests that can be made without delay
+    burst: u32,
+    /// Available tokens, negative if requests are waiting for tokens
+    tokens: f64,
+    /// Last time tokens were added
+    last_refill: Instant,
+    /// Maximum number of requests per day
+    daily_quota: Option<u64>,
+    /// Requests made today
+    daily: Option<DailyCount>,
+}
+
+impl ServiceLimit {
+    fn new(minimum_delay_ms: u64, burst: u32, now: Instant) -> Self {
+        let burst = burst.max(1);
+        ServiceLimit {
+            minimum_delay_ms,
+            burst,
+            tokens: burst as f64,
+            last_refill: now,
+            daily_quota: None,
+            daily: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed_ms = now.duration_since(self.last_refill).as_secs_f64() * 1000.0;
+        let gained = if self.minimum_delay_ms == 0 {
+            self.burst as f64
+        } else {
+            elapsed_ms / self.minimum_delay_ms as f64
+        };
+        self.tokens = (self.tokens + gained).min(self.burst as f64);
+        self.last_refill = now;
+    }
+
+    /// Take a token and return how long the caller has to wait for it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens * self.minimum_delay_ms as f64 / 1000.0)
+        }
+    }
+
+    fn used_on(&self, date: NaiveDate) -> u64 {
+        self.daily.filter(|d| d.date == date).map(|d| d.count).unwrap_or(0)
+    }
+
+    fn remaining_on(&self, date: NaiveDate) -> Option<u64> {
+        self.daily_quota.map(|quota| quota.saturating_sub(self.used_on(date)))
+    }
+
+    /// Count a request, returns the new count
+    fn count_request(&mut self, date: NaiveDate) -> DailyCount {
+        let count = DailyCount { date, count: self.used_on(date) + 1 };
+        self.daily = Some(count);
+        count
+    }
+}
+
+/// Rate limit and quota state of a service as reported by the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceQuota {
+    pub service: String,
+    pub minimum_delay_ms: u64,
+    pub burst: u32,
+    /// Requests that can be made now without waiting
+    pub available: u32,
+    pub daily_quota: Option<u64>,
+    pub used_today: u64,
+    pub remaining_today: Option<u64>,
 }
 
 /// RateLimiter ensures that API calls to external services respect rate limits
 pub struct RateLimiter {
-    /// Maps service names to their last access time and rate limit
+    /// Maps service names to their token bucket and quota
     services: HashMap<String, ServiceLimit>,
 }
 
 // Global singleton for the rate limiter
 static RATE_LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| Mutex::new(RateLimiter::new()));
 
+fn today() -> NaiveDate {
+    chrono::Utc::now().date_naive()
+}
+
 impl RateLimiter {
     /// Create a new rate limiter
     fn new() -> Self {
@@ -32,55 +123,61 @@ impl RateLimiter {
     }
 
     /// Register a rate limit for a specific service
-    /// 
+    ///
     /// # Arguments
     /// * `service_name` - Name of the service to register
     /// * `minimum_delay_ms` - Minimum delay between requests in milliseconds
-    fn register_service(&mut self, service_name: &str, minimum_delay_ms: u64) {
-        let service_limit = ServiceLimit {
-            last_access: Instant::now() - Duration::from_millis(minimum_delay_ms),
-            minimum_delay_ms,
-        };
-        
+    /// * `burst` - Number of requests that can be made without delay
+    fn register_service(&mut self, service_name: &str, minimum_delay_ms: u64, burst: u32) {
+        let mut service_limit = ServiceLimit::new(minimum_delay_ms, burst, Instant::now());
+        // Keep the quota and today's count if the service is registered again
+        if let Some(existing) = self.services.remove(service_name) {
+            service_limit.daily_quota = existing.daily_quota;
+            service_limit.daily = existing.daily;
+        }
+
         self.services.insert(service_name.to_string(), service_limit);
-        debug!("Registered rate limit for service '{}': {} ms", service_name, minimum_delay_ms);
+        debug!("Registered rate limit for service '{}': {} ms, burst {}", service_name, minimum_delay_ms, burst);
     }
 
-    /// Apply rate limiting to a service
-    /// 
-    /// This method will block the current thread if necessary to respect the
-    /// configured rate limit for the specified service.
-    /// 
-    /// # Arguments
-    /// * `service_name` - Name of the service to rate limit
-    fn rate_limit(&mut self, service_name: &str) {
-        let now = Instant::now();
-        
-        // Get or create the service limit
-        let service_limit = self.services
+    fn service(&mut self, service_name: &str) -> &mut ServiceLimit {
+        self.services
             .entry(service_name.to_string())
             .or_insert_with(|| {
-                debug!("Using default rate limit for unregistered service '{}': {} ms", 
+                debug!("Using default rate limit for unregistered service '{}': {} ms",
                        service_name, DEFAULT_RATE_LIMIT_MS);
-                
-                ServiceLimit {
-                    last_access: now - Duration::from_millis(DEFAULT_RATE_LIMIT_MS),
-                    minimum_delay_ms: DEFAULT_RATE_LIMIT_MS,
-                }
-            });
-        
-        // Calculate elapsed time since last access
-        let elapsed_ms = now.duration_since(service_limit.last_access).as_millis() as u64;
-        
-        // If not enough time has passed, sleep for the remaining time
-        if elapsed_ms < service_limit.minimum_delay_ms {
-            let sleep_time = service_limit.minimum_delay_ms - elapsed_ms;
-            debug!("Rate limiting service '{}': sleeping for {} ms", service_name, sleep_time);
-            std::thread::sleep(Duration::from_millis(sleep_time));
+                ServiceLimit::new(DEFAULT_RATE_LIMIT_MS, 1, Instant::now())
+            })
+    }
+
+    /// Take a token for a request and count it
+    ///
+    /// Returns how long the caller has to wait, or None if the daily quota is used up.
+    /// The new daily count is returned if the service has a quota, so it can be stored.
+    fn reserve(&mut self, service_name: &str, check_quota: bool) -> Option<(Duration, Option<DailyCount>)> {
+        let date = today();
+        let service_limit = self.service(service_name);
+        if check_quota && service_limit.remaining_on(date) == Some(0) {
+            return None;
+        }
+        let wait = service_limit.reserve(Instant::now());
+        let count = service_limit.count_request(date);
+        Some((wait, service_limit.daily_quota.map(|_| count)))
+    }
+
+    fn quota(&mut self, service_name: &str) -> ServiceQuota {
+        let date = today();
+        let service_limit = self.service(service_name);
+        service_limit.refill(Instant::now());
+        ServiceQuota {
+            service: service_name.to_string(),
+            minimum_delay_ms: service_limit.minimum_delay_ms,
+            burst: service_limit.burst,
+            available: service_limit.tokens.max(0.0).floor() as u32,
+            daily_quota: service_limit.daily_quota,
+            used_today: service_limit.used_on(date),
+            remaining_today: service_limit.remaining_on(date),
         }
-        
-        // Update the last access time
-        service_limit.last_access = Instant::now();
     }
 }
 
@@ -90,22 +187,167 @@ fn get_rate_limiter() -> parking_lot::MutexGuard<'static, RateLimiter> {
 }
 
 /// Register a rate limit for a specific service
-/// 
+///
 /// # Arguments
 /// * `service_name` - Name of the service to register
 /// * `minimum_delay_ms` - Minimum delay between requests in milliseconds
 pub fn register_service(service_name: &str, minimum_delay_ms: u64) {
-    get_rate_limiter().register_service(service_name, minimum_delay_ms);
+    get_rate_limiter().register_service(service_name, minimum_delay_ms, 1);
+}
+
+/// Register a rate limit that allows bursts of requests
+///
+/// Up to `burst` requests can be made without delay, after that requests are
+/// spaced by `minimum_delay_ms` until the burst capacity has been refilled.
+pub fn register_service_with_burst(service_name: &str, minimum_delay_ms: u64, burst: u32) {
+    get_rate_limiter().register_service(service_name, minimum_delay_ms, burst);
+}
+
+/// Register the rate limit of a service from its configuration section
+///
+/// Reads `rate_limit_ms` (the sustained rate), `burst` and `daily_quota`.
+/// Returns the delay between requests in milliseconds.
+pub fn register_from_config(service_name: &str, service_config: &serde_json::Value, default_delay_ms: u64) -> u64 {
+    let rate_limit_ms = service_config.get("rate_limit_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(default_delay_ms);
+    let burst = service_config.get("burst")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1);
+    register_service_with_burst(service_name, rate_limit_ms, burst.min(u32::MAX as u64) as u32);
+
+    if let Some(quota) = service_config.get("daily_quota").and_then(|v| v.as_u64()) {
+        set_daily_quota(service_name, quota);
+    }
+    rate_limit_ms
+}
+
+/// Set the maximum number of requests per day (UTC) for a service
+///
+/// The requests made today are restored from the settings database, so the
+/// quota also holds across restarts.
+pub fn set_daily_quota(service_name: &str, daily_quota: u64) {
+    let stored = crate::helpers::settingsdb::get::<DailyCount>(&format!("{}{}", QUOTA_KEY_PREFIX, service_name))
+        .unwrap_or_else(|e| {
+            warn!("Failed to read request count of '{}': {}", service_name, e);
+            None
+        });
+    let mut limiter = get_rate_limiter();
+    let service_limit = limiter.service(service_name);
+    service_limit.daily_quota = Some(daily_quota);
+    if service_limit.daily.is_none() {
+        service_limit.daily = stored;
+    }
+    debug!("Daily quota for service '{}': {} requests", service_name, daily_quota);
+}
+
+fn store_count(service_name: &str, count: DailyCount) {
+    if let Err(e) = crate::helpers::settingsdb::set(&format!("{}{}", QUOTA_KEY_PREFIX, service_name), &count) {
+        warn!("Failed to store request count of '{}': {}", service_name, e);
+    }
+}
+
+fn wait_for(service_name: &str, wait: Duration) {
+    if !wait.is_zero() {
+        debug!("Rate limiting service '{}': sleeping for {} ms", service_name, wait.as_millis());
+        std::thread::sleep(wait);
+    }
 }
 
 /// Apply rate limiting to a service
-/// 
+///
 /// This function will block the current thread if necessary to respect the
 /// configured rate limit for the specified service. If the service has not been
 /// registered, a default limit of 500ms (2 requests per second) will be applied.
-/// 
+/// The request is counted, but the daily quota is not checked, use `acquire` for
+/// services with a quota.
+///
 /// # Arguments
 /// * `service_name` - Name of the service to rate limit
 pub fn rate_limit(service_name: &str) {
-    get_rate_limiter().rate_limit(service_name);
-}
\ No newline at end of file
+    let reserved = get_rate_limiter().reserve(service_name, false);
+    if let Some((wait, count)) = reserved {
+        if let Some(count) = count {
+            store_count(service_name, count);
+        }
+        wait_for(service_name, wait);
+    }
+}
+
+/// Apply rate limiting to a service with a daily quota
+///
+/// Like `rate_limit`, but returns false without waiting if the daily quota of the
+/// service is used up. The request must not be made in this case.
+pub fn acquire(service_name: &str) -> bool {
+    let reserved = get_rate_limiter().reserve(service_name, true);
+    match reserved {
+        Some((wait, count)) => {
+            if let Some(count) = count {
+                store_count(service_name, count);
+            }
+            wait_for(service_name, wait);
+            true
+        }
+        None => {
+            debug!("Daily quota of service '{}' is used up", service_name);
+            false
+        }
+    }
+}
+
+/// Get the rate limit and quota state of all registered services, sorted by name
+pub fn get_quotas() -> Vec<ServiceQuota> {
+    let mut limiter = get_rate_limiter();
+    let mut names: Vec<String> = limiter.services.keys().cloned().collect();
+    names.sort();
+    names.iter().map(|name| limiter.quota(name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_burst() {
+        let start = Instant::now();
+        let mut limit = ServiceLimit::new(1000, 3, start);
+
+        // The burst can be used without waiting
+        assert_eq!(limit.reserve(start), Duration::ZERO);
+        assert_eq!(limit.reserve(start), Duration::ZERO);
+        assert_eq!(limit.reserve(start), Duration::ZERO);
+        // Then requests are spaced by the sustained rate
+        assert_eq!(limit.reserve(start), Duration::from_millis(1000));
+        assert_eq!(limit.reserve(start), Duration::from_millis(2000));
+
+        // Refilling never exceeds the burst capacity
+        let later = start + Duration::from_secs(60);
+        limit.refill(later);
+        assert_eq!(limit.tokens, 3.0);
+    }
+
+    #[test]
+    fn test_fixed_interval_without_burst() {
+        let start = Instant::now();
+        let mut limit = ServiceLimit::new(500, 1, start);
+        assert_eq!(limit.reserve(start), Duration::ZERO);
+        assert_eq!(limit.reserve(start + Duration::from_millis(200)), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_daily_quota() {
+        let mut limit = ServiceLimit::new(0, 1, Instant::now());
+        limit.daily_quota = Some(2);
+        let day = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+
+        assert_eq!(limit.remaining_on(day), Some(2));
+        limit.count_request(day);
+        assert_eq!(limit.count_request(day).count, 2);
+        assert_eq!(limit.remaining_on(day), Some(0));
+
+        // The count starts over on the next day
+        let next_day = day.succ_opt().unwrap();
+        assert_eq!(limit.remaining_on(next_day), Some(2));
+        assert_eq!(limit.count_request(next_day).count, 1);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::config::get_service_config;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Thresholds that decide when a played track counts as a scrobble
+///
+/// These are shared by all scrobbling services, so a track is either
+/// scrobbled everywhere or nowhere. The defaults follow the Last.fm rules:
+/// a track must be longer than 30 seconds and must have been played for
+/// half its duration or for 4 minutes, whichever comes first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrobbleThresholds {
+    /// Percentage of the track that must have been played
+    #[serde(default = "default_min_percentage")]
+    pub min_percentage: u32,
+
+    /// Playing this many seconds always counts, even for long tracks
+    #[serde(default = "default_max_seconds")]
+    pub max_seconds: u64,
+
+    /// Tracks shorter than this (in seconds) are never scrobbled
+    #[serde(default = "default_min_track_length")]
+    pub min_track_length: u32,
+
+    /// For radio streams without a track length, playing this many seconds counts
+    #[serde(default = "default_stream_min_seconds")]
+    pub stream_min_seconds: u64,
+
+    /// Only log scrobble decisions instead of submitting them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn default_min_percentage() -> u32 {
+    50
+}
+
+fn default_max_seconds() -> u64 {
+    240
+}
+
+fn default_min_track_length() -> u32 {
+    30
+}
+
+fn default_stream_min_seconds() -> u64 {
+    60
+}
+
+impl Default for ScrobbleThresholds {
+    fn default() -> Self {
+        Self {
+            min_percentage: default_min_percentage(),
+            max_seconds: default_max_seconds(),
+            min_track_length: default_min_track_length(),
+            stream_min_seconds: default_stream_min_seconds(),
+            dry_run: false,
+        }
+    }
+}
+
+/// Outcome of checking a track against the scrobble thresholds
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrobbleDecision {
+    /// Enough of the track was played to reach the percentage threshold
+    PercentageReached { played: u64, length: u32, percentage: u32 },
+
+    /// The track was played longer than the absolute time threshold
+    DurationReached { played: u64, max_seconds: u64 },
+
+    /// The track is too short to be scrobbled at all
+    TrackTooShort { length: u32, min_track_length: u32 },
+
+    /// The track length is unknown and the track is not a radio stream
+    UnknownLength,
+
+    /// Not enough of the track has been played yet
+    NotYet { played: u64, needed: u64 },
+}
+
+impl ScrobbleDecision {
+    /// Whether the track should be submitted as a scrobble
+    pub fn should_scrobble(&self) -> bool {
+        matches!(self, ScrobbleDecision::PercentageReached { .. } | ScrobbleDecision::DurationReached { .. })
+    }
+
+    /// Whether the decision can't change anymore while the track keeps playing
+    pub fn is_final(&self) -> bool {
+        !matches!(self, ScrobbleDecision::NotYet { .. })
+    }
+}
+
+impl fmt::Display for ScrobbleDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrobbleDecision::PercentageReached { played, length, percentage } => write!(
+                f,
+                "scrobble: played {}s of {}s, reached {}% threshold",
+                played, length, percentage
+            ),
+            ScrobbleDecision::DurationReached { played, max_seconds } => write!(
+                f,
+                "scrobble: played {}s, reached {}s threshold",
+                played, max_seconds
+            ),
+            ScrobbleDecision::TrackTooShort { length, min_track_length } => write!(
+                f,
+                "no scrobble: track length {}s is below minimum of {}s",
+                length, min_track_length
+            ),
+            ScrobbleDecision::UnknownLength => write!(f, "no scrobble: track length unknown"),
+            ScrobbleDecision::NotYet { played, needed } => write!(
+                f,
+                "no scrobble: played {}s, {}s needed",
+                played, needed
+            ),
+        }
+    }
+}
+
+impl ScrobbleThresholds {
+    /// Check a radio stream track without a known length against the thresholds
+    ///
+    /// # Arguments
+    /// * `played` - Seconds of the track that have actually been played
+    pub fn evaluate_stream(&self, played: u64) -> ScrobbleDecision {
+        if played >= self.stream_min_seconds {
+            ScrobbleDecision::DurationReached {
+                played,
+                max_seconds: self.stream_min_seconds,
+            }
+        } else {
+            ScrobbleDecision::NotYet {
+                played,
+                needed: self.stream_min_seconds,
+            }
+        }
+    }
+
+    /// Check a track against the thresholds
+    ///
+    /// # Arguments
+    /// * `length` - Track length in seconds
+    /// * `played` - Seconds of the track that have actually been played
+    pub fn evaluate(&self, length: u32, played: u64) -> ScrobbleDecision {
+        if length < self.min_track_length {
+            return ScrobbleDecision::TrackTooShort {
+                length,
+                min_track_length: self.min_track_length,
+            };
+        }
+
+        let percentage_needed = u64::from(length).saturating_mul(u64::from(self.min_percentage)) / 100;
+
+        if played >= percentage_needed {
+            ScrobbleDecision::PercentageReached {
+                played,
+                length,
+                percentage: self.min_percentage,
+            }
+        } else if played >= self.max_seconds {
+            ScrobbleDecision::DurationReached {
+                played,
+                max_seconds: self.max_seconds,
+            }
+        } else {
+            ScrobbleDecision::NotYet {
+                played,
+                needed: percentage_needed.min(self.max_seconds),
+            }
+        }
+    }
+}
+
+static SCROBBLE_THRESHOLDS: Lazy<RwLock<ScrobbleThresholds>> =
+    Lazy::new(|| RwLock::new(ScrobbleThresholds::default()));
+
+/// Initialize the scrobble thresholds from the `scrobbling` service configuration
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let Some(scrobbling_config) = get_service_config(config, "scrobbling") else {
+        info!("No scrobbling configuration found, using default thresholds");
+        return;
+    };
+
+    match serde_json::from_value::<ScrobbleThresholds>(scrobbling_config.clone()) {
+        Ok(thresholds) => {
+            info!(
+                "Scrobble thresholds: {}% or {}s, minimum track length {}s{}",
+                thresholds.min_percentage,
+                thresholds.max_seconds,
+                thresholds.min_track_length,
+                if thresholds.dry_run { " (dry run)" } else { "" }
+            );
+            set_thresholds(thresholds);
+        }
+        Err(e) => {
+            warn!("Invalid scrobbling configuration, using default thresholds: {}", e);
+        }
+    }
+}
+
+/// Get the current scrobble thresholds
+pub fn thresholds() -> ScrobbleThresholds {
+    SCROBBLE_THRESHOLDS.read().clone()
+}
+
+/// Replace the current scrobble thresholds
+pub fn set_thresholds(thresholds: ScrobbleThresholds) {
+    *SCROBBLE_THRESHOLDS.write() = thresholds;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_thresholds() {
+        let thresholds = ScrobbleThresholds::default();
+        assert_eq!(thresholds.min_percentage, 50);
+        assert_eq!(thresholds.max_seconds, 240);
+        assert_eq!(thresholds.min_track_length, 30);
+        assert!(!thresholds.dry_run);
+    }
+
+    #[test]
+    fn test_evaluate_percentage() {
+        let thresholds = ScrobbleThresholds::default();
+        assert!(!thresholds.evaluate(200, 99).should_scrobble());
+        assert_eq!(
+            thresholds.evaluate(200, 100),
+            ScrobbleDecision::PercentageReached { played: 100, length: 200, percentage: 50 }
+        );
+    }
+
+    #[test]
+    fn test_evaluate_long_track() {
+        let thresholds = ScrobbleThresholds::default();
+        assert_eq!(thresholds.evaluate(1200, 200), ScrobbleDecision::NotYet { played: 200, needed: 240 });
+        assert_eq!(
+            thresholds.evaluate(1200, 240),
+            ScrobbleDecision::DurationReached { played: 240, max_seconds: 240 }
+        );
+    }
+
+    #[test]
+    fn test_evaluate_short_track() {
+        let thresholds = ScrobbleThresholds::default();
+        let decision = thresholds.evaluate(20, 20);
+        assert!(!decision.should_scrobble());
+        assert!(decision.is_final());
+    }
+
+    #[test]
+    fn test_evaluate_stream() {
+        let thresholds = ScrobbleThresholds::default();
+        assert_eq!(thresholds.evaluate_stream(59), ScrobbleDecision::NotYet { played: 59, needed: 60 });
+        assert!(thresholds.evaluate_stream(60).should_scrobble());
+        assert!(ScrobbleDecision::UnknownLength.is_final());
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let thresholds: ScrobbleThresholds =
+            serde_json::from_value(serde_json::json!({ "min_percentage": 80, "dry_run": true })).unwrap();
+        assert_eq!(thresholds.min_percentage, 80);
+        assert_eq!(thresholds.max_seconds, 240);
+        assert!(thresholds.dry_run);
+        assert!(!thresholds.evaluate(100, 79).should_scrobble());
+    }
+}
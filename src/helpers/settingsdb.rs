@@ -543,6 +543,13 @@ pub fn get_all_favourite_songs() -> Result<Vec<(String, String)>, String> {
     Ok(favourite_songs)
 }
 
+/// Normalize an artist or title the way `get_all_favourite_songs` returns it
+///
+/// The stored keys are lossy, names must be compared in this form.
+pub fn normalize_favourite_name(name: &str) -> String {
+    sanitize_key_component(name).replace("_", " ")
+}
+
 /// Sanitize a key component by replacing problematic characters
 fn sanitize_key_component(input: &str) -> String {
     input
@@ -553,6 +560,51 @@ fn sanitize_key_component(input: &str) -> String {
         .to_lowercase()
 }
 
+/// Key of a favourite album or artist in the settings database
+fn favourite_item_key(item: &crate::helpers::favourites::FavouriteItem) -> String {
+    use crate::helpers::favourites::FavouriteItem;
+    match item {
+        FavouriteItem::Album { artist, name } => {
+            format!("favourite_album:{}:{}", sanitize_key_component(artist.trim()), sanitize_key_component(name.trim()))
+        }
+        FavouriteItem::Artist { name } => format!("favourite_artist:{}", sanitize_key_component(name.trim())),
+    }
+}
+
+/// Add an album or artist to favourites in the settings database
+///
+/// The item is stored with its original names, the key only identifies it.
+pub fn add_favourite_item(item: &crate::helpers::favourites::FavouriteItem) -> Result<(), String> {
+    set(&favourite_item_key(item), item)
+}
+
+/// Remove an album or artist from favourites in the settings database
+pub fn remove_favourite_item(item: &crate::helpers::favourites::FavouriteItem) -> Result<(), String> {
+    remove(&favourite_item_key(item)).map(|_| ())
+}
+
+/// Check if an album or artist is marked as favourite in the settings database
+pub fn is_favourite_item(item: &crate::helpers::favourites::FavouriteItem) -> Result<bool, String> {
+    contains_key(&favourite_item_key(item))
+}
+
+/// Get all favourite albums or artists from the settings database
+pub fn get_all_favourite_items(
+    kind: crate::helpers::favourites::FavouriteKind,
+) -> Result<Vec<crate::helpers::favourites::FavouriteItem>, String> {
+    let prefix = match kind {
+        crate::helpers::favourites::FavouriteKind::Album => "favourite_album:",
+        crate::helpers::favourites::FavouriteKind::Artist => "favourite_artist:",
+    };
+    let mut items = Vec::new();
+    for key in get_all_keys()?.into_iter().filter(|key| key.starts_with(prefix)) {
+        if let Some(item) = get(&key)? {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
 /// Settings DB implementation of FavouriteProvider
 pub struct SettingsDbFavouriteProvider;
 
@@ -631,6 +683,32 @@ impl crate::helpers::favourites::FavouriteProvider for SettingsDbFavouriteProvid
         // No authentication or external connectivity required
         self.is_enabled() && get_settings_db().db.is_some()
     }
+
+    fn capabilities(&self) -> crate::helpers::favourites::ProviderCapabilities {
+        crate::helpers::favourites::ProviderCapabilities {
+            albums_and_artists: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_favourite_item(&self, item: &crate::helpers::favourites::FavouriteItem) -> Result<bool, crate::helpers::favourites::FavouriteError> {
+        is_favourite_item(item).map_err(crate::helpers::favourites::FavouriteError::StorageError)
+    }
+
+    fn add_favourite_item(&self, item: &crate::helpers::favourites::FavouriteItem) -> Result<(), crate::helpers::favourites::FavouriteError> {
+        add_favourite_item(item).map_err(crate::helpers::favourites::FavouriteError::StorageError)
+    }
+
+    fn remove_favourite_item(&self, item: &crate::helpers::favourites::FavouriteItem) -> Result<(), crate::helpers::favourites::FavouriteError> {
+        remove_favourite_item(item).map_err(crate::helpers::favourites::FavouriteError::StorageError)
+    }
+
+    fn get_favourite_items(
+        &self,
+        kind: crate::helpers::favourites::FavouriteKind,
+    ) -> Result<Vec<crate::helpers::favourites::FavouriteItem>, crate::helpers::favourites::FavouriteError> {
+        get_all_favourite_items(kind).map_err(crate::helpers::favourites::FavouriteError::StorageError)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,415 @@
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use redis::Commands;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Key prefixes shared by default, these are the results of metadata lookups
+pub const DEFAULT_SHARED_PREFIXES: &[&str] = &[
+    "artist::",
+    "album::",
+    "theaudiodb::",
+    "wikipedia::",
+    "spotify::",
+];
+
+/// Default timeout for connecting and for each request
+const DEFAULT_TIMEOUT_MS: u64 = 200;
+
+/// How long a key that isn't in the shared cache isn't looked up again
+const MISS_TTL: Duration = Duration::from_secs(60);
+
+/// Number of remembered misses that triggers removing the expired ones
+const MAX_MISSES: usize = 1000;
+
+/// How long to wait before connecting again after a failure
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// A serialized value and its remaining time to live in seconds
+pub type SharedValue = (Vec<u8>, Option<u64>);
+
+/// A cache that is shared with other devices, e.g. all players in a household
+///
+/// Values are the serialized values of the attribute cache. The shared cache is only an
+/// addition to the local cache, so implementations should fail fast if the server isn't reachable.
+pub trait SharedCache: Send {
+    /// Name of the backend, used in log messages
+    fn name(&self) -> &str;
+
+    /// Get a value and its remaining time to live
+    fn get(&mut self, key: &str) -> Result<Option<SharedValue>, String>;
+
+    /// Store a value, without a TTL it never expires
+    fn set(&mut self, key: &str, value: &[u8], ttl_seconds: Option<u64>) -> Result<(), String>;
+
+    /// Remove a value
+    fn remove(&mut self, key: &str) -> Result<(), String>;
+
+    /// Remove all values whose key starts with the prefix, returns the number of removed values
+    fn remove_by_prefix(&mut self, prefix: &str) -> Result<usize, String>;
+}
+
+/// The shared cache with the prefixes of the keys stored in it
+///
+/// The attribute cache hands this out and talks to the server without holding its own lock, so a slow
+/// server only delays the caller that needs the value. Keys that the server doesn't have are remembered
+/// for a minute, so looking up a missing key again doesn't cost a request.
+pub struct SharedStore {
+    name: String,
+    cache: Mutex<Box<dyn SharedCache>>,
+    prefixes: Vec<String>,
+    /// Keys not found on the server and until when they aren't looked up again
+    misses: Mutex<HashMap<String, Instant>>,
+}
+
+impl SharedStore {
+    pub fn new(cache: Box<dyn SharedCache>, prefixes: Vec<String>) -> Self {
+        SharedStore {
+            name: cache.name().to_string(),
+            cache: Mutex::new(cache),
+            prefixes,
+            misses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Name of the backend
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether a key is stored in the shared cache
+    pub fn is_shared(&self, key: &str) -> bool {
+        self.prefixes.iter().any(|prefix| key.starts_with(prefix.as_str()))
+    }
+
+    /// Get a value and its remaining time to live, None if it isn't available for any reason
+    pub fn get(&self, key: &str) -> Option<SharedValue> {
+        let now = Instant::now();
+        if self.misses.lock().get(key).is_some_and(|until| *until > now) {
+            return None;
+        }
+
+        let result = self.cache.lock().get(key);
+        match result {
+            Ok(Some(found)) => Some(found),
+            Ok(None) => {
+                let mut misses = self.misses.lock();
+                if misses.len() >= MAX_MISSES {
+                    misses.retain(|_, until| *until > now);
+                }
+                misses.insert(key.to_string(), now + MISS_TTL);
+                None
+            }
+            Err(e) => {
+                debug!("Failed to look up '{}' in shared cache: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Store serialized data until `expires_at` (Unix timestamp), failures only affect other devices
+    pub fn set(&self, key: &str, data: &[u8], expires_at: Option<i64>) {
+        let ttl_seconds = match expires_at {
+            Some(expires_at) => match remaining_seconds(expires_at) {
+                Some(remaining) => Some(remaining),
+                None => return,
+            },
+            None => None,
+        };
+
+        self.misses.lock().remove(key);
+        if let Err(e) = self.cache.lock().set(key, data, ttl_seconds) {
+            debug!("Failed to store '{}' in shared cache: {}", key, e);
+        }
+    }
+
+    pub fn remove(&self, key: &str) {
+        if let Err(e) = self.cache.lock().remove(key) {
+            warn!("Failed to remove '{}' from shared cache: {}", key, e);
+        }
+    }
+
+    pub fn remove_by_prefix(&self, prefix: &str) {
+        match self.cache.lock().remove_by_prefix(prefix) {
+            Ok(count) => debug!("Removed {} shared cache entries with prefix '{}'", count, prefix),
+            Err(e) => warn!("Failed to remove entries with prefix '{}' from shared cache: {}", prefix, e),
+        }
+    }
+}
+
+/// Seconds until a Unix timestamp, None if it has passed
+fn remaining_seconds(expires_at: i64) -> Option<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    u64::try_from(expires_at - now).ok().filter(|remaining| *remaining > 0)
+}
+
+/// Configuration of the shared cache in `datastore.attribute_cache.shared`
+#[derive(Debug, Clone)]
+pub struct SharedCacheConfig {
+    /// Backend, currently only `redis` (also for KeyDB and other compatible servers)
+    pub backend: String,
+    pub url: String,
+    /// Prepended to all keys, allows several installations on one server
+    pub key_prefix: String,
+    /// Only keys with these prefixes are shared
+    pub prefixes: Vec<String>,
+    pub timeout: Duration,
+}
+
+impl SharedCacheConfig {
+    /// Parse the configuration, returns None if the shared cache is not enabled
+    pub fn from_json(config: &serde_json::Value) -> Result<Option<Self>, String> {
+        if !config.get("enable").and_then(|v| v.as_bool()).unwrap_or(true) {
+            return Ok(None);
+        }
+
+        let backend = config.get("backend").and_then(|v| v.as_str()).unwrap_or("redis").to_string();
+        let url = config.get("url")
+            .and_then(|v| v.as_str())
+            .ok_or("The shared cache needs a url, e.g. redis://192.168.1.10:6379/0")?
+            .to_string();
+        let key_prefix = config.get("key_prefix").and_then(|v| v.as_str()).unwrap_or("audiocontrol:").to_string();
+        let prefixes = match config.get("prefixes").and_then(|v| v.as_array()) {
+            Some(prefixes) => prefixes.iter().filter_map(|p| p.as_str()).map(String::from).collect(),
+            None => DEFAULT_SHARED_PREFIXES.iter().map(|p| p.to_string()).collect(),
+        };
+        let timeout = Duration::from_millis(
+            config.get("timeout_ms").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_TIMEOUT_MS).max(1),
+        );
+
+        Ok(Some(SharedCacheConfig {
+            backend,
+            url,
+            key_prefix,
+            prefixes,
+            timeout,
+        }))
+    }
+
+    /// Create the cache of the configured backend
+    pub fn create_cache(&self) -> Result<Box<dyn SharedCache>, String> {
+        match self.backend.as_str() {
+            "redis" | "keydb" => Ok(Box::new(RedisCache::new(&self.url, &self.key_prefix, self.timeout)?)),
+            other => Err(format!("Unknown shared cache backend '{}', supported: redis", other)),
+        }
+    }
+}
+
+/// Shared cache on a Redis or KeyDB server
+pub struct RedisCache {
+    client: redis::Client,
+    connection: Option<redis::Connection>,
+    key_prefix: String,
+    timeout: Duration,
+    /// No connection attempts before this time after a failure
+    retry_after: Option<Instant>,
+}
+
+impl RedisCache {
+    /// Create the cache, the connection is opened on first use
+    pub fn new(url: &str, key_prefix: &str, timeout: Duration) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("Invalid Redis URL '{}': {}", url, e))?;
+        info!("Sharing attribute cache entries on {}", client.get_connection_info().addr);
+        Ok(RedisCache {
+            client,
+            connection: None,
+            key_prefix: key_prefix.to_string(),
+            timeout,
+            retry_after: None,
+        })
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.key_prefix, key)
+    }
+
+    /// Run a command, reconnecting if necessary
+    ///
+    /// After a failure the connection is dropped and no further attempts are made for a while,
+    /// so an unreachable server doesn't slow down every cache access.
+    fn with_connection<T>(
+        &mut self,
+        f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T, String> {
+        if self.connection.is_none() {
+            if self.retry_after.is_some_and(|t| Instant::now() < t) {
+                return Err("Redis server not reachable".to_string());
+            }
+            match self.connect() {
+                Ok(connection) => {
+                    debug!("Connected to Redis server {}", self.client.get_connection_info().addr);
+                    self.connection = Some(connection);
+                    self.retry_after = None;
+                }
+                Err(e) => {
+                    warn!("Failed to connect to Redis server {}: {}", self.client.get_connection_info().addr, e);
+                    self.retry_after = Some(Instant::now() + RECONNECT_DELAY);
+                    return Err(e.to_string());
+                }
+            }
+        }
+
+        let result = match self.connection.as_mut() {
+            Some(connection) => f(connection),
+            None => return Err("Not connected".to_string()),
+        };
+        result.map_err(|e| {
+            if e.is_io_error() || e.is_connection_dropped() || e.is_timeout() {
+                self.connection = None;
+                self.retry_after = Some(Instant::now() + RECONNECT_DELAY);
+            }
+            e.to_string()
+        })
+    }
+
+    fn connect(&self) -> redis::RedisResult<redis::Connection> {
+        let connection = self.client.get_connection_with_timeout(self.timeout)?;
+        connection.set_read_timeout(Some(self.timeout))?;
+        connection.set_write_timeout(Some(self.timeout))?;
+        Ok(connection)
+    }
+}
+
+/// Escape the characters that have a meaning in Redis glob patterns
+fn escape_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl SharedCache for RedisCache {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<SharedValue>, String> {
+        let key = self.full_key(key);
+        let (value, ttl): (Option<Vec<u8>>, i64) = self.with_connection(|con| {
+            redis::pipe().get(&key).ttl(&key).query(con)
+        })?;
+        // TTL is -1 for keys without expiry
+        Ok(value.map(|value| (value, u64::try_from(ttl).ok())))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8], ttl_seconds: Option<u64>) -> Result<(), String> {
+        let key = self.full_key(key);
+        self.with_connection(|con| match ttl_seconds {
+            Some(ttl) => con.set_ex(&key, value, ttl.max(1)),
+            None => con.set(&key, value),
+        })
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), String> {
+        let key = self.full_key(key);
+        self.with_connection(|con| con.del(&key))
+    }
+
+    fn remove_by_prefix(&mut self, prefix: &str) -> Result<usize, String> {
+        let pattern = format!("{}*", escape_pattern(&self.full_key(prefix)));
+        self.with_connection(|con| {
+            let keys: Vec<String> = con.scan_match::<_, String>(&pattern)?.collect();
+            if keys.is_empty() {
+                return Ok(0);
+            }
+            con.del(keys)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_config() {
+        let config = SharedCacheConfig::from_json(&json!({"url": "redis://127.0.0.1/2"})).unwrap().unwrap();
+        assert_eq!(config.backend, "redis");
+        assert_eq!(config.key_prefix, "audiocontrol:");
+        assert_eq!(config.prefixes.len(), DEFAULT_SHARED_PREFIXES.len());
+        assert!(config.create_cache().is_ok());
+
+        assert!(SharedCacheConfig::from_json(&json!({"enable": false})).unwrap().is_none());
+        assert!(SharedCacheConfig::from_json(&json!({})).is_err());
+
+        let config = SharedCacheConfig::from_json(&json!({"url": "redis://127.0.0.1", "backend": "memcached"}))
+            .unwrap()
+            .unwrap();
+        assert!(config.create_cache().is_err());
+    }
+
+    #[test]
+    fn test_escape_pattern() {
+        assert_eq!(escape_pattern("artist::mbid::AC*DC?"), "artist::mbid::AC\\*DC\\?");
+        assert_eq!(escape_pattern("[a]\\"), "\\[a\\]\\\\");
+    }
+
+    /// Counts the requests, the server never has a value
+    struct EmptyCache {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl SharedCache for EmptyCache {
+        fn name(&self) -> &str {
+            "empty"
+        }
+
+        fn get(&mut self, _key: &str) -> Result<Option<SharedValue>, String> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+
+        fn set(&mut self, _key: &str, _value: &[u8], _ttl_seconds: Option<u64>) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn remove(&mut self, _key: &str) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn remove_by_prefix(&mut self, _prefix: &str) -> Result<usize, String> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_remember_misses() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let store = SharedStore::new(Box::new(EmptyCache { requests: requests.clone() }), vec!["artist::".to_string()]);
+        assert!(store.is_shared("artist::mbid::Adele"));
+        assert!(!store.is_shared("image_meta::/file.jpg"));
+
+        assert!(store.get("artist::mbid::Adele").is_none());
+        assert!(store.get("artist::mbid::Adele").is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Storing the key makes it worth asking again
+        store.set("artist::mbid::Adele", b"[]", None);
+        assert!(store.get("artist::mbid::Adele").is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_remaining_seconds() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        assert!(remaining_seconds(now + 100).is_some_and(|s| (99..=100).contains(&s)));
+        assert_eq!(remaining_seconds(now - 1), None);
+    }
+
+    #[test]
+    fn test_unreachable_server() {
+        // Nothing listens on port 1
+        let mut cache = RedisCache::new("redis://127.0.0.1:1/0", "test:", Duration::from_millis(100)).unwrap();
+        assert!(cache.get("key").is_err());
+        let start = Instant::now();
+        assert!(cache.set("key", b"value", None).is_err());
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
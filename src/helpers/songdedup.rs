@@ -0,0 +1,128 @@
+use crate::data::{PlayerSource, Song};
+use crate::helpers::settingsdb::normalize_favourite_name;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Song metadata key holding the id of the player that reported the song first
+pub const DUPLICATE_OF_KEY: &str = "duplicate_of";
+
+/// The same song reported by another player within this time is a duplicate
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Songs whose durations differ by more than this are different recordings
+const MAX_DURATION_DIFFERENCE: f64 = 3.0;
+
+/// Identifies a song independent of the player reporting it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SongFingerprint {
+    pub artist: String,
+    pub title: String,
+}
+
+impl SongFingerprint {
+    /// Fingerprint from the normalized artist and title, None if one of them is missing
+    pub fn from_song(song: &Song) -> Option<Self> {
+        let artist = normalize_favourite_name(song.artist.as_deref()?.trim());
+        let title = normalize_favourite_name(song.title.as_deref()?.trim());
+        if artist.is_empty() || title.is_empty() {
+            return None;
+        }
+        Some(Self { artist, title })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Report {
+    player_id: String,
+    duration: Option<f64>,
+    seen: Instant,
+}
+
+/// Remembers which player reported a song first
+#[derive(Debug, Default)]
+struct DuplicateDetector {
+    reports: HashMap<SongFingerprint, Report>,
+}
+
+impl DuplicateDetector {
+    /// Returns the id of the player that reported the same song shortly before, if it was another player
+    fn check(&mut self, player_id: &str, song: &Song, now: Instant) -> Option<String> {
+        let fingerprint = SongFingerprint::from_song(song)?;
+        self.reports.retain(|_, r| now.duration_since(r.seen) < DUPLICATE_WINDOW);
+
+        if let Some(report) = self.reports.get_mut(&fingerprint) {
+            let same_recording = match (report.duration, song.duration) {
+                (Some(a), Some(b)) => (a - b).abs() <= MAX_DURATION_DIFFERENCE,
+                _ => true,
+            };
+            if report.player_id == player_id {
+                report.seen = now;
+                return None;
+            }
+            if same_recording {
+                return Some(report.player_id.clone());
+            }
+        }
+
+        self.reports.insert(fingerprint, Report {
+            player_id: player_id.to_string(),
+            duration: song.duration,
+            seen: now,
+        });
+        None
+    }
+}
+
+static DETECTOR: Lazy<Mutex<DuplicateDetector>> = Lazy::new(|| Mutex::new(DuplicateDetector::default()));
+
+/// Mark a song that another player reported shortly before, e.g. MPD and its MPRIS mirror
+///
+/// Called once per song change before the event is published. Duplicates keep being
+/// sent so the player state stays correct, but they carry the `duplicate_of` metadata
+/// and are not recorded in the history or scrobbled a second time.
+pub fn mark_duplicate(source: &PlayerSource, song: &mut Song) {
+    if let Some(original) = DETECTOR.lock().check(source.player_id(), song, Instant::now()) {
+        log::debug!("Song '{:?}' from {} was already reported by {}", song.title, source.player_id(), original);
+        song.metadata.insert(DUPLICATE_OF_KEY.to_string(), serde_json::Value::String(original));
+    }
+}
+
+/// Whether the song was reported by another player first
+pub fn is_duplicate(song: &Song) -> bool {
+    song.metadata.contains_key(DUPLICATE_OF_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(artist: &str, title: &str, duration: Option<f64>) -> Song {
+        Song {
+            artist: Some(artist.to_string()),
+            title: Some(title.to_string()),
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_duplicate_detector() {
+        let mut detector = DuplicateDetector::default();
+        let start = Instant::now();
+
+        assert_eq!(detector.check("mpd", &song("Artist", "Title", Some(200.0)), start), None);
+        // The mirror reports the same song with slightly different metadata
+        assert_eq!(
+            detector.check("mpris", &song("artist", "Title ", Some(201.0)), start + Duration::from_secs(1)),
+            Some("mpd".to_string())
+        );
+        // The original player reporting again is not a duplicate
+        assert_eq!(detector.check("mpd", &song("Artist", "Title", Some(200.0)), start + Duration::from_secs(2)), None);
+        // A different recording with the same title
+        assert_eq!(detector.check("spotify", &song("Artist", "Title", Some(320.0)), start + Duration::from_secs(3)), None);
+        // Long after the first report
+        assert_eq!(detector.check("mpris", &song("Artist", "Title", Some(200.0)), start + Duration::from_secs(60)), None);
+    }
+}
@@ -5,6 +5,10 @@ use std::sync::Arc;
 use parking_lot::Mutex;
 use log::{debug, info, warn};
 
+/// Song metadata key set when artist and title were split from a stream title.
+/// The value is the splitter ID, which is the station URL.
+pub const SPLIT_TITLE_SOURCE_KEY: &str = "split_title_source";
+
 /// Manager for song title splitters that handles creation, reuse, and lifecycle
 /// 
 /// This manager ensures that splitters are reused for the same ID, allowing
@@ -92,28 +96,8 @@ impl SongSplitManager {
     /// * `Option<(String, String)>` - Tuple of (artist, song) if successfully split
     pub fn split_song(&self, splitter_id: &str, title: &str) -> Option<(String, String)> {
         let mut splitters = self.splitters.lock();
-        // Check if we already have a splitter for this ID in memory
-        if !splitters.contains_key(splitter_id) {
-            // Check if we've reached the maximum number of splitters
-            if splitters.len() >= self.max_splitters {
-                warn!("Maximum number of splitters ({}) reached, cannot create new splitter for ID: {}", 
-                      self.max_splitters, splitter_id);
-                return None;
-            }
-            
-            // Try to load from persistent storage first
-            let new_splitter = if let Some(cached_splitter) = self.load_from_cache(splitter_id) {
-                debug!("Loaded splitter for ID '{}' from persistent storage for splitting", splitter_id);
-                cached_splitter
-            } else {
-                // Create a new splitter if not found in cache
-                debug!("Creating new splitter for ID: {}", splitter_id);
-                SongTitleSplitter::new(splitter_id)
-            };
-            
-            splitters.insert(splitter_id.to_string(), new_splitter);
-            info!("Created/loaded song title splitter for '{}' (total splitters: {})", 
-                  splitter_id, splitters.len());
+        if !self.ensure_splitter(&mut splitters, splitter_id) {
+            return None;
         }
         
         // Now get mutable access to the splitter and split the song
@@ -125,6 +109,98 @@ impl SongSplitManager {
         }
     }
     
+    /// Make sure a splitter for the given ID is in memory, loading or creating it if needed
+    /// 
+    /// # Returns
+    /// * `bool` - false if the splitter doesn't exist and the maximum number of splitters is reached
+    fn ensure_splitter(&self, splitters: &mut HashMap<String, SongTitleSplitter>, splitter_id: &str) -> bool {
+        if splitters.contains_key(splitter_id) {
+            return true;
+        }
+        
+        // Check if we've reached the maximum number of splitters
+        if splitters.len() >= self.max_splitters {
+            warn!("Maximum number of splitters ({}) reached, cannot create new splitter for ID: {}", 
+                  self.max_splitters, splitter_id);
+            return false;
+        }
+        
+        // Try to load from persistent storage first
+        let new_splitter = if let Some(cached_splitter) = self.load_from_cache(splitter_id) {
+            debug!("Loaded splitter for ID '{}' from persistent storage", splitter_id);
+            cached_splitter
+        } else {
+            // Create a new splitter if not found in cache
+            debug!("Creating new splitter for ID: {}", splitter_id);
+            SongTitleSplitter::new(splitter_id)
+        };
+        
+        splitters.insert(splitter_id.to_string(), new_splitter);
+        info!("Created/loaded song title splitter for '{}' (total splitters: {})", 
+              splitter_id, splitters.len());
+        true
+    }
+    
+    /// Get a copy of the splitter for the given ID without creating it
+    pub fn get_splitter(&self, splitter_id: &str) -> Option<SongTitleSplitter> {
+        let splitters = self.splitters.lock();
+        splitters.get(splitter_id).cloned()
+    }
+    
+    /// Modify the splitter for the given ID and save it to persistent storage
+    /// 
+    /// The splitter is loaded or created if needed, so overrides can be set
+    /// for stations that haven't been played yet.
+    /// 
+    /// # Arguments
+    /// * `splitter_id` - Unique identifier for the splitter
+    /// * `update` - Function that modifies the splitter
+    /// 
+    /// # Returns
+    /// * `Result<R, String>` - Result of the update function, or an error if the splitter can't be created
+    pub fn update_splitter<R, F>(&self, splitter_id: &str, update: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut SongTitleSplitter) -> R,
+    {
+        let result = {
+            let mut splitters = self.splitters.lock();
+            if !self.ensure_splitter(&mut splitters, splitter_id) {
+                return Err(format!("Maximum number of splitters ({}) reached", self.max_splitters));
+            }
+            match splitters.get_mut(splitter_id) {
+                Some(splitter) => update(splitter),
+                None => return Err(format!("No splitter found for ID: {}", splitter_id)),
+            }
+        };
+        
+        // The change stays active in memory even if it can't be persisted
+        if let Err(e) = self.save(splitter_id) {
+            warn!("Failed to persist splitter update for '{}': {}", splitter_id, e);
+        }
+        Ok(result)
+    }
+    
+    /// Reset the learned order and separator of the splitter for the given ID
+    /// 
+    /// # Returns
+    /// * `bool` - true if a splitter was found and reset
+    pub fn reset_splitter(&self, splitter_id: &str) -> bool {
+        if self.get_splitter(splitter_id).is_none() && self.load_from_cache(splitter_id).is_none() {
+            debug!("No splitter found to reset for ID: {}", splitter_id);
+            return false;
+        }
+        match self.update_splitter(splitter_id, |splitter| splitter.reset()) {
+            Ok(()) => {
+                info!("Reset song title splitter for '{}'", splitter_id);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to reset splitter for '{}': {}", splitter_id, e);
+                false
+            }
+        }
+    }
+    
     /// Get the number of active splitters
     pub fn get_splitter_count(&self) -> usize {
         let splitters = self.splitters.lock();
@@ -320,6 +396,7 @@ impl Clone for SongSplitManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::songtitlesplitter::OrderResult;
     use std::thread;
 
     #[test]
@@ -486,4 +563,30 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("No splitter found"));
     }
+
+    #[test]
+    fn test_update_and_reset_splitter() {
+        let manager = SongSplitManager::new();
+        let splitter_id = "test_update_station";
+        
+        assert!(manager.get_splitter(splitter_id).is_none());
+        
+        // Updating creates the splitter if needed
+        let result = manager.update_splitter(splitter_id, |splitter| {
+            splitter.set_default_order(Some(OrderResult::SongArtist))
+        });
+        assert_eq!(result, Ok(true));
+        assert_eq!(manager.get_splitter(splitter_id).unwrap().get_default_order(), Some(OrderResult::SongArtist));
+        
+        assert!(manager.reset_splitter(splitter_id));
+        assert!(!manager.get_splitter(splitter_id).unwrap().has_default_order());
+    }
+
+    #[test]
+    fn test_update_splitter_limit() {
+        let manager = SongSplitManager::with_max_splitters(1);
+        manager.get_or_create_splitter("station1");
+        
+        assert!(manager.update_splitter("station2", |_| ()).is_err());
+    }
 }
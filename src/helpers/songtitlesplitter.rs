@@ -46,6 +46,14 @@ pub fn split_song(input: &str) -> Option<(String, String)> {
     split_song_with_separator(input, None)
 }
 
+/// Separator characters that can be used to split song titles
+pub const SUPPORTED_SEPARATORS: [char; 3] = ['-', '/', ':'];
+
+/// Check if a character can be used as a song title separator
+pub fn is_supported_separator(separator: char) -> bool {
+    SUPPORTED_SEPARATORS.contains(&separator)
+}
+
 /// Split a song title with optional preferred separator character
 ///
 /// This function finds the first occurrence of supported separators: "-", "/", or ":"
@@ -632,6 +640,43 @@ impl SongTitleSplitter {
         self.default_order.is_some()
     }
     
+    /// Manually set the order for this splitter
+    /// 
+    /// This overrides the learned order, e.g. for stations that are known to
+    /// send "Song - Artist" titles. Passing None returns to automatic detection.
+    /// Only ArtistSong and SongArtist can be used as a default order.
+    /// 
+    /// # Returns
+    /// true if the order was set, false if the order can't be used as a default
+    pub fn set_default_order(&mut self, order: Option<OrderResult>) -> bool {
+        match order {
+            Some(OrderResult::Unknown) | Some(OrderResult::Undecided) => false,
+            order => {
+                info!("Manually setting default order for '{}' to {:?}", self.id, order);
+                self.default_order = order;
+                self.lookup_cache.clear();
+                true
+            }
+        }
+    }
+    
+    /// Manually set the separator for this splitter
+    /// 
+    /// Passing None returns to automatic detection.
+    /// 
+    /// # Returns
+    /// true if the separator was set, false if the separator is not supported
+    pub fn set_default_separator(&mut self, separator: Option<char>) -> bool {
+        if let Some(sep) = separator {
+            if !is_supported_separator(sep) {
+                return false;
+            }
+        }
+        info!("Manually setting default separator for '{}' to {:?}", self.id, separator);
+        self.default_separator = separator;
+        true
+    }
+    
     /// Get the percentage of successful detections for each order type
     /// 
     /// # Returns
@@ -1421,4 +1466,26 @@ mod tests {
         assert_eq!(cloned.get_id(), "cloned");
         assert_eq!(original.get_id(), "original");
     }
+
+    #[test]
+    fn test_manual_overrides() {
+        let mut splitter = SongTitleSplitter::new("test_override");
+        
+        // Song - Artist stations can be fixed without waiting for learning
+        assert!(splitter.set_default_order(Some(OrderResult::SongArtist)));
+        assert_eq!(splitter.split_song("Hey Jude - The Beatles"), 
+                   Some(("The Beatles".to_string(), "Hey Jude".to_string())));
+        
+        // Undetermined results can't be used as default
+        assert!(!splitter.set_default_order(Some(OrderResult::Unknown)));
+        assert_eq!(splitter.get_default_order(), Some(OrderResult::SongArtist));
+        
+        assert!(splitter.set_default_separator(Some('/')));
+        assert!(!splitter.set_default_separator(Some('|')));
+        assert_eq!(splitter.get_default_separator(), Some('/'));
+        
+        splitter.reset();
+        assert!(!splitter.has_default_order());
+        assert!(!splitter.has_default_separator());
+    }
 }
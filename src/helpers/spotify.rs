@@ -97,6 +97,15 @@ pub struct SpotifyDevice {
     pub id: Option<String>,
     pub name: String,
     pub volume_percent: Option<u32>,
+    /// Whether this device is the currently active device
+    #[serde(default)]
+    pub is_active: bool,
+    /// Device type, e.g. "Computer", "Smartphone", "Speaker"
+    #[serde(rename = "type", default)]
+    pub device_type: Option<String>,
+    /// Restricted devices can't be controlled through the Web API
+    #[serde(default)]
+    pub is_restricted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,7 +130,7 @@ pub struct SpotifyAlbum {
     pub images: Option<Vec<SpotifyImage>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpotifyImage {
     pub url: String,
     pub width: Option<u32>,
@@ -153,6 +162,10 @@ pub struct SpotifyConfig {
     pub proxy_secret: String,
     pub client_id: Option<String>,
     pub client_secret: Option<String>,
+    /// Spotify Connect name of the local librespot device, the host name if not set
+    pub device_name: Option<String>,
+    /// Add images, genres and popularity from Spotify to the artists of the libraries
+    pub artist_metadata: bool,
 }
 
 /// Spotify helper class for managing authentication and tokens
@@ -183,7 +196,11 @@ impl SpotifyConfig {
         };
         let client_id = spotify_config.get("client_id").and_then(|v| v.as_str()).map(|s| s.to_string());
         let client_secret = spotify_config.get("client_secret").and_then(|v| v.as_str()).map(|s| s.to_string());
-        SpotifyConfig { oauth_url, proxy_secret, client_id, client_secret }
+        let device_name = spotify_config.get("device_name").and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.to_string());
+        let artist_metadata = spotify_config.get("artist_metadata").and_then(|v| v.as_bool()).unwrap_or(true);
+        SpotifyConfig { oauth_url, proxy_secret, client_id, client_secret, device_name, artist_metadata }
     }
 }
 
@@ -196,6 +213,8 @@ impl Spotify {
                 proxy_secret: crate::helpers::spotify::default_spotify_proxy_secret(),
                 client_id: None,
                 client_secret: None,
+                device_name: None,
+                artist_metadata: true,
             }),
         }
     }    /// Initialize the Spotify client with OAuth configuration
@@ -224,6 +243,8 @@ impl Spotify {
             proxy_secret,
             client_id: None,
             client_secret: None,
+            device_name: None,
+            artist_metadata: true,
         };
         
         let spotify = Spotify { config };
@@ -520,6 +541,11 @@ impl Spotify {
                         },
                         Err(e) => {
                             error!("Direct API token refresh failed: {}", e);
+                            crate::helpers::notifications::notify(
+                                crate::helpers::notifications::NotificationCategory::AuthExpiry,
+                                "Spotify login expired",
+                                &format!("The Spotify login of this device couldn't be renewed: {}. Log in to Spotify again.", e),
+                            );
                             Err(e)
                         }
                     }
@@ -656,6 +682,65 @@ impl Spotify {
             Err(e) => Err(SpotifyError::ApiError(format!("Command failed: {}", e))),
         }
     }
+    /// Get all Spotify Connect devices of the user
+    ///
+    /// See: https://developer.spotify.com/documentation/web-api/reference/get-a-users-available-devices
+    pub fn get_devices(&self) -> Result<Vec<SpotifyDevice>> {
+        use crate::helpers::http_client::new_http_client;
+        let access_token = self.ensure_valid_token()?;
+        let http_client = new_http_client(10);
+        let url = "https://api.spotify.com/v1/me/player/devices";
+        let headers = [
+            ("Authorization", &format!("Bearer {}", access_token)[..]),
+            ("Content-Type", "application/json"),
+        ];
+        let response = http_client.get_json_with_headers(url, &headers)
+            .map_err(|e| SpotifyError::ApiError(format!("Failed to get devices: {}", e)))?;
+        let devices = response.get("devices").cloned().unwrap_or(serde_json::Value::Array(Vec::new()));
+        serde_json::from_value::<Vec<SpotifyDevice>>(devices).map_err(SpotifyError::SerializationError)
+    }
+
+    /// Transfer playback to another Spotify Connect device
+    ///
+    /// See: https://developer.spotify.com/documentation/web-api/reference/transfer-a-users-playback
+    ///
+    /// # Arguments
+    /// * `device_id` - ID of the target device
+    /// * `play` - Start playback on the new device, otherwise the current playback state is kept
+    pub fn transfer_playback(&self, device_id: &str, play: bool) -> Result<()> {
+        use crate::helpers::http_client::{new_http_client, HttpClientError};
+        let access_token = self.ensure_valid_token()?;
+        let http_client = new_http_client(10);
+        let url = "https://api.spotify.com/v1/me/player";
+        let headers = [
+            ("Authorization", &format!("Bearer {}", access_token)[..]),
+            ("Content-Type", "application/json"),
+        ];
+        let body = serde_json::json!({ "device_ids": [device_id], "play": play });
+        match http_client.put_json_value_with_headers(url, body, &headers) {
+            Ok(_) | Err(HttpClientError::EmptyResponse) => {
+                info!("Transferred Spotify playback to device {}", device_id);
+                Ok(())
+            },
+            Err(e) => Err(SpotifyError::ApiError(format!("Transfer failed: {}", e))),
+        }
+    }
+
+    /// Spotify Connect name of the local device
+    pub fn local_device_name(&self) -> String {
+        self.config.device_name.clone().unwrap_or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .map(|name| name.trim().to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Check if a Spotify Connect device is the local librespot instance
+    pub fn is_local_device(&self, device: &SpotifyDevice) -> bool {
+        let local_name = self.local_device_name();
+        !local_name.is_empty() && device.name.trim().eq_ignore_ascii_case(local_name.trim())
+    }
+
     /// Get the user's currently playing track from Spotify
     pub fn get_currently_playing(&self) -> Result<Option<serde_json::Value>> {
         use crate::helpers::http_client::new_http_client;
@@ -964,6 +1049,15 @@ impl crate::helpers::favourites::FavouriteProvider for SpotifyFavouriteProvider
         }
     }
 
+    fn capabilities(&self) -> crate::helpers::favourites::ProviderCapabilities {
+        crate::helpers::favourites::ProviderCapabilities {
+            read_only: true,
+            sync: false,
+            lookup_latency: crate::helpers::favourites::LookupLatency::Network,
+            albums_and_artists: false,
+        }
+    }
+
     fn is_active(&self) -> bool {
         // For Spotify, active means we have valid authentication tokens and can make API calls
         // This is the same as is_enabled for Spotify
@@ -976,5 +1070,163 @@ impl Spotify {
     pub fn set_global_config(spotify_config: &serde_json::Value) {
         let config = SpotifyConfig::from_json(spotify_config);
         let _ = GLOBAL_SPOTIFY_CONFIG.set(config);
+        // Artist lookups of a library update must not hit the Web API rate limit
+        crate::helpers::ratelimit::register_from_config("spotify", spotify_config, 200);
+    }
+}
+
+/// Artist details from the Spotify Web API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpotifyArtistDetails {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub popularity: Option<u32>,
+    #[serde(default)]
+    pub images: Vec<SpotifyImage>,
+}
+
+/// The artist with exactly this name, ignoring case, from a search response
+fn find_artist(search_result: &serde_json::Value, name: &str) -> Option<SpotifyArtistDetails> {
+    search_result["artists"]["items"]
+        .as_array()?
+        .iter()
+        .filter_map(|item| serde_json::from_value::<SpotifyArtistDetails>(item.clone()).ok())
+        .find(|artist| artist.name.to_lowercase() == name.to_lowercase())
+}
+
+impl Spotify {
+    /// Look up an artist by name
+    ///
+    /// Spotify doesn't know MusicBrainz IDs, only an artist with exactly the same name is used.
+    pub fn lookup_artist(&self, name: &str) -> Result<Option<SpotifyArtistDetails>> {
+        crate::helpers::ratelimit::rate_limit("spotify");
+        let search_result = self.search(&format!("artist:\"{}\"", name), &["artist"], None)?;
+        Ok(find_artist(&search_result, name))
+    }
+}
+
+/// Adds images, genres and popularity from Spotify to artists
+///
+/// Only used when a Spotify account is connected and `artist_metadata` is enabled.
+pub struct SpotifyArtistUpdater;
+
+impl SpotifyArtistUpdater {
+    /// Cached details, None if Spotify doesn't know the artist
+    fn details(spotify: &Spotify, name: &str) -> Option<SpotifyArtistDetails> {
+        let cache_key = format!("spotify::artist::{}", name);
+        let not_found_cache_key = format!("spotify::artist_not_found::{}", name);
+        if let Ok(Some(details)) = crate::helpers::attributecache::get::<SpotifyArtistDetails>(&cache_key) {
+            return Some(details);
+        }
+        if let Ok(Some(true)) = crate::helpers::attributecache::get::<bool>(&not_found_cache_key) {
+            return None;
+        }
+        match spotify.lookup_artist(name) {
+            Ok(Some(details)) => {
+                if let Err(e) = crate::helpers::attributecache::set(&cache_key, &details) {
+                    debug!("Failed to cache Spotify data for artist {}: {}", name, e);
+                }
+                Some(details)
+            }
+            Ok(None) => {
+                if let Err(e) = crate::helpers::attributecache::set(&not_found_cache_key, &true) {
+                    debug!("Failed to cache negative Spotify result for artist {}: {}", name, e);
+                }
+                None
+            }
+            Err(e) => {
+                debug!("Spotify lookup of artist {} failed: {}", name, e);
+                None
+            }
+        }
+    }
+}
+
+impl crate::helpers::ArtistUpdater for SpotifyArtistUpdater {
+    fn update_artist(&self, mut artist: crate::data::artist::Artist) -> crate::data::artist::Artist {
+        let spotify = match Spotify::get_instance() {
+            Ok(spotify) if spotify.config.artist_metadata && spotify.has_valid_tokens() => spotify,
+            _ => return artist,
+        };
+        let Some(details) = Self::details(&spotify, &artist.name) else {
+            return artist;
+        };
+
+        artist.ensure_metadata();
+        if let Some(meta) = &mut artist.metadata {
+            // Spotify lists the largest image first
+            if let Some(image) = details.images.first() {
+                meta.add_thumb_url(image.url.clone());
+            }
+            for genre in crate::helpers::genre_cleanup::clean_genres_global(details.genres.clone()) {
+                meta.add_genre(genre);
+            }
+            if details.popularity.is_some() {
+                meta.popularity = details.popularity;
+            }
+            debug!("Added Spotify data of artist {} ({})", artist.name, details.id);
+        }
+        artist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device() {
+        let device: SpotifyDevice = serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "is_active": true,
+            "is_private_session": false,
+            "is_restricted": false,
+            "name": "Kitchen",
+            "type": "Speaker",
+            "volume_percent": 40
+        }))
+        .unwrap();
+        assert!(device.is_active);
+        assert_eq!(device.device_type.as_deref(), Some("Speaker"));
+
+        // Playback state responses without the new fields still parse
+        let device: SpotifyDevice = serde_json::from_value(serde_json::json!({"id": null, "name": "Phone", "volume_percent": null})).unwrap();
+        assert!(!device.is_active);
+    }
+
+    #[test]
+    fn test_find_artist() {
+        let search_result = serde_json::json!({ "artists": { "items": [
+            { "id": "1", "name": "Miles Davis Quintet", "genres": [], "popularity": 40, "images": [] },
+            { "id": "2", "name": "Miles Davis", "genres": ["jazz", "cool jazz"], "popularity": 68,
+              "images": [{ "url": "https://i.scdn.co/image/large", "width": 640, "height": 640 }] }
+        ] } });
+        let artist = find_artist(&search_result, "miles davis").unwrap();
+        assert_eq!(artist.id, "2");
+        assert_eq!(artist.popularity, Some(68));
+        assert_eq!(artist.images[0].width, Some(640));
+        assert!(find_artist(&search_result, "John Coltrane").is_none());
+    }
+
+    #[test]
+    fn test_is_local_device() {
+        let mut config = SpotifyConfig::from_json(&serde_json::json!({"device_name": "HiFiBerry"}));
+        let spotify = Spotify { config: config.clone() };
+        let device = |name: &str| SpotifyDevice {
+            id: Some("1".to_string()),
+            name: name.to_string(),
+            volume_percent: None,
+            is_active: false,
+            device_type: None,
+            is_restricted: false,
+        };
+        assert!(spotify.is_local_device(&device("hifiberry")));
+        assert!(!spotify.is_local_device(&device("Kitchen")));
+
+        config.device_name = Some("Living Room".to_string());
+        assert!(Spotify { config }.is_local_device(&device("Living Room")));
     }
 }
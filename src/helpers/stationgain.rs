@@ -0,0 +1,360 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::PlaybackState;
+use crate::helpers::global_volume;
+use crate::players::mpd::MPDPlayerController;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::thread;
+use std::time::Duration;
+
+/// Key in the settings database storing the gain offsets by stream URL
+const OFFSETS_KEY: &str = "station_gain.offsets";
+
+/// Configuration of the gain offsets for radio presets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationGainConfig {
+    /// Apply gain offsets when a radio preset is playing
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Largest offset that is applied or suggested, in dB
+    #[serde(default = "default_max_offset_db")]
+    pub max_offset_db: f64,
+
+    /// Minimum listening time of a station before an offset is suggested for it
+    #[serde(default = "default_min_listen_secs")]
+    pub min_listen_secs: u64,
+
+    /// Seconds between two checks of the playing station
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_max_offset_db() -> f64 {
+    12.0
+}
+
+fn default_min_listen_secs() -> u64 {
+    600
+}
+
+fn default_poll_interval_secs() -> u64 {
+    5
+}
+
+impl Default for StationGainConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            max_offset_db: default_max_offset_db(),
+            min_listen_secs: default_min_listen_secs(),
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+/// A radio preset with its gain offset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StationGain {
+    pub url: String,
+    pub name: Option<String>,
+    /// Volume change while this station is playing, in dB
+    pub offset_db: f64,
+}
+
+/// Volume levels recorded while a station was playing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelStats {
+    pub seconds: u64,
+    /// Sum of the volume in dB, weighted by seconds
+    volume_sum: f64,
+    /// Sum of the volume without the applied offset in dB, weighted by seconds
+    base_sum: f64,
+}
+
+impl LevelStats {
+    /// Record the volume chosen while the station played with the given offset
+    pub fn add(&mut self, seconds: u64, volume_db: f64, offset_db: f64) {
+        self.seconds += seconds;
+        self.volume_sum += volume_db * seconds as f64;
+        self.base_sum += (volume_db - offset_db) * seconds as f64;
+    }
+}
+
+/// Suggested gain offset for a station, based on the volume chosen while listening
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GainSuggestion {
+    pub url: String,
+    pub name: Option<String>,
+    pub offset_db: Option<f64>,
+    pub suggested_offset_db: f64,
+    pub listened_seconds: u64,
+}
+
+/// Suggest offsets from the volume levels chosen for each station
+///
+/// The reference level is the average volume without offsets over all stations.
+/// A station that is turned up compared to the reference gets a positive offset.
+/// Only stations with at least `min_seconds` of listening are considered, and
+/// suggestions need at least two of them.
+pub fn suggest_offsets(
+    stats: &HashMap<String, LevelStats>,
+    min_seconds: u64,
+    max_offset_db: f64,
+) -> Vec<(String, f64, u64)> {
+    let measured: Vec<(&String, &LevelStats)> = stats.iter().filter(|(_, s)| s.seconds >= min_seconds).collect();
+    if measured.len() < 2 {
+        return Vec::new();
+    }
+    let total_seconds: u64 = measured.iter().map(|(_, s)| s.seconds).sum();
+    let reference = measured.iter().map(|(_, s)| s.base_sum).sum::<f64>() / total_seconds as f64;
+
+    let mut suggestions: Vec<(String, f64, u64)> = measured
+        .into_iter()
+        .map(|(url, s)| {
+            let offset = (s.volume_sum / s.seconds as f64 - reference).clamp(-max_offset_db, max_offset_db);
+            // Half a dB is precise enough and keeps the values readable
+            (url.clone(), (offset * 2.0).round() / 2.0, s.seconds)
+        })
+        .collect();
+    suggestions.sort_by(|a, b| a.0.cmp(&b.0));
+    suggestions
+}
+
+/// Station that is currently playing and the offset applied for it
+#[derive(Debug, Default)]
+struct Applied {
+    url: Option<String>,
+    offset_db: f64,
+}
+
+static CONFIG: Lazy<RwLock<StationGainConfig>> = Lazy::new(|| RwLock::new(StationGainConfig::default()));
+static APPLIED: Lazy<RwLock<Applied>> = Lazy::new(|| RwLock::new(Applied::default()));
+static LEVELS: Lazy<RwLock<HashMap<String, LevelStats>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static NO_DB_WARNED: AtomicBool = AtomicBool::new(false);
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize gain offsets from the `station_gain` service configuration
+///
+/// If enabled, a background thread applies the offset of the playing station and
+/// records the volume levels used for suggestions.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let gain_config = match get_service_config(config, "station_gain") {
+        Some(c) => match serde_json::from_value::<StationGainConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid station_gain configuration, station gain offsets disabled: {}", e);
+                return;
+            }
+        },
+        None => StationGainConfig::default(),
+    };
+    *CONFIG.write() = gain_config.clone();
+
+    if !gain_config.enable {
+        debug!("Station gain offsets are disabled");
+        return;
+    }
+
+    info!("Applying gain offsets for radio presets");
+    let interval = Duration::from_secs(gain_config.poll_interval_secs.max(1));
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        check_station(interval);
+    });
+}
+
+/// Check whether gain offsets are enabled
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+fn load_offsets() -> HashMap<String, StationGain> {
+    match crate::helpers::settingsdb::get::<HashMap<String, StationGain>>(OFFSETS_KEY) {
+        Ok(offsets) => offsets.unwrap_or_default(),
+        Err(e) => {
+            warn!("Failed to read station gain offsets: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Get the saved radio presets with their offsets, sorted by URL
+///
+/// Contains streams saved in MPD playlists and all stations that have an offset.
+pub fn list_presets() -> Vec<StationGain> {
+    let mut presets = load_offsets();
+    for (url, name) in saved_streams() {
+        presets.entry(url.clone()).or_insert(StationGain { url, name, offset_db: 0.0 });
+    }
+    let mut presets: Vec<StationGain> = presets.into_values().collect();
+    presets.sort_by(|a, b| a.url.cmp(&b.url));
+    presets
+}
+
+/// Set the gain offset of a station, an offset of 0 removes it
+///
+/// The offset is applied immediately if the station is playing.
+pub fn set_offset(url: &str, name: Option<String>, offset_db: f64) -> Result<StationGain, String> {
+    let max = CONFIG.read().max_offset_db;
+    if !offset_db.is_finite() || offset_db.abs() > max {
+        return Err(format!("Offset must be between -{0} and {0} dB", max));
+    }
+
+    let mut offsets = load_offsets();
+    let name = name.or_else(|| offsets.get(url).and_then(|g| g.name.clone()));
+    let gain = StationGain { url: url.to_string(), name, offset_db };
+    if offset_db == 0.0 {
+        offsets.remove(url);
+    } else {
+        offsets.insert(url.to_string(), gain.clone());
+    }
+    crate::helpers::settingsdb::set(OFFSETS_KEY, &offsets)?;
+
+    let playing = APPLIED.read().url.as_deref() == Some(url);
+    if playing && is_enabled() {
+        apply_offset(offset_db);
+    }
+    Ok(gain)
+}
+
+/// Suggest offsets for all stations that have been listened to long enough
+pub fn get_suggestions() -> Vec<GainSuggestion> {
+    let config = CONFIG.read().clone();
+    let offsets = load_offsets();
+    let names: HashMap<String, Option<String>> = saved_streams().into_iter().collect();
+    suggest_offsets(&LEVELS.read(), config.min_listen_secs, config.max_offset_db)
+        .into_iter()
+        .map(|(url, suggested_offset_db, listened_seconds)| GainSuggestion {
+            name: offsets
+                .get(&url)
+                .and_then(|g| g.name.clone())
+                .or_else(|| names.get(&url).cloned().flatten()),
+            offset_db: offsets.get(&url).map(|g| g.offset_db),
+            url,
+            suggested_offset_db,
+            listened_seconds,
+        })
+        .collect()
+}
+
+fn get_controller() -> Option<std::sync::Arc<AudioController>> {
+    CONTROLLER.read().as_ref().and_then(|c| c.upgrade())
+}
+
+/// Stream URLs saved in MPD playlists with the station names
+fn saved_streams() -> Vec<(String, Option<String>)> {
+    let Some(controller) = get_controller() else {
+        return Vec::new();
+    };
+    let mut streams = Vec::new();
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            streams.extend(mpd.get_stored_playlist_streams().into_iter().map(|s| (s.url, s.name)));
+        }
+    }
+    streams
+}
+
+/// Get the URL of the station the active player is playing
+fn playing_station(controller: &AudioController) -> Option<String> {
+    let active = controller.get_active_controller()?;
+    let player = active.read();
+    if player.get_playback_state() != PlaybackState::Playing {
+        return None;
+    }
+    player
+        .get_song()
+        .and_then(|song| song.stream_url)
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+}
+
+/// Apply the offset of the playing station and record its volume level
+fn check_station(interval: Duration) {
+    let Some(controller) = get_controller() else {
+        return;
+    };
+    let station = playing_station(&controller);
+
+    if station != APPLIED.read().url {
+        let offset = station
+            .as_ref()
+            .and_then(|url| load_offsets().get(url).map(|g| g.offset_db))
+            .unwrap_or(0.0);
+        if let Some(url) = &station {
+            debug!("Station {} is playing, gain offset {} dB", url, offset);
+        }
+        apply_offset(offset);
+        APPLIED.write().url = station.clone();
+        controller.enforce_volume_limit();
+    }
+
+    if let (Some(url), Some(volume_db)) = (station, global_volume::get_volume_db()) {
+        let offset = APPLIED.read().offset_db;
+        LEVELS.write().entry(url).or_default().add(interval.as_secs(), volume_db, offset);
+    }
+}
+
+/// Change the volume by the difference between the new and the applied offset
+///
+/// Changing by the difference keeps volume changes made while a station is playing.
+fn apply_offset(offset_db: f64) {
+    let delta = offset_db - APPLIED.read().offset_db;
+    if delta == 0.0 {
+        return;
+    }
+    let Some(volume_db) = global_volume::get_volume_db() else {
+        if !NO_DB_WARNED.swap(true, Ordering::SeqCst) {
+            warn!("Volume control has no decibel scale, station gain offsets can't be applied");
+        }
+        return;
+    };
+    if global_volume::set_volume_db(volume_db + delta) {
+        APPLIED.write().offset_db = offset_db;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_offsets() {
+        let mut stats = HashMap::new();
+        // Quiet station, turned up to -20 dB
+        stats.entry("http://quiet".to_string()).or_insert_with(LevelStats::default).add(600, -20.0, 0.0);
+        // Loud station, turned down to -30 dB
+        stats.entry("http://loud".to_string()).or_insert_with(LevelStats::default).add(600, -30.0, 0.0);
+        // Not listened to long enough
+        stats.entry("http://short".to_string()).or_insert_with(LevelStats::default).add(30, -10.0, 0.0);
+
+        let suggestions = suggest_offsets(&stats, 300, 12.0);
+        assert_eq!(suggestions, vec![
+            ("http://loud".to_string(), -5.0, 600),
+            ("http://quiet".to_string(), 5.0, 600),
+        ]);
+    }
+
+    #[test]
+    fn test_suggestions_keep_good_offsets() {
+        let mut stats = HashMap::new();
+        // An offset of +4 dB already levels the station, the user didn't change the volume
+        stats.entry("http://a".to_string()).or_insert_with(LevelStats::default).add(600, -21.0, 4.0);
+        stats.entry("http://b".to_string()).or_insert_with(LevelStats::default).add(1200, -25.0, 0.0);
+
+        let suggestions = suggest_offsets(&stats, 300, 12.0);
+        assert_eq!(suggestions[0], ("http://a".to_string(), 4.0, 600));
+        assert_eq!(suggestions[1], ("http://b".to_string(), 0.0, 1200));
+
+        assert!(suggest_offsets(&stats, 900, 12.0).is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::players::mpd::MPDPlayerController;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const JOB_ID: &str = "stream_check";
+const RADIO_BROWSER_API_BASE: &str = "https://all.api.radio-browser.info/json";
+const USER_AGENT: &str = "HifiBerry-ACR/1.0 (https://www.hifiberry.com/)";
+
+/// Configuration of the stream URL health checker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamCheckConfig {
+    /// Check stream URLs periodically in the background
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Hours between two checks
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u64,
+
+    /// Timeout for connecting to a single stream
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Look up replacement URLs for dead streams on radio-browser.info
+    #[serde(default)]
+    pub radio_browser: bool,
+}
+
+fn default_interval_hours() -> u64 {
+    24
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for StreamCheckConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            interval_hours: default_interval_hours(),
+            timeout_secs: default_timeout_secs(),
+            radio_browser: false,
+        }
+    }
+}
+
+/// A saved stream URL that should be checked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamSource {
+    /// The stream URL
+    pub url: String,
+    /// Station name if known
+    pub name: Option<String>,
+    /// Where the URL is saved, e.g. "mpd playlist: Radio"
+    pub source: String,
+}
+
+/// Result of checking a single stream URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamCheckResult {
+    pub url: String,
+    pub name: Option<String>,
+    /// All places where this URL is saved
+    pub sources: Vec<String>,
+    /// Whether the stream could be opened
+    pub ok: bool,
+    /// HTTP status code if the server answered
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    /// Station name reported in the icy-name header
+    pub icy_name: Option<String>,
+    /// Bitrate reported in the icy-br header
+    pub icy_bitrate: Option<String>,
+    pub error: Option<String>,
+    /// Working URL for the same station from radio-browser.info, only for dead streams
+    pub replacement: Option<String>,
+    /// Unix timestamp of the check
+    pub checked_at: u64,
+}
+
+static RESULTS: Lazy<RwLock<HashMap<String, StreamCheckResult>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static CONFIG: Lazy<RwLock<StreamCheckConfig>> = Lazy::new(|| RwLock::new(StreamCheckConfig::default()));
+static CHECK_RUNNING: AtomicBool = AtomicBool::new(false);
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize the stream checker from the `streamcheck` service configuration
+///
+/// The controller is used to find saved stream URLs. If checking is enabled,
+/// a background thread runs the check every `interval_hours`.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let check_config = match get_service_config(config, "streamcheck") {
+        Some(c) => match serde_json::from_value::<StreamCheckConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid streamcheck configuration, stream checking disabled: {}", e);
+                return;
+            }
+        },
+        None => StreamCheckConfig::default(),
+    };
+    *CONFIG.write() = check_config.clone();
+
+    if !check_config.enable {
+        debug!("Periodic stream URL checking is disabled");
+        return;
+    }
+
+    info!(
+        "Checking saved stream URLs every {}h{}",
+        check_config.interval_hours,
+        if check_config.radio_browser { " with radio-browser.info lookups" } else { "" }
+    );
+
+    let interval = Duration::from_secs(check_config.interval_hours.max(1) * 3600);
+    thread::spawn(move || {
+        // Give the players some time to connect before the first check
+        thread::sleep(Duration::from_secs(60));
+        loop {
+            run_check();
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// Start a check of all saved stream URLs in a background thread
+///
+/// # Returns
+/// * `bool` - false if a check is already running
+pub fn start_check() -> bool {
+    if CHECK_RUNNING.load(Ordering::SeqCst) {
+        return false;
+    }
+    thread::spawn(run_check);
+    true
+}
+
+/// Check whether a stream check is currently running
+pub fn is_running() -> bool {
+    CHECK_RUNNING.load(Ordering::SeqCst)
+}
+
+/// Get the results of the last check, sorted by URL
+pub fn get_results() -> Vec<StreamCheckResult> {
+    let mut results: Vec<StreamCheckResult> = RESULTS.read().values().cloned().collect();
+    results.sort_by(|a, b| a.url.cmp(&b.url));
+    results
+}
+
+/// Collect saved stream URLs from all players
+fn collect_sources() -> Vec<StreamSource> {
+    let Some(controller) = CONTROLLER.read().as_ref().and_then(|c| c.upgrade()) else {
+        return Vec::new();
+    };
+
+    let mut sources = Vec::new();
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            sources.extend(mpd.get_stored_playlist_streams());
+        }
+    }
+    sources
+}
+
+/// Group sources by URL, so every URL is only checked once
+fn group_sources(sources: Vec<StreamSource>) -> Vec<(String, Option<String>, Vec<String>)> {
+    let mut grouped: Vec<(String, Option<String>, Vec<String>)> = Vec::new();
+    for source in sources {
+        if let Some(entry) = grouped.iter_mut().find(|(url, _, _)| *url == source.url) {
+            if entry.1.is_none() {
+                entry.1 = source.name;
+            }
+            if !entry.2.contains(&source.source) {
+                entry.2.push(source.source);
+            }
+        } else {
+            grouped.push((source.url, source.name, vec![source.source]));
+        }
+    }
+    grouped
+}
+
+fn run_check() {
+    if CHECK_RUNNING.swap(true, Ordering::SeqCst) {
+        debug!("Stream check already running");
+        return;
+    }
+
+    let config = CONFIG.read().clone();
+    let streams = group_sources(collect_sources());
+    let total = streams.len();
+
+    if let Err(e) = crate::helpers::backgroundjobs::register_job(JOB_ID.to_string(), "Stream URL Check".to_string()) {
+        warn!("Failed to register stream check background job: {}", e);
+    }
+    info!("Checking {} saved stream URLs", total);
+
+    let mut results = HashMap::new();
+    let mut dead = 0usize;
+    for (index, (url, name, sources)) in streams.into_iter().enumerate() {
+        let _ = crate::helpers::backgroundjobs::update_job(
+            JOB_ID,
+            Some(format!("Checking: {}", url)),
+            Some(index),
+            Some(total),
+        );
+
+        let mut result = check_stream_url(&url, config.timeout_secs);
+        result.name = name;
+        result.sources = sources;
+
+        if !result.ok {
+            dead += 1;
+            warn!("Stream URL {} is not working: {}", url, result.error.as_deref().unwrap_or("unknown error"));
+            if config.radio_browser {
+                result.replacement = lookup_replacement(&url, result.name.as_deref(), config.timeout_secs);
+                if let Some(replacement) = &result.replacement {
+                    info!("Found replacement for {} on radio-browser.info: {}", url, replacement);
+                }
+            }
+        }
+
+        results.insert(url, result);
+    }
+
+    // Replace all results, so URLs that were removed from playlists disappear
+    *RESULTS.write() = results;
+
+    info!("Stream check complete: {}/{} stream URLs not working", dead, total);
+    let _ = crate::helpers::backgroundjobs::update_job(
+        JOB_ID,
+        Some(format!("{} of {} stream URLs not working", dead, total)),
+        Some(total),
+        Some(total),
+    );
+    let _ = crate::helpers::backgroundjobs::complete_job(JOB_ID);
+    CHECK_RUNNING.store(false, Ordering::SeqCst);
+}
+
+fn now_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn header_value(response: &ureq::Response, name: &str) -> Option<String> {
+    response.header(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Check if a stream URL can be opened
+///
+/// Only the response headers are read, the stream itself is not downloaded.
+pub fn check_stream_url(url: &str, timeout_secs: u64) -> StreamCheckResult {
+    let mut result = StreamCheckResult {
+        url: url.to_string(),
+        name: None,
+        sources: Vec::new(),
+        ok: false,
+        status: None,
+        content_type: None,
+        icy_name: None,
+        icy_bitrate: None,
+        error: None,
+        replacement: None,
+        checked_at: now_timestamp(),
+    };
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        result.error = Some("Not an HTTP stream URL".to_string());
+        return result;
+    }
+
+    let response = ureq::get(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .set("User-Agent", USER_AGENT)
+        .set("Icy-MetaData", "1")
+        .call();
+
+    match response {
+        Ok(response) => {
+            result.status = Some(response.status());
+            result.content_type = header_value(&response, "Content-Type");
+            result.icy_name = header_value(&response, "icy-name");
+            result.icy_bitrate = header_value(&response, "icy-br");
+
+            let is_audio = result.content_type.as_deref().is_some_and(is_stream_content_type);
+            if is_audio || result.icy_name.is_some() {
+                result.ok = true;
+            } else {
+                result.error = Some(format!(
+                    "Unexpected content type: {}",
+                    result.content_type.as_deref().unwrap_or("none")
+                ));
+            }
+        }
+        Err(ureq::Error::Status(code, _)) => {
+            result.status = Some(code);
+            result.error = Some(format!("HTTP status {}", code));
+        }
+        Err(e) => {
+            result.error = Some(e.to_string());
+        }
+    }
+
+    result
+}
+
+/// Check if a content type belongs to an audio stream or a playlist pointing to one
+fn is_stream_content_type(content_type: &str) -> bool {
+    let content_type = content_type.to_lowercase();
+    content_type.starts_with("audio/")
+        || content_type.starts_with("application/ogg")
+        || content_type.contains("mpegurl")
+        || content_type.contains("scpls")
+        || content_type.starts_with("video/mp2t")
+}
+
+#[derive(Deserialize)]
+struct RadioBrowserStation {
+    #[serde(default)]
+    url_resolved: String,
+    #[serde(default)]
+    lastcheckok: i32,
+}
+
+fn radio_browser_query(path: &str, params: &[(&str, &str)], timeout_secs: u64) -> Vec<RadioBrowserStation> {
+    let mut request = ureq::get(&format!("{}/{}", RADIO_BROWSER_API_BASE, path))
+        .timeout(Duration::from_secs(timeout_secs))
+        .set("User-Agent", USER_AGENT);
+    for (key, value) in params {
+        request = request.query(key, value);
+    }
+
+    match request.call() {
+        Ok(response) => match response.into_string() {
+            Ok(body) => serde_json::from_str::<Vec<RadioBrowserStation>>(&body).unwrap_or_else(|e| {
+                debug!("Failed to parse radio-browser.info response: {}", e);
+                Vec::new()
+            }),
+            Err(e) => {
+                debug!("Failed to read radio-browser.info response: {}", e);
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            debug!("radio-browser.info request failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Look up a working URL for a dead stream on radio-browser.info
+///
+/// Stations are first searched by the old URL, then by name.
+pub fn lookup_replacement(url: &str, name: Option<&str>, timeout_secs: u64) -> Option<String> {
+    let pick = |stations: Vec<RadioBrowserStation>| {
+        stations
+            .into_iter()
+            .find(|s| s.lastcheckok == 1 && !s.url_resolved.is_empty() && s.url_resolved != url)
+            .map(|s| s.url_resolved)
+    };
+
+    if let Some(replacement) = pick(radio_browser_query("stations/byurl", &[("url", url)], timeout_secs)) {
+        return Some(replacement);
+    }
+
+    let name = name?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    pick(radio_browser_query(
+        "stations/search",
+        &[("name", name), ("name_exact", "true"), ("hidebroken", "true"), ("order", "clickcount"), ("reverse", "true"), ("limit", "5")],
+        timeout_secs,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_sources() {
+        let source = |url: &str, name: Option<&str>, playlist: &str| StreamSource {
+            url: url.to_string(),
+            name: name.map(|n| n.to_string()),
+            source: playlist.to_string(),
+        };
+        let grouped = group_sources(vec![
+            source("http://a", None, "mpd playlist: One"),
+            source("http://b", Some("B"), "mpd playlist: One"),
+            source("http://a", Some("A"), "mpd playlist: Two"),
+        ]);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].1.as_deref(), Some("A"));
+        assert_eq!(grouped[0].2, vec!["mpd playlist: One", "mpd playlist: Two"]);
+    }
+
+    #[test]
+    fn test_stream_content_types() {
+        assert!(is_stream_content_type("audio/mpeg"));
+        assert!(is_stream_content_type("application/vnd.apple.mpegurl"));
+        assert!(!is_stream_content_type("text/html; charset=utf-8"));
+    }
+
+    #[test]
+    fn test_non_http_url() {
+        let result = check_stream_url("local/file.mp3", 1);
+        assert!(!result.ok);
+        assert!(result.status.is_none());
+    }
+}
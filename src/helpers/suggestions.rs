@@ -0,0 +1,225 @@
+use crate::helpers::listening_history::{self, PlayedSong};
+use crate::helpers::locale;
+use crate::helpers::settingsdb::normalize_favourite_name;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Songs played at least this often count as highly played
+const MIN_PLAY_COUNT: usize = 5;
+
+/// A favourite or often played song that has not been played for a while
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForgottenSong {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub play_count: usize,
+    /// Unix timestamp of the last play, None for favourites that were never played
+    pub last_played: Option<u64>,
+    /// Last play as localized relative time, e.g. "8 months ago"
+    pub last_played_display: String,
+    pub is_favourite: bool,
+}
+
+/// Songs played on the same day in an earlier year
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OnThisDay {
+    pub date: NaiveDate,
+    /// Localized date, e.g. "March 5, 2024"
+    pub date_display: String,
+    pub years_ago: i32,
+    pub plays: Vec<PlayedSong>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestions {
+    pub forgotten: Vec<ForgottenSong>,
+    pub on_this_day: Vec<OnThisDay>,
+}
+
+/// Suggestions from the listening history and favourites
+///
+/// # Arguments
+/// * `months` - Songs not played for this many months are forgotten
+/// * `limit` - Maximum number of forgotten songs and of plays per day
+pub fn get_suggestions(months: u32, limit: usize) -> Suggestions {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let cutoff = now.saturating_sub(months as u64 * 30 * 24 * 3600);
+    let favourites = crate::helpers::settingsdb::get_all_favourite_songs().unwrap_or_default();
+
+    let today = Local::now().date_naive();
+    let on_this_day = same_day_in_earlier_years(&listening_history::get_days(), today)
+        .into_iter()
+        .map(|date| OnThisDay {
+            date,
+            date_display: locale::language().format_date(date),
+            years_ago: today.year() - date.year(),
+            plays: distinct_plays(listening_history::get_plays_on(date), limit),
+        })
+        .collect();
+
+    Suggestions {
+        forgotten: find_forgotten(&listening_history::get_plays_since(0), &favourites, cutoff, limit),
+        on_this_day,
+    }
+}
+
+/// Find favourites and often played songs whose last play is older than the cutoff
+///
+/// Favourites without any play are included if the history goes back beyond the cutoff.
+/// Favourites come first, then the songs played most.
+fn find_forgotten(plays: &[PlayedSong], favourites: &[(String, String)], cutoff: u64, limit: usize) -> Vec<ForgottenSong> {
+    let favourites: HashSet<(String, String)> = favourites
+        .iter()
+        .map(|(artist, title)| (normalize_favourite_name(artist), normalize_favourite_name(title)))
+        .collect();
+
+    let mut songs: HashMap<(String, String), ForgottenSong> = HashMap::new();
+    for play in plays {
+        let key = (normalize_favourite_name(&play.artist), normalize_favourite_name(&play.title));
+        let song = songs.entry(key).or_insert_with(|| ForgottenSong {
+            artist: play.artist.clone(),
+            title: play.title.clone(),
+            album: None,
+            play_count: 0,
+            last_played: None,
+            last_played_display: String::new(),
+            is_favourite: false,
+        });
+        song.play_count += 1;
+        song.last_played = song.last_played.max(Some(play.timestamp));
+        if play.album.is_some() {
+            song.album = play.album.clone();
+        }
+    }
+
+    let history_covers_cutoff = plays.first().is_some_and(|p| p.timestamp < cutoff);
+    for (artist, title) in &favourites {
+        match songs.get_mut(&(artist.clone(), title.clone())) {
+            Some(song) => song.is_favourite = true,
+            None if history_covers_cutoff => {
+                songs.insert((artist.clone(), title.clone()), ForgottenSong {
+                    artist: artist.clone(),
+                    title: title.clone(),
+                    album: None,
+                    play_count: 0,
+                    last_played: None,
+                    last_played_display: String::new(),
+                    is_favourite: true,
+                });
+            }
+            None => {}
+        }
+    }
+
+    let mut forgotten: Vec<ForgottenSong> = songs
+        .into_values()
+        .filter(|s| s.is_favourite || s.play_count >= MIN_PLAY_COUNT)
+        .filter(|s| s.last_played.is_none_or(|t| t < cutoff))
+        .collect();
+    forgotten.sort_by(|a, b| {
+        b.is_favourite
+            .cmp(&a.is_favourite)
+            .then_with(|| b.play_count.cmp(&a.play_count))
+            .then_with(|| a.last_played.cmp(&b.last_played))
+            .then_with(|| a.artist.cmp(&b.artist))
+    });
+    forgotten.truncate(limit);
+    let language = locale::language();
+    for song in &mut forgotten {
+        song.last_played_display = language.relative_timestamp(song.last_played);
+    }
+    forgotten
+}
+
+/// Days with the same month and day as today in earlier years, most recent first
+fn same_day_in_earlier_years(days: &[NaiveDate], today: NaiveDate) -> Vec<NaiveDate> {
+    let mut matching: Vec<NaiveDate> = days
+        .iter()
+        .copied()
+        .filter(|d| d.month() == today.month() && d.day() == today.day() && d.year() < today.year())
+        .collect();
+    matching.sort_by(|a, b| b.cmp(a));
+    matching
+}
+
+/// Plays with repeated songs removed
+fn distinct_plays(plays: Vec<PlayedSong>, limit: usize) -> Vec<PlayedSong> {
+    let mut seen = HashSet::new();
+    plays
+        .into_iter()
+        .filter(|p| seen.insert((p.artist.to_lowercase(), p.title.to_lowercase())))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(artist: &str, title: &str, timestamp: u64) -> PlayedSong {
+        PlayedSong { artist: artist.to_string(), title: title.to_string(), album: None, timestamp }
+    }
+
+    #[test]
+    fn test_find_forgotten() {
+        let mut plays = vec![play("Old", "Loved", 100), play("Recent", "Loved", 2000)];
+        plays.extend((0..MIN_PLAY_COUNT as u64).map(|i| play("Old", "Often", 200 + i)));
+        plays.extend((0..MIN_PLAY_COUNT as u64).map(|i| play("Old", "Still Played", 300 + i * 500)));
+        plays.push(play("Old", "Rare", 400));
+        plays.sort_by_key(|p| p.timestamp);
+        let favourites = vec![
+            ("old".to_string(), "loved".to_string()),
+            ("recent".to_string(), "loved".to_string()),
+            ("never".to_string(), "played".to_string()),
+        ];
+
+        let forgotten = find_forgotten(&plays, &favourites, 1000, 10);
+        let titles: Vec<(&str, &str)> = forgotten.iter().map(|s| (s.artist.as_str(), s.title.as_str())).collect();
+        assert_eq!(titles, vec![("Old", "Loved"), ("never", "played"), ("Old", "Often")]);
+        assert_eq!(forgotten[2].play_count, MIN_PLAY_COUNT);
+
+        // The history does not reach back to the cutoff, unplayed favourites are unknown
+        assert!(find_forgotten(&plays, &favourites, 50, 10).is_empty());
+    }
+
+    #[test]
+    fn test_same_day_in_earlier_years() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let days = vec![day(2022, 6, 1), day(2023, 6, 1), day(2023, 6, 2), day(2024, 6, 1)];
+        assert_eq!(same_day_in_earlier_years(&days, day(2024, 6, 1)), vec![day(2023, 6, 1), day(2022, 6, 1)]);
+    }
+
+    #[test]
+    fn test_suggestions_from_history() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let noon = |date: NaiveDate| date.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(Local).unwrap().timestamp() as u64;
+        let last_year = noon(day(2023, 6, 1));
+        let recently = noon(day(2024, 5, 20));
+        let mut plays = vec![("Old", "Loved", last_year), ("Old", "Loved", last_year + 600), ("Recent", "Song", recently)];
+        plays.extend((0..MIN_PLAY_COUNT as u64).map(|i| ("Old", "Often", last_year + 1000 + i * 300)));
+        let store = listening_history::seeded_store(&plays);
+
+        let (forgotten, days, on_that_day) = listening_history::with_test_store(store, || {
+            let forgotten = find_forgotten(
+                &listening_history::get_plays_since(0),
+                &[("old".to_string(), "loved".to_string())],
+                noon(day(2024, 1, 1)),
+                10,
+            );
+            let days = same_day_in_earlier_years(&listening_history::get_days(), day(2024, 6, 1));
+            let on_that_day = distinct_plays(listening_history::get_plays_on(day(2023, 6, 1)), 10);
+            (forgotten, days, on_that_day)
+        });
+
+        let titles: Vec<(&str, &str)> = forgotten.iter().map(|s| (s.artist.as_str(), s.title.as_str())).collect();
+        assert_eq!(titles, vec![("Old", "Loved"), ("Old", "Often")]);
+        assert_eq!(forgotten[0].play_count, 2);
+        assert_eq!(days, vec![day(2023, 6, 1)]);
+        let titles: Vec<&str> = on_that_day.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles.len(), 2);
+        assert!(titles.contains(&"Loved") && titles.contains(&"Often"));
+    }
+}
@@ -0,0 +1,330 @@
+use crate::audiocontrol::eventbus::EventBus;
+use crate::config::get_service_config;
+use crate::data::PlayerEvent;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+const THERMAL_ZONE: &str = "/sys/class/thermal/thermal_zone0/temp";
+const THROTTLED_SYSFS: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+/// Degrees the temperature has to drop below the threshold before the alert ends
+const TEMPERATURE_HYSTERESIS: f64 = 3.0;
+
+/// Configuration of the `system_monitor` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemMonitorConfig {
+    /// Check the thresholds periodically and send events
+    #[serde(default = "default_true")]
+    pub enable: bool,
+
+    /// Seconds between two checks
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+
+    /// CPU temperature in °C
+    #[serde(default = "default_temperature_critical")]
+    pub temperature_critical: f64,
+
+    /// 1 minute load average divided by the number of CPUs
+    #[serde(default = "default_load_per_cpu_critical")]
+    pub load_per_cpu_critical: f64,
+
+    /// Available memory in MB, less is critical
+    #[serde(default = "default_memory_available_critical_mb")]
+    pub memory_available_critical_mb: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_temperature_critical() -> f64 {
+    80.0
+}
+
+fn default_load_per_cpu_critical() -> f64 {
+    2.0
+}
+
+fn default_memory_available_critical_mb() -> u64 {
+    50
+}
+
+impl Default for SystemMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            poll_interval_secs: default_poll_interval_secs(),
+            temperature_critical: default_temperature_critical(),
+            load_per_cpu_critical: default_load_per_cpu_critical(),
+            memory_available_critical_mb: default_memory_available_critical_mb(),
+        }
+    }
+}
+
+/// Throttling state reported by the Raspberry Pi firmware
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ThrottleStatus {
+    /// Raw value of `vcgencmd get_throttled`
+    pub raw: u32,
+    pub under_voltage: bool,
+    pub frequency_capped: bool,
+    pub throttled: bool,
+    pub soft_temperature_limit: bool,
+    pub under_voltage_occurred: bool,
+    pub frequency_capping_occurred: bool,
+    pub throttling_occurred: bool,
+    pub soft_temperature_limit_occurred: bool,
+}
+
+impl ThrottleStatus {
+    pub fn from_raw(raw: u32) -> Self {
+        let bit = |n: u32| raw & (1 << n) != 0;
+        Self {
+            raw,
+            under_voltage: bit(0),
+            frequency_capped: bit(1),
+            throttled: bit(2),
+            soft_temperature_limit: bit(3),
+            under_voltage_occurred: bit(16),
+            frequency_capping_occurred: bit(17),
+            throttling_occurred: bit(18),
+            soft_temperature_limit_occurred: bit(19),
+        }
+    }
+
+    /// Parse "throttled=0x50005" or "50005"
+    pub fn parse(s: &str) -> Option<Self> {
+        let value = s.trim();
+        let value = value.strip_prefix("throttled=").unwrap_or(value);
+        let value = value.strip_prefix("0x").unwrap_or(value);
+        u32::from_str_radix(value, 16).ok().map(Self::from_raw)
+    }
+
+    /// Any of the conditions is active right now
+    pub fn is_active(&self) -> bool {
+        self.raw & 0xf != 0
+    }
+}
+
+/// Memory usage from /proc/meminfo, in kB
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MemoryInfo {
+    pub total_kb: u64,
+    pub free_kb: u64,
+    /// Memory available for new processes without swapping, includes caches
+    pub available_kb: u64,
+}
+
+impl MemoryInfo {
+    pub fn parse(meminfo: &str) -> Self {
+        let mut info = Self::default();
+        for line in meminfo.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(key), Some(value)) = (parts.next(), parts.next().and_then(|v| v.parse().ok())) else {
+                continue;
+            };
+            match key {
+                "MemTotal:" => info.total_kb = value,
+                "MemFree:" => info.free_kb = value,
+                "MemAvailable:" => info.available_kb = value,
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// Current temperature, throttling, load and memory
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemStatus {
+    /// CPU temperature in °C, None if there is no thermal sensor
+    pub temperature: Option<f64>,
+    /// None if not running on a Raspberry Pi
+    pub throttling: Option<ThrottleStatus>,
+    /// 1, 5 and 15 minute load average
+    pub load_average: Option<[f64; 3]>,
+    pub cpus: usize,
+    pub memory: Option<MemoryInfo>,
+    /// Thresholds that are exceeded, e.g. "temperature"
+    pub alerts: Vec<String>,
+    pub thresholds: SystemMonitorConfig,
+}
+
+fn read_temperature() -> Option<f64> {
+    let millidegrees: f64 = std::fs::read_to_string(THERMAL_ZONE).ok()?.trim().parse().ok()?;
+    Some(millidegrees / 1000.0)
+}
+
+fn read_throttling() -> Option<ThrottleStatus> {
+    if let Some(status) = std::fs::read_to_string(THROTTLED_SYSFS).ok().and_then(|s| ThrottleStatus::parse(&s)) {
+        return Some(status);
+    }
+    let output = Command::new("vcgencmd").arg("get_throttled").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    ThrottleStatus::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn read_load_average() -> Option<[f64; 3]> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let mut values = loadavg.split_whitespace().map(|v| v.parse::<f64>().ok());
+    Some([values.next()??, values.next()??, values.next()??])
+}
+
+fn read_memory() -> Option<MemoryInfo> {
+    std::fs::read_to_string("/proc/meminfo").ok().map(|s| MemoryInfo::parse(&s))
+}
+
+fn cpu_count() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+static CONFIG: Lazy<RwLock<SystemMonitorConfig>> = Lazy::new(|| RwLock::new(SystemMonitorConfig::default()));
+
+/// Active alerts with the value that triggered them
+static ALERTS: Lazy<RwLock<BTreeMap<String, f64>>> = Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Read the current values
+pub fn get_status() -> SystemStatus {
+    SystemStatus {
+        temperature: read_temperature(),
+        throttling: read_throttling(),
+        load_average: read_load_average(),
+        cpus: cpu_count(),
+        memory: read_memory(),
+        alerts: ALERTS.read().keys().cloned().collect(),
+        thresholds: CONFIG.read().clone(),
+    }
+}
+
+/// A threshold check: resource name, value, threshold and whether it is exceeded
+type Check = (&'static str, f64, f64, bool);
+
+/// Compare the values with the thresholds, `active` tells which alerts were active before
+fn evaluate(status: &SystemStatus, config: &SystemMonitorConfig, active: &BTreeMap<String, f64>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(temperature) = status.temperature {
+        let threshold = config.temperature_critical;
+        let exceeded = if active.contains_key("temperature") {
+            temperature > threshold - TEMPERATURE_HYSTERESIS
+        } else {
+            temperature >= threshold
+        };
+        checks.push(("temperature", temperature, threshold, exceeded));
+    }
+    if let Some(throttling) = status.throttling {
+        checks.push(("throttling", throttling.raw as f64, 0.0, throttling.is_active()));
+    }
+    if let Some([load, _, _]) = status.load_average {
+        let per_cpu = load / status.cpus.max(1) as f64;
+        checks.push(("load", per_cpu, config.load_per_cpu_critical, per_cpu >= config.load_per_cpu_critical));
+    }
+    if let Some(memory) = status.memory.filter(|m| m.total_kb > 0) {
+        let available_mb = (memory.available_kb / 1024) as f64;
+        let threshold = config.memory_available_critical_mb as f64;
+        checks.push(("memory", available_mb, threshold, available_mb < threshold));
+    }
+    checks
+}
+
+/// Check the thresholds and send an event for every alert that started or ended
+fn check_thresholds() {
+    let status = get_status();
+    let config = CONFIG.read().clone();
+    let previous = ALERTS.read().clone();
+    for (resource, value, threshold, exceeded) in evaluate(&status, &config, &previous) {
+        if exceeded == previous.contains_key(resource) {
+            continue;
+        }
+        if exceeded {
+            warn!("System {} critical: {} (threshold {})", resource, value, threshold);
+            ALERTS.write().insert(resource.to_string(), value);
+        } else {
+            info!("System {} back to normal: {}", resource, value);
+            ALERTS.write().remove(resource);
+        }
+        EventBus::instance().publish(PlayerEvent::SystemResourceAlert {
+            resource: resource.to_string(),
+            critical: exceeded,
+            value,
+            threshold,
+        });
+    }
+}
+
+/// Start checking the thresholds from the `system_monitor` service configuration
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let monitor_config = match get_service_config(config, "system_monitor") {
+        Some(c) => match serde_json::from_value::<SystemMonitorConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid system_monitor configuration, using defaults: {}", e);
+                SystemMonitorConfig::default()
+            }
+        },
+        None => SystemMonitorConfig::default(),
+    };
+    *CONFIG.write() = monitor_config.clone();
+    if !monitor_config.enable {
+        debug!("System monitor is disabled");
+        return;
+    }
+
+    info!("Checking system resources every {}s", monitor_config.poll_interval_secs);
+    let interval = Duration::from_secs(monitor_config.poll_interval_secs.max(1));
+    thread::spawn(move || loop {
+        check_thresholds();
+        thread::sleep(interval);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_throttling_and_memory() {
+        let status = ThrottleStatus::parse("throttled=0x50005\n").unwrap();
+        assert!(status.under_voltage && status.throttled && !status.frequency_capped);
+        assert!(status.under_voltage_occurred && status.throttling_occurred);
+        assert!(status.is_active());
+        assert!(!ThrottleStatus::parse("0x50000").unwrap().is_active());
+        assert!(ThrottleStatus::parse("error").is_none());
+
+        let memory = MemoryInfo::parse("MemTotal:        3884240 kB\nMemFree:          212344 kB\nMemAvailable:    2403796 kB\n");
+        assert_eq!(memory, MemoryInfo { total_kb: 3884240, free_kb: 212344, available_kb: 2403796 });
+    }
+
+    #[test]
+    fn test_evaluate_thresholds() {
+        let config = SystemMonitorConfig::default();
+        let status = SystemStatus {
+            temperature: Some(78.5),
+            throttling: None,
+            load_average: Some([9.0, 4.0, 2.0]),
+            cpus: 4,
+            memory: Some(MemoryInfo { total_kb: 1024 * 1024, free_kb: 0, available_kb: 40 * 1024 }),
+            alerts: Vec::new(),
+            thresholds: config.clone(),
+        };
+        let exceeded = |active: &BTreeMap<String, f64>| -> Vec<&str> {
+            evaluate(&status, &config, active).into_iter().filter(|c| c.3).map(|c| c.0).collect()
+        };
+        assert_eq!(exceeded(&BTreeMap::new()), vec!["load", "memory"]);
+        // An active temperature alert only ends 3 °C below the threshold
+        let active = BTreeMap::from([("temperature".to_string(), 81.0)]);
+        assert_eq!(exceeded(&active), vec!["temperature", "load", "memory"]);
+    }
+}
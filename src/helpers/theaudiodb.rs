@@ -86,11 +86,7 @@ pub fn initialize_from_config(config: &serde_json::Value) {
             warn!("No API key found for TheAudioDB in configuration");
         }
           // Register rate limit - default to 2 requests per second (500ms)
-        let rate_limit_ms = audiodb_config.get("rate_limit_ms")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(500);
-            
-        ratelimit::register_service("theaudiodb", rate_limit_ms);
+        let rate_limit_ms = ratelimit::register_from_config("theaudiodb", audiodb_config, 500);
         info!("TheAudioDB rate limit set to {} ms", rate_limit_ms);
         
         let status = if enabled { "enabled" } else { "disabled" };
@@ -179,7 +175,9 @@ pub fn lookup_theaudiodb_by_mbid(mbid: &str) -> Result<serde_json::Value, String
     };    debug!("Looking up artist with MBID {}", mbid);
     
     // Apply rate limiting before making the request
-    ratelimit::rate_limit("theaudiodb");
+    if !ratelimit::acquire("theaudiodb") {
+        return Err("Daily TheAudioDB request quota reached".to_string());
+    }
     
     // Construct the API URL
     let url = format!(
@@ -314,7 +312,9 @@ pub fn lookup_theaudiodb_by_artist_name(artist_name: &str) -> Result<serde_json:
     debug!("Looking up artist by name '{}'", artist_name);
     
     // Apply rate limiting before making the request
-    ratelimit::rate_limit("theaudiodb");
+    if !ratelimit::acquire("theaudiodb") {
+        return Err("Daily TheAudioDB request quota reached".to_string());
+    }
     
     // Construct the API URL
     let url = format!(
@@ -439,7 +439,9 @@ pub fn lookup_theaudiodb_albums_by_artist(artist_name: &str) -> Result<serde_jso
     debug!("Looking up albums for artist '{}'", artist_name);
     
     // Apply rate limiting before making the request
-    ratelimit::rate_limit("theaudiodb");
+    if !ratelimit::acquire("theaudiodb") {
+        return Err("Daily TheAudioDB request quota reached".to_string());
+    }
     
     // Construct the API URL
     let url = format!(
@@ -565,7 +567,9 @@ pub fn lookup_theaudiodb_album_by_name(artist_name: &str, album_name: &str) -> R
     debug!("Looking up album '{}' by artist '{}'", album_name, artist_name);
     
     // Apply rate limiting before making the request
-    ratelimit::rate_limit("theaudiodb");
+    if !ratelimit::acquire("theaudiodb") {
+        return Err("Daily TheAudioDB request quota reached".to_string());
+    }
     
     // Construct the API URL
     let url = format!(
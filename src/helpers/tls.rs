@@ -0,0 +1,168 @@
+use crate::config::get_service_config;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// Errors when preparing the certificate
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Invalid tls configuration: {0}")]
+    Config(String),
+    #[error("Certificate or key {0} not found")]
+    Missing(String),
+    #[error("Failed to create a self-signed certificate: {0}")]
+    SelfSigned(String),
+}
+
+/// Configuration of HTTPS in the `tls` section of the `webserver` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Port of the HTTPS server, the HTTP server keeps running on the webserver port
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// PEM certificate chain
+    #[serde(default = "default_certificate")]
+    pub certificate: String,
+
+    /// PEM private key
+    #[serde(default = "default_key")]
+    pub key: String,
+
+    /// Create a self-signed certificate if there is none
+    #[serde(default = "default_true")]
+    pub self_signed: bool,
+
+    /// Additional host names and IP addresses of the self-signed certificate
+    #[serde(default)]
+    pub subject_alt_names: Vec<String>,
+}
+
+fn default_port() -> u16 {
+    1443
+}
+
+fn default_certificate() -> String {
+    "/var/lib/audiocontrol/tls/cert.pem".to_string()
+}
+
+fn default_key() -> String {
+    "/var/lib/audiocontrol/tls/key.pem".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            port: default_port(),
+            certificate: default_certificate(),
+            key: default_key(),
+            self_signed: true,
+            subject_alt_names: Vec::new(),
+        }
+    }
+}
+
+/// Read the TLS configuration and make sure the certificate exists
+///
+/// Returns None if HTTPS is disabled.
+pub fn prepare_from_config(config: &serde_json::Value) -> Result<Option<TlsConfig>, TlsError> {
+    let tls_config = match get_service_config(config, "webserver").and_then(|ws| ws.get("tls")) {
+        Some(c) => serde_json::from_value::<TlsConfig>(c.clone()).map_err(|e| TlsError::Config(e.to_string()))?,
+        None => return Ok(None),
+    };
+    if !tls_config.enable {
+        return Ok(None);
+    }
+    let exists = Path::new(&tls_config.certificate).exists() && Path::new(&tls_config.key).exists();
+    if !exists {
+        if !tls_config.self_signed {
+            return Err(TlsError::Missing(format!("{}/{}", tls_config.certificate, tls_config.key)));
+        }
+        create_self_signed(&tls_config)?;
+    }
+    Ok(Some(tls_config))
+}
+
+/// Create a self-signed certificate for the host name with openssl
+fn create_self_signed(config: &TlsConfig) -> Result<(), TlsError> {
+    for path in [&config.certificate, &config.key] {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent).map_err(|e| TlsError::SelfSigned(format!("{}: {}", parent.display(), e)))?;
+        }
+    }
+    let hostname = crate::helpers::device_identity::hostname();
+    let args = openssl_args(config, &hostname);
+    let output = Command::new("openssl")
+        .args(&args)
+        .output()
+        .map_err(|e| TlsError::SelfSigned(e.to_string()))?;
+    if !output.status.success() {
+        return Err(TlsError::SelfSigned(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    // Only the daemon needs the key
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = std::fs::set_permissions(&config.key, std::fs::Permissions::from_mode(0o600)) {
+            warn!("Failed to restrict access to {}: {}", config.key, e);
+        }
+    }
+    info!("Created a self-signed certificate for {} in {}", hostname, config.certificate);
+    Ok(())
+}
+
+fn openssl_args(config: &TlsConfig, hostname: &str) -> Vec<String> {
+    let hostname = if hostname.is_empty() { "localhost" } else { hostname };
+    let mut names = vec![format!("DNS:{}", hostname), format!("DNS:{}.local", hostname), "DNS:localhost".to_string(), "IP:127.0.0.1".to_string()];
+    for name in &config.subject_alt_names {
+        let kind = if name.parse::<std::net::IpAddr>().is_ok() { "IP" } else { "DNS" };
+        names.push(format!("{}:{}", kind, name));
+    }
+    names.dedup();
+    [
+        "req", "-x509", "-newkey", "ec", "-pkeyopt", "ec_paramgen_curve:prime256v1", "-nodes", "-days", "3650",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .chain([
+        "-subj".to_string(),
+        format!("/CN={}", hostname),
+        "-addext".to_string(),
+        format!("subjectAltName={}", names.join(",")),
+        "-keyout".to_string(),
+        config.key.clone(),
+        "-out".to_string(),
+        config.certificate.clone(),
+    ])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openssl_args() {
+        let config = TlsConfig {
+            subject_alt_names: vec!["192.168.1.20".to_string(), "music.example.com".to_string()],
+            ..TlsConfig::default()
+        };
+        let args = openssl_args(&config, "hifiberry");
+        assert!(args.contains(&"/CN=hifiberry".to_string()));
+        assert!(args.contains(
+            &"subjectAltName=DNS:hifiberry,DNS:hifiberry.local,DNS:localhost,IP:127.0.0.1,IP:192.168.1.20,DNS:music.example.com"
+                .to_string()
+        ));
+        assert_eq!(args.last(), Some(&"/var/lib/audiocontrol/tls/cert.pem".to_string()));
+    }
+}
@@ -0,0 +1,546 @@
+use crate::config::get_service_config;
+use crate::constants::API_PREFIX;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error;
+
+/// Errors of speech rendering
+#[derive(Debug, Error)]
+pub enum TtsError {
+    #[error("Text-to-speech is disabled")]
+    Disabled,
+
+    #[error("No text given")]
+    EmptyText,
+
+    #[error("Text is longer than {0} characters")]
+    TextTooLong(usize),
+
+    #[error("Invalid voice '{0}'")]
+    InvalidVoice(String),
+
+    #[error("Speech synthesis failed: {0}")]
+    Backend(String),
+
+    #[error("Speech cache error: {0}")]
+    Cache(String),
+}
+
+pub type Result<T> = std::result::Result<T, TtsError>;
+
+/// Engine rendering the speech
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TtsBackend {
+    /// Local Piper engine, voices are the model names in `models_dir`, e.g. "en_US-lessac-medium"
+    Piper {
+        #[serde(default = "default_piper_command")]
+        command: String,
+        #[serde(default = "default_models_dir")]
+        models_dir: String,
+    },
+    /// Cloud or self-hosted service with an OpenAI compatible `/v1/audio/speech` endpoint
+    Http {
+        url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        /// Audio format requested from the service, "mp3", "wav", "opus" or "flac"
+        #[serde(default = "default_http_format")]
+        format: String,
+    },
+}
+
+fn default_piper_command() -> String {
+    "piper".to_string()
+}
+
+fn default_models_dir() -> String {
+    "/usr/share/piper-voices".to_string()
+}
+
+fn default_http_format() -> String {
+    "mp3".to_string()
+}
+
+impl Default for TtsBackend {
+    fn default() -> Self {
+        TtsBackend::Piper {
+            command: default_piper_command(),
+            models_dir: default_models_dir(),
+        }
+    }
+}
+
+impl TtsBackend {
+    fn name(&self) -> &'static str {
+        match self {
+            TtsBackend::Piper { .. } => "piper",
+            TtsBackend::Http { .. } => "http",
+        }
+    }
+
+    /// File extension of the rendered clips
+    fn extension(&self) -> String {
+        match self {
+            TtsBackend::Piper { .. } => "wav".to_string(),
+            TtsBackend::Http { format, .. } => format.to_ascii_lowercase(),
+        }
+    }
+}
+
+fn default_voice() -> String {
+    "en_US-lessac-medium".to_string()
+}
+
+fn default_cache_dir() -> String {
+    "/var/lib/audiocontrol/cache/tts".to_string()
+}
+
+fn default_base_url() -> String {
+    "http://127.0.0.1:1080".to_string()
+}
+
+fn default_max_text_length() -> usize {
+    500
+}
+
+fn default_max_cache_mb() -> u64 {
+    100
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// Configuration of the `tts` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtsConfig {
+    #[serde(default)]
+    pub enable: bool,
+
+    #[serde(default)]
+    pub backend: TtsBackend,
+
+    /// Voice used if a request doesn't name one
+    #[serde(default = "default_voice")]
+    pub default_voice: String,
+
+    /// Directory of the rendered clips
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: String,
+
+    /// Base URL of the AudioControl API as seen by the players
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+
+    #[serde(default = "default_max_text_length")]
+    pub max_text_length: usize,
+
+    /// The least recently used clips are removed above this size
+    #[serde(default = "default_max_cache_mb")]
+    pub max_cache_mb: u64,
+
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            backend: TtsBackend::default(),
+            default_voice: default_voice(),
+            cache_dir: default_cache_dir(),
+            base_url: default_base_url(),
+            max_text_length: default_max_text_length(),
+            max_cache_mb: default_max_cache_mb(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// A rendered speech clip
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtsClip {
+    /// File name in the cache, the hash of backend, voice and text with the format as extension
+    pub file: String,
+    pub voice: String,
+    pub mime_type: String,
+    /// URL players can stream the clip from
+    pub url: String,
+    /// True if the clip was rendered before
+    pub cached: bool,
+}
+
+/// Size of the clip cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TtsCacheStats {
+    pub clips: usize,
+    pub size: u64,
+}
+
+static CONFIG: Lazy<RwLock<TtsConfig>> = Lazy::new(|| RwLock::new(TtsConfig::default()));
+
+/// Serializes rendering, so the same text isn't rendered twice in parallel
+static RENDER_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Initialize text-to-speech from the `tts` service configuration
+pub fn initialize_from_config(config: &serde_json::Value) {
+    let tts_config = match get_service_config(config, "tts") {
+        Some(c) => serde_json::from_value::<TtsConfig>(c.clone()).unwrap_or_else(|e| {
+            warn!("Invalid tts configuration, using defaults: {}", e);
+            TtsConfig::default()
+        }),
+        None => TtsConfig::default(),
+    };
+    if tts_config.enable {
+        if let Err(e) = fs::create_dir_all(&tts_config.cache_dir) {
+            warn!("Failed to create speech cache directory {}: {}", tts_config.cache_dir, e);
+        }
+        info!(
+            "Text-to-speech enabled with {} backend, default voice {}",
+            tts_config.backend.name(),
+            tts_config.default_voice
+        );
+    } else {
+        debug!("Text-to-speech is disabled");
+    }
+    *CONFIG.write() = tts_config;
+}
+
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+pub fn default_voice_name() -> String {
+    CONFIG.read().default_voice.clone()
+}
+
+pub fn backend_name() -> &'static str {
+    CONFIG.read().backend.name()
+}
+
+/// Voices and clips are plain file names, no paths
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Cache key of a text spoken by a voice
+fn clip_hash(backend: &TtsBackend, voice: &str, text: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(backend.name().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(voice.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn mime_type(file: &str) -> &'static str {
+    match Path::new(file).extension().and_then(|e| e.to_str()) {
+        Some("wav") => "audio/wav",
+        Some("mp3") => "audio/mpeg",
+        Some("opus") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Render a text, or return the clip rendered before for the same text and voice
+pub fn render(text: &str, voice: Option<&str>) -> Result<TtsClip> {
+    let config = CONFIG.read().clone();
+    if !config.enable {
+        return Err(TtsError::Disabled);
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(TtsError::EmptyText);
+    }
+    if text.chars().count() > config.max_text_length {
+        return Err(TtsError::TextTooLong(config.max_text_length));
+    }
+    let voice = voice.map(str::trim).filter(|v| !v.is_empty()).unwrap_or(&config.default_voice);
+    if !valid_name(voice) {
+        return Err(TtsError::InvalidVoice(voice.to_string()));
+    }
+
+    let file = format!("{}.{}", clip_hash(&config.backend, voice, text), config.backend.extension());
+    let path = Path::new(&config.cache_dir).join(&file);
+    let clip = |cached| TtsClip {
+        url: format!("{}{}/tts/clip/{}", config.base_url.trim_end_matches('/'), API_PREFIX, file),
+        mime_type: mime_type(&file).to_string(),
+        file: file.clone(),
+        voice: voice.to_string(),
+        cached,
+    };
+
+    let _guard = RENDER_LOCK.lock();
+    if path.exists() {
+        // The modification time orders the clips for removal
+        if let Ok(f) = fs::File::options().append(true).open(&path) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        return Ok(clip(true));
+    }
+
+    fs::create_dir_all(&config.cache_dir).map_err(|e| TtsError::Cache(e.to_string()))?;
+    let partial = path.with_extension("part");
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let result = match &config.backend {
+        TtsBackend::Piper { command, models_dir } => render_piper(command, models_dir, voice, text, timeout, &partial),
+        TtsBackend::Http { url, api_key, model, format } => {
+            render_http(url, api_key.as_deref(), model.as_deref(), format, voice, text, timeout, &partial)
+        }
+    };
+    if let Err(e) = result.and_then(|_| fs::rename(&partial, &path).map_err(|e| TtsError::Cache(e.to_string()))) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    info!("Rendered speech with voice {}: {:?}", voice, text);
+
+    prune_cache(Path::new(&config.cache_dir), config.max_cache_mb * 1024 * 1024);
+    Ok(clip(false))
+}
+
+/// Render a text and return the URL players can stream it from
+pub fn render_url(text: &str, voice: Option<&str>) -> Result<String> {
+    render(text, voice).map(|clip| clip.url)
+}
+
+/// Run Piper with the text on stdin
+///
+/// Piper is killed if it doesn't finish within the timeout, so a hanging
+/// process doesn't hold the render lock.
+fn render_piper(
+    command: &str,
+    models_dir: &str,
+    voice: &str,
+    text: &str,
+    timeout: Duration,
+    output: &Path,
+) -> Result<()> {
+    let model = Path::new(models_dir).join(format!("{}.onnx", voice));
+    if !model.exists() {
+        return Err(TtsError::InvalidVoice(voice.to_string()));
+    }
+    let mut child = Command::new(command)
+        .arg("--model")
+        .arg(&model)
+        .arg("--output_file")
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| TtsError::Backend(format!("{}: {}", command, e)))?;
+    // Read stderr on its own thread, so a chatty process can't block on a full pipe
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf);
+            buf
+        })
+    });
+    let status = wait_with_timeout(&mut child, text, timeout)
+        .map_err(|e| TtsError::Backend(format!("{}: {}", command, e)));
+    let stderr = stderr.and_then(|handle| handle.join().ok()).unwrap_or_default();
+    let status = status?;
+    if !status.success() {
+        return Err(TtsError::Backend(format!(
+            "{} exited with {}: {}",
+            command,
+            status,
+            String::from_utf8_lossy(&stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Write the text to the child's stdin and wait for it, killing it after the timeout
+fn wait_with_timeout(child: &mut Child, text: &str, timeout: Duration) -> std::io::Result<ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()),
+        None => Ok(()),
+    };
+    if let Err(e) = written {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("no result after {}s", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Request the speech from an OpenAI compatible service
+#[allow(clippy::too_many_arguments)]
+fn render_http(
+    url: &str,
+    api_key: Option<&str>,
+    model: Option<&str>,
+    format: &str,
+    voice: &str,
+    text: &str,
+    timeout: Duration,
+    output: &Path,
+) -> Result<()> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| TtsError::Backend(e.to_string()))?;
+    let mut body = serde_json::json!({
+        "input": text,
+        "voice": voice,
+        "response_format": format,
+    });
+    if let Some(model) = model {
+        body["model"] = serde_json::json!(model);
+    }
+    let mut request = client.post(url).json(&body);
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+    let response = request.send().map_err(|e| TtsError::Backend(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().unwrap_or_default();
+        return Err(TtsError::Backend(format!("{} returned {}: {}", url, status, message.trim())));
+    }
+    let data = response.bytes().map_err(|e| TtsError::Backend(e.to_string()))?;
+    if data.is_empty() {
+        return Err(TtsError::Backend(format!("{} returned no audio", url)));
+    }
+    fs::write(output, &data).map_err(|e| TtsError::Cache(e.to_string()))
+}
+
+/// Clips in the cache with their size and modification time
+fn cached_clips(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext != "part"))
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            Some((e.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        })
+        .collect()
+}
+
+/// Remove the least recently used clips until the cache is below `max_size` bytes
+fn prune_cache(dir: &Path, max_size: u64) -> usize {
+    let mut clips = cached_clips(dir);
+    let mut size: u64 = clips.iter().map(|(_, len, _)| len).sum();
+    clips.sort_by_key(|(_, _, modified)| *modified);
+    let mut removed = 0;
+    for (path, len, _) in clips {
+        if size <= max_size {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            size -= len;
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        debug!("Removed {} speech clips from the cache", removed);
+    }
+    removed
+}
+
+/// Path of a cached clip, None if it doesn't exist
+pub fn clip_path(file: &str) -> Option<PathBuf> {
+    // Clip names are hashes, anything else can't be in the cache
+    if !valid_name(file) || file.ends_with(".part") {
+        return None;
+    }
+    let path = Path::new(&CONFIG.read().cache_dir).join(file);
+    path.is_file().then_some(path)
+}
+
+pub fn cache_stats() -> TtsCacheStats {
+    let clips = cached_clips(Path::new(&CONFIG.read().cache_dir));
+    TtsCacheStats {
+        clips: clips.len(),
+        size: clips.iter().map(|(_, len, _)| len).sum(),
+    }
+}
+
+/// Remove all rendered clips, returns the number of removed clips
+pub fn clear_cache() -> usize {
+    let _guard = RENDER_LOCK.lock();
+    prune_cache(Path::new(&CONFIG.read().cache_dir), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_hash_and_voices() {
+        let backend = TtsBackend::default();
+        let hash = clip_hash(&backend, "en_US-lessac-medium", "Dinner is ready");
+        assert_eq!(hash.len(), 40);
+        assert_ne!(hash, clip_hash(&backend, "de_DE-thorsten-medium", "Dinner is ready"));
+        assert_eq!(backend.extension(), "wav");
+
+        assert!(valid_name("en_US-lessac-medium"));
+        assert!(!valid_name("../../etc/passwd"));
+        assert!(!valid_name("voices/en"));
+        assert!(!valid_name(""));
+    }
+
+    #[test]
+    fn test_prune_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["old.wav", "middle.wav", "new.wav"].iter().enumerate() {
+            let path = dir.path().join(name);
+            fs::write(&path, [0u8; 100]).unwrap();
+            let f = fs::File::options().append(true).open(&path).unwrap();
+            f.set_modified(now - Duration::from_secs(100 - i as u64 * 10)).unwrap();
+        }
+        assert_eq!(prune_cache(dir.path(), 250), 1);
+        assert!(!dir.path().join("old.wav").exists());
+        assert!(dir.path().join("new.wav").exists());
+        assert_eq!(prune_cache(dir.path(), 0), 2);
+    }
+
+    #[test]
+    fn test_wait_with_timeout_kills_hanging_process() {
+        let mut child = Command::new("sleep").arg("10").stdin(Stdio::piped()).spawn().unwrap();
+        let started = Instant::now();
+        let err = wait_with_timeout(&mut child, "", Duration::from_millis(200)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(child.try_wait().unwrap().is_some());
+
+        let mut child = Command::new("cat").stdin(Stdio::piped()).stdout(Stdio::null()).spawn().unwrap();
+        assert!(wait_with_timeout(&mut child, "hello", Duration::from_secs(5)).unwrap().success());
+    }
+}
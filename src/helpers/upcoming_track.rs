@@ -0,0 +1,213 @@
+use crate::audiocontrol::eventbus::EventBus;
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::{LoopMode, PlaybackState, PlayerCapability, PlayerEvent, PlayerSource, Song, Track};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Weak;
+use std::thread;
+use std::time::Duration;
+
+/// Configuration of the `upcoming_track` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpcomingTrackConfig {
+    /// Send upcoming_track events
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Seconds before the end of the current track the event is sent
+    #[serde(default = "default_seconds_before")]
+    pub seconds_before: f64,
+
+    /// Seconds between two checks of the active player
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: f64,
+}
+
+fn default_seconds_before() -> f64 {
+    10.0
+}
+
+fn default_poll_interval() -> f64 {
+    0.5
+}
+
+impl Default for UpcomingTrackConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            seconds_before: default_seconds_before(),
+            poll_interval: default_poll_interval(),
+        }
+    }
+}
+
+/// Decides when the end of a track is announced
+///
+/// Every track is announced once. Seeking back before the announcement point announces it again.
+#[derive(Debug)]
+pub struct Announcer {
+    seconds_before: f64,
+    announced: Option<String>,
+}
+
+impl Announcer {
+    pub fn new(seconds_before: f64) -> Self {
+        Self {
+            seconds_before,
+            announced: None,
+        }
+    }
+
+    /// Check the position of a track, returns the remaining seconds if it should be announced now
+    pub fn check(&mut self, track_key: &str, position: f64, duration: f64) -> Option<f64> {
+        let remaining = duration - position;
+        if remaining > self.seconds_before {
+            if self.announced.as_deref() == Some(track_key) {
+                self.announced = None;
+            }
+            return None;
+        }
+        if remaining <= 0.0 || self.announced.as_deref() == Some(track_key) {
+            return None;
+        }
+        self.announced = Some(track_key.to_string());
+        Some(remaining)
+    }
+}
+
+/// Find the queue index of the current song
+///
+/// The song is found by URI, or by title and artist.
+pub fn current_index(queue: &[Track], song: &Song) -> Option<usize> {
+    queue.iter().position(|track| {
+        match (&track.uri, &song.stream_url) {
+            (Some(uri), Some(url)) => uri == url,
+            _ => {
+                song.title.as_deref() == Some(track.name.as_str())
+                    && (track.artist.is_none() || track.artist == song.artist)
+            }
+        }
+    })
+}
+
+/// Find the queue entry that follows the current song
+///
+/// In shuffle mode the next entry is not known.
+pub fn next_track(queue: &[Track], song: &Song, loop_mode: LoopMode, shuffle: bool) -> Option<Track> {
+    if shuffle {
+        return None;
+    }
+    let index = current_index(queue, song)?;
+    match loop_mode {
+        LoopMode::Track => queue.get(index).cloned(),
+        LoopMode::Playlist => queue.get(index + 1).or_else(|| queue.first()).cloned(),
+        LoopMode::None => queue.get(index + 1).cloned(),
+    }
+}
+
+/// Start announcing the end of tracks on the active player
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let upcoming_config = match get_service_config(config, "upcoming_track") {
+        Some(c) => match serde_json::from_value::<UpcomingTrackConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid upcoming_track configuration, using defaults: {}", e);
+                UpcomingTrackConfig::default()
+            }
+        },
+        None => UpcomingTrackConfig::default(),
+    };
+    if !upcoming_config.enable {
+        debug!("Upcoming track announcements are disabled");
+        return;
+    }
+
+    info!("Announcing the next track {}s before the current one ends", upcoming_config.seconds_before);
+    let interval = Duration::from_secs_f64(upcoming_config.poll_interval.max(0.1));
+    let mut announcer = Announcer::new(upcoming_config.seconds_before);
+    thread::spawn(move || {
+        while let Some(controller) = controller.upgrade() {
+            check_active_player(&controller, &mut announcer);
+            drop(controller);
+            thread::sleep(interval);
+        }
+    });
+}
+
+fn check_active_player(controller: &AudioController, announcer: &mut Announcer) {
+    let Some(active) = controller.get_active_controller() else {
+        return;
+    };
+    let player = active.read();
+    if player.get_playback_state() != PlaybackState::Playing {
+        return;
+    }
+    // Streams without a length can't be announced
+    let capabilities = player.get_capabilities();
+    if !capabilities.has_capability(PlayerCapability::Seek) {
+        return;
+    }
+    let Some(song) = player.get_song() else {
+        return;
+    };
+    let (Some(duration), Some(position)) = (song.duration.filter(|d| *d > 0.0), player.get_position()) else {
+        return;
+    };
+
+    let track_key = format!(
+        "{}|{}|{}|{}",
+        player.get_player_id(),
+        song.stream_url.as_deref().unwrap_or_default(),
+        song.artist.as_deref().unwrap_or_default(),
+        song.title.as_deref().unwrap_or_default()
+    );
+    let Some(remaining) = announcer.check(&track_key, position, duration) else {
+        return;
+    };
+
+    let next = if capabilities.has_capability(PlayerCapability::RemoteQueue) {
+        player.get_upcoming_tracks().into_iter().next()
+    } else {
+        next_track(&player.get_queue(), &song, player.get_loop_mode(), player.get_shuffle())
+    };
+    debug!("Announcing upcoming track {:?}, {:.1}s remaining", next.as_ref().map(|t| &t.name), remaining);
+    EventBus::instance().publish(PlayerEvent::UpcomingTrack {
+        source: PlayerSource::new(player.get_player_name(), player.get_player_id()),
+        remaining,
+        next,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announce_once() {
+        let mut announcer = Announcer::new(10.0);
+        assert_eq!(announcer.check("a", 100.0, 200.0), None);
+        assert_eq!(announcer.check("a", 191.0, 200.0), Some(9.0));
+        assert_eq!(announcer.check("a", 192.0, 200.0), None);
+        // Seeking back announces the track again
+        assert_eq!(announcer.check("a", 50.0, 200.0), None);
+        assert_eq!(announcer.check("a", 195.0, 200.0), Some(5.0));
+        // The next track is announced independently
+        assert_eq!(announcer.check("b", 175.0, 180.0), Some(5.0));
+    }
+
+    #[test]
+    fn test_next_track() {
+        let mut queue: Vec<Track> = ["A", "B", "C"].iter().map(|name| Track::with_name(name.to_string())).collect();
+        queue[2].uri = Some("music/c.flac".to_string());
+        let song = |title: &str| Song { title: Some(title.to_string()), ..Default::default() };
+
+        assert_eq!(next_track(&queue, &song("A"), LoopMode::None, false).unwrap().name, "B");
+        assert_eq!(next_track(&queue, &song("A"), LoopMode::Track, false).unwrap().name, "A");
+        assert!(next_track(&queue, &song("A"), LoopMode::None, true).is_none());
+
+        let last = Song { stream_url: Some("music/c.flac".to_string()), ..song("Other title") };
+        assert!(next_track(&queue, &last, LoopMode::None, false).is_none());
+        assert_eq!(next_track(&queue, &last, LoopMode::Playlist, false).unwrap().name, "A");
+    }
+}
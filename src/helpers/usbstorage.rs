@@ -0,0 +1,525 @@
+use crate::audiocontrol::eventbus::EventBus;
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::data::PlayerEvent;
+use crate::players::mpd::library::MPDLibrary;
+use crate::players::mpd::MPDPlayerController;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Weak;
+use std::thread;
+use std::time::Duration;
+
+/// Configuration of USB storage detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbStorageConfig {
+    /// Watch for USB storage devices
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Mount devices that haven't been mounted by the system
+    #[serde(default = "default_true")]
+    pub auto_mount: bool,
+
+    /// Directory in which devices are mounted
+    #[serde(default = "default_mount_base")]
+    pub mount_base: String,
+
+    /// Directory inside the MPD music directory in which devices are linked
+    #[serde(default = "default_library_dir")]
+    pub library_dir: String,
+
+    /// Seconds between two checks for new devices
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+
+    /// Mount devices read-only
+    #[serde(default = "default_true")]
+    pub read_only: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_mount_base() -> String {
+    "/media".to_string()
+}
+
+fn default_library_dir() -> String {
+    "USB".to_string()
+}
+
+fn default_poll_interval_secs() -> u64 {
+    2
+}
+
+impl Default for UsbStorageConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            auto_mount: true,
+            mount_base: default_mount_base(),
+            library_dir: default_library_dir(),
+            poll_interval_secs: default_poll_interval_secs(),
+            read_only: true,
+        }
+    }
+}
+
+/// A connected USB storage device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbStorageDevice {
+    /// Device node, e.g. /dev/sda1
+    pub device: String,
+    /// File system label, the device name if the file system has no label
+    pub label: String,
+    /// Where the device is mounted, None if it isn't mounted
+    pub mount_point: Option<String>,
+    /// Path relative to the MPD music directory, None if the device isn't part of the library
+    pub library_path: Option<String>,
+    /// Whether the device has been mounted by audiocontrol and will be unmounted on removal
+    pub mounted_by_us: bool,
+}
+
+static CONFIG: Lazy<RwLock<UsbStorageConfig>> = Lazy::new(|| RwLock::new(UsbStorageConfig::default()));
+static DEVICES: Lazy<RwLock<HashMap<String, UsbStorageDevice>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static CONTROLLER: Lazy<RwLock<Option<Weak<AudioController>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Initialize USB storage detection from the `usbstorage` service configuration
+///
+/// If enabled, a background thread checks for added and removed USB storage devices.
+/// Devices are added to the library of the first MPD player.
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    *CONTROLLER.write() = Some(controller);
+
+    let usb_config = match get_service_config(config, "usbstorage") {
+        Some(c) => match serde_json::from_value::<UsbStorageConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid usbstorage configuration, USB storage detection disabled: {}", e);
+                return;
+            }
+        },
+        None => UsbStorageConfig::default(),
+    };
+    *CONFIG.write() = usb_config.clone();
+
+    if !usb_config.enable {
+        debug!("USB storage detection is disabled");
+        return;
+    }
+
+    info!(
+        "Watching for USB storage devices{}",
+        if usb_config.auto_mount { ", mounting them automatically" } else { "" }
+    );
+
+    let interval = Duration::from_secs(usb_config.poll_interval_secs.max(1));
+    thread::spawn(move || loop {
+        check_devices();
+        thread::sleep(interval);
+    });
+}
+
+/// Check whether USB storage detection is enabled
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+/// Get all connected USB storage devices, sorted by device name
+pub fn list_devices() -> Vec<UsbStorageDevice> {
+    let mut devices: Vec<UsbStorageDevice> = DEVICES.read().values().cloned().collect();
+    devices.sort_by(|a, b| a.device.cmp(&b.device));
+    devices
+}
+
+/// Compare connected devices with the known ones and add/remove them
+fn check_devices() {
+    let current = scan_devices(Path::new("/sys"));
+    let auto_mount = CONFIG.read().auto_mount;
+
+    let known: Vec<String> = DEVICES.read().keys().cloned().collect();
+    for name in known.iter().filter(|name| !current.contains(*name)) {
+        if let Some(device) = DEVICES.write().remove(name) {
+            remove_device(device);
+        }
+    }
+
+    for name in &current {
+        let retry = match DEVICES.read().get(name) {
+            None => true,
+            // Without auto mounting, wait until the device is mounted by the system
+            Some(device) => device.mount_point.is_none() && !auto_mount,
+        };
+        if retry {
+            let device = add_device(name);
+            DEVICES.write().insert(name.clone(), device);
+        }
+    }
+}
+
+/// Find USB storage block devices in sysfs
+///
+/// Partitions are returned instead of the disk if the disk is partitioned.
+/// Disks without medium (e.g. empty card readers) are ignored.
+///
+/// # Arguments
+/// * `sys_root` - Root of the sysfs file system, usually /sys
+///
+/// # Returns
+/// Device names, e.g. "sda1"
+fn scan_devices(sys_root: &Path) -> BTreeSet<String> {
+    let mut devices = BTreeSet::new();
+    let Ok(entries) = std::fs::read_dir(sys_root.join("block")) else {
+        return devices;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("sd") {
+            continue;
+        }
+        let disk_path = entry.path();
+        if !is_usb_disk(&disk_path) || read_sysfs(&disk_path.join("size")).as_deref() == Some("0") {
+            continue;
+        }
+
+        let partitions: Vec<String> = std::fs::read_dir(&disk_path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.path().join("partition").exists())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .filter(|p| p.starts_with(&name))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if partitions.is_empty() {
+            devices.insert(name);
+        } else {
+            devices.extend(partitions);
+        }
+    }
+    devices
+}
+
+/// A disk is considered USB storage if it's removable or connected via USB
+fn is_usb_disk(disk_path: &Path) -> bool {
+    if read_sysfs(&disk_path.join("removable")).as_deref() == Some("1") {
+        return true;
+    }
+    std::fs::canonicalize(disk_path)
+        .map(|p| p.to_string_lossy().contains("/usb"))
+        .unwrap_or(false)
+}
+
+fn read_sysfs(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Read file system labels from /dev/disk/by-label
+///
+/// # Returns
+/// Map of device name (e.g. "sda1") to label
+fn read_labels(by_label_dir: &Path) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(by_label_dir) else {
+        return labels;
+    };
+    for entry in entries.flatten() {
+        if let Ok(target) = std::fs::read_link(entry.path()) {
+            if let Some(device) = target.file_name() {
+                labels.insert(
+                    device.to_string_lossy().to_string(),
+                    unescape_label(&entry.file_name().to_string_lossy()),
+                );
+            }
+        }
+    }
+    labels
+}
+
+/// udev encodes special characters in label links as \xNN
+///
+/// Characters outside ASCII can be encoded byte by byte, so the bytes are decoded as UTF-8 together.
+fn unescape_label(label: &str) -> String {
+    let input = label.as_bytes();
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let code = input
+            .get(i..i + 4)
+            .filter(|escape| escape.starts_with(b"\\x"))
+            .and_then(|escape| std::str::from_utf8(&escape[2..]).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match code {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 4;
+            }
+            None => {
+                bytes.push(input[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Parse /proc/mounts into a map of device path to mount point
+fn parse_mounts(mounts: &str) -> HashMap<String, String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let target = fields.next()?;
+            // Spaces in mount points are encoded as \040
+            Some((device.to_string(), target.replace("\\040", " ")))
+        })
+        .collect()
+}
+
+/// Name of the directory for a device, used for the mount point and the library link
+fn directory_name(label: &str, device_name: &str) -> String {
+    let name: String = label
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') { c } else { '_' })
+        .collect();
+    let name = name.trim().trim_start_matches('.').to_string();
+    if name.is_empty() {
+        device_name.to_string()
+    } else {
+        name
+    }
+}
+
+/// Find the first MPD player, its music directory and its library
+fn find_mpd() -> Option<(String, Option<MPDLibrary>)> {
+    let controller = CONTROLLER.read().as_ref().and_then(|c| c.upgrade())?;
+    for ctrl_lock in controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        if let Some(mpd) = ctrl.as_any().downcast_ref::<MPDPlayerController>() {
+            if let Some(music_dir) = mpd.get_effective_music_directory() {
+                return Some((music_dir, mpd.get_library()));
+            }
+        }
+    }
+    None
+}
+
+/// Mount a new device if necessary and add it to the MPD library
+fn add_device(name: &str) -> UsbStorageDevice {
+    let config = CONFIG.read().clone();
+    let device_path = format!("/dev/{}", name);
+    let label = read_labels(Path::new("/dev/disk/by-label"))
+        .remove(name)
+        .unwrap_or_else(|| name.to_string());
+
+    let mut device = UsbStorageDevice {
+        device: device_path.clone(),
+        label: label.clone(),
+        mount_point: None,
+        library_path: None,
+        mounted_by_us: false,
+    };
+
+    let mounts = parse_mounts(&std::fs::read_to_string("/proc/mounts").unwrap_or_default());
+    let dir_name = unique_directory_name(&directory_name(&label, name), name);
+
+    if let Some(mount_point) = mounts.get(&device_path) {
+        device.mount_point = Some(mount_point.clone());
+    } else if config.auto_mount {
+        let mount_point = Path::new(&config.mount_base).join(&dir_name);
+        match mount_device(&device_path, &mount_point, config.read_only) {
+            Ok(()) => {
+                info!("Mounted USB storage {} at {}", device_path, mount_point.display());
+                device.mount_point = Some(mount_point.to_string_lossy().to_string());
+                device.mounted_by_us = true;
+            }
+            Err(e) => {
+                warn!("Failed to mount USB storage {}: {}", device_path, e);
+                return device;
+            }
+        }
+    } else {
+        debug!("USB storage {} detected, waiting until it is mounted", device_path);
+        return device;
+    }
+
+    let Some((music_dir, library)) = find_mpd() else {
+        warn!("No MPD player with a known music directory, USB storage {} not added to the library", device_path);
+        return device;
+    };
+
+    let library_path = format!("{}/{}", config.library_dir.trim_matches('/'), dir_name);
+    let link = Path::new(&music_dir).join(&library_path);
+    let mount_point = device.mount_point.clone().unwrap_or_default();
+    if let Err(e) = create_link(&link, Path::new(&mount_point)) {
+        warn!("Failed to link USB storage {} into the music directory: {}", device_path, e);
+        return device;
+    }
+
+    info!("USB storage '{}' available in the library at {}", label, library_path);
+    device.library_path = Some(library_path.clone());
+    if let Some(library) = library {
+        library.update_database(Some(&library_path));
+    }
+
+    EventBus::instance().publish(PlayerEvent::UsbStorageChanged {
+        device: device_path,
+        label,
+        library_path: Some(library_path),
+        available: true,
+    });
+    device
+}
+
+/// Remove a disconnected device from the library and unmount it
+fn remove_device(device: UsbStorageDevice) {
+    info!("USB storage '{}' ({}) removed", device.label, device.device);
+
+    if let Some(library_path) = &device.library_path {
+        if let Some((music_dir, library)) = find_mpd() {
+            let link = Path::new(&music_dir).join(library_path);
+            if let Err(e) = std::fs::remove_file(&link) {
+                warn!("Failed to remove link {}: {}", link.display(), e);
+            }
+            if let Some(library) = library {
+                // Update the parent directory, MPD removes the missing directory from its database
+                let parent = Path::new(library_path)
+                    .parent()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                library.update_database(Some(&parent));
+            }
+        }
+    }
+
+    if device.mounted_by_us {
+        if let Some(mount_point) = &device.mount_point {
+            // The device is gone, a lazy unmount detaches the stale mount without blocking
+            match Command::new("umount").arg("-l").arg(mount_point).status() {
+                Ok(status) if status.success() => {
+                    let _ = std::fs::remove_dir(mount_point);
+                }
+                Ok(status) => warn!("umount {} failed with {}", mount_point, status),
+                Err(e) => warn!("Failed to run umount for {}: {}", mount_point, e),
+            }
+        }
+    }
+
+    if device.library_path.is_some() {
+        EventBus::instance().publish(PlayerEvent::UsbStorageChanged {
+            device: device.device,
+            label: device.label,
+            library_path: device.library_path,
+            available: false,
+        });
+    }
+}
+
+/// Add the device name if another device already uses the directory name
+fn unique_directory_name(dir_name: &str, device_name: &str) -> String {
+    let used = DEVICES.read().values().any(|d| {
+        d.library_path
+            .as_deref()
+            .and_then(|p| Path::new(p).file_name())
+            .is_some_and(|n| n.to_string_lossy() == dir_name)
+    });
+    if used {
+        format!("{}-{}", dir_name, device_name)
+    } else {
+        dir_name.to_string()
+    }
+}
+
+fn mount_device(device: &str, mount_point: &Path, read_only: bool) -> Result<(), String> {
+    std::fs::create_dir_all(mount_point).map_err(|e| e.to_string())?;
+
+    let mut command = Command::new("mount");
+    if read_only {
+        command.args(["-o", "ro"]);
+    }
+    let output = command.arg(device).arg(mount_point).output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let _ = std::fs::remove_dir(mount_point);
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Create a symlink to the mount point, replacing a stale link from an earlier run
+fn create_link(link: &Path, target: &Path) -> std::io::Result<()> {
+    if let Some(parent) = link.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if link.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) {
+        std::fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_label() {
+        assert_eq!(unescape_label("MY\\x20MUSIC"), "MY MUSIC");
+        assert_eq!(unescape_label("plain"), "plain");
+        assert_eq!(unescape_label("bad\\xZZ"), "bad\\xZZ");
+        assert_eq!(unescape_label("M\\xc3\\xbcsik"), "Müsik");
+        assert_eq!(unescape_label("Caf\\xc3\\xa9\\x20\\xff"), "Café \u{fffd}");
+    }
+
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = "/dev/mmcblk0p2 / ext4 rw 0 0\n/dev/sda1 /media/MY\\040MUSIC vfat ro 0 0\n";
+        let parsed = parse_mounts(mounts);
+        assert_eq!(parsed.get("/dev/sda1").map(String::as_str), Some("/media/MY MUSIC"));
+        assert_eq!(parsed.get("/dev/mmcblk0p2").map(String::as_str), Some("/"));
+    }
+
+    #[test]
+    fn test_directory_name() {
+        assert_eq!(directory_name("MY MUSIC", "sda1"), "MY MUSIC");
+        assert_eq!(directory_name("a/b", "sda1"), "a_b");
+        assert_eq!(directory_name("..", "sda1"), "sda1");
+    }
+
+    #[test]
+    fn test_scan_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        let block = dir.path().join("block");
+
+        // Partitioned USB stick
+        std::fs::create_dir_all(block.join("sda/sda1")).unwrap();
+        std::fs::write(block.join("sda/removable"), "1\n").unwrap();
+        std::fs::write(block.join("sda/size"), "1000\n").unwrap();
+        std::fs::write(block.join("sda/sda1/partition"), "1\n").unwrap();
+        // Unpartitioned stick
+        std::fs::create_dir_all(block.join("sdb")).unwrap();
+        std::fs::write(block.join("sdb/removable"), "1\n").unwrap();
+        std::fs::write(block.join("sdb/size"), "1000\n").unwrap();
+        // Card reader without card
+        std::fs::create_dir_all(block.join("sdc")).unwrap();
+        std::fs::write(block.join("sdc/removable"), "1\n").unwrap();
+        std::fs::write(block.join("sdc/size"), "0\n").unwrap();
+        // Internal disk
+        std::fs::create_dir_all(block.join("sdd")).unwrap();
+        std::fs::write(block.join("sdd/removable"), "0\n").unwrap();
+        std::fs::create_dir_all(block.join("mmcblk0")).unwrap();
+
+        let devices: Vec<String> = scan_devices(dir.path()).into_iter().collect();
+        assert_eq!(devices, vec!["sda1", "sdb"]);
+    }
+}
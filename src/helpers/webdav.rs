@@ -0,0 +1,628 @@
+use crate::config::get_service_config;
+use crate::helpers::security_store::SecurityStore;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Settings database key for the configured sources
+const SOURCES_KEY: &str = "webdav.sources";
+
+/// Timeout of a single PROPFIND request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Properties requested for every file and directory
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:resourcetype/>
+    <d:getetag/>
+    <d:getcontentlength/>
+    <d:getlastmodified/>
+    <d:getcontenttype/>
+  </d:prop>
+</d:propfind>"#;
+
+/// Errors that can occur when accessing WebDAV sources
+#[derive(Debug, Error)]
+pub enum WebDavError {
+    #[error("Invalid source configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Source '{0}' not found")]
+    NotFound(String),
+
+    #[error("Source '{0}' already exists")]
+    AlreadyExists(String),
+
+    #[error("File '{0}' is not in the index")]
+    FileNotIndexed(String),
+
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    #[error("Failed to store source: {0}")]
+    Storage(String),
+}
+
+pub type Result<T> = std::result::Result<T, WebDavError>;
+
+/// How the URLs of indexed files are passed to MPD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlMode {
+    /// Stream via /api/webdav/stream, credentials stay in AudioControl
+    Proxy,
+    /// Let MPD access the server directly, credentials are part of the URL
+    Direct,
+}
+
+/// Configuration of the `webdav` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebDavConfig {
+    #[serde(default = "default_url_mode")]
+    pub url_mode: UrlMode,
+
+    /// Base URL of the AudioControl API as seen by MPD
+    #[serde(default = "default_proxy_base_url")]
+    pub proxy_base_url: String,
+
+    /// Minutes between index refreshes of all sources, 0 disables refreshing
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval_minutes: u64,
+
+    /// File extensions that are indexed
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+}
+
+fn default_url_mode() -> UrlMode {
+    UrlMode::Proxy
+}
+
+fn default_proxy_base_url() -> String {
+    "http://127.0.0.1:1080".to_string()
+}
+
+fn default_refresh_interval() -> u64 {
+    360
+}
+
+fn default_extensions() -> Vec<String> {
+    ["flac", "mp3", "ogg", "opus", "m4a", "aac", "wav", "aif", "aiff", "wv", "ape", "dsf", "dff"]
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+impl Default for WebDavConfig {
+    fn default() -> Self {
+        Self {
+            url_mode: default_url_mode(),
+            proxy_base_url: default_proxy_base_url(),
+            refresh_interval_minutes: default_refresh_interval(),
+            extensions: default_extensions(),
+        }
+    }
+}
+
+static CONFIG: Lazy<RwLock<WebDavConfig>> = Lazy::new(|| RwLock::new(WebDavConfig::default()));
+
+/// Only one refresh runs at a time
+static REFRESH_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// A WebDAV folder containing music, e.g. a Nextcloud folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebDavSource {
+    /// Unique name, used in stream URLs
+    pub name: String,
+
+    /// URL of the music folder, e.g. https://cloud.example.com/remote.php/dav/files/alice/Music
+    pub url: String,
+
+    pub username: Option<String>,
+}
+
+/// An audio file of a source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// Path relative to the source URL, e.g. "Artist/Album/01 Song.flac"
+    pub path: String,
+    pub size: Option<u64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+/// Index of a source, directories are stored with their ETag to detect changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebDavIndex {
+    pub directories: BTreeMap<String, String>,
+    pub files: BTreeMap<String, IndexedFile>,
+    /// Time of the last refresh
+    pub updated: Option<String>,
+}
+
+/// Result of an index refresh
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RefreshResult {
+    pub source: String,
+    /// Directories requested from the server
+    pub directories_scanned: usize,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    /// Number of indexed files after the refresh
+    pub files: usize,
+}
+
+/// An entry of a PROPFIND response
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DavEntry {
+    /// Decoded path of the href
+    path: String,
+    collection: bool,
+    etag: Option<String>,
+    size: Option<u64>,
+    last_modified: Option<String>,
+    content_type: Option<String>,
+}
+
+/// Initialize the WebDAV sources from the configuration and start the periodic index refresh
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(webdav_config) = get_service_config(config, "webdav") {
+        match serde_json::from_value::<WebDavConfig>(webdav_config.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid webdav configuration, using defaults: {}", e),
+        }
+    }
+    debug!("WebDAV configuration: {:?}", CONFIG.read());
+
+    let interval = CONFIG.read().refresh_interval_minutes;
+    if interval == 0 {
+        return;
+    }
+    thread::spawn(move || loop {
+        for source in list_sources() {
+            if let Err(e) = refresh_index(&source.name, false) {
+                warn!("Failed to refresh WebDAV source '{}': {}", source.name, e);
+            }
+        }
+        thread::sleep(Duration::from_secs(interval * 60));
+    });
+}
+
+/// Get all configured sources
+pub fn list_sources() -> Vec<WebDavSource> {
+    match crate::helpers::settingsdb::get::<Vec<WebDavSource>>(SOURCES_KEY) {
+        Ok(Some(sources)) => sources,
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            warn!("Failed to read WebDAV sources from settings database: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn save_sources(sources: &[WebDavSource]) -> Result<()> {
+    crate::helpers::settingsdb::set(SOURCES_KEY, &sources.to_vec()).map_err(WebDavError::Storage)
+}
+
+/// Get a configured source by name
+pub fn get_source(name: &str) -> Option<WebDavSource> {
+    list_sources().into_iter().find(|source| source.name == name)
+}
+
+fn password_key(name: &str) -> String {
+    format!("webdav_password_{}", name)
+}
+
+fn index_key(name: &str) -> String {
+    format!("webdav::index::{}", name)
+}
+
+fn validate_source(source: &WebDavSource) -> Result<()> {
+    let invalid = |msg: &str| Err(WebDavError::InvalidConfig(msg.to_string()));
+
+    if source.name.is_empty()
+        || !source.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return invalid("name may only contain lower case letters, digits, '-' and '_'");
+    }
+    match url::Url::parse(&source.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        _ => return invalid("url must be an http or https URL"),
+    }
+    Ok(())
+}
+
+/// Add a source, the password is stored in the security store
+pub fn add_source(source: WebDavSource, password: Option<&str>) -> Result<()> {
+    validate_source(&source)?;
+    let mut sources = list_sources();
+    if sources.iter().any(|s| s.name == source.name) {
+        return Err(WebDavError::AlreadyExists(source.name));
+    }
+
+    if let Some(password) = password {
+        SecurityStore::set(&password_key(&source.name), password).map_err(|e| WebDavError::Storage(e.to_string()))?;
+    }
+    info!("Added WebDAV source '{}' at {}", source.name, source.url);
+    sources.push(source);
+    save_sources(&sources)
+}
+
+/// Remove a source, its password and its index
+pub fn remove_source(name: &str) -> Result<()> {
+    let mut sources = list_sources();
+    let count = sources.len();
+    sources.retain(|s| s.name != name);
+    if sources.len() == count {
+        return Err(WebDavError::NotFound(name.to_string()));
+    }
+    save_sources(&sources)?;
+
+    if let Err(e) = SecurityStore::remove(&password_key(name)) {
+        warn!("Failed to remove password of WebDAV source '{}': {}", name, e);
+    }
+    if let Err(e) = crate::helpers::attributecache::remove(&index_key(name)) {
+        warn!("Failed to remove index of WebDAV source '{}': {}", name, e);
+    }
+    info!("Removed WebDAV source '{}'", name);
+    Ok(())
+}
+
+/// User name and password of a source, None if it doesn't need authentication
+pub fn credentials(source: &WebDavSource) -> Option<(String, String)> {
+    let username = source.username.clone()?;
+    let password = SecurityStore::get(&password_key(&source.name)).unwrap_or_default();
+    Some((username, password))
+}
+
+/// Get the stored index of a source
+pub fn get_index(name: &str) -> WebDavIndex {
+    match crate::helpers::attributecache::get::<WebDavIndex>(&index_key(name)) {
+        Ok(Some(index)) => index,
+        Ok(None) => WebDavIndex::default(),
+        Err(e) => {
+            warn!("Failed to read index of WebDAV source '{}': {}", name, e);
+            WebDavIndex::default()
+        }
+    }
+}
+
+/// Percent-encode the segments of a relative path
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// URL of a file or directory of a source
+pub fn file_url(source: &WebDavSource, path: &str) -> String {
+    let encoded = encode_path(path);
+    if encoded.is_empty() {
+        format!("{}/", source.url.trim_end_matches('/'))
+    } else {
+        format!("{}/{}", source.url.trim_end_matches('/'), encoded)
+    }
+}
+
+/// URL MPD uses to play an indexed file
+pub fn stream_url(source: &WebDavSource, path: &str) -> String {
+    let config = CONFIG.read();
+    match config.url_mode {
+        UrlMode::Proxy => format!(
+            "{}/api/webdav/stream/{}/{}",
+            config.proxy_base_url.trim_end_matches('/'),
+            source.name,
+            encode_path(path)
+        ),
+        UrlMode::Direct => {
+            let url = file_url(source, path);
+            let Some((username, password)) = credentials(source) else {
+                return url;
+            };
+            match url::Url::parse(&url) {
+                Ok(mut parsed) => {
+                    let _ = parsed.set_username(&username);
+                    let _ = parsed.set_password(Some(&password));
+                    parsed.to_string()
+                }
+                Err(_) => url,
+            }
+        }
+    }
+}
+
+/// Resolve the URL and credentials of an indexed file for the streaming proxy
+///
+/// Only indexed files can be streamed, so the proxy doesn't expose other files of the server.
+pub fn resolve_file(name: &str, path: &str) -> Result<(String, Option<(String, String)>)> {
+    let source = get_source(name).ok_or_else(|| WebDavError::NotFound(name.to_string()))?;
+    let path = path.trim_matches('/');
+    if !get_index(name).files.contains_key(path) {
+        return Err(WebDavError::FileNotIndexed(path.to_string()));
+    }
+    Ok((file_url(&source, path), credentials(&source)))
+}
+
+/// Get the inner text of all elements with the given local name, ignoring namespace prefixes
+fn elements<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = xml[pos..].find('<').map(|i| pos + i) {
+        let Some(end) = xml[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        pos = end + 1;
+        let tag = &xml[start + 1..end];
+        if tag.starts_with(['/', '?', '!']) {
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let qname = tag.trim_end_matches('/').split_whitespace().next().unwrap_or("");
+        if qname.rsplit(':').next() != Some(local_name) {
+            continue;
+        }
+        if self_closing {
+            result.push("");
+            continue;
+        }
+        let closing = format!("</{}>", qname);
+        if let Some(close) = xml[pos..].find(&closing).map(|i| pos + i) {
+            result.push(&xml[pos..close]);
+            pos = close + closing.len();
+        }
+    }
+    result
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.trim()
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Parse a PROPFIND multistatus response, properties with a status other than 200 are empty
+fn parse_multistatus(xml: &str) -> Vec<DavEntry> {
+    elements(xml, "response")
+        .into_iter()
+        .filter_map(|response| {
+            let href = unescape_xml(elements(response, "href").first()?);
+            // hrefs are either absolute paths or full URLs
+            let href_path = match url::Url::parse(&href) {
+                Ok(url) => url.path().to_string(),
+                Err(_) => href,
+            };
+            let text = |name: &str| {
+                elements(response, name)
+                    .first()
+                    .map(|t| unescape_xml(t))
+                    .filter(|t| !t.is_empty())
+            };
+            Some(DavEntry {
+                path: urlencoding::decode(&href_path).map(|p| p.into_owned()).unwrap_or(href_path),
+                collection: elements(response, "resourcetype")
+                    .first()
+                    .is_some_and(|t| !elements(t, "collection").is_empty()),
+                etag: text("getetag"),
+                size: text("getcontentlength").and_then(|s| s.parse().ok()),
+                last_modified: text("getlastmodified"),
+                content_type: text("getcontenttype"),
+            })
+        })
+        .collect()
+}
+
+/// Path of an entry relative to the source, None if it is outside the source
+fn relative_path(base_path: &str, entry_path: &str) -> Option<String> {
+    let base = base_path.trim_end_matches('/');
+    let rest = entry_path.strip_prefix(base)?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(rest.trim_matches('/').to_string())
+}
+
+/// List a directory of a source with PROPFIND, the first entry is the directory itself
+fn propfind(client: &reqwest::blocking::Client, source: &WebDavSource, dir: &str) -> Result<Vec<(String, DavEntry)>> {
+    let method = reqwest::Method::from_bytes(b"PROPFIND").map_err(|e| WebDavError::Http(e.to_string()))?;
+    let mut request = client
+        .request(method, file_url(source, dir))
+        .header("Depth", "1")
+        .header("Content-Type", "application/xml")
+        .body(PROPFIND_BODY);
+    if let Some((username, password)) = credentials(source) {
+        request = request.basic_auth(username, Some(password));
+    }
+    let response = request.send().map_err(|e| WebDavError::Http(e.to_string()))?;
+    if response.status().as_u16() != 207 {
+        return Err(WebDavError::Http(format!("PROPFIND {} returned {}", dir, response.status())));
+    }
+    let body = response.text().map_err(|e| WebDavError::Http(e.to_string()))?;
+
+    let base_path = url::Url::parse(&source.url).map(|u| u.path().to_string()).unwrap_or_default();
+    let base_path = urlencoding::decode(&base_path).map(|p| p.into_owned()).unwrap_or(base_path);
+    Ok(parse_multistatus(&body)
+        .into_iter()
+        .filter_map(|entry| relative_path(&base_path, &entry.path).map(|path| (path, entry)))
+        .collect())
+}
+
+fn has_extension(path: &str, extensions: &[String]) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Copy a directory and everything below it from the old index
+fn copy_subtree(old: &WebDavIndex, new: &mut WebDavIndex, dir: &str) {
+    let prefix = format!("{}/", dir);
+    for (path, etag) in old.directories.range(prefix.clone()..).take_while(|(p, _)| p.starts_with(&prefix)) {
+        new.directories.insert(path.clone(), etag.clone());
+    }
+    for (path, file) in old.files.range(prefix.clone()..).take_while(|(p, _)| p.starts_with(&prefix)) {
+        new.files.insert(path.clone(), file.clone());
+    }
+}
+
+/// Refresh the index of a source
+///
+/// Only directories whose ETag changed since the last refresh are requested from
+/// the server, unless `full` is set.
+pub fn refresh_index(name: &str, full: bool) -> Result<RefreshResult> {
+    let source = get_source(name).ok_or_else(|| WebDavError::NotFound(name.to_string()))?;
+    let _lock = REFRESH_LOCK.lock();
+    let extensions = CONFIG.read().extensions.clone();
+    let old = if full { WebDavIndex::default() } else { get_index(name) };
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| WebDavError::Http(e.to_string()))?;
+
+    let job_id = format!("webdav-refresh-{}", name);
+    let _ = crate::helpers::backgroundjobs::register_job(job_id.clone(), format!("Refresh WebDAV source {}", name));
+
+    let mut new = WebDavIndex::default();
+    let mut queue = vec![String::new()];
+    let mut scanned = 0;
+    while let Some(dir) = queue.pop() {
+        let entries = match propfind(&client, &source, &dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let _ = crate::helpers::backgroundjobs::complete_job(&job_id);
+                return Err(e);
+            }
+        };
+        scanned += 1;
+        let _ = crate::helpers::backgroundjobs::update_job(
+            &job_id,
+            Some(format!("Scanned {} directories", scanned)),
+            Some(scanned),
+            None,
+        );
+
+        for (path, entry) in entries {
+            if entry.collection {
+                let etag = entry.etag.unwrap_or_default();
+                if path == dir {
+                    new.directories.insert(path, etag);
+                } else if !etag.is_empty() && old.directories.get(&path) == Some(&etag) {
+                    copy_subtree(&old, &mut new, &path);
+                    new.directories.insert(path, etag);
+                } else {
+                    queue.push(path);
+                }
+            } else if has_extension(&path, &extensions) {
+                new.files.insert(
+                    path.clone(),
+                    IndexedFile {
+                        path,
+                        size: entry.size,
+                        etag: entry.etag,
+                        last_modified: entry.last_modified,
+                        content_type: entry.content_type,
+                    },
+                );
+            }
+        }
+    }
+    let _ = crate::helpers::backgroundjobs::complete_job(&job_id);
+
+    let result = RefreshResult {
+        source: name.to_string(),
+        directories_scanned: scanned,
+        added: new.files.keys().filter(|p| !old.files.contains_key(*p)).count(),
+        changed: new
+            .files
+            .values()
+            .filter(|f| old.files.get(&f.path).is_some_and(|o| o.etag != f.etag || o.size != f.size))
+            .count(),
+        removed: old.files.keys().filter(|p| !new.files.contains_key(*p)).count(),
+        files: new.files.len(),
+    };
+    new.updated = Some(chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false));
+    crate::helpers::attributecache::set(&index_key(name), &new).map_err(WebDavError::Storage)?;
+    info!(
+        "Refreshed WebDAV source '{}': {} directories scanned, {} files ({} added, {} changed, {} removed)",
+        name, result.directories_scanned, result.files, result.added, result.changed, result.removed
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/Music/</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype><d:collection/></d:resourcetype>
+      <d:getetag>&quot;64f1a&quot;</d:getetag>
+    </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/Music/Miles%20Davis/</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype><d:collection/></d:resourcetype>
+      <d:getetag>&quot;64f1b&quot;</d:getetag>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/Music/So%20What%20%26%20more.flac</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype/>
+      <d:getetag>&quot;9a2c&quot;</d:getetag>
+      <d:getcontentlength>31457280</d:getcontentlength>
+      <d:getcontenttype>audio/flac</d:getcontenttype>
+    </d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let entries = parse_multistatus(RESPONSE);
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].collection);
+        assert_eq!(entries[0].etag.as_deref(), Some("\"64f1a\""));
+        assert_eq!(entries[1].path, "/remote.php/dav/files/alice/Music/Miles Davis/");
+        assert!(!entries[2].collection);
+        assert_eq!(entries[2].size, Some(31457280));
+        assert_eq!(entries[2].content_type.as_deref(), Some("audio/flac"));
+
+        let base = "/remote.php/dav/files/alice/Music";
+        assert_eq!(relative_path(base, &entries[0].path).as_deref(), Some(""));
+        assert_eq!(relative_path(base, &entries[2].path).as_deref(), Some("So What & more.flac"));
+        assert_eq!(relative_path(base, "/remote.php/dav/files/alice/Musicals/x.flac"), None);
+    }
+
+    #[test]
+    fn test_urls() {
+        let source = WebDavSource {
+            name: "cloud".to_string(),
+            url: "https://cloud.example.com/remote.php/dav/files/alice/Music/".to_string(),
+            username: None,
+        };
+        assert_eq!(
+            file_url(&source, "Miles Davis/So What.flac"),
+            "https://cloud.example.com/remote.php/dav/files/alice/Music/Miles%20Davis/So%20What.flac"
+        );
+        assert_eq!(
+            stream_url(&source, "Miles Davis/So What.flac"),
+            "http://127.0.0.1:1080/api/webdav/stream/cloud/Miles%20Davis/So%20What.flac"
+        );
+        assert!(has_extension("a/b.FLAC", &default_extensions()));
+        assert!(!has_extension("a/cover.jpg", &default_extensions()));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::get_service_config;
+use crate::data::artist::Artist;
+use crate::helpers::attributecache;
+use crate::helpers::http_client;
+use crate::helpers::locale;
+use crate::helpers::ratelimit;
+use crate::helpers::ArtistUpdater;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Wikimedia asks API clients to identify themselves
+const USER_AGENT: &str = "HifiBerry-ACR/1.0 (https://www.hifiberry.com/)";
+
+/// Configuration of the `wikipedia` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikipediaConfig {
+    #[serde(default = "default_enable")]
+    pub enable: bool,
+    /// Wikipedia language, e.g. "de", defaults to the language of the `locale` service
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+fn default_enable() -> bool {
+    true
+}
+
+impl Default for WikipediaConfig {
+    fn default() -> Self {
+        Self {
+            enable: default_enable(),
+            language: None,
+        }
+    }
+}
+
+static CONFIG: Lazy<RwLock<WikipediaConfig>> = Lazy::new(|| RwLock::new(WikipediaConfig::default()));
+
+/// Initialize the Wikipedia biography lookup from the `wikipedia` service configuration
+///
+/// Requests to Wikidata and Wikipedia are rate-limited to one per second by default.
+pub fn initialize_from_config(config: &serde_json::Value) {
+    if let Some(c) = get_service_config(config, "wikipedia") {
+        match serde_json::from_value::<WikipediaConfig>(c.clone()) {
+            Ok(parsed) => *CONFIG.write() = parsed,
+            Err(e) => warn!("Invalid wikipedia configuration, using defaults: {}", e),
+        }
+        ratelimit::register_from_config("wikipedia", c, 1000);
+    } else {
+        ratelimit::register_service("wikipedia", 1000);
+    }
+    info!("Wikipedia biography lookup {}", if is_enabled() { "enabled" } else { "disabled" });
+}
+
+/// Check if Wikipedia lookups are enabled
+pub fn is_enabled() -> bool {
+    CONFIG.read().enable
+}
+
+/// Wikipedia languages to use, the configured language first, English as fallback
+fn languages() -> Vec<String> {
+    let configured = CONFIG.read().language.clone();
+    let language = configured.unwrap_or_else(|| locale::language().code().to_string());
+    if language == "en" {
+        vec![language]
+    } else {
+        vec![language, "en".to_string()]
+    }
+}
+
+fn get_json(url: &str) -> Result<Value, String> {
+    if !ratelimit::acquire("wikipedia") {
+        return Err("Wikipedia request quota reached".to_string());
+    }
+    http_client::new_http_client(10)
+        .get_json_with_headers(url, &[("User-Agent", USER_AGENT)])
+        .map_err(|e| format!("Request to {} failed: {}", url, e))
+}
+
+/// Wikidata entity ID, e.g. "Q1299", from a search for the MusicBrainz artist ID (property P434)
+fn parse_entity_id(response: &Value) -> Option<String> {
+    response["query"]["search"]
+        .as_array()?
+        .first()?
+        .get("title")?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// Title of the Wikipedia article of an entity in the first of `languages` that has one
+fn parse_sitelink(response: &Value, entity_id: &str, languages: &[String]) -> Option<(String, String)> {
+    let sitelinks = &response["entities"][entity_id]["sitelinks"];
+    languages.iter().find_map(|language| {
+        sitelinks[format!("{}wiki", language)]["title"]
+            .as_str()
+            .map(|title| (language.clone(), title.to_string()))
+    })
+}
+
+/// Plain text introduction of an article
+fn parse_extract(response: &Value) -> Option<String> {
+    response["query"]["pages"]
+        .as_object()?
+        .values()
+        .find_map(|page| page["extract"].as_str())
+        .map(|extract| extract.trim().to_string())
+        .filter(|extract| !extract.is_empty())
+}
+
+/// Look up the biography of an artist by MusicBrainz ID
+///
+/// The artist's Wikidata entity links to the Wikipedia articles, the introduction of the
+/// article in the configured language, or English, is the biography.
+/// Returns the biography with the language of the article.
+pub fn lookup_biography(mbid: &str) -> Result<(String, String), String> {
+    if !is_enabled() {
+        return Err("Wikipedia lookups are disabled".to_string());
+    }
+    let languages = languages();
+    let cache_key = format!("wikipedia::biography::{}::{}", mbid, languages[0]);
+    let not_found_cache_key = format!("wikipedia::not_found::{}::{}", mbid, languages[0]);
+    if let Ok(Some(cached)) = attributecache::get::<(String, String)>(&cache_key) {
+        debug!("Found cached Wikipedia biography for MBID {}", mbid);
+        return Ok(cached);
+    }
+    if let Ok(Some(true)) = attributecache::get::<bool>(&not_found_cache_key) {
+        return Err(format!("No Wikipedia biography for MBID {} (from cache)", mbid));
+    }
+
+    let not_found = |reason: String| {
+        if let Err(e) = attributecache::set(&not_found_cache_key, &true) {
+            debug!("Failed to cache negative result for MBID {}: {}", mbid, e);
+        }
+        Err(reason)
+    };
+
+    let search = get_json(&format!(
+        "https://www.wikidata.org/w/api.php?action=query&list=search&srsearch=haswbstatement:P434={}&format=json",
+        urlencoding::encode(mbid)
+    ))?;
+    let Some(entity_id) = parse_entity_id(&search) else {
+        return not_found(format!("No Wikidata entity with MusicBrainz ID {}", mbid));
+    };
+
+    let sitefilter = languages.iter().map(|l| format!("{}wiki", l)).collect::<Vec<_>>().join("|");
+    let entity = get_json(&format!(
+        "https://www.wikidata.org/w/api.php?action=wbgetentities&ids={}&props=sitelinks&sitefilter={}&format=json",
+        entity_id, sitefilter
+    ))?;
+    let Some((language, title)) = parse_sitelink(&entity, &entity_id, &languages) else {
+        return not_found(format!("No Wikipedia article for {} in {}", entity_id, languages.join(", ")));
+    };
+
+    let extract = get_json(&format!(
+        "https://{}.wikipedia.org/w/api.php?action=query&prop=extracts&exintro=1&explaintext=1&redirects=1&format=json&titles={}",
+        language,
+        urlencoding::encode(&title)
+    ))?;
+    let Some(biography) = parse_extract(&extract) else {
+        return not_found(format!("Wikipedia article '{}' has no introduction", title));
+    };
+
+    let result = (biography, language);
+    if let Err(e) = attributecache::set(&cache_key, &result) {
+        debug!("Failed to cache Wikipedia biography for MBID {}: {}", mbid, e);
+    }
+    Ok(result)
+}
+
+/// Adds biographies from Wikipedia to artists with a MusicBrainz ID
+#[derive(Default)]
+pub struct WikipediaUpdater;
+
+impl ArtistUpdater for WikipediaUpdater {
+    fn update_artist(&self, mut artist: Artist) -> Artist {
+        let Some(mbid) = artist.metadata.as_ref().and_then(|meta| meta.mbid.first()).cloned() else {
+            return artist;
+        };
+        match lookup_biography(&mbid) {
+            Ok((biography, language)) => {
+                if let Some(meta) = &mut artist.metadata {
+                    meta.biography = Some(biography);
+                    meta.biography_source = Some("Wikipedia".to_string());
+                    debug!("Added {} Wikipedia biography for artist {}", language, artist.name);
+                }
+            }
+            Err(e) => debug!("No Wikipedia biography for {} with MBID {}: {}", artist.name, mbid, e),
+        }
+        artist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_responses() {
+        let search = json!({ "query": { "search": [{ "ns": 0, "title": "Q1299" }] } });
+        assert_eq!(parse_entity_id(&search).as_deref(), Some("Q1299"));
+        assert_eq!(parse_entity_id(&json!({ "query": { "search": [] } })), None);
+
+        let entity = json!({ "entities": { "Q1299": { "sitelinks": {
+            "enwiki": { "site": "enwiki", "title": "The Beatles" }
+        } } } });
+        let languages = vec!["de".to_string(), "en".to_string()];
+        assert_eq!(
+            parse_sitelink(&entity, "Q1299", &languages),
+            Some(("en".to_string(), "The Beatles".to_string()))
+        );
+        assert_eq!(parse_sitelink(&entity, "Q1299", &languages[..1]), None);
+
+        let extract = json!({ "query": { "pages": { "29812": {
+            "title": "The Beatles", "extract": "The Beatles were an English rock band.\n"
+        } } } });
+        assert_eq!(parse_extract(&extract).as_deref(), Some("The Beatles were an English rock band."));
+        assert_eq!(parse_extract(&json!({ "query": { "pages": { "-1": { "missing": "" } } } })), None);
+    }
+}
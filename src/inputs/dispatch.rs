@@ -38,7 +38,11 @@ impl GlobalActionTarget {
 
 impl ActionTarget for GlobalActionTarget {
     fn volume_adjust(&self, delta: f64) -> bool {
-        global_volume::adjust_volume_percentage(delta)
+        // Go through the AudioController so the playback limits cap the volume
+        match self.controller.upgrade() {
+            Some(controller) => controller.adjust_volume(delta),
+            None => global_volume::adjust_volume_percentage(delta),
+        }
     }
 
     fn volume_toggle_mute(&self) -> bool {
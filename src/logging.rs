@@ -70,7 +70,7 @@ impl LoggingSubsystem {
             LoggingSubsystem::Api => "audiocontrol::api",
             LoggingSubsystem::Players => "audiocontrol::players,audiocontrol::players::mpd::libraryloader,audiocontrol::players::lms::libraryloader",
             LoggingSubsystem::Cache => "audiocontrol::helpers::attributecache,audiocontrol::helpers::imagecache",
-            LoggingSubsystem::Metadata => "audiocontrol::helpers::musicbrainz,audiocontrol::helpers::theaudiodb,audiocontrol::helpers::lastfm",
+            LoggingSubsystem::Metadata => "audiocontrol::helpers::musicbrainz,audiocontrol::helpers::theaudiodb,audiocontrol::helpers::wikipedia,audiocontrol::helpers::lastfm",
             LoggingSubsystem::Spotify => "audiocontrol::helpers::spotify",
             LoggingSubsystem::WebSocket => "audiocontrol::api::websocket,rocket_ws",
             LoggingSubsystem::Library => "audiocontrol::data::library",
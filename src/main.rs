@@ -1,7 +1,9 @@
 use audiocontrol::api::server;
-use audiocontrol::config::{get_service_config, merge_player_includes};
+use audiocontrol::config::{get_service_config, merge_player_includes, merge_service_includes};
 use audiocontrol::helpers::imagecache::ImageCache;
 use audiocontrol::helpers::lastfm;
+use audiocontrol::helpers::library_export;
+use audiocontrol::helpers::cache_backup;
 use audiocontrol::helpers::musicbrainz;
 use audiocontrol::helpers::security_store::SecurityStore;
 use audiocontrol::helpers::settingsdb::SettingsDb;
@@ -27,6 +29,9 @@ use std::time::Duration;
 // Import global Tokio runtime functions from lib.rs
 use audiocontrol::{get_tokio_runtime, initialize_tokio_runtime};
 
+/// How long --export-library waits for the libraries to load
+const LIBRARY_EXPORT_TIMEOUT: Duration = Duration::from_secs(600);
+
 fn main() {
     // Initialize the Tokio runtime early
     initialize_tokio_runtime();
@@ -101,9 +106,10 @@ fn main() {
         std::process::exit(1);
     };
 
-    // Merge player configurations from players.d/ include directory
+    // Merge player and service configurations from the players.d/ and services.d/ include directories
     if let Some(config_dir) = config_path_obj.parent() {
         merge_player_includes(&mut controllers_config, config_dir);
+        merge_service_includes(&mut controllers_config, config_dir);
     }
 
     // Initialize the Security Store
@@ -143,7 +149,12 @@ fn main() {
             "Security store initialized successfully at {}",
             security_store_path.display()
         );
-    } // Get the attribute cache configuration from datastore
+    }
+
+    // Load the API tokens, they are kept in the security store
+    audiocontrol::helpers::api_tokens::initialize_from_config(&controllers_config);
+
+    // Get the attribute cache configuration from datastore
     let (_attribute_cache_path, _preload_prefixes, _cache_size) = if let Some(datastore_config) =
         get_service_config(&controllers_config, "datastore")
     {
@@ -267,6 +278,52 @@ fn main() {
 
     // Initialize the global settings database with the configured path from JSON
     initialize_settingsdb(&settingsdb_path);
+
+    // Back up or restore the attribute cache and settings database instead of starting
+    if let Some(path) = find_path_in_args(&args, "--backup-cache") {
+        let result = {
+            let mut cache = audiocontrol::helpers::attributecache::get_attribute_cache();
+            let mut settings = audiocontrol::helpers::settingsdb::get_settings_db();
+            cache_backup::backup(&mut cache, &mut settings)
+        };
+        match result.and_then(|backup| cache_backup::write_backup(&backup, &path).map(|_| backup)) {
+            Ok(backup) => println!(
+                "Saved {} cache entries and {} settings to {}",
+                backup.attribute_cache.len(),
+                backup.settings.len(),
+                path.display()
+            ),
+            Err(e) => {
+                eprintln!("Error: Failed to back up to {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(path) = find_path_in_args(&args, "--restore-cache") {
+        let result = cache_backup::read_backup(&path).and_then(|backup| {
+            let mut cache = audiocontrol::helpers::attributecache::get_attribute_cache();
+            let mut settings = audiocontrol::helpers::settingsdb::get_settings_db();
+            cache_backup::restore(&backup, &mut cache, &mut settings)
+        });
+        match result {
+            Ok(summary) => println!(
+                "Restored {} cache entries and {} settings from {}",
+                summary.cache_entries,
+                summary.settings,
+                path.display()
+            ),
+            Err(e) => {
+                eprintln!("Error: Failed to restore {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Load or create the device ID and friendly name, they are kept in the settings database
+    audiocontrol::helpers::device_identity::initialize_from_config(&controllers_config);
+
     // Initialize MusicBrainz with the configuration
     initialize_musicbrainz(&controllers_config);
 
@@ -275,10 +332,31 @@ fn main() {
     
     // Initialize FanArt.tv with the configuration
     initialize_fanarttv(&controllers_config);
-    
+
+    // Configure the Wikipedia biography lookup
+    audiocontrol::helpers::wikipedia::initialize_from_config(&controllers_config);
+
+    // Configure the metadata enrichment queue before the libraries load
+    audiocontrol::helpers::metadataqueue::initialize_from_config(&controllers_config);
+
     // Initialize configurator with the configuration
     initialize_configurator(&controllers_config);
     
+    // Initialize network share management, the MPD library checks shares before updating
+    audiocontrol::helpers::mounts::initialize_from_config(&controllers_config);
+
+    // Initialize WebDAV music sources and their periodic index refresh
+    audiocontrol::helpers::webdav::initialize_from_config(&controllers_config);
+
+    // Initialize the playlist store of players without native playlists
+    audiocontrol::helpers::playlist_store::initialize_from_config(&controllers_config);
+
+    // Initialize text-to-speech rendering for announcements
+    audiocontrol::helpers::tts::initialize_from_config(&controllers_config);
+
+    // Initialize the scrobble thresholds shared by all scrobbling services
+    initialize_scrobbling(&controllers_config);
+
     // Initialize Last.fm with the configuration
     initialize_lastfm(&controllers_config);
     // Initialize Spotify with the configuration
@@ -287,6 +365,9 @@ fn main() {
     }
     initialize_spotify(&controllers_config);
 
+    // Initialize Qobuz with the configuration
+    audiocontrol::helpers::qobuz::initialize_from_config(&controllers_config);
+
     // Initialize volume control with the configuration
     audiocontrol::helpers::global_volume::initialize_volume_control(&controllers_config);
 
@@ -308,6 +389,25 @@ fn main() {
     // Initialize favourite providers (Last.fm and SettingsDB)
     audiocontrol::helpers::favourites::initialize_favourite_providers();
 
+    // Import the artist metadata and favourites of a library export instead of starting
+    if let Some(path) = find_path_in_args(&args, "--import-library") {
+        let overwrite = args.iter().any(|arg| arg == "--overwrite");
+        match library_export::read_export(&path).and_then(|export| library_export::import(&export, overwrite)) {
+            Ok(summary) => println!(
+                "Imported metadata of {} artists ({} already had metadata) and {} favourites from {}",
+                summary.artists_imported,
+                summary.artists_skipped,
+                summary.favourites_imported,
+                path.display()
+            ),
+            Err(e) => {
+                eprintln!("Error: Failed to import {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // Initialize genre cleanup with configuration
     if let Err(e) = audiocontrol::helpers::genre_cleanup::initialize_genre_cleanup_with_config(Some(&controllers_config)) {
         warn!("Failed to initialize genre cleanup: {}", e);
@@ -373,6 +473,58 @@ fn main() {
     // and the AudioController singleton exist, so the first keypress can act.
     audiocontrol::inputs::init_inputs(&controllers_config, Arc::downgrade(&controller));
 
+    // Start checking saved stream URLs, this needs the players to find the URLs
+    audiocontrol::helpers::streamcheck::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Apply gain offsets of radio presets, needs the players to find the playing station
+    audiocontrol::helpers::stationgain::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Start watching for USB storage, devices are added to the MPD library
+    audiocontrol::helpers::usbstorage::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Start watching for audio CDs, they are played and ripped with MPD
+    audiocontrol::helpers::cdsource::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Start the idle policy, it checks the playback state of all players
+    audiocontrol::helpers::idle::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Announce the end of the current track, e.g. to prefetch artwork of the next one
+    audiocontrol::helpers::upcoming_track::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Announce when the queue runs dry and let the auto-DJ add a mix
+    audiocontrol::helpers::queue_estimate::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Language of display strings in API responses
+    audiocontrol::helpers::locale::initialize_from_config(&controllers_config);
+
+    // Watch network changes to reconnect players right away
+    audiocontrol::helpers::network::initialize_from_config(&controllers_config);
+
+    // Check temperature, throttling, load and memory against critical thresholds
+    audiocontrol::helpers::system_monitor::initialize_from_config(&controllers_config);
+
+    // Network diagnostics and detection of playback stalls
+    audiocontrol::helpers::network_diagnostics::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Send errors, expired logins and the weekly digest to ntfy, Telegram or e-mail
+    audiocontrol::helpers::notifications::initialize_from_config(&controllers_config);
+
+    // Store played tracks with the time they were listened to and generate daily mixes from them
+    audiocontrol::helpers::listening_history::initialize_from_config(&controllers_config);
+    audiocontrol::helpers::dailymix::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Save the position of long tracks like DJ mixes and audiobooks when playback stops
+    audiocontrol::helpers::bookmarks::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Switch DSP profiles by genre, active player and time of day
+    audiocontrol::helpers::dspprofiles::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Replay the beginning of tracks that DACs mute while waking up or changing the sample rate
+    audiocontrol::helpers::dacwake::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Publish player state to MQTT and announce the players to Home Assistant
+    audiocontrol::helpers::mqtt::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
     // Wrap the AudioController in a Box that implements PlayerController
     let player: Box<dyn PlayerController + Send + Sync> = Box::new(controller.as_ref().clone());
 
@@ -383,6 +535,36 @@ fn main() {
         warn!("Failed to start player");
     }
 
+    // Export the libraries once they are loaded instead of running the server
+    if let Some(path) = find_path_in_args(&args, "--export-library") {
+        if !library_export::wait_for_libraries(&controller, LIBRARY_EXPORT_TIMEOUT) {
+            warn!("Exporting the libraries that have been loaded so far");
+        }
+        let export = library_export::export(&controller);
+        let format = library_export::ExportFormat::from_path(&path);
+        if let Err(e) = library_export::write_export(&export, &path, format) {
+            eprintln!("Error: Failed to export the library: {}", e);
+            std::process::exit(1);
+        }
+        println!("Exported the libraries of {} players to {}", export.players.len(), path.display());
+        flush_attribute_cache();
+        return;
+    }
+
+    // Register controllers for MPRIS players that appear on D-Bus
+    #[cfg(not(windows))]
+    audiocontrol::players::mpris::discovery::initialize_from_config(&controllers_config, Arc::downgrade(&controller));
+
+    // Register the other players of LMS servers as child players
+    audiocontrol::players::lms::childplayer::initialize(Arc::downgrade(&controller));
+
+    // Announce this device and search for other AudioControl instances
+    let api_port = get_service_config(&controllers_config, "webserver")
+        .and_then(|ws| ws.get("port"))
+        .and_then(|p| p.as_u64())
+        .unwrap_or(1080) as u16;
+    audiocontrol::helpers::federation::initialize_from_config(&controllers_config, api_port);
+
     // Log initial state information
     debug!("Initial player state:");
     debug!("State: {}", player.get_playback_state());
@@ -431,13 +613,34 @@ fn main() {
         thread::sleep(Duration::from_millis(100));
     }
 
+    flush_attribute_cache();
     info!("Exiting application");
 }
 
+// Store attribute cache writes that are still buffered
+fn flush_attribute_cache() {
+    match audiocontrol::helpers::attributecache::flush() {
+        Ok(count) if count > 0 => info!("Stored {} buffered attribute cache entries", count),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to store buffered attribute cache entries: {}", e),
+    }
+}
+
 // Helper function to initialize the global image cache
 fn initialize_image_cache(image_cache_path: &str) {
     match ImageCache::initialize(image_cache_path) {
-        Ok(_) => info!("Image cache initialized with path: {}", image_cache_path),
+        Ok(_) => {
+            info!("Image cache initialized with path: {}", image_cache_path);
+            // Move images stored before content addressing and remove unused contents
+            std::thread::spawn(|| {
+                if let Err(e) = audiocontrol::helpers::imagecache::deduplicate() {
+                    warn!("Failed to deduplicate image cache: {}", e);
+                }
+                if let Err(e) = audiocontrol::helpers::imagecache::collect_garbage() {
+                    warn!("Failed to collect image cache garbage: {}", e);
+                }
+            });
+        }
         Err(e) => warn!("Failed to initialize image cache: {}", e),
     }
 }
@@ -456,6 +659,11 @@ fn initialize_musicbrainz(config: &serde_json::Value) {
     info!("MusicBrainz initialized successfully");
 }
 
+// Helper function to initialize the shared scrobble thresholds
+fn initialize_scrobbling(config: &serde_json::Value) {
+    audiocontrol::helpers::scrobble::initialize_from_config(config);
+}
+
 // Helper function to initialize TheAudioDB
 fn initialize_theaudiodb(config: &serde_json::Value) {
     theaudiodb::initialize_from_config(config);
@@ -618,6 +826,14 @@ fn find_config_file_in_args(args: &[String]) -> Option<String> {
     None
 }
 
+/// Find the file given after an option in the command line arguments
+fn find_path_in_args(args: &[String], option: &str) -> Option<PathBuf> {
+    args.iter()
+        .position(|arg| arg == option)
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+}
+
 /// Find logging config file path from command line arguments (--log-config option)
 fn find_log_config_in_args(args: &[String]) -> Option<PathBuf> {
     let mut i = 1;
@@ -781,6 +997,19 @@ fn print_help() {
     println!();
     println!("    -d, --debug                 Enable debug logging (if no log config)");
     println!();
+    println!("    --export-library <FILE>     Export the libraries of all players, their artist");
+    println!("                                metadata and favourites, then exit. Files ending");
+    println!("                                with .csv get one row per track, others JSON");
+    println!();
+    println!("    --import-library <FILE>     Import artist metadata and favourites of a JSON");
+    println!("                                export, then exit");
+    println!("    --overwrite                 Replace the metadata of artists on import");
+    println!();
+    println!("    --backup-cache <FILE>       Save the attribute cache and settings database");
+    println!("                                to a JSON file, then exit");
+    println!("    --restore-cache <FILE>      Restore a backup of --backup-cache, then exit.");
+    println!("                                Entries that are not in the backup are kept");
+    println!();
     println!("    -h, --help                  Show this help message");
     println!();
     println!("EXAMPLES:");
@@ -796,5 +1025,11 @@ fn print_help() {
     println!("    audiocontrol --debug");
     println!("        Start with debug logging enabled");
     println!();
+    println!("    audiocontrol --export-library /tmp/library.json");
+    println!("        Save the library and looked up metadata, e.g. before wiping the cache");
+    println!();
+    println!("    audiocontrol --backup-cache /boot/audiocontrol-backup.json");
+    println!("        Keep the cache and settings, e.g. before re-imaging the SD card");
+    println!();
     println!("For more information, see the documentation in the doc/ directory.");
 }
@@ -1,4 +1,5 @@
 use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::players::poll_task::PollTask;
 use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
 use delegate::delegate;
 use std::sync::Arc;
@@ -10,8 +11,6 @@ use dbus::blocking::Connection;
 use dbus::blocking::stdintf::org_freedesktop_dbus::{Properties, ObjectManager};
 use dbus::arg::RefArg;
 use std::time::{Duration, SystemTime};
-use std::thread;
-use std::sync::atomic::Ordering;
 
 /// Bluetooth player controller implementation
 /// This controller interfaces with Bluetooth audio devices via D-Bus using BlueZ MediaPlayer1 interface
@@ -37,17 +36,11 @@ pub struct BluetoothPlayerController {
     /// Device name (friendly name)
     device_name: Arc<RwLock<Option<String>>>,
     
-    /// Background thread handle for device scanning
-    scan_thread: Arc<RwLock<Option<std::thread::JoinHandle<()>>>>,
+    /// Device scanning on the global runtime, idles once a device is found
+    scan_task: Arc<RwLock<Option<PollTask>>>,
     
-    /// Flag to stop scanning thread
-    stop_scanning: Arc<std::sync::atomic::AtomicBool>,
-    
-    /// Background thread handle for status polling
-    poll_thread: Arc<RwLock<Option<std::thread::JoinHandle<()>>>>,
-    
-    /// Flag to stop polling thread
-    stop_polling: Arc<std::sync::atomic::AtomicBool>,
+    /// Status polling on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
 }
 
 // Manually implement Clone for BluetoothPlayerController
@@ -61,34 +54,18 @@ impl Clone for BluetoothPlayerController {
             device_address: Arc::clone(&self.device_address),
             player_path: Arc::clone(&self.player_path),
             device_name: Arc::clone(&self.device_name),
-            scan_thread: Arc::new(RwLock::new(None)),
-            stop_scanning: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            poll_thread: Arc::new(RwLock::new(None)),
-            stop_polling: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            scan_task: Arc::new(RwLock::new(None)),
+            poll_task: Arc::new(RwLock::new(None)),
         }
     }
 }
 
 impl Drop for BluetoothPlayerController {
     fn drop(&mut self) {
-        // Signal both threads to stop
-        self.stop_scanning.store(true, Ordering::Relaxed);
-        self.stop_polling.store(true, Ordering::Relaxed);
-        
-        // Wait for the scanning thread to finish
-        {
-            let mut guard = self.scan_thread.write();
-            if let Some(handle) = guard.take() {
-                let _ = handle.join();
-            }
-        }
-
-        // Wait for the polling thread to finish
-        {
-            let mut guard = self.poll_thread.write();
-            if let Some(handle) = guard.take() {
-                let _ = handle.join();
-            }
+        // Stop both tasks and wait for a scan or poll in progress
+        let tasks = [self.scan_task.write().take(), self.poll_task.write().take()];
+        for task in tasks.into_iter().flatten() {
+            task.stop();
         }
         
         debug!("BluetoothPlayerController dropped");
@@ -131,6 +108,7 @@ impl BluetoothPlayerController {
             PlayerCapability::Stop,
             PlayerCapability::Next,
             PlayerCapability::Previous,
+            PlayerCapability::RemoteQueue, // The queue lives on the phone
         ]);
         base.set_capabilities_set(capabilities, false);
 
@@ -142,10 +120,8 @@ impl BluetoothPlayerController {
             device_address: Arc::new(RwLock::new(device_address.clone())),
             player_path: Arc::new(RwLock::new(None)),
             device_name: Arc::new(RwLock::new(None)),
-            scan_thread: Arc::new(RwLock::new(None)),
-            stop_scanning: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            poll_thread: Arc::new(RwLock::new(None)),
-            stop_polling: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            scan_task: Arc::new(RwLock::new(None)),
+            poll_task: Arc::new(RwLock::new(None)),
         };
         
         info!("Created BluetoothPlayerController with address: {:?}", device_address);
@@ -153,7 +129,7 @@ impl BluetoothPlayerController {
         // If no specific device address is given, start auto-discovery
         if device_address.is_none() {
             info!("Starting auto-discovery for Bluetooth devices");
-            controller.start_scanning_thread();
+            controller.start_scanning();
         } else {
             // Try to find the specific device immediately
             controller.find_player_path();
@@ -307,7 +283,7 @@ impl BluetoothPlayerController {
         }
     }
     
-    /// Static helper for checking and updating active player in the polling thread
+    /// Static helper for checking and updating active player in the polling task
     fn check_and_update_active_player(
         player_path: &Arc<RwLock<Option<String>>>,
         connection: &Arc<Mutex<Option<Connection>>>,
@@ -352,7 +328,7 @@ impl BluetoothPlayerController {
         }
     }
 
-    /// Static helper to find active player (for use in polling thread)
+    /// Static helper to find active player (for use in the polling task)
     fn find_active_player_static(
         connection: &Arc<Mutex<Option<Connection>>>,
         device_address: &str,
@@ -654,74 +630,64 @@ impl BluetoothPlayerController {
         }
     }
     
-    /// Start background scanning for devices
-    fn start_scanning_thread(&self) {
+    /// Start background scanning for devices every 5 seconds
+    fn start_scanning(&self) {
         // Don't start if we already have a device
-        if self.device_address.read().is_some() {
+        if self.device_address.read().is_some() || self.scan_task.read().is_some() {
             return;
         }
+        info!("Starting Bluetooth device scanning");
         
         let device_address = Arc::clone(&self.device_address);
         let device_name = Arc::clone(&self.device_name);
         let player_path = Arc::clone(&self.player_path);
-        let stop_flag = Arc::clone(&self.stop_scanning);
         let connection = Arc::clone(&self.connection);
         
-        let handle = thread::spawn(move || {
-            info!("Starting Bluetooth device scanning thread");
-            
-            while !stop_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                // Check if we still need to scan
-                if device_address.read().is_some() {
-                    // We found a device, stop scanning
-                    break;
-                }
+        let task = PollTask::spawn_blocking("Bluetooth scan", Duration::from_secs(5), None, move || {
+            // Nothing to do once a device has been found
+            if device_address.read().is_some() {
+                return;
+            }
 
-                // Try to discover devices
-                {
-                    let conn_guard = connection.lock();
-                    if let Some(conn) = conn_guard.as_ref() {
-                        // Simplified discovery logic for background thread
-                        let proxy = conn.with_proxy("org.bluez", "/", Duration::from_millis(2000));
+            // Try to discover devices
+            {
+                let conn_guard = connection.lock();
+                if let Some(conn) = conn_guard.as_ref() {
+                    // Simplified discovery logic for the scan task
+                    let proxy = conn.with_proxy("org.bluez", "/", Duration::from_millis(2000));
 
-                        if let Ok(objects) = proxy.get_managed_objects() {
-                            for (path, interfaces) in objects {
-                                if interfaces.contains_key("org.bluez.MediaPlayer1") {
-                                    if let Some(device_part) = path.strip_prefix("/org/bluez/hci0/dev_") {
-                                        if let Some(addr_part) = device_part.split('/').next() {
-                                            let discovered_address = addr_part.replace('_', ":");
+                    if let Ok(objects) = proxy.get_managed_objects() {
+                        for (path, interfaces) in objects {
+                            if interfaces.contains_key("org.bluez.MediaPlayer1") {
+                                if let Some(device_part) = path.strip_prefix("/org/bluez/hci0/dev_") {
+                                    if let Some(addr_part) = device_part.split('/').next() {
+                                        let discovered_address = addr_part.replace('_', ":");
 
-                                            // Get device name
-                                            let device_path = format!("/org/bluez/hci0/dev_{}", addr_part);
-                                            let device_proxy = conn.with_proxy("org.bluez", &device_path, Duration::from_millis(1000));
+                                        // Get device name
+                                        let device_path = format!("/org/bluez/hci0/dev_{}", addr_part);
+                                        let device_proxy = conn.with_proxy("org.bluez", &device_path, Duration::from_millis(1000));
 
-                                            let discovered_name = device_proxy.get::<String>("org.bluez.Device1", "Name")
-                                                .unwrap_or_else(|_| discovered_address.clone());
+                                        let discovered_name = device_proxy.get::<String>("org.bluez.Device1", "Name")
+                                            .unwrap_or_else(|_| discovered_address.clone());
 
-                                            info!("Background scan found Bluetooth device: {} ({})", discovered_name, discovered_address);
+                                        info!("Background scan found Bluetooth device: {} ({})", discovered_name, discovered_address);
 
-                                            // Update stored values
-                                            *device_address.write() = Some(discovered_address);
-                                            *device_name.write() = Some(discovered_name);
-                                            *player_path.write() = Some(path.to_string());
-                                            // Found a device, stop scanning
-                                            return;
-                                        }
+                                        // Update stored values
+                                        *device_address.write() = Some(discovered_address);
+                                        *device_name.write() = Some(discovered_name);
+                                        *player_path.write() = Some(path.to_string());
+                                        // Found a device, later scans return right away
+                                        return;
                                     }
                                 }
                             }
                         }
                     }
                 }
-                
-                // Wait 5 seconds before next scan
-                thread::sleep(Duration::from_secs(5));
             }
-            
-            debug!("Bluetooth scanning thread stopped");
         });
         
-        *self.scan_thread.write() = Some(handle);
+        *self.scan_task.write() = Some(task);
     }
 
     /// Manually trigger a rescan for devices
@@ -857,74 +823,69 @@ impl BluetoothPlayerController {
         }
     }
 
-    /// Main polling loop logic
-    fn run_polling_loop(
-        player_path: Arc<RwLock<Option<String>>>,
-        connection: Arc<Mutex<Option<Connection>>>,
-        current_song: Arc<RwLock<Option<Song>>>,
-        current_state: Arc<RwLock<PlayerState>>,
-        stop_flag: Arc<std::sync::atomic::AtomicBool>,
-        base: BasePlayerController,
-        device_address: Arc<RwLock<Option<String>>>,
+    /// Read the state of the player once
+    fn poll_once(
+        player_path: &Arc<RwLock<Option<String>>>,
+        connection: &Arc<Mutex<Option<Connection>>>,
+        current_song: &Arc<RwLock<Option<Song>>>,
+        current_state: &Arc<RwLock<PlayerState>>,
+        base: &BasePlayerController,
+        device_address: &Arc<RwLock<Option<String>>>,
+        last_no_path_warning: &Mutex<SystemTime>,
     ) {
-        info!("Starting Bluetooth status polling thread");
+        // Check if the active player is still available before polling
+        // This handles transitions like player0 -> player1 -> player2
+        Self::check_and_update_active_player(player_path, connection, device_address);
         
-        let mut last_no_path_warning = SystemTime::UNIX_EPOCH;
-        
-        while !stop_flag.load(Ordering::Relaxed) {
-            // Check if the active player is still available before polling
-            // This handles transitions like player0 -> player1 -> player2
-            Self::check_and_update_active_player(&player_path, &connection, &device_address);
-            
-            // Get current player path
-            let path = player_path.read().clone();
+        // Get current player path
+        let path = player_path.read().clone();
 
-            if let Some(ref path_str) = path {
-                let conn_guard = connection.lock();
-                if let Some(ref conn) = *conn_guard {
-                    let proxy = conn.with_proxy("org.bluez", path_str, Duration::from_millis(1000));
+        if let Some(ref path_str) = path {
+            let conn_guard = connection.lock();
+            if let Some(ref conn) = *conn_guard {
+                let proxy = conn.with_proxy("org.bluez", path_str, Duration::from_millis(1000));
 
-                    // Poll different aspects of the player state
-                    debug!("Polling Bluetooth player state at {}", path_str);
-                    Self::poll_playback_state(&proxy, &current_state, &current_song, &base);
-                    Self::poll_track_information(&proxy, &current_song, &base);
-                    Self::poll_position_information(&proxy, &current_state, &base);
-                }
-            } else {
-                // Only log this message every 10 seconds to avoid spam
-                if let Ok(elapsed) = SystemTime::now().duration_since(last_no_path_warning) {
-                    if elapsed >= Duration::from_secs(10) {
-                        debug!("No player path available for polling");
-                        last_no_path_warning = SystemTime::now();
-                    }
+                // Poll different aspects of the player state
+                debug!("Polling Bluetooth player state at {}", path_str);
+                Self::poll_playback_state(&proxy, current_state, current_song, base);
+                Self::poll_track_information(&proxy, current_song, base);
+                Self::poll_position_information(&proxy, current_state, base);
+            }
+        } else {
+            // Only log this message every 10 seconds to avoid spam
+            let mut last_warning = last_no_path_warning.lock();
+            if let Ok(elapsed) = SystemTime::now().duration_since(*last_warning) {
+                if elapsed >= Duration::from_secs(10) {
+                    debug!("No player path available for polling");
+                    *last_warning = SystemTime::now();
                 }
             }
-            
-            // Poll every 2 seconds
-            thread::sleep(Duration::from_secs(2));
         }
-        
-        debug!("Bluetooth polling thread stopped");
     }
 
-    /// Start the status polling thread
-    fn start_polling_thread(&self) {
-        debug!("Starting Bluetooth status polling thread");
+    /// Start polling the status every 2 seconds on the global runtime
+    fn start_polling(&self) {
+        if self.poll_task.read().is_some() {
+            debug!("Bluetooth status polling already started");
+            return;
+        }
+        debug!("Starting Bluetooth status polling");
         
         let player_path = Arc::clone(&self.player_path);
         let connection = Arc::clone(&self.connection);
         let current_song = Arc::clone(&self.current_song);
         let current_state = Arc::clone(&self.current_state);
-        let stop_flag = Arc::clone(&self.stop_polling);
         let base = self.base.clone();
         let device_address = Arc::clone(&self.device_address);
+        let last_no_path_warning = Mutex::new(SystemTime::UNIX_EPOCH);
         
-        let handle = thread::spawn(move || {
-            Self::run_polling_loop(player_path, connection, current_song, current_state, stop_flag, base, device_address);
+        let task = PollTask::spawn_blocking("Bluetooth", Duration::from_secs(2), None, move || {
+            Self::poll_once(&player_path, &connection, &current_song, &current_state, &base, &device_address, &last_no_path_warning);
         });
         
-        *self.poll_thread.write() = Some(handle);
+        *self.poll_task.write() = Some(task);
     }
+
     fn get_playback_status(&self) -> PlaybackState {
         let player_path = self.player_path.read().clone();
 
@@ -1108,8 +1069,8 @@ impl PlayerController for BluetoothPlayerController {
             // Don't return false here as the device might connect later
         }
         
-        // Always start polling thread - it will wait for a device if none is available yet
-        self.start_polling_thread();
+        // Always start polling - it waits for a device if none is available yet
+        self.start_polling();
         
         // Get device name
         if let Some(name) = self.get_device_name() {
@@ -1126,15 +1087,10 @@ impl PlayerController for BluetoothPlayerController {
         let addr = self.device_address.read().clone();
         info!("Stopping Bluetooth player controller for device: {:?}", addr);
         
-        // Signal polling thread to stop
-        self.stop_polling.store(true, Ordering::Relaxed);
-        
-        // Wait for polling thread to finish
-        {
-            let mut guard = self.poll_thread.write();
-            if let Some(handle) = guard.take() {
-                let _ = handle.join();
-            }
+        // Stop polling and wait for a poll in progress
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
         }
 
         // Clear connection
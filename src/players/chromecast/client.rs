@@ -0,0 +1,445 @@
+use crate::data::{PlaybackState, Song};
+use crate::helpers::netaddr;
+use log::debug;
+use native_tls::{TlsConnector, TlsStream};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// Default port of the Cast protocol
+pub const DEFAULT_PORT: u16 = 8009;
+
+const NS_CONNECTION: &str = "urn:x-cast:com.google.cast.tp.connection";
+const NS_HEARTBEAT: &str = "urn:x-cast:com.google.cast.tp.heartbeat";
+const NS_RECEIVER: &str = "urn:x-cast:com.google.cast.receiver";
+pub const NS_MEDIA: &str = "urn:x-cast:com.google.cast.media";
+
+const SENDER_ID: &str = "sender-0";
+const RECEIVER_ID: &str = "receiver-0";
+
+/// Cast messages are limited to 64 KiB
+const MAX_MESSAGE_SIZE: usize = 65536;
+
+/// A CASTV2 message with a JSON payload
+///
+/// On the wire this is a protobuf `CastMessage` prefixed with its length. Only the
+/// string payload type is used, binary payloads are read as empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastMessage {
+    pub source_id: String,
+    pub destination_id: String,
+    pub namespace: String,
+    pub payload: String,
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or("Truncated varint")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Varint too long".to_string())
+}
+
+impl CastMessage {
+    pub fn new(destination_id: &str, namespace: &str, payload: &Value) -> Self {
+        Self {
+            source_id: SENDER_ID.to_string(),
+            destination_id: destination_id.to_string(),
+            namespace: namespace.to_string(),
+            payload: payload.to_string(),
+        }
+    }
+
+    /// Protobuf encoding of the message, without the length prefix
+    pub fn encode(&self) -> Vec<u8> {
+        fn string_field(buffer: &mut Vec<u8>, field: u64, value: &str) {
+            write_varint(buffer, (field << 3) | 2);
+            write_varint(buffer, value.len() as u64);
+            buffer.extend_from_slice(value.as_bytes());
+        }
+
+        let mut buffer = Vec::with_capacity(self.payload.len() + 128);
+        // protocol_version = CASTV2_1_0
+        write_varint(&mut buffer, 1 << 3);
+        write_varint(&mut buffer, 0);
+        string_field(&mut buffer, 2, &self.source_id);
+        string_field(&mut buffer, 3, &self.destination_id);
+        string_field(&mut buffer, 4, &self.namespace);
+        // payload_type = STRING
+        write_varint(&mut buffer, 5 << 3);
+        write_varint(&mut buffer, 0);
+        string_field(&mut buffer, 6, &self.payload);
+        buffer
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let mut message = CastMessage {
+            source_id: String::new(),
+            destination_id: String::new(),
+            namespace: String::new(),
+            payload: String::new(),
+        };
+        let mut pos = 0;
+        while pos < data.len() {
+            let key = read_varint(data, &mut pos)?;
+            match key & 7 {
+                0 => {
+                    read_varint(data, &mut pos)?;
+                }
+                1 => pos += 8,
+                2 => {
+                    let len = read_varint(data, &mut pos)? as usize;
+                    let value = data.get(pos..pos + len).ok_or("Truncated field")?;
+                    pos += len;
+                    let text = || String::from_utf8(value.to_vec()).map_err(|e| e.to_string());
+                    match key >> 3 {
+                        2 => message.source_id = text()?,
+                        3 => message.destination_id = text()?,
+                        4 => message.namespace = text()?,
+                        6 => message.payload = text()?,
+                        _ => {}
+                    }
+                }
+                5 => pos += 4,
+                wire_type => return Err(format!("Unsupported wire type {}", wire_type)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// Volume of the Cast device, level is 0.0 - 1.0
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CastVolume {
+    pub level: Option<f64>,
+    pub muted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CastNamespace {
+    pub name: String,
+}
+
+/// Application running on the Cast device, e.g. the Default Media Receiver or YouTube
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CastApplication {
+    pub app_id: String,
+    pub display_name: Option<String>,
+    pub status_text: Option<String>,
+    pub transport_id: String,
+    #[serde(default)]
+    pub namespaces: Vec<CastNamespace>,
+}
+
+impl CastApplication {
+    /// Whether the application can be controlled through the media namespace
+    pub fn supports_media(&self) -> bool {
+        self.namespaces.iter().any(|ns| ns.name == NS_MEDIA)
+    }
+}
+
+/// Status of the receiver (RECEIVER_STATUS)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReceiverStatus {
+    #[serde(default)]
+    pub applications: Vec<CastApplication>,
+    #[serde(default)]
+    pub volume: CastVolume,
+}
+
+impl ReceiverStatus {
+    /// The application that plays media, if any
+    pub fn media_application(&self) -> Option<&CastApplication> {
+        self.applications.iter().find(|app| app.supports_media())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CastImage {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaMetadata {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub artist: Option<String>,
+    pub album_name: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<i32>,
+    #[serde(default)]
+    pub images: Vec<CastImage>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInformation {
+    #[serde(default)]
+    pub content_id: String,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    pub metadata: Option<MediaMetadata>,
+}
+
+/// Status of a media session (MEDIA_STATUS)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaStatus {
+    pub media_session_id: i64,
+    #[serde(default)]
+    pub player_state: String,
+    pub current_time: Option<f64>,
+    pub media: Option<MediaInformation>,
+}
+
+impl MediaStatus {
+    pub fn playback_state(&self) -> PlaybackState {
+        match self.player_state.as_str() {
+            "PLAYING" | "BUFFERING" => PlaybackState::Playing,
+            "PAUSED" => PlaybackState::Paused,
+            "IDLE" => PlaybackState::Stopped,
+            _ => PlaybackState::Unknown,
+        }
+    }
+
+    /// The playing song, None if the session has no media information
+    pub fn song(&self) -> Option<Song> {
+        let media = self.media.as_ref()?;
+        let metadata = media.metadata.clone().unwrap_or_default();
+        Some(Song {
+            title: metadata.title,
+            artist: metadata.artist.or(metadata.album_artist.clone()).or(metadata.subtitle),
+            album: metadata.album_name,
+            album_artist: metadata.album_artist,
+            track_number: metadata.track_number,
+            duration: media.duration.filter(|d| *d > 0.0),
+            cover_art_url: metadata.images.first().map(|image| image.url.clone()),
+            stream_url: Some(media.content_id.clone()).filter(|id| !id.is_empty()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Connection to the receiver of a Cast device
+pub struct CastConnection {
+    stream: TlsStream<TcpStream>,
+    timeout: Duration,
+    request_id: u64,
+    /// Transport id of the application the virtual connection has been opened to
+    connected_transport: Option<String>,
+}
+
+impl CastConnection {
+    /// Open a TLS connection to the device and connect to its receiver
+    ///
+    /// Cast devices use self-signed certificates, so the certificate is not verified.
+    pub fn open(host: &str, port: u16, timeout: Duration) -> Result<Self, String> {
+        let host = netaddr::strip_brackets(host);
+        let tcp = netaddr::connect_with_timeout(host, port, Some(timeout))
+            .map_err(|e| format!("Failed to connect to {}: {}", netaddr::host_port(host, port), e))?;
+        tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+        tcp.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+        let connector = TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .build()
+            .map_err(|e| format!("Failed to create TLS connector: {}", e))?;
+        let stream = connector
+            .connect(host, tcp)
+            .map_err(|e| format!("TLS handshake with {} failed: {}", host, e))?;
+
+        let mut connection = Self {
+            stream,
+            timeout,
+            request_id: 0,
+            connected_transport: None,
+        };
+        connection.send(RECEIVER_ID, NS_CONNECTION, &json!({"type": "CONNECT"}))?;
+        debug!("Connected to Cast device {}", netaddr::host_port(host, port));
+        Ok(connection)
+    }
+
+    fn send(&mut self, destination: &str, namespace: &str, payload: &Value) -> Result<(), String> {
+        let data = CastMessage::new(destination, namespace, payload).encode();
+        self.stream
+            .write_all(&(data.len() as u32).to_be_bytes())
+            .and_then(|_| self.stream.write_all(&data))
+            .map_err(|e| format!("Failed to send Cast message: {}", e))
+    }
+
+    fn read(&mut self) -> Result<CastMessage, String> {
+        let mut length = [0u8; 4];
+        self.stream
+            .read_exact(&mut length)
+            .map_err(|e| format!("Failed to read Cast message: {}", e))?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length > MAX_MESSAGE_SIZE {
+            return Err(format!("Cast message too large ({} bytes)", length));
+        }
+        let mut data = vec![0u8; length];
+        self.stream
+            .read_exact(&mut data)
+            .map_err(|e| format!("Failed to read Cast message: {}", e))?;
+        CastMessage::decode(&data)
+    }
+
+    /// Send a request and wait for the reply with the same request id
+    ///
+    /// Heartbeats of the device are answered while waiting, other messages are ignored.
+    fn request(&mut self, destination: &str, namespace: &str, mut payload: Value) -> Result<Value, String> {
+        self.request_id += 1;
+        let request_id = self.request_id;
+        payload["requestId"] = json!(request_id);
+        self.send(destination, namespace, &payload)?;
+
+        let deadline = Instant::now() + self.timeout;
+        while Instant::now() < deadline {
+            let message = self.read()?;
+            if message.namespace == NS_HEARTBEAT {
+                if message.payload.contains("\"PING\"") {
+                    self.send(&message.source_id, NS_HEARTBEAT, &json!({"type": "PONG"}))?;
+                }
+                continue;
+            }
+            let Ok(reply) = serde_json::from_str::<Value>(&message.payload) else {
+                continue;
+            };
+            if reply.get("requestId").and_then(|id| id.as_u64()) != Some(request_id) {
+                continue;
+            }
+            return match reply.get("type").and_then(|t| t.as_str()) {
+                Some(error @ ("INVALID_REQUEST" | "LOAD_FAILED" | "LOAD_CANCELLED")) => {
+                    Err(format!("Cast device rejected the request: {} {}", error, reply.get("reason").unwrap_or(&Value::Null)))
+                }
+                _ => Ok(reply),
+            };
+        }
+        Err("Timeout waiting for the Cast device".to_string())
+    }
+
+    pub fn receiver_status(&mut self) -> Result<ReceiverStatus, String> {
+        let reply = self.request(RECEIVER_ID, NS_RECEIVER, json!({"type": "GET_STATUS"}))?;
+        serde_json::from_value(reply.get("status").cloned().unwrap_or_default())
+            .map_err(|e| format!("Invalid receiver status: {}", e))
+    }
+
+    /// Status of the media session of an application, None if nothing is loaded
+    pub fn media_status(&mut self, transport_id: &str) -> Result<Option<MediaStatus>, String> {
+        self.connect_transport(transport_id)?;
+        let reply = self.request(transport_id, NS_MEDIA, json!({"type": "GET_STATUS"}))?;
+        Self::first_media_status(&reply)
+    }
+
+    /// Send a media command like PLAY, PAUSE, STOP or SEEK to a media session
+    pub fn media_command(
+        &mut self,
+        transport_id: &str,
+        media_session_id: i64,
+        command: &str,
+        mut arguments: Value,
+    ) -> Result<Option<MediaStatus>, String> {
+        self.connect_transport(transport_id)?;
+        arguments["type"] = json!(command);
+        arguments["mediaSessionId"] = json!(media_session_id);
+        let reply = self.request(transport_id, NS_MEDIA, arguments)?;
+        Self::first_media_status(&reply)
+    }
+
+    /// Set the volume level (0.0 - 1.0) and/or mute state of the device
+    pub fn set_volume(&mut self, level: Option<f64>, muted: Option<bool>) -> Result<(), String> {
+        let mut volume = json!({});
+        if let Some(level) = level {
+            volume["level"] = json!(level.clamp(0.0, 1.0));
+        }
+        if let Some(muted) = muted {
+            volume["muted"] = json!(muted);
+        }
+        self.request(RECEIVER_ID, NS_RECEIVER, json!({"type": "SET_VOLUME", "volume": volume}))
+            .map(|_| ())
+    }
+
+    fn connect_transport(&mut self, transport_id: &str) -> Result<(), String> {
+        if self.connected_transport.as_deref() == Some(transport_id) {
+            return Ok(());
+        }
+        self.send(transport_id, NS_CONNECTION, &json!({"type": "CONNECT"}))?;
+        self.connected_transport = Some(transport_id.to_string());
+        Ok(())
+    }
+
+    fn first_media_status(reply: &Value) -> Result<Option<MediaStatus>, String> {
+        match reply.get("status").and_then(|s| s.as_array()).and_then(|s| s.first()) {
+            Some(status) => serde_json::from_value(status.clone())
+                .map(Some)
+                .map_err(|e| format!("Invalid media status: {}", e)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_message_roundtrip() {
+        let message = CastMessage::new(RECEIVER_ID, NS_RECEIVER, &json!({"type": "GET_STATUS", "requestId": 1}));
+        let decoded = CastMessage::decode(&message.encode()).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(decoded.source_id, "sender-0");
+
+        assert!(CastMessage::decode(&[0x12, 0x05, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_media_status_mapping() {
+        let status: MediaStatus = serde_json::from_value(json!({
+            "mediaSessionId": 3,
+            "playerState": "BUFFERING",
+            "currentTime": 12.5,
+            "media": {
+                "contentId": "http://radio.example/stream.mp3",
+                "duration": 241.0,
+                "metadata": {
+                    "metadataType": 3,
+                    "title": "Song",
+                    "albumArtist": "Band",
+                    "albumName": "Album",
+                    "images": [{"url": "http://radio.example/cover.jpg"}]
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(status.playback_state(), PlaybackState::Playing);
+        let song = status.song().unwrap();
+        assert_eq!(song.title.as_deref(), Some("Song"));
+        assert_eq!(song.artist.as_deref(), Some("Band"));
+        assert_eq!(song.album.as_deref(), Some("Album"));
+        assert_eq!(song.duration, Some(241.0));
+        assert_eq!(song.cover_art_url.as_deref(), Some("http://radio.example/cover.jpg"));
+        assert_eq!(song.stream_url.as_deref(), Some("http://radio.example/stream.mp3"));
+
+        let idle: MediaStatus = serde_json::from_value(json!({"mediaSessionId": 1, "playerState": "IDLE"})).unwrap();
+        assert_eq!(idle.playback_state(), PlaybackState::Stopped);
+        assert!(idle.song().is_none());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::players::poll_task::PollTask;
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
+use crate::players::chromecast::client::{CastConnection, MediaStatus, DEFAULT_PORT};
+use crate::players::chromecast::discovery::{self, CastDevice};
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::{Mutex, RwLock};
+use serde_json::json;
+use std::time::Duration;
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// Timeout for connecting to the device and waiting for replies
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for mDNS answers
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Media session of the application playing on the device
+#[derive(Debug, Clone, PartialEq)]
+struct MediaSession {
+    transport_id: String,
+    media_session_id: i64,
+}
+
+/// Shared state of the controller, updated by the poll task
+#[derive(Clone)]
+struct ChromecastState {
+    /// Configured address, if not set the device is found by name with mDNS
+    host: Option<String>,
+    port: u16,
+    device_name: Option<String>,
+    device: Arc<RwLock<Option<CastDevice>>>,
+    connection: Arc<Mutex<Option<CastConnection>>>,
+    current_song: Arc<RwLock<Option<Song>>>,
+    current_state: Arc<RwLock<PlayerState>>,
+    session: Arc<RwLock<Option<MediaSession>>>,
+    application: Arc<RwLock<Option<String>>>,
+}
+
+/// Google Cast controller
+///
+/// Controls Chromecasts, Cast speakers and TVs through the CASTV2 protocol. The
+/// media session of the application currently casting to the device is shown as
+/// the player, whichever sender started it.
+pub struct ChromecastController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// State shared with the poll task
+    state: ChromecastState,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Whether polling is started
+    should_poll: Arc<AtomicBool>,
+
+    /// Task polling the player on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
+}
+
+// Manually implement Clone for ChromecastController
+impl Clone for ChromecastController {
+    fn clone(&self) -> Self {
+        ChromecastController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            state: self.state.clone(),
+            poll_interval: self.poll_interval,
+            should_poll: Arc::clone(&self.should_poll),
+            poll_task: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl ChromecastState {
+    /// Address of the device, discovered with mDNS if no host is configured
+    fn address(&self) -> Result<(String, u16), String> {
+        if let Some(host) = &self.host {
+            return Ok((host.clone(), self.port));
+        }
+        if let Some(device) = self.device.read().as_ref() {
+            return Ok((device.host.clone(), device.port));
+        }
+        let device = discovery::find_device(self.device_name.as_deref(), DISCOVERY_TIMEOUT)?;
+        info!("Found Cast device {} ({}) at {}:{}",
+              device.friendly_name.as_deref().unwrap_or("unknown"),
+              device.model.as_deref().unwrap_or("unknown model"),
+              device.host, device.port);
+        let address = (device.host.clone(), device.port);
+        *self.device.write() = Some(device);
+        Ok(address)
+    }
+
+    /// Run a function on the connection to the device, connecting first if necessary
+    fn with_connection<T>(&self, f: impl FnOnce(&mut CastConnection) -> Result<T, String>) -> Result<T, String> {
+        let mut connection = self.connection.lock();
+        if connection.is_none() {
+            let (host, port) = self.address()?;
+            *connection = Some(CastConnection::open(&host, port, REQUEST_TIMEOUT).inspect_err(|_| {
+                // The device may have a new address
+                *self.device.write() = None;
+            })?);
+        }
+        let result = f(connection.as_mut().expect("connection has been opened"));
+        if result.is_err() {
+            // The stream may be in an undefined state, reconnect on the next request
+            *connection = None;
+        }
+        result
+    }
+
+    fn media_command(&self, command: &str, arguments: serde_json::Value) -> Result<(), String> {
+        let session = self.session.read().clone().ok_or_else(|| "Nothing is casting to the device".to_string())?;
+        self.with_connection(|c| c.media_command(&session.transport_id, session.media_session_id, command, arguments))
+            .map(|_| ())
+    }
+}
+
+impl ChromecastController {
+    /// Create a controller for the device at `host`, or the device found by name with mDNS
+    pub fn new(host: Option<&str>, port: u16, device_name: Option<&str>, name: Option<&str>, poll_interval: Duration) -> Self {
+        let player_id = match (host, device_name) {
+            (Some(host), _) => crate::helpers::netaddr::host_port(host, port),
+            (None, Some(device_name)) => format!("chromecast:{}", device_name),
+            (None, None) => "chromecast".to_string(),
+        };
+        debug!("Creating new ChromecastController for {}", player_id);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info(name.unwrap_or("chromecast"), &player_id),
+            state: ChromecastState {
+                host: host.map(|h| h.to_string()),
+                port,
+                device_name: device_name.map(|d| d.to_string()),
+                device: Arc::new(RwLock::new(None)),
+                connection: Arc::new(Mutex::new(None)),
+                current_song: Arc::new(RwLock::new(None)),
+                current_state: Arc::new(RwLock::new(PlayerState::new())),
+                session: Arc::new(RwLock::new(None)),
+                application: Arc::new(RwLock::new(None)),
+            },
+            poll_interval,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            poll_task: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    ///
+    /// The device is given by `host`, or by its friendly name in `device`. Without
+    /// both the first device found with mDNS is used.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        let host = config.get("host").and_then(|v| v.as_str()).filter(|h| !h.is_empty());
+        let device_name = config.get("device").and_then(|v| v.as_str()).filter(|d| !d.is_empty());
+        let name = config.get("name").and_then(|v| v.as_str());
+
+        let port = match config.get("port").and_then(|v| v.as_u64()) {
+            Some(port) => u16::try_from(port).map_err(|_| format!("Invalid port {}", port))?,
+            None => DEFAULT_PORT,
+        };
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        Ok(Self::new(host, port, device_name, name, poll_interval))
+    }
+
+    /// Set the default capabilities for Cast devices
+    fn set_default_capabilities(&self) {
+        debug!("Setting default ChromecastController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::PlayPause,
+            PlayerCapability::Stop,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Volume,
+            PlayerCapability::Mute,
+            PlayerCapability::Metadata,
+            PlayerCapability::AlbumArt,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Set the volume of the device in percent
+    pub fn set_volume(&self, volume: u32) -> Result<(), String> {
+        let level = volume.min(100) as f64 / 100.0;
+        self.state.with_connection(|c| c.set_volume(Some(level), None))
+    }
+
+    /// Mute or unmute the device
+    pub fn set_mute(&self, mute: bool) -> Result<(), String> {
+        self.state.with_connection(|c| c.set_volume(None, Some(mute)))
+    }
+
+    /// Update internal state from the device (static version for threading)
+    fn update_state_static(state: &ChromecastState, base: &BasePlayerController) {
+        let status = state.with_connection(|c| {
+            let receiver = c.receiver_status()?;
+            let media = match receiver.media_application() {
+                Some(app) => c.media_status(&app.transport_id)?.map(|media| (app.transport_id.clone(), media)),
+                None => None,
+            };
+            Ok((receiver, media))
+        });
+        let (receiver, media) = match status {
+            Ok(status) => status,
+            Err(e) => {
+                debug!("Cast device not reachable: {}", e);
+                return;
+            }
+        };
+
+        *state.application.write() = receiver.applications.first()
+            .map(|app| app.display_name.clone().unwrap_or_else(|| app.app_id.clone()));
+        *state.session.write() = media.as_ref().map(|(transport_id, media)| MediaSession {
+            transport_id: transport_id.clone(),
+            media_session_id: media.media_session_id,
+        });
+
+        let media = media.map(|(_, media)| media);
+        let playback_state = media.as_ref().map(MediaStatus::playback_state).unwrap_or(PlaybackState::Stopped);
+        let position = media.as_ref().and_then(|m| m.current_time);
+
+        let (state_changed, position_changed) = {
+            let mut current = state.current_state.write();
+            let state_changed = current.state != playback_state;
+            current.state = playback_state;
+            let position_changed = position.is_some() && current.position != position;
+            current.position = position;
+            current.volume = receiver.volume.level.map(|level| (level * 100.0).round() as i32);
+            current.muted = receiver.volume.muted.unwrap_or(false);
+            (state_changed, position_changed)
+        };
+        if state_changed {
+            base.notify_state_changed(playback_state);
+        }
+        if position_changed {
+            if let Some(position) = position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        let song = media.as_ref().and_then(MediaStatus::song);
+        let song_changed = {
+            let mut current_song = state.current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.stream_url != new.stream_url,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("Cast song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+
+        base.alive();
+    }
+
+    /// Update internal state from the device
+    fn update_state(&self) {
+        Self::update_state_static(&self.state, &self.base);
+    }
+
+    /// Start polling on the global runtime
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for Cast device");
+            return;
+        }
+
+        info!("Starting polling for Cast device {} with interval {:?}",
+              self.base.get_player_id(), self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        let state = self.state.clone();
+        let poll_interval = self.poll_interval;
+        let base = self.base.clone();
+
+        let task = PollTask::spawn_blocking("Chromecast", poll_interval, None, move || {
+            Self::update_state_static(&state, &base);
+        });
+
+        *self.poll_task.write() = Some(task);
+    }
+
+    /// Stop polling, waits for a poll in progress
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for Cast device");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
+        *self.state.connection.lock() = None;
+    }
+}
+
+impl PlayerController for ChromecastController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, serde_json::Value>> {
+        let mut metadata = HashMap::new();
+        if let Some(device) = self.state.device.read().as_ref() {
+            if let Some(name) = &device.friendly_name {
+                metadata.insert("friendly_name".to_string(), json!(name));
+            }
+            if let Some(model) = &device.model {
+                metadata.insert("model".to_string(), json!(model));
+            }
+        }
+        if let Some(application) = self.state.application.read().as_ref() {
+            metadata.insert("application".to_string(), json!(application));
+        }
+        let current = self.state.current_state.read();
+        if let Some(volume) = current.volume {
+            metadata.insert("volume".to_string(), json!(volume));
+            metadata.insert("muted".to_string(), json!(current.muted));
+        }
+        Some(metadata)
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.state.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.state.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        Vec::new()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        false
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        LoopMode::None
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.state.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to Cast device: {}", command);
+
+        let result = match &command {
+            PlayerCommand::Play => self.state.media_command("PLAY", json!({})),
+            PlayerCommand::Pause => self.state.media_command("PAUSE", json!({})),
+            PlayerCommand::PlayPause => {
+                let action = if self.get_playback_state() == PlaybackState::Playing { "PAUSE" } else { "PLAY" };
+                self.state.media_command(action, json!({}))
+            }
+            PlayerCommand::Stop => self.state.media_command("STOP", json!({})),
+            PlayerCommand::Seek(position) => self.state.media_command("SEEK", json!({"currentTime": position.max(0.0)})),
+            _ => {
+                warn!("Command not supported by Cast device: {}", command);
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to Cast device: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting Chromecast controller for {}", self.base.get_player_id());
+        // The device may be switched off, polling picks it up when it becomes reachable
+        if let Err(e) = self.state.address() {
+            warn!("Cast device is not reachable yet: {}", e);
+        }
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping Chromecast controller");
+        self.stop_polling();
+        true
+    }
+}
@@ -0,0 +1,86 @@
+use crate::helpers::mdns::{self, MdnsService};
+use std::time::Duration;
+
+/// mDNS service type announced by Google Cast devices
+pub const CAST_SERVICE: &str = "_googlecast._tcp.local";
+
+/// A Cast device found on the network
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastDevice {
+    /// Device UUID from the `id` TXT record
+    pub id: Option<String>,
+    /// Name set up in the Google Home app, from the `fn` TXT record
+    pub friendly_name: Option<String>,
+    /// Model, e.g. "Chromecast Audio", from the `md` TXT record
+    pub model: Option<String>,
+    /// IP address, or the mDNS host name if no address was sent
+    pub host: String,
+    pub port: u16,
+}
+
+impl CastDevice {
+    /// Whether the device has this friendly name or id
+    pub fn matches(&self, name: &str) -> bool {
+        [&self.friendly_name, &self.id]
+            .into_iter()
+            .flatten()
+            .any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+impl From<MdnsService> for CastDevice {
+    fn from(service: MdnsService) -> Self {
+        Self {
+            id: service.txt.get("id").cloned(),
+            friendly_name: service.txt.get("fn").cloned(),
+            model: service.txt.get("md").cloned(),
+            host: service.host,
+            port: service.port,
+        }
+    }
+}
+
+/// Find Cast devices with an mDNS query
+pub fn discover(timeout: Duration) -> Result<Vec<CastDevice>, String> {
+    Ok(mdns::browse(CAST_SERVICE, timeout)?.into_iter().map(CastDevice::from).collect())
+}
+
+/// Find a Cast device by friendly name or id, or the first device if no name is given
+pub fn find_device(name: Option<&str>, timeout: Duration) -> Result<CastDevice, String> {
+    let devices = discover(timeout)?;
+    let device = match name {
+        Some(name) => devices.into_iter().find(|d| d.matches(name)),
+        None => devices.into_iter().next(),
+    };
+    device.ok_or_else(|| match name {
+        Some(name) => format!("Cast device '{}' not found", name),
+        None => "No Cast device found".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_from_txt() {
+        let device = CastDevice::from(MdnsService {
+            instance: "kitchen-abc._googlecast._tcp.local".to_string(),
+            host: "192.168.1.30".to_string(),
+            port: 8009,
+            txt: [("id", "abc123"), ("md", "Chromecast Audio"), ("fn", "Kitchen")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            sender: None,
+        });
+        assert_eq!(device, CastDevice {
+            id: Some("abc123".to_string()),
+            friendly_name: Some("Kitchen".to_string()),
+            model: Some("Chromecast Audio".to_string()),
+            host: "192.168.1.30".to_string(),
+            port: 8009,
+        });
+        assert!(device.matches("kitchen"));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod client;
+pub mod controller;
+pub mod discovery;
+
+pub use client::CastConnection;
+pub use controller::ChromecastController;
+pub use discovery::CastDevice;
@@ -29,6 +29,7 @@ pub struct GenericPlayerController {
     current_shuffle: Arc<RwLock<bool>>,
     current_position: Arc<RwLock<Option<f64>>>,
     current_queue: Arc<RwLock<Vec<Track>>>,
+    upcoming_tracks: Arc<RwLock<Vec<Track>>>,
     current_stream_details: Arc<RwLock<Option<StreamDetails>>>,
 
     /// Configuration from JSON
@@ -55,6 +56,7 @@ impl GenericPlayerController {
             current_shuffle: Arc::new(RwLock::new(false)),
             current_position: Arc::new(RwLock::new(None)),
             current_queue: Arc::new(RwLock::new(Vec::new())),
+            upcoming_tracks: Arc::new(RwLock::new(Vec::new())),
             current_stream_details: Arc::new(RwLock::new(None)),
             config: Arc::new(RwLock::new(HashMap::new())),
             command_url: None,
@@ -166,6 +168,7 @@ impl GenericPlayerController {
                         "db_update" => capabilities.add_capability(PlayerCapability::DatabaseUpdate),
                         "killable" => capabilities.add_capability(PlayerCapability::Killable),
                         "receives_updates" => capabilities.add_capability(PlayerCapability::ReceivesUpdates),
+                        "remote_queue" => capabilities.add_capability(PlayerCapability::RemoteQueue),
                         unknown => warn!("Unknown capability '{}' for generic player '{}'", unknown, self.player_name),
                     }
                 }
@@ -216,6 +219,7 @@ impl GenericPlayerController {
             "loop_mode_changed" => self.handle_loop_mode_change_event(event_data),
             "shuffle_changed" => self.handle_shuffle_change_event(event_data),
             "stream_info" => self.handle_stream_info_event(event_data),
+            "upcoming_changed" => self.handle_upcoming_change_event(event_data),
             _ => {
                 debug!("Unknown event type '{}' for generic player", event_type);
                 false
@@ -325,6 +329,33 @@ impl GenericPlayerController {
         true
     }
 
+    /// Handle upcoming-track hints for players whose queue lives on the sender.
+    /// Accepts a `tracks` array with `title`, `artist` and `uri` per entry, an
+    /// empty/absent array clears the hints.
+    fn handle_upcoming_change_event(&self, event_data: &Value) -> bool {
+        let tracks: Vec<Track> = event_data
+            .get("tracks")
+            .and_then(|t| t.as_array())
+            .map(|tracks| {
+                tracks
+                    .iter()
+                    .filter_map(|t| {
+                        let title = t.get("title").and_then(|v| v.as_str())?;
+                        let mut track = Track::with_name(title.to_string());
+                        track.artist = t.get("artist").and_then(|v| v.as_str()).map(|s| s.to_string());
+                        track.uri = t.get("uri").and_then(|v| v.as_str()).map(|s| s.to_string());
+                        Some(track)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        debug!("Generic player '{}' announced {} upcoming tracks", self.player_name, tracks.len());
+        *self.upcoming_tracks.write() = tracks;
+        self.base.notify_queue_changed();
+        true
+    }
+
     /// Handle loop mode change events
     fn handle_loop_mode_change_event(&self, event_data: &Value) -> bool {
         if let Some(mode_str) = event_data.get("loop_mode").and_then(|m| m.as_str()) {
@@ -422,6 +453,7 @@ impl Clone for GenericPlayerController {
             current_shuffle: Arc::clone(&self.current_shuffle),
             current_position: Arc::clone(&self.current_position),
             current_queue: Arc::clone(&self.current_queue),
+            upcoming_tracks: Arc::clone(&self.upcoming_tracks),
             current_stream_details: Arc::clone(&self.current_stream_details),
             config: Arc::clone(&self.config),
             command_url: self.command_url.clone(),
@@ -447,6 +479,10 @@ impl PlayerController for GenericPlayerController {
         let queue = self.current_queue.read();
         queue.clone()
     }
+
+    fn get_upcoming_tracks(&self) -> Vec<Track> {
+        self.upcoming_tracks.read().clone()
+    }
     
     fn get_loop_mode(&self) -> LoopMode {
         let mode = self.current_loop_mode.read();
@@ -261,6 +261,32 @@ mod tests {
         assert_eq!(controller.get_loop_mode(), LoopMode::Track);
     }
 
+    #[test]
+    fn test_upcoming_event() {
+        let controller = create_test_controller();
+        assert!(controller.get_upcoming_tracks().is_empty());
+
+        let upcoming = json!({
+            "type": "upcoming_changed",
+            "tracks": [
+                {"title": "Next Song", "artist": "Artist", "uri": "spotify:track:1"},
+                {"artist": "Entry without title is ignored"},
+                {"title": "After That"}
+            ]
+        });
+        assert!(controller.process_api_event(&upcoming));
+        let tracks = controller.get_upcoming_tracks();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].name, "Next Song");
+        assert_eq!(tracks[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(tracks[1].uri, None);
+
+        assert!(controller.process_api_event(&json!({ "type": "upcoming_changed" })));
+        assert!(controller.get_upcoming_tracks().is_empty());
+        // Generic players only report queue data they were given
+        assert!(controller.get_queue().is_empty());
+    }
+
     #[test]
     fn test_invalid_event() {
         let controller = create_test_controller();
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::data::PlaybackState;
+
+/// Default port of the HQPlayer control API
+pub const DEFAULT_PORT: u16 = 4321;
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>";
+
+static ELEMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<([A-Za-z]\w*)((?:\s+[\w:-]+\s*=\s*"[^"]*")*)\s*/?>"#).unwrap());
+static ATTRIBUTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"([\w:-]+)\s*=\s*"([^"]*)""#).unwrap());
+
+/// A selectable entry of the HQPlayer processing pipeline (filter, shaper, mode or rate)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineOption {
+    /// Index used by HQPlayer to select this entry
+    pub index: u32,
+    /// Display name, e.g. "poly-sinc-gauss-long" or "705600"
+    pub name: String,
+}
+
+/// Current state of the HQPlayer processing pipeline with the available options
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HQPlayerPipeline {
+    /// Output mode (PCM or SDM), as reported by HQPlayer
+    pub mode: Option<String>,
+    pub filter: Option<String>,
+    pub shaper: Option<String>,
+    /// Output sample rate in Hz
+    pub rate: Option<u32>,
+    /// Volume in dB
+    pub volume: Option<f64>,
+    pub modes: Vec<PipelineOption>,
+    pub filters: Vec<PipelineOption>,
+    pub shapers: Vec<PipelineOption>,
+    pub rates: Vec<PipelineOption>,
+}
+
+/// Playback status reported by HQPlayer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HQPlayerStatus {
+    pub state: PlaybackState,
+    /// Playback position in seconds
+    pub position: Option<f64>,
+    /// Track length in seconds
+    pub duration: Option<f64>,
+    pub track: Option<u32>,
+    pub tracks_total: Option<u32>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// Sample rate of the source in Hz
+    pub source_rate: Option<u32>,
+    pub active_mode: Option<String>,
+    pub active_filter: Option<String>,
+    pub active_shaper: Option<String>,
+    /// Output sample rate in Hz
+    pub active_rate: Option<u32>,
+    pub volume: Option<f64>,
+}
+
+/// Client for the XML control API of HQPlayer
+///
+/// Every request opens a new connection. HQPlayer answers each command with a
+/// single XML document on one line.
+#[derive(Debug, Clone)]
+pub struct HQPlayerClient {
+    host: String,
+    port: u16,
+    timeout: Duration,
+}
+
+impl HQPlayerClient {
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            timeout: Duration::from_secs(3),
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Send a command element, e.g. `<Play/>`, and return the response document
+    pub fn request(&self, command: &str) -> Result<String, String> {
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve {}: {}", self.host, e))?
+            .next()
+            .ok_or_else(|| format!("No address found for {}", self.host))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)
+            .map_err(|e| format!("Failed to connect to HQPlayer at {}:{}: {}", self.host, self.port, e))?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(|e| e.to_string())?;
+
+        debug!("HQPlayer request: {}", command);
+        stream
+            .write_all(format!("{}{}\n", XML_HEADER, command).as_bytes())
+            .map_err(|e| format!("Failed to send command to HQPlayer: {}", e))?;
+
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_line(&mut response)
+            .map_err(|e| format!("Failed to read HQPlayer response: {}", e))?;
+        if response.trim().is_empty() {
+            return Err("Empty response from HQPlayer".to_string());
+        }
+        debug!("HQPlayer response: {}", response.trim());
+        Ok(response)
+    }
+
+    /// Send a command that only reports success or failure
+    pub fn command(&self, command: &str) -> Result<(), String> {
+        let response = self.request(command)?;
+        match first_element(&response).and_then(|(_, attrs)| attrs.get("result").cloned()) {
+            Some(result) if result != "OK" => Err(format!("HQPlayer rejected {}: {}", command, result)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_status(&self) -> Result<HQPlayerStatus, String> {
+        let response = self.request("<Status subscribe=\"0\"/>")?;
+        parse_status(&response).ok_or_else(|| format!("Unexpected status response: {}", response.trim()))
+    }
+
+    /// Get the current pipeline settings and the available options
+    pub fn get_pipeline(&self) -> Result<HQPlayerPipeline, String> {
+        let status = self.get_status()?;
+        Ok(HQPlayerPipeline {
+            mode: status.active_mode,
+            filter: status.active_filter,
+            shaper: status.active_shaper,
+            rate: status.active_rate,
+            volume: status.volume,
+            modes: parse_options(&self.request("<GetModes/>")?, "ModesItem"),
+            filters: parse_options(&self.request("<GetFilters/>")?, "FiltersItem"),
+            shapers: parse_options(&self.request("<GetShapers/>")?, "ShapersItem"),
+            rates: parse_options(&self.request("<GetRates/>")?, "RatesItem"),
+        })
+    }
+
+    pub fn set_mode(&self, index: u32) -> Result<(), String> {
+        self.command(&format!("<SetMode value=\"{}\"/>", index))
+    }
+
+    pub fn set_filter(&self, index: u32) -> Result<(), String> {
+        self.command(&format!("<SetFilter value=\"{}\"/>", index))
+    }
+
+    pub fn set_shaper(&self, index: u32) -> Result<(), String> {
+        self.command(&format!("<SetShaping value=\"{}\"/>", index))
+    }
+
+    pub fn set_rate(&self, index: u32) -> Result<(), String> {
+        self.command(&format!("<SetRate value=\"{}\"/>", index))
+    }
+}
+
+/// Unescape the XML entities used in attribute values
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Get all elements with their attributes in document order, ignoring the XML declaration
+fn parse_elements(xml: &str) -> Vec<(String, HashMap<String, String>)> {
+    ELEMENT_RE
+        .captures_iter(xml)
+        .map(|caps| {
+            let attributes = ATTRIBUTE_RE
+                .captures_iter(&caps[2])
+                .map(|attr| (attr[1].to_string(), unescape(&attr[2])))
+                .collect();
+            (caps[1].to_string(), attributes)
+        })
+        .collect()
+}
+
+fn first_element(xml: &str) -> Option<(String, HashMap<String, String>)> {
+    parse_elements(xml).into_iter().next()
+}
+
+fn non_empty(attrs: &HashMap<String, String>, key: &str) -> Option<String> {
+    attrs.get(key).filter(|v| !v.is_empty()).cloned()
+}
+
+fn number<T: std::str::FromStr>(attrs: &HashMap<String, String>, key: &str) -> Option<T> {
+    attrs.get(key).and_then(|v| v.trim().parse().ok())
+}
+
+/// Parse the response of a `Status` request
+fn parse_status(xml: &str) -> Option<HQPlayerStatus> {
+    let elements = parse_elements(xml);
+    let (_, attrs) = elements.iter().find(|(name, _)| name == "Status")?;
+
+    let state = match attrs.get("state").map(|s| s.as_str()) {
+        Some("0") => PlaybackState::Stopped,
+        Some("1") => PlaybackState::Paused,
+        Some("2") => PlaybackState::Playing,
+        _ => PlaybackState::Unknown,
+    };
+    let seconds = |min: &str, sec: &str| match (number::<f64>(attrs, min), number::<f64>(attrs, sec)) {
+        (None, None) => None,
+        (min, sec) => Some(min.unwrap_or(0.0) * 60.0 + sec.unwrap_or(0.0)),
+    };
+
+    let mut status = HQPlayerStatus {
+        state,
+        position: seconds("min", "sec"),
+        duration: seconds("total_min", "total_sec").filter(|d| *d > 0.0),
+        track: number(attrs, "track"),
+        tracks_total: number(attrs, "tracks_total"),
+        source_rate: number(attrs, "samplerate"),
+        active_mode: non_empty(attrs, "active_mode"),
+        active_filter: non_empty(attrs, "active_filter"),
+        active_shaper: non_empty(attrs, "active_shaper"),
+        active_rate: number(attrs, "active_rate"),
+        volume: number(attrs, "volume"),
+        ..Default::default()
+    };
+
+    if let Some((_, meta)) = elements.iter().find(|(name, _)| name == "metadata") {
+        status.title = non_empty(meta, "song");
+        status.artist = non_empty(meta, "artist");
+        status.album = non_empty(meta, "album");
+        status.genre = non_empty(meta, "genre");
+    }
+    Some(status)
+}
+
+/// Parse the items of a `GetModes`, `GetFilters`, `GetShapers` or `GetRates` response
+fn parse_options(xml: &str, item: &str) -> Vec<PipelineOption> {
+    parse_elements(xml)
+        .into_iter()
+        .filter(|(name, _)| name == item)
+        .filter_map(|(_, attrs)| {
+            let index = number(&attrs, "index")?;
+            let name = non_empty(&attrs, "name").or_else(|| non_empty(&attrs, "rate"))?;
+            Some(PipelineOption { index, name })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?><Status state="2" track="3" tracks_total="12" min="1" sec="5" total_min="4" total_sec="30" volume="-6.5" samplerate="44100" active_mode="SDM" active_filter="poly-sinc-gauss-long" active_shaper="ASDM7EC" active_rate="11289600"><metadata artist="Miles Davis" album="Kind of Blue" song="So What &amp; More" genre=""/></Status>"#;
+        let status = parse_status(xml).unwrap();
+        assert_eq!(status.state, PlaybackState::Playing);
+        assert_eq!(status.position, Some(65.0));
+        assert_eq!(status.duration, Some(270.0));
+        assert_eq!(status.track, Some(3));
+        assert_eq!(status.title.as_deref(), Some("So What & More"));
+        assert_eq!(status.artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(status.genre, None);
+        assert_eq!(status.active_filter.as_deref(), Some("poly-sinc-gauss-long"));
+        assert_eq!(status.active_rate, Some(11289600));
+        assert_eq!(status.volume, Some(-6.5));
+
+        let stopped = parse_status(r#"<Status state="0"/>"#).unwrap();
+        assert_eq!(stopped.state, PlaybackState::Stopped);
+        assert_eq!(stopped.position, None);
+        assert!(parse_status(r#"<Play result="OK"/>"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let xml = r#"<GetFilters><FiltersItem index="0" name="none" value="0" arg="0"/><FiltersItem index="1" name="poly-sinc-ext2" value="26" arg="0"/></GetFilters>"#;
+        assert_eq!(parse_options(xml, "FiltersItem"), vec![
+            PipelineOption { index: 0, name: "none".to_string() },
+            PipelineOption { index: 1, name: "poly-sinc-ext2".to_string() },
+        ]);
+
+        let xml = r#"<GetRates><RatesItem index="0" rate="0"/><RatesItem index="1" rate="705600"/></GetRates>"#;
+        let rates = parse_options(xml, "RatesItem");
+        assert_eq!(rates[1].name, "705600");
+    }
+}
@@ -0,0 +1,394 @@
+use crate::players::poll_task::PollTask;
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
+use crate::data::stream_details::StreamDetails;
+use crate::players::hqplayer::client::{HQPlayerClient, HQPlayerPipeline, HQPlayerStatus, DEFAULT_PORT};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::RwLock;
+use std::time::Duration;
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// HQPlayer controller implementation
+///
+/// Monitors and controls HQPlayer Desktop or Embedded through its XML control API.
+/// Transport commands are mapped to HQPlayer commands, the processing pipeline
+/// (mode, filter, shaper and output rate) can be read and changed separately.
+pub struct HQPlayerController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// Control API client
+    client: HQPlayerClient,
+
+    /// Current song information
+    current_song: Arc<RwLock<Option<Song>>>,
+
+    /// Current player state
+    current_state: Arc<RwLock<PlayerState>>,
+
+    /// Output format of the pipeline
+    stream_details: Arc<RwLock<Option<StreamDetails>>>,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Whether polling is started
+    should_poll: Arc<AtomicBool>,
+
+    /// Task polling the player on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
+}
+
+// Manually implement Clone for HQPlayerController
+impl Clone for HQPlayerController {
+    fn clone(&self) -> Self {
+        HQPlayerController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            client: self.client.clone(),
+            current_song: Arc::clone(&self.current_song),
+            current_state: Arc::clone(&self.current_state),
+            stream_details: Arc::clone(&self.stream_details),
+            poll_interval: self.poll_interval,
+            should_poll: Arc::clone(&self.should_poll),
+            poll_task: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl HQPlayerController {
+    /// Create a new HQPlayer controller
+    pub fn new(host: &str, port: u16, poll_interval: Duration) -> Self {
+        debug!("Creating new HQPlayerController for {}:{}", host, port);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info("hqplayer", &format!("hqplayer:{}", host)),
+            client: HQPlayerClient::new(host, port),
+            current_song: Arc::new(RwLock::new(None)),
+            current_state: Arc::new(RwLock::new(PlayerState::new())),
+            stream_details: Arc::new(RwLock::new(None)),
+            poll_interval,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            poll_task: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    ///
+    /// Supported keys are `host` (default localhost), `port` (default 4321)
+    /// and `poll_interval` in seconds (default 1.0).
+    pub fn from_config(config: &serde_json::Value) -> Self {
+        let host = config.get("host")
+            .and_then(|v| v.as_str())
+            .unwrap_or("localhost");
+
+        let port = config.get("port")
+            .and_then(|v| v.as_u64())
+            .map(|p| p as u16)
+            .unwrap_or(DEFAULT_PORT);
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        Self::new(host, port, poll_interval)
+    }
+
+    /// Set the default capabilities for HQPlayer
+    fn set_default_capabilities(&self) {
+        debug!("Setting default HQPlayerController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::Stop,
+            PlayerCapability::Previous,
+            PlayerCapability::Next,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Metadata,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Get the current pipeline settings and the available options
+    pub fn get_pipeline(&self) -> Result<HQPlayerPipeline, String> {
+        self.client.get_pipeline()
+    }
+
+    /// Select the output mode (PCM/SDM) by its index
+    pub fn set_mode(&self, index: u32) -> Result<(), String> {
+        self.client.set_mode(index)
+    }
+
+    /// Select the upsampling filter by its index
+    pub fn set_filter(&self, index: u32) -> Result<(), String> {
+        self.client.set_filter(index)
+    }
+
+    /// Select the noise shaper or modulator by its index
+    pub fn set_shaper(&self, index: u32) -> Result<(), String> {
+        self.client.set_shaper(index)
+    }
+
+    /// Select the output sample rate by its index
+    pub fn set_rate(&self, index: u32) -> Result<(), String> {
+        self.client.set_rate(index)
+    }
+
+    fn song_from_status(status: &HQPlayerStatus) -> Option<Song> {
+        if status.title.is_none() && status.artist.is_none() && status.album.is_none() {
+            return None;
+        }
+        Some(Song {
+            title: status.title.clone(),
+            artist: status.artist.clone(),
+            album: status.album.clone(),
+            genre: status.genre.clone(),
+            duration: status.duration,
+            track_number: status.track.map(|t| t as i32),
+            total_tracks: status.tracks_total.map(|t| t as i32),
+            source: Some("hqplayer".to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Update internal state from HQPlayer (static version for threading)
+    fn update_state_static(
+        client: &HQPlayerClient,
+        current_song: &Arc<RwLock<Option<Song>>>,
+        current_state: &Arc<RwLock<PlayerState>>,
+        stream_details: &Arc<RwLock<Option<StreamDetails>>>,
+        base: &BasePlayerController,
+    ) {
+        let status = match client.get_status() {
+            Ok(status) => status,
+            Err(e) => {
+                debug!("Failed to get HQPlayer status: {}", e);
+                return;
+            }
+        };
+
+        let (state_changed, position_changed) = {
+            let mut current_state = current_state.write();
+            let state_changed = current_state.state != status.state;
+            let position_changed = current_state.position != status.position;
+            current_state.state = status.state;
+            current_state.position = status.position;
+            (state_changed, position_changed)
+        };
+        if state_changed {
+            debug!("HQPlayer state changed to {:?}", status.state);
+            base.notify_state_changed(status.state);
+        }
+        if position_changed {
+            if let Some(position) = status.position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        let song = Self::song_from_status(&status);
+        let song_changed = {
+            let mut current_song = current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.album != new.album,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("HQPlayer song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+
+        *stream_details.write() = status.active_rate.filter(|rate| *rate > 0).map(|rate| StreamDetails {
+            sample_rate: Some(rate),
+            codec: status.active_mode.clone(),
+            ..StreamDetails::new()
+        });
+
+        base.alive();
+    }
+
+    /// Update internal state from HQPlayer
+    fn update_state(&self) {
+        Self::update_state_static(
+            &self.client,
+            &self.current_song,
+            &self.current_state,
+            &self.stream_details,
+            &self.base,
+        );
+    }
+
+    /// Start polling on the global runtime
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for HQPlayer");
+            return;
+        }
+
+        info!("Starting polling for HQPlayer at {}:{} with interval {:?}",
+              self.client.host(), self.client.port(), self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        let client = self.client.clone();
+        let poll_interval = self.poll_interval;
+        let current_song = Arc::clone(&self.current_song);
+        let current_state = Arc::clone(&self.current_state);
+        let stream_details = Arc::clone(&self.stream_details);
+        let base = self.base.clone();
+
+        let task = PollTask::spawn_blocking("HQPlayer", poll_interval, None, move || {
+            Self::update_state_static(&client, &current_song, &current_state, &stream_details, &base);
+        });
+
+        *self.poll_task.write() = Some(task);
+    }
+
+    /// Stop polling, waits for a poll in progress
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for HQPlayer");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
+    }
+}
+
+impl PlayerController for HQPlayerController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_stream_details(&self) -> Option<StreamDetails> {
+        self.stream_details.read().clone()
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        // The control API only reports the current track
+        Vec::new()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.current_state.read().shuffle
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        self.current_state.read().loop_mode
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to HQPlayer: {}", command);
+
+        let request = match &command {
+            PlayerCommand::Play => "<Play/>".to_string(),
+            PlayerCommand::Pause => "<Pause/>".to_string(),
+            PlayerCommand::PlayPause => {
+                if self.get_playback_state() == PlaybackState::Playing {
+                    "<Pause/>".to_string()
+                } else {
+                    "<Play/>".to_string()
+                }
+            }
+            PlayerCommand::Stop => "<Stop/>".to_string(),
+            PlayerCommand::Next => "<Next/>".to_string(),
+            PlayerCommand::Previous => "<Previous/>".to_string(),
+            PlayerCommand::Seek(position) => format!("<Seek position=\"{}\"/>", position.max(0.0) as u64),
+            PlayerCommand::SetRandom(enabled) => format!("<SetRandom value=\"{}\"/>", u8::from(*enabled)),
+            PlayerCommand::SetLoopMode(mode) => {
+                let value = match mode {
+                    LoopMode::None => 0,
+                    LoopMode::Track => 1,
+                    LoopMode::Playlist => 2,
+                };
+                format!("<SetRepeat value=\"{}\"/>", value)
+            }
+            _ => {
+                warn!("Command not supported by HQPlayer: {}", command);
+                return false;
+            }
+        };
+
+        match self.client.command(&request) {
+            Ok(()) => {
+                match command {
+                    PlayerCommand::SetRandom(enabled) => {
+                        self.current_state.write().shuffle = enabled;
+                        self.base.notify_random_changed(enabled);
+                    }
+                    PlayerCommand::SetLoopMode(mode) => {
+                        self.current_state.write().loop_mode = mode;
+                        self.base.notify_loop_mode_changed(mode);
+                    }
+                    _ => {}
+                }
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to HQPlayer: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting HQPlayer controller for {}:{}", self.client.host(), self.client.port());
+        // HQPlayer may be started later, polling picks it up when it becomes reachable
+        if let Err(e) = self.client.get_status() {
+            warn!("HQPlayer is not reachable yet: {}", e);
+        }
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping HQPlayer controller");
+        self.stop_polling();
+        true
+    }
+}
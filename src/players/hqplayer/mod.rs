@@ -0,0 +1,5 @@
+pub mod client;
+pub mod controller;
+
+pub use client::{HQPlayerClient, HQPlayerPipeline, PipelineOption};
+pub use controller::HQPlayerController;
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::debug;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::data::{Song, Track};
+
+/// Default port of the Kodi web server
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Playlist Kodi uses for music
+pub const AUDIO_PLAYLIST: i64 = 0;
+
+/// Properties read with Player.GetItem and Playlist.GetItems
+pub const ITEM_PROPERTIES: &[&str] = &[
+    "title", "artist", "albumartist", "album", "genre", "track", "duration", "thumbnail", "file", "showtitle",
+];
+
+/// A player returned by Player.GetActivePlayers
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ActivePlayer {
+    #[serde(rename = "playerid")]
+    pub player_id: i64,
+    /// "audio", "video" or "picture"
+    #[serde(rename = "type")]
+    pub player_type: String,
+}
+
+/// Time as used by the Kodi API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct KodiTime {
+    pub hours: u64,
+    pub minutes: u64,
+    pub seconds: u64,
+    pub milliseconds: u64,
+}
+
+impl KodiTime {
+    pub fn from_seconds(seconds: f64) -> Self {
+        let millis = (seconds.max(0.0) * 1000.0) as u64;
+        Self {
+            hours: millis / 3_600_000,
+            minutes: (millis / 60_000) % 60,
+            seconds: (millis / 1000) % 60,
+            milliseconds: millis % 1000,
+        }
+    }
+
+    pub fn to_seconds(self) -> f64 {
+        (self.hours * 3600 + self.minutes * 60 + self.seconds) as f64 + self.milliseconds as f64 / 1000.0
+    }
+
+    pub fn to_json(self) -> Value {
+        json!({
+            "hours": self.hours,
+            "minutes": self.minutes,
+            "seconds": self.seconds,
+            "milliseconds": self.milliseconds,
+        })
+    }
+}
+
+/// Properties returned by Player.GetProperties
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PlayerProperties {
+    /// 0 when paused, 1 when playing, other values while fast forwarding or rewinding
+    pub speed: i64,
+    pub time: KodiTime,
+    pub totaltime: KodiTime,
+    #[serde(rename = "playlistid")]
+    pub playlist_id: i64,
+    /// Position in the playlist, -1 if not playing from a playlist
+    pub position: i64,
+    pub shuffled: bool,
+    /// "off", "one" or "all"
+    pub repeat: String,
+}
+
+/// Item returned by Player.GetItem and Playlist.GetItems
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct KodiItem {
+    pub label: String,
+    pub title: Option<String>,
+    pub artist: Vec<String>,
+    #[serde(rename = "albumartist")]
+    pub album_artist: Vec<String>,
+    pub album: Option<String>,
+    pub genre: Vec<String>,
+    pub track: Option<i32>,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    pub thumbnail: Option<String>,
+    pub file: Option<String>,
+    /// Name of the TV show for episodes
+    #[serde(rename = "showtitle")]
+    pub show_title: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.clone().filter(|v| !v.is_empty())
+}
+
+fn join(values: &[String]) -> Option<String> {
+    Some(values.join(", ")).filter(|v| !v.is_empty())
+}
+
+impl KodiItem {
+    fn title(&self) -> String {
+        non_empty(&self.title).unwrap_or_else(|| self.label.clone())
+    }
+
+    /// Convert to a song, `image_base` is the web server URL used for thumbnails
+    pub fn to_song(&self, image_base: &str) -> Song {
+        let genres: Vec<String> = self.genre.iter().filter(|g| !g.is_empty()).cloned().collect();
+        Song {
+            title: Some(self.title()).filter(|t| !t.is_empty()),
+            artist: join(&self.artist).or_else(|| non_empty(&self.show_title)),
+            album_artist: join(&self.album_artist),
+            album: non_empty(&self.album),
+            genre: genres.first().cloned(),
+            genres,
+            track_number: self.track.filter(|t| *t > 0),
+            duration: self.duration.filter(|d| *d > 0.0),
+            cover_art_url: self.thumbnail.as_deref().and_then(|t| image_url(image_base, t)),
+            stream_url: non_empty(&self.file),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_track(&self) -> Track {
+        let mut track = Track::with_name(self.title());
+        track.artist = join(&self.artist);
+        track.uri = non_empty(&self.file);
+        track
+    }
+}
+
+/// URL of a Kodi thumbnail, images are served by Kodi's web server
+pub fn image_url(base: &str, thumbnail: &str) -> Option<String> {
+    if thumbnail.is_empty() {
+        return None;
+    }
+    Some(format!("{}/image/{}", base, urlencoding::encode(thumbnail)))
+}
+
+/// Kodi JSON-RPC client using the HTTP interface of the web server
+#[derive(Debug, Clone)]
+pub struct KodiClient {
+    base_url: String,
+    authorization: Option<String>,
+    timeout: Duration,
+    next_id: Arc<AtomicU64>,
+}
+
+impl KodiClient {
+    /// Create a client for the web server at `base_url` (e.g. "http://192.168.1.50:8080")
+    pub fn new(base_url: &str, username: Option<&str>, password: Option<&str>) -> Self {
+        let authorization = username.map(|user| {
+            format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password.unwrap_or_default())))
+        });
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            authorization,
+            timeout: Duration::from_secs(5),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Call a JSON-RPC method and return its result
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        debug!("Kodi {} {}", method, request["params"]);
+
+        let mut http = ureq::post(&format!("{}/jsonrpc", self.base_url)).timeout(self.timeout);
+        if let Some(authorization) = &self.authorization {
+            http = http.set("Authorization", authorization);
+        }
+        let body = http
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string())
+            .map_err(|e| format!("{} failed: {}", method, e))?
+            .into_string()
+            .map_err(|e| format!("Failed to read {} response: {}", method, e))?;
+        let mut response: Value = serde_json::from_str(&body)
+            .map_err(|e| format!("Invalid {} response: {}", method, e))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed: {}", method, error.get("message").unwrap_or(error)));
+        }
+        Ok(response.get_mut("result").map(Value::take).unwrap_or(Value::Null))
+    }
+
+    /// Call a method and deserialize its result
+    pub fn call_as<T: for<'de> Deserialize<'de>>(&self, method: &str, params: Value) -> Result<T, String> {
+        serde_json::from_value(self.call(method, params)?).map_err(|e| format!("Unexpected {} response: {}", method, e))
+    }
+
+    pub fn active_players(&self) -> Result<Vec<ActivePlayer>, String> {
+        self.call_as("Player.GetActivePlayers", json!({}))
+    }
+
+    pub fn player_properties(&self, player_id: i64) -> Result<PlayerProperties, String> {
+        self.call_as("Player.GetProperties", json!({
+            "playerid": player_id,
+            "properties": ["speed", "time", "totaltime", "playlistid", "position", "shuffled", "repeat"],
+        }))
+    }
+
+    pub fn current_item(&self, player_id: i64) -> Result<KodiItem, String> {
+        let mut result = self.call("Player.GetItem", json!({ "playerid": player_id, "properties": ITEM_PROPERTIES }))?;
+        serde_json::from_value(result["item"].take()).map_err(|e| format!("Unexpected Player.GetItem response: {}", e))
+    }
+
+    pub fn playlist_items(&self, playlist_id: i64) -> Result<Vec<KodiItem>, String> {
+        let mut result = self.call("Playlist.GetItems", json!({ "playlistid": playlist_id, "properties": ITEM_PROPERTIES }))?;
+        match result.get_mut("items").map(Value::take) {
+            Some(items) => serde_json::from_value(items).map_err(|e| format!("Unexpected Playlist.GetItems response: {}", e)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Volume (0-100) and mute state
+    pub fn volume(&self) -> Result<(i32, bool), String> {
+        let result = self.call("Application.GetProperties", json!({ "properties": ["volume", "muted"] }))?;
+        Ok((
+            result["volume"].as_i64().unwrap_or(0) as i32,
+            result["muted"].as_bool().unwrap_or(false),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_to_song() {
+        let item: KodiItem = serde_json::from_value(json!({
+            "label": "So What",
+            "title": "So What",
+            "artist": ["Miles Davis"],
+            "albumartist": ["Miles Davis"],
+            "album": "Kind of Blue",
+            "genre": ["Jazz"],
+            "track": 1,
+            "duration": 562,
+            "thumbnail": "image://music@smb%3a%2f%2fnas%2fcover.jpg/",
+            "file": "smb://nas/music/01.flac",
+            "showtitle": "",
+            "type": "song"
+        })).unwrap();
+        let song = item.to_song("http://kodi:8080");
+        assert_eq!(song.title.as_deref(), Some("So What"));
+        assert_eq!(song.artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(song.genres, vec!["Jazz".to_string()]);
+        assert_eq!(song.duration, Some(562.0));
+        assert_eq!(
+            song.cover_art_url.as_deref(),
+            Some("http://kodi:8080/image/image%3A%2F%2Fmusic%40smb%253a%252f%252fnas%252fcover.jpg%2F")
+        );
+
+        // Episodes only have a label and the show title
+        let episode: KodiItem = serde_json::from_value(json!({"label": "Pilot", "showtitle": "The Show", "artist": []})).unwrap();
+        let song = episode.to_song("http://kodi:8080");
+        assert_eq!(song.title.as_deref(), Some("Pilot"));
+        assert_eq!(song.artist.as_deref(), Some("The Show"));
+        assert_eq!(song.cover_art_url, None);
+    }
+
+    #[test]
+    fn test_kodi_time() {
+        let time = KodiTime::from_seconds(3725.5);
+        assert_eq!(time, KodiTime { hours: 1, minutes: 2, seconds: 5, milliseconds: 500 });
+        assert_eq!(time.to_seconds(), 3725.5);
+        let properties: PlayerProperties = serde_json::from_value(json!({
+            "speed": 1, "time": {"hours": 0, "minutes": 1, "seconds": 30, "milliseconds": 0}, "repeat": "all"
+        })).unwrap();
+        assert_eq!(properties.time.to_seconds(), 90.0);
+        assert_eq!(properties.position, 0);
+    }
+}
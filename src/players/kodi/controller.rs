@@ -0,0 +1,591 @@
+use crate::players::poll_task::{PollTask, PollTrigger};
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
+use crate::players::kodi::client::{KodiClient, KodiItem, KodiTime, AUDIO_PLAYLIST, DEFAULT_PORT};
+use crate::players::kodi::notifications::{self, DEFAULT_TCP_PORT};
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::RwLock;
+use serde_json::json;
+use std::time::Duration;
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// Shared state of the controller, updated by the poll task
+#[derive(Clone)]
+struct KodiState {
+    client: KodiClient,
+    /// Id of the active player, audio players are preferred over video players
+    player_id: Arc<RwLock<Option<i64>>>,
+    /// Playlist the active player plays from, the audio playlist if nothing is playing
+    playlist_id: Arc<RwLock<i64>>,
+    current_song: Arc<RwLock<Option<Song>>>,
+    current_state: Arc<RwLock<PlayerState>>,
+    queue: Arc<RwLock<Vec<KodiItem>>>,
+    /// Triggered by notifications, updates before the next poll is due
+    update_trigger: PollTrigger,
+    /// Set by playlist notifications, the queue is only re-read then
+    queue_changed: Arc<AtomicBool>,
+}
+
+/// Kodi controller
+///
+/// Monitors and controls playback of a Kodi media center through the JSON-RPC API
+/// of its web server. Notifications on the JSON-RPC TCP port trigger immediate updates.
+/// The queue is Kodi's current playlist, by default the music playlist.
+pub struct KodiController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// State shared with the poll task
+    state: KodiState,
+
+    /// Host for the notification connection
+    host: String,
+
+    /// JSON-RPC TCP port, None disables notifications
+    tcp_port: Option<u16>,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Whether polling is started, also stops the notification listener
+    should_poll: Arc<AtomicBool>,
+
+    /// Task polling Kodi on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
+}
+
+// Manually implement Clone for KodiController
+impl Clone for KodiController {
+    fn clone(&self) -> Self {
+        KodiController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            state: self.state.clone(),
+            host: self.host.clone(),
+            tcp_port: self.tcp_port,
+            poll_interval: self.poll_interval,
+            should_poll: Arc::clone(&self.should_poll),
+            poll_task: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl KodiState {
+    fn player_id(&self) -> Result<i64, String> {
+        self.player_id.read().ok_or_else(|| "Nothing is playing on Kodi".to_string())
+    }
+
+    fn player_call(&self, method: &str, mut params: serde_json::Value) -> Result<(), String> {
+        params["playerid"] = json!(self.player_id()?);
+        self.client.call(method, params).map(|_| ())
+    }
+
+    fn playlist_call(&self, method: &str, mut params: serde_json::Value) -> Result<(), String> {
+        params["playlistid"] = json!(*self.playlist_id.read());
+        self.queue_changed.store(true, Ordering::Relaxed);
+        self.client.call(method, params).map(|_| ())
+    }
+}
+
+/// Swaps of neighbouring items that move the item at `from` to `to`
+fn swap_steps(from: usize, to: usize) -> Vec<(usize, usize)> {
+    if from <= to {
+        (from..to).map(|i| (i, i + 1)).collect()
+    } else {
+        (to..from).rev().map(|i| (i + 1, i)).collect()
+    }
+}
+
+/// Kodi repeat mode for a loop mode
+fn repeat_mode(loop_mode: LoopMode) -> &'static str {
+    match loop_mode {
+        LoopMode::None => "off",
+        LoopMode::Track => "one",
+        LoopMode::Playlist => "all",
+    }
+}
+
+fn file_item(uri: &str) -> serde_json::Value {
+    json!({ "file": uri })
+}
+
+impl KodiController {
+    /// Create a controller for the Kodi web server at `host:port`
+    pub fn new(
+        host: &str,
+        port: u16,
+        username: Option<&str>,
+        password: Option<&str>,
+        tcp_port: Option<u16>,
+        name: Option<&str>,
+        poll_interval: Duration,
+    ) -> Self {
+        let address = crate::helpers::netaddr::host_port(host, port);
+        debug!("Creating new KodiController for {}", address);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info(name.unwrap_or("kodi"), &address),
+            state: KodiState {
+                client: KodiClient::new(&format!("http://{}", address), username, password),
+                player_id: Arc::new(RwLock::new(None)),
+                playlist_id: Arc::new(RwLock::new(AUDIO_PLAYLIST)),
+                current_song: Arc::new(RwLock::new(None)),
+                current_state: Arc::new(RwLock::new(PlayerState::new())),
+                queue: Arc::new(RwLock::new(Vec::new())),
+                update_trigger: PollTrigger::new(),
+                queue_changed: Arc::new(AtomicBool::new(true)),
+            },
+            host: host.to_string(),
+            tcp_port,
+            poll_interval,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            poll_task: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        let host = config.get("host")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "host is required".to_string())?;
+
+        let port = |key: &str, default: u16| -> Result<u16, String> {
+            match config.get(key).and_then(|v| v.as_u64()) {
+                Some(port) => u16::try_from(port).map_err(|_| format!("Invalid {} {}", key, port)),
+                None => Ok(default),
+            }
+        };
+        let http_port = port("port", DEFAULT_PORT)?;
+        let tcp_port = port("tcp_port", DEFAULT_TCP_PORT)?;
+        let use_notifications = config.get("notifications").and_then(|v| v.as_bool()).unwrap_or(true);
+
+        let username = config.get("username").and_then(|v| v.as_str());
+        let password = config.get("password").and_then(|v| v.as_str());
+        let name = config.get("name").and_then(|v| v.as_str());
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        Ok(Self::new(
+            host,
+            http_port,
+            username,
+            password,
+            Some(tcp_port).filter(|_| use_notifications),
+            name,
+            poll_interval,
+        ))
+    }
+
+    /// Set the default capabilities for Kodi
+    fn set_default_capabilities(&self) {
+        debug!("Setting default KodiController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::PlayPause,
+            PlayerCapability::Stop,
+            PlayerCapability::Previous,
+            PlayerCapability::Next,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Volume,
+            PlayerCapability::Mute,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Queue,
+            PlayerCapability::Metadata,
+            PlayerCapability::AlbumArt,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Set the volume of Kodi (0-100)
+    pub fn set_volume(&self, volume: u32) -> Result<(), String> {
+        self.state.client.call("Application.SetVolume", json!({ "volume": volume.min(100) })).map(|_| ())
+    }
+
+    /// Mute or unmute Kodi
+    pub fn set_mute(&self, mute: bool) -> Result<(), String> {
+        self.state.client.call("Application.SetMute", json!({ "mute": mute })).map(|_| ())
+    }
+
+    /// Update internal state from Kodi (static version for threading)
+    fn update_state_static(state: &KodiState, base: &BasePlayerController) {
+        let players = match state.client.active_players() {
+            Ok(players) => players,
+            Err(e) => {
+                debug!("Kodi at {} not reachable: {}", state.client.base_url(), e);
+                return;
+            }
+        };
+        let player = players.iter()
+            .find(|p| p.player_type == "audio")
+            .or_else(|| players.iter().find(|p| p.player_type == "video"));
+        *state.player_id.write() = player.map(|p| p.player_id);
+
+        let (properties, item) = match player {
+            Some(player) => (
+                state.client.player_properties(player.player_id).ok(),
+                state.client.current_item(player.player_id).ok(),
+            ),
+            None => (None, None),
+        };
+
+        let playback_state = match &properties {
+            Some(p) if p.speed == 0 => PlaybackState::Paused,
+            Some(_) => PlaybackState::Playing,
+            None => PlaybackState::Stopped,
+        };
+        let position = properties.as_ref().map(|p| p.time.to_seconds());
+        let volume = state.client.volume().ok();
+
+        let (state_changed, shuffle_changed, loop_changed, position_changed) = {
+            let mut current = state.current_state.write();
+            let state_changed = current.state != playback_state;
+            current.state = playback_state;
+            let mut shuffle_changed = false;
+            let mut loop_changed = false;
+            if let Some(properties) = &properties {
+                shuffle_changed = properties.shuffled != current.shuffle;
+                current.shuffle = properties.shuffled;
+                let loop_mode = match properties.repeat.as_str() {
+                    "one" => LoopMode::Track,
+                    "all" => LoopMode::Playlist,
+                    _ => LoopMode::None,
+                };
+                loop_changed = loop_mode != current.loop_mode;
+                current.loop_mode = loop_mode;
+            }
+            let position_changed = position.is_some() && current.position != position;
+            current.position = position;
+            if let Some((volume, muted)) = volume {
+                current.volume = Some(volume);
+                current.muted = muted;
+            }
+            (state_changed, shuffle_changed, loop_changed, position_changed)
+        };
+        let current = state.current_state.read().clone();
+        if state_changed {
+            base.notify_state_changed(current.state);
+        }
+        if shuffle_changed {
+            base.notify_random_changed(current.shuffle);
+        }
+        if loop_changed {
+            base.notify_loop_mode_changed(current.loop_mode);
+        }
+        if position_changed {
+            if let Some(position) = current.position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        let song = item.map(|item| {
+            let mut song = item.to_song(state.client.base_url());
+            if song.duration.is_none() {
+                song.duration = properties.as_ref().map(|p| p.totaltime.to_seconds()).filter(|d| *d > 0.0);
+            }
+            song
+        });
+        let song_changed = {
+            let mut current_song = state.current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.stream_url != new.stream_url,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("Kodi song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+
+        let playlist_id = properties.as_ref().map(|p| p.playlist_id).filter(|id| *id >= 0).unwrap_or(AUDIO_PLAYLIST);
+        let playlist_switched = std::mem::replace(&mut *state.playlist_id.write(), playlist_id) != playlist_id;
+        if playlist_switched || state.queue_changed.swap(false, Ordering::Relaxed) {
+            match state.client.playlist_items(playlist_id) {
+                Ok(items) => {
+                    let changed = *state.queue.read() != items;
+                    *state.queue.write() = items;
+                    if changed {
+                        base.notify_queue_changed();
+                    }
+                }
+                Err(e) => {
+                    debug!("Failed to read Kodi playlist: {}", e);
+                    state.queue_changed.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        base.alive();
+    }
+
+    /// Update internal state from Kodi
+    fn update_state(&self) {
+        Self::update_state_static(&self.state, &self.base);
+    }
+
+    /// Start polling on the global runtime and the notification listener
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for Kodi");
+            return;
+        }
+
+        info!("Starting polling for Kodi at {} with interval {:?}",
+              self.state.client.base_url(), self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        match self.tcp_port {
+            Some(tcp_port) => {
+                let update_trigger = self.state.update_trigger.clone();
+                let queue_changed = Arc::clone(&self.state.queue_changed);
+                notifications::start_listener(&self.host, tcp_port, Arc::clone(&self.should_poll), move |method| {
+                    debug!("Kodi notification {}", method);
+                    if method.starts_with("Playlist.") {
+                        queue_changed.store(true, Ordering::Relaxed);
+                    }
+                    update_trigger.trigger();
+                });
+            }
+            // Without notifications the playlist is re-read on every poll
+            None => self.state.queue_changed.store(true, Ordering::Relaxed),
+        }
+
+        let state = self.state.clone();
+        let poll_interval = self.poll_interval;
+        let poll_queue = self.tcp_port.is_none();
+        let base = self.base.clone();
+        let trigger = self.state.update_trigger.clone();
+
+        let task = PollTask::spawn_blocking("Kodi", poll_interval, Some(trigger), move || {
+            if poll_queue {
+                state.queue_changed.store(true, Ordering::Relaxed);
+            }
+            Self::update_state_static(&state, &base);
+        });
+
+        *self.poll_task.write() = Some(task);
+    }
+
+    /// Stop polling, the notification listener ends by itself
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for Kodi");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
+    }
+
+    fn queue_command(&self, command: &PlayerCommand) -> Result<(), String> {
+        let state = &self.state;
+        match command {
+            PlayerCommand::QueueTracks { uris, insert_at_beginning, insert_after_current, .. } => {
+                let position = if *insert_after_current {
+                    state.current_state.read().state.ne(&PlaybackState::Stopped)
+                        .then(|| self.current_position())
+                        .flatten()
+                        .map(|p| p + 1)
+                } else if *insert_at_beginning {
+                    Some(0)
+                } else {
+                    None
+                };
+                for (i, uri) in uris.iter().enumerate() {
+                    match position {
+                        Some(position) => state.playlist_call("Playlist.Insert", json!({ "position": position + i, "item": file_item(uri) }))?,
+                        None => state.playlist_call("Playlist.Add", json!({ "item": file_item(uri) }))?,
+                    }
+                }
+                Ok(())
+            }
+            PlayerCommand::PlayNow { uris, start_index, .. } => {
+                // Play in the music playlist, even if a video was playing before
+                *state.playlist_id.write() = AUDIO_PLAYLIST;
+                state.playlist_call("Playlist.Clear", json!({}))?;
+                let items: Vec<serde_json::Value> = uris.iter().map(|uri| file_item(uri)).collect();
+                state.playlist_call("Playlist.Add", json!({ "item": items }))?;
+                let position = if *start_index < uris.len() { *start_index } else { 0 };
+                state.client.call("Player.Open", json!({ "item": { "playlistid": AUDIO_PLAYLIST, "position": position } })).map(|_| ())
+            }
+            PlayerCommand::RemoveTrack(index) => state.playlist_call("Playlist.Remove", json!({ "position": index })),
+            PlayerCommand::MoveTrack { from, to } => {
+                // Kodi can only swap two items, move step by step
+                for (a, b) in swap_steps(*from, *to) {
+                    state.playlist_call("Playlist.Swap", json!({ "position1": a, "position2": b }))?;
+                }
+                Ok(())
+            }
+            PlayerCommand::ClearQueue => state.playlist_call("Playlist.Clear", json!({})),
+            PlayerCommand::PlayQueueIndex(index) => {
+                let playlist_id = *state.playlist_id.read();
+                state.client.call("Player.Open", json!({ "item": { "playlistid": playlist_id, "position": index } })).map(|_| ())
+            }
+            _ => Err(format!("Not a queue command: {}", command)),
+        }
+    }
+
+    /// Position of the current item in the playlist
+    fn current_position(&self) -> Option<usize> {
+        let player_id = *self.state.player_id.read();
+        player_id
+            .and_then(|id| self.state.client.player_properties(id).ok())
+            .and_then(|p| usize::try_from(p.position).ok())
+    }
+}
+
+impl PlayerController for KodiController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, serde_json::Value>> {
+        let mut metadata = HashMap::new();
+        metadata.insert("url".to_string(), json!(self.state.client.base_url()));
+        metadata.insert("notifications".to_string(), json!(self.tcp_port.is_some()));
+        let current = self.state.current_state.read();
+        if let Some(volume) = current.volume {
+            metadata.insert("volume".to_string(), json!(volume));
+            metadata.insert("muted".to_string(), json!(current.muted));
+        }
+        Some(metadata)
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.state.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.state.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        self.state.queue.read().iter().map(KodiItem::to_track).collect()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.state.current_state.read().shuffle
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        self.state.current_state.read().loop_mode
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.state.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to Kodi: {}", command);
+
+        let result = match &command {
+            PlayerCommand::Play => self.state.player_call("Player.PlayPause", json!({ "play": true })),
+            PlayerCommand::Pause => self.state.player_call("Player.PlayPause", json!({ "play": false })),
+            PlayerCommand::PlayPause => self.state.player_call("Player.PlayPause", json!({ "play": "toggle" })),
+            PlayerCommand::Stop => self.state.player_call("Player.Stop", json!({})),
+            PlayerCommand::Next => self.state.player_call("Player.GoTo", json!({ "to": "next" })),
+            PlayerCommand::Previous => self.state.player_call("Player.GoTo", json!({ "to": "previous" })),
+            PlayerCommand::Seek(position) => self.state
+                .player_call("Player.Seek", json!({ "value": { "time": KodiTime::from_seconds(*position).to_json() } })),
+            PlayerCommand::SetRandom(enabled) => self.state.player_call("Player.SetShuffle", json!({ "shuffle": enabled })),
+            PlayerCommand::SetLoopMode(mode) => self.state.player_call("Player.SetRepeat", json!({ "repeat": repeat_mode(*mode) })),
+            PlayerCommand::QueueTracks { .. }
+            | PlayerCommand::PlayNow { .. }
+            | PlayerCommand::RemoveTrack(_)
+            | PlayerCommand::MoveTrack { .. }
+            | PlayerCommand::ClearQueue
+            | PlayerCommand::PlayQueueIndex(_) => self.queue_command(&command),
+            _ => {
+                warn!("Command not supported by Kodi: {}", command);
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to Kodi: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting Kodi controller for {}", self.state.client.base_url());
+        // Kodi may not be running yet, polling picks it up when it becomes reachable
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping Kodi controller");
+        self.stop_polling();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let controller = KodiController::from_config(&json!({
+            "host": "192.168.1.50",
+            "notifications": false,
+            "poll_interval": 2.0
+        })).unwrap();
+        assert_eq!(controller.get_player_name(), "kodi");
+        assert_eq!(controller.get_player_id(), "192.168.1.50:8080");
+        assert_eq!(controller.tcp_port, None);
+        assert_eq!(controller.poll_interval, Duration::from_secs(2));
+        assert_eq!(repeat_mode(LoopMode::Track), "one");
+
+        assert!(KodiController::from_config(&json!({ "port": 8080 })).is_err());
+        assert!(KodiController::from_config(&json!({ "host": "kodi", "port": 70000 })).is_err());
+    }
+
+    #[test]
+    fn test_swap_steps() {
+        assert_eq!(swap_steps(1, 3), vec![(1, 2), (2, 3)]);
+        assert_eq!(swap_steps(3, 1), vec![(3, 2), (2, 1)]);
+        assert!(swap_steps(2, 2).is_empty());
+    }
+}
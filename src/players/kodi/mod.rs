@@ -0,0 +1,6 @@
+pub mod client;
+pub mod controller;
+pub mod notifications;
+
+pub use client::KodiClient;
+pub use controller::KodiController;
@@ -0,0 +1,97 @@
+use std::io::{ErrorKind, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use log::{debug, info};
+use serde_json::Value;
+use crate::helpers::netaddr;
+
+/// Default port of Kodi's JSON-RPC TCP interface
+pub const DEFAULT_TCP_PORT: u16 = 9090;
+
+/// Wait time before reconnecting after the connection failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Read notifications from a stream of JSON-RPC messages until it ends
+///
+/// Kodi sends the JSON objects back to back without separators. `on_notification`
+/// gets the method name, e.g. "Player.OnPlay". Returns Ok(()) when `running` is
+/// cleared, which is checked whenever a read times out.
+pub fn read_notifications<R: Read>(
+    reader: R,
+    running: &AtomicBool,
+    mut on_notification: impl FnMut(&str),
+) -> std::io::Result<()> {
+    let mut reader = reader;
+    'connection: while running.load(Ordering::Relaxed) {
+        for message in serde_json::Deserializer::from_reader(&mut reader).into_iter::<Value>() {
+            match message {
+                Ok(message) => {
+                    if let Some(method) = message.get("method").and_then(|m| m.as_str()) {
+                        on_notification(method);
+                    }
+                }
+                Err(e) if e.is_io() => {
+                    let error = std::io::Error::from(e);
+                    if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) {
+                        // Start a new deserializer after checking the running flag
+                        continue 'connection;
+                    }
+                    return Err(error);
+                }
+                Err(e) if e.is_eof() => return Err(std::io::Error::new(ErrorKind::UnexpectedEof, e)),
+                Err(e) => return Err(std::io::Error::new(ErrorKind::InvalidData, e)),
+            }
+        }
+        return Err(std::io::Error::new(ErrorKind::UnexpectedEof, "connection closed"));
+    }
+    Ok(())
+}
+
+/// Listen for notifications in a background thread, reconnecting if the connection fails
+///
+/// The thread ends within a few seconds after `running` has been cleared.
+pub fn start_listener(host: &str, port: u16, running: Arc<AtomicBool>, on_notification: impl FnMut(&str) + Send + 'static) {
+    let host = host.to_string();
+    let mut on_notification = on_notification;
+    thread::spawn(move || {
+        while running.load(Ordering::Relaxed) {
+            match netaddr::connect_with_timeout(&host, port, Some(Duration::from_secs(5))) {
+                Ok(stream) => {
+                    info!("Receiving Kodi notifications from {}", netaddr::host_port(&host, port));
+                    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                    if let Err(e) = read_notifications(stream, &running, &mut on_notification) {
+                        debug!("Kodi notification connection closed: {}", e);
+                    }
+                }
+                Err(e) => debug!("Can't connect to Kodi at {}: {}", netaddr::host_port(&host, port), e),
+            }
+            // Sleep in short steps to notice a stop request
+            for _ in 0..RECONNECT_DELAY.as_secs() {
+                if !running.load(Ordering::Relaxed) {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+        debug!("Kodi notification listener stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_notifications() {
+        let data = r#"{"jsonrpc":"2.0","method":"Player.OnPlay","params":{"data":{},"sender":"xbmc"}}{"jsonrpc":"2.0","method":"Playlist.OnAdd","params":{}}
+            {"jsonrpc":"2.0","id":1,"result":"OK"}"#;
+        let running = AtomicBool::new(true);
+        let mut methods = Vec::new();
+        let result = read_notifications(data.as_bytes(), &running, |m| methods.push(m.to_string()));
+        assert_eq!(methods, vec!["Player.OnPlay", "Playlist.OnAdd"]);
+        // The end of the stream is an error, the listener reconnects
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,416 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+use log::{debug, info, warn};
+use parking_lot::{Mutex, RwLock};
+
+use crate::audiocontrol::AudioController;
+use crate::data::{LoopMode, PlaybackState, PlayerCapability, PlayerCapabilitySet, PlayerCommand, Song, Track};
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::players::poll_task::PollTask;
+use crate::players::lms::jsonrps::{LmsRpcClient, Player};
+use crate::players::lms::lmsaudio::LMSAudioController;
+use crate::players::lms::lmspplayer::LMSPlayer;
+use crate::players::lms::cli_listener::{LMSListener, AudioControllerRef};
+
+/// Prefix of the player ids of child players, followed by the MAC address
+pub const CHILD_ID_PREFIX: &str = "lms:";
+
+/// Interval between two checks of the player list if reconnection is disabled
+const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Player id of the child controller of an LMS player
+pub fn child_id(playerid: &str) -> String {
+    format!("{}{}", CHILD_ID_PREFIX, playerid.to_lowercase())
+}
+
+/// Map the power and mode reported by LMS to a playback state
+fn playback_state(power: u8, mode: &str) -> PlaybackState {
+    if power == 0 {
+        return PlaybackState::Disconnected;
+    }
+    match mode {
+        "play" => PlaybackState::Playing,
+        "pause" => PlaybackState::Paused,
+        "stop" | "" => PlaybackState::Stopped,
+        _ => PlaybackState::Unknown,
+    }
+}
+
+/// Controller for another player of the LMS server the main LMS controller is connected to
+///
+/// Child players are created by the main controller if `child_players` is enabled. Each one
+/// controls a single player of the server and reports its own now-playing information.
+/// Queue editing and the library are only available through the main controller.
+pub struct LMSChildPlayer {
+    base: BasePlayerController,
+
+    /// Player object for interacting with the LMS server
+    player: LMSPlayer,
+
+    /// Server address the player is connected to
+    server: String,
+
+    /// CLI listener for receiving real-time events of this player
+    cli_listener: Arc<RwLock<Option<LMSListener>>>,
+
+    /// Strong reference that keeps the controller alive while the listener is active
+    controller_ref: Arc<RwLock<Option<Arc<dyn AudioControllerRef>>>>,
+}
+
+impl Clone for LMSChildPlayer {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            player: self.player.clone(),
+            server: self.server.clone(),
+            cli_listener: self.cli_listener.clone(),
+            controller_ref: self.controller_ref.clone(),
+        }
+    }
+}
+
+impl LMSChildPlayer {
+    /// Create a controller for a player of an LMS server
+    pub fn new(client: LmsRpcClient, server: &str, player: &Player) -> Self {
+        let base = BasePlayerController::with_player_info(&player.name, &child_id(&player.playerid));
+        base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::PlayPause,
+            PlayerCapability::Stop,
+            PlayerCapability::Next,
+            PlayerCapability::Previous,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Metadata,
+            PlayerCapability::Length,
+        ], false);
+
+        Self {
+            base,
+            player: LMSPlayer::new(client, &player.playerid),
+            server: server.to_string(),
+            cli_listener: Arc::new(RwLock::new(None)),
+            controller_ref: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn notify_song(&self) {
+        self.base.notify_song_changed(self.get_song().as_ref());
+    }
+
+    fn notify_position(&self) {
+        if let Some(position) = self.get_position() {
+            self.base.notify_position_changed(position);
+        }
+    }
+
+    /// Run a command, log and return false if it fails
+    fn run(&self, command: &PlayerCommand, result: Result<(), String>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to send {} to LMS player {}: {}", command, self.base.get_player_name(), e);
+                false
+            }
+        }
+    }
+}
+
+impl PlayerController for LMSChildPlayer {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.player.get_current_song()
+    }
+
+    fn get_stream_details(&self) -> Option<crate::data::stream_details::StreamDetails> {
+        self.player.get_stream_details()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        self.player.get_queue().unwrap_or_else(|e| {
+            warn!("Failed to get queue of LMS player {}: {}", self.base.get_player_name(), e);
+            Vec::new()
+        })
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        match self.player.get_repeat() {
+            Ok(1) => LoopMode::Track,
+            Ok(2) => LoopMode::Playlist,
+            _ => LoopMode::None,
+        }
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        match self.player.get_client().get_player_status(self.player.get_player_id()) {
+            Ok(status) => playback_state(status.power, &status.mode),
+            Err(e) => {
+                debug!("Failed to get status of LMS player {}: {}", self.base.get_player_name(), e);
+                PlaybackState::Unknown
+            }
+        }
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.player.get_current_position().ok().map(|pos| pos as f64)
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.player.get_shuffle().is_ok_and(|mode| mode > 0)
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![self.player.get_player_id().to_string()]
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        debug!("Sending {} to LMS player {}", command, self.base.get_player_name());
+        match command {
+            PlayerCommand::Play => self.run(&command, self.player.play(None)),
+            PlayerCommand::Pause => self.run(&command, self.player.pause(Some(true), None, None)),
+            PlayerCommand::PlayPause => self.run(&command, self.player.pause(None, None, None)),
+            PlayerCommand::Stop => self.run(&command, self.player.stop()),
+            PlayerCommand::Next | PlayerCommand::Previous => {
+                let result = match command {
+                    PlayerCommand::Next => self.player.next(),
+                    _ => self.player.previous(),
+                };
+                let sent = self.run(&command, result);
+                if sent {
+                    self.notify_song();
+                    self.notify_position();
+                }
+                sent
+            }
+            PlayerCommand::Seek(position) => {
+                let sent = self.run(&command, self.player.seek(position as f32));
+                if sent {
+                    self.notify_position();
+                }
+                sent
+            }
+            PlayerCommand::SetRandom(enabled) => {
+                let sent = self.run(&command, self.player.set_shuffle(u8::from(enabled)));
+                if sent {
+                    self.base.notify_random_changed(enabled);
+                }
+                sent
+            }
+            PlayerCommand::SetLoopMode(mode) => {
+                let repeat = match mode {
+                    LoopMode::None => 0,
+                    LoopMode::Track => 1,
+                    LoopMode::Playlist => 2,
+                };
+                let sent = self.run(&command, self.player.set_repeat(repeat));
+                if sent {
+                    self.base.notify_loop_mode_changed(mode);
+                }
+                sent
+            }
+            PlayerCommand::PlayQueueIndex(index) => {
+                let sent = self.run(&command, self.player.play_queue_index(index));
+                if sent {
+                    self.notify_song();
+                }
+                sent
+            }
+            _ => {
+                debug!("Command {} is not supported by LMS child players", command);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        let controller_arc: Arc<dyn AudioControllerRef> = Arc::new(self.clone());
+        let mut listener = LMSListener::new(&self.server, self.player.get_player_id(), Arc::downgrade(&controller_arc));
+        listener.start();
+        *self.cli_listener.write() = Some(listener);
+        *self.controller_ref.write() = Some(controller_arc);
+        debug!("Started LMS child player {}", self.base.get_player_name());
+        true
+    }
+
+    fn stop(&self) -> bool {
+        if let Some(mut listener) = self.cli_listener.write().take() {
+            listener.stop();
+        }
+        *self.controller_ref.write() = None;
+        true
+    }
+}
+
+impl AudioControllerRef for LMSChildPlayer {
+    fn seen(&self) {
+        self.base.alive();
+    }
+
+    fn state_changed(&self, state: PlaybackState) {
+        self.seen();
+        self.base.notify_state_changed(state);
+    }
+
+    fn update_song(&self) {
+        self.notify_song();
+    }
+
+    fn update_position(&self) {
+        self.notify_position();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Player ids to add and to remove so that the registered children match the connected players
+///
+/// `own_id` is the player of the main controller, it never gets a child.
+fn plan_sync(players: &[Player], own_id: Option<&str>, registered: &HashSet<String>) -> (Vec<String>, Vec<String>) {
+    let wanted: HashSet<String> = players
+        .iter()
+        .filter(|p| p.is_connected != 0)
+        .filter(|p| own_id.is_none_or(|own| !own.eq_ignore_ascii_case(&p.playerid)))
+        .map(|p| child_id(&p.playerid))
+        .collect();
+    let mut add: Vec<String> = wanted.difference(registered).cloned().collect();
+    let mut remove: Vec<String> = registered.difference(&wanted).cloned().collect();
+    add.sort();
+    remove.sort();
+    (add, remove)
+}
+
+/// Register child players for every LMS controller with `child_players` enabled
+pub fn initialize(controller: Weak<AudioController>) {
+    let Some(audio_controller) = controller.upgrade() else {
+        return;
+    };
+    for ctrl_lock in audio_controller.list_controllers() {
+        let ctrl = ctrl_lock.read();
+        let Some(lms) = ctrl.as_any().downcast_ref::<LMSAudioController>() else {
+            continue;
+        };
+        if !lms.child_players_enabled() {
+            continue;
+        }
+        info!("Registering the other players of the LMS server as child players");
+        let interval = lms.sync_interval().unwrap_or(DEFAULT_SYNC_INTERVAL);
+        let registered = Mutex::new(HashSet::new());
+        let task_lms = lms.clone();
+        let controller = controller.clone();
+        let task = PollTask::spawn_blocking("LMS child players", interval, None, move || {
+            let mut registered = registered.lock();
+            match controller.upgrade() {
+                Some(audio_controller) if task_lms.is_running() => {
+                    sync(&audio_controller, &task_lms, &mut registered)
+                }
+                Some(audio_controller) => remove_all(&audio_controller, &mut registered),
+                None => registered.clear(),
+            }
+        });
+        lms.set_child_sync_task(task);
+    }
+}
+
+/// Add controllers for new players of the server and remove those of players that left
+fn sync(controller: &AudioController, lms: &LMSAudioController, registered: &mut HashSet<String>) {
+    let Some((server, port)) = lms.connected_server() else {
+        remove_all(controller, registered);
+        return;
+    };
+    let client = LmsRpcClient::new(&server, port);
+    let players = match client.get_players() {
+        Ok(players) => players,
+        Err(e) => {
+            warn!("Failed to list the players of LMS server {}: {}", server, e);
+            return;
+        }
+    };
+
+    let own_id = lms.connected_player_id();
+    let (add, remove) = plan_sync(&players, own_id.as_deref(), registered);
+    for id in remove {
+        registered.remove(&id);
+        if let Some(child) = controller.remove_controller_by_id(&id) {
+            child.read().stop();
+            info!("Removed LMS child player {}", id);
+        }
+    }
+    for player in players.iter().filter(|p| add.contains(&child_id(&p.playerid))) {
+        let child = LMSChildPlayer::new(client.clone(), &server, player);
+        child.start();
+        controller.add_controller(Box::new(child));
+        registered.insert(child_id(&player.playerid));
+        info!("Registered LMS child player {} ({})", player.name, player.playerid);
+    }
+}
+
+fn remove_all(controller: &AudioController, registered: &mut HashSet<String>) {
+    for id in registered.drain() {
+        if let Some(child) = controller.remove_controller_by_id(&id) {
+            child.read().stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: &str, connected: u8) -> Player {
+        Player {
+            playerid: id.to_string(),
+            name: id.to_string(),
+            ip: String::new(),
+            model: String::new(),
+            is_connected: connected,
+            power: 1,
+        }
+    }
+
+    #[test]
+    fn test_plan_sync() {
+        let players = vec![
+            player("00:04:20:aa:bb:01", 1),
+            player("00:04:20:AA:BB:02", 1),
+            player("00:04:20:aa:bb:03", 0),
+        ];
+        let registered: HashSet<String> = [child_id("00:04:20:aa:bb:03"), child_id("00:04:20:aa:bb:04")].into();
+
+        let (add, remove) = plan_sync(&players, Some("00:04:20:AA:BB:01"), &registered);
+        assert_eq!(add, vec!["lms:00:04:20:aa:bb:02"]);
+        assert_eq!(remove, vec!["lms:00:04:20:aa:bb:03", "lms:00:04:20:aa:bb:04"]);
+    }
+
+    #[test]
+    fn test_playback_state() {
+        assert_eq!(playback_state(0, "play"), PlaybackState::Disconnected);
+        assert_eq!(playback_state(1, "play"), PlaybackState::Playing);
+        assert_eq!(playback_state(1, ""), PlaybackState::Stopped);
+    }
+}
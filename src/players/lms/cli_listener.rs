@@ -8,6 +8,7 @@ use log::{warn, debug, error, trace, info};
 use urlencoding::decode;
 
 use crate::data::PlaybackState;
+use crate::helpers::netaddr;
 
 // Forward declaration to avoid circular dependency
 type WeakAudioController = Weak<dyn AudioControllerRef>;
@@ -80,6 +81,9 @@ pub struct LMSListener {
     
     /// Last time displaynotify was processed (to avoid duplicate events)
     last_display_notify: Arc<RwLock<Option<SystemTime>>>,
+
+    /// Socket of the current CLI connection
+    connection: Arc<RwLock<Option<TcpStream>>>,
 }
 
 impl LMSListener {
@@ -97,6 +101,7 @@ impl LMSListener {
             thread_handle: None,
             controller,
             last_display_notify: Arc::new(RwLock::new(None)),
+            connection: Arc::new(RwLock::new(None)),
         }
     }
     
@@ -114,11 +119,12 @@ impl LMSListener {
         let running = self.running.clone();
         let controller = self.controller.clone();
         let last_display_notify = self.last_display_notify.clone();
+        let connection = self.connection.clone();
         
         self.thread_handle = Some(thread::spawn(move || {
             // Main connection loop - try to reconnect if connection fails
             while running.load(Ordering::SeqCst) {
-                match Self::connect_and_listen(&server, &player_id, running.clone(), controller.clone(), last_display_notify.clone()) {
+                match Self::connect_and_listen(&server, &player_id, running.clone(), controller.clone(), last_display_notify.clone(), connection.clone()) {
                     Ok(_) => {
                         // Connection closed normally, try to reconnect after a delay
                         if running.load(Ordering::SeqCst) {
@@ -190,16 +196,18 @@ impl LMSListener {
     }
     
     /// Connect to the server and listen for messages
-    fn connect_and_listen(server: &str, player_id: &str, running: Arc<AtomicBool>, controller: WeakAudioController, last_display_notify: Arc<RwLock<Option<SystemTime>>>) -> Result<(), String> {
+    fn connect_and_listen(server: &str, player_id: &str, running: Arc<AtomicBool>, controller: WeakAudioController, last_display_notify: Arc<RwLock<Option<SystemTime>>>, connection: Arc<RwLock<Option<TcpStream>>>) -> Result<(), String> {
         // Connect to the LMS CLI on port 9090
-        let address = format!("{}:9090", server);
+        let address = netaddr::host_port(server, 9090);
         debug!("Connecting to LMS CLI at {}", address);
         
-        let stream = match TcpStream::connect(&address) {
+        let stream = match netaddr::connect(server, 9090) {
             Ok(s) => s,
             Err(e) => return Err(format!("Failed to connect to LMS CLI: {}", e)),
         };
         
+        *connection.write() = stream.try_clone().ok();
+
         // Set read timeout to allow checking the running flag periodically
         if let Err(e) = stream.set_read_timeout(Some(Duration::from_secs(1))) {
             return Err(format!("Failed to set read timeout: {}", e));
@@ -689,6 +697,17 @@ impl LMSListener {
         Ok(())
     }
     
+    /// Close the current connection, the listener thread connects again
+    ///
+    /// Used after network changes, a connection through a dropped Wi-Fi or a rebooted
+    /// router would otherwise stay silent until the TCP timeout.
+    pub fn reconnect(&self) {
+        if let Some(stream) = self.connection.write().take() {
+            info!("Reconnecting to LMS CLI at {}", self.server_address);
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
     /// Stop the listener thread
     pub fn stop(&mut self) {
         self.running.store(false, Ordering::SeqCst);
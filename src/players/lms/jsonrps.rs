@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize, Deserializer};
 use serde_json::Value;
 use log::{debug, error};
 use crate::helpers::macaddress::normalize_mac_address;
+use crate::helpers::netaddr;
 use crate::helpers::http_client::{HttpClient, HttpClientError, new_http_client, post_json};
 use crate::data::stream_details::StreamDetails;
 use std::sync::Arc;
@@ -87,7 +88,7 @@ impl LmsRpcClient {
     /// * `host` - Hostname or IP address of the LMS server
     /// * `port` - HTTP port of the LMS server (typically 9000)
     pub fn new(host: &str, port: u16) -> Self {
-        let base_url = format!("http://{}:{}", host, port);
+        let base_url = format!("http://{}:{}", netaddr::url_host(host), port);
         let client = Arc::from(new_http_client(DEFAULT_TIMEOUT_SECS)); // Wrapped in Arc
             
         LmsRpcClient {
@@ -327,11 +328,12 @@ impl LmsRpcClient {
     /// The server address as a String if it can be extracted
     pub fn get_server_address(&self) -> Result<String, LmsRpcError> {
         // Parse the base URL to extract the server address
+        // rfind, IPv6 literals contain colons themselves
         if let Some(stripped) = self.base_url.strip_prefix("http://") {
-            if let Some(index) = stripped.find(':') {
-                return Ok(stripped[..index].to_string());
+            if let Some(index) = stripped.rfind(':').filter(|&i| !stripped[i..].contains(']')) {
+                return Ok(netaddr::strip_brackets(&stripped[..index]).to_string());
             }
-            return Ok(stripped.to_string());
+            return Ok(netaddr::strip_brackets(stripped).to_string());
         }
         
         Err(LmsRpcError::ParseError("Could not extract server address from base URL".to_string()))
@@ -344,7 +346,7 @@ impl LmsRpcClient {
     pub fn get_server_port(&self) -> u16 {
         // Parse the base URL to extract the port
         if let Some(stripped) = self.base_url.strip_prefix("http://") {
-            if let Some(index) = stripped.find(':') {
+            if let Some(index) = stripped.rfind(':') {
                 if let Some(port_str) = stripped.get((index + 1)..) {
                     if let Ok(port) = port_str.parse::<u16>() {
                         return port;
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use parking_lot::{Mutex, RwLock};
 use std::time::Instant;
 use log::{debug, info, warn, error};
-use crate::data::{Album, AlbumArtists, Artist, LibraryError, LibraryInterface};
+use crate::data::{Album, AlbumArtists, Artist, LibraryDiff, LibraryError, LibraryInterface, LibrarySnapshot, PlayerEvent, PlayerSource};
 use crate::helpers::http_client;
 use crate::players::lms::jsonrps::LmsRpcClient;
 use crate::players::lms::lmsaudio::lms_image_url;
@@ -25,6 +25,9 @@ pub struct LMSLibrary {
     
     /// Flag indicating if library is loaded
     library_loaded: Arc<Mutex<bool>>,
+
+    /// Albums added, removed and changed by the last refresh
+    last_diff: Arc<Mutex<Option<LibraryDiff>>>,
     
     /// Library loading progress (0.0 - 1.0)
     loading_progress: Arc<Mutex<f32>>,
@@ -50,6 +53,7 @@ impl LMSLibrary {
             artists: Arc::new(RwLock::new(HashMap::new())),
             album_artists: Arc::new(RwLock::new(AlbumArtists::new())),
             library_loaded: Arc::new(Mutex::new(false)),
+            last_diff: Arc::new(Mutex::new(None)),
             loading_progress: Arc::new(Mutex::new(0.0)),
             artist_separators: Arc::new(Mutex::new(None)),
             enhance_metadata: true,
@@ -324,7 +328,7 @@ impl LMSLibrary {
         };
         
         // Construct and return the URL
-        format!("http://{}:{}/music/{}/cover.jpg", server_addr, port, id)
+        format!("http://{}:{}/music/{}/cover.jpg", crate::helpers::netaddr::url_host(&server_addr), port, id)
     }
 }
 
@@ -353,6 +357,10 @@ impl LibraryInterface for LMSLibrary {
         
         let result = match loader.load_albums_from_lms(artist_separators) {
             Ok(albums) => {
+                // Remember the albums of a loaded library to report the changes
+                let previous = self.is_loaded()
+                    .then(|| LibrarySnapshot::from_albums(self.albums.read().values()));
+
                 // Mark as not loaded during update
                 { let mut loaded = self.library_loaded.lock(); *loaded = false; }
 
@@ -383,11 +391,25 @@ impl LibraryInterface for LMSLibrary {
                     *loaded = true;
                     info!("Setting library_loaded flag to true");
                 }
+                crate::helpers::library_search::index_library("lms", self.albums.read().values(), self.artists.read().values());
 
                 { let mut progress = self.loading_progress.lock(); *progress = 1.0; }
                 
                 let total_time = start_time.elapsed();
                 info!("Library load complete in {:.2?}", total_time);
+
+                if let Some(previous) = previous {
+                    let diff = LibraryDiff::between(&previous, &LibrarySnapshot::from_albums(self.albums.read().values()));
+                    info!("Library refresh added {} albums, removed {}, changed {}",
+                        diff.added.len(), diff.removed.len(), diff.changed.len());
+                    if !diff.is_empty() {
+                        crate::audiocontrol::eventbus::EventBus::instance().publish(PlayerEvent::LibraryChanged {
+                            source: PlayerSource::new("lms".to_string(), "lms".to_string()),
+                            diff: diff.clone(),
+                        });
+                    }
+                    *self.last_diff.lock() = Some(diff);
+                }
                 
                 // Start background update of artist metadata now that the library is fully loaded
                 if self.enhance_metadata {
@@ -408,6 +430,10 @@ impl LibraryInterface for LMSLibrary {
         result
     }
     
+    fn get_last_diff(&self) -> Option<LibraryDiff> {
+        self.last_diff.lock().clone()
+    }
+
     fn get_albums(&self) -> Vec<Album> {
         warn!("Retrieving all albums from LMSLibrary");
         let albums = self.albums.read();
@@ -10,6 +10,7 @@ use serde_json::Value;
 use crate::data::{LoopMode, PlaybackState, PlayerCapabilitySet, PlayerCapability, PlayerCommand, Song, Track};
 use crate::data::library::LibraryInterface;
 use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::players::poll_task::PollTask;
 use crate::players::lms::jsonrps::LmsRpcClient;
 use crate::players::lms::lmsserver::{get_local_mac_addresses};
 use crate::players::lms::lmspplayer::LMSPlayer;
@@ -50,6 +51,14 @@ pub struct LMSAudioConfig {
     /// Enable library features
     #[serde(default = "default_true")]
     pub enable_library: bool,
+
+    /// Network interface for the connections to the server on multi-homed devices
+    #[serde(default)]
+    pub bind_interface: Option<String>,
+
+    /// Register a controller for every other player of the server
+    #[serde(default)]
+    pub child_players: bool,
 }
 
 /// Default LMS server port
@@ -77,6 +86,8 @@ impl Default for LMSAudioConfig {
             player_macs: Vec::new(),
             reconnection_interval: default_reconnection_interval(),
             enable_library: true,
+            bind_interface: None,
+            child_players: false,
         }
     }
 }
@@ -98,8 +109,14 @@ pub struct LMSAudioController {
     /// Last known connection state
     is_connected: Arc<AtomicBool>,
     
-    /// Flag to control the reconnection thread
+    /// Whether the controller is started
     running: Arc<AtomicBool>,
+
+    /// Reconnection checks on the global runtime
+    reconnection_task: Arc<RwLock<Option<PollTask>>>,
+
+    /// Synchronization of the child players, see `childplayer::initialize`
+    child_sync_task: Arc<RwLock<Option<PollTask>>>,
     
     /// Currently connected server address
     connected_server: Arc<RwLock<Option<String>>>,
@@ -223,6 +240,8 @@ impl LMSAudioController {
             player: Arc::new(RwLock::new(None)),
             is_connected,
             running,
+            reconnection_task: Arc::new(RwLock::new(None)),
+            child_sync_task: Arc::new(RwLock::new(None)),
             connected_server,
             cli_listener: Arc::new(RwLock::new(None)),
             controller_ref: Arc::new(RwLock::new(None)),
@@ -277,90 +296,109 @@ impl LMSAudioController {
         controller
     }
     
-    /// Start the reconnection thread
-    fn start_reconnection_thread(&self) {
+    /// Check the connection every `reconnection_interval` on the global runtime
+    fn start_reconnection_task(&self) {
         let config = self.config.read().clone();
         
-        // Don't start the reconnection thread if the interval is 0 (disabled)
+        // Don't start the reconnection checks if the interval is 0 (disabled)
         if config.reconnection_interval == 0 {
             info!("LMS reconnection is disabled (interval = 0)");
             return;
         }
+        if self.reconnection_task.read().is_some() {
+            debug!("LMS reconnection task already started");
+            return;
+        }
         
         let interval = Duration::from_secs(config.reconnection_interval);
         let is_connected = self.is_connected.clone();
-        let running = self.running.clone();
         let controller_config = self.config.clone();
         let base = self.base.clone();
         
         // Create a clone of the controller so we can use the find_server_connection method
         let controller = self.clone();
+        // start() has just tried to connect, the first check is due after one interval
+        let first = AtomicBool::new(true);
         
-        thread::spawn(move || {
-            info!("LMS reconnection thread started (interval: {} seconds)", config.reconnection_interval);
+        info!("LMS reconnection task started (interval: {} seconds)", config.reconnection_interval);
+        let task = PollTask::spawn_blocking("LMS reconnection", interval, None, move || {
+            if first.swap(false, Ordering::SeqCst) {
+                return;
+            }
+
+            // Get the current connection state
+            let was_connected = is_connected.load(Ordering::SeqCst);
             
-            while running.load(Ordering::SeqCst) {
-                // Sleep for the configured interval
-                thread::sleep(interval);
-                
-                if !running.load(Ordering::SeqCst) {
-                    break;
-                }
-                
-                // Get the current connection state
-                let was_connected = is_connected.load(Ordering::SeqCst);
-                
-                // Read the current configuration
-                let current_config = controller_config.read().clone();
-                
-                // Check connection status using find_server_connection
-                let (now_connected, found_server, matched_mac, _) = controller.find_server_connection(&current_config);
+            // Read the current configuration
+            let current_config = controller_config.read().clone();
+            
+            // Check connection status using find_server_connection
+            let (now_connected, found_server, matched_mac, _) = controller.find_server_connection(&current_config);
+            
+            // Update connection state if it changed
+            if was_connected != now_connected {
+                is_connected.store(now_connected, Ordering::SeqCst);
                 
-                // Update connection state if it changed
-                if was_connected != now_connected {
-                    is_connected.store(now_connected, Ordering::SeqCst);
+                if now_connected {
+                    info!("LMS connection established");
+                    base.notify_state_changed(PlaybackState::Stopped);
                     
-                    if now_connected {
-                        info!("LMS connection established");
-                        base.notify_state_changed(PlaybackState::Stopped);
-                        
-                        // Start the CLI listener if we have both server and player information
-                        if let (Some(server), Some(player_id)) = (found_server, matched_mac) {
-                            controller.start_cli_listener(&server, &player_id);
-                        }
-                    } else {
-                        info!("LMS connection lost");
-                        base.notify_state_changed(PlaybackState::Disconnected);
-                        
-                        // Stop the CLI listener when connection is lost
-                        controller.stop_cli_listener();
+                    // Start the CLI listener if we have both server and player information
+                    if let (Some(server), Some(player_id)) = (found_server, matched_mac) {
+                        controller.start_cli_listener(&server, &player_id);
                     }
+                } else {
+                    info!("LMS connection lost");
+                    base.notify_state_changed(PlaybackState::Disconnected);
+                    
+                    // Stop the CLI listener when connection is lost
+                    controller.stop_cli_listener();
                 }
-                
-                // If still disconnected, log an attempt with MAC addresses
-                if !now_connected {
-                    if !current_config.player_macs.is_empty() {
-                        // Check if "local" was in the original configuration
-                        let has_local = current_config.player_macs.iter().any(|m| m.to_lowercase() == "local");
-                        if has_local {
-                            info!("LMS player still disconnected (tested configured and local MAC addresses) - will retry in {} seconds", 
-                                  config.reconnection_interval);
-                        } else {
-                            info!("LMS player still disconnected (tested configured MAC addresses: {}) - will retry in {} seconds", 
-                                  current_config.player_macs.join(", "), config.reconnection_interval);
-                        }
+            }
+            
+            // If still disconnected, log an attempt with MAC addresses
+            if !now_connected {
+                if !current_config.player_macs.is_empty() {
+                    // Check if "local" was in the original configuration
+                    let has_local = current_config.player_macs.iter().any(|m| m.to_lowercase() == "local");
+                    if has_local {
+                        info!("LMS player still disconnected (tested configured and local MAC addresses) - will retry in {} seconds", 
+                              config.reconnection_interval);
                     } else {
-                        debug!("LMS player still disconnected, no MAC addresses available - will retry in {} seconds", 
-                               config.reconnection_interval);
+                        info!("LMS player still disconnected (tested configured MAC addresses: {}) - will retry in {} seconds", 
+                              current_config.player_macs.join(", "), config.reconnection_interval);
                     }
+                } else {
+                    debug!("LMS player still disconnected, no MAC addresses available - will retry in {} seconds", 
+                           config.reconnection_interval);
                 }
             }
-            
-            // Stop the CLI listener when stopping the reconnection thread
-            controller.stop_cli_listener();
-            
-            info!("LMS reconnection thread stopped");
         });
+        *self.reconnection_task.write() = Some(task);
+    }
+
+    /// Stop the reconnection checks and the CLI listener
+    fn stop_reconnection_task(&self) {
+        let task = self.reconnection_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+            info!("LMS reconnection task stopped");
+        }
+        self.stop_cli_listener();
+    }
+
+    /// Keep the child player synchronization, it is stopped together with the controller
+    pub fn set_child_sync_task(&self, task: PollTask) {
+        if let Some(previous) = self.child_sync_task.write().replace(task) {
+            previous.stop();
+        }
+    }
+
+    fn stop_child_sync_task(&self) {
+        let task = self.child_sync_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
     }
     
     /// Find a server that any of the configured MAC addresses is connected to
@@ -538,6 +576,10 @@ impl LMSAudioController {
         
         // First stop any existing listener
         self.stop_cli_listener();
+
+        if let Some(interface) = &self.config.read().bind_interface {
+            crate::helpers::netaddr::set_bind_interface(server, interface);
+        }
         
         // Create a strong reference to self that will be stored alongside the listener
         let controller_arc: Arc<dyn AudioControllerRef> = Arc::new(self.clone());
@@ -561,6 +603,16 @@ impl LMSAudioController {
 
         // Store the strong reference to the controller
         { let mut controller_ref_lock = self.controller_ref.write(); *controller_ref_lock = Some(controller_arc); }
+
+        // Reconnect right away after network changes
+        let listener_ref = Arc::downgrade(&self.cli_listener);
+        crate::helpers::network::register_reconnect_handler(&format!("lms:{}", player_id), move || {
+            if let Some(listener) = listener_ref.upgrade() {
+                if let Some(listener) = listener.read().as_ref() {
+                    listener.reconnect();
+                }
+            }
+        });
     }
     
     /// Stop the CLI listener if running
@@ -631,6 +683,36 @@ impl LMSAudioController {
         position
     }
 
+    /// Whether controllers for the other players of the server should be registered
+    pub fn child_players_enabled(&self) -> bool {
+        self.config.read().child_players
+    }
+
+    /// Whether the controller has not been stopped
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Interval of the connection checks, None if reconnection is disabled
+    pub fn sync_interval(&self) -> Option<Duration> {
+        let interval = self.config.read().reconnection_interval;
+        (interval > 0).then(|| Duration::from_secs(interval))
+    }
+
+    /// Address and port of the server, if connected
+    pub fn connected_server(&self) -> Option<(String, u16)> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return None;
+        }
+        let server = self.connected_server.read().clone()?;
+        Some((server, self.config.read().port))
+    }
+
+    /// Id (MAC address) of the player this controller controls
+    pub fn connected_player_id(&self) -> Option<String> {
+        self.player.read().as_ref().map(|p| p.get_player_id().to_string())
+    }
+
     /// Notify listeners about a random/shuffle mode change
     pub fn notify_random_mode(&self, enabled: bool) {
         self.base.notify_random_changed(enabled);
@@ -651,6 +733,8 @@ impl Clone for LMSAudioController {
             player: self.player.clone(),
             is_connected: self.is_connected.clone(),
             running: self.running.clone(),
+            reconnection_task: self.reconnection_task.clone(),
+            child_sync_task: self.child_sync_task.clone(),
             connected_server: self.connected_server.clone(),
             cli_listener: self.cli_listener.clone(),
             controller_ref: self.controller_ref.clone(),
@@ -660,6 +744,19 @@ impl Clone for LMSAudioController {
     }
 }
 
+/// Order in which queued tracks are sent to LMS
+///
+/// Tracks for "play next" are sent with the LMS insert command, which places
+/// each one directly after the current song, so they are sent in reverse to
+/// keep the requested order. Appended tracks keep their order.
+fn queue_order(uris: Vec<String>, insert_after_current: bool) -> Vec<String> {
+    if insert_after_current {
+        uris.into_iter().rev().collect()
+    } else {
+        uris
+    }
+}
+
 impl PlayerController for LMSAudioController {
     fn get_capabilities(&self) -> PlayerCapabilitySet {
         self.base.get_capabilities()
@@ -1073,6 +1170,21 @@ impl PlayerController for LMSAudioController {
                     }
                 }
             },
+            PlayerCommand::MoveTrack { from, to } => {
+                debug!("Moving track from index {} to {} in LMS player queue", from, to);
+                match player.move_in_playlist(from, to) {
+                    Ok(_) => {
+                        debug!("Move track command sent successfully");
+                        // Notify listeners that the queue has been modified
+                        self.base.notify_queue_changed();
+                        true
+                    },
+                    Err(e) => {
+                        warn!("Failed to move track from index {} to {}: {}", from, to, e);
+                        false
+                    }
+                }
+            },
             PlayerCommand::PlayQueueIndex(index) => {
                 warn!("Playing track at index {} from LMS player queue", index);
                 match player.play_queue_index(index) {
@@ -1089,10 +1201,10 @@ impl PlayerController for LMSAudioController {
                     }
                 }
             },
-            PlayerCommand::QueueTracks { uris, insert_at_beginning, metadata: _ } => {
+            PlayerCommand::QueueTracks { uris, insert_at_beginning, insert_after_current, metadata: _ } => {
                 debug!("Adding {} tracks to LMS player queue at {}", 
                       uris.len(), 
-                      if insert_at_beginning { "beginning" } else { "end" });
+                      if insert_after_current { "next" } else if insert_at_beginning { "beginning" } else { "end" });
                 if uris.is_empty() {
                     debug!("No URIs provided to queue");
                     // Nothing to do, but not an error
@@ -1100,6 +1212,9 @@ impl PlayerController for LMSAudioController {
                 }
                 
                 let mut all_success = true;
+
+                let insert = insert_at_beginning || insert_after_current;
+                let uris = queue_order(uris, insert_after_current);
                 
                 // Process each URI
                 for uri in uris {
@@ -1108,7 +1223,7 @@ impl PlayerController for LMSAudioController {
                     // Otherwise, it might be a file path or URL
                       if uri.trim().parse::<u64>().is_ok() {
                         // Looks like a numeric track ID, use add_to_queue method with track_id
-                        match player.add_to_queue(&uri, insert_at_beginning) {
+                        match player.add_to_queue(&uri, insert) {
                             Ok(_) => {
                                 debug!("Successfully added track ID {} to queue", uri);
                             },
@@ -1129,6 +1244,38 @@ impl PlayerController for LMSAudioController {
                 
                 all_success
             },
+            PlayerCommand::PlayNow { uris, metadata: _, start_index } => {
+                debug!("Replacing LMS player queue with {} tracks, starting at {}", uris.len(), start_index);
+
+                if uris.is_empty() || start_index >= uris.len() {
+                    warn!("Invalid play now request: {} tracks, start index {}", uris.len(), start_index);
+                    return false;
+                }
+
+                // Only numeric track IDs can be loaded into the LMS playlist
+                if let Some(uri) = uris.iter().find(|uri| uri.trim().parse::<u64>().is_err()) {
+                    warn!("URI-based track addition is not supported for LMS player: {}", uri);
+                    return false;
+                }
+                let track_ids: Vec<String> = uris.iter().map(|uri| uri.trim().to_string()).collect();
+
+                if let Err(e) = player.load_tracks(&track_ids) {
+                    warn!("Failed to load tracks into LMS playlist: {}", e);
+                    return false;
+                }
+                self.base.notify_queue_changed();
+
+                if start_index > 0 {
+                    if let Err(e) = player.play_queue_index(start_index) {
+                        warn!("Failed to play track at index {}: {}", start_index, e);
+                        return false;
+                    }
+                }
+
+                self.update_and_notify_song();
+                self.update_and_notify_position();
+                true
+            },
             // Other commands are not yet implemented
             _ => {
                 error!("Command {} not implemented for LMS player", command);
@@ -1215,8 +1362,8 @@ impl PlayerController for LMSAudioController {
             }
         }
         
-        // Start the reconnection thread
-        self.start_reconnection_thread();
+        // Start checking the connection in the background
+        self.start_reconnection_task();
         
         // Return true as the player controller started successfully,
         // even if the connection to LMS server failed
@@ -1224,9 +1371,11 @@ impl PlayerController for LMSAudioController {
     }
     
     fn stop(&self) -> bool {
-        // Stop the reconnection thread
+        // Stop the reconnection checks, waits for a check in progress
         self.running.store(false, Ordering::SeqCst);
-        info!("LMS player stopping, reconnection thread will terminate");
+        info!("LMS player stopping");
+        self.stop_reconnection_task();
+        self.stop_child_sync_task();
         
         // Not yet implemented - would perform any necessary cleanup
         true
@@ -1308,4 +1457,30 @@ impl AudioControllerRef for LMSAudioController {
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris() -> Vec<String> {
+        vec!["1".to_string(), "2".to_string(), "3".to_string()]
+    }
+
+    #[test]
+    fn test_queue_order_append() {
+        assert_eq!(queue_order(uris(), false), uris());
+    }
+
+    #[test]
+    fn test_queue_order_insert_at_beginning() {
+        // insert_at_beginning without play_next keeps the requested order
+        let insert_after_current = false;
+        assert_eq!(queue_order(uris(), insert_after_current), uris());
+    }
+
+    #[test]
+    fn test_queue_order_play_next() {
+        assert_eq!(queue_order(uris(), true), vec!["3", "2", "1"]);
+    }
+}
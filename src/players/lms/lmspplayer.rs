@@ -276,7 +276,7 @@ impl LMSPlayer {
             // Create thumbnail URL from track ID using the server address and port
             if let Ok(server_addr) = self.client.get_server_address() {
                 let port = self.client.get_server_port();
-                cover_art_url = Some(format!("http://{}:{}/music/{}/cover.jpg", server_addr, port, id));
+                cover_art_url = Some(format!("http://{}:{}/music/{}/cover.jpg", crate::helpers::netaddr::url_host(&server_addr), port, id));
                 debug!("Generated cover art URL from track ID: {:?}", cover_art_url);
             } else {
                 warn!("Could not get server address for thumbnail URL");
@@ -896,6 +896,61 @@ impl LMSPlayer {
         }
     }
 
+    /// Move a track within the current playlist
+    ///
+    /// # Arguments
+    /// * `from` - The zero-based index of the track to move
+    /// * `to` - The zero-based index the track is moved to
+    ///
+    /// # Returns
+    /// `Ok(())` if the command was sent successfully, or an error message
+    pub fn move_in_playlist(&self, from: usize, to: usize) -> Result<(), String> {
+        debug!("Moving track from index {} to {} in playlist for player {}", from, to, self.player_id);
+
+        let from_param = from.to_string();
+        let to_param = to.to_string();
+
+        match self.client.control_request(
+            &self.player_id,
+            "playlist",
+            vec!["move", &from_param, &to_param]
+        ) {
+            Ok(_) => {
+                debug!("Track moved from index {} to {} in playlist", from, to);
+                Ok(())
+            },
+            Err(e) => Err(format!("Failed to move track in playlist: {}", e)),
+        }
+    }
+
+    /// Replace the playlist with the given tracks and start playback
+    /// 
+    /// Uses the playlistcontrol command with cmd:load, which replaces the
+    /// current playlist and starts playing it in a single server-side operation.
+    /// 
+    /// # Arguments
+    /// * `track_ids` - The IDs of the tracks that make up the new playlist
+    /// 
+    /// # Returns
+    /// `Ok(())` if the command was sent successfully, or an error message
+    pub fn load_tracks(&self, track_ids: &[String]) -> Result<(), String> {
+        debug!("Loading {} tracks into playlist for player {}", track_ids.len(), self.player_id);
+
+        let track_param = format!("track_id:{}", track_ids.join(","));
+
+        match self.client.control_request(
+            &self.player_id,
+            "playlistcontrol",
+            vec!["cmd:load", &track_param]
+        ) {
+            Ok(_) => {
+                debug!("Tracks loaded successfully into playlist");
+                Ok(())
+            },
+            Err(e) => Err(format!("Failed to load tracks into playlist: {}", e)),
+        }
+    }
+
     /// Play a specific song in the queue by its index
     /// 
     /// Uses the playlist index command to jump to a specific track in the playlist.
@@ -89,9 +89,9 @@ pub fn map_track(lms_track: &LmsTrack, album_artist: Option<&str>) -> AcrTrack {
         }
     }
     
-    // Set duration if available
-    if let Some(_duration_secs) = lms_track.duration {
-        // We don't need to modify the duration, as both use seconds
+    // Set duration if available, both use seconds
+    if let Some(duration_secs) = lms_track.duration {
+        track.duration = Some(duration_secs as f64);
     }
     
     // Set URI if we can construct one
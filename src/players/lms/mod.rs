@@ -8,10 +8,12 @@ pub mod cli_listener;
 pub mod mapping;
 pub mod library;
 pub mod libraryloader;
+pub mod childplayer;
 
 // Re-export main components for easier access
 pub use jsonrps::{LmsRpcClient, LmsRpcError, Player, PlayerStatus, Track, Album, Artist, Playlist, SearchResults};
 pub use lmsserver::{LmsServer, find_local_servers};
 pub use lmsaudio::{LMSAudioController, LMSAudioConfig};
 pub use lmspplayer::LMSPlayer;
-pub use library::LMSLibrary;
\ No newline at end of file
+pub use library::LMSLibrary;
+pub use childplayer::LMSChildPlayer;
\ No newline at end of file
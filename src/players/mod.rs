@@ -1,5 +1,6 @@
 /// Player management and functionality for AudioControl3
 mod player_controller;
+pub mod poll_task;
 pub mod mpd;
 mod null_controller;
 pub mod player_factory;
@@ -10,6 +11,12 @@ pub mod event_api;
 pub mod generic;
 pub mod shairport;
 pub mod bluetooth;
+pub mod hqplayer;
+pub mod openhome;
+pub mod chromecast;
+pub mod upnp;
+pub mod kodi;
+pub mod sonos;
 
 // MPRIS support is only available on Unix-like systems (Linux, macOS)
 #[cfg(not(windows))]
@@ -17,10 +24,17 @@ pub mod mpris;
 
 // Re-export the PlayerController trait and related components
 pub use player_controller::{PlayerController, BasePlayerController};
+pub use poll_task::{PollTask, PollTrigger};
 pub use mpd::MPDPlayerController;
 pub use null_controller::NullPlayerController;
 pub use shairport::ShairportController;
 pub use bluetooth::BluetoothPlayerController;
+pub use hqplayer::HQPlayerController;
+pub use openhome::OpenHomeController;
+pub use chromecast::ChromecastController;
+pub use upnp::UpnpController;
+pub use kodi::KodiController;
+pub use sonos::SonosController;
 pub use player_factory::{create_player_from_json, create_player_from_json_str, PlayerCreationError};
 pub use raat::MetadataPipeReader;
 // Export the LibrespotPlayerController for use in player_factory
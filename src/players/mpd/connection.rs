@@ -0,0 +1,250 @@
+use crate::helpers::netaddr;
+use log::debug;
+use mpd::{Client, error::Error as MpdError};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// Address of an MPD server and the password to authenticate with
+#[derive(Debug, Clone, PartialEq)]
+pub struct MpdAddress {
+    pub hostname: String,
+    pub port: u16,
+    /// Path of MPD's unix socket, used instead of hostname and port if set
+    pub socket: Option<String>,
+    /// Sent with the `password` command after connecting
+    pub password: Option<String>,
+}
+
+impl MpdAddress {
+    pub fn tcp(hostname: &str, port: u16) -> Self {
+        Self {
+            hostname: hostname.to_string(),
+            port,
+            socket: None,
+            password: None,
+        }
+    }
+
+    pub fn with_socket(mut self, socket: Option<&str>) -> Self {
+        self.socket = socket.filter(|s| !s.is_empty()).map(str::to_string);
+        self
+    }
+
+    pub fn with_password(mut self, password: Option<&str>) -> Self {
+        self.password = password.filter(|p| !p.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// True if MPD runs on this device
+    pub fn is_local(&self) -> bool {
+        self.socket.is_some() || matches!(self.hostname.as_str(), "localhost" | "127.0.0.1" | "::1")
+    }
+
+    /// Identifies the server, "host:port" or the socket path
+    pub fn id(&self) -> String {
+        match &self.socket {
+            Some(socket) => socket.clone(),
+            None => format!("{}:{}", self.hostname, self.port),
+        }
+    }
+
+    /// Open a connection without reading the greeting
+    pub fn connect(&self) -> io::Result<MpdStream> {
+        match &self.socket {
+            #[cfg(unix)]
+            Some(socket) => UnixStream::connect(socket).map(MpdStream::Unix),
+            #[cfg(not(unix))]
+            Some(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets are not supported")),
+            None => netaddr::connect(&self.hostname, self.port).map(MpdStream::Tcp),
+        }
+    }
+
+    /// Connect with the MPD client library and log in if a password is configured
+    pub fn client(&self) -> Result<Client<MpdStream>, MpdError> {
+        self.login(self.connect()?)
+    }
+
+    /// Create a client on an open connection and log in if a password is configured
+    pub fn login(&self, stream: MpdStream) -> Result<Client<MpdStream>, MpdError> {
+        let mut client = Client::new(stream)?;
+        if let Some(password) = &self.password {
+            client.login(password)?;
+        }
+        Ok(client)
+    }
+
+    /// Connect for sending raw commands, the greeting is read and the password sent
+    ///
+    /// Returns the reader and the writer of the connection.
+    pub fn connect_raw(&self, read_timeout: Option<Duration>) -> io::Result<(BufReader<MpdStream>, MpdStream)> {
+        let stream = self.connect()?;
+        stream.set_read_timeout(read_timeout)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        let mut welcome = String::new();
+        reader.read_line(&mut welcome)?;
+        if !welcome.starts_with("OK") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected MPD greeting: {}", welcome.trim()),
+            ));
+        }
+
+        if let Some(password) = &self.password {
+            writer.write_all(format!("password \"{}\"\n", escape_argument(password)).as_bytes())?;
+            let mut response = String::new();
+            reader.read_line(&mut response)?;
+            if response.trim() != "OK" {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("MPD rejected the password: {}", response.trim()),
+                ));
+            }
+            debug!("Authenticated to MPD at {}", self);
+        }
+        Ok((reader, writer))
+    }
+}
+
+impl fmt::Display for MpdAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.socket {
+            Some(socket) => write!(f, "unix:{}", socket),
+            None => f.write_str(&netaddr::host_port(&self.hostname, self.port)),
+        }
+    }
+}
+
+fn escape_argument(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A TCP or unix socket connection to MPD
+#[derive(Debug)]
+pub enum MpdStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl MpdStream {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            MpdStream::Tcp(s) => s.try_clone().map(MpdStream::Tcp),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.try_clone().map(MpdStream::Unix),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            MpdStream::Tcp(s) => s.set_read_timeout(timeout),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            MpdStream::Tcp(s) => s.shutdown(how),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.shutdown(how),
+        }
+    }
+}
+
+impl Read for MpdStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            MpdStream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for MpdStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            MpdStream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            MpdStream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            MpdStream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    /// MPD server on a unix socket that only accepts the password "secret"
+    fn fake_mpd(path: &std::path::Path) {
+        let listener = UnixListener::bind(path).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    writer.write_all(b"OK MPD 0.23.5\n").unwrap();
+                    let mut authenticated = false;
+                    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                        let response = if line == "password \"secret\"" {
+                            authenticated = true;
+                            "OK\n".to_string()
+                        } else if line.starts_with("password") {
+                            "ACK [3@0] {password} incorrect password\n".to_string()
+                        } else if authenticated {
+                            "OK\n".to_string()
+                        } else {
+                            "ACK [4@0] {ping} you don't have permission for \"ping\"\n".to_string()
+                        };
+                        if writer.write_all(response.as_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_unix_socket_with_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mpd.sock");
+        fake_mpd(&path);
+
+        let address = MpdAddress::tcp("localhost", 6600).with_socket(path.to_str());
+        assert!(address.is_local());
+        assert_eq!(address.id(), path.to_str().unwrap());
+        assert!(address.client().unwrap().ping().is_err());
+
+        let address = address.with_password(Some("secret"));
+        address.client().unwrap().ping().unwrap();
+        let (mut reader, mut writer) = address.connect_raw(Some(Duration::from_secs(3))).unwrap();
+        writer.write_all(b"ping\n").unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "OK\n");
+
+        let address = address.with_password(Some("wrong"));
+        assert!(address.client().is_err());
+        assert_eq!(
+            address.connect_raw(None).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{debug, error, info, warn};
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+use crate::players::mpd::MPDPlayerController;
+
+/// If more directories than this changed at once, MPD scans the whole library
+const MAX_TARGETED_UPDATES: usize = 20;
+
+/// Time without further changes before MPD is asked to update, copying an album
+/// to a NAS creates many events
+const DEBOUNCE: Duration = Duration::from_secs(5);
+
+/// Start watching the music directory of an MPD player for changes
+///
+/// New, changed and removed music files trigger `update <path>` commands on MPD for the
+/// affected directories. MPD reports the database change when the update is finished,
+/// which refreshes the library.
+///
+/// # Arguments
+/// * `player` - The MPD player controller
+/// * `running` - The watcher stops when this is set to false
+pub fn start_watcher(player: Arc<MPDPlayerController>, running: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        let Some(music_dir) = player.get_effective_music_directory() else {
+            warn!("Music directory unknown, not watching for file changes");
+            return;
+        };
+        if !Path::new(&music_dir).is_dir() {
+            warn!("Music directory {} is not accessible, not watching for file changes", music_dir);
+            return;
+        }
+        watcher_loop(&player, &music_dir, &running);
+    });
+}
+
+fn watcher_loop(player: &MPDPlayerController, music_dir: &str, running: &AtomicBool) {
+    let (tx, rx) = mpsc::channel();
+
+    let mut watcher = match recommended_watcher(move |res: Result<Event, notify::Error>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    }) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Failed to create file system watcher: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = watcher.watch(Path::new(music_dir), RecursiveMode::Recursive) {
+        error!("Failed to watch music directory {}: {}", music_dir, e);
+        return;
+    }
+
+    info!("Watching music directory {} for changes", music_dir);
+
+    let base = PathBuf::from(music_dir);
+    let mut pending: BTreeSet<String> = BTreeSet::new();
+    let mut last_change = Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_millis(1000)) {
+            Ok(event) => {
+                if !is_relevant_event(&event.kind) {
+                    continue;
+                }
+                for path in &event.paths {
+                    if let Some(dir) = changed_directory(&base, path) {
+                        debug!("Music directory change detected in '{}'", dir);
+                        pending.insert(dir);
+                        last_change = Instant::now();
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                error!("File system watcher channel disconnected");
+                break;
+            }
+        }
+
+        if !pending.is_empty() && last_change.elapsed() >= DEBOUNCE {
+            send_updates(player, &collapse_directories(&pending));
+            pending.clear();
+        }
+    }
+
+    debug!("Music directory watcher stopped");
+}
+
+fn send_updates(player: &MPDPlayerController, directories: &[String]) {
+    let Some(library) = player.get_library() else {
+        debug!("MPD library not initialized, ignoring music directory changes");
+        return;
+    };
+
+    if directories.len() > MAX_TARGETED_UPDATES || directories.iter().any(|dir| dir.is_empty()) {
+        info!("{} directories changed, requesting full MPD database update", directories.len());
+        library.update_database(None);
+        return;
+    }
+
+    for dir in directories {
+        info!("Requesting MPD database update for '{}'", dir);
+        library.update_database(Some(dir));
+    }
+}
+
+fn is_relevant_event(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_))
+}
+
+/// Get the directory relative to the music directory that has to be updated for a changed path
+///
+/// Only music files and paths without extension (usually directories) are considered.
+/// Hidden files like partial downloads are ignored.
+fn changed_directory(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let file_name = relative.file_name()?.to_string_lossy();
+    if file_name.starts_with('.') {
+        return None;
+    }
+    if relative.extension().is_some() && !crate::helpers::local_coverart::is_audio_file(relative) {
+        return None;
+    }
+    Some(relative.parent()?.to_string_lossy().to_string())
+}
+
+/// Remove directories that are contained in another changed directory
+///
+/// MPD updates directories recursively, so only the topmost ones are needed.
+fn collapse_directories(directories: &BTreeSet<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    // BTreeSet is sorted, parents always come before their subdirectories
+    for dir in directories {
+        let covered = result.iter().any(|parent| {
+            parent.is_empty() || dir == parent || dir.starts_with(&format!("{}/", parent))
+        });
+        if !covered {
+            result.push(dir.clone());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_directory() {
+        let base = Path::new("/music");
+        assert_eq!(
+            changed_directory(base, Path::new("/music/Artist/Album/01 Track.flac")),
+            Some("Artist/Album".to_string())
+        );
+        // New album directory
+        assert_eq!(
+            changed_directory(base, Path::new("/music/Artist/Album")),
+            Some("Artist".to_string())
+        );
+        assert_eq!(changed_directory(base, Path::new("/music/Artist/Album/cover.jpg")), None);
+        assert_eq!(changed_directory(base, Path::new("/music/Artist/.01 Track.flac.part")), None);
+        assert_eq!(changed_directory(base, Path::new("/other/Track.mp3")), None);
+    }
+
+    #[test]
+    fn test_collapse_directories() {
+        let dirs: BTreeSet<String> = ["A/B", "A", "A B/C", "C/D", "C/D/E"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(collapse_directories(&dirs), vec!["A", "A B/C", "C/D"]);
+
+        let dirs: BTreeSet<String> = ["", "A"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(collapse_directories(&dirs), vec![""]);
+    }
+}
@@ -4,19 +4,19 @@ use parking_lot::{Mutex, RwLock};
 use std::time::Instant;
 use log::{debug, info, warn, error};
 use chrono::Datelike;
-use crate::data::{Album, Artist, AlbumArtists, LibraryInterface, LibraryError};
+use crate::data::{Album, Artist, AlbumArtists, LibraryInterface, LibraryError, LibraryDiff, LibrarySnapshot};
 use crate::players::mpd::mpd::{MPDPlayerController, mpd_image_url};
+use crate::players::PlayerController;
 use crate::helpers::url_encoding;
+use crate::players::mpd::connection::MpdAddress;
 use crate::helpers::lyrics::LyricsProvider;
+use crate::helpers::coverwriteback::{CoverWriteBackEntry, CoverWriteBackStatus};
 
 /// MPD library interface that provides access to albums and artists
 #[derive(Clone)]
 pub struct MPDLibrary {
-    /// MPD server hostname
-    hostname: String,
-    
-    /// MPD server port
-    port: u16,
+    /// MPD server address and password
+    address: MpdAddress,
     
     /// Cache of albums, key is album name
     albums: Arc<RwLock<HashMap<String, Album>>>,
@@ -32,6 +32,9 @@ pub struct MPDLibrary {
     
     /// Library loading progress (0.0 - 1.0)
     loading_progress: Arc<Mutex<f32>>,
+
+    /// Albums added, removed and changed by the last refresh
+    last_diff: Arc<Mutex<Option<LibraryDiff>>>,
     
     /// Custom artist separators for splitting artist names
     artist_separators: Arc<Mutex<Option<Vec<String>>>>,
@@ -45,20 +48,20 @@ pub struct MPDLibrary {
 
 impl MPDLibrary {
     /// Create a new MPD library interface with specific connection details
-    pub fn with_connection(hostname: &str, port: u16, controller: Arc<MPDPlayerController>) -> Self {
-        debug!("Creating new MPDLibrary with connection {}:{}", hostname, port);
+    pub fn with_connection(address: MpdAddress, controller: Arc<MPDPlayerController>) -> Self {
+        debug!("Creating new MPDLibrary with connection {}", address);
         
         // Get the enhance_metadata setting from the controller, if available
         let enhance_metadata = controller.get_enhance_metadata().unwrap_or(true);
         
         MPDLibrary {
-            hostname: hostname.to_string(),
-            port,
+            address,
             albums: Arc::new(RwLock::new(HashMap::new())),
             artists: Arc::new(RwLock::new(HashMap::new())),
             album_artists: Arc::new(RwLock::new(AlbumArtists::new())),
             library_loaded: Arc::new(Mutex::new(false)),
             loading_progress: Arc::new(Mutex::new(0.0)),
+            last_diff: Arc::new(Mutex::new(None)),
             artist_separators: Arc::new(Mutex::new(None)),
             enhance_metadata,
             controller,
@@ -145,34 +148,18 @@ impl MPDLibrary {
     /// 
     /// Returns a tuple of (binary data, mime-type) of the cover art if found, None otherwise
     pub fn cover_art(&self, uri: &str) -> Option<(Vec<u8>, String)> {
-        use std::io::{Read, BufRead, BufReader, Write};
-        use std::net::TcpStream;
+        use std::io::{Read, BufRead, Write};
         debug!("Retrieving cover art for URI: {}", uri);
         
         // Connect to MPD server
-        let stream = match TcpStream::connect(format!("{}:{}", self.hostname, self.port)) {
-            Ok(s) => s,
+        let (mut reader, mut writer) = match self.address.connect_raw(None) {
+            Ok(connection) => connection,
             Err(e) => {
                 error!("Failed to connect to MPD server: {}", e);
                 return None;
             }
         };
         
-        let mut reader = BufReader::new(stream.try_clone().unwrap());
-        let mut writer = stream;
-        
-        // Read the welcome message
-        let mut welcome = String::new();
-        if reader.read_line(&mut welcome).is_err() {
-            error!("Failed to read welcome message from MPD");
-            return None;
-        }
-        
-        if !welcome.starts_with("OK") {
-            error!("Unexpected welcome message from MPD: {}", welcome);
-            return None;
-        }
-        
         // Send albumart command with URI and offset 0
         let cmd = format!("albumart \"{}\" 0\n", uri);
         if writer.write_all(cmd.as_bytes()).is_err() {
@@ -713,13 +700,7 @@ impl MPDLibrary {
             debug!("Successfully retrieved cover art from MPD for album: {}", album.name);
             
             // Store the cover art in the imagecache with artist and album name
-            let _ = crate::helpers::imagecache::store_album_cover(
-                &artist_name, 
-                &album.name, 
-                year, 
-                data.clone(), 
-                mime_type.clone()
-            );
+            Self::cache_album_cover(&artist_name, &album.name, year, &data, &mime_type, "mpd");
             
             return Some((data, mime_type));
         }
@@ -736,13 +717,7 @@ impl MPDLibrary {
             // Try to get track cover
             if let Some((data, mime_type)) = self.get_track_cover(uri, None) {
                 // Store in image cache with artist and album info for future requests
-                let _ = crate::helpers::imagecache::store_album_cover(
-                    &artist_name,
-                    &album.name,
-                    year,
-                    data.clone(),
-                    mime_type.clone()
-                );
+                Self::cache_album_cover(&artist_name, &album.name, year, &data, &mime_type, "mpd");
                 
                 return Some((data, mime_type));
             }
@@ -763,13 +738,7 @@ impl MPDLibrary {
                 self.save_cover_to_album_dir(&dir_path, &data);
                 
                 // Step 6: Store in imagecache for future requests
-                let _ = crate::helpers::imagecache::store_album_cover(
-                    &artist_name,
-                    &album.name,
-                    year,
-                    data.clone(),
-                    mime_type.clone()
-                );
+                Self::cache_album_cover(&artist_name, &album.name, year, &data, &mime_type, "music_file");
                 
                 return Some((data, mime_type));
             }
@@ -781,13 +750,7 @@ impl MPDLibrary {
         // Try to get track cover
         if let Some((data, mime_type)) = self.get_track_cover(uri, None) {
             // Store in image cache with artist and album info for future requests
-            let _ = crate::helpers::imagecache::store_album_cover(
-                &artist_name,
-                &album.name,
-                year,
-                data.clone(),
-                mime_type.clone()
-            );
+            Self::cache_album_cover(&artist_name, &album.name, year, &data, &mime_type, "mpd");
             
             Some((data, mime_type))
         } else {
@@ -795,6 +758,14 @@ impl MPDLibrary {
         }
     }
     
+    /// Store an album cover in the image cache and remember where it came from
+    fn cache_album_cover(artist_name: &str, album_name: &str, year: Option<i32>, data: &[u8], mime_type: &str, provider: &str) {
+        if crate::helpers::imagecache::store_album_cover(artist_name, album_name, year, data.to_vec(), mime_type.to_string()).is_ok() {
+            let cache_key = crate::helpers::local_coverart::album_cache_key(artist_name, album_name, year);
+            crate::helpers::coverart::store_artwork_source("album", &cache_key, &crate::helpers::coverart::ArtworkSource::new(provider, None));
+        }
+    }
+    
     /// Get artist cover art using the artist store
     /// 
     /// # Arguments
@@ -886,24 +857,7 @@ impl MPDLibrary {
     fn extract_cover_from_music_files(&self, dir_path: &str) -> Option<(Vec<u8>, String)> {
         debug!("Extracting cover art from music files in directory: {}", dir_path);
         
-        // Get the music directory from configuration or /etc/mpd.conf
-        let mut base_paths = Vec::new();
-        
-        if let Some(music_dir) = self.controller.get_effective_music_directory() {
-            debug!("Using configured music directory: {}", music_dir);
-            base_paths.push(music_dir);
-        }
-        
-        // Add fallback paths in case the configured path doesn't work
-        base_paths.extend([
-            "/var/lib/mpd/music".to_string(),   // Common default
-            "/music".to_string(),               // Common mount point  
-            "/home/mpd/music".to_string(),      // Another common path
-            "/srv/music".to_string(),           // Another common path
-            "".to_string(),                     // Use relative path as-is
-        ]);
-        
-        for base_path in base_paths {
+        for base_path in self.music_base_paths() {
             let full_path = if base_path.is_empty() {
                 dir_path.to_string()
             } else {
@@ -930,13 +884,13 @@ impl MPDLibrary {
         None
     }
     
-    /// Save cover art to the album directory as cover.jpg
-    fn save_cover_to_album_dir(&self, dir_path: &str, data: &[u8]) -> bool {
+    /// Directories where the music files might be stored locally
+    fn music_base_paths(&self) -> Vec<String> {
         // Get the music directory from configuration or /etc/mpd.conf
         let mut base_paths = Vec::new();
         
         if let Some(music_dir) = self.controller.get_effective_music_directory() {
-            debug!("Using configured music directory for saving cover: {}", music_dir);
+            debug!("Using configured music directory: {}", music_dir);
             base_paths.push(music_dir);
         }
         
@@ -949,7 +903,154 @@ impl MPDLibrary {
             "".to_string(),                     // Use relative path as-is
         ]);
         
-        for base_path in base_paths {
+        base_paths
+    }
+    
+    /// Find the full local path of an album directory
+    fn find_local_album_path(&self, dir_path: &str) -> Option<String> {
+        self.music_base_paths()
+            .into_iter()
+            .map(|base_path| {
+                if base_path.is_empty() {
+                    dir_path.to_string()
+                } else {
+                    format!("{}/{}", base_path, dir_path)
+                }
+            })
+            .find(|full_path| std::path::Path::new(full_path).is_dir())
+    }
+    
+    /// Configured network shares with music files that are currently offline
+    fn offline_music_shares(&self) -> Vec<String> {
+        match self.controller.get_effective_music_directory() {
+            Some(music_dir) => crate::helpers::mounts::offline_shares_for_path(&music_dir),
+            None => Vec::new(),
+        }
+    }
+    
+    /// Whether the library must not be modified
+    pub fn is_read_only(&self) -> bool {
+        self.controller.get_library_read_only()
+    }
+    
+    /// Full path of the local directory of an album
+    fn local_album_directory(&self, album: &Album) -> Option<String> {
+        let uri = album.tracks.lock().first().and_then(|track| track.uri.clone());
+        uri.as_deref()
+            .and_then(|uri| self.get_album_directory(uri))
+            .and_then(|dir_path| self.find_local_album_path(&dir_path))
+    }
+
+    /// Directory `write_back_album_cover` would write a cover into, if one is found
+    ///
+    /// None if the directory can't be found or already contains a cover file.
+    /// Nothing is looked up or written.
+    pub fn cover_write_back_directory(&self, album: &Album) -> Option<String> {
+        self.local_album_directory(album)
+            .filter(|dir| crate::helpers::local_coverart::find_cover_file(dir).is_none())
+    }
+
+    /// Write the cover of an album into its directory
+    ///
+    /// Directories that already contain a cover file are never modified.
+    /// JPEG covers are written as cover.jpg, PNG covers as cover.png.
+    pub fn write_back_album_cover(&self, album: &Album) -> CoverWriteBackEntry {
+        let artist_name = album.artists.lock().first().cloned().unwrap_or_default();
+        let entry = |directory: Option<String>, status: CoverWriteBackStatus, detail: Option<String>| {
+            CoverWriteBackEntry::new(&album.name, &artist_name, directory, status, detail)
+        };
+
+        let Some(full_path) = self.local_album_directory(album) else {
+            return entry(None, CoverWriteBackStatus::NoDirectory, None);
+        };
+
+        if let Some(existing) = crate::helpers::local_coverart::find_cover_file(&full_path) {
+            return entry(Some(full_path), CoverWriteBackStatus::Conflict, Some(existing.to_string()));
+        }
+
+        let Some((data, mime_type)) = self.get_album_cover(&album.id) else {
+            return entry(Some(full_path), CoverWriteBackStatus::NoCover, None);
+        };
+
+        // Extracting the cover from music files already saves it into the directory
+        if let Some(saved) = crate::helpers::local_coverart::find_cover_file(&full_path) {
+            return entry(Some(full_path), CoverWriteBackStatus::Written, Some(saved.to_string()));
+        }
+
+        let file_name = match mime_type.as_str() {
+            "image/jpeg" | "image/jpg" => "cover.jpg",
+            "image/png" => "cover.png",
+            _ => return entry(Some(full_path), CoverWriteBackStatus::Unsupported, Some(mime_type)),
+        };
+
+        match std::fs::write(std::path::Path::new(&full_path).join(file_name), &data) {
+            Ok(()) => entry(Some(full_path), CoverWriteBackStatus::Written, Some(file_name.to_string())),
+            Err(e) => entry(Some(full_path), CoverWriteBackStatus::Failed, Some(e.to_string())),
+        }
+    }
+    
+    /// Ask MPD to scan for new or changed files
+    ///
+    /// # Arguments
+    /// * `path` - Directory relative to the music directory, or None to scan everything
+    pub fn update_database(&self, path: Option<&str>) -> bool {
+        use std::io::{Write, BufRead};
+        
+        let offline = self.offline_music_shares();
+        if !offline.is_empty() {
+            // MPD would remove all files on the share from its database
+            warn!("Network share(s) {} offline, not updating MPD database", offline.join(", "));
+            return false;
+        }
+        
+        debug!("Sending update command for {:?} to MPD server at {}", path, self.address);
+        
+        // Connect to MPD server
+        match self.address.connect_raw(None) {
+            Ok((mut reader, mut writer)) => {
+                // Send update command to rescan the library
+                let command = match path {
+                    Some(path) => format!("update \"{}\"\n", escape_mpd_argument(path)),
+                    None => "update\n".to_string(),
+                };
+                match writer.write_all(command.as_bytes()) {
+                    Ok(_) => {
+                        // Read the response
+                        let mut response = String::new();
+                        if reader.read_line(&mut response).is_err() {
+                            error!("Failed to read response from MPD");
+                            return false;
+                        }
+                        
+                        // Check if the response contains the update ID
+                        if response.starts_with("updating_db:") {
+                            debug!("MPD update command accepted: {}", response.trim());
+                            true
+                        } else if response == "OK\n" {
+                            // Some MPD servers might just respond with OK
+                            debug!("MPD update command accepted with OK response");
+                            true
+                        } else {
+                            error!("Unexpected response from MPD update command: {}", response.trim());
+                            false
+                        }
+                    },
+                    Err(e) => {
+                        error!("Failed to send update command to MPD: {}", e);
+                        false
+                    }
+                }
+            },
+            Err(e) => {
+                error!("Failed to connect to MPD server: {}", e);
+                false
+            }
+        }
+    }
+    
+    /// Save cover art to the album directory as cover.jpg
+    fn save_cover_to_album_dir(&self, dir_path: &str, data: &[u8]) -> bool {
+        for base_path in self.music_base_paths() {
             let full_path = if base_path.is_empty() {
                 dir_path.to_string()
             } else {
@@ -969,13 +1070,18 @@ impl MPDLibrary {
     }
 }
 
+/// Escape a string for use as a quoted argument of an MPD command
+pub(super) fn escape_mpd_argument(argument: &str) -> String {
+    argument.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 impl LibraryInterface for MPDLibrary {
     fn new() -> Self {
         debug!("Creating new MPDLibrary with default connection");
         // Create a new default MPDPlayerController
         let controller = Arc::new(MPDPlayerController::new());
         
-        Self::with_connection("localhost", 6600, controller)
+        Self::with_connection(MpdAddress::tcp("localhost", 6600), controller)
     }
     
     fn is_loaded(&self) -> bool {
@@ -984,17 +1090,27 @@ impl LibraryInterface for MPDLibrary {
     }
     
     fn refresh_library(&self) -> Result<(), LibraryError> {
+        let offline = self.offline_music_shares();
+        if !offline.is_empty() {
+            warn!("Network share(s) {} offline, not refreshing MPD library", offline.join(", "));
+            return Err(LibraryError::ConnectionError(format!("Network share(s) offline: {}", offline.join(", "))));
+        }
+        
         debug!("Refreshing MPD library data using MPDLibraryLoader");
         let start_time = Instant::now();
         
         // Use our MPDLibraryLoader to load albums, passing the controller reference
-        let loader = super::libraryloader::MPDLibraryLoader::new(&self.hostname, self.port, self.controller.clone());
+        let loader = super::libraryloader::MPDLibraryLoader::new(self.address.clone(), self.controller.clone());
         
         // Get artist separators from the MPD configuration, if any
         let artist_separators = self.get_artist_separators();
         
         let result = match loader.load_albums_from_mpd(artist_separators) {
             Ok(albums) => {
+                // Remember the albums of a loaded library to report the changes
+                let previous = self.is_loaded()
+                    .then(|| LibrarySnapshot::from_albums(self.albums.read().values()));
+
                 // Mark as not loaded during update
                 *self.library_loaded.lock() = false;
                 
@@ -1025,6 +1141,11 @@ impl LibraryInterface for MPDLibrary {
 
                 // Mark as loaded and update progress
                 *self.library_loaded.lock() = true;
+                crate::helpers::library_search::index_library(
+                    &self.controller.get_player_name(),
+                    self.albums.read().values(),
+                    self.artists.read().values(),
+                );
                 {
                     let mut progress = self.loading_progress.lock();
                     *progress = 1.0;
@@ -1032,6 +1153,16 @@ impl LibraryInterface for MPDLibrary {
                 
                 let total_time = start_time.elapsed();
                 info!("Library load complete in {:.2?}", total_time);
+
+                if let Some(previous) = previous {
+                    let diff = LibraryDiff::between(&previous, &LibrarySnapshot::from_albums(self.albums.read().values()));
+                    info!("Library refresh added {} albums, removed {}, changed {}",
+                        diff.added.len(), diff.removed.len(), diff.changed.len());
+                    if !diff.is_empty() {
+                        self.controller.notify_library_changed(diff.clone());
+                    }
+                    *self.last_diff.lock() = Some(diff);
+                }
                 
                 // Start background metadata updates now that the library is fully loaded
                 if self.enhance_metadata {
@@ -1059,6 +1190,10 @@ impl LibraryInterface for MPDLibrary {
         result
     }
     
+    fn get_last_diff(&self) -> Option<LibraryDiff> {
+        self.last_diff.lock().clone()
+    }
+
     fn get_albums(&self) -> Vec<Album> {
         let albums = self.albums.read();
         albums.values().cloned().map(|mut album| {
@@ -1167,63 +1302,7 @@ impl LibraryInterface for MPDLibrary {
     }
 
     fn force_update(&self) -> bool {
-        use std::io::{Write, BufRead, BufReader};
-        use std::net::TcpStream;
-        
-        debug!("Sending update command to MPD server at {}:{}", self.hostname, self.port);
-        
-        // Connect to MPD server
-        match TcpStream::connect(format!("{}:{}", self.hostname, self.port)) {
-            Ok(stream) => {
-                let mut reader = BufReader::new(stream.try_clone().unwrap());
-                let mut writer = stream;
-                
-                // Read the welcome message
-                let mut welcome = String::new();
-                if reader.read_line(&mut welcome).is_err() {
-                    error!("Failed to read welcome message from MPD");
-                    return false;
-                }
-                
-                if !welcome.starts_with("OK") {
-                    error!("Unexpected welcome message from MPD: {}", welcome);
-                    return false;
-                }
-                
-                // Send update command to rescan the library
-                match writer.write_all(b"update\n") {
-                    Ok(_) => {
-                        // Read the response
-                        let mut response = String::new();
-                        if reader.read_line(&mut response).is_err() {
-                            error!("Failed to read response from MPD");
-                            return false;
-                        }
-                        
-                        // Check if the response contains the update ID
-                        if response.starts_with("updating_db:") {
-                            debug!("MPD update command accepted: {}", response.trim());
-                            true
-                        } else if response == "OK\n" {
-                            // Some MPD servers might just respond with OK
-                            debug!("MPD update command accepted with OK response");
-                            true
-                        } else {
-                            error!("Unexpected response from MPD update command: {}", response.trim());
-                            false
-                        }
-                    },
-                    Err(e) => {
-                        error!("Failed to send update command to MPD: {}", e);
-                        false
-                    }
-                }
-            },
-            Err(e) => {
-                error!("Failed to connect to MPD server: {}", e);
-                false
-            }
-        }
+        self.update_database(None)
     }
 
     fn get_meta_keys(&self) -> Vec<String> {
@@ -1328,8 +1407,8 @@ impl LibraryInterface for MPDLibrary {
                 }
                 Some(total_tracks.to_string())
             },
-            "hostname" => Some(self.hostname.clone()),
-            "port" => Some(self.port.to_string()),
+            "hostname" => Some(self.address.hostname.clone()),
+            "port" => Some(self.address.port.to_string()),
             "library_loaded" => {
                 let loaded = self.library_loaded.lock();
                 Some(loaded.to_string())
@@ -1348,41 +1427,26 @@ impl LibraryInterface for MPDLibrary {
     }
 
     fn supports_delete(&self) -> bool {
-        !self.controller.get_library_read_only()
+        !self.is_read_only()
     }
 
     fn delete_album(&self, album_id: &crate::data::Identifier) -> Result<(), crate::data::library::LibraryError> {
         use std::collections::HashSet;
         use std::path::PathBuf;
 
-        let album = self.get_album_by_id(album_id)
-            .ok_or_else(|| crate::data::library::LibraryError::QueryError(
-                format!("Album not found: {:?}", album_id)
-            ))?;
-
-        let music_dir = self.controller.get_effective_music_directory()
-            .ok_or_else(|| crate::data::library::LibraryError::InternalError(
-                "Music directory not configured".to_string()
-            ))?;
-
         let mut dirs_to_clean: HashSet<PathBuf> = HashSet::new();
-        let tracks = album.tracks.lock();
-        for track in tracks.iter() {
-            if let Some(uri) = &track.uri {
-                let full_path = PathBuf::from(&music_dir).join(uri);
-                if let Some(parent) = full_path.parent() {
-                    dirs_to_clean.insert(parent.to_path_buf());
-                }
-                if let Err(e) = std::fs::remove_file(&full_path) {
-                    error!("Failed to delete track {:?}: {}", full_path, e);
-                    return Err(crate::data::library::LibraryError::InternalError(
-                        format!("Failed to delete file: {}", e)
-                    ));
-                }
-                info!("Deleted track file: {:?}", full_path);
+        for full_path in self.album_file_paths(album_id)? {
+            if let Some(parent) = full_path.parent() {
+                dirs_to_clean.insert(parent.to_path_buf());
             }
+            if let Err(e) = std::fs::remove_file(&full_path) {
+                error!("Failed to delete track {:?}: {}", full_path, e);
+                return Err(crate::data::library::LibraryError::InternalError(
+                    format!("Failed to delete file: {}", e)
+                ));
+            }
+            info!("Deleted track file: {:?}", full_path);
         }
-        drop(tracks);
 
         // Remove now-empty album directories
         for dir in &dirs_to_clean {
@@ -1405,14 +1469,7 @@ impl LibraryInterface for MPDLibrary {
     }
 
     fn delete_track(&self, track_uri: &str) -> Result<(), crate::data::library::LibraryError> {
-        use std::path::PathBuf;
-
-        let music_dir = self.controller.get_effective_music_directory()
-            .ok_or_else(|| crate::data::library::LibraryError::InternalError(
-                "Music directory not configured".to_string()
-            ))?;
-
-        let full_path = PathBuf::from(&music_dir).join(track_uri);
+        let full_path = self.track_file_path(track_uri)?;
         std::fs::remove_file(&full_path)
             .map_err(|e| crate::data::library::LibraryError::InternalError(
                 format!("Failed to delete file {:?}: {}", full_path, e)
@@ -1422,6 +1479,45 @@ impl LibraryInterface for MPDLibrary {
         self.force_update();
         Ok(())
     }
+
+    fn plan_delete_album(&self, album_id: &crate::data::Identifier) -> Result<Vec<String>, crate::data::library::LibraryError> {
+        Ok(self.album_file_paths(album_id)?
+            .into_iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    fn plan_delete_track(&self, track_uri: &str) -> Result<String, crate::data::library::LibraryError> {
+        let full_path = self.track_file_path(track_uri)?;
+        if !full_path.is_file() {
+            return Err(crate::data::library::LibraryError::InternalError(
+                format!("File {:?} does not exist", full_path)
+            ));
+        }
+        Ok(full_path.to_string_lossy().into_owned())
+    }
+}
+
+impl MPDLibrary {
+    /// Full path of a track file in the music directory
+    fn track_file_path(&self, track_uri: &str) -> Result<std::path::PathBuf, crate::data::library::LibraryError> {
+        let music_dir = self.controller.get_effective_music_directory()
+            .ok_or_else(|| crate::data::library::LibraryError::InternalError(
+                "Music directory not configured".to_string()
+            ))?;
+        Ok(std::path::PathBuf::from(&music_dir).join(track_uri))
+    }
+
+    /// Full paths of the track files of an album
+    fn album_file_paths(&self, album_id: &crate::data::Identifier) -> Result<Vec<std::path::PathBuf>, crate::data::library::LibraryError> {
+        let album = self.get_album_by_id(album_id)
+            .ok_or_else(|| crate::data::library::LibraryError::QueryError(
+                format!("Album not found: {:?}", album_id)
+            ))?;
+
+        let uris: Vec<String> = album.tracks.lock().iter().filter_map(|track| track.uri.clone()).collect();
+        uris.iter().map(|uri| self.track_file_path(uri)).collect()
+    }
 }
 
 impl MPDLibrary {
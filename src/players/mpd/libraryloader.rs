@@ -1,35 +1,51 @@
 use std::collections::HashMap;
-use std::time::Instant;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use std::sync::Arc;
+use std::thread;
 use log::{debug, info, error, warn};
 use chrono::NaiveDate;
+use parking_lot::Mutex;
 use crate::data::LibraryError;
+use crate::players::mpd::library::escape_mpd_argument;
 use crate::players::mpd::mpd::MPDPlayerController;
-use crate::helpers::backgroundjobs::{register_job, update_job, complete_job};
+use crate::helpers::backgroundjobs::{register_cancellable_job, register_job, update_job, complete_job};
+use crate::players::mpd::connection::{MpdAddress, MpdStream};
 
-/// Number of songs to process before updating progress
+/// Number of albums to process before updating progress
 const PROGRESS_UPDATE_FREQUENCY: usize = 100;
 
+/// Default number of connections reading the library from MPD in parallel
+pub const DEFAULT_LIBRARY_LOAD_WORKERS: usize = 4;
+
+/// Default number of artists requested from MPD in one command list
+pub const DEFAULT_LIBRARY_BATCH_SIZE: usize = 32;
+
 /// MPD library loader that can load a library from MPD
 pub struct MPDLibraryLoader {
-    /// MPD server hostname
-    hostname: String,
-    
-    /// MPD server port
-    port: u16,
+    /// MPD server address and password
+    address: MpdAddress,
     
     /// Reference to the MPDPlayerController that owns this library
     controller: Arc<MPDPlayerController>,
+
+    /// Number of connections reading songs in parallel
+    workers: usize,
+
+    /// Number of artists requested in one command list
+    batch_size: usize,
 }
 
 impl MPDLibraryLoader {
     /// Create a new MPD library loader with specific connection details
-    pub fn new(hostname: &str, port: u16, controller: Arc<MPDPlayerController>) -> Self {
-        debug!("Creating new MPDLibraryLoader with connection {}:{}", hostname, port);
+    pub fn new(address: MpdAddress, controller: Arc<MPDPlayerController>) -> Self {
+        debug!("Creating new MPDLibraryLoader with connection {}", address);
         
         MPDLibraryLoader {
-            hostname: hostname.to_string(),
-            port,
+            address,
+            workers: controller.get_library_load_workers(),
+            batch_size: controller.get_library_batch_size(),
             controller,
         }
     }
@@ -127,8 +143,12 @@ impl MPDLibraryLoader {
             Track::new(Some(disc_number), Some(track_number), track_name.to_string())
         };
         
-        // Add URI to the track and return it
-        track.with_uri(uri)
+        // Add URI and duration to the track and return it
+        let track = track.with_uri(uri);
+        match song.duration {
+            Some(duration) => track.with_duration(duration.as_secs_f64()),
+            None => track,
+        }
     }
     
     /// Create an Album object from an MPD song
@@ -242,12 +262,11 @@ impl MPDLibraryLoader {
     
     /// Load all album artists from the MPD server
     fn load_artists(&self) -> Result<Vec<String>, LibraryError> {
-        debug!("Loading album artists from MPD server at {}:{}", self.hostname, self.port);
+        debug!("Loading album artists from MPD server at {}", self.address);
         let start_time = Instant::now();
         
         // Create a fresh MPD client using the MPD crate
-        let conn_string = format!("{}:{}", self.hostname, self.port);
-        let mut client = mpd::Client::connect(&conn_string)
+        let mut client = self.address.client()
             .map_err(|e| LibraryError::ConnectionError(format!("Failed to connect to MPD: {}", e)))?;
         
         // Use the list command to get all artists
@@ -269,22 +288,74 @@ impl MPDLibraryLoader {
         Ok(albumartists)
     }
     
+    /// Add a song to the album it belongs to, creating the album if needed
+    fn add_song_to_albums(albums_map: &mut HashMap<String, crate::data::Album>, song: &mpd::Song, custom_separators: Option<&[String]>) {
+        // Create a unique key for the album based on song metadata
+        let album_key = Self::album_key(song);
+        let album = albums_map.entry(album_key)
+            .or_insert_with(|| Self::album_from_mpd_song(song, custom_separators));
+
+        // Add the track to the album's track list, but only if the track is not already present
+        let track = Self::track_from_mpd_song(song);
+        let mut tracks = album.tracks.lock();
+        if !tracks.iter().any(|t| t.name == track.name && t.disc_number == track.disc_number) {
+            tracks.push(track);
+        }
+        drop(tracks);
+
+        // Merge genres from this song into the album (deduplicated)
+        for genre in song.tags.iter()
+            .filter(|(tag, _)| tag == "Genre")
+            .map(|(_, v)| v.as_str())
+        {
+            if !album.genres.iter().any(|g| g == genre) {
+                album.genres.push(genre.to_string());
+            }
+        }
+    }
+
+    /// Fetch the songs of a batch of artists, reusing the worker's connection
+    ///
+    /// If the batched request fails, the artists are requested one by one.
+    fn fetch_batch(&self, connection: &mut Option<BatchConnection>, artists: &[String]) -> Result<Vec<mpd::Song>, LibraryError> {
+        if connection.is_none() {
+            *connection = BatchConnection::connect(&self.address).ok();
+        }
+        if let Some(conn) = connection.as_mut() {
+            match conn.find_artists(artists) {
+                Ok(songs) => return Ok(songs.into_iter().flatten().collect()),
+                Err(e) => {
+                    warn!("Batched MPD request for {} artists failed, requesting them one by one: {}", artists.len(), e);
+                    *connection = None;
+                }
+            }
+        }
+
+        let mut songs = Vec::new();
+        for artist in artists {
+            songs.extend(self.fetch_all_songs_for_artist(artist)?);
+        }
+        Ok(songs)
+    }
+
     /// Load albums from MPD
+    ///
+    /// Songs are read by several workers, each requesting batches of artists over its own
+    /// connection. Progress is reported as library load progress events with the number of
+    /// artists loaded and albums processed.
     pub fn load_albums_from_mpd(&self, custom_separators: Option<Vec<String>>) -> Result<Vec<crate::data::Album>, LibraryError> {
-        // Use separate job IDs for loading data and processing songs
+        // Use separate job IDs for loading data and processing albums
         let load_job_id = "mpd_load_data".to_string();
-        let process_job_id = "mpd_process_songs".to_string();
+        let process_job_id = "mpd_process_albums".to_string();
         
-        // Register background job for data loading
-        if let Err(e) = register_job(load_job_id.clone(), "MPD Load Data".to_string()) {
-            warn!("Failed to register background job for MPD data loading: {}", e);
-        }
+        // Register background job for data loading, it can be cancelled between two batches
+        let cancellation = register_cancellable_job(load_job_id.clone(), "MPD Load Data".to_string());
         
         // progress indicator (f32 0.0..100.0)
         let mut progress: f32 = 0.0;
         self.controller.notify_database_update(Some("Starting MPD database import".to_string()), None, None, Some(progress)); 
 
-        info!("Loading MPD library from {}:{}", self.hostname, self.port);
+        info!("Loading MPD library from {}", self.address);
         let start_time = Instant::now();
         
         // Step 1: Load all artists
@@ -295,188 +366,145 @@ impl MPDLibraryLoader {
                 return Err(e);
             }
         };
+        let total_artists = artists.len();
         
-        info!("Found {} artists in MPD database", artists.len());
+        info!("Found {} artists in MPD database", total_artists);
         progress = 10.0; // Update progress to 10%
         
         // Update background job progress
-        let _ = update_job(&load_job_id, Some("Loading artists".to_string()), None, Some(artists.len()));
+        let _ = update_job(&load_job_id, Some("Loading artists".to_string()), Some(0), Some(total_artists));
         
         // Send database update event to show initial progress
-        // Note: We no longer need to pass the source parameter
         self.controller.notify_database_update(Some("Loading artists".to_string()), None, None, Some(progress));
+        self.controller.notify_library_load_progress(0, total_artists, 0, 0);
 
-        info!("Sent notify - this is INFO level");
-        warn!("This is WARN level for testing");
-        error!("This is ERROR level for testing");
-        
-        debug!("Database loading progress: {:.1}%", progress);
+        // Step 2: Load the songs of all artists in parallel and group them into albums
+        let batches: Vec<&[String]> = artists.chunks(self.batch_size.max(1)).collect();
+        let workers = self.workers.clamp(1, batches.len().max(1));
+        info!("Loading songs of {} artists in {} batches with {} workers", total_artists, batches.len(), workers);
 
-        // Step 2: Load all songs for each album artist
-        let mut all_songs = Vec::new();
-        for (artist_index, artist) in artists.iter().enumerate() {
-            // more verbose logging for "real" artists
-            if artist.contains(",") {
-                debug!("Loading songs for artist: {}", artist);
-            } else {
-                info!("Loading songs for artist: {}", artist);
+        let next_batch = AtomicUsize::new(0);
+        let artists_loaded = AtomicUsize::new(0);
+        let albums_map: Mutex<HashMap<String, crate::data::Album>> = Mutex::new(HashMap::new());
+        let failure: Mutex<Option<LibraryError>> = Mutex::new(None);
+        let separators = custom_separators.as_deref();
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    let mut connection = None;
+                    while failure.lock().is_none() && !cancellation.is_cancelled() {
+                        let Some(batch) = batches.get(next_batch.fetch_add(1, Ordering::SeqCst)) else {
+                            break;
+                        };
+                        let songs = match self.fetch_batch(&mut connection, batch) {
+                            Ok(songs) => songs,
+                            Err(e) => {
+                                failure.lock().get_or_insert(e);
+                                break;
+                            }
+                        };
+                        debug!("Loaded {} songs for {} artists", songs.len(), batch.len());
+
+                        let albums_found = {
+                            let mut map = albums_map.lock();
+                            for song in &songs {
+                                Self::add_song_to_albums(&mut map, song, separators);
+                            }
+                            map.len()
+                        };
+
+                        let loaded = artists_loaded.fetch_add(batch.len(), Ordering::SeqCst) + batch.len();
+                        let current_artist = batch.last().cloned().unwrap_or_default();
+                        let _ = update_job(&load_job_id,
+                            Some(format!("Loaded songs for {}/{} artists: {}", loaded, total_artists, current_artist)),
+                            Some(loaded), Some(total_artists));
+                        let load_progress = 10.0 + 70.0 * loaded as f32 / total_artists.max(1) as f32;
+                        self.controller.notify_database_update(Some(current_artist), None, None, Some(load_progress));
+                        self.controller.notify_library_load_progress(loaded, total_artists, 0, albums_found);
+                    }
+                });
             }
-            
-            // Update background job progress for artist processing
-            let artist_progress = format!("Loading songs for artist {}/{}: {}", 
-                artist_index + 1, artists.len(), artist);
-            let _ = update_job(&load_job_id, Some(artist_progress), Some(artist_index + 1), Some(artists.len()));
-            
-            // Fetch all songs for this artist
-            let songs = match self.fetch_all_songs_for_artist(artist) {
-                Ok(songs) => songs,
-                Err(e) => {
-                    let _ = complete_job(&load_job_id);
-                    return Err(e);
-                }
-            };
-            debug!("Found {} songs for album artist '{}'", songs.len(), artist);
-            all_songs.extend(songs);
-        }
-        progress = 20.0; // Update progress to 20%
-        
+        });
+
         // Complete the data loading job
         if let Err(e) = complete_job(&load_job_id) {
             warn!("Failed to complete data loading job {}: {}", load_job_id, e);
         }
-        
-        // Register background job for song processing
-        if let Err(e) = register_job(process_job_id.clone(), "MPD Process Songs".to_string()) {
-            warn!("Failed to register background job for MPD song processing: {}", e);
+        if let Some(e) = failure.into_inner() {
+            error!("Failed to load songs from MPD: {}", e);
+            return Err(e);
         }
-        
-        // Update background job for song processing phase
-        let _ = update_job(&process_job_id, Some("Processing songs".to_string()), None, Some(all_songs.len()));
-        
-        // Send database update event to show progress
-        self.controller.notify_database_update(Some("Processing songs".to_string()), None, None, Some(progress));
-        
-        debug!("Database loading progress: {:.1}%", progress);
-
-        info!("Loaded {} songs in total", all_songs.len());
-
-        // Step 3: Create album objects from songs
-        // use a HashMap with album ID as key to avoid duplicates
-        // This will also help in tracking the number of unique albums
-        // and their associated tracks
-        let mut albums_map: HashMap<String, crate::data::Album> = std::collections::HashMap::new();
-        let total_songs = all_songs.len();
-        let songs_per_progress_point = (90.0 - 20.0) / (total_songs as f32);
-        
-        for (index, song) in all_songs.iter().enumerate() {
-            // Create a unique key for the album based on song metadata
-            let album_key = Self::album_key(song);
-
-            // check if the album already exists in the map
-            if !albums_map.contains_key(&album_key) {
-                // Create an album object from the song, using custom separators if provided
-                let album = Self::album_from_mpd_song(song, custom_separators.as_deref());
-                // Insert into the map using the album ID as key
-                albums_map.insert(album_key.clone(), album);
-            }
+        if cancellation.is_cancelled() {
+            info!("Loading the MPD library was cancelled after {} artists", artists_loaded.load(Ordering::SeqCst));
+            return Err(LibraryError::InternalError("Library load cancelled".to_string()));
+        }
+        progress = 80.0;
 
-            // create a track object from the song
-            let track = Self::track_from_mpd_song(song);
+        // Step 3: Complete the albums, this loads cached genres and sorts the tracks
+        let mut albums: Vec<crate::data::Album> = albums_map.into_inner().into_values().collect();
+        let total_albums = albums.len();
+        info!("Created {} unique albums from songs", total_albums);
 
-            // Add the track to the album's track list, but only if the track is not already present
-            // Also merge any new genres from this song into the album
-            if let Some(album) = albums_map.get_mut(&album_key) {
-                // Check if the track is already present in the album's track list
-                let mut tracks = album.tracks.lock();
-                if !tracks.iter().any(|t| t.name == track.name && t.disc_number == track.disc_number) {
-                    tracks.push(track);
-                }
-                drop(tracks);
-                // Merge genres from this song into the album (deduplicated)
-                for genre in song.tags.iter()
-                    .filter(|(tag, _)| tag == "Genre")
-                    .map(|(_, v)| v.as_str())
-                {
-                    if !album.genres.iter().any(|g| g == genre) {
-                        album.genres.push(genre.to_string());
-                    }
-                }
-            } else {
-                error!("Album not found in map for key: {}", album_key);
-            }
-            
-            // Update progress every PROGRESS_UPDATE_FREQUENCY songs or on the last song
-            if index % PROGRESS_UPDATE_FREQUENCY == 0 || index == total_songs - 1 {
-                // Calculate progress (range 20-90%)
-                progress = 20.0 + (index as f32 * songs_per_progress_point);
-                progress = progress.min(90.0); // Cap at 90%
-                
-                debug!("Album processing progress: {:.1}% ({}/{} songs)", progress, index + 1, total_songs);
-                
-                // Get album and artist names for the current song
-                let album_name = song.tags.iter()
-                    .find(|(tag, _)| tag == "Album")
-                    .map(|(_, value)| value.as_str())
-                    .unwrap_or("Unknown Album").to_string();
-                
-                let artist_name = song.tags.iter()
-                    .find(|(tag, _)| tag == "Artist")
-                    .map(|(_, value)| value.as_str())
-                    .unwrap_or("Unknown Artist").to_string();
-                
-                let song_name = song.title.as_deref()
-                    .unwrap_or("Unknown Song").to_string();
-                
-                // Update background job with current song processing
-                let song_progress = format!("Processing song {}/{}: {} - {}", 
-                    index + 1, total_songs, artist_name, song_name);
-                let _ = update_job(&process_job_id, Some(song_progress), Some(index + 1), Some(total_songs));
-                
-                // Send database update event with current item details
-                self.controller.notify_database_update(Some(artist_name), Some(album_name), Some(song_name), Some(progress));
-                
-                debug!("Database loading progress: {:.1}%", progress);
-            }
+        if let Err(e) = register_job(process_job_id.clone(), "MPD Process Albums".to_string()) {
+            warn!("Failed to register background job for MPD album processing: {}", e);
         }
-        
-        info!("Created {} unique albums from songs", albums_map.len());
-        
-        // Move albums from HashMap to vector; load any cached genres while we have ownership
-        let mut albums = Vec::with_capacity(albums_map.len());
-        for (_, mut album) in albums_map.drain() {
-            // If the album has no genres from file tags, try the attribute cache
-            if album.genres.is_empty() {
-                let album_id = album.id.to_string();
-                if let Some(cached) = crate::helpers::albumupdater::load_cached_genres(&album_id) {
-                    if !cached.is_empty() {
-                        debug!("Loaded {} cached genre(s) for album '{}'", cached.len(), album.name);
-                        album.genres = cached;
+        let _ = update_job(&process_job_id, Some("Processing albums".to_string()), Some(0), Some(total_albums));
+        self.controller.notify_database_update(Some("Processing albums".to_string()), None, None, Some(progress));
+        self.controller.notify_library_load_progress(total_artists, total_artists, 0, total_albums);
+
+        let albums_processed = AtomicUsize::new(0);
+        let chunk_size = total_albums.div_ceil(workers).max(1);
+        thread::scope(|scope| {
+            for chunk in albums.chunks_mut(chunk_size) {
+                let albums_processed = &albums_processed;
+                let process_job_id = &process_job_id;
+                scope.spawn(move || {
+                    for album in chunk {
+                        // If the album has no genres from file tags, try the attribute cache
+                        if album.genres.is_empty() {
+                            let album_id = album.id.to_string();
+                            if let Some(cached) = crate::helpers::albumupdater::load_cached_genres(&album_id) {
+                                if !cached.is_empty() {
+                                    debug!("Loaded {} cached genre(s) for album '{}'", cached.len(), album.name);
+                                    album.genres = cached;
+                                }
+                            }
+                        }
+                        // Sort the tracks by disc and track number
+                        album.sort_tracks();
+
+                        let processed = albums_processed.fetch_add(1, Ordering::SeqCst) + 1;
+                        if processed % PROGRESS_UPDATE_FREQUENCY == 0 {
+                            let _ = update_job(process_job_id,
+                                Some(format!("Processing album {}/{}: {}", processed, total_albums, album.name)),
+                                Some(processed), Some(total_albums));
+                            self.controller.notify_library_load_progress(total_artists, total_artists, processed, total_albums);
+                        }
                     }
-                }
+                });
             }
-            // Sort the tracks by disc and track number before adding to the result
-            album.sort_tracks();
-            albums.push(album);
-        }
-        
+        });
+
         // Final progress update (99%)
         progress = 99.0;
         
         // Update background job with final status
-        let final_progress = format!("Library load complete: {} albums created", albums.len());
-        let _ = update_job(&process_job_id, Some(final_progress), Some(albums.len()), Some(albums.len()));
+        let final_progress = format!("Library load complete: {} albums created", total_albums);
+        let _ = update_job(&process_job_id, Some(final_progress), Some(total_albums), Some(total_albums));
         
-        // Send the final database update event
+        // Send the final database update events
+        self.controller.notify_library_load_progress(total_artists, total_artists, total_albums, total_albums);
         self.controller.notify_database_update(Some("Library load complete".to_string()), None, None, Some(progress));
         
         debug!("Database loading progress: {:.1}%", progress);
         
         let elapsed = start_time.elapsed();
-        info!("Loaded {} albums in {:?}", albums.len(), elapsed);
+        info!("Loaded {} albums in {:?}", total_albums, elapsed);
         
-        // Complete the song processing background job
+        // Complete the album processing background job
         if let Err(e) = complete_job(&process_job_id) {
-            warn!("Failed to complete song processing job {}: {}", process_job_id, e);
+            warn!("Failed to complete album processing job {}: {}", process_job_id, e);
         }
         
         Ok(albums)
@@ -487,8 +515,7 @@ impl MPDLibraryLoader {
         debug!("Fetching all songs for artist: {}", artist_name);
         
         // Create a new MPD client connection
-        let conn_string = format!("{}:{}", self.hostname, self.port);
-        let mut client = mpd::Client::connect(&conn_string)
+        let mut client = self.address.client()
             .map_err(|e| LibraryError::ConnectionError(format!("Failed to connect to MPD: {}", e)))?;
         
         // Use the MPD find command to get all songs by this artist
@@ -508,4 +535,147 @@ impl MPDLibraryLoader {
         Ok(songs)
     }
     
-}
\ No newline at end of file
+}
+
+/// Connection to MPD that requests the songs of several artists with one command list
+struct BatchConnection {
+    reader: BufReader<MpdStream>,
+    writer: MpdStream,
+}
+
+impl BatchConnection {
+    fn connect(address: &MpdAddress) -> Result<Self, LibraryError> {
+        // Large artists can take a while, but a stalled server must not block the load forever
+        let (reader, writer) = address.connect_raw(Some(Duration::from_secs(60)))
+            .map_err(|e| LibraryError::ConnectionError(format!("Failed to connect to MPD: {}", e)))?;
+        Ok(BatchConnection { reader, writer })
+    }
+
+    /// Find the songs of each artist, the result has one list per artist
+    fn find_artists(&mut self, artists: &[String]) -> Result<Vec<Vec<mpd::Song>>, LibraryError> {
+        let mut command = String::from("command_list_ok_begin\n");
+        for artist in artists {
+            command.push_str(&format!("find Artist \"{}\"\n", escape_mpd_argument(artist)));
+        }
+        command.push_str("command_list_end\n");
+
+        self.writer.write_all(command.as_bytes())
+            .map_err(|e| LibraryError::ConnectionError(format!("Failed to send find command list to MPD: {}", e)))?;
+        parse_find_responses(&mut self.reader, artists.len())
+    }
+}
+
+/// Parse the response of a command list of find commands
+///
+/// Each command's songs are terminated by `list_OK`, the whole response by `OK`. Song
+/// attributes are mapped like the mpd crate does, other keys are kept as tags.
+fn parse_find_responses<R: BufRead>(reader: &mut R, commands: usize) -> Result<Vec<Vec<mpd::Song>>, LibraryError> {
+    let mut results = Vec::with_capacity(commands);
+    let mut songs = Vec::new();
+    let mut current: Option<mpd::Song> = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)
+            .map_err(|e| LibraryError::ConnectionError(format!("Failed to read from MPD: {}", e)))?;
+        if read == 0 {
+            return Err(LibraryError::ConnectionError("MPD closed the connection".to_string()));
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line == "list_OK" {
+            songs.extend(current.take());
+            results.push(std::mem::take(&mut songs));
+            continue;
+        }
+        if line == "OK" {
+            break;
+        }
+        if let Some(error) = line.strip_prefix("ACK ") {
+            return Err(LibraryError::ConnectionError(format!("MPD error: {}", error)));
+        }
+
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        if key == "file" {
+            songs.extend(current.replace(mpd::Song { file: value.to_string(), ..Default::default() }));
+            continue;
+        }
+        // Attributes before the first file belong to directories or playlists
+        let Some(song) = current.as_mut() else {
+            continue;
+        };
+        match key {
+            "Title" => song.title = Some(value.to_string()),
+            "Last-Modified" => song.last_mod = Some(value.to_string()),
+            "Artist" => song.artist = Some(value.to_string()),
+            "Name" => song.name = Some(value.to_string()),
+            "Time" => song.duration = value.parse().ok().map(Duration::from_secs),
+            _ => song.tags.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    if results.len() != commands {
+        return Err(LibraryError::ConnectionError(format!(
+            "MPD returned {} results for {} find commands", results.len(), commands)));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_find_responses() {
+        let response = "file: a/01.flac\nTitle: One\nArtist: A\nAlbum: First\nGenre: Jazz\nTime: 61\n\
+            file: a/02.flac\nTitle: Two\nAlbum: First\nlist_OK\nlist_OK\nfile: b/01.flac\nArtist: B\nlist_OK\nOK\n";
+        let results = parse_find_responses(&mut response.as_bytes(), 3).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].len(), 2);
+        assert_eq!(results[0][0].title.as_deref(), Some("One"));
+        assert_eq!(results[0][0].artist.as_deref(), Some("A"));
+        assert_eq!(results[0][0].duration, Some(Duration::from_secs(61)));
+        assert_eq!(results[0][0].tags, vec![
+            ("Album".to_string(), "First".to_string()),
+            ("Genre".to_string(), "Jazz".to_string()),
+        ]);
+        assert!(results[1].is_empty());
+        assert_eq!(results[2][0].file, "b/01.flac");
+    }
+
+    #[test]
+    fn test_parse_find_error() {
+        let response = "file: a/01.flac\nlist_OK\nACK [2@1] {find} wrong number of arguments\n";
+        assert!(parse_find_responses(&mut response.as_bytes(), 2).is_err());
+        // A truncated response is an error as well
+        assert!(parse_find_responses(&mut "list_OK\nOK\n".as_bytes(), 2).is_err());
+    }
+
+    #[test]
+    fn test_group_songs_into_albums() {
+        let song = |file: &str, title: &str, genre: &str| mpd::Song {
+            file: file.to_string(),
+            title: Some(title.to_string()),
+            tags: vec![
+                ("Album".to_string(), "Kind of Blue".to_string()),
+                ("AlbumArtist".to_string(), "Miles Davis".to_string()),
+                ("Genre".to_string(), genre.to_string()),
+            ],
+            ..Default::default()
+        };
+
+        let mut albums = HashMap::new();
+        MPDLibraryLoader::add_song_to_albums(&mut albums, &song("1.flac", "So What", "Jazz"), None);
+        MPDLibraryLoader::add_song_to_albums(&mut albums, &song("2.flac", "Freddie Freeloader", "Modal Jazz"), None);
+        MPDLibraryLoader::add_song_to_albums(&mut albums, &song("1.flac", "So What", "Jazz"), None);
+
+        assert_eq!(albums.len(), 1);
+        let album = albums.values().next().unwrap();
+        assert_eq!(album.tracks.lock().len(), 2);
+        assert_eq!(album.genres, vec!["Jazz", "Modal Jazz"]);
+    }
+}
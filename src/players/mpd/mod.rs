@@ -7,3 +7,27 @@ pub mod library;
 
 // Export the MPD library loader
 mod libraryloader;
+
+// Export the music directory watcher
+mod dirwatcher;
+
+// TCP and unix socket connections with authentication
+pub mod connection;
+
+// Pool of MPD command connections
+mod pool;
+
+// Export the stored playlists
+pub mod playlists;
+
+// Export the audio outputs
+pub mod outputs;
+
+// Export the sticker based ratings and play counts
+pub mod stickers;
+
+// Export the ReplayGain and crossfade settings
+pub mod playback_options;
+
+// Export the database search
+pub mod search;
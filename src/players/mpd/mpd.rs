@@ -1,12 +1,19 @@
 use crate::players::player_controller::{BasePlayerController, PlayerController};
-use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, QueueTrackMetadata, Track};
 use crate::data::library::LibraryInterface;
 use crate::constants::API_PREFIX;
 use crate::helpers::retry::RetryHandler;
 use crate::helpers::url_encoding;
-use crate::helpers::songsplitmanager::SongSplitManager;
+use crate::helpers::songsplitmanager::{SongSplitManager, SPLIT_TITLE_SOURCE_KEY};
 use crate::helpers::attributecache;
 use crate::helpers::backgroundjobs::BackgroundJobs;
+use crate::helpers::streamcheck::StreamSource;
+use crate::players::mpd::libraryloader::{DEFAULT_LIBRARY_BATCH_SIZE, DEFAULT_LIBRARY_LOAD_WORKERS};
+use crate::players::mpd::playlists::track_from_mpd_song;
+use crate::players::mpd::connection::{MpdAddress, MpdStream};
+use crate::players::mpd::pool::{ConnectionPool, PooledClient};
+use crate::players::mpd::stickers::{self, PLAY_COUNT_KEY, RATING_KEY};
+use crate::players::mpd::playback_options::{self, CONSUME_KEY, CROSSFADE_KEY, MIXRAMPDB_KEY, REPLAY_GAIN_MODE_KEY, SINGLE_KEY};
 use delegate::delegate;
 use std::sync::Arc;
 use parking_lot::Mutex;
@@ -15,7 +22,6 @@ use std::io::{BufRead, BufReader};
 use log::{debug, info, warn, error, trace};
 use mpd::{Client, error::Error as MpdError, idle::Subsystem};
 use mpd::Idle; // Add the Idle trait import
-use std::net::TcpStream;
 use std::thread;
 use std::time::Duration;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -33,11 +39,8 @@ pub struct MPDPlayerController {
     /// Base controller for managing state listeners
     base: BasePlayerController,
     
-    /// MPD server hostname
-    hostname: String,
-    
-    /// MPD server port
-    port: u16,
+    /// MPD server address and password
+    address: MpdAddress,
     
     /// Current song information
     current_song: Arc<Mutex<Option<Song>>>,
@@ -65,6 +68,15 @@ pub struct MPDPlayerController {
 
     /// If true, the library is read-only and deletion is not supported
     library_read_only: bool,
+
+    /// Watch the music directory and ask MPD to update changed directories
+    watch_music_directory: bool,
+
+    /// Number of connections reading the library from MPD in parallel
+    library_load_workers: usize,
+
+    /// Number of artists requested from MPD in one command list
+    library_batch_size: usize,
     
     /// Cached effective music directory (to avoid parsing /etc/mpd.conf repeatedly)
     effective_music_directory: Arc<Mutex<Option<String>>>,
@@ -80,6 +92,12 @@ pub struct MPDPlayerController {
     
     /// Flag indicating if connection has been permanently disabled due to max attempts
     connection_disabled: Arc<AtomicBool>,
+
+    /// Socket of the idle connection, shut down to force a reconnect after network changes
+    idle_stream: Arc<Mutex<Option<MpdStream>>>,
+
+    /// Command connections reused between calls
+    connection_pool: Arc<ConnectionPool>,
     
     /// Song title splitter manager for radio stations that combine artist and song in title
     song_split_manager: SongSplitManager,
@@ -94,8 +112,7 @@ impl Clone for MPDPlayerController {
         MPDPlayerController {
             // Share the BasePlayerController instance to maintain listener registrations
             base: self.base.clone(),
-            hostname: self.hostname.clone(),
-            port: self.port,
+            address: self.address.clone(),
             current_song: Arc::clone(&self.current_song),
             current_state: Arc::clone(&self.current_state),
             current_stream_details: Arc::clone(&self.current_stream_details),
@@ -109,9 +126,14 @@ impl Clone for MPDPlayerController {
             max_reconnect_attempts: self.max_reconnect_attempts,
             reconnect_attempts: Arc::clone(&self.reconnect_attempts),
             connection_disabled: Arc::clone(&self.connection_disabled),
+            idle_stream: Arc::clone(&self.idle_stream),
+            connection_pool: Arc::clone(&self.connection_pool),
             song_split_manager: self.song_split_manager.clone(),
             current_update_job_id: Arc::clone(&self.current_update_job_id),
             library_read_only: self.library_read_only,
+            watch_music_directory: self.watch_music_directory,
+            library_load_workers: self.library_load_workers,
+            library_batch_size: self.library_batch_size,
         }
     }
 }
@@ -134,8 +156,7 @@ impl MPDPlayerController {
         
         let player = Self {
             base,
-            hostname: host.to_string(),
-            port,
+            address: MpdAddress::tcp(host, port),
             current_song: Arc::new(Mutex::new(None)),
             current_state: Arc::new(Mutex::new(PlayerState::new())),
             current_stream_details: Arc::new(Mutex::new(None)),
@@ -145,11 +166,16 @@ impl MPDPlayerController {
             artist_separators: None,
             music_directory: String::new(),
             library_read_only: false,
+            watch_music_directory: false,
+            library_load_workers: DEFAULT_LIBRARY_LOAD_WORKERS,
+            library_batch_size: DEFAULT_LIBRARY_BATCH_SIZE,
             effective_music_directory: Arc::new(Mutex::new(None)),
             library: Arc::new(Mutex::new(None)),
             max_reconnect_attempts: 5, // Default value
             reconnect_attempts: Arc::new(Mutex::new(0)),
             connection_disabled: Arc::new(AtomicBool::new(false)),
+            idle_stream: Arc::new(Mutex::new(None)),
+            connection_pool: Arc::new(ConnectionPool::new(MpdAddress::tcp(host, port))),
             song_split_manager: SongSplitManager::new(),
             current_update_job_id: Arc::new(Mutex::new(None)),
         };
@@ -162,15 +188,19 @@ impl MPDPlayerController {
     
     /// Create a new MPD player controller with custom settings
     pub fn with_connection(hostname: &str, port: u16) -> Self {
-        debug!("Creating new MPDPlayerController with connection {}:{}", hostname, port);
+        Self::with_address(MpdAddress::tcp(hostname, port))
+    }
+
+    /// Create a new MPD player controller for a TCP or unix socket address, optionally with a password
+    pub fn with_address(address: MpdAddress) -> Self {
+        debug!("Creating new MPDPlayerController with connection {}", address);
         
         // Create a base controller with player name and ID
-        let base = BasePlayerController::with_player_info("mpd", &format!("{}:{}", hostname, port));
+        let base = BasePlayerController::with_player_info("mpd", &address.id());
         
         let player = Self {
             base,
-            hostname: hostname.to_string(),
-            port,
+            address: address.clone(),
             current_song: Arc::new(Mutex::new(None)),
             current_state: Arc::new(Mutex::new(PlayerState::new())),
             current_stream_details: Arc::new(Mutex::new(None)),
@@ -180,11 +210,16 @@ impl MPDPlayerController {
             artist_separators: None,
             music_directory: String::new(),
             library_read_only: false,
+            watch_music_directory: false,
+            library_load_workers: DEFAULT_LIBRARY_LOAD_WORKERS,
+            library_batch_size: DEFAULT_LIBRARY_BATCH_SIZE,
             effective_music_directory: Arc::new(Mutex::new(None)),
             library: Arc::new(Mutex::new(None)),
             max_reconnect_attempts: 5, // Default value
             reconnect_attempts: Arc::new(Mutex::new(0)),
             connection_disabled: Arc::new(AtomicBool::new(false)),
+            idle_stream: Arc::new(Mutex::new(None)),
+            connection_pool: Arc::new(ConnectionPool::new(address)),
             song_split_manager: SongSplitManager::new(),
             current_update_job_id: Arc::new(Mutex::new(None)),
         };
@@ -210,15 +245,17 @@ impl MPDPlayerController {
             PlayerCapability::Shuffle,
             PlayerCapability::Killable,
             PlayerCapability::Queue,
+            PlayerCapability::Consume,
+            PlayerCapability::Single,
         ], false); // Don't notify on initialization
     }
     
     /// Attempt to reconnect to the MPD server
     pub fn reconnect(&self) -> Result<(), MpdError> {
-        let addr = format!("{}:{}", self.hostname, self.port);
+        let addr = &self.address;
         debug!("Attempting to reconnect to MPD at {}", addr);
         
-        match Client::connect(&addr) {
+        match addr.client() {
             Ok(_) => {
                 info!("Successfully reconnected to MPD at {}", addr);
                 self.reset_reconnect_attempts(); // Reset counter on successful connection
@@ -233,8 +270,8 @@ impl MPDPlayerController {
     
     /// Check if connected to MPD server
     pub fn is_connected(&self) -> bool {
-        // Create a fresh connection to check connectivity
-        if let Some(mut client) = self.get_fresh_client() {
+        // Get a connection from the pool to check connectivity
+        if let Some(mut client) = self.get_client() {
             // Try a simple ping to verify the connection
             match client.ping() {
                 Ok(_) => {
@@ -252,19 +289,24 @@ impl MPDPlayerController {
     
     /// Get the current MPD server hostname
     pub fn hostname(&self) -> &str {
-        &self.hostname
+        &self.address.hostname
     }
     
     /// Get the current MPD server port
     pub fn port(&self) -> u16 {
-        self.port
+        self.address.port
+    }
+
+    /// Get the MPD server address, including the unix socket and password
+    pub fn address(&self) -> &MpdAddress {
+        &self.address
     }
     
     /// Update the connection settings and reconnect
     pub fn set_connection(&mut self, hostname: &str, port: u16) {
         debug!("Updating MPD connection to {}:{}", hostname, port);
-        self.hostname = hostname.to_string();
-        self.port = port;
+        self.address.hostname = hostname.to_string();
+        self.address.port = port;
     }
     
     /// Get whether to load MPD library into memory
@@ -323,6 +365,36 @@ impl MPDPlayerController {
         self.library_read_only = read_only;
     }
     
+    /// Get whether the music directory is watched for changes
+    pub fn get_watch_music_directory(&self) -> bool {
+        self.watch_music_directory
+    }
+
+    /// Get the number of connections reading the library in parallel
+    pub fn get_library_load_workers(&self) -> usize {
+        self.library_load_workers
+    }
+
+    /// Set the number of connections reading the library in parallel
+    pub fn set_library_load_workers(&mut self, workers: usize) {
+        self.library_load_workers = workers.max(1);
+    }
+
+    /// Get the number of artists requested from MPD in one command list
+    pub fn get_library_batch_size(&self) -> usize {
+        self.library_batch_size
+    }
+
+    /// Set the number of artists requested from MPD in one command list
+    pub fn set_library_batch_size(&mut self, batch_size: usize) {
+        self.library_batch_size = batch_size.max(1);
+    }
+
+    /// Set whether to watch the music directory for changes
+    pub fn set_watch_music_directory(&mut self, watch: bool) {
+        self.watch_music_directory = watch;
+    }
+    
     /// Get the effective music directory path
     /// If configured music_directory is empty, attempts to parse it from /etc/mpd.conf
     pub fn get_effective_music_directory(&self) -> Option<String> {
@@ -439,21 +511,9 @@ impl MPDPlayerController {
 
     /// Query MPD directly for its music_directory via the `config` command.
     fn query_music_directory_from_mpd(&self) -> Option<String> {
-        use std::io::{BufRead, BufReader, Write};
-        use std::net::TcpStream;
-
-        let stream = TcpStream::connect(format!("{}:{}", self.hostname, self.port)).ok()?;
-        stream.set_read_timeout(Some(std::time::Duration::from_secs(3))).ok()?;
+        use std::io::{BufRead, Write};
 
-        let mut reader = BufReader::new(stream.try_clone().ok()?);
-        let mut writer = stream;
-
-        // Read welcome line
-        let mut welcome = String::new();
-        reader.read_line(&mut welcome).ok()?;
-        if !welcome.starts_with("OK") {
-            return None;
-        }
+        let (reader, mut writer) = self.address.connect_raw(Some(std::time::Duration::from_secs(3))).ok()?;
 
         writer.write_all(b"config\n").ok()?;
 
@@ -511,6 +571,16 @@ impl MPDPlayerController {
         self.connection_disabled.load(Ordering::Relaxed)
     }
     
+    /// Get the maximum number of idle command connections
+    pub fn get_connection_pool_size(&self) -> usize {
+        self.connection_pool.size()
+    }
+
+    /// Set the maximum number of idle command connections, 0 opens a new connection for every call
+    pub fn set_connection_pool_size(&mut self, size: usize) {
+        self.connection_pool.set_size(size);
+    }
+    
     /// Get a reference to the MPD library, if available
     pub fn get_library(&self) -> Option<crate::players::mpd::library::MPDLibrary> {
         // Lock the mutex and clone the library if it exists
@@ -555,6 +625,45 @@ impl MPDPlayerController {
         self.artist_separators.as_deref()
     }
     
+    /// Get all HTTP stream URLs saved in MPD stored playlists
+    pub fn get_stored_playlist_streams(&self) -> Vec<StreamSource> {
+        let Some(mut client) = self.get_client() else {
+            return Vec::new();
+        };
+
+        let playlists = match client.playlists() {
+            Ok(playlists) => playlists,
+            Err(e) => {
+                warn!("Failed to list MPD stored playlists: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut streams = Vec::new();
+        for playlist in playlists {
+            match client.playlist(playlist.name.as_str()) {
+                Ok(songs) => {
+                    for song in songs {
+                        if song.file.starts_with("http://") || song.file.starts_with("https://") {
+                            streams.push(StreamSource {
+                                url: song.file,
+                                name: song.name.or(song.title),
+                                source: format!("mpd playlist: {}", playlist.name),
+                            });
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to read MPD stored playlist '{}': {}", playlist.name, e),
+            }
+        }
+        streams
+    }
+    
+    /// Get the song title splitter manager (shares state with the player)
+    pub fn get_song_split_manager(&self) -> SongSplitManager {
+        self.song_split_manager.clone()
+    }
+    
     /// Clear all title splitters (useful for cleanup or configuration changes)
     pub fn clear_title_splitters(&self) {
         self.song_split_manager.clear_all_splitters();
@@ -592,6 +701,17 @@ impl MPDPlayerController {
         self.base.notify_database_update(artist, album, song, percentage);
     }
     
+    /// Send a library load progress notification
+    pub fn notify_library_load_progress(&self, artists_loaded: usize, artists_total: usize,
+                                        albums_processed: usize, albums_total: usize) {
+        self.base.notify_library_load_progress(artists_loaded, artists_total, albums_processed, albums_total);
+    }
+
+    /// Send a notification about the albums changed by a library refresh
+    pub fn notify_library_changed(&self, diff: crate::data::LibraryDiff) {
+        self.base.notify_library_changed(diff);
+    }
+
     /// Initialize the MPD library with retry logic
     /// 
     /// This method attempts to initialize the library and will retry with exponential backoff
@@ -617,7 +737,7 @@ impl MPDPlayerController {
                     debug!("Attempting to initialize MPD library");
                     
                     // Try to connect to MPD to test connectivity
-                    if let Some(_client) = player_arc.get_fresh_client() {
+                    if let Some(_client) = player_arc.get_client() {
                         info!("Successfully connected to MPD, initializing library");
                         
                         // Import MPDLibrary here to ensure it's available
@@ -625,8 +745,7 @@ impl MPDPlayerController {
                         
                         // Create a library with the same connection parameters
                         let library = MPDLibrary::with_connection(
-                            &player_arc.hostname, 
-                            player_arc.port, 
+                            player_arc.address.clone(),
                             player_arc.clone()
                         );
                         
@@ -667,25 +786,23 @@ impl MPDPlayerController {
     /// Starts a background thread that listens for MPD events
     /// The thread will run until the running flag is set to false
     fn start_event_listener(&self, running: Arc<AtomicBool>, self_arc: Arc<Self>) {
-        let hostname = self.hostname.clone();
-        let port = self.port;
+        let address = self.address.clone();
         
         info!("Starting MPD event listener thread");
         
         // Spawn a new thread for event listening
         thread::spawn(move || {
             info!("MPD event listener thread started");
-            Self::run_event_loop(&hostname, port, running, self_arc);
+            Self::run_event_loop(&address, running, self_arc);
             info!("MPD event listener thread shutting down");
         });
     }
 
     /// Main event loop for listening to MPD events
-    fn run_event_loop(hostname: &str, port: u16, running: Arc<AtomicBool>, player_arc: Arc<Self>) {
+    fn run_event_loop(idle_addr: &MpdAddress, running: Arc<AtomicBool>, player_arc: Arc<Self>) {
         while running.load(Ordering::SeqCst) {
             // Try to establish a connection for idle mode
-            let idle_addr = format!("{}:{}", hostname, port);
-            let idle_client = match Client::connect(&idle_addr) {
+            let idle_client = match player_arc.connect_idle() {
                 Ok(client) => {
                     debug!("Connected to MPD for idle listening at {}", idle_addr);
                     player_arc.reset_reconnect_attempts(); // Reset counter on successful connection
@@ -731,8 +848,41 @@ impl MPDPlayerController {
         }
     }
     
+    /// Connect for idle mode and keep a handle of the socket to interrupt it later
+    fn connect_idle(&self) -> Result<Client<MpdStream>, MpdError> {
+        let stream = self.address.connect()?;
+        *self.idle_stream.lock() = stream.try_clone().ok();
+        self.address.login(stream)
+    }
+
+    /// Drop the idle connection and retry immediately after the network has changed
+    ///
+    /// A connection through a dropped Wi-Fi or a rebooted router may block until the
+    /// TCP timeout, so it is closed and the event listener connects again.
+    fn reconnect_after_network_change(&self, running: &Arc<AtomicBool>, self_arc: &Arc<Self>) {
+        if !running.load(Ordering::SeqCst) {
+            return;
+        }
+        info!("Network changed, reconnecting to MPD at {}", self.address);
+        let listener_stopped = self.are_connections_disabled();
+        if let Some(stream) = self.idle_stream.lock().take() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        self.connection_pool.clear();
+        self.reset_reconnect_attempts();
+        if listener_stopped {
+            // The listener gave up after too many failed attempts, start it again
+            self.start_event_listener(running.clone(), self_arc.clone());
+        }
+    }
+
+    /// Name of the network reconnect handler, None for a local MPD
+    fn network_handler_name(&self) -> Option<String> {
+        (!self.address.is_local()).then(|| format!("mpd:{}", self.address.id()))
+    }
+
     /// Process MPD events until connection fails or shutdown requested
-    fn process_events(mut idle_client: Client<TcpStream>, 
+    fn process_events(mut idle_client: Client<MpdStream>, 
                      running: &Arc<AtomicBool>, player: &Arc<Self>) {
         while running.load(Ordering::SeqCst) {
             let subsystems = match idle_client.idle(&[
@@ -772,7 +922,7 @@ impl MPDPlayerController {
             info!("Received MPD events: {}", events_str.join(", "));
             
             // Create a fresh command connection for handling events
-            if let Some(mut cmd_client) = player.get_fresh_client() {
+            if let Some(mut cmd_client) = player.get_client() {
                 // Process each subsystem event with our fresh connection
                 for subsystem in events {
                     Self::handle_subsystem_event(subsystem, &mut cmd_client, player.clone());
@@ -784,7 +934,7 @@ impl MPDPlayerController {
     }
     
     /// Handle a specific MPD subsystem event
-    fn handle_subsystem_event(subsystem: Subsystem, client: &mut Client<TcpStream>, player: Arc<Self>) {
+    fn handle_subsystem_event(subsystem: Subsystem, client: &mut Client<MpdStream>, player: Arc<Self>) {
         // mark player as alive
         player.base.alive();
 
@@ -795,8 +945,8 @@ impl MPDPlayerController {
                 Self::handle_player_event(client, player);
             },
             Subsystem::Playlist => {
-                warn!("Playlist changed");
-                // Could notify about playlist/song changes
+                debug!("Queue changed");
+                player.base.notify_queue_changed();
             },
             Subsystem::Options => {
                 warn!("Options changed (repeat, random, etc.)");
@@ -822,7 +972,7 @@ impl MPDPlayerController {
             Subsystem::Update => {
                 debug!("MPD database update status changed");
                 // Get fresh status to check the current update state
-                if let Some(mut status_client) = player.get_fresh_client() {
+                if let Some(mut status_client) = player.get_client() {
                     match status_client.status() {
                         Ok(status) => {
                             player.check_database_update_status(&status);
@@ -842,7 +992,7 @@ impl MPDPlayerController {
     }
     
     /// Handle player events and log song information
-    fn handle_player_event(client: &mut Client<TcpStream>, player: Arc<Self>) {
+    fn handle_player_event(client: &mut Client<MpdStream>, player: Arc<Self>) {
 
         // Update the song information and capabilities
         Self::update_song_from_mpd(client, player.clone());
@@ -913,7 +1063,17 @@ impl MPDPlayerController {
     /// Update the current song and notify listeners
     fn update_current_song(&self, song: Option<Song>) {
         // Enhance the song with cached metadata if available
-        let enhanced_song = song.map(|s| self.enhance_song_with_cache(s));
+        let mut enhanced_song = song.map(|s| self.enhance_song_with_cache(s));
+
+        // Count a play when playback moves on to another song, the song found at startup isn't counted
+        let previous_uri = self.current_song_uri();
+        if let (Some(previous_uri), Some(song)) = (previous_uri, enhanced_song.as_mut()) {
+            if let Some(uri) = song.stream_url.clone().filter(|uri| *uri != previous_uri) {
+                if let Some(count) = self.increment_play_count(&uri) {
+                    song.metadata.insert(PLAY_COUNT_KEY.to_string(), serde_json::Value::from(count));
+                }
+            }
+        }
         
         // Store the new song
         let mut current_song = self.current_song.lock();
@@ -935,23 +1095,40 @@ impl MPDPlayerController {
         }
     }
 
-    /// Create a fresh MPD client connection for sending commands
-    /// This creates a new connection each time, rather than reusing an existing one
-    pub fn get_fresh_client(&self) -> Option<Client<TcpStream>> {
+    /// URI of the current song
+    pub(super) fn current_song_uri(&self) -> Option<String> {
+        self.current_song.lock().as_ref().and_then(|song| song.stream_url.clone())
+    }
+
+    /// Set or remove a metadata value of the current song if it has the given URI
+    ///
+    /// Listeners are not notified, the value is returned with the song from now on.
+    pub(super) fn update_song_metadata(&self, uri: &str, key: &str, value: Option<serde_json::Value>) {
+        let mut current_song = self.current_song.lock();
+        if let Some(song) = current_song.as_mut().filter(|song| song.stream_url.as_deref() == Some(uri)) {
+            match value {
+                Some(value) => song.metadata.insert(key.to_string(), value),
+                None => song.metadata.remove(key),
+            };
+        }
+    }
+
+    /// Get an MPD client connection for sending commands
+    /// Connections come from the pool and are returned to it when the client is dropped
+    pub fn get_client(&self) -> Option<PooledClient> {
         // Check if connections have been disabled due to max reconnection attempts
         if self.are_connections_disabled() {
             debug!("MPD connections are disabled due to max reconnection attempts reached");
             return None;
         }
         
-        debug!("Creating fresh MPD command connection");
-        let addr = format!("{}:{}", self.hostname, self.port);
-        
-        match Client::connect(&addr) {
-            Ok(client) => {
-                debug!("Successfully created new MPD command connection");
-                // Reset connection attempts on successful connection
-                self.reset_reconnect_attempts();
+        match self.connection_pool.get() {
+            Ok((client, opened)) => {
+                if opened {
+                    debug!("Successfully created new MPD command connection");
+                    // Reset connection attempts on successful connection
+                    self.reset_reconnect_attempts();
+                }
                 Some(client)
             },
             Err(e) => {
@@ -972,7 +1149,7 @@ impl MPDPlayerController {
     /// - Available capabilities (Next/Previous/Seek)
     /// 
     /// Returns an updated song with lyrics metadata if applicable
-    fn update_state_and_capabilities_from_mpd(client: &mut Client<TcpStream>, player: Arc<Self>, song: Option<Song>) -> Option<Song> {
+    fn update_state_and_capabilities_from_mpd(client: &mut Client<MpdStream>, player: Arc<Self>, song: Option<Song>) -> Option<Song> {
         debug!("Updating player state and capabilities based on MPD status");
         
         let updated_song = song;
@@ -1232,6 +1409,8 @@ impl MPDPlayerController {
             .find(|(tag, _)| tag == "Genre")
             .map(|(_, value)| value.clone());
         
+        let mut metadata = HashMap::new();
+
         // Handle title splitting for radio stations
         let (final_title, final_artist) = if mpd_song.artist.is_none() && mpd_song.title.is_some() {
             // No artist but has title - try to split it (common for web radio)
@@ -1244,6 +1423,9 @@ impl MPDPlayerController {
                 // Try to split the title using the manager
                 if let Some((artist, song)) = player.song_split_manager.split_song(splitter_id, title_str) {
                     debug!("Split title '{}' into artist='{}', song='{}'", title_str, artist, song);
+
+                    // Mark the song so consumers know artist/title were guessed from a stream title
+                    metadata.insert(SPLIT_TITLE_SOURCE_KEY.to_string(), serde_json::Value::String(splitter_id.clone()));
                     
                     // Save the splitter state after successful split
                     if let Err(e) = player.song_split_manager.save(splitter_id) {
@@ -1280,12 +1462,12 @@ impl MPDPlayerController {
             source: Some("mpd".to_string()),
             liked: None,
             composer: None,
-            metadata: HashMap::new(),
+            metadata,
         }
     }
     
     /// Update the player's current song from MPD
-    fn update_song_from_mpd(client: &mut Client<TcpStream>, player: Arc<Self>) {
+    fn update_song_from_mpd(client: &mut Client<MpdStream>, player: Arc<Self>) {
         // Variable to store the obtained song for later use in updating capabilities
         let mut obtained_song: Option<Song> = None;
         
@@ -1294,10 +1476,21 @@ impl MPDPlayerController {
             Ok(song_opt) => {
                 if let Some(mpd_song) = song_opt {
                     // Convert MPD song to our Song format
+                    let uri = mpd_song.file.clone();
                     let mut song = Self::convert_mpd_song(mpd_song, Some(player.clone()));
+
+                    // Add the rating and play count from the sticker database
+                    let (rating, play_count) = stickers::read_rating_and_play_count(client, &uri);
+                    if let Some(rating) = rating {
+                        song.metadata.insert(RATING_KEY.to_string(), serde_json::Value::from(rating));
+                    }
+                    if let Some(play_count) = play_count {
+                        song.metadata.insert(PLAY_COUNT_KEY.to_string(), serde_json::Value::from(play_count));
+                    }
                     
                     // Check for lyrics and add to song metadata
-                    if let Some(library) = player.get_library() {
+                    let lyrics_enabled = crate::helpers::enrichment::settings_for_player(&player.get_player_name(), &player.get_player_id()).lyrics;
+                    if let Some(library) = player.get_library().filter(|_| lyrics_enabled) {
                         if let Some(music_dir) = library.get_music_directory() {
                             use crate::helpers::lyrics::{MPDLyricsProvider, LyricsProvider};
                             let lyrics_provider = MPDLyricsProvider::new(music_dir.clone());
@@ -1396,7 +1589,7 @@ impl MPDPlayerController {
     pub fn queue_url(&self, url: &str, at_beginning: Option<bool>) -> bool {
         debug!("Adding URL to queue: {}, at_beginning: {:?}", url, at_beginning);
         
-        if let Some(mut client) = self.get_fresh_client() {
+        if let Some(mut client) = self.get_client() {
             // Use the appropriate method based on whether to add at beginning or end
             let result = if at_beginning.unwrap_or(false) {
                 // Insert at position 0 (beginning of queue)
@@ -1433,6 +1626,113 @@ impl MPDPlayerController {
             false
         }
     }
+
+    /// Insert a URL into the queue at an absolute position
+    pub fn insert_url_at(&self, url: &str, position: usize) -> bool {
+        debug!("Inserting URL into queue at position {}: {}", position, url);
+
+        if let Some(mut client) = self.get_client() {
+            let song_path = mpd::Song {
+                file: url.to_string(),
+                ..Default::default()
+            };
+            match client.insert(&song_path, position) {
+                Ok(_) => {
+                    debug!("Successfully inserted URL at position {}: {}", position, url);
+                    true
+                },
+                Err(e) => {
+                    warn!("Failed to insert URL at position {}: {} - Error: {}", position, url, e);
+                    false
+                }
+            }
+        } else {
+            warn!("Failed to get MPD client connection for insert_url_at");
+            false
+        }
+    }
+
+    /// Store metadata supplied with a queued URL so it can be shown while the track plays
+    fn cache_url_metadata(uri: &str, meta: &QueueTrackMetadata) {
+        if meta.metadata.is_empty() {
+            return;
+        }
+        debug!("Caching metadata for URI {}: {:?}", uri, meta.metadata);
+        let cache_key = format!("mpd.urlmeta.{}", uri);
+
+        match attributecache::set(&cache_key, &meta.metadata) {
+            Ok(_) => {
+                debug!("Successfully cached metadata for URI: {}", uri);
+            },
+            Err(e) => {
+                warn!("Failed to cache metadata for URI {}: {}", uri, e);
+            }
+        }
+    }
+
+    /// Quote an argument for the MPD protocol
+    ///
+    /// Commands end at a line break, which can't be escaped, so arguments containing one are refused.
+    fn quote_mpd_argument(arg: &str) -> Result<String, String> {
+        if arg.contains(['\n', '\r']) {
+            return Err(format!("Line break in MPD argument {:?}", arg));
+        }
+        Ok(format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")))
+    }
+
+    /// Replace the queue with the given URLs and start playback at `start_index`
+    ///
+    /// The clear, add and play commands are sent as a single MPD command list,
+    /// so MPD executes them without other clients interleaving.
+    pub fn replace_queue_and_play(&self, uris: &[String], start_index: usize) -> Result<(), String> {
+        use std::io::{BufRead, Write};
+
+        if uris.is_empty() {
+            return Err("No URIs given".to_string());
+        }
+        if start_index >= uris.len() {
+            return Err(format!("Start index {} out of range for {} tracks", start_index, uris.len()));
+        }
+        if self.are_connections_disabled() {
+            return Err("MPD connections are disabled".to_string());
+        }
+
+        let mut command_list = String::from("command_list_begin\nclear\n");
+        for uri in uris {
+            command_list.push_str(&format!("add {}\n", Self::quote_mpd_argument(uri)?));
+        }
+        command_list.push_str(&format!("play {}\ncommand_list_end\n", start_index));
+
+        let (mut reader, mut writer) = self.address.connect_raw(Some(Duration::from_secs(10)))
+            .map_err(|e| format!("Failed to connect to MPD: {}", e))?;
+
+        writer.write_all(command_list.as_bytes())
+            .map_err(|e| format!("Failed to send command list: {}", e))?;
+
+        let mut response = String::new();
+        reader.read_line(&mut response).map_err(|e| e.to_string())?;
+        let response = response.trim();
+        if response == "OK" {
+            Ok(())
+        } else {
+            Err(format!("MPD rejected command list: {}", response))
+        }
+    }
+
+    /// Get the queue position of the current song, if there is one
+    ///
+    /// MPD keeps the current song position while stopped, so this also
+    /// works when playback has not been started yet.
+    fn current_queue_position(&self) -> Option<usize> {
+        let mut client = self.get_client()?;
+        match client.status() {
+            Ok(status) => status.song.map(|place| place.pos as usize),
+            Err(e) => {
+                warn!("Failed to get MPD status for queue position: {}", e);
+                None
+            }
+        }
+    }
 }
 
 /// Structure to store player state for each instance
@@ -1523,7 +1823,7 @@ impl PlayerController for MPDPlayerController {
 
     fn get_loop_mode(&self) -> LoopMode {
         trace!("MPDController: get_loop_mode called");
-        if let Some(mut mpd_client) = self.get_fresh_client() {
+        if let Some(mut mpd_client) = self.get_client() {
             if let Ok(status) = mpd_client.status() {
                 return match (status.repeat, status.single) {
                     (true, true) => LoopMode::Track,
@@ -1538,7 +1838,7 @@ impl PlayerController for MPDPlayerController {
     
     fn get_playback_state(&self) -> PlaybackState {
         trace!("MPDController: get_playback_state called");
-        if let Some(mut mpd_client) = self.get_fresh_client() {
+        if let Some(mut mpd_client) = self.get_client() {
             if let Ok(status) = mpd_client.status() {
                 match status.state {
                     mpd::State::Play => return PlaybackState::Playing,
@@ -1553,7 +1853,7 @@ impl PlayerController for MPDPlayerController {
     
     fn get_position(&self) -> Option<f64> {
         trace!("MPDController: get_position called");
-        if let Some(mut mpd_client) = self.get_fresh_client() {
+        if let Some(mut mpd_client) = self.get_client() {
             if let Ok(status) = mpd_client.status() {
                 if let Some(elapsed) = status.elapsed {
                     // Convert Duration to f64 seconds
@@ -1567,7 +1867,7 @@ impl PlayerController for MPDPlayerController {
     
     fn get_shuffle(&self) -> bool {
         trace!("MPDController: get_shuffle called");
-        if let Some(mut mpd_client) = self.get_fresh_client() {
+        if let Some(mut mpd_client) = self.get_client() {
             if let Ok(status) = mpd_client.status() {
                 return status.random;
             }
@@ -1585,7 +1885,7 @@ impl PlayerController for MPDPlayerController {
     }
     
     fn get_player_id(&self) -> String {
-        format!("{}:{}", self.hostname, self.port)
+        self.address.id()
     }
     
     fn send_command(&self, command: PlayerCommand) -> bool {
@@ -1594,7 +1894,7 @@ impl PlayerController for MPDPlayerController {
         let mut success = false;
         
         // Create a fresh connection for each command
-        if let Some(mut client) = self.get_fresh_client() {
+        if let Some(mut client) = self.get_client() {
             // Process the command based on its type
             match command {
                 PlayerCommand::Play => {
@@ -1730,6 +2030,41 @@ impl PlayerController for MPDPlayerController {
                         debug!("MPD random mode set to: {}", enabled);
                     }
                 },
+
+                PlayerCommand::SetReplayGainMode(mode) => {
+                    success = client.replaygain(playback_options::to_mpd_replay_gain(mode)).is_ok();
+                    if success {
+                        debug!("MPD ReplayGain mode set to: {}", mode);
+                    }
+                },
+
+                PlayerCommand::SetConsume(enabled) => {
+                    success = client.consume(enabled).is_ok();
+                    if success {
+                        debug!("MPD consume mode set to: {}", enabled);
+                    }
+                },
+
+                PlayerCommand::SetSingle(mode) => {
+                    success = self.set_single_mode(mode);
+                    if success {
+                        debug!("MPD single mode set to: {}", mode);
+                    }
+                },
+
+                PlayerCommand::SetCrossfade(seconds) => {
+                    success = client.crossfade(seconds as i64).is_ok();
+                    if success {
+                        debug!("MPD crossfade set to {} seconds", seconds);
+                    }
+                },
+
+                PlayerCommand::SetMixRampDb(db) => {
+                    success = client.mixrampdb(db).is_ok();
+                    if success {
+                        debug!("MPD MixRamp threshold set to {} dB", db);
+                    }
+                },
                 
                 PlayerCommand::Kill => {
                     // Kill the MPD process via the kill command
@@ -1745,9 +2080,17 @@ impl PlayerController for MPDPlayerController {
                     }
                 },
                 
-                PlayerCommand::QueueTracks { uris, insert_at_beginning, metadata } => {
-                    debug!("Adding {} tracks to MPD queue at {}", uris.len(), 
-                          if insert_at_beginning { "beginning" } else { "end" });
+                PlayerCommand::QueueTracks { uris, insert_at_beginning, insert_after_current, metadata } => {
+                    debug!("Adding {} tracks to MPD queue at {}", uris.len(),
+                          if insert_after_current { "next" } else if insert_at_beginning { "beginning" } else { "end" });
+
+                    // For "play next", tracks go after the current song in the order given.
+                    // Without a current song, the beginning of the queue is the next to play.
+                    let next_position = if insert_after_current {
+                        Some(self.current_queue_position().map(|pos| pos + 1).unwrap_or(0))
+                    } else {
+                        None
+                    };
                     
                     if uris.is_empty() {
                         debug!("No URIs provided to queue");
@@ -1762,23 +2105,13 @@ impl PlayerController for MPDPlayerController {
                             
                             // Store metadata in cache if provided
                             if let Some(meta) = track_metadata {
-                                if !meta.metadata.is_empty() {
-                                    debug!("Caching metadata for URI {}: {:?}", 
-                                           uri, meta.metadata);
-                                    let cache_key = format!("mpd.urlmeta.{}", uri);
-                                    
-                                    match attributecache::set(&cache_key, &meta.metadata) {
-                                        Ok(_) => {
-                                            debug!("Successfully cached metadata for URI: {}", uri);
-                                        },
-                                        Err(e) => {
-                                            warn!("Failed to cache metadata for URI {}: {}", uri, e);
-                                        }
-                                    }
-                                }
+                                Self::cache_url_metadata(uri, meta);
                             }
                             
-                            let result = self.queue_url(uri, Some(insert_at_beginning));
+                            let result = match next_position {
+                                Some(position) => self.insert_url_at(uri, position + i),
+                                None => self.queue_url(uri, Some(insert_at_beginning)),
+                            };
                             if !result {
                                 all_success = false;
                             }
@@ -1811,6 +2144,47 @@ impl PlayerController for MPDPlayerController {
                         self.base.notify_queue_changed();
                     }
                 },
+                PlayerCommand::MoveTrack { from, to } => {
+                    debug!("Moving track in MPD queue from {} to {}", from, to);
+
+                    // Look up the song ID and move it with moveid
+                    let id = client.songs(from as u32).ok()
+                        .and_then(|songs| songs.first().and_then(|song| song.place))
+                        .map(|place| place.id);
+
+                    match id.map(|id| client.shift(id, to)) {
+                        Some(Ok(())) => {
+                            debug!("Successfully moved track from position {} to {}", from, to);
+                            success = true;
+
+                            // Notify listeners that the queue has been modified
+                            self.base.notify_queue_changed();
+                        },
+                        Some(Err(e)) => warn!("Failed to move track from position {} to {}: {}", from, to, e),
+                        None => warn!("No track at position {} in MPD queue", from),
+                    }
+                },
+                PlayerCommand::PlayNow { uris, metadata, start_index } => {
+                    debug!("Replacing MPD queue with {} tracks, starting at {}", uris.len(), start_index);
+
+                    for (i, uri) in uris.iter().enumerate() {
+                        if let Some(meta) = metadata.get(i).and_then(|m| m.as_ref()) {
+                            Self::cache_url_metadata(uri, meta);
+                        }
+                    }
+
+                    match self.replace_queue_and_play(&uris, start_index) {
+                        Ok(()) => {
+                            debug!("Replaced MPD queue and started playback");
+                            success = true;
+                            self.base.notify_queue_changed();
+                        },
+                        Err(e) => {
+                            warn!("Failed to replace MPD queue: {}", e);
+                        }
+                    }
+                },
+
                   PlayerCommand::ClearQueue => {
                     debug!("Clearing MPD queue");
                     
@@ -1861,7 +2235,7 @@ impl PlayerController for MPDPlayerController {
         // Create a new running flag
         let running = Arc::new(AtomicBool::new(true));
           // Try to get the current song from MPD first
-        if let Some(mut client) = self.get_fresh_client() {
+        if let Some(mut client) = self.get_client() {
             // Initialize song state and capabilities
             info!("Fetching initial song state from MPD");
             Self::update_song_from_mpd(&mut client, player_arc.clone());
@@ -1899,6 +2273,18 @@ impl PlayerController for MPDPlayerController {
             // Start a new listener thread
             self.start_event_listener(running.clone(), player_arc.clone());
 
+            if let Some(handler_name) = self.network_handler_name() {
+                let player = player_arc.clone();
+                let running = running.clone();
+                crate::helpers::network::register_reconnect_handler(&handler_name, move || {
+                    player.reconnect_after_network_change(&running, &player);
+                });
+            }
+
+            if self.watch_music_directory && self.load_mpd_library {
+                super::dirwatcher::start_watcher(player_arc.clone(), running.clone());
+            }
+
             // Store the running flag
             state.insert(instance_id, PlayerInstanceData { running_flag: running });
             true
@@ -1915,6 +2301,10 @@ impl PlayerController for MPDPlayerController {
 
             if let Some(data) = state.remove(&instance_id) {
                 data.running_flag.store(false, Ordering::SeqCst);
+                if let Some(handler_name) = self.network_handler_name() {
+                    crate::helpers::network::unregister_reconnect_handler(&handler_name);
+                }
+                self.connection_pool.clear();
                 debug!("Signaled event listener thread to stop");
                 return true;
             }
@@ -1937,7 +2327,7 @@ impl PlayerController for MPDPlayerController {
         debug!("MPDController: get_queue called - fetching playlist");
         
         // Get a fresh client connection
-        if let Some(mut client) = self.get_fresh_client() {
+        if let Some(mut client) = self.get_client() {
             // Use the queue method to get all songs in the current queue
             match client.queue() {
                 Ok(songs) => {
@@ -1945,26 +2335,7 @@ impl PlayerController for MPDPlayerController {
                     
                     // Convert MPD songs to our Track format
                     let tracks: Vec<Track> = songs.into_iter()
-                        .map(|mpd_song| {
-                            // Extract useful information from the song
-                            let title = mpd_song.title.unwrap_or_else(|| "Unknown Title".to_string());
-                            let artist = mpd_song.artist;
-                            
-                            // Create a Track with just the name
-                            let mut track = Track::with_name(title);
-                            
-                            // Set artist if available
-                            if let Some(artist_name) = artist {
-                                track.artist = Some(artist_name);
-                            }
-                            
-                            // Set URI if available
-                            if !mpd_song.file.is_empty() {
-                                track.uri = Some(mpd_song.file);
-                            }
-                            
-                            track
-                        })
+                        .map(track_from_mpd_song)
                         .collect();
                     
                     return tracks;
@@ -1985,7 +2356,9 @@ impl PlayerController for MPDPlayerController {
         vec![
             "hostname".to_string(),
             "port".to_string(),
+            "socket".to_string(),
             "connection_status".to_string(),
+            "idle_connections".to_string(),
             "queue_length".to_string(),
             "volume".to_string(),
             "playback_state".to_string(),
@@ -1994,19 +2367,26 @@ impl PlayerController for MPDPlayerController {
             "library_loaded".to_string(),
             "library_loading_progress".to_string(),
             "mpd_version".to_string(),
+            REPLAY_GAIN_MODE_KEY.to_string(),
+            CROSSFADE_KEY.to_string(),
+            MIXRAMPDB_KEY.to_string(),
+            CONSUME_KEY.to_string(),
+            SINGLE_KEY.to_string(),
         ]
     }
 
     fn get_metadata_value(&self, key: &str) -> Option<String> {
         match key {
-            "hostname" => Some(self.hostname.clone()),
-            "port" => Some(self.port.to_string()),
+            "hostname" => Some(self.address.hostname.clone()),
+            "port" => Some(self.address.port.to_string()),
+            "socket" => self.address.socket.clone(),
             "connection_status" => {
                 let connected = self.is_connected();
                 Some(if connected { "connected".to_string() } else { "disconnected".to_string() })
             },
+            "idle_connections" => Some(self.connection_pool.idle_count().to_string()),
             "queue_length" => {
-                if let Some(mut client) = self.get_fresh_client() {
+                if let Some(mut client) = self.get_client() {
                     match client.status() {
                         Ok(status) => Some(status.queue_len.to_string()),
                         Err(_) => Some("0".to_string())
@@ -2016,7 +2396,7 @@ impl PlayerController for MPDPlayerController {
                 }
             },
             "mpd_version" => {
-                if let Some(client) = self.get_fresh_client() {
+                if let Some(client) = self.get_client() {
                     // Get MPD version from the client and format it as major.minor.patch
                     Some(format!("{}.{}.{}", client.version.0, client.version.1, client.version.2))
                 } else {
@@ -2024,7 +2404,7 @@ impl PlayerController for MPDPlayerController {
                 }
             },
             "volume" => {
-                if let Some(mut client) = self.get_fresh_client() {
+                if let Some(mut client) = self.get_client() {
                     match client.status() {
                         Ok(status) => {
                             if status.volume >= 0 {
@@ -2040,22 +2420,24 @@ impl PlayerController for MPDPlayerController {
                 }
             },
             "playback_state" => Some(self.get_playback_state().to_string()),
+            REPLAY_GAIN_MODE_KEY => self.get_playback_options().map(|o| o.replay_gain_mode.to_string()),
+            CROSSFADE_KEY => self.get_playback_options().map(|o| o.crossfade.to_string()),
+            MIXRAMPDB_KEY => self.get_playback_options().and_then(|o| o.mixrampdb).map(|db| db.to_string()),
+            CONSUME_KEY => self.get_playback_options().map(|o| o.consume.to_string()),
+            SINGLE_KEY => self.get_playback_options().map(|o| o.single.to_string()),
             "last_seen" => {
-                if let Some(timestamp) = self.get_last_seen() {
-                    let duration = std::time::SystemTime::now()
-                        .duration_since(timestamp)
-                        .unwrap_or_else(|_| std::time::Duration::from_secs(0));
-                    Some(format!("{} seconds ago", duration.as_secs()))
-                } else {
-                    Some("never".to_string())
-                }
+                let timestamp = self.get_last_seen()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs());
+                Some(crate::helpers::locale::language().relative_timestamp(timestamp))
             },
             "stats" => {
-                if let Some(mut client) = self.get_fresh_client() {
+                if let Some(mut client) = self.get_client() {
                     match client.stats() {
                         Ok(stats) => {
                             // Format MPD stats as JSON
                             // Note: db_update is not a duration but rather a timestamp
+                            let language = crate::helpers::locale::language();
                             Some(serde_json::json!({
                                 "artists": stats.artists,
                                 "albums": stats.albums,
@@ -2063,7 +2445,11 @@ impl PlayerController for MPDPlayerController {
                                 "uptime": stats.uptime.as_secs(),
                                 "db_playtime": stats.db_playtime.as_secs(),
                                 "db_update": stats.db_update,
-                                "playtime": stats.playtime.as_secs()
+                                "playtime": stats.playtime.as_secs(),
+                                "uptime_display": language.format_duration(stats.uptime.as_secs()),
+                                "db_playtime_display": language.format_duration(stats.db_playtime.as_secs()),
+                                "db_update_display": language.relative_timestamp(Some(stats.db_update.as_secs())),
+                                "playtime_display": language.format_duration(stats.playtime.as_secs())
                             }).to_string())
                         },
                         Err(_) => Some("{}".to_string())
@@ -2099,6 +2485,18 @@ mod tests {
     use serde_json::Value;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_quote_mpd_argument() {
+        assert_eq!(MPDPlayerController::quote_mpd_argument("a/b.flac").unwrap(), "\"a/b.flac\"");
+        assert_eq!(
+            MPDPlayerController::quote_mpd_argument("say \"hi\" \\ bye").unwrap(),
+            "\"say \\\"hi\\\" \\\\ bye\""
+        );
+        // A line break would end the command and start another one
+        assert!(MPDPlayerController::quote_mpd_argument("a.flac\"\nclear\nadd \"b.flac").is_err());
+        assert!(MPDPlayerController::quote_mpd_argument("a.flac\r").is_err());
+    }
+
     /// Test that songs without cached metadata are not affected
     #[test]
     fn test_mpd_no_cached_metadata() {
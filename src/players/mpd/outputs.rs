@@ -0,0 +1,94 @@
+use super::MPDPlayerController;
+use log::info;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors of audio output operations
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error("Output '{0}' not found")]
+    NotFound(String),
+
+    #[error("MPD is not connected")]
+    NotConnected,
+
+    #[error("MPD error: {0}")]
+    Mpd(String),
+}
+
+pub type Result<T> = std::result::Result<T, OutputError>;
+
+/// An audio output of MPD, e.g. a DAC or an S/PDIF output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MpdOutput {
+    pub id: u32,
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// Find an output by id or name, names are compared case-insensitively
+fn find_output<'a>(outputs: &'a [MpdOutput], output: &str) -> Option<&'a MpdOutput> {
+    outputs
+        .iter()
+        .find(|o| o.id.to_string() == output)
+        .or_else(|| outputs.iter().find(|o| o.name.eq_ignore_ascii_case(output)))
+}
+
+impl MPDPlayerController {
+    /// List the audio outputs configured in MPD
+    pub fn list_outputs(&self) -> Result<Vec<MpdOutput>> {
+        let mut client = self.get_client().ok_or(OutputError::NotConnected)?;
+        Ok(client
+            .outputs()
+            .map_err(|e| OutputError::Mpd(e.to_string()))?
+            .into_iter()
+            .map(|o| MpdOutput { id: o.id, name: o.name, enabled: o.enabled })
+            .collect())
+    }
+
+    /// Enable or disable an audio output given by id or name
+    ///
+    /// With `exclusive` all other outputs are disabled when the output is enabled.
+    /// Returns the outputs after the change.
+    pub fn set_output_enabled(&self, output: &str, enabled: bool, exclusive: bool) -> Result<Vec<MpdOutput>> {
+        let outputs = self.list_outputs()?;
+        let target = find_output(&outputs, output).ok_or_else(|| OutputError::NotFound(output.to_string()))?;
+
+        let mut client = self.get_client().ok_or(OutputError::NotConnected)?;
+        // Enable the new output first, so playback doesn't stop without any output
+        client.output(target.id, enabled).map_err(|e| OutputError::Mpd(e.to_string()))?;
+        if enabled && exclusive {
+            for other in outputs.iter().filter(|o| o.id != target.id && o.enabled) {
+                client.out_disable(other.id).map_err(|e| OutputError::Mpd(e.to_string()))?;
+            }
+        }
+        drop(client);
+
+        info!(
+            "{} MPD output '{}'{}",
+            if enabled { "Enabled" } else { "Disabled" },
+            target.name,
+            if enabled && exclusive { ", disabled all other outputs" } else { "" }
+        );
+        self.list_outputs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_output() {
+        let outputs = vec![
+            MpdOutput { id: 0, name: "DAC".to_string(), enabled: true },
+            MpdOutput { id: 1, name: "S/PDIF".to_string(), enabled: false },
+            MpdOutput { id: 2, name: "1".to_string(), enabled: false },
+        ];
+        assert_eq!(find_output(&outputs, "dac").unwrap().id, 0);
+        assert_eq!(find_output(&outputs, "S/PDIF").unwrap().id, 1);
+        // Ids take precedence over names
+        assert_eq!(find_output(&outputs, "1").unwrap().id, 1);
+        assert!(find_output(&outputs, "HDMI").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use super::MPDPlayerController;
+use crate::data::{ReplayGainMode, SingleMode};
+use log::{debug, warn};
+use mpd::ReplayGain;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Player metadata key of the ReplayGain mode
+pub const REPLAY_GAIN_MODE_KEY: &str = "replay_gain_mode";
+
+/// Player metadata key of the crossfade duration in seconds
+pub const CROSSFADE_KEY: &str = "crossfade";
+
+/// Player metadata key of the MixRamp threshold in dB
+pub const MIXRAMPDB_KEY: &str = "mixrampdb";
+
+/// Player metadata key of the consume mode
+pub const CONSUME_KEY: &str = "consume";
+
+/// Player metadata key of the single mode
+pub const SINGLE_KEY: &str = "single";
+
+/// Timeout when reading the options from MPD
+const READ_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// ReplayGain, crossfade, consume and single settings of MPD
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackOptions {
+    pub replay_gain_mode: ReplayGainMode,
+    /// Crossfade duration in seconds, 0 if crossfading is disabled
+    pub crossfade: u32,
+    /// MixRamp threshold in dB, None if MPD doesn't report it
+    pub mixrampdb: Option<f32>,
+    /// Played tracks are removed from the queue
+    pub consume: bool,
+    pub single: SingleMode,
+}
+
+pub(super) fn to_mpd_replay_gain(mode: ReplayGainMode) -> ReplayGain {
+    match mode {
+        ReplayGainMode::Off => ReplayGain::Off,
+        ReplayGainMode::Track => ReplayGain::Track,
+        ReplayGainMode::Album => ReplayGain::Album,
+        ReplayGainMode::Auto => ReplayGain::Auto,
+    }
+}
+
+/// Single mode from the `single` value of MPD's status
+fn mpd_single_mode(value: &str) -> SingleMode {
+    match value {
+        "1" => SingleMode::On,
+        "oneshot" => SingleMode::Oneshot,
+        _ => SingleMode::Off,
+    }
+}
+
+/// Argument of MPD's `single` command
+fn mpd_single_argument(mode: SingleMode) -> &'static str {
+    match mode {
+        SingleMode::Off => "0",
+        SingleMode::On => "1",
+        SingleMode::Oneshot => "oneshot",
+    }
+}
+
+/// Parse the responses of the `status` and `replay_gain_status` commands
+fn parse_playback_options<I: IntoIterator<Item = String>>(lines: I) -> PlaybackOptions {
+    let mut options = PlaybackOptions::default();
+    for line in lines {
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        match key {
+            "replay_gain_mode" => {
+                options.replay_gain_mode = ReplayGainMode::from_str(value).unwrap_or_default();
+            }
+            "xfade" => options.crossfade = value.parse().unwrap_or(0),
+            "mixrampdb" => options.mixrampdb = value.parse().ok(),
+            "consume" => options.consume = value == "1",
+            "single" => options.single = mpd_single_mode(value),
+            _ => {}
+        }
+    }
+    options
+}
+
+impl MPDPlayerController {
+    /// Read the ReplayGain mode, crossfade, MixRamp threshold, consume and single mode
+    ///
+    /// The MPD client library doesn't parse `mixrampdb`, so the status is read directly.
+    pub fn get_playback_options(&self) -> Option<PlaybackOptions> {
+        let (reader, mut writer) = self.address().connect_raw(Some(READ_TIMEOUT)).ok()?;
+
+        writer
+            .write_all(b"command_list_begin\nstatus\nreplay_gain_status\ncommand_list_end\n")
+            .ok()?;
+
+        let mut lines = Vec::new();
+        for line in reader.lines().map_while(Result::ok) {
+            if line == "OK" {
+                break;
+            }
+            if line.starts_with("ACK") {
+                debug!("Failed to read MPD playback options: {}", line);
+                return None;
+            }
+            lines.push(line);
+        }
+        Some(parse_playback_options(lines))
+    }
+
+    /// Set the single mode
+    ///
+    /// The MPD client library only knows on and off, so the command is sent directly.
+    pub fn set_single_mode(&self, mode: SingleMode) -> bool {
+        let result = self.address().connect_raw(Some(READ_TIMEOUT)).and_then(|(mut reader, mut writer)| {
+            writer.write_all(format!("single {}\n", mpd_single_argument(mode)).as_bytes())?;
+            let mut response = String::new();
+            reader.read_line(&mut response)?;
+            Ok(response)
+        });
+        match result {
+            Ok(response) if response.trim() == "OK" => true,
+            Ok(response) => {
+                warn!("MPD rejected single mode {}: {}", mode, response.trim());
+                false
+            }
+            Err(e) => {
+                warn!("Failed to set MPD single mode: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_playback_options() {
+        let lines = [
+            "volume: 80", "single: oneshot", "consume: 1", "xfade: 5", "mixrampdb: -17.000000", "state: play",
+            "replay_gain_mode: album",
+        ];
+        let options = parse_playback_options(lines.iter().map(|l| l.to_string()));
+        assert_eq!(options.replay_gain_mode, ReplayGainMode::Album);
+        assert_eq!(options.crossfade, 5);
+        assert_eq!(options.mixrampdb, Some(-17.0));
+        assert!(options.consume);
+        assert_eq!(options.single, SingleMode::Oneshot);
+        assert_eq!(mpd_single_argument(options.single), "oneshot");
+
+        // MPD omits xfade if crossfading is disabled
+        let options = parse_playback_options(vec!["single: 0".to_string(), "consume: 0".to_string(), "replay_gain_mode: off".to_string()]);
+        assert_eq!(options, PlaybackOptions::default());
+    }
+}
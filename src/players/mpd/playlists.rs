@@ -0,0 +1,130 @@
+use super::MPDPlayerController;
+use super::pool::PooledClient;
+use crate::data::playlist::{check_playlist_name as check_name, PlaylistStore};
+use crate::data::Track;
+use log::info;
+use mpd::error::{Error as MpdError, ErrorCode};
+
+pub use crate::data::playlist::{PlaylistError, StoredPlaylist};
+
+pub type Result<T> = crate::data::playlist::PlaylistResult<T>;
+
+/// Convert a song of MPD to a track of the queue or a playlist
+pub fn track_from_mpd_song(song: mpd::Song) -> Track {
+    let mut track = Track::with_name(song.title.unwrap_or_else(|| "Unknown Title".to_string()));
+    track.artist = song.artist;
+    track.duration = song.duration.map(|d| d.as_secs_f64());
+    if !song.file.is_empty() {
+        track.uri = Some(song.file);
+    }
+    track
+}
+
+fn map_error(name: &str, error: MpdError) -> PlaylistError {
+    match &error {
+        MpdError::Server(e) if e.code == ErrorCode::NoExist => PlaylistError::NotFound(name.to_string()),
+        MpdError::Server(e) if e.code == ErrorCode::Exist => PlaylistError::Exists(name.to_string()),
+        _ => PlaylistError::Mpd(error.to_string()),
+    }
+}
+
+impl MPDPlayerController {
+    /// Add a stored playlist to the queue
+    ///
+    /// With `replace` the queue is cleared first, with `play` playback is started.
+    pub fn load_playlist(&self, name: &str, replace: bool, play: bool) -> Result<()> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        // Check the playlist before the queue is cleared
+        client.playlist(name).map_err(|e| map_error(name, e))?;
+        if replace {
+            client.clear().map_err(|e| PlaylistError::Mpd(e.to_string()))?;
+        }
+        client.load(name, ..).map_err(|e| map_error(name, e))?;
+        if play {
+            client.play().map_err(|e| PlaylistError::Mpd(e.to_string()))?;
+        }
+        info!("Loaded MPD playlist '{}' into the queue", name);
+        Ok(())
+    }
+
+    /// Save the current queue as a stored playlist
+    ///
+    /// An existing playlist with the same name is only replaced with `overwrite`.
+    pub fn save_queue_as_playlist(&self, name: &str, overwrite: bool) -> Result<()> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        if overwrite {
+            match client.pl_remove(name).map_err(|e| map_error(name, e)) {
+                Ok(()) | Err(PlaylistError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        client.save(name).map_err(|e| map_error(name, e))?;
+        info!("Saved MPD queue as playlist '{}'", name);
+        Ok(())
+    }
+}
+
+impl PlaylistStore for MPDPlayerController {
+    fn list_playlists(&self) -> Result<Vec<StoredPlaylist>> {
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        let mut playlists: Vec<StoredPlaylist> = client
+            .playlists()
+            .map_err(|e| PlaylistError::Mpd(e.to_string()))?
+            .into_iter()
+            .map(|p| StoredPlaylist { name: p.name, last_modified: p.last_mod })
+            .collect();
+        playlists.sort_by_key(|p| p.name.to_lowercase());
+        Ok(playlists)
+    }
+
+    fn get_playlist_tracks(&self, name: &str) -> Result<Vec<Track>> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        let songs = client.playlist(name).map_err(|e| map_error(name, e))?;
+        Ok(songs.into_iter().map(track_from_mpd_song).collect())
+    }
+
+    /// Tracks without URI are skipped
+    fn save_playlist(&self, name: &str, tracks: &[Track], overwrite: bool) -> Result<()> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        match client.playlist(name).map_err(|e| map_error(name, e)) {
+            Ok(_) if !overwrite => return Err(PlaylistError::Exists(name.to_string())),
+            Ok(_) => client.pl_remove(name).map_err(|e| map_error(name, e))?,
+            Err(PlaylistError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        push_tracks(&mut client, name, tracks)?;
+        info!("Saved {} tracks as MPD playlist '{}'", tracks.len(), name);
+        Ok(())
+    }
+
+    fn add_to_playlist(&self, name: &str, tracks: &[Track]) -> Result<()> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        client.playlist(name).map_err(|e| map_error(name, e))?;
+        push_tracks(&mut client, name, tracks)
+    }
+
+    fn delete_playlist(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        let mut client = self.get_client().ok_or(PlaylistError::NotConnected)?;
+        client.pl_remove(name).map_err(|e| map_error(name, e))?;
+        info!("Deleted MPD playlist '{}'", name);
+        Ok(())
+    }
+}
+
+/// Append tracks to a stored playlist, tracks without URI are skipped
+fn push_tracks(client: &mut PooledClient, name: &str, tracks: &[Track]) -> Result<()> {
+    for uri in tracks.iter().filter_map(|t| t.uri.as_ref()) {
+        let song = mpd::Song {
+            file: uri.clone(),
+            ..Default::default()
+        };
+        client.pl_push(name, &song).map_err(|e| map_error(name, e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use super::connection::{MpdAddress, MpdStream};
+use log::{debug, trace};
+use mpd::{Client, error::Error as MpdError};
+use parking_lot::Mutex;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Default number of idle command connections kept open
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Connections idle for longer than this are checked with a ping before they are reused
+const HEALTH_CHECK_AFTER: Duration = Duration::from_secs(2);
+
+/// Connections idle for longer than this are closed, MPD drops them after `connection_timeout` (60s by default)
+const MAX_IDLE_TIME: Duration = Duration::from_secs(50);
+
+struct IdleConnection {
+    client: Client<MpdStream>,
+    since: Instant,
+}
+
+/// Pool of MPD command connections
+///
+/// Every status query used to open its own TCP connection. The pool keeps up to `size`
+/// connections open and hands them out again, checking connections that have been idle
+/// for a while with a ping first.
+pub struct ConnectionPool {
+    address: MpdAddress,
+    size: AtomicUsize,
+    idle: Mutex<Vec<IdleConnection>>,
+}
+
+impl ConnectionPool {
+    pub fn new(address: MpdAddress) -> Self {
+        Self {
+            address,
+            size: AtomicUsize::new(DEFAULT_POOL_SIZE),
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the maximum number of idle connections, 0 disables pooling
+    pub fn set_size(&self, size: usize) {
+        self.size.store(size, Ordering::Relaxed);
+        self.idle.lock().truncate(size);
+    }
+
+    pub fn size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Number of connections waiting to be reused
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().len()
+    }
+
+    /// Close all idle connections, e.g. after the network has changed
+    pub fn clear(&self) {
+        let closed = std::mem::take(&mut *self.idle.lock()).len();
+        if closed > 0 {
+            debug!("Closed {} idle MPD connections", closed);
+        }
+    }
+
+    /// Get a healthy idle connection or open a new one
+    ///
+    /// The second value is true if a new connection had to be opened.
+    pub fn get(self: &Arc<Self>) -> Result<(PooledClient, bool), MpdError> {
+        while let Some(mut idle) = self.take_idle() {
+            let idle_time = idle.since.elapsed();
+            if idle_time > MAX_IDLE_TIME {
+                trace!("Closing MPD connection idle for {:?}", idle_time);
+                continue;
+            }
+            if idle_time > HEALTH_CHECK_AFTER {
+                if let Err(e) = idle.client.ping() {
+                    debug!("Dropping broken MPD connection: {}", e);
+                    continue;
+                }
+            }
+            return Ok((self.wrap(idle.client), false));
+        }
+
+        debug!("Opening new MPD command connection");
+        let client = self.address.client()?;
+        Ok((self.wrap(client), true))
+    }
+
+    fn take_idle(&self) -> Option<IdleConnection> {
+        // The most recently used connection is the most likely to be alive
+        self.idle.lock().pop()
+    }
+
+    fn wrap(self: &Arc<Self>, client: Client<MpdStream>) -> PooledClient {
+        PooledClient {
+            client: Some(client),
+            pool: Arc::clone(self),
+        }
+    }
+
+    fn release(&self, client: Client<MpdStream>) {
+        let mut idle = self.idle.lock();
+        if idle.len() < self.size() {
+            idle.push(IdleConnection { client, since: Instant::now() });
+        }
+    }
+}
+
+/// A connection borrowed from the pool, it is returned when dropped
+pub struct PooledClient {
+    client: Option<Client<MpdStream>>,
+    pool: Arc<ConnectionPool>,
+}
+
+impl Deref for PooledClient {
+    type Target = Client<MpdStream>;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("pooled MPD client used after release")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client.as_mut().expect("pooled MPD client used after release")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.release(client);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Minimal MPD server answering every command with OK, counts the accepted connections
+    fn fake_mpd() -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    writer.write_all(b"OK MPD 0.23.5\n").unwrap();
+                    for line in BufReader::new(stream).lines() {
+                        if line.is_err() || writer.write_all(b"OK\n").is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (port, accepted)
+    }
+
+    #[test]
+    fn test_connections_are_reused() {
+        let (port, accepted) = fake_mpd();
+        let pool = Arc::new(ConnectionPool::new(MpdAddress::tcp("127.0.0.1", port)));
+        pool.set_size(1);
+
+        let (mut first, opened) = pool.get().unwrap();
+        assert!(opened);
+        first.ping().unwrap();
+        drop(first);
+        assert_eq!(pool.idle_count(), 1);
+
+        let (second, opened) = pool.get().unwrap();
+        assert!(!opened);
+        // Only one idle connection is kept, the extra one is closed when returned
+        let (third, opened) = pool.get().unwrap();
+        assert!(opened);
+        drop(second);
+        drop(third);
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+
+        pool.clear();
+        assert_eq!(pool.idle_count(), 0);
+    }
+}
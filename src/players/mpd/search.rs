@@ -0,0 +1,150 @@
+use super::MPDPlayerController;
+use log::debug;
+use mpd::{Query, Term};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Number of tracks returned if the query doesn't set a limit
+pub const DEFAULT_SEARCH_LIMIT: u32 = 100;
+
+/// Highest number of tracks returned by a single search
+pub const MAX_SEARCH_LIMIT: u32 = 1000;
+
+/// Errors of database searches
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("At least one of artist, album, album_artist, title, genre or any must be given")]
+    EmptyQuery,
+
+    #[error("MPD is not connected")]
+    NotConnected,
+
+    #[error("MPD error: {0}")]
+    Mpd(String),
+}
+
+pub type Result<T> = std::result::Result<T, SearchError>;
+
+/// A structured query of the MPD database, all given fields must match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub title: Option<String>,
+    pub genre: Option<String>,
+    /// Matches any tag or the file name
+    pub any: Option<String>,
+    /// Match whole values case-sensitively (MPD's `find`) instead of substrings (`search`)
+    #[serde(default)]
+    pub exact: bool,
+    pub limit: Option<u32>,
+}
+
+impl SearchQuery {
+    /// The MPD tags and values of the query, empty values are ignored
+    fn terms(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("albumartist", &self.album_artist),
+            ("title", &self.title),
+            ("genre", &self.genre),
+            ("any", &self.any),
+        ]
+        .into_iter()
+        .filter_map(|(tag, value)| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(|v| (tag, v)))
+        .collect()
+    }
+
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT)
+    }
+}
+
+/// A song of the MPD database found by a search
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchTrack {
+    pub uri: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub track_number: Option<u16>,
+    pub disc_number: Option<String>,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+}
+
+impl From<mpd::Song> for SearchTrack {
+    fn from(song: mpd::Song) -> Self {
+        let tag = |name: &str| {
+            song.tags
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        SearchTrack {
+            album: tag("Album"),
+            album_artist: tag("AlbumArtist"),
+            genre: tag("Genre"),
+            // Track numbers are reported as "3" or "3/12"
+            track_number: tag("Track").and_then(|t| t.split('/').next()?.trim().parse().ok()),
+            disc_number: tag("Disc"),
+            duration: song.duration.map(|d| d.as_secs_f64()),
+            title: song.title,
+            artist: song.artist,
+            uri: song.file,
+        }
+    }
+}
+
+impl MPDPlayerController {
+    /// Search the MPD database directly, without loading the library
+    pub fn search_songs(&self, query: &SearchQuery) -> Result<Vec<SearchTrack>> {
+        let terms = query.terms();
+        if terms.is_empty() {
+            return Err(SearchError::EmptyQuery);
+        }
+        let mut mpd_query = Query::new();
+        for (tag, value) in &terms {
+            let term = match *tag {
+                "any" => Term::Any,
+                tag => Term::Tag(tag.into()),
+            };
+            mpd_query.and(term, *value);
+        }
+
+        let window = (0, query.limit());
+        let mut client = self.get_client().ok_or(SearchError::NotConnected)?;
+        let songs = if query.exact {
+            client.find(&mpd_query, window)
+        } else {
+            client.search(&mpd_query, window)
+        }
+        .map_err(|e| SearchError::Mpd(e.to_string()))?;
+
+        debug!("MPD {} {:?} returned {} songs", if query.exact { "find" } else { "search" }, terms, songs.len());
+        Ok(songs.into_iter().map(SearchTrack::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_terms() {
+        let query = SearchQuery {
+            artist: Some("Miles Davis".to_string()),
+            album_artist: Some("  ".to_string()),
+            any: Some("blue".to_string()),
+            limit: Some(5000),
+            ..Default::default()
+        };
+        assert_eq!(query.terms(), vec![("artist", "Miles Davis"), ("any", "blue")]);
+        assert_eq!(query.limit(), MAX_SEARCH_LIMIT);
+        assert!(SearchQuery::default().terms().is_empty());
+    }
+}
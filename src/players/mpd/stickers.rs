@@ -0,0 +1,138 @@
+use super::connection::MpdStream;
+use super::MPDPlayerController;
+use log::{debug, info};
+use mpd::error::{Error as MpdError, ErrorCode};
+use mpd::Client;
+use thiserror::Error;
+
+/// Sticker holding the rating of a song, 1 to 5 stars
+pub const RATING_STICKER: &str = "rating";
+
+/// Sticker holding the number of times a song was played
+pub const PLAY_COUNT_STICKER: &str = "playCount";
+
+/// Song metadata key of the rating
+pub const RATING_KEY: &str = "rating";
+
+/// Song metadata key of the play count
+pub const PLAY_COUNT_KEY: &str = "play_count";
+
+/// Highest possible rating
+pub const MAX_RATING: u8 = 5;
+
+/// Errors of rating operations
+#[derive(Debug, Error)]
+pub enum StickerError {
+    #[error("Invalid rating {0}, ratings are 0 to {MAX_RATING}")]
+    InvalidRating(u8),
+
+    #[error("No song to rate")]
+    NoSong,
+
+    #[error("'{0}' is not a song of the MPD database")]
+    NotInDatabase(String),
+
+    #[error("MPD is not connected")]
+    NotConnected,
+
+    #[error("MPD error: {0}")]
+    Mpd(String),
+}
+
+pub type Result<T> = std::result::Result<T, StickerError>;
+
+/// Only songs of the database can have stickers, streams can't
+pub fn has_stickers(uri: &str) -> bool {
+    !uri.is_empty() && !uri.contains("://")
+}
+
+/// Read a numeric sticker of a song, None if it isn't set or the sticker database is disabled
+fn read_number<T: std::str::FromStr>(client: &mut Client<MpdStream>, uri: &str, name: &str) -> Option<T> {
+    match client.sticker("song", uri, name) {
+        Ok(value) => value.trim().parse().ok(),
+        Err(MpdError::Server(e)) if e.code == ErrorCode::NoExist => None,
+        Err(e) => {
+            debug!("Failed to read sticker {} of {}: {}", name, uri, e);
+            None
+        }
+    }
+}
+
+/// Read the rating and play count of a song
+pub fn read_rating_and_play_count(client: &mut Client<MpdStream>, uri: &str) -> (Option<u8>, Option<u64>) {
+    if !has_stickers(uri) {
+        return (None, None);
+    }
+    let rating = read_number::<u8>(client, uri, RATING_STICKER).filter(|r| *r <= MAX_RATING);
+    let play_count = read_number::<u64>(client, uri, PLAY_COUNT_STICKER);
+    (rating, play_count)
+}
+
+impl MPDPlayerController {
+    /// Rate a song of the database, the current song if no URI is given
+    ///
+    /// Ratings are 1 to 5, 0 removes the rating. Returns the URI of the rated song.
+    pub fn set_rating(&self, uri: Option<&str>, rating: u8) -> Result<String> {
+        if rating > MAX_RATING {
+            return Err(StickerError::InvalidRating(rating));
+        }
+        let uri = match uri {
+            Some(uri) => uri.to_string(),
+            None => self.current_song_uri().ok_or(StickerError::NoSong)?,
+        };
+        if !has_stickers(&uri) {
+            return Err(StickerError::NotInDatabase(uri));
+        }
+
+        let mut client = self.get_client().ok_or(StickerError::NotConnected)?;
+        let result = if rating == 0 {
+            match client.delete_sticker("song", &uri, RATING_STICKER) {
+                Err(MpdError::Server(e)) if e.code == ErrorCode::NoExist => Ok(()),
+                other => other,
+            }
+        } else {
+            client.set_sticker("song", &uri, RATING_STICKER, &rating.to_string())
+        };
+        result.map_err(|e| match e {
+            MpdError::Server(e) if e.code == ErrorCode::NoExist => StickerError::NotInDatabase(uri.clone()),
+            e => StickerError::Mpd(e.to_string()),
+        })?;
+        drop(client);
+
+        info!("Rated {} with {} stars", uri, rating);
+        let value = (rating > 0).then(|| serde_json::Value::from(rating));
+        self.update_song_metadata(&uri, RATING_KEY, value);
+        Ok(uri)
+    }
+
+    /// Add one to the play count of a song, returns the new count
+    pub fn increment_play_count(&self, uri: &str) -> Option<u64> {
+        if !has_stickers(uri) {
+            return None;
+        }
+        let mut client = self.get_client()?;
+        let count = read_number::<u64>(&mut client, uri, PLAY_COUNT_STICKER).unwrap_or(0) + 1;
+        match client.set_sticker("song", uri, PLAY_COUNT_STICKER, &count.to_string()) {
+            Ok(()) => {
+                debug!("Play count of {} is now {}", uri, count);
+                Some(count)
+            }
+            Err(e) => {
+                debug!("Failed to store play count of {}: {}", uri, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_stickers() {
+        assert!(has_stickers("Jazz/Kind of Blue/01.flac"));
+        assert!(!has_stickers("http://stream.example.com/radio.mp3"));
+        assert!(!has_stickers(""));
+    }
+}
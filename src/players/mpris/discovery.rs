@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::sync::Weak;
+use std::thread;
+use std::time::Duration;
+use dbus::blocking::{Connection, Proxy};
+use dbus::message::MatchRule;
+use dbus::Message;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use crate::audiocontrol::AudioController;
+use crate::config::get_service_config;
+use crate::helpers::mpris::{create_connection, BusType};
+use crate::players::PlayerController;
+use super::MprisPlayerController;
+
+/// Prefix of the bus names of MPRIS players
+pub const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// Maximum time the watcher waits for a signal before checking if AudioControl is still running
+const WATCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bus names of the players registered by the discovery
+///
+/// Only these are removed when their name disappears, configured players stay.
+static DISCOVERED: Lazy<RwLock<HashSet<String>>> = Lazy::new(|| RwLock::new(HashSet::new()));
+
+/// Configuration of the `mpris_discovery` service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MprisDiscoveryConfig {
+    /// Register a controller for every MPRIS player that appears
+    #[serde(default)]
+    pub enable: bool,
+
+    /// Buses to watch, "session" and/or "system"
+    #[serde(default = "default_buses")]
+    pub buses: Vec<String>,
+
+    /// Players to ignore, as bus name or the part after "org.mpris.MediaPlayer2."
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Polling interval in seconds for players without signals
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: f64,
+}
+
+fn default_buses() -> Vec<String> {
+    vec!["session".to_string(), "system".to_string()]
+}
+
+fn default_poll_interval() -> f64 {
+    1.0
+}
+
+impl Default for MprisDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            buses: default_buses(),
+            exclude: Vec::new(),
+            poll_interval: default_poll_interval(),
+        }
+    }
+}
+
+impl MprisDiscoveryConfig {
+    /// Check if a bus name belongs to an MPRIS player that should be registered
+    ///
+    /// Excluding "vlc" also excludes instances like "org.mpris.MediaPlayer2.vlc.instance4711".
+    pub fn accepts(&self, bus_name: &str) -> bool {
+        let Some(player) = bus_name.strip_prefix(MPRIS_PREFIX) else {
+            return false;
+        };
+        !self.exclude.iter().any(|excluded| {
+            excluded == bus_name
+                || excluded == player
+                || player.strip_prefix(excluded.as_str()).is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+/// Start watching the configured buses for MPRIS players
+pub fn initialize_from_config(config: &serde_json::Value, controller: Weak<AudioController>) {
+    let discovery_config = match get_service_config(config, "mpris_discovery") {
+        Some(c) => match serde_json::from_value::<MprisDiscoveryConfig>(c.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Invalid mpris_discovery configuration, using defaults: {}", e);
+                MprisDiscoveryConfig::default()
+            }
+        },
+        None => MprisDiscoveryConfig::default(),
+    };
+    if !discovery_config.enable {
+        debug!("MPRIS player discovery is disabled");
+        return;
+    }
+
+    for bus in &discovery_config.buses {
+        let bus_type = match bus.to_lowercase().as_str() {
+            "session" => BusType::Session,
+            "system" => BusType::System,
+            other => {
+                warn!("Unknown D-Bus bus '{}' in mpris_discovery, ignoring it", other);
+                continue;
+            }
+        };
+        let config = discovery_config.clone();
+        let controller = controller.clone();
+        thread::spawn(move || {
+            if let Err(e) = watch(&bus_type, &config, &controller) {
+                warn!("MPRIS player discovery on {} bus stopped: {}", bus_type, e);
+            }
+        });
+    }
+}
+
+/// Register the players already on the bus and follow NameOwnerChanged until AudioControl stops
+fn watch(bus_type: &BusType, config: &MprisDiscoveryConfig, controller: &Weak<AudioController>) -> Result<(), String> {
+    let conn = create_connection(bus_type.clone()).map_err(|e| e.to_string())?;
+    let poll_interval = Duration::from_secs_f64(config.poll_interval.max(0.1));
+
+    // Subscribe before listing the names, so no player that starts in between is missed
+    let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged").with_sender("org.freedesktop.DBus");
+    {
+        let config = config.clone();
+        let controller = controller.clone();
+        let bus_type = bus_type.clone();
+        conn.add_match(rule, move |(name, _old_owner, new_owner): (String, String, String), _: &Connection, _: &Message| {
+            if config.accepts(&name) {
+                if new_owner.is_empty() {
+                    unregister(&controller, &name);
+                } else {
+                    register(&controller, &name, &bus_type, poll_interval);
+                }
+            }
+            true
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    let proxy = Proxy::new("org.freedesktop.DBus", "/org/freedesktop/DBus", Duration::from_secs(5), &conn);
+    let (names,): (Vec<String>,) = proxy
+        .method_call("org.freedesktop.DBus", "ListNames", ())
+        .map_err(|e| e.to_string())?;
+    for name in names.iter().filter(|name| config.accepts(name)) {
+        register(controller, name, bus_type, poll_interval);
+    }
+
+    info!("Watching the {} bus for MPRIS players", bus_type);
+    while controller.strong_count() > 0 {
+        conn.process(WATCH_TIMEOUT).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Create and start a controller for a player, unless there is one already
+fn register(controller: &Weak<AudioController>, bus_name: &str, bus_type: &BusType, poll_interval: Duration) {
+    let Some(controller) = controller.upgrade() else {
+        return;
+    };
+    if controller.get_player_by_name(bus_name).is_some() {
+        debug!("MPRIS player {} already has a controller", bus_name);
+        return;
+    }
+
+    let player = MprisPlayerController::with_bus_type(bus_name, bus_type.clone(), poll_interval);
+    if !player.start() {
+        warn!("Failed to start controller for discovered MPRIS player {}", bus_name);
+        return;
+    }
+    controller.add_controller(Box::new(player));
+    DISCOVERED.write().insert(bus_name.to_string());
+    info!("Registered discovered MPRIS player {} on {} bus", bus_name, bus_type);
+}
+
+/// Stop and remove the controller of a player registered by the discovery
+fn unregister(controller: &Weak<AudioController>, bus_name: &str) {
+    if !DISCOVERED.write().remove(bus_name) {
+        return;
+    }
+    let Some(controller) = controller.upgrade() else {
+        return;
+    };
+    if let Some(player) = controller.remove_controller_by_id(bus_name) {
+        player.read().stop();
+        info!("Removed MPRIS player {}, it left the bus", bus_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts() {
+        let config = MprisDiscoveryConfig {
+            exclude: vec!["vlc".to_string(), "org.mpris.MediaPlayer2.spotifyd".to_string()],
+            ..Default::default()
+        };
+        assert!(config.accepts("org.mpris.MediaPlayer2.mpv"));
+        assert!(config.accepts("org.mpris.MediaPlayer2.vlcplayer"));
+        assert!(!config.accepts("org.mpris.MediaPlayer2.vlc"));
+        assert!(!config.accepts("org.mpris.MediaPlayer2.vlc.instance4711"));
+        assert!(!config.accepts("org.mpris.MediaPlayer2.spotifyd"));
+        assert!(!config.accepts("org.freedesktop.Notifications"));
+    }
+}
@@ -2,9 +2,8 @@ use crate::players::player_controller::{BasePlayerController, PlayerController};
 use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
 use crate::data::stream_details::StreamDetails;
 use crate::helpers::mpris::{
-    retrieve_mpris_metadata, extract_song_from_mpris_metadata, create_connection, 
-    create_player_proxy, get_string_property, get_bool_property,
-    get_i64_property, send_player_method, send_player_method_with_args, 
+    retrieve_player_properties, retrieve_track_list, extract_song_from_mpris_metadata, create_connection,
+    create_player_proxy, get_i64_property, send_player_method, send_player_method_with_args,
     set_player_property, bool_to_dbus_variant, BusType
 };
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
@@ -13,10 +12,23 @@ use std::thread;
 use std::time::{Duration, Instant};
 use log::{debug, info, warn, error};
 use std::any::Any;
-use dbus::blocking::Connection;
+use dbus::arg::{PropMap, RefArg};
+use dbus::blocking::{Connection, Proxy};
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::message::{MatchRule, MessageType};
+use dbus::Message;
+
+pub mod discovery;
+
+/// Object path of MPRIS players
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Maximum time the listener waits for a signal before checking if it should stop
+const LISTENER_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// MPRIS player controller implementation
-/// This controller interfaces with MPRIS-compatible media players via D-Bus
+/// This controller interfaces with MPRIS-compatible media players via D-Bus.
+/// State changes are received as PropertiesChanged and Seeked signals.
 pub struct MprisPlayerController {
     /// Base controller
     base: BasePlayerController,
@@ -35,15 +47,18 @@ pub struct MprisPlayerController {
     
     /// Current stream details
     stream_details: Arc<RwLock<Option<StreamDetails>>>,
+
+    /// Tracks of the TrackList interface, empty if the player doesn't have one
+    queue: Arc<RwLock<Vec<Track>>>,
     
-    /// Polling interval in seconds
+    /// Polling interval, only used if the player's signals can't be subscribed
     poll_interval: Duration,
     
-    /// Flag to control the polling thread
-    should_poll: Arc<AtomicBool>,
+    /// Flag to control the listener thread
+    should_run: Arc<AtomicBool>,
     
-    /// Handle to the polling thread
-    poll_thread_handle: Arc<RwLock<Option<thread::JoinHandle<()>>>>,
+    /// Handle to the listener thread
+    listener_thread_handle: Arc<RwLock<Option<thread::JoinHandle<()>>>>,
 }
 
 // Manually implement Clone for MprisPlayerController
@@ -57,9 +72,10 @@ impl Clone for MprisPlayerController {
             current_song: Arc::clone(&self.current_song),
             current_state: Arc::clone(&self.current_state),
             stream_details: Arc::clone(&self.stream_details),
+            queue: Arc::clone(&self.queue),
             poll_interval: self.poll_interval,
-            should_poll: Arc::clone(&self.should_poll),
-            poll_thread_handle: Arc::new(RwLock::new(None)), // New instance gets new thread handle
+            should_run: Arc::clone(&self.should_run),
+            listener_thread_handle: Arc::new(RwLock::new(None)), // New instance gets new thread handle
         }
     }
 }
@@ -70,17 +86,21 @@ impl MprisPlayerController {
         Self::new_with_poll_interval(bus_name, Duration::from_secs_f64(1.0))
     }
     
-    /// Create a new MPRIS player controller with configurable polling interval
+    /// Create a new MPRIS player controller with a polling interval for players without signals
     pub fn new_with_poll_interval(bus_name: &str, poll_interval: Duration) -> Self {
         debug!("Creating new MprisPlayerController for bus: {} with poll interval: {:?}", bus_name, poll_interval);
         
+        // Determine bus type - default to session, but check if it exists on system bus
+        let bus_type = Self::determine_bus_type(bus_name);
+        Self::with_bus_type(bus_name, bus_type, poll_interval)
+    }
+
+    /// Create a new MPRIS player controller for a player on a known bus
+    pub fn with_bus_type(bus_name: &str, bus_type: BusType, poll_interval: Duration) -> Self {
         // Create a base controller with player name and ID derived from bus name
         let player_name = Self::extract_player_name(bus_name);
         let base = BasePlayerController::with_player_info(&player_name, bus_name);
-        
-        // Determine bus type - default to session, but check if it exists on system bus
-        let bus_type = Self::determine_bus_type(bus_name);
-        
+
         let controller = Self {
             base,
             bus_name: bus_name.to_string(),
@@ -88,9 +108,10 @@ impl MprisPlayerController {
             current_song: Arc::new(RwLock::new(None)),
             current_state: Arc::new(RwLock::new(PlayerState::new())),
             stream_details: Arc::new(RwLock::new(None)),
+            queue: Arc::new(RwLock::new(Vec::new())),
             poll_interval,
-            should_poll: Arc::new(AtomicBool::new(false)),
-            poll_thread_handle: Arc::new(RwLock::new(None)),
+            should_run: Arc::new(AtomicBool::new(false)),
+            listener_thread_handle: Arc::new(RwLock::new(None)),
         };
         
         // Set capabilities based on what MPRIS typically supports
@@ -170,12 +191,13 @@ impl MprisPlayerController {
         Ok(conn)
     }
     
-    /// Update internal state from MPRIS player (static version for threading)
+    /// Update internal state from all properties of the MPRIS player (static version for threading)
     fn update_state_from_mpris_static(
         bus_name: &str,
         bus_type: &BusType,
         current_song: &Arc<RwLock<Option<Song>>>,
         current_state: &Arc<RwLock<PlayerState>>,
+        queue: &Arc<RwLock<Vec<Track>>>,
         base: &BasePlayerController,
     ) {
         debug!("Updating state from MPRIS player: {}", bus_name);
@@ -191,85 +213,125 @@ impl MprisPlayerController {
         }
         
         let proxy = create_player_proxy(&conn, bus_name);
+        match retrieve_player_properties(&proxy) {
+            Some(properties) => Self::apply_properties(bus_name, &properties, current_song, current_state, base),
+            None => debug!("No properties available for {}", bus_name),
+        }
+        Self::apply_track_list(bus_name, &proxy, queue, base);
         
-        // Update playback state
-        if let Some(status) = get_string_property(&proxy, "org.mpris.MediaPlayer2.Player", "PlaybackStatus") {
-            debug!("MPRIS PlaybackStatus for {}: {}", bus_name, status);
-            
-            let state = match status.as_str() {
-                "Playing" => PlaybackState::Playing,
-                "Paused" => PlaybackState::Paused,
-                "Stopped" => PlaybackState::Stopped,
-                _ => {
-                    debug!("Unknown playback status '{}' for {}", status, bus_name);
-                    PlaybackState::Unknown
-                }
-            };
-            
-            {
-                let mut current_state = current_state.write();
-                let old_state = current_state.state;
-                current_state.state = state;
-                if old_state != state {
-                    debug!("MPRIS state changed for {}: {:?} -> {:?}", bus_name, old_state, state);
+        // Mark player as alive
+        base.alive();
+        debug!("Completed state update for MPRIS player: {}", bus_name);
+    }
+
+    /// Read the tracks of the TrackList interface (static version for threading)
+    fn update_queue_static(bus_name: &str, bus_type: &BusType, queue: &Arc<RwLock<Vec<Track>>>, base: &BasePlayerController) {
+        let Ok(conn) = create_connection(bus_type.clone()) else {
+            debug!("Failed to connect to MPRIS player {} for queue update", bus_name);
+            return;
+        };
+        let proxy = create_player_proxy(&conn, bus_name);
+        Self::apply_track_list(bus_name, &proxy, queue, base);
+    }
+
+    /// Replace the queue with the tracks of the TrackList interface and notify listeners if it changed
+    fn apply_track_list(bus_name: &str, proxy: &Proxy<'_, &Connection>, queue: &Arc<RwLock<Vec<Track>>>, base: &BasePlayerController) {
+        let track_list = retrieve_track_list(proxy);
+        // Only players with a track list have a queue
+        base.set_capability(PlayerCapability::Queue, track_list.is_some(), true);
+
+        let tracks: Vec<Track> = track_list.unwrap_or_default().iter().map(song_to_track).collect();
+        let changed = {
+            let mut queue = queue.write();
+            let changed = *queue != tracks;
+            *queue = tracks;
+            changed
+        };
+        if changed {
+            debug!("MPRIS track list of {} changed: {} tracks", bus_name, queue.read().len());
+            base.notify_queue_changed();
+        }
+    }
+
+    /// Apply changed properties of the player interface and notify listeners about changes
+    ///
+    /// Used for the initial state as well as for PropertiesChanged signals, which only
+    /// contain the properties that changed.
+    fn apply_properties(
+        bus_name: &str,
+        properties: &PropMap,
+        current_song: &Arc<RwLock<Option<Song>>>,
+        current_state: &Arc<RwLock<PlayerState>>,
+        base: &BasePlayerController,
+    ) {
+        let mut state_changed = None;
+        let mut shuffle_changed = None;
+        let mut loop_mode_changed = None;
+        {
+            let mut current_state = current_state.write();
+
+            if let Some(status) = properties.get("PlaybackStatus").and_then(|v| v.as_str()) {
+                let state = match status {
+                    "Playing" => PlaybackState::Playing,
+                    "Paused" => PlaybackState::Paused,
+                    "Stopped" => PlaybackState::Stopped,
+                    _ => {
+                        debug!("Unknown playback status '{}' for {}", status, bus_name);
+                        PlaybackState::Unknown
+                    }
+                };
+                if current_state.state != state {
+                    debug!("MPRIS state changed for {}: {:?} -> {:?}", bus_name, current_state.state, state);
+                    current_state.state = state;
+                    state_changed = Some(state);
                 }
+            }
 
-                // Update shuffle
-                let shuffle = get_bool_property(&proxy, "org.mpris.MediaPlayer2.Player", "Shuffle")
-                    .unwrap_or(false);
-                debug!("MPRIS Shuffle property for {}: {}", bus_name, shuffle);
+            if let Some(shuffle) = properties.get("Shuffle").and_then(|v| v.as_u64().or_else(|| v.as_i64().map(|i| i as u64))) {
+                let shuffle = shuffle != 0;
                 if current_state.shuffle != shuffle {
                     debug!("MPRIS shuffle changed for {}: {} -> {}", bus_name, current_state.shuffle, shuffle);
+                    current_state.shuffle = shuffle;
+                    shuffle_changed = Some(shuffle);
                 }
-                current_state.shuffle = shuffle;
-
-                // Update loop mode
-                if let Some(loop_status) = get_string_property(&proxy, "org.mpris.MediaPlayer2.Player", "LoopStatus") {
-                    debug!("MPRIS LoopStatus for {}: {}", bus_name, loop_status);
-
-                    let loop_mode = match loop_status.as_str() {
-                        "None" => LoopMode::None,
-                        "Track" => LoopMode::Track,
-                        "Playlist" => LoopMode::Playlist,
-                        _ => {
-                            debug!("Unknown loop status '{}' for {}", loop_status, bus_name);
-                            LoopMode::None
-                        }
-                    };
+            }
 
-                    if current_state.loop_mode != loop_mode {
-                        debug!("MPRIS loop mode changed for {}: {:?} -> {:?}", bus_name, current_state.loop_mode, loop_mode);
+            if let Some(loop_status) = properties.get("LoopStatus").and_then(|v| v.as_str()) {
+                let loop_mode = match loop_status {
+                    "None" => LoopMode::None,
+                    "Track" => LoopMode::Track,
+                    "Playlist" => LoopMode::Playlist,
+                    _ => {
+                        debug!("Unknown loop status '{}' for {}", loop_status, bus_name);
+                        LoopMode::None
                     }
+                };
+                if current_state.loop_mode != loop_mode {
+                    debug!("MPRIS loop mode changed for {}: {:?} -> {:?}", bus_name, current_state.loop_mode, loop_mode);
                     current_state.loop_mode = loop_mode;
+                    loop_mode_changed = Some(loop_mode);
                 }
+            }
 
-                // Update position (convert from microseconds to seconds)
-                if let Some(position_us) = get_i64_property(&proxy, "org.mpris.MediaPlayer2.Player", "Position") {
-                    let position_seconds = position_us as f64 / 1_000_000.0;
-                    debug!("MPRIS position for {}: {:.2}s ({}μs)", bus_name, position_seconds, position_us);
-                    current_state.position = Some(position_seconds);
-                } else {
-                    debug!("No position information available for {}", bus_name);
-                }
-
-                // Log the complete state after all updates
-                debug!("Complete MPRIS state for {}: state={:?}, shuffle={}, loop_mode={:?}, position={:?}",
-                       bus_name, current_state.state, current_state.shuffle, current_state.loop_mode, current_state.position);
+            // Position is in microseconds, players don't send signals for its regular progress
+            if let Some(position_us) = properties.get("Position").and_then(|v| v.as_i64()) {
+                current_state.position = Some(position_us as f64 / 1_000_000.0);
             }
-        } else {
-            debug!("No PlaybackStatus property available for {}", bus_name);
         }
-        
-        // Update song metadata using helper functions
-        if let Some(metadata_variant) = retrieve_mpris_metadata(&proxy) {
-            debug!("Retrieved MPRIS metadata for {}: {:?}", bus_name, metadata_variant);
-            let song = extract_song_from_mpris_metadata(&metadata_variant);
-            debug!("Extracted song from MPRIS metadata for {}: title={:?}, artist={:?}, album={:?}", 
-                   bus_name,
-                   song.as_ref().and_then(|s| s.title.as_ref()),
-                   song.as_ref().and_then(|s| s.artist.as_ref()),
-                   song.as_ref().and_then(|s| s.album.as_ref()));
-            {
+
+        if let Some(state) = state_changed {
+            base.notify_state_changed(state);
+        }
+        if let Some(shuffle) = shuffle_changed {
+            base.notify_random_changed(shuffle);
+        }
+        if let Some(loop_mode) = loop_mode_changed {
+            base.notify_loop_mode_changed(loop_mode);
+        }
+
+        if let Some(metadata_variant) = properties.get("Metadata") {
+            let song = extract_song_from_mpris_metadata(metadata_variant);
+            let song_changed = {
                 let mut current_song = current_song.write();
                 let song_changed = match (&*current_song, &song) {
                     (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist,
@@ -277,111 +339,224 @@ impl MprisPlayerController {
                     (Some(_), None) => true,
                     (None, None) => false,
                 };
+                *current_song = song.clone();
+                song_changed
+            };
 
-                if song_changed {
-                    debug!("MPRIS song changed for {}: {:?} -> {:?}",
-                           bus_name,
-                           current_song.as_ref().map(|s| format!("{} - {}",
-                               s.artist.as_deref().unwrap_or("Unknown Artist"),
-                               s.title.as_deref().unwrap_or("Unknown Title"))),
-                           song.as_ref().map(|s| format!("{} - {}",
-                               s.artist.as_deref().unwrap_or("Unknown Artist"),
-                               s.title.as_deref().unwrap_or("Unknown Title"))));
-                }
-
-                *current_song = song;
+            if song_changed {
+                debug!("MPRIS song changed for {}: {:?}",
+                       bus_name,
+                       song.as_ref().map(|s| format!("{} - {}",
+                           s.artist.as_deref().unwrap_or("Unknown Artist"),
+                           s.title.as_deref().unwrap_or("Unknown Title"))));
+                base.notify_song_changed(song.as_ref());
             }
-        } else {
-            debug!("No metadata available for {}", bus_name);
         }
-        
-        // Mark player as alive
-        base.alive();
-        debug!("Completed state update for MPRIS player: {}", bus_name);
     }
 
-    /// Update internal state from MPRIS player
-    fn update_state_from_mpris(&self) {
-        Self::update_state_from_mpris_static(
-            &self.bus_name,
-            &self.bus_type,
-            &self.current_song,
-            &self.current_state,
-            &self.base,
-        );
-    }
-    
-    /// Start the polling thread
-    fn start_polling(&self) {
-        if self.should_poll.load(Ordering::Relaxed) {
-            debug!("Polling already started for MPRIS player {}", self.bus_name);
+    /// Start the thread that receives PropertiesChanged and Seeked signals of the player
+    ///
+    /// Falls back to polling if the signals can't be subscribed.
+    fn start_listener(&self) {
+        if self.should_run.load(Ordering::Relaxed) {
+            debug!("Listener already started for MPRIS player {}", self.bus_name);
             return;
         }
         
-        info!("Starting polling thread for MPRIS player {} with interval {:?}", self.bus_name, self.poll_interval);
-        self.should_poll.store(true, Ordering::Relaxed);
+        info!("Starting signal listener for MPRIS player {}", self.bus_name);
+        self.should_run.store(true, Ordering::Relaxed);
         
         let bus_name = self.bus_name.clone();
         let bus_type = self.bus_type.clone();
         let poll_interval = self.poll_interval;
-        let should_poll = Arc::clone(&self.should_poll);
+        let should_run = Arc::clone(&self.should_run);
         let current_song = Arc::clone(&self.current_song);
         let current_state = Arc::clone(&self.current_state);
+        let queue = Arc::clone(&self.queue);
         let base = self.base.clone();
         
         let handle = thread::spawn(move || {
-            debug!("MPRIS polling thread started for {}", bus_name);
-            let mut last_update = Instant::now();
-            
-            while should_poll.load(Ordering::Relaxed) {
-                let now = Instant::now();
-                if now.duration_since(last_update) >= poll_interval {
-                    debug!("MPRIS polling cycle for {} - attempting connection", bus_name);
-                    // Use the static method to get full debug logging
-                    Self::update_state_from_mpris_static(
-                        &bus_name,
-                        &bus_type,
-                        &current_song,
-                        &current_state,
-                        &base,
-                    );
-                    last_update = now;
+            debug!("MPRIS listener thread started for {}", bus_name);
+            let refresh = Arc::new(AtomicBool::new(true));
+            let queue_refresh = Arc::new(AtomicBool::new(false));
+
+            let subscribed = create_connection(bus_type.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|conn| {
+                    Self::subscribe(&conn, &bus_name, &refresh, &queue_refresh, &current_song, &current_state, &base)
+                        .map(|_| conn)
+                        .map_err(|e| e.to_string())
+                });
+
+            match subscribed {
+                Ok(conn) => {
+                    while should_run.load(Ordering::Relaxed) {
+                        // Read everything after start, invalidated properties and player restarts
+                        if refresh.swap(false, Ordering::Relaxed) {
+                            queue_refresh.store(false, Ordering::Relaxed);
+                            Self::update_state_from_mpris_static(&bus_name, &bus_type, &current_song, &current_state, &queue, &base);
+                        } else if queue_refresh.swap(false, Ordering::Relaxed) {
+                            Self::update_queue_static(&bus_name, &bus_type, &queue, &base);
+                        }
+                        // Blocks until a signal arrives, the timeout only limits the time to stop the thread
+                        if let Err(e) = conn.process(LISTENER_TIMEOUT) {
+                            warn!("Error receiving D-Bus signals for {}: {}", bus_name, e);
+                            thread::sleep(LISTENER_TIMEOUT);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Can't subscribe to signals of MPRIS player {}, polling every {:?}: {}", bus_name, poll_interval, e);
+                    let mut last_update: Option<Instant> = None;
+                    while should_run.load(Ordering::Relaxed) {
+                        if last_update.is_none_or(|t| t.elapsed() >= poll_interval) {
+                            Self::update_state_from_mpris_static(&bus_name, &bus_type, &current_song, &current_state, &queue, &base);
+                            last_update = Some(Instant::now());
+                        }
+
+                        // Sleep for a short time to avoid busy waiting
+                        thread::sleep(Duration::from_millis(100));
+                    }
                 }
-                
-                // Sleep for a short time to avoid busy waiting
-                thread::sleep(Duration::from_millis(100));
             }
             
-            debug!("MPRIS polling thread stopped for {}", bus_name);
+            debug!("MPRIS listener thread stopped for {}", bus_name);
         });
         
         {
-            let mut thread_handle = self.poll_thread_handle.write();
+            let mut thread_handle = self.listener_thread_handle.write();
             *thread_handle = Some(handle);
         }
     }
+
+    /// Subscribe to the signals of the player and to owner changes of its bus name
+    fn subscribe(
+        conn: &Connection,
+        bus_name: &str,
+        refresh: &Arc<AtomicBool>,
+        queue_refresh: &Arc<AtomicBool>,
+        current_song: &Arc<RwLock<Option<Song>>>,
+        current_state: &Arc<RwLock<PlayerState>>,
+        base: &BasePlayerController,
+    ) -> Result<(), dbus::Error> {
+        // The bus delivers signals of whichever process currently owns the bus name
+        let properties_rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+            .with_sender(bus_name.to_string())
+            .with_path(MPRIS_PATH);
+        {
+            let bus_name = bus_name.to_string();
+            let refresh = Arc::clone(refresh);
+            let queue_refresh = Arc::clone(queue_refresh);
+            let current_song = Arc::clone(current_song);
+            let current_state = Arc::clone(current_state);
+            let base = base.clone();
+            conn.add_match(properties_rule, move |signal: PropertiesPropertiesChanged, _: &Connection, _: &Message| {
+                // Tracks or HasTrackList changed, the tracks are read after the signal was handled
+                if signal.interface_name == "org.mpris.MediaPlayer2.TrackList" || signal.interface_name == "org.mpris.MediaPlayer2" {
+                    queue_refresh.store(true, Ordering::Relaxed);
+                }
+                if signal.interface_name == "org.mpris.MediaPlayer2.Player" {
+                    debug!("MPRIS properties changed for {}: {:?}", bus_name, signal.changed_properties.keys());
+                    Self::apply_properties(&bus_name, &signal.changed_properties, &current_song, &current_state, &base);
+                    if !signal.invalidated_properties.is_empty() {
+                        refresh.store(true, Ordering::Relaxed);
+                    }
+                    base.alive();
+                }
+                true
+            })?;
+        }
+
+        let seeked_rule = MatchRule::new_signal("org.mpris.MediaPlayer2.Player", "Seeked")
+            .with_sender(bus_name.to_string())
+            .with_path(MPRIS_PATH);
+        {
+            let current_state = Arc::clone(current_state);
+            let base = base.clone();
+            conn.add_match(seeked_rule, move |(position_us,): (i64,), _: &Connection, _: &Message| {
+                let position = position_us as f64 / 1_000_000.0;
+                current_state.write().position = Some(position);
+                base.notify_position_changed(position);
+                true
+            })?;
+        }
+
+        // TrackListReplaced, TrackAdded, TrackRemoved and TrackMetadataChanged
+        let track_list_rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_interface("org.mpris.MediaPlayer2.TrackList")
+            .with_sender(bus_name.to_string())
+            .with_path(MPRIS_PATH);
+        {
+            let queue_refresh = Arc::clone(queue_refresh);
+            conn.add_match(track_list_rule, move |_: (), _: &Connection, _: &Message| {
+                queue_refresh.store(true, Ordering::Relaxed);
+                true
+            })?;
+        }
+
+        let owner_rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+            .with_sender("org.freedesktop.DBus")
+            .with_path("/org/freedesktop/DBus");
+        {
+            let bus_name = bus_name.to_string();
+            let refresh = Arc::clone(refresh);
+            let current_state = Arc::clone(current_state);
+            let base = base.clone();
+            conn.add_match(owner_rule, move |(name, _old_owner, new_owner): (String, String, String), _: &Connection, _: &Message| {
+                if name != bus_name {
+                    return true;
+                }
+                if new_owner.is_empty() {
+                    info!("MPRIS player {} disappeared from the bus", bus_name);
+                    let was_stopped = {
+                        let mut current_state = current_state.write();
+                        std::mem::replace(&mut current_state.state, PlaybackState::Stopped) == PlaybackState::Stopped
+                    };
+                    if !was_stopped {
+                        base.notify_state_changed(PlaybackState::Stopped);
+                    }
+                } else {
+                    info!("MPRIS player {} appeared on the bus", bus_name);
+                    refresh.store(true, Ordering::Relaxed);
+                }
+                true
+            })?;
+        }
+        Ok(())
+    }
     
-    /// Stop the polling thread
-    fn stop_polling(&self) {
-        if !self.should_poll.load(Ordering::Relaxed) {
-            debug!("Polling already stopped for MPRIS player {}", self.bus_name);
+    /// Stop the listener thread
+    fn stop_listener(&self) {
+        if !self.should_run.load(Ordering::Relaxed) {
+            debug!("Listener already stopped for MPRIS player {}", self.bus_name);
             return;
         }
         
-        info!("Stopping polling thread for MPRIS player {}", self.bus_name);
-        self.should_poll.store(false, Ordering::Relaxed);
+        info!("Stopping listener thread for MPRIS player {}", self.bus_name);
+        self.should_run.store(false, Ordering::Relaxed);
         
         {
-            let mut thread_handle = self.poll_thread_handle.write();
+            let mut thread_handle = self.listener_thread_handle.write();
             if let Some(handle) = thread_handle.take() {
                 if let Err(e) = handle.join() {
-                    warn!("Error joining polling thread for {}: {:?}", self.bus_name, e);
+                    warn!("Error joining listener thread for {}: {:?}", self.bus_name, e);
                 }
             }
         }
     }
 }
 
+/// Convert the metadata of a track list entry to a queue track
+fn song_to_track(song: &Song) -> Track {
+    let mut track = Track::with_name(song.title.clone().unwrap_or_default());
+    track.artist = song.artist.clone();
+    track.track_number = song.track_number.and_then(|n| u16::try_from(n).ok());
+    track.uri = song.metadata.get("xesam:url").and_then(|u| u.as_str()).map(|u| u.to_string());
+    track.duration = song.duration;
+    track
+}
+
 impl PlayerController for MprisPlayerController {
     fn get_capabilities(&self) -> PlayerCapabilitySet {
         self.base.get_capabilities()
@@ -417,30 +592,26 @@ impl PlayerController for MprisPlayerController {
     }
     
     fn get_playback_state(&self) -> PlaybackState {
-        self.update_state_from_mpris();
         let state = self.current_state.read();
         state.state
     }
     
     fn get_song(&self) -> Option<Song> {
-        self.update_state_from_mpris();
         let song = self.current_song.read();
         song.clone()
     }
     
     fn get_queue(&self) -> Vec<Track> {
-        // MPRIS doesn't typically expose queue information
-        Vec::new()
+        // Only players with the TrackList interface expose their queue
+        self.queue.read().clone()
     }
     
     fn get_shuffle(&self) -> bool {
-        self.update_state_from_mpris();
         let state = self.current_state.read();
         state.shuffle
     }
     
     fn get_loop_mode(&self) -> LoopMode {
-        self.update_state_from_mpris();
         let state = self.current_state.read();
         state.loop_mode
     }
@@ -503,9 +674,8 @@ impl PlayerController for MprisPlayerController {
         
         match result {
             Ok(()) => {
+                // The new state arrives as PropertiesChanged signal
                 info!("Successfully sent command {} to MPRIS player", command);
-                // Trigger an immediate state update
-                self.update_state_from_mpris();
                 true
             }
             Err(e) => {
@@ -528,8 +698,8 @@ impl PlayerController for MprisPlayerController {
                 info!("Successfully connected to MPRIS player: {}", self.bus_name);
                 self.base.alive();
                 
-                // Start polling thread
-                self.start_polling();
+                // Start listening to the player's signals
+                self.start_listener();
                 
                 true
             }
@@ -543,9 +713,61 @@ impl PlayerController for MprisPlayerController {
     fn stop(&self) -> bool {
         info!("Stopping MPRIS player controller for {}", self.bus_name);
         
-        // Stop polling thread
-        self.stop_polling();
+        // Stop listener thread
+        self.stop_listener();
         
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dbus::arg::Variant;
+
+    #[test]
+    fn test_apply_properties() {
+        let base = BasePlayerController::with_player_info("vlc", "org.mpris.MediaPlayer2.vlc");
+        let current_song = Arc::new(RwLock::new(None));
+        let current_state = Arc::new(RwLock::new(PlayerState::new()));
+
+        let mut properties = PropMap::new();
+        properties.insert("PlaybackStatus".to_string(), Variant(Box::new("Playing".to_string()) as Box<dyn RefArg>));
+        properties.insert("Shuffle".to_string(), Variant(Box::new(true) as Box<dyn RefArg>));
+        properties.insert("LoopStatus".to_string(), Variant(Box::new("Track".to_string()) as Box<dyn RefArg>));
+        properties.insert("Position".to_string(), Variant(Box::new(1_500_000i64) as Box<dyn RefArg>));
+        MprisPlayerController::apply_properties("vlc", &properties, &current_song, &current_state, &base);
+        {
+            let state = current_state.read();
+            assert_eq!(state.state, PlaybackState::Playing);
+            assert!(state.shuffle);
+            assert_eq!(state.loop_mode, LoopMode::Track);
+            assert_eq!(state.position, Some(1.5));
+        }
+
+        // Signals only contain the changed properties
+        let mut changed = PropMap::new();
+        changed.insert("PlaybackStatus".to_string(), Variant(Box::new("Paused".to_string()) as Box<dyn RefArg>));
+        MprisPlayerController::apply_properties("vlc", &changed, &current_song, &current_state, &base);
+        let state = current_state.read();
+        assert_eq!(state.state, PlaybackState::Paused);
+        assert!(state.shuffle);
+        assert_eq!(state.loop_mode, LoopMode::Track);
+    }
+
+    #[test]
+    fn test_song_to_track() {
+        let mut metadata = PropMap::new();
+        metadata.insert("xesam:title".to_string(), Variant(Box::new("So What".to_string()) as Box<dyn RefArg>));
+        metadata.insert("xesam:artist".to_string(), Variant(Box::new(vec!["Miles Davis".to_string()]) as Box<dyn RefArg>));
+        metadata.insert("xesam:url".to_string(), Variant(Box::new("file:///music/so_what.flac".to_string()) as Box<dyn RefArg>));
+        metadata.insert("mpris:length".to_string(), Variant(Box::new(545_000_000i64) as Box<dyn RefArg>));
+        let song = extract_song_from_mpris_metadata(&Variant(Box::new(metadata) as Box<dyn RefArg>)).unwrap();
+
+        let track = song_to_track(&song);
+        assert_eq!(track.name, "So What");
+        assert_eq!(track.artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(track.uri.as_deref(), Some("file:///music/so_what.flac"));
+        assert_eq!(track.duration, Some(545.0));
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::data::{QueueTrackMetadata, Song};
+
+static SERVICE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<service>(.*?)</service>").unwrap());
+static ARGUMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([A-Za-z_]\w*)\s*(?:/>|>([^<]*)</[A-Za-z_]\w*>)").unwrap());
+static ENTRY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<Entry>\s*<Id>(\d+)</Id>\s*<Uri>(.*?)</Uri>\s*<Metadata>(.*?)</Metadata>\s*</Entry>").unwrap()
+});
+static DURATION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<res[^>]*\sduration="([^"]*)""#).unwrap());
+static RES_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<res[^>]*>([^<]*)</res>").unwrap());
+
+/// Services of an OpenHome device, keyed by service name (e.g. "Playlist")
+///
+/// Each entry holds the full service type and the absolute control URL.
+#[derive(Debug, Clone, Default)]
+pub struct OpenHomeDevice {
+    pub friendly_name: Option<String>,
+    pub services: HashMap<String, (String, String)>,
+}
+
+impl OpenHomeDevice {
+    pub fn has_service(&self, name: &str) -> bool {
+        self.services.contains_key(name)
+    }
+}
+
+/// Track metadata from a DIDL-Lite document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DidlItem {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub album_art_uri: Option<String>,
+    pub track_number: Option<i32>,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    pub uri: Option<String>,
+}
+
+impl DidlItem {
+    /// Convert queue metadata (title, artist, album, coverart_url, duration) to a DIDL-Lite item
+    pub fn from_queue_metadata(metadata: &QueueTrackMetadata) -> Self {
+        let text = |key: &str| metadata.metadata.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        DidlItem {
+            title: text("title"),
+            artist: text("artist"),
+            album: text("album"),
+            album_art_uri: text("coverart_url"),
+            duration: metadata.metadata.get("duration").and_then(|v| v.as_f64()),
+            ..Default::default()
+        }
+    }
+
+    /// Song for this item, `uri` is the track URI reported by the renderer
+    pub fn to_song(&self, uri: Option<&str>) -> Song {
+        Song {
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            album: self.album.clone(),
+            genre: self.genre.clone(),
+            track_number: self.track_number,
+            duration: self.duration,
+            cover_art_url: self.album_art_uri.clone(),
+            stream_url: uri.map(|u| u.to_string()).or_else(|| self.uri.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Playlist entry read from the Playlist service
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    pub id: u32,
+    pub uri: String,
+    pub item: DidlItem,
+}
+
+/// SOAP client for the OpenHome services of a renderer
+#[derive(Debug, Clone)]
+pub struct OpenHomeClient {
+    location: String,
+    timeout: Duration,
+}
+
+impl OpenHomeClient {
+    /// Create a client for the device description at `location`
+    pub fn new(location: &str) -> Self {
+        Self {
+            location: location.to_string(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Read the device description and find the OpenHome services
+    pub fn discover(&self) -> Result<OpenHomeDevice, String> {
+        let description = ureq::get(&self.location)
+            .timeout(self.timeout)
+            .call()
+            .map_err(|e| format!("Failed to read device description {}: {}", self.location, e))?
+            .into_string()
+            .map_err(|e| format!("Failed to read device description {}: {}", self.location, e))?;
+        let device = parse_description(&description, &self.location);
+        if !device.has_service("Playlist") {
+            return Err(format!("{} doesn't provide the OpenHome Playlist service", self.location));
+        }
+        Ok(device)
+    }
+
+    /// Invoke an action and return its output arguments
+    pub fn action(
+        &self,
+        device: &OpenHomeDevice,
+        service: &str,
+        action: &str,
+        args: &[(&str, &str)],
+    ) -> Result<HashMap<String, String>, String> {
+        let (service_type, control_url) = device
+            .services
+            .get(service)
+            .ok_or_else(|| format!("Service {} not available", service))?;
+
+        debug!("OpenHome {}#{} {:?}", service, action, args);
+        soap_action(control_url, service_type, action, args, self.timeout)
+            .map_err(|e| format!("{}#{} failed: {}", service, action, e))
+    }
+}
+
+/// Invoke a UPnP SOAP action and return its output arguments
+pub(crate) fn soap_action(
+    control_url: &str,
+    service_type: &str,
+    action: &str,
+    args: &[(&str, &str)],
+    timeout: Duration,
+) -> Result<HashMap<String, String>, String> {
+    let arguments: String = args
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape(value)))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service_type}\">{arguments}</u:{action}></s:Body></s:Envelope>"
+    );
+
+    let response = ureq::post(control_url)
+        .timeout(timeout)
+        .set("Content-Type", "text/xml; charset=\"utf-8\"")
+        .set("SOAPACTION", &format!("\"{}#{}\"", service_type, action))
+        .send_string(&body)
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    parse_action_response(&response, action).ok_or_else(|| "Unexpected response".to_string())
+}
+
+/// Escape text for use in XML
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Unescape XML text
+pub fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Get the unescaped text of the first element with the given (possibly prefixed) name
+pub(crate) fn element_text(xml: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"(?s)<{}(?:\s[^>]*)?>([^<]*)</{}>", regex::escape(name), regex::escape(name))).ok()?;
+    re.captures(xml)
+        .map(|caps| unescape(caps[1].trim()))
+        .filter(|text| !text.is_empty())
+}
+
+/// Resolve a possibly relative URL against the device description location
+pub(crate) fn resolve_url(base: &str, url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return url.to_string();
+    }
+    let root_end = base
+        .find("://")
+        .and_then(|scheme| base[scheme + 3..].find('/').map(|p| scheme + 3 + p))
+        .unwrap_or(base.len());
+    if url.starts_with('/') {
+        format!("{}{}", &base[..root_end], url)
+    } else {
+        let dir_end = base.rfind('/').filter(|p| *p >= root_end).unwrap_or(root_end);
+        format!("{}/{}", &base[..dir_end], url)
+    }
+}
+
+/// Parse a UPnP device description and collect the OpenHome services
+fn parse_description(xml: &str, location: &str) -> OpenHomeDevice {
+    let base = element_text(xml, "URLBase").unwrap_or_else(|| location.to_string());
+    let mut device = OpenHomeDevice {
+        friendly_name: element_text(xml, "friendlyName"),
+        services: HashMap::new(),
+    };
+
+    for caps in SERVICE_RE.captures_iter(xml) {
+        let (Some(service_type), Some(control_url)) =
+            (element_text(&caps[1], "serviceType"), element_text(&caps[1], "controlURL"))
+        else {
+            continue;
+        };
+        // urn:av-openhome-org:service:Playlist:1
+        let parts: Vec<&str> = service_type.split(':').collect();
+        if parts.len() != 5 || parts[1] != "av-openhome-org" {
+            continue;
+        }
+        let name = parts[3].to_string();
+        // Prefer the highest service version if a device lists several
+        let newer = device
+            .services
+            .get(&name)
+            .is_none_or(|(existing, _)| existing.as_str() < service_type.as_str());
+        if newer {
+            device.services.insert(name, (service_type.clone(), resolve_url(&base, &control_url)));
+        }
+    }
+    device
+}
+
+/// Get the output arguments of a SOAP action response
+fn parse_action_response(xml: &str, action: &str) -> Option<HashMap<String, String>> {
+    let start_re = Regex::new(&format!(r"<(?:\w+:)?{}Response[^>]*?(/?)>", regex::escape(action))).ok()?;
+    let start = start_re.captures(xml)?;
+    let whole = start.get(0)?;
+    if &start[1] == "/" {
+        return Some(HashMap::new());
+    }
+    let body = &xml[whole.end()..];
+    let body = &body[..body.find(&format!("{}Response>", action)).unwrap_or(body.len())];
+    Some(
+        ARGUMENT_RE
+            .captures_iter(body)
+            .map(|caps| (caps[1].to_string(), caps.get(2).map(|m| unescape(m.as_str())).unwrap_or_default()))
+            .collect(),
+    )
+}
+
+/// Decode the id array of the Playlist service (base64 encoded big endian u32 values)
+pub fn decode_id_array(array: &str) -> Vec<u32> {
+    STANDARD
+        .decode(array.trim())
+        .map(|bytes| {
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse the track list returned by Playlist#ReadList
+pub fn parse_track_list(xml: &str) -> Vec<PlaylistEntry> {
+    ENTRY_RE
+        .captures_iter(xml)
+        .filter_map(|caps| {
+            Some(PlaylistEntry {
+                id: caps[1].parse().ok()?,
+                uri: unescape(&caps[2]),
+                item: parse_didl(&unescape(&caps[3])),
+            })
+        })
+        .collect()
+}
+
+/// Parse a duration in the DIDL-Lite format H+:MM:SS[.F+]
+pub(crate) fn parse_duration(duration: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in duration.trim().split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Parse the first item of a DIDL-Lite document
+pub fn parse_didl(didl: &str) -> DidlItem {
+    DidlItem {
+        title: element_text(didl, "dc:title"),
+        artist: element_text(didl, "upnp:artist").or_else(|| element_text(didl, "dc:creator")),
+        album: element_text(didl, "upnp:album"),
+        genre: element_text(didl, "upnp:genre"),
+        album_art_uri: element_text(didl, "upnp:albumArtURI"),
+        track_number: element_text(didl, "upnp:originalTrackNumber").and_then(|n| n.parse().ok()),
+        duration: DURATION_RE.captures(didl).and_then(|caps| parse_duration(&caps[1])),
+        uri: RES_RE.captures(didl).map(|caps| unescape(caps[1].trim())).filter(|u| !u.is_empty()),
+    }
+}
+
+/// Create a DIDL-Lite document for a track to insert into the playlist
+pub fn build_didl(uri: &str, item: &DidlItem) -> String {
+    let mut fields = String::new();
+    let mut add = |tag: &str, value: &Option<String>| {
+        if let Some(value) = value {
+            fields.push_str(&format!("<{0}>{1}</{0}>", tag, escape(value)));
+        }
+    };
+    add("dc:title", &item.title);
+    add("upnp:artist", &item.artist);
+    add("upnp:album", &item.album);
+    add("upnp:genre", &item.genre);
+    add("upnp:albumArtURI", &item.album_art_uri);
+
+    let duration = item
+        .duration
+        .map(|d| {
+            let secs = d.max(0.0) as u64;
+            format!(" duration=\"{}:{:02}:{:02}.000\"", secs / 3600, (secs / 60) % 60, secs % 60)
+        })
+        .unwrap_or_default();
+
+    format!(
+        "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">\
+         <item id=\"\" parentID=\"\" restricted=\"1\">{}\
+         <res protocolInfo=\"http-get:*:*:*\"{}>{}</res>\
+         <upnp:class>object.item.audioItem.musicTrack</upnp:class></item></DIDL-Lite>",
+        fields,
+        duration,
+        escape(uri)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        let xml = r#"<root><device><friendlyName>Living Room:Akurate DSM</friendlyName><serviceList>
+            <service><serviceType>urn:av-openhome-org:service:Product:2</serviceType><controlURL>/Ds/Product/control</controlURL></service>
+            <service><serviceType>urn:av-openhome-org:service:Playlist:1</serviceType><controlURL>Playlist/control</controlURL></service>
+            <service><serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType><controlURL>/AVTransport</controlURL></service>
+            </serviceList></device></root>"#;
+        let device = parse_description(xml, "http://192.168.1.20:55178/Ds/device.xml");
+        assert_eq!(device.friendly_name.as_deref(), Some("Living Room:Akurate DSM"));
+        assert_eq!(device.services.len(), 2);
+        assert_eq!(device.services["Product"].1, "http://192.168.1.20:55178/Ds/Product/control");
+        assert_eq!(device.services["Playlist"].0, "urn:av-openhome-org:service:Playlist:1");
+        assert_eq!(device.services["Playlist"].1, "http://192.168.1.20:55178/Ds/Playlist/control");
+    }
+
+    #[test]
+    fn test_parse_action_response() {
+        let xml = r#"<?xml version="1.0"?><s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body>
+            <u:IdArrayResponse xmlns:u="urn:av-openhome-org:service:Playlist:1"><Token>7</Token><Array>AAAAAQAAAAUAAAEA</Array></u:IdArrayResponse>
+            </s:Body></s:Envelope>"#;
+        let args = parse_action_response(xml, "IdArray").unwrap();
+        assert_eq!(args["Token"], "7");
+        assert_eq!(decode_id_array(&args["Array"]), vec![1, 5, 256]);
+
+        let empty = r#"<s:Body><u:PlayResponse xmlns:u="urn:av-openhome-org:service:Playlist:1"/></s:Body>"#;
+        assert!(parse_action_response(empty, "Play").unwrap().is_empty());
+        assert!(parse_action_response(empty, "Pause").is_none());
+    }
+
+    #[test]
+    fn test_didl_roundtrip() {
+        let item = DidlItem {
+            title: Some("Rock & Roll".to_string()),
+            artist: Some("Led Zeppelin".to_string()),
+            album: Some("IV".to_string()),
+            duration: Some(220.0),
+            ..Default::default()
+        };
+        let didl = build_didl("http://server/track?id=1&fmt=flac", &item);
+        assert!(didl.contains("duration=\"0:03:40.000\""));
+
+        let parsed = parse_didl(&didl);
+        assert_eq!(parsed.title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(parsed.artist.as_deref(), Some("Led Zeppelin"));
+        assert_eq!(parsed.duration, Some(220.0));
+        assert_eq!(parsed.uri.as_deref(), Some("http://server/track?id=1&fmt=flac"));
+
+        // ReadList returns the DIDL-Lite escaped once more inside the track list
+        let list = format!(
+            "<TrackList><Entry><Id>12</Id><Uri>http://server/track?id=1&amp;fmt=flac</Uri><Metadata>{}</Metadata></Entry></TrackList>",
+            escape(&didl)
+        );
+        let entries = parse_track_list(&list);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, 12);
+        assert_eq!(entries[0].uri, "http://server/track?id=1&fmt=flac");
+        assert_eq!(entries[0].item.album.as_deref(), Some("IV"));
+    }
+}
@@ -0,0 +1,556 @@
+use crate::players::poll_task::PollTask;
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::data::{Identifier, PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, QueueTrackMetadata, Track};
+use crate::data::stream_details::StreamDetails;
+use crate::players::openhome::client::{
+    build_didl, decode_id_array, parse_didl, parse_track_list, DidlItem, OpenHomeClient, OpenHomeDevice, PlaylistEntry,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::RwLock;
+use std::time::Duration;
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// Number of playlist entries requested with a single ReadList call
+const READ_LIST_CHUNK: usize = 100;
+
+/// Playlist of the renderer, only re-read when the token of the id array changes
+#[derive(Debug, Clone, Default)]
+struct PlaylistCache {
+    token: Option<String>,
+    entries: Vec<PlaylistEntry>,
+}
+
+/// Shared state of the controller, updated by the poll task
+#[derive(Clone)]
+struct OpenHomeState {
+    client: OpenHomeClient,
+    device: Arc<RwLock<Option<OpenHomeDevice>>>,
+    current_song: Arc<RwLock<Option<Song>>>,
+    current_state: Arc<RwLock<PlayerState>>,
+    current_id: Arc<RwLock<Option<u32>>>,
+    playlist: Arc<RwLock<PlaylistCache>>,
+    stream_details: Arc<RwLock<Option<StreamDetails>>>,
+}
+
+/// OpenHome renderer controller
+///
+/// Controls renderers implementing the OpenHome services (Linn DS, BubbleUPnP Server,
+/// upmpdcli and others). Unlike plain UPnP AVTransport renderers they keep their own
+/// playlist, which is exposed as the player queue.
+pub struct OpenHomeController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// State shared with the poll task
+    state: OpenHomeState,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Whether polling is started
+    should_poll: Arc<AtomicBool>,
+
+    /// Task polling the player on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
+}
+
+// Manually implement Clone for OpenHomeController
+impl Clone for OpenHomeController {
+    fn clone(&self) -> Self {
+        OpenHomeController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            state: self.state.clone(),
+            poll_interval: self.poll_interval,
+            should_poll: Arc::clone(&self.should_poll),
+            poll_task: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl OpenHomeState {
+    fn device(&self) -> Result<OpenHomeDevice, String> {
+        if let Some(device) = self.device.read().clone() {
+            return Ok(device);
+        }
+        let device = self.client.discover()?;
+        info!("Found OpenHome renderer {} with services {:?}",
+              device.friendly_name.as_deref().unwrap_or("unknown"),
+              device.services.keys().collect::<Vec<_>>());
+        *self.device.write() = Some(device.clone());
+        Ok(device)
+    }
+
+    fn action(&self, service: &str, action: &str, args: &[(&str, &str)]) -> Result<HashMap<String, String>, String> {
+        let device = self.device()?;
+        let result = self.client.action(&device, service, action, args);
+        if result.is_err() {
+            // The renderer may have restarted with different control URLs
+            *self.device.write() = None;
+        }
+        result
+    }
+
+    fn value(&self, service: &str, action: &str, key: &str) -> Option<String> {
+        self.action(service, action, &[]).ok()?.remove(key)
+    }
+
+    fn has_service(&self, service: &str) -> bool {
+        self.device.read().as_ref().is_some_and(|d| d.has_service(service))
+    }
+
+    /// Re-read the playlist if its token changed, returns true if it was re-read
+    fn refresh_playlist(&self) -> Result<bool, String> {
+        let mut ids = self.action("Playlist", "IdArray", &[])?;
+        let token = ids.remove("Token");
+        if token.is_some() && self.playlist.read().token == token {
+            return Ok(false);
+        }
+
+        let ids = decode_id_array(&ids.remove("Array").unwrap_or_default());
+        let mut entries = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(READ_LIST_CHUNK) {
+            let id_list = chunk.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" ");
+            let mut result = self.action("Playlist", "ReadList", &[("IdList", &id_list)])?;
+            entries.extend(parse_track_list(&result.remove("TrackList").unwrap_or_default()));
+        }
+        debug!("OpenHome playlist changed, {} entries", entries.len());
+
+        *self.playlist.write() = PlaylistCache { token, entries };
+        Ok(true)
+    }
+
+    fn playlist_ids(&self) -> Vec<u32> {
+        self.playlist.read().entries.iter().map(|e| e.id).collect()
+    }
+}
+
+impl OpenHomeController {
+    /// Create a new OpenHome controller for the device description at `location`
+    pub fn new(location: &str, name: Option<&str>, poll_interval: Duration) -> Self {
+        debug!("Creating new OpenHomeController for {}", location);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info(name.unwrap_or("openhome"), location),
+            state: OpenHomeState {
+                client: OpenHomeClient::new(location),
+                device: Arc::new(RwLock::new(None)),
+                current_song: Arc::new(RwLock::new(None)),
+                current_state: Arc::new(RwLock::new(PlayerState::new())),
+                current_id: Arc::new(RwLock::new(None)),
+                playlist: Arc::new(RwLock::new(PlaylistCache::default())),
+                stream_details: Arc::new(RwLock::new(None)),
+            },
+            poll_interval,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            poll_task: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    ///
+    /// `location` is the URL of the UPnP device description of the renderer.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        let location = config.get("location")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "location of the device description is required".to_string())?;
+
+        let name = config.get("name").and_then(|v| v.as_str());
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        Ok(Self::new(location, name, poll_interval))
+    }
+
+    /// Set the default capabilities for OpenHome renderers
+    fn set_default_capabilities(&self) {
+        debug!("Setting default OpenHomeController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::Stop,
+            PlayerCapability::Previous,
+            PlayerCapability::Next,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Queue,
+            PlayerCapability::Metadata,
+            PlayerCapability::AlbumArt,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Set the volume of the renderer (Volume service)
+    pub fn set_volume(&self, volume: u32) -> Result<(), String> {
+        self.state.action("Volume", "SetVolume", &[("Value", &volume.to_string())]).map(|_| ())
+    }
+
+    /// Mute or unmute the renderer (Volume service)
+    pub fn set_mute(&self, mute: bool) -> Result<(), String> {
+        self.state.action("Volume", "SetMute", &[("Value", &mute.to_string())]).map(|_| ())
+    }
+
+    fn track_from_entry(entry: &PlaylistEntry) -> Track {
+        let mut track = Track::with_name(entry.item.title.clone().unwrap_or_else(|| entry.uri.clone()))
+            .with_uri(entry.uri.clone());
+        track.id = Some(Identifier::Numeric(entry.id as u64));
+        track.artist = entry.item.artist.clone();
+        track
+    }
+
+    /// Update internal state from the renderer (static version for threading)
+    fn update_state_static(state: &OpenHomeState, base: &BasePlayerController) {
+        let Some(transport) = state.value("Playlist", "TransportState", "Value") else {
+            debug!("OpenHome renderer {} not reachable", state.client.location());
+            return;
+        };
+
+        let playback_state = match transport.as_str() {
+            "Playing" | "Buffering" => PlaybackState::Playing,
+            "Paused" => PlaybackState::Paused,
+            "Stopped" => PlaybackState::Stopped,
+            _ => PlaybackState::Unknown,
+        };
+        let shuffle = state.value("Playlist", "Shuffle", "Value").map(|v| v == "true" || v == "1");
+        let repeat = state.value("Playlist", "Repeat", "Value").map(|v| v == "true" || v == "1");
+        let position = if state.has_service("Time") {
+            state.value("Time", "Time", "Seconds").and_then(|s| s.parse::<f64>().ok())
+        } else {
+            None
+        };
+        let volume = if state.has_service("Volume") {
+            state.value("Volume", "Volume", "Value").and_then(|v| v.parse::<i32>().ok())
+        } else {
+            None
+        };
+
+        let (state_changed, shuffle_changed, loop_changed, position_changed) = {
+            let mut current = state.current_state.write();
+            let state_changed = current.state != playback_state;
+            current.state = playback_state;
+            let shuffle_changed = shuffle.is_some_and(|s| s != current.shuffle);
+            if let Some(shuffle) = shuffle {
+                current.shuffle = shuffle;
+            }
+            let loop_mode = repeat.map(|r| if r { LoopMode::Playlist } else { LoopMode::None });
+            let loop_changed = loop_mode.is_some_and(|m| m != current.loop_mode);
+            if let Some(loop_mode) = loop_mode {
+                current.loop_mode = loop_mode;
+            }
+            let position_changed = position.is_some() && current.position != position;
+            current.position = position;
+            current.volume = volume;
+            (state_changed, shuffle_changed, loop_changed, position_changed)
+        };
+        let current = state.current_state.read().clone();
+        if state_changed {
+            base.notify_state_changed(current.state);
+        }
+        if shuffle_changed {
+            base.notify_random_changed(current.shuffle);
+        }
+        if loop_changed {
+            base.notify_loop_mode_changed(current.loop_mode);
+        }
+        if position_changed {
+            if let Some(position) = current.position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        match state.refresh_playlist() {
+            Ok(true) => base.notify_queue_changed(),
+            Ok(false) => {}
+            Err(e) => debug!("Failed to read OpenHome playlist: {}", e),
+        }
+
+        *state.current_id.write() = state
+            .value("Playlist", "Id", "Value")
+            .and_then(|id| id.parse::<u32>().ok())
+            .filter(|id| *id != 0);
+
+        // The Info service also knows about tracks from other sources (radio, inputs)
+        let song = if state.has_service("Info") {
+            state.action("Info", "Track", &[]).ok().and_then(|mut track| {
+                let uri = track.remove("Uri").filter(|u| !u.is_empty());
+                let metadata = track.remove("Metadata").unwrap_or_default();
+                if uri.is_none() && metadata.is_empty() {
+                    return None;
+                }
+                Some(parse_didl(&metadata).to_song(uri.as_deref()))
+            })
+        } else {
+            let id = *state.current_id.read();
+            state.playlist.read().entries.iter()
+                .find(|entry| Some(entry.id) == id)
+                .map(|entry| entry.item.to_song(Some(&entry.uri)))
+        };
+
+        let song_changed = {
+            let mut current_song = state.current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.stream_url != new.stream_url,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("OpenHome song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+
+        if state.has_service("Info") {
+            if let Ok(details) = state.action("Info", "Details", &[]) {
+                let number = |key: &str| details.get(key).and_then(|v| v.parse::<u32>().ok()).filter(|v| *v > 0);
+                *state.stream_details.write() = Some(StreamDetails {
+                    sample_rate: number("SampleRate"),
+                    bits_per_sample: number("BitDepth").map(|b| b as u8),
+                    lossless: details.get("Lossless").map(|v| v == "true" || v == "1"),
+                    codec: details.get("CodecName").filter(|c| !c.is_empty()).cloned(),
+                    ..StreamDetails::new()
+                });
+            }
+        }
+
+        base.alive();
+    }
+
+    /// Update internal state from the renderer
+    fn update_state(&self) {
+        Self::update_state_static(&self.state, &self.base);
+    }
+
+    /// Start polling on the global runtime
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for OpenHome renderer");
+            return;
+        }
+
+        info!("Starting polling for OpenHome renderer {} with interval {:?}",
+              self.state.client.location(), self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        let state = self.state.clone();
+        let poll_interval = self.poll_interval;
+        let base = self.base.clone();
+
+        let task = PollTask::spawn_blocking("OpenHome", poll_interval, None, move || {
+            Self::update_state_static(&state, &base);
+        });
+
+        *self.poll_task.write() = Some(task);
+    }
+
+    /// Stop polling, waits for a poll in progress
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for OpenHome renderer");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
+    }
+
+    /// Insert tracks into the playlist after the given id, returns the new ids
+    fn insert_tracks(&self, after_id: u32, uris: &[String], metadata: &[Option<QueueTrackMetadata>]) -> Result<Vec<u32>, String> {
+        let mut after_id = after_id;
+        let mut new_ids = Vec::with_capacity(uris.len());
+        for (i, uri) in uris.iter().enumerate() {
+            let item = metadata.get(i).and_then(|m| m.as_ref()).map(DidlItem::from_queue_metadata).unwrap_or_default();
+            let result = self.state.action("Playlist", "Insert", &[
+                ("AfterId", &after_id.to_string()),
+                ("Uri", uri),
+                ("Metadata", &build_didl(uri, &item)),
+            ])?;
+            after_id = result.get("NewId")
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| "Insert didn't return a new id".to_string())?;
+            new_ids.push(after_id);
+        }
+        Ok(new_ids)
+    }
+
+    fn queue_command(&self, command: &PlayerCommand) -> Result<(), String> {
+        // Make sure the ids match the renderer's current playlist
+        self.state.refresh_playlist()?;
+        let ids = self.state.playlist_ids();
+
+        match command {
+            PlayerCommand::QueueTracks { uris, insert_at_beginning, insert_after_current, metadata } => {
+                let after_id = if *insert_after_current {
+                    self.state.current_id.read().unwrap_or(0)
+                } else if *insert_at_beginning {
+                    0
+                } else {
+                    ids.last().copied().unwrap_or(0)
+                };
+                self.insert_tracks(after_id, uris, metadata).map(|_| ())
+            }
+            PlayerCommand::PlayNow { uris, metadata, start_index } => {
+                self.state.action("Playlist", "DeleteAll", &[])?;
+                let new_ids = self.insert_tracks(0, uris, metadata)?;
+                let id = new_ids.get(*start_index).or(new_ids.first())
+                    .ok_or_else(|| "No tracks to play".to_string())?;
+                self.state.action("Playlist", "SeekId", &[("Value", &id.to_string())]).map(|_| ())
+            }
+            PlayerCommand::RemoveTrack(index) => {
+                let id = ids.get(*index).ok_or_else(|| format!("Invalid queue index {}", index))?;
+                self.state.action("Playlist", "DeleteId", &[("Value", &id.to_string())]).map(|_| ())
+            }
+            PlayerCommand::ClearQueue => self.state.action("Playlist", "DeleteAll", &[]).map(|_| ()),
+            PlayerCommand::PlayQueueIndex(index) => {
+                let id = ids.get(*index).ok_or_else(|| format!("Invalid queue index {}", index))?;
+                self.state.action("Playlist", "SeekId", &[("Value", &id.to_string())]).map(|_| ())
+            }
+            _ => Err(format!("Not a queue command: {}", command)),
+        }
+    }
+}
+
+impl PlayerController for OpenHomeController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, serde_json::Value>> {
+        let device = self.state.device.read().clone()?;
+        let mut metadata = HashMap::new();
+        if let Some(name) = device.friendly_name {
+            metadata.insert("friendly_name".to_string(), serde_json::Value::String(name));
+        }
+        let mut services: Vec<String> = device.services.keys().cloned().collect();
+        services.sort();
+        metadata.insert("services".to_string(), serde_json::json!(services));
+        if let Some(volume) = self.state.current_state.read().volume {
+            metadata.insert("volume".to_string(), serde_json::json!(volume));
+        }
+        Some(metadata)
+    }
+
+    fn get_stream_details(&self) -> Option<StreamDetails> {
+        self.state.stream_details.read().clone()
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.state.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.state.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        self.state.playlist.read().entries.iter().map(Self::track_from_entry).collect()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.state.current_state.read().shuffle
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        self.state.current_state.read().loop_mode
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.state.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to OpenHome renderer: {}", command);
+
+        let result = match &command {
+            PlayerCommand::Play => self.state.action("Playlist", "Play", &[]).map(|_| ()),
+            PlayerCommand::Pause => self.state.action("Playlist", "Pause", &[]).map(|_| ()),
+            PlayerCommand::PlayPause => {
+                let action = if self.get_playback_state() == PlaybackState::Playing { "Pause" } else { "Play" };
+                self.state.action("Playlist", action, &[]).map(|_| ())
+            }
+            PlayerCommand::Stop => self.state.action("Playlist", "Stop", &[]).map(|_| ()),
+            PlayerCommand::Next => self.state.action("Playlist", "Next", &[]).map(|_| ()),
+            PlayerCommand::Previous => self.state.action("Playlist", "Previous", &[]).map(|_| ()),
+            PlayerCommand::Seek(position) => {
+                let seconds = (position.max(0.0) as u32).to_string();
+                self.state.action("Playlist", "SeekSecondAbsolute", &[("Value", &seconds)]).map(|_| ())
+            }
+            PlayerCommand::SetRandom(enabled) => {
+                self.state.action("Playlist", "SetShuffle", &[("Value", &enabled.to_string())]).map(|_| ())
+            }
+            PlayerCommand::SetLoopMode(mode) => {
+                // OpenHome only knows repeating the whole playlist
+                let repeat = *mode != LoopMode::None;
+                self.state.action("Playlist", "SetRepeat", &[("Value", &repeat.to_string())]).map(|_| ())
+            }
+            PlayerCommand::QueueTracks { .. }
+            | PlayerCommand::PlayNow { .. }
+            | PlayerCommand::RemoveTrack(_)
+            | PlayerCommand::ClearQueue
+            | PlayerCommand::PlayQueueIndex(_) => self.queue_command(&command),
+            _ => {
+                warn!("Command not supported by OpenHome renderer: {}", command);
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to OpenHome renderer: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting OpenHome controller for {}", self.state.client.location());
+        // The renderer may be switched off, polling picks it up when it becomes reachable
+        if let Err(e) = self.state.device() {
+            warn!("OpenHome renderer is not reachable yet: {}", e);
+        }
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping OpenHome controller");
+        self.stop_polling();
+        true
+    }
+}
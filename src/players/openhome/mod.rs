@@ -0,0 +1,5 @@
+pub mod client;
+pub mod controller;
+
+pub use client::{OpenHomeClient, OpenHomeDevice};
+pub use controller::OpenHomeController;
@@ -32,6 +32,15 @@ pub trait PlayerController: Send + Sync {
     /// Returns a vector of songs in the queue (can be empty if no songs are queued)
     /// If the player does not support queues, this will return an empty vector
     fn get_queue(&self) -> Vec<Track>;
+
+    /// Get upcoming tracks announced by the sending device
+    ///
+    /// Only relevant for players with the `RemoteQueue` capability. The list is a hint and
+    /// doesn't need to contain the whole remote queue. Returns an empty vector if the
+    /// protocol doesn't announce upcoming tracks.
+    fn get_upcoming_tracks(&self) -> Vec<Track> {
+        Vec::new()
+    }
     
     /// Get the current loop mode setting
     /// 
@@ -399,9 +408,19 @@ impl BasePlayerController {
         let player_id = self.get_player_id();
         
         // Create a cloned version of the song to pass to listeners
-        let song_copy = song.cloned();
+        let mut song_copy = song.cloned();
         
         let source = PlayerSource::new(player_name, player_id);
+
+        if crate::helpers::enrichment::settings_for(&source).genre_cleanup {
+            if let Some(song) = song_copy.as_mut().filter(|s| !s.genres.is_empty()) {
+                song.genres = crate::helpers::genre_cleanup::clean_genres_global(std::mem::take(&mut song.genres));
+            }
+        }
+
+        if let Some(song) = song_copy.as_mut() {
+            crate::helpers::songdedup::mark_duplicate(&source, song);
+        }
         
         let event = PlayerEvent::SongChanged {
             source,
@@ -519,6 +538,32 @@ impl BasePlayerController {
         
     }    
     
+    /// Notify listeners about the progress of loading the library
+    pub fn notify_library_load_progress(&self, artists_loaded: usize, artists_total: usize,
+                                        albums_processed: usize, albums_total: usize) {
+        let event = PlayerEvent::LibraryLoadProgress {
+            source: self.create_player_source(),
+            artists_loaded,
+            artists_total,
+            albums_processed,
+            albums_total,
+        };
+
+        debug!("Publishing library load progress event to the global event bus");
+        crate::audiocontrol::eventbus::EventBus::instance().publish(event);
+    }
+
+    /// Notify listeners about the albums added, removed and changed by a library refresh
+    pub fn notify_library_changed(&self, diff: crate::data::LibraryDiff) {
+        let event = PlayerEvent::LibraryChanged {
+            source: self.create_player_source(),
+            diff,
+        };
+
+        debug!("Publishing library changed event to the global event bus");
+        crate::audiocontrol::eventbus::EventBus::instance().publish(event);
+    }
+
     /// Notify listeners that the player's queue has changed
     pub fn notify_queue_changed(&self) {
         let event = PlayerEvent::QueueChanged {
@@ -1,4 +1,7 @@
-use crate::players::{MPDPlayerController, NullPlayerController, PlayerController, raat::RAATPlayerController, librespot::LibrespotPlayerController, lms::lmsaudio::LMSAudioController, generic::GenericPlayerController, ShairportController, BluetoothPlayerController};
+use crate::players::{MPDPlayerController, NullPlayerController, PlayerController, raat::RAATPlayerController, librespot::LibrespotPlayerController, lms::lmsaudio::LMSAudioController, generic::GenericPlayerController, ShairportController, BluetoothPlayerController, HQPlayerController, OpenHomeController, ChromecastController, UpnpController, KodiController, SonosController};
+
+use crate::helpers::enrichment::{set_player_settings, EnrichmentSettings};
+use crate::players::mpd::connection::MpdAddress;
 
 // MPRIS support is only available on Unix-like systems
 #[cfg(not(windows))]
@@ -53,7 +56,11 @@ pub fn create_player_from_json(config: &Value) -> Result<Box<dyn PlayerControlle
             ));
         }
         
-        match player_type.as_str() {
+        // Song-level metadata enrichment, e.g. no lookups for a TV on the Bluetooth input
+        let enrichment = EnrichmentSettings::from_player_config(config_obj)
+            .map_err(PlayerCreationError::ParseError)?;
+
+        let player: Result<Box<dyn PlayerController>, PlayerCreationError> = match player_type.as_str() {
             "mpd" => {
                 // Create MPDPlayer with config
                 let host = config_obj.get("host")
@@ -63,6 +70,19 @@ pub fn create_player_from_json(config: &Value) -> Result<Box<dyn PlayerControlle
                 let port = config_obj.get("port")
                     .and_then(|v| v.as_u64())
                     .unwrap_or(6600) as u16;
+
+                // Unix socket path, used instead of host and port, e.g. "/run/mpd/socket"
+                let socket = config_obj.get("socket")
+                    .and_then(|v| v.as_str());
+
+                // Password for MPD servers that require authentication
+                let password = config_obj.get("password")
+                    .and_then(|v| v.as_str());
+
+                // Network interface to use for the connections on multi-homed devices
+                if let Some(interface) = config_obj.get("bind_interface").and_then(|v| v.as_str()) {
+                    crate::helpers::netaddr::set_bind_interface(host, interface);
+                }
                 
                 // Check if load_mpd_library parameter is specified in the JSON
                 let load_library = config_obj.get("load_mpd_library")
@@ -104,13 +124,44 @@ pub fn create_player_from_json(config: &Value) -> Result<Box<dyn PlayerControlle
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false); // Default: deletion supported
 
-                let mut player = MPDPlayerController::with_connection(host, port);
+                // Check if watch_music_directory is specified in the JSON
+                let watch_music_directory = config_obj.get("watch_music_directory")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false); // Default: no file system watching
+
+                // Check if the library load parallelism is specified in the JSON
+                let library_load_workers = config_obj.get("library_load_workers")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as usize);
+                let library_batch_size = config_obj.get("library_batch_size")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as usize);
+
+                // Check if the number of pooled command connections is specified in the JSON
+                let connection_pool_size = config_obj.get("connection_pool_size")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as usize);
+
+                let address = MpdAddress::tcp(host, port)
+                    .with_socket(socket)
+                    .with_password(password);
+                let mut player = MPDPlayerController::with_address(address);
                 player.set_load_mpd_library(load_library);
                 player.set_enhance_metadata(enhance_metadata);
                 player.set_extract_coverart(extract_coverart);
                 player.set_max_reconnect_attempts(max_reconnect_attempts);
                 player.set_music_directory(music_directory);
                 player.set_library_read_only(library_read_only);
+                player.set_watch_music_directory(watch_music_directory);
+                if let Some(workers) = library_load_workers {
+                    player.set_library_load_workers(workers);
+                }
+                if let Some(batch_size) = library_batch_size {
+                    player.set_library_batch_size(batch_size);
+                }
+                if let Some(pool_size) = connection_pool_size {
+                    player.set_connection_pool_size(pool_size);
+                }
                 
                 // Set custom artist separators if provided
                 if let Some(separators) = artist_separators {
@@ -198,6 +249,41 @@ pub fn create_player_from_json(config: &Value) -> Result<Box<dyn PlayerControlle
                 let player = BluetoothPlayerController::new_with_address(device_address);
                 Ok(Box::new(player))
             },
+            "hqplayer" => {
+                // Create HQPlayerController with config
+                let player = HQPlayerController::from_config(config_obj);
+                Ok(Box::new(player))
+            },
+            "openhome" => {
+                // Create OpenHomeController from config
+                let player = OpenHomeController::from_config(config_obj)
+                    .map_err(PlayerCreationError::ParseError)?;
+                Ok(Box::new(player))
+            },
+            "chromecast" => {
+                // Create ChromecastController from config
+                let player = ChromecastController::from_config(config_obj)
+                    .map_err(PlayerCreationError::ParseError)?;
+                Ok(Box::new(player))
+            },
+            "upnp" | "dlna" => {
+                // Create UpnpController from config
+                let player = UpnpController::from_config(config_obj)
+                    .map_err(PlayerCreationError::ParseError)?;
+                Ok(Box::new(player))
+            },
+            "kodi" => {
+                // Create KodiController from config
+                let player = KodiController::from_config(config_obj)
+                    .map_err(PlayerCreationError::ParseError)?;
+                Ok(Box::new(player))
+            },
+            "sonos" => {
+                // Create SonosController from config
+                let player = SonosController::from_config(config_obj)
+                    .map_err(PlayerCreationError::ParseError)?;
+                Ok(Box::new(player))
+            },
             #[cfg(not(windows))]
             "mpris" => {
                 // Create MprisPlayerController with config (Unix/Linux only)
@@ -221,7 +307,13 @@ pub fn create_player_from_json(config: &Value) -> Result<Box<dyn PlayerControlle
             unknown => {
                 Err(PlayerCreationError::InvalidType(unknown.to_string()))
             }
+        };
+        let player = player?;
+
+        if let Some(enrichment) = enrichment {
+            set_player_settings(&player.get_player_name(), &player.get_player_id(), enrichment);
         }
+        Ok(player)
     } else {
         Err(PlayerCreationError::ParseError(
             "Expected object with player type as key".to_string()
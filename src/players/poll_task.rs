@@ -0,0 +1,161 @@
+//! Polling of players on the global Tokio runtime
+
+use log::{debug, warn};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::Notify;
+use tokio::task::{JoinError, JoinHandle};
+
+/// Wait for a task of the global runtime
+fn wait<T>(handle: JoinHandle<T>) -> Result<T, JoinError> {
+    match tokio::runtime::Handle::try_current() {
+        // Let the runtime move its other tasks to another worker while this one waits
+        Ok(current) if current.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| futures::executor::block_on(handle))
+        }
+        _ => futures::executor::block_on(handle),
+    }
+}
+
+/// Wakes up a poll task before its interval has passed, e.g. when the player announced a change
+#[derive(Clone, Default)]
+pub struct PollTrigger(Arc<Notify>);
+
+impl PollTrigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Poll as soon as possible, several triggers before the next poll result in one poll
+    pub fn trigger(&self) {
+        self.0.notify_one();
+    }
+}
+
+/// Polls a player on the global Tokio runtime instead of a thread of its own
+pub struct PollTask {
+    name: String,
+    running: Arc<AtomicBool>,
+    shutdown: Arc<Notify>,
+    handle: JoinHandle<()>,
+}
+
+impl PollTask {
+    /// Call an async poll function right away and then every `interval`
+    pub fn spawn<F, Fut>(name: &str, interval: Duration, trigger: Option<PollTrigger>, poll: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let shutdown = Arc::new(Notify::new());
+        let trigger = trigger.unwrap_or_default();
+
+        let task_name = name.to_string();
+        let task_running = Arc::clone(&running);
+        let task_shutdown = Arc::clone(&shutdown);
+        let handle = crate::get_tokio_runtime().spawn(async move {
+            debug!("{} polling task started", task_name);
+            while task_running.load(Ordering::Relaxed) {
+                poll().await;
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = trigger.0.notified() => {}
+                    _ = task_shutdown.notified() => break,
+                }
+            }
+            debug!("{} polling task stopped", task_name);
+        });
+
+        PollTask {
+            name: name.to_string(),
+            running,
+            shutdown,
+            handle,
+        }
+    }
+
+    /// Call a blocking poll function, e.g. one using a synchronous HTTP client
+    ///
+    /// The function runs on the blocking thread pool of the runtime, which only
+    /// occupies a thread while a request is in progress.
+    pub fn spawn_blocking<F>(name: &str, interval: Duration, trigger: Option<PollTrigger>, poll: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let poll = Arc::new(poll);
+        let task_name = name.to_string();
+        Self::spawn(name, interval, trigger, move || {
+            let poll = Arc::clone(&poll);
+            let task_name = task_name.clone();
+            async move {
+                if let Err(e) = tokio::task::spawn_blocking(move || poll()).await {
+                    warn!("Polling {} failed: {}", task_name, e);
+                }
+            }
+        })
+    }
+
+    /// Stop polling and wait until a poll in progress has finished
+    pub fn stop(self) {
+        self.running.store(false, Ordering::Relaxed);
+        self.shutdown.notify_one();
+        if let Err(e) = wait(self.handle) {
+            warn!("Error stopping {} polling task: {}", self.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_stop() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&polls);
+        let task = PollTask::spawn("test", Duration::from_millis(20), None, move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+        task.stop();
+        let count = polls.load(Ordering::SeqCst);
+        assert!(count >= 2);
+
+        // No polls after stopping
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(polls.load(Ordering::SeqCst), count);
+    }
+
+    #[test]
+    fn test_poll_trigger() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let trigger = PollTrigger::new();
+        let counter = Arc::clone(&polls);
+        let task = PollTask::spawn_blocking("test", Duration::from_secs(3600), Some(trigger.clone()), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(polls.load(Ordering::SeqCst), 1);
+        trigger.trigger();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+        task.stop();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stop_inside_runtime() {
+        let task = PollTask::spawn_blocking("test", Duration::from_millis(10), None, || {});
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        task.stop();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::players::poll_task::PollTask;
 use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track, PlayerUpdate}; // Added PlayerUpdate
 use crate::players::raat::metadata_pipe_reader::MetadataPipeReader;
 use crate::data::stream_details::StreamDetails;
@@ -61,7 +62,7 @@ impl Clone for RAATPlayerController {
 /// Structure to store player state for each instance
 struct PlayerInstanceData {
     running_flag: Arc<AtomicBool>,
-    timeout_thread_flag: Arc<AtomicBool>,
+    timeout_task: PollTask,
 }
 
 /// A map to store running state for each player instance
@@ -122,6 +123,7 @@ impl RAATPlayerController {
             PlayerCapability::Pause,
             PlayerCapability::Stop,
             PlayerCapability::ReceivesUpdates, // Added ReceivesUpdates capability
+            PlayerCapability::RemoteQueue, // The queue is managed by Roon
         ], false); // Don't notify on initialization
     }
     
@@ -280,7 +282,9 @@ impl RAATPlayerController {
             current_state.metadata = player_state.metadata.clone();
         }
         
-        // Update stored capabilities
+        // Update stored capabilities, the queue is always managed by Roon
+        let mut capabilities = capabilities;
+        capabilities.add_capability(PlayerCapability::RemoteQueue);
         let capabilities_changed = self.base.set_capabilities_set(capabilities, false);
         if capabilities_changed {
             let current_caps = self.base.get_capabilities();
@@ -344,59 +348,48 @@ impl RAATPlayerController {
         self.write_to_control_pipe(&format!("seek {:.1}", position))
     }
 
-    /// Starts a background thread that monitors for timeouts when playing
+    /// Starts a poll task that monitors for timeouts when playing
     /// If no updates are received for 10 seconds while playing, state becomes Unknown
-    fn start_timeout_monitor(&self, timeout_flag: Arc<AtomicBool>, self_arc: Arc<Self>) {
-        debug!("Starting RAAT timeout monitor thread");
-        
-        thread::spawn(move || {
-            debug!("RAAT timeout monitor thread started");
-            
-            while timeout_flag.load(Ordering::SeqCst) {
-                // Check timeout every second
-                thread::sleep(Duration::from_secs(1));
-                
-                if !timeout_flag.load(Ordering::SeqCst) {
-                    break;
-                }
-                
-                // Check if we're currently playing
-                let is_playing = {
-                    if let Some(state) = self_arc.current_state.try_read() {
-                        state.state == PlaybackState::Playing
-                    } else {
-                        false
-                    }
-                };
+    fn start_timeout_monitor(&self, self_arc: Arc<Self>) -> PollTask {
+        debug!("Starting RAAT timeout monitor");
+        PollTask::spawn("RAAT timeout monitor", Duration::from_secs(1), None, move || {
+            let player = Arc::clone(&self_arc);
+            async move { player.check_timeout() }
+        })
+    }
 
-                if is_playing {
-                    // Check if we've exceeded the timeout
-                    let last_update = {
-                        if let Some(time) = self_arc.last_update_time.try_read() {
-                            *time
-                        } else {
-                            continue; // Skip this check if we can't get the time
-                        }
-                    };
-
-                    let elapsed = last_update.elapsed();
-                    if elapsed > Duration::from_secs(10) {
-                        warn!("RAAT player timeout: no updates for {} seconds while playing, setting state to Unknown", elapsed.as_secs());
-
-                        // Update state to Unknown
-                        let mut state = self_arc.current_state.write();
-                        if state.state == PlaybackState::Playing {
-                            state.state = PlaybackState::Unknown;
-                            // Release lock before notifying
-                            drop(state);
-                            self_arc.base.notify_state_changed(PlaybackState::Unknown);
-                        }
-                    }
-                }
+    /// Set the state to Unknown if no updates were received for a while during playback
+    fn check_timeout(&self) {
+        // Check if we're currently playing
+        let is_playing = {
+            if let Some(state) = self.current_state.try_read() {
+                state.state == PlaybackState::Playing
+            } else {
+                false
             }
-            
-            debug!("RAAT timeout monitor thread shutting down");
-        });
+        };
+        if !is_playing {
+            return;
+        }
+
+        // Check if we've exceeded the timeout, skip this check if we can't get the time
+        let Some(last_update) = self.last_update_time.try_read().map(|time| *time) else {
+            return;
+        };
+
+        let elapsed = last_update.elapsed();
+        if elapsed > Duration::from_secs(10) {
+            warn!("RAAT player timeout: no updates for {} seconds while playing, setting state to Unknown", elapsed.as_secs());
+
+            // Update state to Unknown
+            let mut state = self.current_state.write();
+            if state.state == PlaybackState::Playing {
+                state.state = PlaybackState::Unknown;
+                // Release lock before notifying
+                drop(state);
+                self.base.notify_state_changed(PlaybackState::Unknown);
+            }
+        }
     }
 }
 
@@ -554,6 +547,10 @@ impl PlayerController for RAATPlayerController {
             PlayerCommand::RemoveTrack(_) => {
                 warn!("Remove track not supported by RAAT player");
                 return false;
+            },
+            PlayerCommand::MoveTrack { .. } => {
+                warn!("Move track not supported by RAAT player");
+                return false;
             },            PlayerCommand::ClearQueue => {
                 warn!("Clear queue not supported by RAAT player");
                 return false;
@@ -562,6 +559,15 @@ impl PlayerController for RAATPlayerController {
                 warn!("Play queue by index not supported by RAAT player");
                 return false;
             },
+            PlayerCommand::SetReplayGainMode(_) | PlayerCommand::SetCrossfade(_) | PlayerCommand::SetMixRampDb(_)
+            | PlayerCommand::SetConsume(_) | PlayerCommand::SetSingle(_) => {
+                warn!("Playback options not supported by RAAT player");
+                return false;
+            },
+            PlayerCommand::PlayNow { .. } => {
+                warn!("Play now not supported by RAAT player");
+                return false;
+            },
         };
         
         // Send the command to the control pipe
@@ -580,29 +586,28 @@ impl PlayerController for RAATPlayerController {
         
         // Create new running flags
         let running = Arc::new(AtomicBool::new(true));
-        let timeout_flag = Arc::new(AtomicBool::new(true));
         
         // Store the running flags in the player instance
         {
             let mut state = PLAYER_STATE.lock();
             let instance_id = self as *const _ as usize;
 
-            if let Some(data) = state.get(&instance_id) {
-                // Stop any existing threads
+            if let Some(data) = state.remove(&instance_id) {
+                // Stop any existing listener and monitor
                 data.running_flag.store(false, Ordering::SeqCst);
-                data.timeout_thread_flag.store(false, Ordering::SeqCst);
+                data.timeout_task.stop();
             }
 
             // Start the metadata listener thread
             self.start_metadata_listener(running.clone(), player_arc.clone());
 
-            // Start the timeout monitor thread
-            self.start_timeout_monitor(timeout_flag.clone(), player_arc.clone());
+            // Start the timeout monitor
+            let timeout_task = self.start_timeout_monitor(player_arc.clone());
 
             // Store the running flags
             state.insert(instance_id, PlayerInstanceData {
                 running_flag: running,
-                timeout_thread_flag: timeout_flag,
+                timeout_task,
             });
             true
         }
@@ -611,15 +616,15 @@ impl PlayerController for RAATPlayerController {
     fn stop(&self) -> bool {
         info!("Stopping RAAT player controller");
         
-        // Signal both threads to stop
+        // Signal the listener thread to stop and stop the timeout monitor
         {
             let mut state = PLAYER_STATE.lock();
             let instance_id = self as *const _ as usize;
 
             if let Some(data) = state.remove(&instance_id) {
                 data.running_flag.store(false, Ordering::SeqCst);
-                data.timeout_thread_flag.store(false, Ordering::SeqCst);
-                debug!("Signaled metadata listener and timeout monitor threads to stop");
+                data.timeout_task.stop();
+                debug!("Signaled metadata listener to stop and stopped the timeout monitor");
                 return true;
             }
         }
@@ -140,6 +140,7 @@ impl ShairportController {
         let mut capabilities = vec![
             PlayerCapability::Metadata,
             PlayerCapability::AlbumArt,
+            PlayerCapability::RemoteQueue, // The queue lives on the AirPlay sender
         ];
         
         // If systemd unit is configured, we can control playback
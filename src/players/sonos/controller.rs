@@ -0,0 +1,710 @@
+use crate::players::poll_task::PollTask;
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, QueueTrackMetadata, Track};
+use crate::players::openhome::client::{build_didl, parse_duration, DidlItem};
+use crate::players::sonos::metadata::{item_to_track, parse_items, song_from_track};
+use crate::players::sonos::topology::{self, ZoneGroup};
+use crate::players::upnp::client::{format_time, UpnpClient, UpnpDevice};
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::RwLock;
+use std::time::{Duration, Instant};
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// How long to wait for SSDP answers
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Interval for re-reading the zone groups, rooms may be grouped or ungrouped in the Sonos app
+const TOPOLOGY_REFRESH: Duration = Duration::from_secs(30);
+
+/// Number of queue entries read with one Browse request, Sonos returns at most 100
+const BROWSE_COUNT: usize = 100;
+
+/// A UPnP device of the household with its client
+#[derive(Clone)]
+struct SonosDevice {
+    client: UpnpClient,
+    device: UpnpDevice,
+}
+
+impl SonosDevice {
+    fn connect(location: &str) -> Result<Self, String> {
+        let client = UpnpClient::new(location);
+        let device = client.discover()?;
+        Ok(Self { client, device })
+    }
+
+    fn location(&self) -> &str {
+        self.client.location()
+    }
+}
+
+/// The speaker of the room and the coordinator of its group
+#[derive(Clone)]
+struct Zone {
+    room: String,
+    uuid: String,
+    speaker: SonosDevice,
+    coordinator_uuid: String,
+    /// Receives transport and queue commands, the speaker itself if it isn't grouped
+    coordinator: SonosDevice,
+    group: ZoneGroup,
+    resolved_at: Instant,
+}
+
+/// Shared state of the controller, updated by the poll task
+#[derive(Clone)]
+struct SonosState {
+    /// Configured room, the first group coordinator is used if not set
+    room: Option<String>,
+    /// Speaker to read the zone groups from, found with SSDP if not set
+    host: Option<String>,
+    zone: Arc<RwLock<Option<Zone>>>,
+    current_song: Arc<RwLock<Option<Song>>>,
+    current_state: Arc<RwLock<PlayerState>>,
+    queue: Arc<RwLock<Vec<DidlItem>>>,
+    /// UpdateID of the queue when it was read
+    queue_update_id: Arc<RwLock<Option<String>>>,
+}
+
+/// Sonos controller
+///
+/// Controls a Sonos room through the UPnP services of the speakers. The zone groups are
+/// read from the ZoneGroupTopology service, transport and queue commands go to the
+/// coordinator of the room's group, volume is set on the room's speaker.
+pub struct SonosController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// State shared with the poll task
+    state: SonosState,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Whether polling is started
+    should_poll: Arc<AtomicBool>,
+
+    /// Task polling the player on the global runtime
+    poll_task: Arc<RwLock<Option<PollTask>>>,
+}
+
+// Manually implement Clone for SonosController
+impl Clone for SonosController {
+    fn clone(&self) -> Self {
+        SonosController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            state: self.state.clone(),
+            poll_interval: self.poll_interval,
+            should_poll: Arc::clone(&self.should_poll),
+            poll_task: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl SonosState {
+    /// The room's speaker and group coordinator, the zone groups are re-read when they are outdated
+    fn zone(&self) -> Result<Zone, String> {
+        if let Some(zone) = self.zone.read().clone().filter(|z| z.resolved_at.elapsed() < TOPOLOGY_REFRESH) {
+            return Ok(zone);
+        }
+        let previous = self.zone.read().clone();
+        let zone = self.resolve(previous.as_ref())?;
+        *self.zone.write() = Some(zone.clone());
+        Ok(zone)
+    }
+
+    fn resolve(&self, previous: Option<&Zone>) -> Result<Zone, String> {
+        let groups = match previous {
+            // Ask the known speaker first, SSDP is only needed if it went away
+            Some(zone) => topology::zone_groups(zone.speaker.location())
+                .map(|(_, groups)| groups)
+                .or_else(|_| topology::discover_zones(self.host.as_deref(), DISCOVERY_TIMEOUT))?,
+            None => topology::discover_zones(self.host.as_deref(), DISCOVERY_TIMEOUT)?,
+        };
+        let (group, member) = match &self.room {
+            Some(room) => topology::find_room(&groups, room).ok_or_else(|| format!("Sonos room '{}' not found", room))?,
+            None => groups
+                .iter()
+                .find_map(|g| g.coordinator().filter(|c| !c.invisible).map(|c| (g, c)))
+                .ok_or_else(|| "No Sonos rooms found".to_string())?,
+        };
+        let coordinator = group.coordinator().ok_or_else(|| format!("Coordinator of {} not found", member.zone_name))?;
+
+        // Reuse the devices if their locations didn't change
+        let reuse = |location: &str| {
+            previous.and_then(|zone| {
+                [&zone.speaker, &zone.coordinator].into_iter().find(|d| d.location() == location).cloned()
+            })
+        };
+        let speaker = match reuse(&member.location) {
+            Some(device) => device,
+            None => SonosDevice::connect(&member.location)?,
+        };
+        let coordinator_device = if coordinator.uuid == member.uuid {
+            speaker.clone()
+        } else {
+            match reuse(&coordinator.location) {
+                Some(device) => device,
+                None => SonosDevice::connect(&coordinator.location)?,
+            }
+        };
+
+        if previous.is_none_or(|zone| zone.coordinator_uuid != coordinator.uuid || zone.uuid != member.uuid) {
+            info!("Sonos room {} ({}) plays in group {:?}, coordinator {}",
+                  member.zone_name, member.uuid, group.rooms(), coordinator.zone_name);
+        }
+        Ok(Zone {
+            room: member.zone_name.clone(),
+            uuid: member.uuid.clone(),
+            speaker,
+            coordinator_uuid: coordinator.uuid.clone(),
+            coordinator: coordinator_device,
+            group: group.clone(),
+            resolved_at: Instant::now(),
+        })
+    }
+
+    /// Forget the zone, the speakers may have new addresses or the groups changed
+    fn reset(&self) {
+        *self.zone.write() = None;
+    }
+
+    fn with_zone<T>(&self, call: impl FnOnce(&Zone) -> Result<T, String>) -> Result<T, String> {
+        let zone = self.zone()?;
+        let result = call(&zone);
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    /// Invoke an AVTransport action on the group coordinator
+    fn transport(&self, action: &str, args: &[(&str, &str)]) -> Result<HashMap<String, String>, String> {
+        self.with_zone(|zone| zone.coordinator.client.action(&zone.coordinator.device, "AVTransport", action, args))
+    }
+
+    /// Invoke a RenderingControl action on the room's speaker
+    fn rendering(&self, action: &str, args: &[(&str, &str)]) -> Result<HashMap<String, String>, String> {
+        self.with_zone(|zone| zone.speaker.client.action(&zone.speaker.device, "RenderingControl", action, args))
+    }
+
+    /// Browse the queue of the coordinator, returns the items and the UpdateID
+    fn browse_queue(&self, start: usize, count: usize) -> Result<(Vec<DidlItem>, usize, Option<String>), String> {
+        let (start, count) = (start.to_string(), count.to_string());
+        let mut result = self.with_zone(|zone| {
+            zone.coordinator.client.invoke(&zone.coordinator.device, "ContentDirectory", "Browse", &[
+                ("ObjectID", "Q:0"),
+                ("BrowseFlag", "BrowseDirectChildren"),
+                ("Filter", "dc:title,res,dc:creator,upnp:artist,upnp:album,upnp:albumArtURI"),
+                ("StartingIndex", &start),
+                ("RequestedCount", &count),
+                ("SortCriteria", ""),
+            ])
+        })?;
+        let items = parse_items(&result.remove("Result").unwrap_or_default());
+        let total = result.get("TotalMatches").and_then(|t| t.parse().ok()).unwrap_or(items.len());
+        Ok((items, total, result.remove("UpdateID")))
+    }
+
+    /// URI that makes the coordinator play its queue
+    fn queue_uri(&self) -> Result<String, String> {
+        Ok(format!("x-rincon-queue:{}#0", self.zone()?.coordinator_uuid))
+    }
+}
+
+/// Shuffle and loop mode of a Sonos play mode
+fn parse_play_mode(mode: &str) -> (bool, LoopMode) {
+    match mode {
+        "SHUFFLE_NOREPEAT" => (true, LoopMode::None),
+        "SHUFFLE" => (true, LoopMode::Playlist),
+        "SHUFFLE_REPEAT_ONE" => (true, LoopMode::Track),
+        "REPEAT_ALL" => (false, LoopMode::Playlist),
+        "REPEAT_ONE" => (false, LoopMode::Track),
+        _ => (false, LoopMode::None),
+    }
+}
+
+/// Sonos play mode for shuffle and loop mode, "SHUFFLE" repeats the queue on Sonos
+fn play_mode(shuffle: bool, loop_mode: LoopMode) -> &'static str {
+    match (shuffle, loop_mode) {
+        (true, LoopMode::None) => "SHUFFLE_NOREPEAT",
+        (true, LoopMode::Playlist) => "SHUFFLE",
+        (true, LoopMode::Track) => "SHUFFLE_REPEAT_ONE",
+        (false, LoopMode::Playlist) => "REPEAT_ALL",
+        (false, LoopMode::Track) => "REPEAT_ONE",
+        (false, LoopMode::None) => "NORMAL",
+    }
+}
+
+fn didl_for(uri: &str, metadata: Option<&QueueTrackMetadata>) -> String {
+    build_didl(uri, &metadata.map(DidlItem::from_queue_metadata).unwrap_or_default())
+}
+
+impl SonosController {
+    /// Create a controller for a Sonos room, `host` is any speaker of the household
+    pub fn new(room: Option<&str>, host: Option<&str>, name: Option<&str>, poll_interval: Duration) -> Self {
+        let player_id = format!("sonos:{}", room.unwrap_or("default"));
+        debug!("Creating new SonosController for {}", player_id);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info(name.unwrap_or("sonos"), &player_id),
+            state: SonosState {
+                room: room.map(|r| r.to_string()),
+                host: host.map(|h| h.to_string()),
+                zone: Arc::new(RwLock::new(None)),
+                current_song: Arc::new(RwLock::new(None)),
+                current_state: Arc::new(RwLock::new(PlayerState::new())),
+                queue: Arc::new(RwLock::new(Vec::new())),
+                queue_update_id: Arc::new(RwLock::new(None)),
+            },
+            poll_interval,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            poll_task: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    ///
+    /// `room` is the room name as shown in the Sonos app. `host` is the address of any
+    /// speaker, without it the speakers are found with SSDP.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        let room = config.get("room").and_then(|v| v.as_str());
+        let host = config.get("host").and_then(|v| v.as_str());
+        let name = config.get("name").and_then(|v| v.as_str());
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        Ok(Self::new(room, host, name, poll_interval))
+    }
+
+    /// Set the default capabilities for Sonos
+    fn set_default_capabilities(&self) {
+        debug!("Setting default SonosController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::PlayPause,
+            PlayerCapability::Stop,
+            PlayerCapability::Previous,
+            PlayerCapability::Next,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Volume,
+            PlayerCapability::Mute,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Queue,
+            PlayerCapability::Metadata,
+            PlayerCapability::AlbumArt,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Set the volume of the room (0-100)
+    pub fn set_volume(&self, volume: u32) -> Result<(), String> {
+        self.state
+            .rendering("SetVolume", &[("Channel", "Master"), ("DesiredVolume", &volume.min(100).to_string())])
+            .map(|_| ())
+    }
+
+    /// Mute or unmute the room
+    pub fn set_mute(&self, mute: bool) -> Result<(), String> {
+        let value = if mute { "1" } else { "0" };
+        self.state
+            .rendering("SetMute", &[("Channel", "Master"), ("DesiredMute", value)])
+            .map(|_| ())
+    }
+
+    /// Update internal state from the speakers (static version for threading)
+    fn update_state_static(state: &SonosState, base: &BasePlayerController) {
+        let zone = match state.zone() {
+            Ok(zone) => zone,
+            Err(e) => {
+                debug!("Sonos room {} not reachable: {}", state.room.as_deref().unwrap_or("default"), e);
+                return;
+            }
+        };
+        let Ok(mut position_info) = state.transport("GetPositionInfo", &[]) else {
+            return;
+        };
+        let transport = state.transport("GetTransportInfo", &[]).ok()
+            .and_then(|mut info| info.remove("CurrentTransportState"));
+        let play_mode = state.transport("GetTransportSettings", &[]).ok()
+            .and_then(|mut settings| settings.remove("PlayMode"))
+            .map(|mode| parse_play_mode(&mode));
+
+        let playback_state = transport.map(|transport| match transport.as_str() {
+            "PLAYING" | "TRANSITIONING" => PlaybackState::Playing,
+            "PAUSED_PLAYBACK" => PlaybackState::Paused,
+            "STOPPED" | "NO_MEDIA_PRESENT" => PlaybackState::Stopped,
+            _ => PlaybackState::Unknown,
+        });
+        let position = position_info.get("RelTime").and_then(|time| parse_duration(time));
+        let volume = state.rendering("GetVolume", &[("Channel", "Master")]).ok()
+            .and_then(|mut v| v.remove("CurrentVolume"))
+            .and_then(|v| v.parse::<i32>().ok());
+        let muted = state.rendering("GetMute", &[("Channel", "Master")]).ok()
+            .and_then(|mut v| v.remove("CurrentMute"))
+            .map(|v| v == "1");
+
+        let (state_changed, shuffle_changed, loop_changed, position_changed) = {
+            let mut current = state.current_state.write();
+            let state_changed = playback_state.is_some_and(|s| s != current.state);
+            if let Some(playback_state) = playback_state {
+                current.state = playback_state;
+            }
+            let shuffle_changed = play_mode.is_some_and(|(shuffle, _)| shuffle != current.shuffle);
+            let loop_changed = play_mode.is_some_and(|(_, loop_mode)| loop_mode != current.loop_mode);
+            if let Some((shuffle, loop_mode)) = play_mode {
+                current.shuffle = shuffle;
+                current.loop_mode = loop_mode;
+            }
+            let position_changed = position.is_some() && current.position != position;
+            current.position = position;
+            current.volume = volume;
+            current.muted = muted.unwrap_or(current.muted);
+            (state_changed, shuffle_changed, loop_changed, position_changed)
+        };
+        let current = state.current_state.read().clone();
+        if state_changed {
+            base.notify_state_changed(current.state);
+        }
+        if shuffle_changed {
+            base.notify_random_changed(current.shuffle);
+        }
+        if loop_changed {
+            base.notify_loop_mode_changed(current.loop_mode);
+        }
+        if position_changed {
+            if let Some(position) = current.position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        let uri = position_info.remove("TrackURI").filter(|u| !u.is_empty());
+        let track_metadata = position_info.remove("TrackMetaData").filter(|m| m.as_str() != "NOT_IMPLEMENTED").unwrap_or_default();
+        // The station name of radio streams is only in the media info
+        let media_metadata = if uri.as_deref().and_then(crate::players::sonos::metadata::source_of) == Some("radio") {
+            state.transport("GetMediaInfo", &[]).ok().and_then(|mut info| info.remove("CurrentURIMetaData"))
+        } else {
+            None
+        };
+        let song = song_from_track(&track_metadata, uri.as_deref(), media_metadata.as_deref(), zone.coordinator.location())
+            .map(|mut song| {
+                if song.duration.is_none() && song.source.as_deref() != Some("radio") {
+                    song.duration = position_info.get("TrackDuration").and_then(|d| parse_duration(d)).filter(|d| *d > 0.0);
+                }
+                song
+            });
+
+        let song_changed = {
+            let mut current_song = state.current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.stream_url != new.stream_url,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("Sonos song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+
+        Self::update_queue(state, base);
+        base.alive();
+    }
+
+    /// Read the queue if its UpdateID changed
+    fn update_queue(state: &SonosState, base: &BasePlayerController) {
+        let update_id = match state.browse_queue(0, 1) {
+            Ok((_, _, update_id)) => update_id,
+            Err(e) => {
+                debug!("Failed to read Sonos queue: {}", e);
+                return;
+            }
+        };
+        if update_id.is_some() && *state.queue_update_id.read() == update_id {
+            return;
+        }
+
+        let mut items = Vec::new();
+        loop {
+            match state.browse_queue(items.len(), BROWSE_COUNT) {
+                Ok((page, total, _)) => {
+                    let done = page.is_empty() || items.len() + page.len() >= total;
+                    items.extend(page);
+                    if done {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    debug!("Failed to read Sonos queue: {}", e);
+                    return;
+                }
+            }
+        }
+        let changed = *state.queue.read() != items;
+        *state.queue.write() = items;
+        *state.queue_update_id.write() = update_id;
+        if changed {
+            base.notify_queue_changed();
+        }
+    }
+
+    /// Update internal state from the speakers
+    fn update_state(&self) {
+        Self::update_state_static(&self.state, &self.base);
+    }
+
+    /// Start polling on the global runtime
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for Sonos");
+            return;
+        }
+
+        info!("Starting polling for Sonos with interval {:?}", self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        let state = self.state.clone();
+        let poll_interval = self.poll_interval;
+        let base = self.base.clone();
+
+        let task = PollTask::spawn_blocking("Sonos", poll_interval, None, move || {
+            Self::update_state_static(&state, &base);
+        });
+
+        *self.poll_task.write() = Some(task);
+    }
+
+    /// Stop polling, waits for a poll in progress
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for Sonos");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let task = self.poll_task.write().take();
+        if let Some(task) = task {
+            task.stop();
+        }
+    }
+
+    /// Add tracks to the queue, `position` is 1-based, 0 appends
+    fn add_to_queue(&self, uris: &[String], metadata: &[Option<QueueTrackMetadata>], position: usize) -> Result<(), String> {
+        for (i, uri) in uris.iter().enumerate() {
+            let desired = if position == 0 { 0 } else { position + i };
+            self.state.transport("AddURIToQueue", &[
+                ("EnqueuedURI", uri),
+                ("EnqueuedURIMetaData", &didl_for(uri, metadata.get(i).and_then(|m| m.as_ref()))),
+                ("DesiredFirstTrackNumberEnqueued", &desired.to_string()),
+                ("EnqueueAsNext", "0"),
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Switch the coordinator to its queue and play the track at `index`
+    fn play_queue_index(&self, index: usize) -> Result<(), String> {
+        self.state.transport("SetAVTransportURI", &[("CurrentURI", &self.state.queue_uri()?), ("CurrentURIMetaData", "")])?;
+        self.state.transport("Seek", &[("Unit", "TRACK_NR"), ("Target", &(index + 1).to_string())])?;
+        self.state.transport("Play", &[("Speed", "1")]).map(|_| ())
+    }
+
+    /// 1-based number of the current track in the queue
+    fn current_track_number(&self) -> Option<usize> {
+        self.state.transport("GetPositionInfo", &[]).ok()
+            .and_then(|mut info| info.remove("Track"))
+            .and_then(|track| track.parse().ok())
+            .filter(|track| *track > 0)
+    }
+}
+
+impl PlayerController for SonosController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, serde_json::Value>> {
+        let zone = self.state.zone.read().clone()?;
+        let mut metadata = HashMap::new();
+        metadata.insert("room".to_string(), serde_json::json!(zone.room));
+        metadata.insert("uuid".to_string(), serde_json::json!(zone.uuid));
+        metadata.insert("location".to_string(), serde_json::json!(zone.speaker.location()));
+        if let Some(model) = &zone.speaker.device.model {
+            metadata.insert("model".to_string(), serde_json::json!(model));
+        }
+        if let Some(coordinator) = zone.group.coordinator() {
+            metadata.insert("coordinator".to_string(), serde_json::json!(coordinator.zone_name));
+        }
+        metadata.insert("group".to_string(), serde_json::json!(zone.group.rooms()));
+        let current = self.state.current_state.read();
+        if let Some(volume) = current.volume {
+            metadata.insert("volume".to_string(), serde_json::json!(volume));
+            metadata.insert("muted".to_string(), serde_json::json!(current.muted));
+        }
+        Some(metadata)
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.state.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.state.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        self.state.queue.read().iter().map(item_to_track).collect()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.state.current_state.read().shuffle
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        self.state.current_state.read().loop_mode
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.state.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to Sonos: {}", command);
+
+        let result = match &command {
+            PlayerCommand::Play => self.state.transport("Play", &[("Speed", "1")]).map(|_| ()),
+            PlayerCommand::Pause => self.state.transport("Pause", &[]).map(|_| ()),
+            PlayerCommand::PlayPause => {
+                if self.get_playback_state() == PlaybackState::Playing {
+                    self.state.transport("Pause", &[]).map(|_| ())
+                } else {
+                    self.state.transport("Play", &[("Speed", "1")]).map(|_| ())
+                }
+            }
+            PlayerCommand::Stop => self.state.transport("Stop", &[]).map(|_| ()),
+            PlayerCommand::Next => self.state.transport("Next", &[]).map(|_| ()),
+            PlayerCommand::Previous => self.state.transport("Previous", &[]).map(|_| ()),
+            PlayerCommand::Seek(position) => self.state
+                .transport("Seek", &[("Unit", "REL_TIME"), ("Target", &format_time(*position))])
+                .map(|_| ()),
+            PlayerCommand::SetRandom(enabled) => {
+                let mode = play_mode(*enabled, self.get_loop_mode());
+                self.state.transport("SetPlayMode", &[("NewPlayMode", mode)]).map(|_| ())
+            }
+            PlayerCommand::SetLoopMode(loop_mode) => {
+                let mode = play_mode(self.get_shuffle(), *loop_mode);
+                self.state.transport("SetPlayMode", &[("NewPlayMode", mode)]).map(|_| ())
+            }
+            PlayerCommand::QueueTracks { uris, insert_at_beginning, insert_after_current, metadata } => {
+                let position = if *insert_after_current {
+                    self.current_track_number().map(|track| track + 1).unwrap_or(0)
+                } else if *insert_at_beginning {
+                    1
+                } else {
+                    0
+                };
+                self.add_to_queue(uris, metadata, position)
+            }
+            PlayerCommand::RemoveTrack(index) => self.state
+                .transport("RemoveTrackFromQueue", &[("ObjectID", &format!("Q:0/{}", index + 1)), ("UpdateID", "0")])
+                .map(|_| ()),
+            PlayerCommand::MoveTrack { from, to } => {
+                // InsertBefore is the 1-based position before the move
+                let insert_before = if to > from { to + 2 } else { to + 1 };
+                self.state
+                    .transport("ReorderTracksInQueue", &[
+                        ("StartingIndex", &(from + 1).to_string()),
+                        ("NumberOfTracks", "1"),
+                        ("InsertBefore", &insert_before.to_string()),
+                        ("UpdateID", "0"),
+                    ])
+                    .map(|_| ())
+            }
+            PlayerCommand::ClearQueue => self.state.transport("RemoveAllTracksFromQueue", &[]).map(|_| ()),
+            PlayerCommand::PlayQueueIndex(index) => self.play_queue_index(*index),
+            PlayerCommand::PlayNow { uris, metadata, start_index } => self.state
+                .transport("RemoveAllTracksFromQueue", &[])
+                .and_then(|_| self.add_to_queue(uris, metadata, 0))
+                .and_then(|_| self.play_queue_index(if *start_index < uris.len() { *start_index } else { 0 })),
+            _ => {
+                warn!("Command not supported by Sonos: {}", command);
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to Sonos: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting Sonos controller for {}", self.base.get_player_id());
+        // The speakers may be offline, polling picks them up when they become reachable
+        if let Err(e) = self.state.zone() {
+            warn!("Sonos room is not reachable yet: {}", e);
+        }
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping Sonos controller");
+        self.stop_polling();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_mode() {
+        for shuffle in [false, true] {
+            for loop_mode in [LoopMode::None, LoopMode::Track, LoopMode::Playlist] {
+                assert_eq!(parse_play_mode(play_mode(shuffle, loop_mode)), (shuffle, loop_mode));
+            }
+        }
+        // Sonos shuffles with repeat for "SHUFFLE"
+        assert_eq!(parse_play_mode("SHUFFLE"), (true, LoopMode::Playlist));
+    }
+}
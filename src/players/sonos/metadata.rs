@@ -0,0 +1,125 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::data::{Song, Track};
+use crate::players::openhome::client::{element_text, parse_didl, resolve_url, DidlItem};
+
+static ITEM_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<item\b.*?</item>").unwrap());
+
+/// Kind of source a track URI belongs to, as used in `Song::source`
+pub fn source_of(uri: &str) -> Option<&'static str> {
+    let scheme = uri.split(':').next().unwrap_or_default();
+    match scheme {
+        "x-rincon-stream" => Some("linein"),
+        "x-sonos-htastream" => Some("tv"),
+        "x-sonos-spotify" => Some("spotify"),
+        "x-sonosapi-stream" | "x-sonosapi-radio" | "x-sonosapi-hls" | "x-rincon-mp3radio" | "aac" | "hls-radio" => Some("radio"),
+        "x-file-cifs" | "x-smb" => Some("library"),
+        _ if uri.contains("spotify") => Some("spotify"),
+        _ => None,
+    }
+}
+
+/// Map the metadata of the current track to a song
+///
+/// `track_metadata` is the DIDL-Lite of GetPositionInfo, `media_metadata` the one of
+/// GetMediaInfo, which holds the station name for radio. Relative album art URLs are
+/// resolved against `location`, the device description URL.
+pub fn song_from_track(track_metadata: &str, uri: Option<&str>, media_metadata: Option<&str>, location: &str) -> Option<Song> {
+    let source = uri.and_then(source_of);
+    let item = parse_didl(track_metadata);
+    let mut song = item.to_song(uri);
+    song.source = source.map(|s| s.to_string());
+    song.cover_art_url = item.album_art_uri.as_deref().map(|art| resolve_url(location, art));
+
+    match source {
+        Some("linein") => song.title = Some("Line-In".to_string()),
+        Some("tv") => song.title = Some("TV".to_string()),
+        Some("radio") => {
+            let station = media_metadata
+                .and_then(|m| element_text(m, "dc:title"))
+                .or_else(|| item.title.clone());
+            // Radio stations send "Artist - Title" as stream content
+            match element_text(track_metadata, "r:streamContent").filter(|c| !c.starts_with("ZPSTR_")) {
+                Some(content) => {
+                    let (artist, title) = match content.split_once(" - ") {
+                        Some((artist, title)) => (Some(artist.trim().to_string()), title.trim().to_string()),
+                        None => (None, content),
+                    };
+                    song.artist = artist;
+                    song.title = Some(title);
+                    song.album = station;
+                }
+                None => {
+                    song.title = station;
+                    song.artist = None;
+                }
+            }
+            // Streams report their position but have no length
+            song.duration = None;
+        }
+        _ => {}
+    }
+
+    if song.title.is_none() && song.artist.is_none() && song.stream_url.is_none() {
+        return None;
+    }
+    Some(song)
+}
+
+/// Parse the items of a ContentDirectory Browse result, e.g. of the queue "Q:0"
+pub fn parse_items(didl: &str) -> Vec<DidlItem> {
+    ITEM_RE.find_iter(didl).map(|item| parse_didl(item.as_str())).collect()
+}
+
+pub fn item_to_track(item: &DidlItem) -> Track {
+    let mut track = Track::with_name(item.title.clone().unwrap_or_default());
+    track.artist = item.artist.clone();
+    track.uri = item.uri.clone();
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCATION: &str = "http://192.168.1.30:1400/xml/device_description.xml";
+
+    #[test]
+    fn test_song_from_track() {
+        let library = r#"<DIDL-Lite><item id="-1"><res duration="0:04:12">x-file-cifs://nas/music/01.flac</res>
+            <upnp:albumArtURI>/getaa?s=1&amp;u=x-file-cifs%3a%2f%2fnas%2fmusic%2f01.flac</upnp:albumArtURI>
+            <dc:title>Blue in Green</dc:title><dc:creator>Miles Davis</dc:creator><upnp:album>Kind of Blue</upnp:album></item></DIDL-Lite>"#;
+        let song = song_from_track(library, Some("x-file-cifs://nas/music/01.flac"), None, LOCATION).unwrap();
+        assert_eq!(song.title.as_deref(), Some("Blue in Green"));
+        assert_eq!(song.artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(song.duration, Some(252.0));
+        assert_eq!(song.source.as_deref(), Some("library"));
+        assert_eq!(
+            song.cover_art_url.as_deref(),
+            Some("http://192.168.1.30:1400/getaa?s=1&u=x-file-cifs%3a%2f%2fnas%2fmusic%2f01.flac")
+        );
+
+        let radio = r#"<DIDL-Lite><item id="-1"><res>x-rincon-mp3radio://stream.example.com/jazz</res>
+            <r:streamContent>John Coltrane - Naima</r:streamContent><dc:title>jazz</dc:title></item></DIDL-Lite>"#;
+        let station = r#"<DIDL-Lite><item id="R:0/0/0"><dc:title>Jazz Radio</dc:title></item></DIDL-Lite>"#;
+        let song = song_from_track(radio, Some("x-rincon-mp3radio://stream.example.com/jazz"), Some(station), LOCATION).unwrap();
+        assert_eq!(song.title.as_deref(), Some("Naima"));
+        assert_eq!(song.artist.as_deref(), Some("John Coltrane"));
+        assert_eq!(song.album.as_deref(), Some("Jazz Radio"));
+        assert_eq!(song.source.as_deref(), Some("radio"));
+
+        let line_in = song_from_track("", Some("x-rincon-stream:RINCON_A1400"), None, LOCATION).unwrap();
+        assert_eq!(line_in.title.as_deref(), Some("Line-In"));
+        assert!(song_from_track("", None, None, LOCATION).is_none());
+    }
+
+    #[test]
+    fn test_parse_items() {
+        let didl = r#"<DIDL-Lite><item id="Q:0/1" parentID="Q:0"><res>x-file-cifs://nas/a.flac</res><dc:title>A</dc:title><dc:creator>X</dc:creator></item>
+            <item id="Q:0/2" parentID="Q:0"><res>x-file-cifs://nas/b.flac</res><dc:title>B</dc:title></item></DIDL-Lite>"#;
+        let tracks: Vec<Track> = parse_items(didl).iter().map(item_to_track).collect();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].artist.as_deref(), Some("X"));
+        assert_eq!(tracks[1].uri.as_deref(), Some("x-file-cifs://nas/b.flac"));
+    }
+}
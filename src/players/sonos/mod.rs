@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod metadata;
+pub mod topology;
+
+pub use controller::SonosController;
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::players::upnp::client::{UpnpClient, UpnpDevice};
+use crate::players::upnp::discovery;
+
+/// Device type of Sonos speakers, searched for with SSDP
+pub const ZONE_PLAYER: &str = "urn:schemas-upnp-org:device:ZonePlayer:1";
+
+/// Port of the web server of Sonos speakers
+pub const SONOS_PORT: u16 = 1400;
+
+static GROUP_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<ZoneGroup\s([^>]*)>(.*?)</ZoneGroup>").unwrap());
+static MEMBER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<ZoneGroupMember\s([^>]*?)/?>").unwrap());
+static ATTRIBUTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+/// A speaker of a zone group
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneMember {
+    /// Player UUID, e.g. "RINCON_000E58A0123401400"
+    pub uuid: String,
+    /// Room name
+    pub zone_name: String,
+    /// URL of the device description
+    pub location: String,
+    /// Satellites and subwoofers of a home theater are invisible
+    pub invisible: bool,
+}
+
+/// Speakers that play in sync, transport commands go to the coordinator
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneGroup {
+    pub id: String,
+    pub coordinator: String,
+    pub members: Vec<ZoneMember>,
+}
+
+impl ZoneGroup {
+    pub fn coordinator(&self) -> Option<&ZoneMember> {
+        self.members.iter().find(|m| m.uuid == self.coordinator)
+    }
+
+    /// Rooms of the group, invisible speakers are left out
+    pub fn rooms(&self) -> Vec<&str> {
+        self.members.iter().filter(|m| !m.invisible).map(|m| m.zone_name.as_str()).collect()
+    }
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTRIBUTE_RE
+        .captures_iter(tag)
+        .map(|caps| (caps[1].to_string(), crate::players::openhome::client::unescape(&caps[2])))
+        .collect()
+}
+
+/// Parse the ZoneGroupState returned by ZoneGroupTopology#GetZoneGroupState
+pub fn parse_zone_group_state(xml: &str) -> Vec<ZoneGroup> {
+    GROUP_RE
+        .captures_iter(xml)
+        .map(|group| {
+            let group_attributes = attributes(&group[1]);
+            let members = MEMBER_RE
+                .captures_iter(&group[2])
+                .filter_map(|member| {
+                    let mut member_attributes = attributes(&member[1]);
+                    Some(ZoneMember {
+                        uuid: member_attributes.remove("UUID")?,
+                        zone_name: member_attributes.remove("ZoneName").unwrap_or_default(),
+                        location: member_attributes.remove("Location").unwrap_or_default(),
+                        invisible: member_attributes.get("Invisible").is_some_and(|v| v == "1"),
+                    })
+                })
+                .collect();
+            ZoneGroup {
+                id: group_attributes.get("ID").cloned().unwrap_or_default(),
+                coordinator: group_attributes.get("Coordinator").cloned().unwrap_or_default(),
+                members,
+            }
+        })
+        .collect()
+}
+
+/// Find the group and the visible speaker of a room, the room name is not case sensitive
+pub fn find_room<'a>(groups: &'a [ZoneGroup], room: &str) -> Option<(&'a ZoneGroup, &'a ZoneMember)> {
+    groups.iter().find_map(|group| {
+        group
+            .members
+            .iter()
+            .find(|m| !m.invisible && m.zone_name.eq_ignore_ascii_case(room))
+            .map(|member| (group, member))
+    })
+}
+
+/// Read the zone groups from any speaker of the household
+pub fn zone_groups(location: &str) -> Result<(UpnpDevice, Vec<ZoneGroup>), String> {
+    let client = UpnpClient::new(location);
+    let device = client.discover()?;
+    let state = client
+        .invoke(&device, "ZoneGroupTopology", "GetZoneGroupState", &[])?
+        .remove("ZoneGroupState")
+        .ok_or_else(|| "GetZoneGroupState didn't return the zone groups".to_string())?;
+    Ok((device, parse_zone_group_state(&state)))
+}
+
+/// Find Sonos speakers with SSDP and read the zone groups from the first one that answers
+///
+/// `host` skips the search and asks this speaker directly.
+pub fn discover_zones(host: Option<&str>, timeout: Duration) -> Result<Vec<ZoneGroup>, String> {
+    let locations = match host {
+        Some(host) => vec![description_url(host)],
+        None => discovery::search(ZONE_PLAYER, timeout)?,
+    };
+    let mut last_error = "No Sonos speakers found".to_string();
+    for location in locations {
+        match zone_groups(&location) {
+            Ok((_, groups)) => return Ok(groups),
+            Err(e) => {
+                debug!("Can't read Sonos zones from {}: {}", location, e);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Device description URL of the speaker at `host`
+pub fn description_url(host: &str) -> String {
+    format!("http://{}/xml/device_description.xml", crate::helpers::netaddr::host_port(host, SONOS_PORT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_zone_group_state() {
+        let xml = r#"<ZoneGroupState><ZoneGroups>
+            <ZoneGroup Coordinator="RINCON_A1400" ID="RINCON_A1400:12">
+              <ZoneGroupMember UUID="RINCON_A1400" Location="http://192.168.1.30:1400/xml/device_description.xml" ZoneName="Living Room" SoftwareVersion="79.1">
+                <Satellite UUID="RINCON_S1400" Location="http://192.168.1.33:1400/xml/device_description.xml" ZoneName="Living Room" Invisible="1"/>
+              </ZoneGroupMember>
+              <ZoneGroupMember UUID="RINCON_B1400" Location="http://192.168.1.31:1400/xml/device_description.xml" ZoneName="Kitchen"/>
+            </ZoneGroup>
+            <ZoneGroup Coordinator="RINCON_C1400" ID="RINCON_C1400:3">
+              <ZoneGroupMember UUID="RINCON_C1400" Location="http://192.168.1.32:1400/xml/device_description.xml" ZoneName="Bedroom"/>
+            </ZoneGroup>
+        </ZoneGroups><VanishedDevices/></ZoneGroupState>"#;
+        let groups = parse_zone_group_state(xml);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].rooms(), vec!["Living Room", "Kitchen"]);
+        assert_eq!(groups[0].coordinator().unwrap().zone_name, "Living Room");
+
+        let (group, member) = find_room(&groups, "kitchen").unwrap();
+        assert_eq!(member.location, "http://192.168.1.31:1400/xml/device_description.xml");
+        assert_eq!(group.coordinator, "RINCON_A1400");
+        assert!(find_room(&groups, "Garage").is_none());
+
+        assert_eq!(description_url("192.168.1.30"), "http://192.168.1.30:1400/xml/device_description.xml");
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use log::debug;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::players::openhome::client::{element_text, resolve_url, soap_action, unescape};
+
+static SERVICE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<service>(.*?)</service>").unwrap());
+static VALUE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<([A-Za-z]\w*)\s+val="([^"]*)"\s*/?>"#).unwrap());
+
+/// A service of a UPnP device
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpnpService {
+    pub service_type: String,
+    pub control_url: String,
+    pub event_sub_url: Option<String>,
+}
+
+/// The standard UPnP services of a media renderer, keyed by service name (e.g. "AVTransport")
+#[derive(Debug, Clone, Default)]
+pub struct UpnpDevice {
+    pub friendly_name: Option<String>,
+    pub model: Option<String>,
+    pub services: HashMap<String, UpnpService>,
+}
+
+impl UpnpDevice {
+    pub fn has_service(&self, name: &str) -> bool {
+        self.services.contains_key(name)
+    }
+
+    fn service(&self, name: &str) -> Result<&UpnpService, String> {
+        self.services.get(name).ok_or_else(|| format!("Service {} not available", name))
+    }
+}
+
+/// An event subscription (GENA) on a service of the renderer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    pub sid: String,
+    /// Time after which the renderer drops the subscription
+    pub timeout: Duration,
+}
+
+/// SOAP and event subscription client for a UPnP media renderer
+#[derive(Debug, Clone)]
+pub struct UpnpClient {
+    location: String,
+    timeout: Duration,
+}
+
+impl UpnpClient {
+    /// Create a client for the device description at `location`
+    pub fn new(location: &str) -> Self {
+        Self {
+            location: location.to_string(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Read the device description and find the renderer services
+    pub fn discover(&self) -> Result<UpnpDevice, String> {
+        let description = ureq::get(&self.location)
+            .timeout(self.timeout)
+            .call()
+            .map_err(|e| format!("Failed to read device description {}: {}", self.location, e))?
+            .into_string()
+            .map_err(|e| format!("Failed to read device description {}: {}", self.location, e))?;
+        let device = parse_description(&description, &self.location);
+        if !device.has_service("AVTransport") {
+            return Err(format!("{} doesn't provide the AVTransport service", self.location));
+        }
+        Ok(device)
+    }
+
+    /// Invoke an action on instance 0 of a service and return its output arguments
+    pub fn action(
+        &self,
+        device: &UpnpDevice,
+        service: &str,
+        action: &str,
+        args: &[(&str, &str)],
+    ) -> Result<HashMap<String, String>, String> {
+        let mut all_args = vec![("InstanceID", "0")];
+        all_args.extend_from_slice(args);
+        self.invoke(device, service, action, &all_args)
+    }
+
+    /// Invoke an action of a service without instances, e.g. ContentDirectory
+    pub fn invoke(
+        &self,
+        device: &UpnpDevice,
+        service: &str,
+        action: &str,
+        args: &[(&str, &str)],
+    ) -> Result<HashMap<String, String>, String> {
+        let service_info = device.service(service)?;
+        debug!("UPnP {}#{} {:?}", service, action, args);
+        soap_action(&service_info.control_url, &service_info.service_type, action, args, self.timeout)
+            .map_err(|e| format!("{}#{} failed: {}", service, action, e))
+    }
+
+    /// Subscribe to the events of a service, they are sent to `callback_url`
+    pub fn subscribe(&self, device: &UpnpDevice, service: &str, callback_url: &str, timeout: Duration) -> Result<Subscription, String> {
+        let request = ureq::request("SUBSCRIBE", &self.event_url(device, service)?)
+            .set("CALLBACK", &format!("<{}>", callback_url))
+            .set("NT", "upnp:event");
+        self.send_subscription(request, service, timeout, None)
+    }
+
+    /// Renew a subscription before it times out
+    pub fn renew(&self, device: &UpnpDevice, service: &str, sid: &str, timeout: Duration) -> Result<Subscription, String> {
+        let request = ureq::request("SUBSCRIBE", &self.event_url(device, service)?).set("SID", sid);
+        self.send_subscription(request, service, timeout, Some(sid))
+    }
+
+    pub fn unsubscribe(&self, device: &UpnpDevice, service: &str, sid: &str) -> Result<(), String> {
+        ureq::request("UNSUBSCRIBE", &self.event_url(device, service)?)
+            .timeout(self.timeout)
+            .set("SID", sid)
+            .call()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to unsubscribe from {} events: {}", service, e))
+    }
+
+    fn event_url(&self, device: &UpnpDevice, service: &str) -> Result<String, String> {
+        device
+            .service(service)?
+            .event_sub_url
+            .clone()
+            .ok_or_else(|| format!("Service {} doesn't send events", service))
+    }
+
+    fn send_subscription(&self, request: ureq::Request, service: &str, timeout: Duration, sid: Option<&str>) -> Result<Subscription, String> {
+        let response = request
+            .timeout(self.timeout)
+            .set("TIMEOUT", &format!("Second-{}", timeout.as_secs()))
+            .call()
+            .map_err(|e| format!("Failed to subscribe to {} events: {}", service, e))?;
+        let sid = response
+            .header("SID")
+            .or(sid)
+            .ok_or_else(|| format!("Subscription to {} events didn't return a SID", service))?
+            .to_string();
+        Ok(Subscription {
+            sid,
+            timeout: response.header("TIMEOUT").and_then(parse_timeout).unwrap_or(timeout),
+        })
+    }
+}
+
+/// Parse a GENA timeout header, e.g. "Second-1800"
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let seconds = value.trim().strip_prefix("Second-")?;
+    if seconds.eq_ignore_ascii_case("infinite") {
+        return Some(Duration::from_secs(86400));
+    }
+    seconds.parse().ok().map(Duration::from_secs)
+}
+
+/// Parse a UPnP device description and collect the standard UPnP services
+fn parse_description(xml: &str, location: &str) -> UpnpDevice {
+    let base = element_text(xml, "URLBase").unwrap_or_else(|| location.to_string());
+    let mut device = UpnpDevice {
+        friendly_name: element_text(xml, "friendlyName"),
+        model: element_text(xml, "modelName"),
+        services: HashMap::new(),
+    };
+
+    for caps in SERVICE_RE.captures_iter(xml) {
+        let (Some(service_type), Some(control_url)) =
+            (element_text(&caps[1], "serviceType"), element_text(&caps[1], "controlURL"))
+        else {
+            continue;
+        };
+        // urn:schemas-upnp-org:service:AVTransport:1
+        let parts: Vec<&str> = service_type.split(':').collect();
+        if parts.len() != 5 || parts[1] != "schemas-upnp-org" {
+            continue;
+        }
+        let name = parts[3].to_string();
+        let newer = device
+            .services
+            .get(&name)
+            .is_none_or(|existing| existing.service_type < service_type);
+        if newer {
+            device.services.insert(name, UpnpService {
+                service_type: service_type.clone(),
+                control_url: resolve_url(&base, &control_url),
+                event_sub_url: element_text(&caps[1], "eventSubURL").map(|url| resolve_url(&base, &url)),
+            });
+        }
+    }
+    device
+}
+
+/// Get the state variables from the LastChange property of an event notification
+///
+/// The LastChange value is an escaped XML document with one element per changed
+/// variable of instance 0, e.g. `<TransportState val="PLAYING"/>`.
+pub fn parse_last_change(notification: &str) -> HashMap<String, String> {
+    let Some(last_change) = element_text(notification, "LastChange") else {
+        return HashMap::new();
+    };
+    VALUE_RE
+        .captures_iter(&last_change)
+        .map(|caps| (caps[1].to_string(), unescape(&caps[2])))
+        .collect()
+}
+
+/// Format seconds as H:MM:SS for Seek
+pub fn format_time(seconds: f64) -> String {
+    let secs = seconds.max(0.0) as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        let xml = r#"<root><device><friendlyName>Kitchen</friendlyName><modelName>WiiM Mini</modelName><serviceList>
+            <service><serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+            <controlURL>/upnp/control/rendertransport1</controlURL><eventSubURL>/upnp/event/rendertransport1</eventSubURL></service>
+            <service><serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+            <controlURL>/upnp/control/rendercontrol1</controlURL></service>
+            <service><serviceType>urn:av-openhome-org:service:Playlist:1</serviceType><controlURL>/Playlist</controlURL></service>
+            </serviceList></device></root>"#;
+        let device = parse_description(xml, "http://192.168.1.40:49152/description.xml");
+        assert_eq!(device.friendly_name.as_deref(), Some("Kitchen"));
+        assert_eq!(device.services.len(), 2);
+        let transport = &device.services["AVTransport"];
+        assert_eq!(transport.control_url, "http://192.168.1.40:49152/upnp/control/rendertransport1");
+        assert_eq!(transport.event_sub_url.as_deref(), Some("http://192.168.1.40:49152/upnp/event/rendertransport1"));
+        assert_eq!(device.services["RenderingControl"].event_sub_url, None);
+
+        assert_eq!(parse_timeout("Second-1800"), Some(Duration::from_secs(1800)));
+        assert_eq!(format_time(3725.4), "1:02:05");
+    }
+
+    #[test]
+    fn test_parse_last_change() {
+        let notification = r#"<?xml version="1.0"?><e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0"><e:property><LastChange>&lt;Event xmlns=&quot;urn:schemas-upnp-org:metadata-1-0/AVT/&quot;&gt;&lt;InstanceID val=&quot;0&quot;&gt;&lt;TransportState val=&quot;PLAYING&quot;/&gt;&lt;CurrentTrackMetaData val=&quot;&amp;lt;DIDL-Lite&amp;gt;&amp;lt;item&amp;gt;&amp;lt;dc:title&amp;gt;Song&amp;lt;/dc:title&amp;gt;&amp;lt;/item&amp;gt;&amp;lt;/DIDL-Lite&amp;gt;&quot;/&gt;&lt;/InstanceID&gt;&lt;/Event&gt;</LastChange></e:property></e:propertyset>"#;
+        let values = parse_last_change(notification);
+        assert_eq!(values["TransportState"], "PLAYING");
+        assert_eq!(values["CurrentTrackMetaData"], "<DIDL-Lite><item><dc:title>Song</dc:title></item></DIDL-Lite>");
+        assert_eq!(values["InstanceID"], "0");
+    }
+}
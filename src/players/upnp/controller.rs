@@ -0,0 +1,645 @@
+use crate::players::player_controller::{BasePlayerController, PlayerController};
+use crate::players::poll_task::{PollTask, PollTrigger};
+use crate::data::{PlayerCapability, PlayerCapabilitySet, Song, LoopMode, PlaybackState, PlayerCommand, PlayerState, Track};
+use crate::players::openhome::client::{build_didl, parse_didl, parse_duration, DidlItem};
+use crate::players::upnp::client::{format_time, parse_last_change, Subscription, UpnpClient, UpnpDevice};
+use crate::players::upnp::discovery;
+use crate::players::upnp::events::EventListener;
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use parking_lot::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn, error};
+use std::any::Any;
+
+/// How long to wait for SSDP answers
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Requested duration of the event subscription, it is renewed after half of it
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Wait time before subscribing again after the renderer refused a subscription
+const SUBSCRIBE_RETRY: Duration = Duration::from_secs(60);
+
+/// Shared state of the controller, updated by the poll task
+#[derive(Clone)]
+struct UpnpState {
+    /// Configured description URL, if not set the renderer is found by name with SSDP
+    configured_location: Option<String>,
+    device_name: Option<String>,
+    location: Arc<RwLock<Option<String>>>,
+    device: Arc<RwLock<Option<UpnpDevice>>>,
+    current_song: Arc<RwLock<Option<Song>>>,
+    current_state: Arc<RwLock<PlayerState>>,
+    /// LastChange events are received, so the transport state doesn't have to be polled
+    subscribed: Arc<AtomicBool>,
+}
+
+/// UPnP AV / DLNA renderer controller
+///
+/// Controls media renderers through the standard AVTransport and RenderingControl
+/// services. Changes are received as LastChange events, the position is polled.
+/// AVTransport has no playlist, so the renderer doesn't expose a queue.
+pub struct UpnpController {
+    /// Base controller
+    base: BasePlayerController,
+
+    /// State shared with the poll task
+    state: UpnpState,
+
+    /// Polling interval
+    poll_interval: Duration,
+
+    /// Port for event notifications, None disables event subscriptions
+    event_port: Option<u16>,
+
+    /// Whether polling is started
+    should_poll: Arc<AtomicBool>,
+
+    /// Poll task and its event subscription
+    polling: Arc<RwLock<Option<Polling>>>,
+}
+
+/// Event subscription of the poll task
+struct EventSession {
+    listener: Option<EventListener>,
+    subscription: Option<Subscription>,
+    next_subscribe: Instant,
+    last_update: Option<Instant>,
+}
+
+impl EventSession {
+    /// Apply the received LastChange events and renew the subscription when it's due
+    fn receive_events(&mut self, state: &UpnpState, base: &BasePlayerController) {
+        let Some(listener) = &self.listener else {
+            return;
+        };
+        for notification in listener.notifications() {
+            if self.subscription.as_ref().is_some_and(|s| notification.sid.as_deref() == Some(s.sid.as_str())) {
+                UpnpController::apply_values(state, base, &parse_last_change(&notification.body));
+            }
+        }
+
+        if Instant::now() >= self.next_subscribe {
+            match state.subscribe(listener, self.subscription.take()) {
+                Ok(new_subscription) => {
+                    self.next_subscribe = Instant::now() + new_subscription.timeout / 2;
+                    self.subscription = Some(new_subscription);
+                }
+                Err(e) => {
+                    debug!("No UPnP event subscription: {}", e);
+                    self.next_subscribe = Instant::now() + SUBSCRIBE_RETRY;
+                }
+            }
+            state.subscribed.store(self.subscription.is_some(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Running poll task with the subscription it holds
+struct Polling {
+    task: PollTask,
+    session: Arc<Mutex<EventSession>>,
+}
+
+// Manually implement Clone for UpnpController
+impl Clone for UpnpController {
+    fn clone(&self) -> Self {
+        UpnpController {
+            // Share the BasePlayerController instance to maintain listener registrations
+            base: self.base.clone(),
+            state: self.state.clone(),
+            poll_interval: self.poll_interval,
+            event_port: self.event_port,
+            should_poll: Arc::clone(&self.should_poll),
+            polling: Arc::new(RwLock::new(None)), // New instance gets new poll task
+        }
+    }
+}
+
+impl UpnpState {
+    /// Client and services of the renderer, discovered with SSDP if no location is configured
+    fn renderer(&self) -> Result<(UpnpClient, UpnpDevice), String> {
+        if let (Some(location), Some(device)) = (self.location.read().clone(), self.device.read().clone()) {
+            return Ok((UpnpClient::new(&location), device));
+        }
+        let (location, device) = match &self.configured_location {
+            Some(location) => (location.clone(), UpnpClient::new(location).discover()?),
+            None => discovery::find_renderer(self.device_name.as_deref(), DISCOVERY_TIMEOUT)?,
+        };
+        info!("Found UPnP renderer {} at {} with services {:?}",
+              device.friendly_name.as_deref().unwrap_or("unknown"), location,
+              device.services.keys().collect::<Vec<_>>());
+        *self.location.write() = Some(location.clone());
+        *self.device.write() = Some(device.clone());
+        Ok((UpnpClient::new(&location), device))
+    }
+
+    /// Forget the renderer, it may have restarted with different URLs or a new address
+    fn reset(&self) {
+        *self.device.write() = None;
+        if self.configured_location.is_none() {
+            *self.location.write() = None;
+        }
+    }
+
+    fn action(&self, service: &str, action: &str, args: &[(&str, &str)]) -> Result<HashMap<String, String>, String> {
+        let (client, device) = self.renderer()?;
+        let result = client.action(&device, service, action, args);
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    fn has_service(&self, service: &str) -> bool {
+        self.device.read().as_ref().is_some_and(|d| d.has_service(service))
+    }
+
+    /// Subscribe to AVTransport events, or renew the previous subscription
+    fn subscribe(&self, listener: &EventListener, previous: Option<Subscription>) -> Result<Subscription, String> {
+        let (client, device) = self.renderer()?;
+        if let Some(previous) = previous {
+            match client.renew(&device, "AVTransport", &previous.sid, SUBSCRIPTION_TIMEOUT) {
+                Ok(subscription) => return Ok(subscription),
+                Err(e) => debug!("Renewing UPnP subscription failed, subscribing again: {}", e),
+            }
+        }
+        let host = url::Url::parse(client.location())
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_string()))
+            .ok_or_else(|| format!("Invalid device location {}", client.location()))?;
+        let subscription = client.subscribe(&device, "AVTransport", &listener.callback_url(&host)?, SUBSCRIPTION_TIMEOUT)?;
+        info!("Subscribed to AVTransport events of {} ({})", client.location(), subscription.sid);
+        Ok(subscription)
+    }
+
+    fn unsubscribe(&self, subscription: &Subscription) {
+        if let Ok((client, device)) = self.renderer() {
+            if let Err(e) = client.unsubscribe(&device, "AVTransport", &subscription.sid) {
+                debug!("{}", e);
+            }
+        }
+    }
+}
+
+/// Shuffle and loop mode of an AVTransport play mode
+fn parse_play_mode(mode: &str) -> (bool, LoopMode) {
+    match mode {
+        "SHUFFLE" | "RANDOM" | "SHUFFLE_NOREPEAT" => (true, LoopMode::None),
+        "SHUFFLE_REPEAT_ONE" => (true, LoopMode::Track),
+        "REPEAT_ONE" | "REPEAT_TRACK" => (false, LoopMode::Track),
+        "REPEAT_ALL" => (false, LoopMode::Playlist),
+        _ => (false, LoopMode::None),
+    }
+}
+
+/// AVTransport play mode for shuffle and loop mode, only the modes of the standard are used
+fn play_mode(shuffle: bool, loop_mode: LoopMode) -> &'static str {
+    match (shuffle, loop_mode) {
+        (true, _) => "SHUFFLE",
+        (false, LoopMode::Track) => "REPEAT_ONE",
+        (false, LoopMode::Playlist) => "REPEAT_ALL",
+        (false, LoopMode::None) => "NORMAL",
+    }
+}
+
+impl UpnpController {
+    /// Create a controller for the renderer at `location`, or the renderer found by name with SSDP
+    pub fn new(location: Option<&str>, device_name: Option<&str>, name: Option<&str>, poll_interval: Duration, event_port: Option<u16>) -> Self {
+        let player_id = match (location, device_name) {
+            (Some(location), _) => location.to_string(),
+            (None, Some(device_name)) => format!("upnp:{}", device_name),
+            (None, None) => "upnp".to_string(),
+        };
+        debug!("Creating new UpnpController for {}", player_id);
+
+        let controller = Self {
+            base: BasePlayerController::with_player_info(name.unwrap_or("upnp"), &player_id),
+            state: UpnpState {
+                configured_location: location.map(|l| l.to_string()),
+                device_name: device_name.map(|d| d.to_string()),
+                location: Arc::new(RwLock::new(location.map(|l| l.to_string()))),
+                device: Arc::new(RwLock::new(None)),
+                current_song: Arc::new(RwLock::new(None)),
+                current_state: Arc::new(RwLock::new(PlayerState::new())),
+                subscribed: Arc::new(AtomicBool::new(false)),
+            },
+            poll_interval,
+            event_port,
+            should_poll: Arc::new(AtomicBool::new(false)),
+            polling: Arc::new(RwLock::new(None)),
+        };
+
+        controller.set_default_capabilities();
+        controller
+    }
+
+    /// Create a controller from the player configuration
+    ///
+    /// `location` is the URL of the UPnP device description of the renderer. Without it
+    /// the renderer with the friendly name `device`, or the first renderer, is used.
+    /// `event_port` sets the port for event notifications (default: any free port),
+    /// `events: false` disables event subscriptions.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        let location = config.get("location").and_then(|v| v.as_str());
+        let device_name = config.get("device").and_then(|v| v.as_str());
+        let name = config.get("name").and_then(|v| v.as_str());
+
+        let poll_interval = config.get("poll_interval")
+            .and_then(|v| v.as_f64())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0));
+
+        let events = config.get("events").and_then(|v| v.as_bool()).unwrap_or(true);
+        let event_port = match config.get("event_port").and_then(|v| v.as_u64()) {
+            Some(port) => Some(u16::try_from(port).map_err(|_| format!("Invalid event_port {}", port))?),
+            None => Some(0),
+        };
+
+        Ok(Self::new(location, device_name, name, poll_interval, event_port.filter(|_| events)))
+    }
+
+    /// Set the default capabilities for UPnP renderers
+    fn set_default_capabilities(&self) {
+        debug!("Setting default UpnpController capabilities");
+        self.base.set_capabilities(vec![
+            PlayerCapability::Play,
+            PlayerCapability::Pause,
+            PlayerCapability::PlayPause,
+            PlayerCapability::Stop,
+            PlayerCapability::Previous,
+            PlayerCapability::Next,
+            PlayerCapability::Seek,
+            PlayerCapability::Position,
+            PlayerCapability::Length,
+            PlayerCapability::Shuffle,
+            PlayerCapability::Loop,
+            PlayerCapability::Metadata,
+            PlayerCapability::AlbumArt,
+        ], false); // Don't notify on initialization
+    }
+
+    /// Set the volume of the renderer (RenderingControl service)
+    pub fn set_volume(&self, volume: u32) -> Result<(), String> {
+        self.state
+            .action("RenderingControl", "SetVolume", &[("Channel", "Master"), ("DesiredVolume", &volume.to_string())])
+            .map(|_| ())
+    }
+
+    /// Mute or unmute the renderer (RenderingControl service)
+    pub fn set_mute(&self, mute: bool) -> Result<(), String> {
+        let value = if mute { "1" } else { "0" };
+        self.state
+            .action("RenderingControl", "SetMute", &[("Channel", "Master"), ("DesiredMute", value)])
+            .map(|_| ())
+    }
+
+    /// Apply AVTransport state variables, as sent in LastChange events, and notify listeners
+    fn apply_values(state: &UpnpState, base: &BasePlayerController, values: &HashMap<String, String>) {
+        let playback_state = values.get("TransportState").map(|transport| match transport.as_str() {
+            "PLAYING" | "TRANSITIONING" => PlaybackState::Playing,
+            "PAUSED_PLAYBACK" | "PAUSED_RECORDING" => PlaybackState::Paused,
+            "STOPPED" | "NO_MEDIA_PRESENT" => PlaybackState::Stopped,
+            _ => PlaybackState::Unknown,
+        });
+        let play_mode = values.get("CurrentPlayMode").map(|mode| parse_play_mode(mode));
+        let position = values.get("RelTime").and_then(|time| parse_duration(time));
+
+        let (state_changed, shuffle_changed, loop_changed, position_changed) = {
+            let mut current = state.current_state.write();
+            let state_changed = playback_state.is_some_and(|s| s != current.state);
+            if let Some(playback_state) = playback_state {
+                current.state = playback_state;
+            }
+            let shuffle_changed = play_mode.is_some_and(|(shuffle, _)| shuffle != current.shuffle);
+            let loop_changed = play_mode.is_some_and(|(_, loop_mode)| loop_mode != current.loop_mode);
+            if let Some((shuffle, loop_mode)) = play_mode {
+                current.shuffle = shuffle;
+                current.loop_mode = loop_mode;
+            }
+            let position_changed = position.is_some() && current.position != position;
+            if position.is_some() {
+                current.position = position;
+            }
+            (state_changed, shuffle_changed, loop_changed, position_changed)
+        };
+        let current = state.current_state.read().clone();
+        if state_changed {
+            base.notify_state_changed(current.state);
+        }
+        if shuffle_changed {
+            base.notify_random_changed(current.shuffle);
+        }
+        if loop_changed {
+            base.notify_loop_mode_changed(current.loop_mode);
+        }
+        if position_changed {
+            if let Some(position) = current.position {
+                base.notify_position_changed(position);
+            }
+        }
+
+        if !values.contains_key("CurrentTrackMetaData") && !values.contains_key("CurrentTrackURI") {
+            return;
+        }
+        let metadata = values
+            .get("CurrentTrackMetaData")
+            .filter(|m| !m.is_empty() && m.as_str() != "NOT_IMPLEMENTED");
+        let uri = values.get("CurrentTrackURI").filter(|u| !u.is_empty());
+        let song = if metadata.is_none() && uri.is_none() {
+            None
+        } else {
+            let mut song = parse_didl(metadata.map(|m| m.as_str()).unwrap_or_default()).to_song(uri.map(|u| u.as_str()));
+            if song.duration.is_none() {
+                // Streams report a duration of 0:00:00
+                song.duration = values.get("CurrentTrackDuration").and_then(|d| parse_duration(d)).filter(|d| *d > 0.0);
+            }
+            Some(song)
+        };
+
+        let song_changed = {
+            let mut current_song = state.current_song.write();
+            let changed = match (&*current_song, &song) {
+                (Some(old), Some(new)) => old.title != new.title || old.artist != new.artist || old.stream_url != new.stream_url,
+                (None, None) => false,
+                _ => true,
+            };
+            *current_song = song.clone();
+            changed
+        };
+        if song_changed {
+            debug!("UPnP song changed: {:?}", song.as_ref().and_then(|s| s.title.as_ref()));
+            base.notify_song_changed(song.as_ref());
+        }
+    }
+
+    /// Update internal state from the renderer (static version for threading)
+    ///
+    /// While events are received only the position is polled.
+    fn update_state_static(state: &UpnpState, base: &BasePlayerController) {
+        let Ok(position_info) = state.action("AVTransport", "GetPositionInfo", &[]) else {
+            debug!("UPnP renderer {} not reachable", state.location.read().as_deref().unwrap_or("unknown"));
+            return;
+        };
+
+        let mut values = HashMap::new();
+        let subscribed = state.subscribed.load(Ordering::Relaxed);
+        for (key, value) in position_info {
+            let key = match key.as_str() {
+                "RelTime" => "RelTime",
+                "TrackMetaData" if !subscribed => "CurrentTrackMetaData",
+                "TrackURI" if !subscribed => "CurrentTrackURI",
+                "TrackDuration" if !subscribed => "CurrentTrackDuration",
+                _ => continue,
+            };
+            values.insert(key.to_string(), value);
+        }
+        if !subscribed {
+            if let Some(transport) = state.action("AVTransport", "GetTransportInfo", &[]).ok()
+                .and_then(|mut info| info.remove("CurrentTransportState")) {
+                values.insert("TransportState".to_string(), transport);
+            }
+            if let Some(mode) = state.action("AVTransport", "GetTransportSettings", &[]).ok()
+                .and_then(|mut settings| settings.remove("PlayMode")) {
+                values.insert("CurrentPlayMode".to_string(), mode);
+            }
+        }
+        Self::apply_values(state, base, &values);
+
+        if state.has_service("RenderingControl") {
+            let volume = state.action("RenderingControl", "GetVolume", &[("Channel", "Master")]).ok()
+                .and_then(|mut v| v.remove("CurrentVolume"))
+                .and_then(|v| v.parse::<i32>().ok());
+            state.current_state.write().volume = volume;
+        }
+
+        base.alive();
+    }
+
+    /// Update internal state from the renderer
+    fn update_state(&self) {
+        Self::update_state_static(&self.state, &self.base);
+    }
+
+    /// Start polling, the poll task also receives the event notifications
+    fn start_polling(&self) {
+        if self.should_poll.load(Ordering::Relaxed) {
+            debug!("Polling already started for UPnP renderer");
+            return;
+        }
+
+        info!("Starting polling for UPnP renderer with interval {:?}", self.poll_interval);
+        self.should_poll.store(true, Ordering::Relaxed);
+
+        let trigger = PollTrigger::new();
+        let listener = self.event_port.and_then(|port| {
+            EventListener::bind(port, trigger.clone())
+                .inspect_err(|e| warn!("Can't listen for UPnP events on port {}, polling only: {}", port, e))
+                .ok()
+        });
+        let session = Arc::new(Mutex::new(EventSession {
+            listener,
+            subscription: None,
+            next_subscribe: Instant::now(),
+            last_update: None,
+        }));
+
+        let state = self.state.clone();
+        let poll_interval = self.poll_interval;
+        let base = self.base.clone();
+        let task_session = Arc::clone(&session);
+
+        // Received notifications trigger a poll right away, the renderer is polled every poll_interval
+        let task = PollTask::spawn_blocking("UPnP", poll_interval, Some(trigger), move || {
+            let mut session = task_session.lock();
+            session.receive_events(&state, &base);
+            if session.last_update.is_none_or(|t| t.elapsed() >= poll_interval) {
+                Self::update_state_static(&state, &base);
+                session.last_update = Some(Instant::now());
+            }
+        });
+
+        *self.polling.write() = Some(Polling { task, session });
+    }
+
+    /// Stop polling and cancel the event subscription
+    fn stop_polling(&self) {
+        if !self.should_poll.load(Ordering::Relaxed) {
+            return;
+        }
+
+        info!("Stopping polling for UPnP renderer");
+        self.should_poll.store(false, Ordering::Relaxed);
+
+        let polling = self.polling.write().take();
+        if let Some(polling) = polling {
+            polling.task.stop();
+            if let Some(subscription) = polling.session.lock().subscription.take() {
+                self.state.unsubscribe(&subscription);
+            }
+        }
+        self.state.subscribed.store(false, Ordering::Relaxed);
+    }
+
+    /// Play a URI, the following URI is set as next track if the renderer supports it
+    fn play_now(&self, uris: &[String], metadata: &[Option<crate::data::QueueTrackMetadata>], start_index: usize) -> Result<(), String> {
+        let index = if start_index < uris.len() { start_index } else { 0 };
+        let uri = uris.get(index).ok_or_else(|| "No tracks to play".to_string())?;
+        let didl = |i: usize, uri: &str| {
+            let item = metadata.get(i).and_then(|m| m.as_ref()).map(DidlItem::from_queue_metadata).unwrap_or_default();
+            build_didl(uri, &item)
+        };
+
+        self.state.action("AVTransport", "SetAVTransportURI", &[("CurrentURI", uri), ("CurrentURIMetaData", &didl(index, uri))])?;
+        self.state.action("AVTransport", "Play", &[("Speed", "1")])?;
+        if let Some(next) = uris.get(index + 1) {
+            if let Err(e) = self.state.action("AVTransport", "SetNextAVTransportURI", &[("NextURI", next), ("NextURIMetaData", &didl(index + 1, next))]) {
+                debug!("Renderer doesn't accept a next track: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PlayerController for UpnpController {
+    fn get_capabilities(&self) -> PlayerCapabilitySet {
+        self.base.get_capabilities()
+    }
+
+    fn get_player_name(&self) -> String {
+        self.base.get_player_name()
+    }
+
+    fn get_player_id(&self) -> String {
+        self.base.get_player_id()
+    }
+
+    fn get_last_seen(&self) -> Option<std::time::SystemTime> {
+        self.base.get_last_seen()
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, serde_json::Value>> {
+        let device = self.state.device.read().clone()?;
+        let mut metadata = HashMap::new();
+        if let Some(name) = device.friendly_name {
+            metadata.insert("friendly_name".to_string(), serde_json::Value::String(name));
+        }
+        if let Some(model) = device.model {
+            metadata.insert("model".to_string(), serde_json::Value::String(model));
+        }
+        if let Some(location) = self.state.location.read().clone() {
+            metadata.insert("location".to_string(), serde_json::Value::String(location));
+        }
+        metadata.insert("events".to_string(), serde_json::json!(self.state.subscribed.load(Ordering::Relaxed)));
+        if let Some(volume) = self.state.current_state.read().volume {
+            metadata.insert("volume".to_string(), serde_json::json!(volume));
+        }
+        Some(metadata)
+    }
+
+    fn get_playback_state(&self) -> PlaybackState {
+        self.state.current_state.read().state
+    }
+
+    fn get_song(&self) -> Option<Song> {
+        self.state.current_song.read().clone()
+    }
+
+    fn get_queue(&self) -> Vec<Track> {
+        Vec::new()
+    }
+
+    fn get_shuffle(&self) -> bool {
+        self.state.current_state.read().shuffle
+    }
+
+    fn get_loop_mode(&self) -> LoopMode {
+        self.state.current_state.read().loop_mode
+    }
+
+    fn get_position(&self) -> Option<f64> {
+        self.state.current_state.read().position
+    }
+
+    fn send_command(&self, command: PlayerCommand) -> bool {
+        info!("Sending command to UPnP renderer: {}", command);
+
+        let result = match &command {
+            PlayerCommand::Play => self.state.action("AVTransport", "Play", &[("Speed", "1")]).map(|_| ()),
+            PlayerCommand::Pause => self.state.action("AVTransport", "Pause", &[]).map(|_| ()),
+            PlayerCommand::PlayPause => {
+                if self.get_playback_state() == PlaybackState::Playing {
+                    self.state.action("AVTransport", "Pause", &[]).map(|_| ())
+                } else {
+                    self.state.action("AVTransport", "Play", &[("Speed", "1")]).map(|_| ())
+                }
+            }
+            PlayerCommand::Stop => self.state.action("AVTransport", "Stop", &[]).map(|_| ()),
+            PlayerCommand::Next => self.state.action("AVTransport", "Next", &[]).map(|_| ()),
+            PlayerCommand::Previous => self.state.action("AVTransport", "Previous", &[]).map(|_| ()),
+            PlayerCommand::Seek(position) => self.state
+                .action("AVTransport", "Seek", &[("Unit", "REL_TIME"), ("Target", &format_time(*position))])
+                .map(|_| ()),
+            PlayerCommand::SetRandom(enabled) => {
+                let mode = play_mode(*enabled, self.get_loop_mode());
+                self.state.action("AVTransport", "SetPlayMode", &[("NewPlayMode", mode)]).map(|_| ())
+            }
+            PlayerCommand::SetLoopMode(loop_mode) => {
+                let mode = play_mode(self.get_shuffle(), *loop_mode);
+                self.state.action("AVTransport", "SetPlayMode", &[("NewPlayMode", mode)]).map(|_| ())
+            }
+            PlayerCommand::PlayNow { uris, metadata, start_index } => self.play_now(uris, metadata, *start_index),
+            _ => {
+                warn!("Command not supported by UPnP renderer: {}", command);
+                return false;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Trigger an immediate state update
+                self.update_state();
+                true
+            }
+            Err(e) => {
+                error!("Failed to send command {} to UPnP renderer: {}", command, e);
+                false
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn start(&self) -> bool {
+        info!("Starting UPnP controller for {}", self.base.get_player_id());
+        // The renderer may be switched off, the poll task picks it up when it becomes reachable
+        if let Err(e) = self.state.renderer() {
+            warn!("UPnP renderer is not reachable yet: {}", e);
+        }
+        self.start_polling();
+        true
+    }
+
+    fn stop(&self) -> bool {
+        info!("Stopping UPnP controller");
+        self.stop_polling();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_mode() {
+        for (shuffle, loop_mode) in [(false, LoopMode::None), (false, LoopMode::Track), (false, LoopMode::Playlist)] {
+            assert_eq!(parse_play_mode(play_mode(shuffle, loop_mode)), (shuffle, loop_mode));
+        }
+        assert_eq!(parse_play_mode("SHUFFLE_NOREPEAT"), (true, LoopMode::None));
+        assert_eq!(parse_play_mode("DIRECT_1"), (false, LoopMode::None));
+    }
+}
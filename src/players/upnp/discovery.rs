@@ -0,0 +1,111 @@
+use log::debug;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+use crate::players::upnp::client::{UpnpClient, UpnpDevice};
+
+/// Device type searched for with SSDP
+pub const MEDIA_RENDERER: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+/// Find the description locations of media renderers with an SSDP search
+pub fn discover(timeout: Duration) -> Result<Vec<String>, String> {
+    search(MEDIA_RENDERER, timeout)
+}
+
+/// Find the description locations of devices of the given type with an SSDP search
+///
+/// Devices answer to the port the search was sent from, so no multicast
+/// group has to be joined.
+pub fn search(search_target: &str, timeout: Duration) -> Result<Vec<String>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to open SSDP socket: {}", e))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+        SSDP_ADDRESS,
+        timeout.as_secs().clamp(1, 5),
+        search_target
+    );
+    socket
+        .send_to(search.as_bytes(), SSDP_ADDRESS)
+        .map_err(|e| format!("Failed to send SSDP search: {}", e))?;
+
+    let mut locations: Vec<String> = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let deadline = Instant::now() + timeout;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+        socket.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+        let len = match socket.recv_from(&mut buffer) {
+            Ok((len, _)) => len,
+            Err(_) => break,
+        };
+        if let Some(location) = parse_search_response(&String::from_utf8_lossy(&buffer[..len]), search_target) {
+            if !locations.contains(&location) {
+                debug!("Found {} at {}", search_target, location);
+                locations.push(location);
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// Find a renderer by friendly name, or the first renderer if no name is given
+///
+/// Returns the location of the device description and the device.
+pub fn find_renderer(name: Option<&str>, timeout: Duration) -> Result<(String, UpnpDevice), String> {
+    for location in discover(timeout)? {
+        let device = match UpnpClient::new(&location).discover() {
+            Ok(device) => device,
+            Err(e) => {
+                debug!("Ignoring {}: {}", location, e);
+                continue;
+            }
+        };
+        let matches = match name {
+            Some(name) => device.friendly_name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name)),
+            None => true,
+        };
+        if matches {
+            return Ok((location, device));
+        }
+    }
+    Err(match name {
+        Some(name) => format!("UPnP renderer '{}' not found", name),
+        None => "No UPnP renderer found".to_string(),
+    })
+}
+
+/// Get the LOCATION header of a search response for the searched device type
+fn parse_search_response(response: &str, search_target: &str) -> Option<String> {
+    let mut lines = response.lines();
+    if !lines.next()?.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    let mut location = None;
+    let mut response_target = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        match name.trim().to_ascii_uppercase().as_str() {
+            "LOCATION" => location = Some(value.trim().to_string()),
+            "ST" => response_target = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if response_target.is_some_and(|st| st != search_target) {
+        return None;
+    }
+    location
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_response() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.40:49152/description.xml\r\n\
+                        ST: urn:schemas-upnp-org:device:MediaRenderer:1\r\nUSN: uuid:abc::urn:schemas-upnp-org:device:MediaRenderer:1\r\n\r\n";
+        assert_eq!(parse_search_response(response, MEDIA_RENDERER).as_deref(), Some("http://192.168.1.40:49152/description.xml"));
+        assert_eq!(parse_search_response(&response.replace("MediaRenderer", "MediaServer"), MEDIA_RENDERER), None);
+        assert_eq!(parse_search_response("NOTIFY * HTTP/1.1\r\nLOCATION: http://x/\r\n\r\n", MEDIA_RENDERER), None);
+    }
+}
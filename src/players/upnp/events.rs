@@ -0,0 +1,224 @@
+use crate::helpers::netaddr;
+use crate::players::poll_task::PollTrigger;
+use log::debug;
+use parking_lot::Mutex;
+use std::io;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Largest accepted notification body, LastChange events are a few KiB
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Largest accepted request line plus headers
+const MAX_HEADER_SIZE: u64 = 16 * 1024;
+
+/// Time a renderer has to send a complete notification
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An event notification sent by the renderer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub sid: Option<String>,
+    pub body: String,
+}
+
+/// HTTP server receiving the event notifications (NOTIFY requests) of subscriptions
+///
+/// Connections are accepted on the global runtime. Received notifications are
+/// kept until they are read with `notifications`, and the trigger wakes up the
+/// poll task that reads them.
+pub struct EventListener {
+    port: u16,
+    pending: Arc<Mutex<Vec<Notification>>>,
+    task: JoinHandle<()>,
+}
+
+impl EventListener {
+    /// Listen on the given port, 0 picks a free port
+    pub fn bind(port: u16, trigger: PollTrigger) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        let runtime = crate::get_tokio_runtime();
+        let listener = {
+            let _guard = runtime.enter();
+            TcpListener::from_std(listener)?
+        };
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let task_pending = Arc::clone(&pending);
+        let task = runtime.spawn(async move {
+            loop {
+                let (stream, address) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        debug!("Failed to accept event notification: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let pending = Arc::clone(&task_pending);
+                let trigger = trigger.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(READ_TIMEOUT, read_notification(stream)).await {
+                        Ok(Ok(Some(notification))) => {
+                            pending.lock().push(notification);
+                            trigger.trigger();
+                        }
+                        Ok(Ok(None)) => {}
+                        Ok(Err(e)) => debug!("Failed to read event notification from {}: {}", address, e),
+                        Err(_) => debug!("Event notification from {} timed out", address),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { port, pending, task })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// URL the renderer at `device_host` can send notifications to
+    ///
+    /// Uses the address of the interface that routes to the renderer.
+    pub fn callback_url(&self, device_host: &str) -> Result<String, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+        socket
+            .connect((netaddr::strip_brackets(device_host), 1900))
+            .map_err(|e| format!("No route to {}: {}", device_host, e))?;
+        let local = socket.local_addr().map_err(|e| e.to_string())?;
+        Ok(format!("http://{}/", netaddr::host_port(&local.ip().to_string(), self.port())))
+    }
+
+    /// Take the notifications received since the last call
+    pub fn notifications(&self) -> Vec<Notification> {
+        std::mem::take(&mut *self.pending.lock())
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Read a line of the request header, counting it against the header limit
+async fn read_header_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, remaining: &mut u64) -> io::Result<String> {
+    let mut line = String::new();
+    let read = (&mut *reader).take(*remaining).read_line(&mut line).await?;
+    *remaining -= read as u64;
+    if *remaining == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request header too large"));
+    }
+    Ok(line)
+}
+
+/// Read a NOTIFY request and answer it
+async fn read_notification(stream: TcpStream) -> io::Result<Option<Notification>> {
+    let mut reader = BufReader::new(stream);
+    let mut remaining = MAX_HEADER_SIZE;
+
+    let request_line = read_header_line(&mut reader, &mut remaining).await?;
+    let is_notify = request_line.starts_with("NOTIFY ");
+
+    let mut sid = None;
+    let mut content_length = 0;
+    loop {
+        let line = read_header_line(&mut reader, &mut remaining).await?;
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_uppercase().as_str() {
+                "SID" => sid = Some(value.trim().to_string()),
+                "CONTENT-LENGTH" => content_length = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_SIZE {
+        respond(&mut reader, "413 Payload Too Large").await?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("body of {} bytes is too large", content_length),
+        ));
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+
+    respond(&mut reader, if is_notify { "200 OK" } else { "405 Method Not Allowed" }).await?;
+
+    Ok(is_notify.then(|| Notification {
+        sid,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }))
+}
+
+async fn respond(reader: &mut BufReader<TcpStream>, status: &str) -> io::Result<()> {
+    reader
+        .get_mut()
+        .write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::thread;
+
+    /// Send a request to the listener and return the response
+    fn send(port: u16, request: String) -> thread::JoinHandle<String> {
+        thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        })
+    }
+
+    #[test]
+    fn test_receive_notification() {
+        let listener = EventListener::bind(0, PollTrigger::new()).unwrap();
+        let body = "<e:propertyset/>";
+        let sender = send(listener.port(), format!(
+            "NOTIFY / HTTP/1.1\r\nNT: upnp:event\r\nSID: uuid:1234\r\nSEQ: 0\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        assert!(sender.join().unwrap().starts_with("HTTP/1.1 200 OK"));
+
+        let mut notifications = Vec::new();
+        for _ in 0..50 {
+            notifications.extend(listener.notifications());
+            if !notifications.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(notifications, vec![Notification {
+            sid: Some("uuid:1234".to_string()),
+            body: "<e:propertyset/>".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_reject_large_notification() {
+        let listener = EventListener::bind(0, PollTrigger::new()).unwrap();
+        let sender = send(listener.port(), format!(
+            "NOTIFY / HTTP/1.1\r\nSID: uuid:1234\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        ));
+        assert!(sender.join().unwrap().starts_with("HTTP/1.1 413"));
+        thread::sleep(Duration::from_millis(50));
+        assert!(listener.notifications().is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod client;
+pub mod controller;
+pub mod discovery;
+pub mod events;
+
+pub use client::{UpnpClient, UpnpDevice};
+pub use controller::UpnpController;
@@ -4,7 +4,7 @@ use std::any::Any;
 use crate::data::PlayerEvent;
 use crate::plugins::plugin::Plugin;
 use crate::audiocontrol::AudioController;
-use crate::audiocontrol::eventbus::EventBus;
+use crate::audiocontrol::eventbus::{EventBus, EventChannel};
 use log;
 
 /// A plugin that can respond to events from an AudioController
@@ -32,11 +32,8 @@ pub struct BaseActionPlugin {
     /// Weak reference to the AudioController
     controller: Option<Weak<AudioController>>,
     
-    /// Subscription to the global event bus
-    event_bus_subscription: Arc<Mutex<Option<(u64, crossbeam::channel::Receiver<PlayerEvent>)>>>,
-    
-    /// Handle to the event listener thread
-    event_listener_thread: Arc<Mutex<Option<std::thread::JoinHandle<()>>>>,
+    /// Task on the global runtime that passes events from the event bus to the plugin
+    event_listener_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
 }
 
 impl BaseActionPlugin {
@@ -46,8 +43,7 @@ impl BaseActionPlugin {
             name: name.to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             controller: None,
-            event_bus_subscription: Arc::new(Mutex::new(None)),
-            event_listener_thread: Arc::new(Mutex::new(None)),
+            event_listener_task: Arc::new(Mutex::new(None)),
         }
     }
     
@@ -61,53 +57,49 @@ impl BaseActionPlugin {
         self.controller = Some(controller);
     }
     
-    /// Subscribe to the event bus and start a listener thread
+    /// Subscribe to all events of the event bus
     pub fn subscribe_to_event_bus<F>(&self, event_handler: F) 
     where
         F: Fn(PlayerEvent) + Send + 'static,
     {
-        log::debug!("Subscribing to event bus for plugin '{}'", self.name);
-        
-        // Set up subscription to the global event bus
-        let event_bus = EventBus::instance();
-        let (id, receiver) = event_bus.subscribe_all();
-        
-        // Store our subscription ID (we'll need it to unsubscribe later)
-        *self.event_bus_subscription.lock() = Some((id, receiver.clone()));
-        
-        // Start a thread to listen for events from the event bus
-        let thread_handle = std::thread::spawn(move || {
-            log::debug!("Event bus listener thread started");
-            
-            // Process events until the channel is closed
-            while let Ok(event) = receiver.recv() {
-                // Handle the event using the provided handler
-                event_handler(event);
+        self.subscribe_to_channels(&EventChannel::ALL, event_handler);
+    }
+
+    /// Subscribe to some channels of the event bus and pass their events to the handler
+    ///
+    /// The handler runs on the global runtime, but may block: it is called with `block_in_place`.
+    /// Events are handled one after the other in the order they were published. A slow handler
+    /// doesn't lose events, they wait in the queue of the plugin.
+    pub fn subscribe_to_channels<F>(&self, channels: &[EventChannel], event_handler: F)
+    where
+        F: Fn(PlayerEvent) + Send + 'static,
+    {
+        log::debug!("Subscribing to event bus channels {:?} for plugin '{}'", channels, self.name);
+
+        // Replace an existing subscription instead of handling events twice
+        self.unsubscribe_from_event_bus();
+
+        let mut queue = EventBus::instance().queue(channels);
+        let name = self.name.clone();
+        let task = crate::get_tokio_runtime().spawn(async move {
+            log::debug!("Event bus listener of plugin '{}' started", name);
+
+            while let Some(event) = queue.recv().await {
+                tokio::task::block_in_place(|| event_handler(event));
             }
-            
-            log::debug!("Event bus listener thread exiting");
+
+            log::debug!("Event bus listener of plugin '{}' exiting", name);
         });
-        
-        // Store the thread handle
-        *self.event_listener_thread.lock() = Some(thread_handle);
+
+        *self.event_listener_task.lock() = Some(task);
     }
     
-    /// Unsubscribe from the event bus and clean up the listener thread
+    /// Unsubscribe from the event bus and stop the listener task
     pub fn unsubscribe_from_event_bus(&self) {
-        log::debug!("Unsubscribing from event bus for plugin '{}'", self.name);
-        
-        // Unsubscribe from the event bus
-        if let Some((id, _)) = self.event_bus_subscription.lock().take() {
-            EventBus::instance().unsubscribe(id);
-            log::debug!("Unsubscribed from event bus");
-        }
-        
-        // Wait for the event listener thread to exit
-        let mut thread_guard = self.event_listener_thread.lock();
-        if thread_guard.is_some() {
-            // Just take the handle and drop it, which detaches the thread
-            let _ = thread_guard.take();
-            log::debug!("Detaching event bus listener thread");
+        if let Some(task) = self.event_listener_task.lock().take() {
+            // The task stops at its next await point, an event being handled is finished first
+            task.abort();
+            log::debug!("Unsubscribed plugin '{}' from event bus", self.name);
         }
     }
 }
@@ -1,14 +1,15 @@
 use std::sync::{Arc, Weak};
 use std::any::Any;
-use crate::data::{PlayerEvent, PlaybackState, PlayerCommand};
+use crate::data::PlayerEvent;
 use crate::plugins::plugin::Plugin;
 use crate::plugins::action_plugin::{ActionPlugin, BaseActionPlugin};
+use crate::audiocontrol::eventbus::EventChannel;
 use crate::audiocontrol::AudioController;
-use log::{debug, info, warn, trace};
+use log::{warn, trace};
 use delegate::delegate;
 
 /// A plugin that monitors player state changes and sets the active player
-/// to any player that enters the Playing state.
+/// to a player that enters the Playing state, if the source priority rules allow it.
 pub struct ActiveMonitor {
     /// Base implementation for common functionality
     base: BaseActionPlugin,
@@ -28,72 +29,15 @@ impl ActiveMonitor {
         }
     }
     
-    /// Try to find a player controller by name and ID and make it active
-    fn set_active_player(&self, player_name: &str, player_id: &str) {
-        if let Some(controller) = self.base.get_controller() {
-            // First check if the given player is already active
-            if let Some(active_controller) = controller.get_active_controller() {
-                let active_player = active_controller.read();
-                if active_player.get_player_name() == player_name &&
-                   active_player.get_player_id() == player_id {
-                    debug!("ActiveMonitor: Player {}:{} is already active, no change needed",
-                           player_name, player_id);
-                    return;
-                }
-            }
-
-            // Find the controller with matching name and ID
-            let controllers = controller.list_controllers();
-            let mut target_index = None;
-
-            // First find the matching player and store its index
-            for (idx, player_controller) in controllers.iter().enumerate() {
-                let player = player_controller.read();
-                if player.get_player_name() == player_name && player.get_player_id() == player_id {
-                    target_index = Some(idx);
-                    break;
-                }
-            }
-
-            // Now set the active controller after all locks have been released
-            if let Some(idx) = target_index {
-                info!("ActiveMonitor: Setting player {}:{} as active", player_name, player_id);
-                if controller.set_active_controller(idx) {
-                    info!("ActiveMonitor: Successfully set active player to {}:{}",
-                          player_name, player_id);
-                    // Only one source should play at a time. Now that a new
-                    // player has started and become active, stop every other
-                    // (now-inactive) player so we don't end up with two sources
-                    // playing simultaneously. We reach this point only on a real
-                    // active-player change (set_active_player returns early when
-                    // the player is already active), so this won't fire on a
-                    // track change within the same player.
-                    let stopped = controller.send_command_to_inactives(PlayerCommand::Stop);
-                    if stopped > 0 {
-                        info!("ActiveMonitor: sent Stop to {} now-inactive player(s)", stopped);
-                    }
-                } else {
-                    warn!("ActiveMonitor: Failed to set active player");
-                }
-            } else {
-                warn!("ActiveMonitor: Could not find player {}:{} to set active", player_name, player_id);
-            }
-        } else {
-            warn!("ActiveMonitor: No valid AudioController reference available");
-        }
-    }
-    
     /// Handle events coming from the event bus
     fn handle_event_bus_events(&self, event: PlayerEvent) {
         trace!("Received event from event bus");
-        
-        // We only care about state changed events
+
+        // The source priority rules of the AudioController decide if the player becomes active
         if let PlayerEvent::StateChanged { source, state } = event {
-            // If a player state changes to Playing, make it the active player
-            if state == PlaybackState::Playing {
-                debug!("ActiveMonitor: Detected player {}:{} state changed to Playing", 
-                       source.player_name(), source.player_id());
-                self.set_active_player(source.player_name(), source.player_id());
+            match self.base.get_controller() {
+                Some(controller) => controller.handle_state_change(source.player_name(), source.player_id(), state),
+                None => warn!("ActiveMonitor: No valid AudioController reference available"),
             }
         }
     }
@@ -129,7 +73,7 @@ impl ActionPlugin for ActiveMonitor {
         // Subscribe to event bus in the initialize method
         log::debug!("ActiveMonitor initializing and subscribing to event bus");
         let self_clone = self.clone();
-        self.base.subscribe_to_event_bus(move |event| {
+        self.base.subscribe_to_channels(&[EventChannel::Player], move |event| {
             self_clone.handle_event(event);
         });
     }
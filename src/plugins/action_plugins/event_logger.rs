@@ -95,10 +95,20 @@ impl EventLogger {
             PlayerEvent::CapabilitiesChanged { .. } => "capabilities_changed",
             PlayerEvent::PositionChanged { .. } => "position_changed",
             PlayerEvent::DatabaseUpdating { .. } => "database_updating",
+            PlayerEvent::LibraryLoadProgress { .. } => "library_load_progress",
+            PlayerEvent::LibraryChanged { .. } => "library_changed",
             PlayerEvent::QueueChanged { .. } => "queue_changed",
+            PlayerEvent::UpcomingTrack { .. } => "upcoming_track",
+            PlayerEvent::QueueEnding { .. } => "queue_ending",
             PlayerEvent::SongInformationUpdate { .. } => "song_information_update",
             PlayerEvent::ActivePlayerChanged { .. } => "active_player_changed",
             PlayerEvent::VolumeChanged { .. } => "volume_changed",
+            PlayerEvent::UsbStorageChanged { .. } => "usb_storage_changed",
+            PlayerEvent::SystemIdle { .. } => "system_idle",
+            PlayerEvent::SystemWake { .. } => "system_wake",
+            PlayerEvent::NetworkChanged { .. } => "network_changed",
+            PlayerEvent::SystemResourceAlert { .. } => "system_resource_alert",
+            PlayerEvent::BackgroundJobProgress { .. } => "background_job_progress",
         }
     }    
     
@@ -272,6 +282,30 @@ impl EventLogger {
                     is_active_player
                 );
             },
+            PlayerEvent::UpcomingTrack { source, remaining, next } => {
+                self.log_message(
+                    &format!(
+                        "Player {} (ID: {}) track ends in {:.1}s, next: {}",
+                        source.player_name(),
+                        source.player_id(),
+                        remaining,
+                        next.as_ref().map_or("unknown", |track| track.name.as_str())
+                    ),
+                    is_active_player
+                );
+            },
+            PlayerEvent::QueueEnding { source, remaining, tracks_remaining } => {
+                self.log_message(
+                    &format!(
+                        "Player {} (ID: {}) queue ends in {:.1}s, {} tracks left",
+                        source.player_name(),
+                        source.player_id(),
+                        remaining,
+                        tracks_remaining
+                    ),
+                    is_active_player
+                );
+            },
             PlayerEvent::SongInformationUpdate { source, song } => {
                 // song is type Song, not Option<Song>
                 self.log_message(
@@ -321,6 +355,66 @@ impl EventLogger {
                     false // Volume events are system-wide, not player-specific
                 );
             },
+            PlayerEvent::UsbStorageChanged { device, label, library_path, available } => {
+                self.log_message(
+                    &format!(
+                        "USB storage '{}' ({}) {}{}",
+                        label,
+                        device,
+                        if *available { "available" } else { "removed" },
+                        library_path.as_ref().map(|p| format!(" at {}", p)).unwrap_or_default()
+                    ),
+                    false
+                );
+            },
+            PlayerEvent::LibraryLoadProgress { source, artists_loaded, artists_total, albums_processed, albums_total } => {
+                self.log_message(
+                    &format!(
+                        "Player {} (ID: {}) loading library: {}/{} artists, {}/{} albums",
+                        source.player_name(),
+                        source.player_id(),
+                        artists_loaded,
+                        artists_total,
+                        albums_processed,
+                        albums_total
+                    ),
+                    is_active_player
+                );
+            },
+            PlayerEvent::LibraryChanged { source, diff } => {
+                self.log_message(
+                    &format!(
+                        "Player {} (ID: {}) library changed: {} albums added, {} removed, {} changed",
+                        source.player_name(),
+                        source.player_id(),
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len()
+                    ),
+                    is_active_player
+                );
+            },
+            PlayerEvent::SystemIdle { inactive_seconds } => {
+                self.log_message(&format!("System idle after {}s without playback", inactive_seconds), false);
+            },
+            PlayerEvent::SystemWake { idle_seconds } => {
+                self.log_message(&format!("System woke up after being idle for {}s", idle_seconds), false);
+            },
+            PlayerEvent::NetworkChanged { online, addresses } => {
+                self.log_message(
+                    &format!("Network {} ({})", if *online { "online" } else { "offline" }, addresses.join(", ")),
+                    false
+                );
+            },
+            PlayerEvent::SystemResourceAlert { resource, critical, value, threshold } => {
+                self.log_message(
+                    &format!("System {} {}: {} (threshold {})", resource, if *critical { "critical" } else { "normal" }, value, threshold),
+                    false
+                );
+            },
+            PlayerEvent::BackgroundJobProgress { .. } => {
+                self.log_message(&event.to_string(), false);
+            },
         }
     }    
 }
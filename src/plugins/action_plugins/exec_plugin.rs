@@ -0,0 +1,446 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use delegate::delegate;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::audiocontrol::AudioController;
+use crate::data::{EventMessage, PlayerEvent};
+use crate::plugins::action_plugin::{ActionPlugin, BaseActionPlugin};
+use crate::players::PlayerController;
+use crate::plugins::plugin::Plugin;
+
+/// Interval of the checks whether a command has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn default_min_interval_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// A command and the events that run it
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecCommandConfig {
+    /// Program and arguments, split at whitespace outside of quotes, `{field}` placeholders are replaced by values of the event
+    pub command: String,
+
+    /// Event types that run the command, e.g. `state_changed`
+    pub event_types: HashSet<String>,
+
+    /// Only run for these playback states, e.g. `playing`
+    #[serde(default)]
+    pub states: Option<HashSet<String>>,
+
+    /// Only run for events of the active player
+    #[serde(default)]
+    pub only_active: bool,
+
+    /// Events within this time after the last run are ignored, in milliseconds
+    #[serde(default = "default_min_interval_ms")]
+    pub min_interval_ms: u64,
+
+    /// Time the command may run before it is killed, in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// Configuration of the exec plugin
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecPluginConfig {
+    /// Commands run by this plugin
+    pub commands: Vec<ExecCommandConfig>,
+}
+
+/// A configured command with the state needed for rate limiting
+struct ExecCommand {
+    config: ExecCommandConfig,
+
+    /// Start of the last run
+    last_run: Mutex<Option<Instant>>,
+
+    /// Whether the command is still running, it isn't started twice
+    running: Arc<AtomicBool>,
+}
+
+impl ExecCommand {
+    fn new(config: ExecCommandConfig) -> Self {
+        Self {
+            config,
+            last_run: Mutex::new(None),
+            running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Check the event type and state filters
+    fn matches(&self, event_type: &str, fields: &HashMap<String, String>) -> bool {
+        if !self.config.event_types.contains(event_type) && !self.config.event_types.contains("*") {
+            return false;
+        }
+        match &self.config.states {
+            Some(states) => fields.get("state").is_some_and(|state| states.contains(state)),
+            None => true,
+        }
+    }
+
+    /// Reserve a run, false if the last run was too recent or is still running
+    fn try_start(&self, now: Instant) -> bool {
+        let mut last_run = self.last_run.lock();
+        if let Some(last) = *last_run {
+            if now.duration_since(last) < Duration::from_millis(self.config.min_interval_ms) {
+                return false;
+            }
+        }
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        *last_run = Some(now);
+        true
+    }
+}
+
+/// Action plugin that runs commands on events
+pub struct ExecPlugin {
+    /// Base action plugin implementation
+    base: BaseActionPlugin,
+
+    /// Configured commands
+    commands: Arc<Vec<ExecCommand>>,
+}
+
+impl ExecPlugin {
+    pub fn new(config: ExecPluginConfig) -> Self {
+        Self {
+            base: BaseActionPlugin::new("ExecPlugin"),
+            commands: Arc::new(config.commands.into_iter().map(ExecCommand::new).collect()),
+        }
+    }
+
+    /// Run the commands that match an event
+    fn process_event(commands: &[ExecCommand], controller: Option<Arc<AudioController>>, event: &PlayerEvent) {
+        let event_type = event.event_type();
+        if !commands.iter().any(|command| command.config.event_types.contains(event_type) || command.config.event_types.contains("*")) {
+            return;
+        }
+
+        let fields = match serde_json::to_value(EventMessage::from(event)) {
+            Ok(json) => template_fields(&json),
+            Err(e) => {
+                warn!("Failed to serialize {} event for exec commands: {}", event_type, e);
+                return;
+            }
+        };
+        let is_active = match (&controller, event.player_id()) {
+            (Some(controller), Some(player_id)) => controller.get_player_id() == player_id,
+            _ => false,
+        };
+
+        let now = Instant::now();
+        for command in commands {
+            if !command.matches(event_type, &fields) || (command.config.only_active && !is_active) {
+                continue;
+            }
+            let args = match command_args(&command.config.command, &fields) {
+                Ok(args) => args,
+                Err(e) => {
+                    warn!("Not running '{}' for {} event: {}", command.config.command, event_type, e);
+                    continue;
+                }
+            };
+            if !command.try_start(now) {
+                debug!("Skipping '{}' for {} event, it ran too recently or is still running", command.config.command, event_type);
+                continue;
+            }
+            run_command(args, command_env(&fields), Duration::from_secs(command.config.timeout_secs), command.running.clone());
+        }
+    }
+}
+
+/// Values of an event that can be used as placeholders
+///
+/// Nested values are available as `{song.title}`, song fields, the player name and the
+/// event type also as `{title}`, `{artist}`, `{album}`, `{player}` and `{event}`.
+fn template_fields(json: &Value) -> HashMap<String, String> {
+    fn flatten(prefix: &str, value: &Value, fields: &mut HashMap<String, String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    flatten(&key, value, fields);
+                }
+            }
+            Value::Null => {}
+            Value::String(s) => {
+                fields.insert(prefix.to_string(), s.clone());
+            }
+            other => {
+                fields.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+
+    let mut fields = HashMap::new();
+    flatten("", json, &mut fields);
+
+    let aliases = [
+        ("event", "type"),
+        ("player", "source.player_name"),
+        ("title", "song.title"),
+        ("artist", "song.artist"),
+        ("album", "song.album"),
+    ];
+    for (alias, key) in aliases {
+        if let Some(value) = fields.get(key).cloned() {
+            fields.entry(alias.to_string()).or_insert(value);
+        }
+    }
+    fields
+}
+
+/// Replace `{field}` placeholders in one argument, unknown fields become empty
+fn expand_placeholders(arg: &str, fields: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name = after
+            .find('}')
+            .map(|end| &after[..end])
+            .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'));
+        match name {
+            Some(name) => {
+                result.push_str(fields.get(name).map(String::as_str).unwrap_or(""));
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Split a command template into arguments at whitespace
+///
+/// Single or double quotes group words into one argument and are removed, there are no escapes.
+fn split_template(template: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in template.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => args.extend(current.take()),
+            None => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    args
+}
+
+/// Split a command template and fill in the placeholders, no shell is involved
+///
+/// A value stays a single argument even if it contains spaces or shell syntax. A value can't turn an
+/// argument into an option: before `--`, arguments may only start with `-` if the template does.
+fn command_args(template: &str, fields: &HashMap<String, String>) -> Result<Vec<String>, String> {
+    let mut options_ended = false;
+    split_template(template)
+        .iter()
+        .map(|arg| {
+            let expanded = expand_placeholders(arg, fields);
+            if !options_ended && expanded.starts_with('-') && !arg.starts_with('-') {
+                return Err(format!("the value for '{}' starts with '-'", arg));
+            }
+            options_ended |= arg == "--";
+            Ok(expanded)
+        })
+        .collect()
+}
+
+/// Environment variables with the event values, e.g. `ACR_TITLE` or `ACR_SONG_DURATION`
+fn command_env(fields: &HashMap<String, String>) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .map(|(key, value)| (format!("ACR_{}", key.replace('.', "_").to_uppercase()), value.clone()))
+        .collect()
+}
+
+/// Run a command in the background, it's killed after the timeout
+fn run_command(args: Vec<String>, env: Vec<(String, String)>, timeout: Duration, running: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let line = args.join(" ");
+        debug!("Running exec command: {}", line);
+        let Some((program, args)) = args.split_first() else {
+            running.store(false, Ordering::SeqCst);
+            return;
+        };
+        match Command::new(program)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(mut child) => {
+                let started = Instant::now();
+                loop {
+                    match child.try_wait() {
+                        Ok(Some(status)) if status.success() => break,
+                        Ok(Some(status)) => {
+                            warn!("Exec command '{}' failed with {}", line, status);
+                            break;
+                        }
+                        Ok(None) if started.elapsed() >= timeout => {
+                            warn!("Exec command '{}' timed out after {} seconds, killing it", line, timeout.as_secs());
+                            let _ = child.kill();
+                            let _ = child.wait();
+                            break;
+                        }
+                        Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                        Err(e) => {
+                            warn!("Failed to wait for exec command '{}': {}", line, e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to run exec command '{}': {}", line, e),
+        }
+        running.store(false, Ordering::SeqCst);
+    });
+}
+
+impl Plugin for ExecPlugin {
+    delegate! {
+        to self.base {
+            fn name(&self) -> &str;
+            fn version(&self) -> &str;
+        }
+    }
+
+    fn init(&mut self) -> bool {
+        log::info!("Exec plugin initialized with {} commands", self.commands.len());
+        self.base.init()
+    }
+
+    fn shutdown(&mut self) -> bool {
+        self.base.shutdown()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ActionPlugin for ExecPlugin {
+    fn initialize(&mut self, controller: Weak<AudioController>) {
+        self.base.set_controller(controller.clone());
+
+        let commands = self.commands.clone();
+        self.base.subscribe_to_event_bus(move |event| {
+            Self::process_event(&commands, controller.upgrade(), &event);
+        });
+    }
+
+    fn handle_event(&self, event: PlayerEvent) {
+        Self::process_event(&self.commands, self.base.get_controller(), &event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields() -> HashMap<String, String> {
+        template_fields(&json!({
+            "type": "song_changed",
+            "song": {"title": "It's Alright", "artist": "Ma", "duration": 215.0, "album": null},
+            "source": {"player_id": "localhost:6600", "player_name": "mpd"}
+        }))
+    }
+
+    #[test]
+    fn test_template_fields() {
+        let fields = fields();
+        assert_eq!(fields["event"], "song_changed");
+        assert_eq!(fields["player"], "mpd");
+        assert_eq!(fields["title"], "It's Alright");
+        assert_eq!(fields["song.duration"], "215.0");
+        assert!(!fields.contains_key("album"));
+    }
+
+    #[test]
+    fn test_command_args() {
+        let fields = fields();
+        assert_eq!(command_args("notify {player} {title}", &fields).unwrap(), vec!["notify", "mpd", "It's Alright"]);
+        assert_eq!(command_args("echo {album} --artist={song.artist}", &fields).unwrap(), vec!["echo", "", "--artist=Ma"]);
+        assert_eq!(command_args("echo {title", &fields).unwrap(), vec!["echo", "{title"]);
+
+        // Quotes group words and are removed
+        assert_eq!(command_args("notify \"{artist} - {title}\" ''", &fields).unwrap(), vec!["notify", "Ma - It's Alright", ""]);
+
+        // Shell syntax in a value is passed on as text
+        let mut fields = fields;
+        fields.insert("title".to_string(), "$(rm -rf ~); `id`".to_string());
+        assert_eq!(command_args("notify \"{title}\"", &fields).unwrap(), vec!["notify", "$(rm -rf ~); `id`"]);
+
+        // A value must not become an option
+        fields.insert("title".to_string(), "--output=/etc/passwd".to_string());
+        assert!(command_args("notify {title}", &fields).is_err());
+        assert!(command_args("notify {album}{title}", &fields).is_err());
+        assert_eq!(command_args("notify --title={title}", &fields).unwrap(), vec!["notify", "--title=--output=/etc/passwd"]);
+        assert_eq!(command_args("notify -- {title}", &fields).unwrap(), vec!["notify", "--", "--output=/etc/passwd"]);
+    }
+
+    #[test]
+    fn test_command_env() {
+        let env: HashMap<String, String> = command_env(&fields()).into_iter().collect();
+        assert_eq!(env["ACR_TITLE"], "It's Alright");
+        assert_eq!(env["ACR_SONG_DURATION"], "215.0");
+        assert_eq!(env["ACR_SOURCE_PLAYER_NAME"], "mpd");
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let command = ExecCommand::new(ExecCommandConfig {
+            command: "true".to_string(),
+            event_types: HashSet::from(["state_changed".to_string()]),
+            states: Some(HashSet::from(["playing".to_string()])),
+            only_active: false,
+            min_interval_ms: 1000,
+            timeout_secs: 30,
+        });
+        let playing = HashMap::from([("state".to_string(), "playing".to_string())]);
+        let paused = HashMap::from([("state".to_string(), "paused".to_string())]);
+        assert!(command.matches("state_changed", &playing));
+        assert!(!command.matches("state_changed", &paused));
+        assert!(!command.matches("song_changed", &playing));
+
+        let start = Instant::now();
+        assert!(command.try_start(start));
+        command.running.store(false, Ordering::SeqCst);
+        assert!(!command.try_start(start + Duration::from_millis(500)));
+        assert!(command.try_start(start + Duration::from_millis(1500)));
+        // Still running
+        assert!(!command.try_start(start + Duration::from_millis(3000)));
+    }
+}
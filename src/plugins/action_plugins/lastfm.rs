@@ -7,10 +7,13 @@ use parking_lot::Mutex;
 use std::time::SystemTime;
 use std::sync::atomic::{AtomicBool, Ordering}; // Added
 
+use crate::audiocontrol::eventbus::EventChannel;
 use crate::audiocontrol::AudioController;
 use crate::data::PlayerEvent;
 use crate::data::Song; // Added import for Song struct
 use crate::helpers::lastfm::{LastfmClient, LastfmTrackInfoDetails}; // Added LastfmTrackInfoDetails
+use crate::helpers::scrobble::{self, ScrobbleDecision, ScrobbleThresholds};
+use crate::helpers::songsplitmanager::SPLIT_TITLE_SOURCE_KEY;
 use crate::plugins::action_plugin::{ActionPlugin, BaseActionPlugin};
 use crate::plugins::plugin::Plugin;
 use log::{debug, error, info, warn, trace};
@@ -26,12 +29,35 @@ pub struct LastfmConfig {
     pub api_secret: String,
     #[serde(default = "default_scrobble_config")]
     pub scrobble: bool,
+    #[serde(default)]
+    pub radio: LastfmRadioConfig,
 }
 
 fn default_scrobble_config() -> bool {
     true
 }
 
+/// Opt-in for radio songs whose artist and title were split from the stream title
+///
+/// Stream titles are often ads, station jingles or show names, so these songs
+/// are only submitted for stations that are explicitly listed.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LastfmRadioConfig {
+    /// Station URLs to submit as now playing, "*" enables all stations
+    #[serde(default)]
+    pub stations: Vec<String>,
+    /// Also scrobble songs from these stations, not only update now playing
+    #[serde(default)]
+    pub scrobble: bool,
+}
+
+impl LastfmRadioConfig {
+    /// Check if songs from the given station URL should be submitted
+    pub fn is_station_enabled(&self, station: &str) -> bool {
+        self.stations.iter().any(|s| s == "*" || s == station)
+    }
+}
+
 pub struct Lastfm {
     base: BaseActionPlugin,
     config: LastfmConfig,
@@ -55,6 +81,7 @@ struct CurrentScrobbleTrack {
     song_details: Option<Song>, // Added to store the full Song object
     track_info_fetched: bool, // Added to track if get_track_info has been called
     player_source: Option<PlayerSource>, // Added to store the source of the song
+    radio_station: Option<String>, // Station URL if artist/title were split from a stream title
 }
 
 impl Default for CurrentScrobbleTrack {
@@ -71,10 +98,22 @@ impl Default for CurrentScrobbleTrack {
             song_details: None, // Initialize new field
             track_info_fetched: false, // Initialize new field
             player_source: None, // Initialize new field
+            radio_station: None,
         }
     }
 }
 
+/// Check the played time of a track against the scrobble thresholds
+///
+/// Radio songs usually have no length, they are checked against the stream threshold instead.
+fn evaluate_track(thresholds: &ScrobbleThresholds, track_data: &CurrentScrobbleTrack, played: u64) -> ScrobbleDecision {
+    match track_data.length {
+        Some(length) => thresholds.evaluate(length, played),
+        None if track_data.radio_station.is_some() => thresholds.evaluate_stream(played),
+        None => ScrobbleDecision::UnknownLength,
+    }
+}
+
 fn merge_song_updates(original_song: &mut Song, partial_update: &Song) {
     // Title and artist in partial_update are for identification, not merging.
     // original_song.title and original_song.artist should remain as they are.
@@ -201,8 +240,8 @@ fn lastfm_worker(
         }
 
 
-        if let (Some(name), Some(artists), Some(length_val), Some(actual_started_time)) =
-            (&track_data.name, &track_data.artists, &track_data.length, &track_data.started_timestamp) {
+        if let (Some(name), Some(artists), Some(actual_started_time)) =
+            (&track_data.name, &track_data.artists, &track_data.started_timestamp) {
             
             let artists_str = artists.join(", ");
 
@@ -216,11 +255,11 @@ fn lastfm_worker(
             let effective_elapsed_seconds = effective_elapsed_ms / 1000;
 
             debug!(
-                "LastFMWorker: Song: '{}' by {}. State: {:?}. Length: {}s. Played: {}s (Accum: {}ms, CurrentSeg: {}ms). Scrobbled: {}",
+                "LastFMWorker: Song: '{}' by {}. State: {:?}. Length: {:?}s. Played: {}s (Accum: {}ms, CurrentSeg: {}ms). Scrobbled: {}",
                 name,
                 artists_str,
                 track_data.current_playback_state,
-                length_val, // This is &u32, displays fine
+                track_data.length,
                 effective_elapsed_seconds,
                 track_data.accumulated_play_duration_ms,
                 current_segment_ms,
@@ -230,11 +269,17 @@ fn lastfm_worker(
             // Only attempt to scrobble if the player is currently playing this song
             if track_data.current_playback_state == PlaybackState::Playing
                 && !track_data.scrobbled_song && scrobble_enabled { // Added scrobble_enabled check
-                    // let scrobble_point_duration_secs = *length_val / 2; // length_val is &u32
-                    let scrobble_point_time_secs = 240; // 4 minutes in seconds, Last.fm recommendation
-                    
-
-                    if effective_elapsed_seconds >= u64::from(*length_val).saturating_mul(50) / 100 || effective_elapsed_seconds >= scrobble_point_time_secs {
+                    let thresholds = scrobble::thresholds();
+                    let decision = evaluate_track(&thresholds, &track_data, effective_elapsed_seconds);
+
+                    if decision.is_final() && !decision.should_scrobble() {
+                        info!("LastFMWorker: '{}' by {}: {}", name, artists_str, decision);
+                        // The decision can't change anymore, don't check this song again
+                        track_data.scrobbled_song = true;
+                    } else if decision.should_scrobble() && thresholds.dry_run {
+                        info!("LastFMWorker (dry run): '{}' by {}: {}", name, artists_str, decision);
+                        track_data.scrobbled_song = true;
+                    } else if decision.should_scrobble() {
                         
                         if client.is_authenticated() { // Check if client is authenticated before scrobbling
                             if let Some(primary_artist) = artists.first() {
@@ -264,7 +309,7 @@ fn lastfm_worker(
                                     None,               // Album artist not tracked yet
                                     scrobble_timestamp,
                                     None,               // Track number not tracked
-                                    Some(*length_val),  // length_val is &u32
+                                    track_data.length,
                                 ) {
                                     Ok(_) => {
                                         info!(
@@ -302,8 +347,8 @@ fn lastfm_worker(
         } else if track_data.name.is_none() {
              debug!("LastFMWorker: No song actively tracked.");
         } else {
-             debug!("LastFMWorker: Track data incomplete. Name: {:?}, Artists: {:?}, Length: {:?}, Started: {:?}",
-                track_data.name.is_some(), track_data.artists.is_some(), track_data.length.is_some(), track_data.started_timestamp.is_some());
+             debug!("LastFMWorker: Track data incomplete. Name: {:?}, Artists: {:?}, Started: {:?}",
+                track_data.name.is_some(), track_data.artists.is_some(), track_data.started_timestamp.is_some());
         }
     }
 }
@@ -396,6 +441,15 @@ impl Lastfm {
                     }
                     was_playing_before_change = true;
                 }
+
+                // In dry-run mode, explain why the previous song was never scrobbled
+                let thresholds = scrobble::thresholds();
+                if thresholds.dry_run && !track_data.scrobbled_song {
+                    if let Some(old_name) = &track_data.name {
+                        let decision = evaluate_track(&thresholds, &track_data, track_data.accumulated_play_duration_ms / 1000);
+                        info!("Lastfm (dry run): '{}' left before scrobbling: {}", old_name, decision);
+                    }
+                }
                 
                 track_data.name = new_name;
                 track_data.artists = new_artists_vec;
@@ -406,6 +460,21 @@ impl Lastfm {
                 track_data.song_details = Some(song_event.clone()); // Store the full Song object
                 track_data.player_source = Some(source.clone()); // Store the PlayerSource
                 track_data.track_info_fetched = false; // Reset flag for new song
+                track_data.radio_station = song_event.metadata.get(SPLIT_TITLE_SOURCE_KEY)
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+
+                let mut submit_now_playing = true;
+                if let Some(station) = &track_data.radio_station {
+                    if !self.config.radio.is_station_enabled(station) {
+                        debug!("Lastfm: Radio station '{}' is not enabled, not submitting split stream title", station);
+                        submit_now_playing = false;
+                        track_data.scrobbled_song = true; // Nothing to scrobble for this song
+                    } else if !self.config.radio.scrobble {
+                        debug!("Lastfm: Radio station '{}' only updates now playing", station);
+                        track_data.scrobbled_song = true;
+                    }
+                }
 
                 if was_playing_before_change {
                     track_data.last_play_timestamp = Some(SystemTime::now());
@@ -426,7 +495,8 @@ impl Lastfm {
                 );
 
                 // Update Now Playing if the song changed and is now considered playing
-                if (track_data.current_playback_state == PlaybackState::Playing || was_playing_before_change) && self.config.scrobble {
+                if (track_data.current_playback_state == PlaybackState::Playing || was_playing_before_change)
+                    && self.config.scrobble && submit_now_playing {
                      if let (Some(client), Some(name_str), Some(artists_vec)) =
                         (&self.lastfm_client, &track_data.name, &track_data.artists) {
                         if let Some(primary_artist) = artists_vec.first() {
@@ -549,8 +619,21 @@ impl Lastfm {
         if !self.config.enabled {
             return;
         }
+
+        // Players with scrobbling disabled, the previous song is not scrobbled after a switch to them either
+        if let Some(source) = event.source() {
+            if !crate::helpers::enrichment::settings_for(source).scrobble {
+                if let PlayerEvent::SongChanged { .. } = &event {
+                    self.clone().handle_song_changed(&None, source);
+                }
+                return;
+            }
+        }
         
         match &event {
+            PlayerEvent::SongChanged { song: Some(song), .. } if crate::helpers::songdedup::is_duplicate(song) => {
+                debug!("Lastfm: Ignoring song already reported by another player");
+            }
             PlayerEvent::SongChanged { song: song_event_opt, source, .. } => {
                 let lastfm_arc = Arc::new(Mutex::new(self.clone()));
                 let mut lastfm = lastfm_arc.lock();
@@ -668,7 +751,7 @@ impl ActionPlugin for Lastfm {
         // Subscribe to event bus in the initialize method
         log::debug!("Lastfm initializing and subscribing to event bus");
         let self_clone = self.clone();
-        self.base.subscribe_to_event_bus(move |event| {
+        self.base.subscribe_to_channels(&[EventChannel::Player], move |event| {
             self_clone.handle_event(event);
         });
         
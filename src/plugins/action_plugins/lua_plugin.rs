@@ -0,0 +1,152 @@
+use std::any::Any;
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use delegate::delegate;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::audiocontrol::AudioController;
+use crate::data::{EventMessage, PlayerEvent};
+use crate::plugins::action_plugin::{ActionPlugin, BaseActionPlugin};
+use crate::plugins::lua_host::{LuaSandbox, LuaScript, LuaScriptError};
+use crate::plugins::plugin::Plugin;
+
+fn default_timeout_ms() -> u64 {
+    500
+}
+
+fn default_max_memory_mb() -> usize {
+    16
+}
+
+fn default_http_timeout() -> u64 {
+    5
+}
+
+/// Configuration of a Lua script plugin
+#[derive(Debug, Clone, Deserialize)]
+pub struct LuaPluginConfig {
+    /// Path of the script
+    pub path: String,
+
+    /// Name used in the log, defaults to the file name without extension
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Time a handler may run before it is stopped, in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Memory the script may use, in MB
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: usize,
+
+    /// Hosts the script may send HTTP requests to
+    #[serde(default)]
+    pub http_hosts: Vec<String>,
+
+    /// Timeout of HTTP requests in seconds
+    #[serde(default = "default_http_timeout")]
+    pub http_timeout: u64,
+}
+
+/// Action plugin that passes events to the handlers of a Lua script
+pub struct LuaPlugin {
+    /// Base action plugin implementation
+    base: BaseActionPlugin,
+
+    /// Configuration of the plugin
+    config: LuaPluginConfig,
+
+    /// The loaded script, calls are serialized
+    script: Arc<Mutex<LuaScript>>,
+}
+
+impl LuaPlugin {
+    /// Load and run the script of the plugin
+    pub fn new(config: LuaPluginConfig) -> Result<Self, LuaScriptError> {
+        let path = Path::new(&config.path);
+        let name = config.name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "lua".to_string())
+        });
+        let sandbox = LuaSandbox {
+            timeout: Duration::from_millis(config.timeout_ms),
+            max_memory: config.max_memory_mb * 1024 * 1024,
+            http_hosts: config.http_hosts.clone(),
+            http_timeout: config.http_timeout,
+        };
+        let script = LuaScript::load(&name, path, sandbox)?;
+
+        Ok(Self {
+            base: BaseActionPlugin::new(&name),
+            config,
+            script: Arc::new(Mutex::new(script)),
+        })
+    }
+
+    /// Call the handlers of the script for an event
+    fn process_event(script: &Mutex<LuaScript>, name: &str, event: &PlayerEvent) {
+        let script = script.lock();
+        let event_type = event.event_type();
+        if !script.handles(event_type) {
+            return;
+        }
+
+        let json = match serde_json::to_value(EventMessage::from(event)) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("{}: failed to serialize {} event: {}", name, event_type, e);
+                return;
+            }
+        };
+
+        match script.handle_event(event_type, &json) {
+            Ok(handlers) => debug!("{}: {} handlers called for {} event", name, handlers, event_type),
+            Err(e) => warn!("{}: handler of {} event failed: {}", name, event_type, e),
+        }
+    }
+}
+
+impl Plugin for LuaPlugin {
+    delegate! {
+        to self.base {
+            fn name(&self) -> &str;
+            fn version(&self) -> &str;
+        }
+    }
+
+    fn init(&mut self) -> bool {
+        log::info!("Lua script '{}' loaded from {}", self.base.name(), self.config.path);
+        self.base.init()
+    }
+
+    fn shutdown(&mut self) -> bool {
+        self.base.shutdown()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ActionPlugin for LuaPlugin {
+    fn initialize(&mut self, controller: Weak<AudioController>) {
+        self.script.lock().set_controller(controller.clone());
+        self.base.set_controller(controller);
+
+        let script = self.script.clone();
+        let name = self.base.name().to_string();
+        self.base.subscribe_to_event_bus(move |event| {
+            Self::process_event(&script, &name, &event);
+        });
+    }
+
+    fn handle_event(&self, event: PlayerEvent) {
+        Self::process_event(&self.script, self.base.name(), &event);
+    }
+}
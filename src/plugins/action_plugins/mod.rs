@@ -1,8 +1,14 @@
 pub mod active_monitor;
 pub mod event_logger;
+pub mod exec_plugin;
 pub mod lastfm; // Renamed from lastfm_plugin
+pub mod lua_plugin;
+pub mod wasm_plugin;
 
 // Re-export commonly used items
 pub use active_monitor::ActiveMonitor;
 pub use event_logger::EventLogger;
-pub use lastfm::{Lastfm, LastfmConfig}; // Renamed from lastfm_plugin and updated structs
\ No newline at end of file
+pub use exec_plugin::{ExecPlugin, ExecPluginConfig};
+pub use lastfm::{Lastfm, LastfmConfig}; // Renamed from lastfm_plugin and updated structs
+pub use lua_plugin::{LuaPlugin, LuaPluginConfig};
+pub use wasm_plugin::{WasmPlugin, WasmPluginConfig};
@@ -0,0 +1,132 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Weak};
+
+use delegate::delegate;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::audiocontrol::AudioController;
+use crate::data::{EventMessage, PlayerEvent};
+use crate::plugins::action_plugin::{ActionPlugin, BaseActionPlugin};
+use crate::plugins::plugin::Plugin;
+use crate::plugins::wasm_host::{WasmError, WasmModule, DEFAULT_FUEL};
+
+fn default_fuel() -> u64 {
+    DEFAULT_FUEL
+}
+
+/// Configuration of a WebAssembly plugin
+#[derive(Debug, Clone, Deserialize)]
+pub struct WasmPluginConfig {
+    /// Path of the `.wasm` file
+    pub path: String,
+
+    /// Name used in the log, defaults to the file name without extension
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Event types passed to the module, all events if not set
+    #[serde(default)]
+    pub event_types: Option<HashSet<String>>,
+
+    /// Fuel for each event, the module is stopped when it has used it up
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+}
+
+/// Action plugin that passes events to a WebAssembly module
+pub struct WasmPlugin {
+    /// Base action plugin implementation
+    base: BaseActionPlugin,
+
+    /// Configuration of the plugin
+    config: WasmPluginConfig,
+
+    /// The loaded module, calls are serialized
+    module: Arc<Mutex<WasmModule>>,
+}
+
+impl WasmPlugin {
+    /// Load the module of the plugin
+    pub fn new(config: WasmPluginConfig) -> Result<Self, WasmError> {
+        let path = Path::new(&config.path);
+        let name = config.name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "wasm".to_string())
+        });
+        let module = WasmModule::load(&name, path, config.fuel)?;
+
+        Ok(Self {
+            base: BaseActionPlugin::new(&name),
+            config,
+            module: Arc::new(Mutex::new(module)),
+        })
+    }
+
+    /// Pass an event to the module if it is subscribed to its type
+    fn process_event(module: &Mutex<WasmModule>, event_types: Option<&HashSet<String>>, name: &str, event: &PlayerEvent) {
+        if let Some(types) = event_types {
+            if !types.contains(event.event_type()) {
+                return;
+            }
+        }
+
+        let json = match serde_json::to_string(&EventMessage::from(event)) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("{}: failed to serialize {} event: {}", name, event.event_type(), e);
+                return;
+            }
+        };
+
+        match module.lock().handle_event(&json) {
+            Ok(0) => {}
+            Ok(_) => debug!("{}: handled {} event", name, event.event_type()),
+            Err(e) => warn!("{}: {} event failed: {}", name, event.event_type(), e),
+        }
+    }
+}
+
+impl Plugin for WasmPlugin {
+    delegate! {
+        to self.base {
+            fn name(&self) -> &str;
+            fn version(&self) -> &str;
+        }
+    }
+
+    fn init(&mut self) -> bool {
+        log::info!("WebAssembly plugin '{}' loaded from {}", self.base.name(), self.config.path);
+        self.base.init()
+    }
+
+    fn shutdown(&mut self) -> bool {
+        self.base.shutdown()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ActionPlugin for WasmPlugin {
+    fn initialize(&mut self, controller: Weak<AudioController>) {
+        self.module.lock().set_controller(controller.clone());
+        self.base.set_controller(controller);
+
+        let module = self.module.clone();
+        let event_types = self.config.event_types.clone();
+        let name = self.base.name().to_string();
+        self.base.subscribe_to_event_bus(move |event| {
+            Self::process_event(&module, event_types.as_ref(), &name, &event);
+        });
+    }
+
+    fn handle_event(&self, event: PlayerEvent) {
+        Self::process_event(&self.module, self.config.event_types.as_ref(), self.base.name(), &event);
+    }
+}
@@ -0,0 +1,369 @@
+//! Sandboxed Lua runtime for event scripts
+//!
+//! A script registers handlers for event types and can send player commands, log and, if the
+//! configuration allows it, send HTTP requests to some hosts:
+//!
+//! ```lua
+//! acr.on("song_changed", function(event)
+//!     acr.log("info", "Now playing " .. event.song.title)
+//! end)
+//!
+//! acr.on("volume_changed", function(event)
+//!     if event.percentage > 80 then
+//!         acr.command("pause")
+//!     end
+//! end)
+//! ```
+//!
+//! Events are tables in the format of the WebSocket API, `"*"` registers a handler for all
+//! events. Scripts can't access files, other processes or load modules, their memory is limited
+//! and a handler that runs longer than the timeout is stopped.
+
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, trace, warn};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Table, Value, VmState};
+use parking_lot::Mutex;
+use thiserror::Error;
+
+use crate::audiocontrol::AudioController;
+use crate::data::PlayerCommand;
+use crate::helpers::http_client::new_http_client;
+
+/// Registry key of the table that maps event types to handlers
+const HANDLERS_KEY: &str = "acr_handlers";
+
+/// Number of instructions between checks of the timeout
+const TIMEOUT_CHECK_INSTRUCTIONS: u32 = 10_000;
+
+/// Globals of the base library that can load code from files
+const UNSAFE_GLOBALS: [&str; 3] = ["dofile", "loadfile", "load"];
+
+/// Errors of loading a script or running a handler
+#[derive(Debug, Error)]
+pub enum LuaScriptError {
+    #[error("Failed to read script {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Lua error: {0}")]
+    Lua(#[from] mlua::Error),
+}
+
+/// Limits of a script and what it may access
+#[derive(Debug, Clone)]
+pub struct LuaSandbox {
+    /// Time a handler may run before it is stopped
+    pub timeout: Duration,
+
+    /// Memory the script may use, in bytes
+    pub max_memory: usize,
+
+    /// Hosts the script may send HTTP requests to, none if empty
+    pub http_hosts: Vec<String>,
+
+    /// Timeout of HTTP requests in seconds
+    pub http_timeout: u64,
+}
+
+impl Default for LuaSandbox {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            max_memory: 16 * 1024 * 1024,
+            http_hosts: Vec::new(),
+            http_timeout: 5,
+        }
+    }
+}
+
+/// State of the host that the `acr` functions can access
+struct HostState {
+    /// Name of the script, used as log prefix
+    name: String,
+
+    /// Controller that receives the commands of the script
+    controller: Option<Weak<AudioController>>,
+
+    /// Hosts the script may send HTTP requests to
+    http_hosts: Vec<String>,
+
+    /// Timeout of HTTP requests in seconds
+    http_timeout: u64,
+
+    /// Time the running handler is stopped at
+    deadline: Arc<Mutex<Instant>>,
+}
+
+/// A loaded script with its handlers
+pub struct LuaScript {
+    lua: Lua,
+    timeout: Duration,
+    deadline: Arc<Mutex<Instant>>,
+}
+
+impl LuaScript {
+    /// Load a script from a file
+    pub fn load(name: &str, path: &Path, sandbox: LuaSandbox) -> Result<Self, LuaScriptError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| LuaScriptError::Io(path.display().to_string(), e))?;
+        Self::from_source(name, &source, sandbox)
+    }
+
+    /// Run a script, which registers its handlers
+    pub fn from_source(name: &str, source: &str, sandbox: LuaSandbox) -> Result<Self, LuaScriptError> {
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8 | StdLib::COROUTINE;
+        let lua = Lua::new_with(libs, LuaOptions::default())?;
+        lua.set_memory_limit(sandbox.max_memory)?;
+
+        let globals = lua.globals();
+        for global in UNSAFE_GLOBALS {
+            globals.set(global, Value::Nil)?;
+        }
+
+        // Stop handlers, and the script itself, that run past their deadline
+        let deadline = Arc::new(Mutex::new(Instant::now() + sandbox.timeout));
+        lua.set_app_data(HostState {
+            name: name.to_string(),
+            controller: None,
+            http_hosts: sandbox.http_hosts,
+            http_timeout: sandbox.http_timeout,
+            deadline: deadline.clone(),
+        });
+        lua.set_named_registry_value(HANDLERS_KEY, lua.create_table()?)?;
+        globals.set("acr", host_functions(&lua)?)?;
+
+        let hook_deadline = deadline.clone();
+        let timeout = sandbox.timeout;
+        lua.set_hook(HookTriggers::new().every_nth_instruction(TIMEOUT_CHECK_INSTRUCTIONS), move |_, _| {
+            if Instant::now() > *hook_deadline.lock() {
+                return Err(mlua::Error::runtime(format!("timeout of {} ms exceeded", timeout.as_millis())));
+            }
+            Ok(VmState::Continue)
+        });
+
+        lua.load(source).set_name(name).exec()?;
+
+        Ok(LuaScript {
+            lua,
+            timeout,
+            deadline,
+        })
+    }
+
+    /// Set the controller that receives the commands of the script
+    pub fn set_controller(&self, controller: Weak<AudioController>) {
+        if let Some(mut state) = self.lua.app_data_mut::<HostState>() {
+            state.controller = Some(controller);
+        }
+    }
+
+    /// Check if the script has a handler for an event type
+    pub fn handles(&self, event_type: &str) -> bool {
+        self.handlers(event_type).map(|handlers| !handlers.is_empty()).unwrap_or(false)
+    }
+
+    /// Call the handlers of an event and return how many there were
+    pub fn handle_event(&self, event_type: &str, event: &serde_json::Value) -> Result<usize, LuaScriptError> {
+        let handlers = self.handlers(event_type)?;
+        if handlers.is_empty() {
+            return Ok(0);
+        }
+
+        let event = self.lua.to_value(event)?;
+        for handler in &handlers {
+            *self.deadline.lock() = Instant::now() + self.timeout;
+            handler.call::<()>(event.clone())?;
+        }
+        Ok(handlers.len())
+    }
+
+    /// Handlers registered for an event type, followed by those for all events
+    fn handlers(&self, event_type: &str) -> Result<Vec<Function>, LuaScriptError> {
+        let registry: Table = self.lua.named_registry_value(HANDLERS_KEY)?;
+        let mut handlers = Vec::new();
+        for key in [event_type, "*"] {
+            if let Some(list) = registry.get::<Option<Table>>(key)? {
+                for handler in list.sequence_values::<Function>() {
+                    handlers.push(handler?);
+                }
+            }
+        }
+        Ok(handlers)
+    }
+}
+
+/// Check if a script may send HTTP requests to the host of a URL
+fn http_allowed(allowed_hosts: &[String], url: &str) -> bool {
+    let Ok(url) = url::Url::parse(url) else {
+        return false;
+    };
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return false;
+    }
+    url.host_str()
+        .map(|host| allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)))
+        .unwrap_or(false)
+}
+
+/// Name and HTTP settings of the script, errors if the host isn't allowed
+fn http_settings(lua: &Lua, url: &str) -> mlua::Result<(String, u64)> {
+    let state = lua
+        .app_data_ref::<HostState>()
+        .ok_or_else(|| mlua::Error::runtime("host state missing"))?;
+    if !http_allowed(&state.http_hosts, url) {
+        return Err(mlua::Error::runtime(format!("HTTP requests to {} are not allowed", url)));
+    }
+    Ok((state.name.clone(), state.http_timeout))
+}
+
+/// Don't count the time spent waiting for an HTTP response against the timeout of the handler
+fn extend_deadline(lua: &Lua, started: Instant) {
+    if let Some(state) = lua.app_data_ref::<HostState>() {
+        *state.deadline.lock() += started.elapsed();
+    }
+}
+
+/// The `acr` table with the functions scripts can call
+fn host_functions(lua: &Lua) -> mlua::Result<Table> {
+    let acr = lua.create_table()?;
+
+    acr.set("on", lua.create_function(|lua, (event_type, handler): (String, Function)| {
+        let registry: Table = lua.named_registry_value(HANDLERS_KEY)?;
+        let list = match registry.get::<Option<Table>>(event_type.as_str())? {
+            Some(list) => list,
+            None => {
+                let list = lua.create_table()?;
+                registry.set(event_type.as_str(), &list)?;
+                list
+            }
+        };
+        list.push(handler)
+    })?)?;
+
+    acr.set("log", lua.create_function(|lua, (level, message): (String, String)| {
+        let name = lua
+            .app_data_ref::<HostState>()
+            .map(|state| state.name.clone())
+            .unwrap_or_default();
+        match level.as_str() {
+            "error" => error!("{}: {}", name, message),
+            "warn" | "warning" => warn!("{}: {}", name, message),
+            "info" => info!("{}: {}", name, message),
+            "debug" => debug!("{}: {}", name, message),
+            _ => trace!("{}: {}", name, message),
+        }
+        Ok(())
+    })?)?;
+
+    // Commands are strings like "next" or tables like { seek = 30 }, as in the JSON API
+    acr.set("command", lua.create_function(|lua, command: Value| {
+        let json: serde_json::Value = lua.from_value(command)?;
+        let command: PlayerCommand = serde_json::from_value(json)
+            .map_err(|e| mlua::Error::runtime(format!("invalid player command: {}", e)))?;
+
+        let controller = lua
+            .app_data_ref::<HostState>()
+            .and_then(|state| state.controller.as_ref().and_then(Weak::upgrade));
+        match controller {
+            Some(controller) => Ok(controller.send_command(command)),
+            None => Ok(false),
+        }
+    })?)?;
+
+    // HTTP functions return the response, or nil and the error message
+    acr.set("http_get", lua.create_function(|lua, url: String| {
+        let (name, timeout) = http_settings(lua, &url)?;
+        debug!("{}: GET {}", name, url);
+        let started = Instant::now();
+        let result = new_http_client(timeout).get_text(&url);
+        extend_deadline(lua, started);
+        match result {
+            Ok(body) => Ok((Some(body), None)),
+            Err(e) => Ok((None, Some(e.to_string()))),
+        }
+    })?)?;
+
+    acr.set("http_post", lua.create_function(|lua, (url, payload): (String, Value)| {
+        let (name, timeout) = http_settings(lua, &url)?;
+        debug!("{}: POST {}", name, url);
+        let payload: serde_json::Value = lua.from_value(payload)?;
+        let started = Instant::now();
+        let result = new_http_client(timeout).post_json_value(&url, payload);
+        extend_deadline(lua, started);
+        match result {
+            Ok(response) => Ok((Some(lua.to_value(&response)?), None)),
+            Err(e) => Ok((None, Some(e.to_string()))),
+        }
+    })?)?;
+
+    Ok(acr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_handle_event() {
+        let script = LuaScript::from_source("test", r#"
+            count = 0
+            acr.on("song_changed", function(event)
+                count = count + 1
+                last_title = event.song.title
+            end)
+            acr.on("*", function(event)
+                count = count + 1
+            end)
+        "#, LuaSandbox::default()).unwrap();
+
+        assert!(script.handles("song_changed"));
+        assert!(script.handles("state_changed"));
+
+        let event = json!({"type": "song_changed", "song": {"title": "Song Title"}});
+        assert_eq!(script.handle_event("song_changed", &event).unwrap(), 2);
+        assert_eq!(script.handle_event("state_changed", &json!({"type": "state_changed"})).unwrap(), 1);
+
+        let globals = script.lua.globals();
+        assert_eq!(globals.get::<i64>("count").unwrap(), 3);
+        assert_eq!(globals.get::<String>("last_title").unwrap(), "Song Title");
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let script = LuaScript::from_source("test", r#"
+            hidden = io == nil and os == nil and require == nil and dofile == nil and load == nil
+        "#, LuaSandbox::default()).unwrap();
+        assert!(script.lua.globals().get::<bool>("hidden").unwrap());
+
+        // HTTP is only allowed to the configured hosts
+        let result = LuaScript::from_source("test", r#"acr.http_get("http://example.com/")"#, LuaSandbox::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let sandbox = LuaSandbox {
+            timeout: Duration::from_millis(50),
+            ..LuaSandbox::default()
+        };
+        let script = LuaScript::from_source("test", r#"
+            acr.on("state_changed", function(event)
+                while true do end
+            end)
+        "#, sandbox).unwrap();
+
+        assert!(script.handle_event("state_changed", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_http_allowed() {
+        let hosts = vec!["ha.local".to_string()];
+        assert!(http_allowed(&hosts, "http://ha.local:8123/api/webhook/x"));
+        assert!(http_allowed(&hosts, "https://HA.local/"));
+        assert!(!http_allowed(&hosts, "http://other.local/"));
+        assert!(!http_allowed(&hosts, "file:///etc/passwd"));
+    }
+}
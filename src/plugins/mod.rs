@@ -2,6 +2,8 @@ pub mod plugin;
 pub mod plugin_factory;
 pub mod action_plugin;
 pub mod action_plugins;
+pub mod lua_host;
+pub mod wasm_host;
 
 // Re-export commonly used items
 pub use plugin::Plugin;
@@ -7,7 +7,10 @@ use crate::plugins::plugin::Plugin;
 use crate::plugins::action_plugin::ActionPlugin;
 use crate::plugins::action_plugins::ActiveMonitor;
 use crate::plugins::action_plugins::event_logger::{EventLogger, LogLevel};
+use crate::plugins::action_plugins::exec_plugin::{ExecPlugin, ExecPluginConfig};
 use crate::plugins::action_plugins::lastfm::{Lastfm, LastfmConfig};
+use crate::plugins::action_plugins::lua_plugin::{LuaPlugin, LuaPluginConfig};
+use crate::plugins::action_plugins::wasm_plugin::{WasmPlugin, WasmPluginConfig};
 
 /// Factory for creating and registering plugins
 pub struct PluginFactory {
@@ -92,6 +95,21 @@ impl PluginFactory {
                 None
             }
         });
+
+        // Register the plugin that runs shell commands on events
+        self.register("exec", |config_value| {
+            create_exec_plugin(config_value).map(|plugin| Box::new(plugin) as Box<dyn Plugin>)
+        });
+
+        // Register the host for plugins compiled to WebAssembly
+        self.register("wasm", |config_value| {
+            create_wasm_plugin(config_value).map(|plugin| Box::new(plugin) as Box<dyn Plugin>)
+        });
+
+        // Register the host for Lua event scripts
+        self.register("lua", |config_value| {
+            create_lua_plugin(config_value).map(|plugin| Box::new(plugin) as Box<dyn Plugin>)
+        });
     }
     
     /// Register a new plugin constructor with JSON config support
@@ -250,6 +268,15 @@ impl PluginFactory {
                 error!("\'{}\' plugin (Lastfm) requires configuration, but none was provided to create_action_plugin_with_config. This indicates an issue.", name);
                 None
             }
+        } else if plugin.as_any().downcast_ref::<ExecPlugin>().is_some() {
+            // For ExecPlugin, create a new instance with its configuration
+            create_exec_plugin(config).map(|plugin| Box::new(plugin) as Box<dyn ActionPlugin + Send + Sync>)
+        } else if plugin.as_any().downcast_ref::<WasmPlugin>().is_some() {
+            // For WasmPlugin, load the module again for the action plugin instance
+            create_wasm_plugin(config).map(|plugin| Box::new(plugin) as Box<dyn ActionPlugin + Send + Sync>)
+        } else if plugin.as_any().downcast_ref::<LuaPlugin>().is_some() {
+            // For LuaPlugin, run the script again for the action plugin instance
+            create_lua_plugin(config).map(|plugin| Box::new(plugin) as Box<dyn ActionPlugin + Send + Sync>)
         } else {
             error!("Plugin \'{}\' is not a compatible ActionPlugin or is not specifically handled in create_action_plugin_with_config.", name);
             None
@@ -301,4 +328,68 @@ impl PluginFactory {
         serde_json::to_string_pretty(&plugins).unwrap_or_else(|_| "[]".to_string())    }
     
     // sample_json_config method for event filters removed as it's no longer used
-}
\ No newline at end of file
+}
+
+/// Create an exec plugin from its configuration, errors are logged
+fn create_exec_plugin(config_value: Option<&Value>) -> Option<ExecPlugin> {
+    let Some(value) = config_value else {
+        error!("'exec' plugin requires configuration (commands). Plugin will not be loaded.");
+        return None;
+    };
+
+    match serde_json::from_value::<ExecPluginConfig>(value.clone()) {
+        Ok(config) => Some(ExecPlugin::new(config)),
+        Err(e) => {
+            error!("Failed to parse ExecPluginConfig for 'exec' plugin: {}. Plugin will not be loaded.", e);
+            None
+        }
+    }
+}
+
+/// Create a WebAssembly plugin from its configuration, errors are logged
+fn create_wasm_plugin(config_value: Option<&Value>) -> Option<WasmPlugin> {
+    let Some(value) = config_value else {
+        error!("'wasm' plugin requires configuration (path). Plugin will not be loaded.");
+        return None;
+    };
+
+    let config = match serde_json::from_value::<WasmPluginConfig>(value.clone()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to parse WasmPluginConfig for 'wasm' plugin: {}. Plugin will not be loaded.", e);
+            return None;
+        }
+    };
+
+    match WasmPlugin::new(config.clone()) {
+        Ok(plugin) => Some(plugin),
+        Err(e) => {
+            error!("Failed to load WebAssembly plugin {}: {}. Plugin will not be loaded.", config.path, e);
+            None
+        }
+    }
+}
+
+/// Create a Lua script plugin from its configuration, errors are logged
+fn create_lua_plugin(config_value: Option<&Value>) -> Option<LuaPlugin> {
+    let Some(value) = config_value else {
+        error!("'lua' plugin requires configuration (path). Plugin will not be loaded.");
+        return None;
+    };
+
+    let config = match serde_json::from_value::<LuaPluginConfig>(value.clone()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to parse LuaPluginConfig for 'lua' plugin: {}. Plugin will not be loaded.", e);
+            return None;
+        }
+    };
+
+    match LuaPlugin::new(config.clone()) {
+        Ok(plugin) => Some(plugin),
+        Err(e) => {
+            error!("Failed to load Lua script {}: {}. Plugin will not be loaded.", config.path, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Runtime for plugins compiled to WebAssembly
+//!
+//! A module talks to the host through a small ABI. It exports
+//!
+//! - `memory`: its linear memory
+//! - `acr_alloc(len: i32) -> i32`: reserve `len` bytes for the host and return their offset
+//! - `acr_on_event(ptr: i32, len: i32) -> i32`: handle the event at `ptr`, JSON in the format of the
+//!   WebSocket API. 0 if the module ignored the event, 1 if it acted on it, a negative value for an error
+//! - `acr_init() -> i32` (optional): called once after loading, a negative value rejects the module
+//!
+//! and can import these functions from the `acr` module:
+//!
+//! - `log(level: i32, ptr: i32, len: i32)`: log a UTF-8 message, levels 0 (error) to 4 (trace)
+//! - `send_command(ptr: i32, len: i32) -> i32`: send a JSON `PlayerCommand`, e.g. `"next"` or
+//!   `{"seek": 30.0}`, to the active player. 1 if it was sent, 0 if not, -1 if the command is invalid
+//!
+//! Modules run in a sandbox: they can't access files or the network, their memory is limited and
+//! every call gets a fixed amount of fuel, so a module that loops forever is stopped.
+
+use std::path::Path;
+use std::sync::Weak;
+
+use log::{debug, error, info, trace, warn};
+use thiserror::Error;
+use wasmi::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+use crate::audiocontrol::AudioController;
+use crate::data::PlayerCommand;
+
+/// Name of the module the host functions are imported from
+const HOST_MODULE: &str = "acr";
+
+/// Memory a module may use, in bytes
+const MAX_MEMORY: usize = 16 * 1024 * 1024;
+
+/// Fuel per call if the plugin configuration doesn't set it, roughly the number of instructions
+pub const DEFAULT_FUEL: u64 = 10_000_000;
+
+/// Errors of loading a module or calling it
+#[derive(Debug, Error)]
+pub enum WasmError {
+    #[error("Failed to read module {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid module: {0}")]
+    Module(wasmi::Error),
+
+    #[error("Module doesn't export {0}")]
+    MissingExport(&'static str),
+
+    #[error("Call of {0} failed: {1}")]
+    Call(&'static str, wasmi::Error),
+
+    #[error("Event of {0} bytes doesn't fit into the memory of the module")]
+    OutOfMemory(usize),
+
+    #[error("{0} returned error {1}")]
+    Rejected(&'static str, i32),
+}
+
+/// State of the host that the imported functions can access
+struct HostState {
+    /// Name of the plugin, used as log prefix
+    name: String,
+
+    /// Controller that receives the commands of the module
+    controller: Option<Weak<AudioController>>,
+
+    /// Resource limits of the module
+    limits: StoreLimits,
+}
+
+/// A loaded module with its own memory and state
+pub struct WasmModule {
+    store: Store<HostState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    on_event: TypedFunc<(i32, i32), i32>,
+    fuel: u64,
+}
+
+impl WasmModule {
+    /// Load a module from a `.wasm` file
+    pub fn load(name: &str, path: &Path, fuel: u64) -> Result<Self, WasmError> {
+        let wasm = std::fs::read(path)
+            .map_err(|e| WasmError::Io(path.display().to_string(), e))?;
+        Self::from_bytes(name, &wasm, fuel)
+    }
+
+    /// Instantiate a module from its binary and call its `acr_init` function
+    pub fn from_bytes(name: &str, wasm: &[u8], fuel: u64) -> Result<Self, WasmError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(WasmError::Module)?;
+
+        let state = HostState {
+            name: name.to_string(),
+            controller: None,
+            limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+        };
+        let mut store = Store::new(&engine, state);
+        store.limiter(|state| &mut state.limits);
+        set_fuel(&mut store, fuel);
+
+        let linker = host_functions(&engine)?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(WasmError::Module)?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or(WasmError::MissingExport("memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "acr_alloc")
+            .map_err(|_| WasmError::MissingExport("acr_alloc"))?;
+        let on_event = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "acr_on_event")
+            .map_err(|_| WasmError::MissingExport("acr_on_event"))?;
+
+        if let Ok(init) = instance.get_typed_func::<(), i32>(&store, "acr_init") {
+            let result = init
+                .call(&mut store, ())
+                .map_err(|e| WasmError::Call("acr_init", e))?;
+            if result < 0 {
+                return Err(WasmError::Rejected("acr_init", result));
+            }
+        }
+
+        Ok(WasmModule {
+            store,
+            memory,
+            alloc,
+            on_event,
+            fuel,
+        })
+    }
+
+    /// Set the controller that receives the commands of the module
+    pub fn set_controller(&mut self, controller: Weak<AudioController>) {
+        self.store.data_mut().controller = Some(controller);
+    }
+
+    /// Pass an event as JSON to the module and return the result of `acr_on_event`
+    pub fn handle_event(&mut self, json: &str) -> Result<i32, WasmError> {
+        set_fuel(&mut self.store, self.fuel);
+
+        let bytes = json.as_bytes();
+        let len = i32::try_from(bytes.len()).map_err(|_| WasmError::OutOfMemory(bytes.len()))?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| WasmError::Call("acr_alloc", e))?;
+        let offset = usize::try_from(ptr).map_err(|_| WasmError::OutOfMemory(bytes.len()))?;
+        self.memory
+            .write(&mut self.store, offset, bytes)
+            .map_err(|_| WasmError::OutOfMemory(bytes.len()))?;
+
+        let result = self
+            .on_event
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| WasmError::Call("acr_on_event", e))?;
+        if result < 0 {
+            return Err(WasmError::Rejected("acr_on_event", result));
+        }
+        Ok(result)
+    }
+}
+
+/// Refill the fuel of a store, every call starts with the configured amount
+fn set_fuel(store: &mut Store<HostState>, fuel: u64) {
+    if let Err(e) = store.set_fuel(fuel) {
+        warn!("Failed to set the fuel of a WebAssembly module: {}", e);
+    }
+}
+
+/// Read a UTF-8 string from the memory of the calling module
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let offset = usize::try_from(ptr).ok()?;
+    let len = usize::try_from(len).ok().filter(|len| *len <= MAX_MEMORY)?;
+    let mut buffer = vec![0; len];
+    memory.read(caller, offset, &mut buffer).ok()?;
+    String::from_utf8(buffer).ok()
+}
+
+/// Functions that modules can import from the host
+fn host_functions(engine: &Engine) -> Result<Linker<HostState>, WasmError> {
+    let mut linker = Linker::new(engine);
+
+    linker
+        .func_wrap(HOST_MODULE, "log", |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+            let name = &caller.data().name;
+            match read_string(&caller, ptr, len) {
+                Some(message) => match level {
+                    0 => error!("{}: {}", name, message),
+                    1 => warn!("{}: {}", name, message),
+                    2 => info!("{}: {}", name, message),
+                    3 => debug!("{}: {}", name, message),
+                    _ => trace!("{}: {}", name, message),
+                },
+                None => warn!("{}: log message outside of the module memory or not UTF-8", name),
+            }
+        })
+        .map_err(|e| WasmError::Module(e.into()))?;
+
+    linker
+        .func_wrap(HOST_MODULE, "send_command", |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i32 {
+            let name = &caller.data().name;
+            let command = match read_string(&caller, ptr, len)
+                .and_then(|json| serde_json::from_str::<PlayerCommand>(&json).ok())
+            {
+                Some(command) => command,
+                None => {
+                    warn!("{}: invalid player command", name);
+                    return -1;
+                }
+            };
+
+            match caller.data().controller.as_ref().and_then(Weak::upgrade) {
+                Some(controller) => {
+                    debug!("{}: sending {}", name, command);
+                    controller.send_command(command) as i32
+                }
+                None => 0,
+            }
+        })
+        .map_err(|e| WasmError::Module(e.into()))?;
+
+    Ok(linker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts events and returns the count, fails if the event isn't a JSON object
+    const COUNTER: &str = r#"
+        (module
+            (import "acr" "log" (func $log (param i32 i32 i32)))
+            (memory (export "memory") 1)
+            (global $count (mut i32) (i32.const 0))
+            (func (export "acr_alloc") (param i32) (result i32)
+                (i32.const 1024))
+            (func (export "acr_on_event") (param $ptr i32) (param $len i32) (result i32)
+                (call $log (i32.const 3) (local.get $ptr) (local.get $len))
+                (if (i32.ne (i32.load8_u (local.get $ptr)) (i32.const 123))
+                    (then (return (i32.const -1))))
+                (global.set $count (i32.add (global.get $count) (i32.const 1)))
+                (global.get $count)))
+    "#;
+
+    /// Never returns from acr_on_event
+    const ENDLESS: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "acr_alloc") (param i32) (result i32)
+                (i32.const 0))
+            (func (export "acr_on_event") (param i32 i32) (result i32)
+                (loop $forever (br $forever))
+                (i32.const 0)))
+    "#;
+
+    fn module(wat: &str) -> Result<WasmModule, WasmError> {
+        let wasm = wat::parse_str(wat).unwrap();
+        WasmModule::from_bytes("test", &wasm, 100_000)
+    }
+
+    #[test]
+    fn test_handle_event() {
+        let mut module = module(COUNTER).unwrap();
+
+        assert_eq!(module.handle_event(r#"{"type":"state_changed"}"#).unwrap(), 1);
+        assert_eq!(module.handle_event(r#"{"type":"song_changed"}"#).unwrap(), 2);
+        assert!(matches!(module.handle_event("[]"), Err(WasmError::Rejected("acr_on_event", -1))));
+    }
+
+    #[test]
+    fn test_out_of_fuel() {
+        let mut module = module(ENDLESS).unwrap();
+
+        assert!(matches!(module.handle_event("{}"), Err(WasmError::Call("acr_on_event", _))));
+        // The fuel is refilled for the next event
+        assert!(matches!(module.handle_event("{}"), Err(WasmError::Call("acr_on_event", _))));
+    }
+
+    #[test]
+    fn test_missing_export() {
+        let result = module(r#"(module (memory (export "memory") 1))"#);
+        assert!(matches!(result, Err(WasmError::MissingExport("acr_alloc"))));
+    }
+}